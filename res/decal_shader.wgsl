@@ -0,0 +1,52 @@
+// Vertex shader
+
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+}
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+}
+struct InstanceInput {
+    @location(5) position_angle: vec4<f32>, // xyz position, w rotation around z in radians
+    @location(6) colour: u32, // rgba8
+    @location(7) radius: f32, // half the length of the mark, it's half as thick as it is long
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) position: vec2<f32>,
+    @location(1) colour: vec4<f32>,
+}
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    // stretch the unit quad into a mark twice as long as it is thick
+    let scaled = vec2<f32>(model.position.x * instance.radius * 2.0, model.position.y * instance.radius);
+    let c = cos(instance.position_angle.w);
+    let s = sin(instance.position_angle.w);
+    let rotated = vec3<f32>(c * scaled.x - s * scaled.y, s * scaled.x + c * scaled.y, 0.0);
+    let world_position = rotated + instance.position_angle.xyz;
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(world_position, 1.0);
+    out.position = model.position.xy * 2.0;
+    out.colour = unpack4x8unorm(instance.colour);
+    return out;
+}
+
+// Fragment shader
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // round the ends off. Overlapping marks don't darken each other, the depth test keeps the first one drawn
+    if (length(in.position) > 1.0) {
+        discard;
+    }
+    return in.colour;
+}
@@ -11,11 +11,8 @@ struct VertexInput {
     @location(1) tex_coords: vec2<f32>,
 }
 struct InstanceInput {
-    @location(5) model_matrix_0: vec4<f32>,
-    @location(6) model_matrix_1: vec4<f32>,
-    @location(7) model_matrix_2: vec4<f32>,
-    @location(8) model_matrix_3: vec4<f32>,
-    @location(9) colour: vec4<f32>,
+    @location(5) position_angle: vec4<f32>, // xyz position, w rotation around z in radians
+    @location(6) colour: u32, // rgba8
 }
 
 struct VertexOutput {
@@ -29,16 +26,14 @@ fn vs_main(
     model: VertexInput,
     instance: InstanceInput,
 ) -> VertexOutput {
-    let model_matrix = mat4x4<f32>(
-        instance.model_matrix_0,
-        instance.model_matrix_1,
-        instance.model_matrix_2,
-        instance.model_matrix_3,
-    );
+    let c = cos(instance.position_angle.w);
+    let s = sin(instance.position_angle.w);
+    let rotated = vec3<f32>(c * model.position.x - s * model.position.y, s * model.position.x + c * model.position.y, model.position.z);
+    let world_position = rotated + instance.position_angle.xyz;
     var out: VertexOutput;
     out.tex_coords = model.tex_coords;
-    out.clip_position = camera.view_proj * model_matrix * vec4<f32>(model.position, 1.0);
-    out.colour = instance.colour;
+    out.clip_position = camera.view_proj * vec4<f32>(world_position, 1.0);
+    out.colour = unpack4x8unorm(instance.colour);
     return out;
 }
 
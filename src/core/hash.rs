@@ -0,0 +1,74 @@
+/// FNV-1a 64 bit hasher.
+/// Unlike std's DefaultHasher the output is stable across Rust versions and platforms,
+/// so it is safe to send over the network or store in files for comparing against other clients.
+#[derive(Debug, Clone, Copy)]
+pub struct Fnv1aHasher {
+    hash: u64,
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+impl Fnv1aHasher {
+    pub fn new() -> Self {
+        Self {
+            hash: FNV_OFFSET_BASIS,
+        }
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        for byte in bytes {
+            self.hash ^= *byte as u64;
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+        self
+    }
+
+    pub fn write_str(&mut self, s: &str) -> &mut Self {
+        // include the length so "ab" + "c" hashes differently to "a" + "bc"
+        self.write_u64(s.len() as u64);
+        self.write_bytes(s.as_bytes())
+    }
+
+    pub fn write_u64(&mut self, v: u64) -> &mut Self {
+        self.write_bytes(&v.to_le_bytes())
+    }
+
+    pub fn write_f32(&mut self, v: f32) -> &mut Self {
+        self.write_bytes(&v.to_bits().to_le_bytes())
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.hash
+    }
+
+    /// The hash as a fixed width hex string, handy for text protocols and json.
+    pub fn finish_hex(&self) -> String {
+        format!("{:016x}", self.hash)
+    }
+}
+
+impl Default for Fnv1aHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Fnv1aHasher;
+
+    #[test]
+    fn matches_reference_values() {
+        assert_eq!(Fnv1aHasher::new().finish(), 0xcbf29ce484222325);
+        assert_eq!(Fnv1aHasher::new().write_bytes(b"a").finish(), 0xaf63dc4c8601ec8c);
+        assert_eq!(Fnv1aHasher::new().write_bytes(b"foobar").finish(), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn str_boundaries_matter() {
+        let a = Fnv1aHasher::new().write_str("ab").write_str("c").finish();
+        let b = Fnv1aHasher::new().write_str("a").write_str("bc").finish();
+        assert_ne!(a, b);
+    }
+}
@@ -2,12 +2,20 @@
 
 use crate::core::math::vec2::Vec2;
 
+#[derive(Debug, Clone)]
 pub struct Aabb2d {
     pub min: Vec2,
     pub max: Vec2,
 }
 
 impl Aabb2d {
+    pub fn new(center: Vec2, half_size: Vec2) -> Self {
+        Self {
+            min: center - half_size,
+            max: center + half_size,
+        }
+    }
+
     pub fn from_point_cloud(points: &[Vec2]) -> Self {
         let mut points_iter = points.iter().map(|p| *p);
         
@@ -24,4 +32,22 @@ impl Aabb2d {
             max,
         }
     }
-}
\ No newline at end of file
+
+    /// Mirror horizontally around x = 0
+    pub fn mirror_x(&self) -> Self {
+        Self {
+            min: Vec2::new(-self.max.x, self.min.y),
+            max: Vec2::new(-self.min.x, self.max.y),
+        }
+    }
+
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        point.x >= self.min.x && point.x <= self.max.x &&
+        point.y >= self.min.y && point.y <= self.max.y
+    }
+
+    /// The point in the box nearest to point, which is point itself if it's inside
+    pub fn closest_point(&self, point: Vec2) -> Vec2 {
+        Vec2::new(point.x.clamp(self.min.x, self.max.x), point.y.clamp(self.min.y, self.max.y))
+    }
+}
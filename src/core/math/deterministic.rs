@@ -0,0 +1,237 @@
+use std::f64::consts::{FRAC_2_PI, FRAC_PI_2, LN_2, LOG2_E, PI};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::math::vec2::Vec2;
+
+/// How the simulation works out sin, cos, atan2, exp and powf.
+///
+/// Basic arithmetic and sqrt are IEEE 754 and come out the same everywhere, but the others come from the platform's
+/// maths library, which can round the last bit differently on another OS or CPU. That is enough for two machines to
+/// drift apart over a run. Deterministic works them out from + - * / and sqrt alone, in f64 so they are still as
+/// accurate as f32 needs. It's slower and the physics differs slightly from Native, so every run on a board has to use
+/// the same mode.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MathMode {
+    #[default]
+    Native,
+    Deterministic,
+}
+
+impl MathMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MathMode::Native => "Native",
+            MathMode::Deterministic => "Deterministic",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            MathMode::Native => MathMode::Deterministic,
+            MathMode::Deterministic => MathMode::Native,
+        }
+    }
+
+    pub fn sin(self, x: f32) -> f32 {
+        match self {
+            MathMode::Native => x.sin(),
+            MathMode::Deterministic => sin_cos(x).0,
+        }
+    }
+
+    pub fn cos(self, x: f32) -> f32 {
+        match self {
+            MathMode::Native => x.cos(),
+            MathMode::Deterministic => sin_cos(x).1,
+        }
+    }
+
+    pub fn atan2(self, y: f32, x: f32) -> f32 {
+        match self {
+            MathMode::Native => y.atan2(x),
+            MathMode::Deterministic => atan2(y as f64, x as f64) as f32,
+        }
+    }
+
+    pub fn exp(self, x: f32) -> f32 {
+        match self {
+            MathMode::Native => x.exp(),
+            MathMode::Deterministic => exp(x as f64) as f32,
+        }
+    }
+
+    pub fn powf(self, x: f32, y: f32) -> f32 {
+        match self {
+            MathMode::Native => x.powf(y),
+            MathMode::Deterministic => powf(x as f64, y as f64) as f32,
+        }
+    }
+
+    /// Rotate anticlockwise by angle_rad, the same as Vec2::rotate_rad in Native mode
+    pub fn rotate(self, vec: Vec2, angle_rad: f32) -> Vec2 {
+        match self {
+            MathMode::Native => Vec2::rotate_rad(vec, angle_rad),
+            MathMode::Deterministic => {
+                let (s, c) = sin_cos(angle_rad);
+                Vec2::new(c * vec.x - s * vec.y, s * vec.x + c * vec.y)
+            }
+        }
+    }
+}
+
+/// Reduced to within a quarter turn of 0 then summed as a Taylor series, which is well converged by then
+fn sin_cos(x: f32) -> (f32, f32) {
+    if !x.is_finite() {
+        return (f32::NAN, f32::NAN);
+    }
+    let x = x as f64;
+    let quadrant = (x * FRAC_2_PI).round();
+    let r = x - quadrant * FRAC_PI_2;
+    let r2 = r * r;
+    let (mut sin, mut sin_term) = (r, r);
+    let (mut cos, mut cos_term) = (1.0, 1.0);
+    for n in 1..10 {
+        sin_term *= -r2 / ((2 * n) * (2 * n + 1)) as f64;
+        cos_term *= -r2 / ((2 * n - 1) * (2 * n)) as f64;
+        sin += sin_term;
+        cos += cos_term;
+    }
+    let (sin, cos) = match (quadrant as i64).rem_euclid(4) {
+        0 => (sin, cos),
+        1 => (cos, -sin),
+        2 => (-sin, -cos),
+        _ => (-cos, sin),
+    };
+    (sin as f32, cos as f32)
+}
+
+fn atan(x: f64) -> f64 {
+    // atan(x) = ±pi/2 - atan(1/x) brings it within -1 to 1
+    let (mut x, offset) = if x.abs() > 1.0 { (-1.0 / x, FRAC_PI_2.copysign(x)) } else { (x, 0.0) };
+    // then halving the angle twice, atan(x) = 2 atan(x / (1 + sqrt(1 + x^2))), gets the series converging quickly
+    for _ in 0..2 {
+        x /= 1.0 + (1.0 + x * x).sqrt();
+    }
+    let x2 = x * x;
+    let (mut sum, mut term) = (x, x);
+    for n in 1..14 {
+        term *= -x2;
+        sum += term / (2 * n + 1) as f64;
+    }
+    offset + 4.0 * sum
+}
+
+fn atan2(y: f64, x: f64) -> f64 {
+    if x.is_nan() || y.is_nan() {
+        return f64::NAN;
+    }
+    if x == 0.0 {
+        if y != 0.0 {
+            return FRAC_PI_2.copysign(y);
+        }
+        return if x.is_sign_negative() { PI.copysign(y) } else { 0.0f64.copysign(y) };
+    }
+    let angle = atan(y / x);
+    if x < 0.0 {
+        angle + PI.copysign(y)
+    } else {
+        angle
+    }
+}
+
+/// e^x as 2^n e^r with r under half of ln 2, where the series converges quickly
+fn exp(x: f64) -> f64 {
+    if x.is_nan() {
+        return x;
+    }
+    // well past what f32 can hold either way
+    if x > 200.0 {
+        return f64::INFINITY;
+    }
+    if x < -200.0 {
+        return 0.0;
+    }
+    let n = (x * LOG2_E).round();
+    let r = x - n * LN_2;
+    let (mut sum, mut term) = (1.0, 1.0);
+    for k in 1..18 {
+        term *= r / k as f64;
+        sum += term;
+    }
+    sum * f64::from_bits(((n as i64 + 1023) as u64) << 52)
+}
+
+/// ln of a positive, finite x, as e ln 2 + ln m with x = m 2^e and m from 1 to 2
+fn ln(x: f64) -> f64 {
+    let bits = x.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i64 - 1023;
+    let m = f64::from_bits((bits & ((1 << 52) - 1)) | (1023 << 52));
+    // ln(m) = 2 atanh(s), s is at most a third
+    let s = (m - 1.0) / (m + 1.0);
+    let s2 = s * s;
+    let (mut sum, mut term) = (s, s);
+    for n in 1..24 {
+        term *= s2;
+        sum += term / (2 * n + 1) as f64;
+    }
+    exponent as f64 * LN_2 + 2.0 * sum
+}
+
+fn powf(x: f64, y: f64) -> f64 {
+    if y == 0.0 || x == 1.0 {
+        return 1.0;
+    }
+    if x.is_nan() || y.is_nan() {
+        return f64::NAN;
+    }
+    if x == 0.0 || x.is_infinite() {
+        let grows = (x == 0.0) == (y < 0.0);
+        return if grows { f64::INFINITY } else { 0.0 };
+    }
+    if x < 0.0 {
+        if y.fract() != 0.0 {
+            return f64::NAN;
+        }
+        let magnitude = powf(-x, y);
+        return if (y % 2.0).abs() == 1.0 { -magnitude } else { magnitude };
+    }
+    exp(y * ln(x))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(deterministic: f32, native: f32) {
+        if deterministic == native {
+            return; // also covers the infinities
+        }
+        let tolerance = 2.0 * f32::EPSILON * native.abs().max(1.0);
+        assert!((deterministic - native).abs() <= tolerance, "{} vs {}", deterministic, native);
+    }
+
+    #[test]
+    fn test_matches_native_closely() {
+        let mode = MathMode::Deterministic;
+        for i in -2000..2000 {
+            let x = i as f32 * 0.0173;
+            assert_close(mode.sin(x), x.sin());
+            assert_close(mode.cos(x), x.cos());
+            assert_close(mode.atan2(x, 3.0), x.atan2(3.0));
+            assert_close(mode.atan2(-2.0, x), (-2.0f32).atan2(x));
+            assert_close(mode.exp(x * 0.1), (x * 0.1).exp());
+            assert_close(mode.powf(x.abs(), 4.0), x.abs().powf(4.0));
+            assert_close(mode.powf(x.abs(), -0.7), x.abs().powf(-0.7));
+        }
+        assert_eq!(mode.atan2(0.0, -1.0), std::f32::consts::PI);
+        assert_eq!(mode.atan2(0.0, 0.0), 0.0);
+        assert_eq!(mode.exp(-500.0), 0.0);
+        assert_eq!(mode.powf(-2.0, 3.0), -8.0);
+        assert!(mode.powf(-2.0, 0.5).is_nan());
+
+        let rotated = mode.rotate(Vec2::new(1.0, 0.0), std::f32::consts::FRAC_PI_2);
+        assert!(rotated.x.abs() < 1e-7 && (rotated.y - 1.0).abs() < 1e-7);
+    }
+}
@@ -6,4 +6,5 @@ pub mod aabb2d;
 pub mod float;
 pub mod unit_conversions;
 pub mod random;
-pub mod bezier_spline;
\ No newline at end of file
+pub mod bezier_spline;
+pub mod deterministic;
@@ -1,7 +1,7 @@
 use rand_seeder::Seeder;
 use rand_pcg::Pcg64;
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use now::DateTimeNow;
 
 /// Random number generator
@@ -17,12 +17,22 @@ impl Random {
     }
 
     pub fn seed_from_beginning_of_day() -> Pcg64 {
-        let time = Utc::now(); 
+        Self::seed_from_day(Utc::now())
+    }
+
+    /// Seed from the start of the day the time is in, so any time on a day gives that day's level
+    pub fn seed_from_day(time: DateTime<Utc>) -> Pcg64 {
         let beginning_of_day = time.beginning_of_day();
         let rng: Pcg64 = Seeder::from(beginning_of_day).into_rng();
         rng
     }
 
+    /// Seed from an arbitrary string, eg. a level pack level id
+    pub fn seed_from_str(seed: &str) -> Pcg64 {
+        let rng: Pcg64 = Seeder::from(seed).into_rng();
+        rng
+    }
+
     pub fn seed_from_now() -> Pcg64 {
         let rng: Pcg64 = Seeder::from(Utc::now()).into_rng();
         rng
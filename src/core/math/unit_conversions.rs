@@ -2,7 +2,7 @@ pub fn m_to_cm(m: f32) -> f32 {
     m * 100.0
 }
 
-pub fn cm_to_m(cm: f32) -> f32 {
+pub const fn cm_to_m(cm: f32) -> f32 {
     cm * 0.01
 }
 
@@ -42,6 +42,11 @@ impl Vec2 {
         self.0.magnitude2()
     }
 
+    /// Neither component is NaN or infinite
+    pub fn is_finite(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite()
+    }
+
     pub fn normalize(&self) -> Self {
         if self.magnitude() == 0.0 {
             Self::zero()
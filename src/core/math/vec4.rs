@@ -59,6 +59,12 @@ impl Vec4 {
     pub const BLUE: Self = Self(cgmath::Vector4::new(0.0, 0.0, 1.0, 1.0));
     pub const WHITE: Self = Self(cgmath::Vector4::new(1.0, 1.0, 1.0, 1.0));
     pub const BLACK: Self = Self(cgmath::Vector4::new(0.0, 0.0, 0.0, 1.0));
+
+    /// Pack a 0 to 1 colour into 8 bits per channel, red in the lowest byte to match WGSL's unpack4x8unorm
+    pub fn to_rgba8(&self) -> u32 {
+        let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u32;
+        channel(self.x) | channel(self.y) << 8 | channel(self.z) << 16 | channel(self.w) << 24
+    }
 }
 
 // Conversions
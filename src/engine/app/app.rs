@@ -14,7 +14,9 @@ use crate::engine::app::{
     window_helper::WindowHelper,
     event_system::EventSystem,
     ui_helper::UIHelper,
+    gamepad_helper::GamepadHelper,
 };
+use crate::engine::audio::audio_manager::AudioManager;
 
 pub struct App<L: GameLoop> {
     #[cfg(target_arch = "wasm32")]
@@ -94,8 +96,10 @@ impl<L: GameLoop> ApplicationHandler<Context> for App<L> {
             let window_helper = WindowHelper::new(window);
             let event_system = EventSystem::new();
             let ui = UIHelper::new_with_engine(&graphics, &window_helper, &graphics.adapter);
+            let gamepads = GamepadHelper::new();
+            let audio = AudioManager::new();
             
-            self.ctx = Some(Context::new(graphics, window_helper, event_system, ui));
+            self.ctx = Some(Context::new(graphics, window_helper, event_system, ui, gamepads, audio));
             self.on_context_set();
         }
 
@@ -136,6 +140,7 @@ impl<L: GameLoop> ApplicationHandler<Context> for App<L> {
                 }
                 self.last_frame_time = Some(now);
                 ctx.frame_count += 1;
+                ctx.gamepads.poll(&mut ctx.event_system);
 
                 if let Some(game_logic) = &mut self.game_logic {
                     game_logic.update(ctx);
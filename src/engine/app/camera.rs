@@ -1,7 +1,9 @@
 use cgmath::prelude::*;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "client")]
 use wgpu::util::DeviceExt;
 
-use crate::engine::app::event_system::KeyCodeType;
+use crate::{core::math::vec2::Vec2, engine::app::event_system::KeyCodeType};
 
 #[rustfmt::skip]
 pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::from_cols(
@@ -11,6 +13,31 @@ pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::from_co
     cgmath::Vector4::new(0.0, 0.0, 0.5, 1.0),
 );
 
+/// How the world gets flattened onto the screen
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Projection {
+    #[default]
+    Perspective, // the original look, the eye stays put and turns to follow the target
+    Orthographic, // flat top down, every particle is the same size in pixels wherever it is on screen
+}
+
+impl Projection {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Projection::Perspective => "Perspective",
+            Projection::Orthographic => "Orthographic",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Projection::Perspective => Projection::Orthographic,
+            Projection::Orthographic => Projection::Perspective,
+        }
+    }
+}
+
 pub struct Camera {
     pub eye: cgmath::Point3<f32>,
     pub target: cgmath::Point3<f32>,
@@ -19,18 +46,40 @@ pub struct Camera {
     pub fovy: f32,
     pub znear: f32,
     pub zfar: f32,
+    pub projection: Projection,
 
     // For now we only have 1 camera, so we can store the uniform and buffer here.
     pub camera_uniform: Option<CameraUniform>,
+    #[cfg(feature = "client")]
     pub camera_buffer: Option<wgpu::Buffer>,
 }
 
 impl Camera {
+    #[cfg(feature = "client")]
     pub fn new(device: &wgpu::Device, aspect: f32) -> Self {
+        let mut camera = Self::new_headless(aspect);
+
+        let mut camera_uniform = CameraUniform::new();
+        camera_uniform.update_view_proj(&camera.build_view_projection_matrix());
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::cast_slice(&[camera_uniform]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        camera.camera_uniform = Some(camera_uniform);
+        camera.camera_buffer = Some(camera_buffer);
+
+        camera
+    }
+
+    /// A camera without a GPU buffer, for running the game logic without a window (eg. the determinism audit)
+    pub fn new_headless(aspect: f32) -> Self {
         // We are using the RHS coordinate system.
         // Use your right hand and point the thumb along the x-axis, index fingers up along the Y Axis,
         // so Z is middle finger points towards you.
-        let mut camera = Self {
+        Self {
             eye: (0.0, 5.0, 15.0).into(), // the position of the camera
             target: (0.0, 0.0, 0.0).into(),
             up: cgmath::Vector3::unit_y(),
@@ -38,31 +87,72 @@ impl Camera {
             fovy: 45.0,
             znear: 0.1,
             zfar: 100.0,
+            projection: Projection::Perspective,
 
             camera_uniform: None,
+            #[cfg(feature = "client")]
             camera_buffer: None,
-        };
+        }
+    }
 
-        let mut camera_uniform = CameraUniform::new();
-        camera_uniform.update_view_proj(&camera.build_view_projection_matrix());
+    /// World units visible from the bottom to the top of the screen in orthographic mode.
+    /// Matches what the perspective camera sees at the height of its eye, and zooms with fovy the same way.
+    pub fn view_height(&self) -> f32 {
+        2.0 * self.eye.z * (cgmath::Deg(self.fovy) / 2.0).tan()
+    }
 
-        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Camera Buffer"),
-            contents: bytemuck::cast_slice(&[camera_uniform]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
+    pub fn build_view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
+        match self.projection {
+            Projection::Perspective => {
+                let view = cgmath::Matrix4::look_at_rh(self.eye, self.target, self.up);
+                let proj = cgmath::perspective(cgmath::Deg(self.fovy), self.aspect, self.znear, self.zfar);
+                proj * view
+            }
+            Projection::Orthographic => {
+                // straight down the z axis at the target, from the same height as the perspective eye
+                let eye = cgmath::Point3::new(self.target.x, self.target.y, self.target.z + self.eye.z);
+                let view = cgmath::Matrix4::look_at_rh(eye, self.target, self.up);
+                let half_height = self.view_height() / 2.0;
+                let half_width = half_height * self.aspect;
+                let proj = cgmath::ortho(-half_width, half_width, -half_height, half_height, self.znear, self.zfar);
+                proj * view
+            }
+        }
+    }
 
-        camera.camera_uniform = Some(camera_uniform);
-        camera.camera_buffer = Some(camera_buffer);
+    /// Where a point on the z = 0 plane ends up on a screen of the given size, in pixels from the top left
+    pub fn world_to_screen(&self, world: Vec2, screen_size: Vec2) -> Vec2 {
+        let clip = self.build_view_projection_matrix() * cgmath::Vector4::new(world.x, world.y, 0.0, 1.0);
+        let ndc = Vec2::new(clip.x / clip.w, clip.y / clip.w);
+        Vec2::new((ndc.x + 1.0) * 0.5 * screen_size.x, (1.0 - ndc.y) * 0.5 * screen_size.y)
+    }
 
-        camera
+    /// The point on the z = 0 plane under a pixel, for mouse picking. None if the ray through it misses the plane.
+    pub fn screen_to_world(&self, screen: Vec2, screen_size: Vec2) -> Option<Vec2> {
+        let inverse = self.build_view_projection_matrix().invert()?;
+        let ndc = Vec2::new(screen.x / screen_size.x * 2.0 - 1.0, 1.0 - screen.y / screen_size.y * 2.0);
+        let unproject = |z: f32| {
+            let point = inverse * cgmath::Vector4::new(ndc.x, ndc.y, z, 1.0);
+            point.truncate() / point.w
+        };
+
+        // ray from the near plane to the far plane, in OpenGL clip space as that's what the matrix is built for
+        let near = unproject(-1.0);
+        let far = unproject(1.0);
+        let t = near.z / (near.z - far.z);
+        if !t.is_finite() || t < 0.0 {
+            return None;
+        }
+        let hit = near + (far - near) * t;
+        Some(Vec2::new(hit.x, hit.y))
     }
-    pub fn build_view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
-        let view = cgmath::Matrix4::look_at_rh(self.eye, self.target, self.up);
-        let proj = cgmath::perspective(cgmath::Deg(self.fovy), self.aspect, self.znear, self.zfar);
-        proj * view
+
+    /// Screen pixels per world unit, which is the same everywhere on screen in orthographic mode
+    pub fn pixels_per_unit(&self, screen_height: f32) -> f32 {
+        screen_height / self.view_height()
     }
 
+    #[cfg(feature = "client")]
     pub fn update_camera_uniform(&mut self, queue: &wgpu::Queue) {
         let projection_matrix = self.build_view_projection_matrix();
 
@@ -187,3 +277,43 @@ impl CameraController {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Vec2, b: Vec2) {
+        assert!(a.distance(b) < 1e-3, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_orthographic_world_to_screen() {
+        let mut camera = Camera::new_headless(2.0);
+        camera.projection = Projection::Orthographic;
+        camera.target = (3.0, 1.0, 0.0).into();
+        let screen_size = Vec2::new(800.0, 400.0);
+
+        // the target is in the middle of the screen
+        assert_near(camera.world_to_screen(Vec2::new(3.0, 1.0), screen_size), Vec2::new(400.0, 200.0));
+
+        // one world unit is the same number of pixels anywhere on screen, and up in the world is up on screen
+        let pixels = camera.pixels_per_unit(screen_size.y);
+        assert_near(camera.world_to_screen(Vec2::new(4.0, 1.0), screen_size), Vec2::new(400.0 + pixels, 200.0));
+        assert_near(camera.world_to_screen(Vec2::new(-2.0, 3.0), screen_size), Vec2::new(400.0 - 5.0 * pixels, 200.0 - 2.0 * pixels));
+    }
+
+    #[test]
+    fn test_screen_to_world_round_trip() {
+        let screen_size = Vec2::new(800.0, 400.0);
+        for projection in [Projection::Perspective, Projection::Orthographic] {
+            let mut camera = Camera::new_headless(2.0);
+            camera.projection = projection;
+            camera.target = (3.0, 1.0, 0.0).into();
+
+            for world in [Vec2::new(3.0, 1.0), Vec2::new(-2.0, 4.0), Vec2::new(6.5, -1.5)] {
+                let screen = camera.world_to_screen(world, screen_size);
+                assert_near(camera.screen_to_world(screen, screen_size).unwrap(), world);
+            }
+        }
+    }
+}
@@ -1,11 +1,15 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fs;
 use std::io::{self, Write};
+#[cfg(feature = "client")]
 use winit::event::{ElementState, MouseButton, WindowEvent, KeyEvent};
+#[cfg(feature = "client")]
 use winit::keyboard::{KeyCode, PhysicalKey};
 
-/// Serializable game event that wraps the relevant parts of WindowEvent
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Serializable game event that wraps the relevant parts of WindowEvent.
+/// It's plain data so events are copied, never cloned, as they are queued, recorded and replayed.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum GameEvent {
     CloseRequested,
     Resized { width: u32, height: u32 },
@@ -22,8 +26,15 @@ pub enum GameEvent {
         x: f32,
         y: f32,
     },
+    GamepadInput {
+        input: GamepadInputType,
+    },
 }
 
+/// Sticks and triggers move in steps of 1 / GAMEPAD_AXIS_STEPS, so recordings and ghosts can store them exactly
+/// and a resting stick doesn't send an event every frame
+pub const GAMEPAD_AXIS_STEPS: f32 = 20.0;
+
 /// Serializable mouse button type
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum MouseButtonType {
@@ -35,6 +46,61 @@ pub enum MouseButtonType {
     Other(u16),
 }
 
+/// Serializable gamepad stick or analog trigger
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadAxisType {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+/// Serializable gamepad button, named by where it sits so it's the same on every make of pad
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadButtonType {
+    South,
+    East,
+    North,
+    West,
+    LeftShoulder,
+    RightShoulder,
+    Select,
+    Start,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+impl GamepadButtonType {
+    /// The key this button does the job of while driving, if any. Ghosts store buttons as these keys.
+    pub fn key_equivalent(&self) -> Option<KeyCodeType> {
+        match self {
+            GamepadButtonType::South => Some(KeyCodeType::KeyC),
+            GamepadButtonType::LeftShoulder => Some(KeyCodeType::KeyQ),
+            GamepadButtonType::RightShoulder => Some(KeyCodeType::KeyE),
+            GamepadButtonType::DPadUp => Some(KeyCodeType::ArrowUp),
+            GamepadButtonType::DPadDown => Some(KeyCodeType::ArrowDown),
+            _ => None,
+        }
+    }
+}
+
+/// A stick or trigger moving, -1 to 1 (0 to 1 for triggers), or a button going down or up
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum GamepadInputType {
+    Axis {
+        axis: GamepadAxisType,
+        value: f32,
+    },
+    Button {
+        button: GamepadButtonType,
+        state: ElementStateType,
+    },
+}
+
 /// Serializable element state (pressed/released)
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum ElementStateType {
@@ -58,15 +124,33 @@ pub enum KeyCodeType {
     KeyS,
     KeyZ,
     KeyX,
+    KeyC,
+    F5,
+    F6,
+    F7,
+    F8,
     F9,
     F10,
     F11,
     F12,
     KeyR,
+    KeyT,
+    KeyP,
+    KeyG,
+    KeyO,
+    KeyQ,
+    KeyE,
+    KeyB,
+    KeyF,
+    KeyI,
+    KeyM,
+    KeyV,
+    Delete,
     // Add more as needed
     Unknown,
 }
 
+#[cfg(feature = "client")]
 impl From<KeyCode> for KeyCodeType {
     fn from(code: KeyCode) -> Self {
         match code {
@@ -83,16 +167,62 @@ impl From<KeyCode> for KeyCodeType {
             KeyCode::KeyS => KeyCodeType::KeyS,
             KeyCode::KeyZ => KeyCodeType::KeyZ,
             KeyCode::KeyX => KeyCodeType::KeyX,
+            KeyCode::KeyC => KeyCodeType::KeyC,
+            KeyCode::F5 => KeyCodeType::F5,
+            KeyCode::F6 => KeyCodeType::F6,
+            KeyCode::F7 => KeyCodeType::F7,
+            KeyCode::F8 => KeyCodeType::F8,
             KeyCode::F9 => KeyCodeType::F9,
             KeyCode::F10 => KeyCodeType::F10,
             KeyCode::F11 => KeyCodeType::F11,
             KeyCode::F12 => KeyCodeType::F12,
             KeyCode::KeyR => KeyCodeType::KeyR,
+            KeyCode::KeyT => KeyCodeType::KeyT,
+            KeyCode::KeyP => KeyCodeType::KeyP,
+            KeyCode::KeyG => KeyCodeType::KeyG,
+            KeyCode::KeyO => KeyCodeType::KeyO,
+            KeyCode::KeyQ => KeyCodeType::KeyQ,
+            KeyCode::KeyE => KeyCodeType::KeyE,
+            KeyCode::KeyB => KeyCodeType::KeyB,
+            KeyCode::KeyF => KeyCodeType::KeyF,
+            KeyCode::KeyI => KeyCodeType::KeyI,
+            KeyCode::KeyM => KeyCodeType::KeyM,
+            KeyCode::KeyV => KeyCodeType::KeyV,
+            KeyCode::Delete => KeyCodeType::Delete,
             _ => KeyCodeType::Unknown,
         }
     }
 }
 
+/// Who keyboard and mouse input is meant for. Contexts are kept on a stack, the top one gets the input,
+/// so eg. keys typed into a text box on top of the game never steer the car.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputContext {
+    Menu,
+    Gameplay, // drives the car and camera, and the only context recorded
+    Editor, // the level editor is open, the car can still be driven to try out the level but nothing is recorded
+    TextEntry, // a text box has the keyboard, eg. the name entry, leaderboard search or an editor param
+}
+
+impl InputContext {
+    /// Whether keys are being typed as text, so hotkeys should leave them alone
+    pub fn is_text_entry(&self) -> bool {
+        *self == InputContext::TextEntry
+    }
+
+    /// Whether input goes to the car and camera
+    pub fn drives_car(&self) -> bool {
+        matches!(self, InputContext::Gameplay | InputContext::Editor)
+    }
+}
+
+/// An event and the input context that was on top when it arrived
+#[derive(Debug, Clone, Copy)]
+pub struct ContextEvent {
+    pub context: InputContext,
+    pub event: GameEvent,
+}
+
 /// Event paired with a frame number for recording/replay
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FramedEvent {
@@ -103,11 +233,66 @@ pub struct FramedEvent {
 /// Recording of a game session
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EventRecording {
+    /// Game specific information about the session, eg. how the level was generated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<serde_json::Value>,
     pub events: Vec<FramedEvent>,
 }
 
+impl EventRecording {
+    pub fn load(path: &str) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+}
+
+/// The keys and sticks held at the moment by a stream of input, eg. a replay, so it can be shown on screen.
+/// Gamepad buttons are kept as the key they stand in for.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HeldInput {
+    pub keys: Vec<KeyCodeType>, // in the order they went down
+    pub axes: Vec<(GamepadAxisType, f32)>, // only those away from rest
+}
+
+impl HeldInput {
+    pub fn apply(&mut self, event: &GameEvent) {
+        let (key_code, pressed) = match *event {
+            GameEvent::KeyboardInput { key_code, state } => (key_code, matches!(state, ElementStateType::Pressed)),
+            GameEvent::GamepadInput { input: GamepadInputType::Button { button, state } } => {
+                let Some(key_code) = button.key_equivalent() else { return };
+                (key_code, matches!(state, ElementStateType::Pressed))
+            }
+            GameEvent::GamepadInput { input: GamepadInputType::Axis { axis, value } } => {
+                self.axes.retain(|(a, _)| *a != axis);
+                if value != 0.0 {
+                    self.axes.push((axis, value));
+                }
+                return;
+            }
+            _ => return,
+        };
+        self.keys.retain(|k| *k != key_code);
+        if pressed {
+            self.keys.push(key_code);
+        }
+    }
+
+    pub fn is_pressed(&self, key_code: KeyCodeType) -> bool {
+        self.keys.contains(&key_code)
+    }
+
+    /// Where a stick or trigger is, 0 at rest
+    pub fn axis(&self, axis: GamepadAxisType) -> f32 {
+        self.axes.iter().find(|(a, _)| *a == axis).map_or(0.0, |(_, value)| *value)
+    }
+}
+
 pub struct EventSystem {
-    pub events: Vec<GameEvent>,
+    pub events: Vec<ContextEvent>,
+    context_stack: Vec<InputContext>, // never empty, the bottom is the base context
+    held_keys: HashSet<KeyCodeType>, // pressed in the gameplay context, released for it if the context changes
+    held_buttons: HashSet<GamepadButtonType>, // likewise for gamepad buttons
+    moved_axes: HashSet<GamepadAxisType>, // sticks and triggers away from rest in the gameplay context, put back to 0 if the context changes
     
     // Live state tracking (former InputHelper)
     // keys_pressed: HashSet<KeyCodeType>,
@@ -116,24 +301,34 @@ pub struct EventSystem {
     // Recording state
     recording: bool,
     recorded_events: Vec<FramedEvent>,
+    recording_header: Option<serde_json::Value>,
     current_frame: u128,
     
     // Replay state
     replaying: bool,
     replay_events: Vec<FramedEvent>,
+    replay_header: Option<serde_json::Value>,
     replay_index: usize,
+    replay_input: HeldInput, // what the replay has held down so far
 }
 
 impl EventSystem {
     pub fn new() -> Self {
         Self {
             events: vec![],
+            context_stack: vec![InputContext::Menu],
+            held_keys: HashSet::new(),
+            held_buttons: HashSet::new(),
+            moved_axes: HashSet::new(),
             recording: false,
             recorded_events: vec![],
+            recording_header: None,
             current_frame: 0,
             replaying: false,
             replay_events: vec![],
+            replay_header: None,
             replay_index: 0,
+            replay_input: HeldInput::default(),
         }
     }
 
@@ -142,6 +337,67 @@ impl EventSystem {
         self.current_frame = frame;
     }
 
+    /// The context that gets input at the moment
+    pub fn context(&self) -> InputContext {
+        *self.context_stack.last().unwrap()
+    }
+
+    /// Swap the context at the bottom of the stack, eg. between menus and gameplay as the game state changes
+    pub fn set_base_context(&mut self, context: InputContext) {
+        let previous = self.context();
+        self.context_stack[0] = context;
+        self.context_changed(previous);
+    }
+
+    /// Put a context on top, eg. when a text box gets the keyboard
+    pub fn push_context(&mut self, context: InputContext) {
+        let previous = self.context();
+        if previous != context {
+            self.context_stack.push(context);
+        }
+        self.context_changed(previous);
+    }
+
+    /// Take a context back off the stack, wherever it is. The base context stays put.
+    pub fn pop_context(&mut self, context: InputContext) {
+        let previous = self.context();
+        if let Some(index) = self.context_stack.iter().rposition(|c| *c == context).filter(|index| *index > 0) {
+            self.context_stack.remove(index);
+        }
+        self.context_changed(previous);
+    }
+
+    /// Keys held down driving the car would stay down forever if their release went to another context,
+    /// so the context that had them gets the releases as soon as the car loses the input. Same for gamepad buttons and sticks.
+    fn context_changed(&mut self, previous: InputContext) {
+        if !previous.drives_car() || self.context().drives_car() {
+            return;
+        }
+
+        let mut held_keys: Vec<KeyCodeType> = self.held_keys.drain().collect();
+        held_keys.sort_by_key(|key| *key as u32); // a stable order, the releases can end up in a recording
+        for key_code in held_keys {
+            self.queue_event_in(previous, GameEvent::KeyboardInput { key_code, state: ElementStateType::Released });
+        }
+
+        let mut held_buttons: Vec<GamepadButtonType> = self.held_buttons.drain().collect();
+        held_buttons.sort_by_key(|button| *button as u32);
+        for button in held_buttons {
+            self.queue_event_in(previous, GameEvent::GamepadInput { input: GamepadInputType::Button { button, state: ElementStateType::Released } });
+        }
+
+        let mut moved_axes: Vec<GamepadAxisType> = self.moved_axes.drain().collect();
+        moved_axes.sort_by_key(|axis| *axis as u32);
+        for axis in moved_axes {
+            self.queue_event_in(previous, GameEvent::GamepadInput { input: GamepadInputType::Axis { axis, value: 0.0 } });
+        }
+    }
+
+    /// Events that arrived while the given context was on top
+    pub fn events_in(&self, context: InputContext) -> impl Iterator<Item = &GameEvent> {
+        self.events.iter().filter(move |e| e.context == context).map(|e| &e.event)
+    }
+
     /// Start recording events
     pub fn start_recording(&mut self) {
         self.recording = true;
@@ -149,6 +405,16 @@ impl EventSystem {
         println!("Started recording events");
     }
 
+    /// Set the header written out with the recording
+    pub fn set_recording_header(&mut self, header: Option<serde_json::Value>) {
+        self.recording_header = header;
+    }
+
+    /// The header of the loaded replay (if it had one)
+    pub fn replay_header(&self) -> Option<&serde_json::Value> {
+        self.replay_header.as_ref()
+    }
+
     /// Stop recording events
     pub fn stop_recording(&mut self) {
         self.recording = false;
@@ -160,9 +426,15 @@ impl EventSystem {
         self.recording
     }
 
+    /// Events captured since recording started
+    pub fn recorded_events(&self) -> &[FramedEvent] {
+        &self.recorded_events
+    }
+
     /// Export recorded events to a JSON file
     pub fn export_recording(&self, path: &str) -> io::Result<()> {
         let recording = EventRecording {
+            header: self.recording_header.clone(),
             events: self.recorded_events.clone(),
         };
         
@@ -176,10 +448,10 @@ impl EventSystem {
 
     /// Load events from a JSON file
     pub fn load_replay(&mut self, path: &str) -> io::Result<()> {
-        let json = fs::read_to_string(path)?;
-        let recording: EventRecording = serde_json::from_str(&json)?;
+        let recording = EventRecording::load(path)?;
         
         self.replay_events = recording.events;
+        self.replay_header = recording.header;
         self.replay_index = 0;
         
         println!("Loaded {} events from {}", self.replay_events.len(), path);
@@ -195,6 +467,7 @@ impl EventSystem {
         
         self.replaying = true;
         self.replay_index = 0;
+        self.replay_input = HeldInput::default();
         println!("Started replay with {} events", self.replay_events.len());
     }
 
@@ -209,6 +482,12 @@ impl EventSystem {
         self.replaying
     }
 
+    /// The keys and sticks the replay is holding down, for the input overlay
+    pub fn replay_input(&self) -> &HeldInput {
+        &self.replay_input
+    }
+
+    #[cfg(feature = "client")]
     pub fn handle_window_event(&mut self, event: &WindowEvent, _scale_factor: f64) {
         if let Some(game_event) = self.window_event_to_game_event(event) {
             self.queue_event(game_event);
@@ -216,6 +495,7 @@ impl EventSystem {
     }
 
     /// Convert WindowEvent to GameEvent for serialization
+    #[cfg(feature = "client")]
     fn window_event_to_game_event(&mut self, event: &WindowEvent) -> Option<GameEvent> {
         match event {
             WindowEvent::MouseInput { button, state, .. } => {
@@ -260,25 +540,53 @@ impl EventSystem {
                     state: state_type,
                 })
             }
-            // WindowEvent::CursorMoved { position, .. } => {
-            //     self.mouse_position = Vec2::new(position.x as f32, position.y as f32);
-            //     Some(GameEvent::CursorMoved {
-            //         x: self.mouse_position.x,
-            //         y: self.mouse_position.y,
-            //     })
-            // }
+            WindowEvent::CursorMoved { position, .. } => {
+                Some(GameEvent::CursorMoved {
+                    x: position.x as f32,
+                    y: position.y as f32,
+                })
+            }
             _ => None,
         }
     }
 
     pub fn queue_event(&mut self, event: GameEvent) {
-        // Record the event if recording is active (only mouse, keyboard and cursor events)
-        if self.recording {
+        self.queue_event_in(self.context(), event);
+    }
+
+    fn queue_event_in(&mut self, context: InputContext, event: GameEvent) {
+        if context.drives_car() {
             match &event {
-                GameEvent::MouseInput { .. } | GameEvent::KeyboardInput { .. } | GameEvent::CursorMoved { .. } => {
+                GameEvent::KeyboardInput { key_code, state } => {
+                    match state {
+                        ElementStateType::Pressed => self.held_keys.insert(*key_code),
+                        ElementStateType::Released => self.held_keys.remove(key_code),
+                    };
+                }
+                GameEvent::GamepadInput { input: GamepadInputType::Button { button, state } } => {
+                    match state {
+                        ElementStateType::Pressed => self.held_buttons.insert(*button),
+                        ElementStateType::Released => self.held_buttons.remove(button),
+                    };
+                }
+                GameEvent::GamepadInput { input: GamepadInputType::Axis { axis, value } } => {
+                    if *value == 0.0 {
+                        self.moved_axes.remove(axis);
+                    } else {
+                        self.moved_axes.insert(*axis);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Record the event if recording is active (only gameplay mouse, keyboard, cursor and gamepad events)
+        if self.recording && context == InputContext::Gameplay {
+            match &event {
+                GameEvent::MouseInput { .. } | GameEvent::KeyboardInput { .. } | GameEvent::CursorMoved { .. } | GameEvent::GamepadInput { .. } => {
                     self.recorded_events.push(FramedEvent {
                         frame: self.current_frame,
-                        event: event.clone(),
+                        event,
                     });
                 }
                 _ => {}
@@ -286,7 +594,7 @@ impl EventSystem {
         }
 
         // Queue all events for processing
-        self.events.push(event);
+        self.events.push(ContextEvent { context, event });
     }
 
     /// Get replay events for the current frame and inject them into the event queue
@@ -305,9 +613,10 @@ impl EventSystem {
             }
             
             if framed_event.frame == self.current_frame {
-                // Directly queue GameEvent - it will also update state
-                let event = framed_event.event.clone();
-                self.queue_event(event);
+                // Directly queue GameEvent - it will also update state. Only gameplay input was recorded.
+                let event = framed_event.event;
+                self.replay_input.apply(&event);
+                self.queue_event_in(InputContext::Gameplay, event);
             }
             
             self.replay_index += 1;
@@ -331,3 +640,89 @@ impl EventSystem {
         self.events.clear();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key_code: KeyCodeType, state: ElementStateType) -> GameEvent {
+        GameEvent::KeyboardInput { key_code, state }
+    }
+
+    #[test]
+    fn test_text_entry_input_is_kept_from_gameplay() {
+        let mut event_system = EventSystem::new();
+        event_system.set_base_context(InputContext::Gameplay);
+        event_system.start_recording();
+
+        event_system.queue_event(key(KeyCodeType::KeyA, ElementStateType::Pressed));
+        event_system.push_context(InputContext::TextEntry);
+        event_system.queue_event(key(KeyCodeType::KeyD, ElementStateType::Pressed));
+        event_system.pop_context(InputContext::TextEntry);
+        assert_eq!(event_system.context(), InputContext::Gameplay);
+
+        // gameplay saw A go down and come back up when the text box got the keyboard, but never saw the D typed into it
+        let gameplay: Vec<_> = event_system.events_in(InputContext::Gameplay).collect();
+        assert!(matches!(gameplay[..], [
+            GameEvent::KeyboardInput { key_code: KeyCodeType::KeyA, state: ElementStateType::Pressed },
+            GameEvent::KeyboardInput { key_code: KeyCodeType::KeyA, state: ElementStateType::Released },
+        ]));
+        assert_eq!(event_system.events_in(InputContext::TextEntry).count(), 1);
+        assert_eq!(event_system.recorded_events().len(), 2);
+    }
+
+    #[test]
+    fn test_gamepad_is_let_go_when_gameplay_loses_input() {
+        let mut event_system = EventSystem::new();
+        event_system.set_base_context(InputContext::Gameplay);
+        event_system.queue_event(GameEvent::GamepadInput { input: GamepadInputType::Axis { axis: GamepadAxisType::RightTrigger, value: 0.5 } });
+        event_system.queue_event(GameEvent::GamepadInput { input: GamepadInputType::Button { button: GamepadButtonType::South, state: ElementStateType::Pressed } });
+        event_system.push_context(InputContext::Menu);
+
+        let gameplay: Vec<_> = event_system.events_in(InputContext::Gameplay).collect();
+        assert!(matches!(gameplay[2..], [
+            GameEvent::GamepadInput { input: GamepadInputType::Button { button: GamepadButtonType::South, state: ElementStateType::Released } },
+            GameEvent::GamepadInput { input: GamepadInputType::Axis { axis: GamepadAxisType::RightTrigger, value: 0.0 } },
+        ]));
+    }
+
+    #[test]
+    fn test_replay_input_follows_the_replay() {
+        let mut event_system = EventSystem::new();
+        event_system.replay_events = vec![
+            FramedEvent { frame: 0, event: key(KeyCodeType::KeyX, ElementStateType::Pressed) },
+            FramedEvent { frame: 0, event: GameEvent::GamepadInput { input: GamepadInputType::Button { button: GamepadButtonType::LeftShoulder, state: ElementStateType::Pressed } } },
+            FramedEvent { frame: 1, event: GameEvent::GamepadInput { input: GamepadInputType::Axis { axis: GamepadAxisType::RightTrigger, value: 0.5 } } },
+            FramedEvent { frame: 1, event: key(KeyCodeType::KeyX, ElementStateType::Released) },
+            FramedEvent { frame: 5, event: key(KeyCodeType::KeyZ, ElementStateType::Pressed) },
+        ];
+        event_system.start_replay();
+        event_system.process_events();
+        assert_eq!(event_system.replay_input().keys, vec![KeyCodeType::KeyX, KeyCodeType::KeyQ]);
+
+        event_system.set_frame(1);
+        event_system.process_events();
+        let input = event_system.replay_input();
+        assert!(!input.is_pressed(KeyCodeType::KeyX) && input.is_pressed(KeyCodeType::KeyQ));
+        assert_eq!(input.axis(GamepadAxisType::RightTrigger), 0.5);
+        assert_eq!(input.axis(GamepadAxisType::LeftTrigger), 0.0);
+
+        // starting over lets go of everything
+        event_system.start_replay();
+        assert_eq!(event_system.replay_input(), &HeldInput::default());
+    }
+
+    #[test]
+    fn test_base_context_is_never_popped() {
+        let mut event_system = EventSystem::new();
+        event_system.pop_context(InputContext::Menu);
+        assert_eq!(event_system.context(), InputContext::Menu);
+
+        event_system.push_context(InputContext::TextEntry);
+        event_system.push_context(InputContext::TextEntry);
+        event_system.set_base_context(InputContext::Gameplay);
+        assert_eq!(event_system.context(), InputContext::TextEntry);
+        event_system.pop_context(InputContext::TextEntry);
+        assert_eq!(event_system.context(), InputContext::Gameplay);
+    }
+}
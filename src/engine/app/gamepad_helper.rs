@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+#[cfg(feature = "gamepad")]
+use crate::engine::app::event_system::{ElementStateType, GameEvent, GamepadButtonType};
+use crate::engine::app::event_system::{EventSystem, GamepadAxisType, GamepadInputType, GAMEPAD_AXIS_STEPS};
+
+/// Sticks closer to the middle than this count as centred, worn sticks never quite come back to 0
+const DEADZONE: f32 = 0.15;
+
+/// Reads gamepads and queues what they do as GameEvents. Built with the gamepad feature this uses gilrs.
+/// Without it, or if gilrs can't start, there are never any gamepads and the game is played on the keyboard.
+#[derive(Default)]
+pub struct GamepadHelper {
+    #[cfg(feature = "gamepad")]
+    gilrs: Option<gilrs::Gilrs>,
+    axes: HashMap<GamepadAxisType, f32>, // the last value queued for each stick and trigger
+    #[cfg(feature = "gamepad")]
+    rumble_effects: Option<[gilrs::ff::Effect; 2]>, // strong and weak motors, left playing with their gain set by rumble
+    #[cfg(feature = "gamepad")]
+    rumble: (f32, f32), // the strong and weak motor strengths last set
+}
+
+impl GamepadHelper {
+    #[cfg(not(feature = "gamepad"))]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[cfg(feature = "gamepad")]
+    pub fn new() -> Self {
+        match gilrs::Gilrs::new() {
+            Ok(gilrs) => {
+                for (_, gamepad) in gilrs.gamepads() {
+                    println!("Gamepad connected: {}", gamepad.name());
+                }
+                Self { gilrs: Some(gilrs), ..Self::default() }
+            }
+            Err(e) => {
+                println!("Gamepads unavailable: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Queue whatever the gamepads did since the last frame
+    #[cfg(not(feature = "gamepad"))]
+    pub fn poll(&mut self, _event_system: &mut EventSystem) {}
+
+    #[cfg(feature = "gamepad")]
+    pub fn poll(&mut self, event_system: &mut EventSystem) {
+        use gilrs::{Axis, Button, EventType};
+
+        let Some(gilrs) = self.gilrs.as_mut() else { return };
+        let mut events = vec![];
+        while let Some(event) = gilrs.next_event() {
+            if event.event == EventType::Connected {
+                println!("Gamepad connected: {}", gilrs.gamepad(event.id).name());
+            }
+            if matches!(event.event, EventType::Connected | EventType::Disconnected) {
+                self.rumble_effects = None; // made again for the gamepads there are now
+            }
+            events.push(event.event);
+        }
+
+        for event in events {
+            let input = match event {
+                // analog triggers say how far they are pulled as a button value
+                EventType::ButtonChanged(Button::LeftTrigger2, value, _) => self.axis_moved(GamepadAxisType::LeftTrigger, value),
+                EventType::ButtonChanged(Button::RightTrigger2, value, _) => self.axis_moved(GamepadAxisType::RightTrigger, value),
+                EventType::AxisChanged(axis, value, _) => {
+                    let axis = match axis {
+                        Axis::LeftStickX => GamepadAxisType::LeftStickX,
+                        Axis::LeftStickY => GamepadAxisType::LeftStickY,
+                        Axis::RightStickX => GamepadAxisType::RightStickX,
+                        Axis::RightStickY => GamepadAxisType::RightStickY,
+                        _ => continue,
+                    };
+                    self.axis_moved(axis, value)
+                }
+                EventType::ButtonPressed(button, _) => button_type(button).map(|button| GamepadInputType::Button { button, state: ElementStateType::Pressed }),
+                EventType::ButtonReleased(button, _) => button_type(button).map(|button| GamepadInputType::Button { button, state: ElementStateType::Released }),
+                _ => None,
+            };
+            if let Some(input) = input {
+                event_system.queue_event(GameEvent::GamepadInput { input });
+            }
+        }
+    }
+
+    /// Shake the gamepads, strong and weak are how hard each motor runs from 0 to 1
+    #[cfg(not(feature = "gamepad"))]
+    pub fn rumble(&mut self, _strong: f32, _weak: f32) {}
+
+    #[cfg(feature = "gamepad")]
+    pub fn rumble(&mut self, strong: f32, weak: f32) {
+        use gilrs::ff::{BaseEffect, BaseEffectType, EffectBuilder};
+
+        let Some(gilrs) = self.gilrs.as_mut() else { return };
+        if (strong, weak) == self.rumble && self.rumble_effects.is_some() {
+            return;
+        }
+        self.rumble = (strong, weak);
+
+        if self.rumble_effects.is_none() {
+            if strong == 0.0 && weak == 0.0 {
+                return;
+            }
+            let ids: Vec<gilrs::GamepadId> = gilrs.gamepads().filter(|(_, gamepad)| gamepad.is_ff_supported()).map(|(id, _)| id).collect();
+            if ids.is_empty() {
+                return;
+            }
+            let mut effect = |kind| EffectBuilder::new().add_effect(BaseEffect { kind, ..Default::default() }).gamepads(&ids).gain(0.0).finish(gilrs);
+            let (Ok(strong_effect), Ok(weak_effect)) = (effect(BaseEffectType::Strong { magnitude: u16::MAX }), effect(BaseEffectType::Weak { magnitude: u16::MAX })) else {
+                return;
+            };
+            let _ = strong_effect.play();
+            let _ = weak_effect.play();
+            self.rumble_effects = Some([strong_effect, weak_effect]);
+        }
+
+        if let Some([strong_effect, weak_effect]) = &self.rumble_effects {
+            let _ = strong_effect.set_gain(strong.clamp(0.0, 1.0));
+            let _ = weak_effect.set_gain(weak.clamp(0.0, 1.0));
+        }
+    }
+
+    /// Round a stick or trigger to its steps, centring it inside the deadzone. None if that leaves it where it was.
+    pub fn axis_moved(&mut self, axis: GamepadAxisType, value: f32) -> Option<GamepadInputType> {
+        let value = if value.abs() < DEADZONE { 0.0 } else { (value.clamp(-1.0, 1.0) * GAMEPAD_AXIS_STEPS).round() / GAMEPAD_AXIS_STEPS };
+        let previous = self.axes.insert(axis, value).unwrap_or(0.0);
+        (value != previous).then_some(GamepadInputType::Axis { axis, value })
+    }
+}
+
+/// The buttons the game uses, the triggers are read as axes
+#[cfg(feature = "gamepad")]
+fn button_type(button: gilrs::Button) -> Option<GamepadButtonType> {
+    use gilrs::Button;
+
+    match button {
+        Button::South => Some(GamepadButtonType::South),
+        Button::East => Some(GamepadButtonType::East),
+        Button::North => Some(GamepadButtonType::North),
+        Button::West => Some(GamepadButtonType::West),
+        Button::LeftTrigger => Some(GamepadButtonType::LeftShoulder),
+        Button::RightTrigger => Some(GamepadButtonType::RightShoulder),
+        Button::Select => Some(GamepadButtonType::Select),
+        Button::Start => Some(GamepadButtonType::Start),
+        Button::DPadUp => Some(GamepadButtonType::DPadUp),
+        Button::DPadDown => Some(GamepadButtonType::DPadDown),
+        Button::DPadLeft => Some(GamepadButtonType::DPadLeft),
+        Button::DPadRight => Some(GamepadButtonType::DPadRight),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_axes_are_stepped_and_deadzoned() {
+        let mut gamepads = GamepadHelper::default();
+        assert!(gamepads.axis_moved(GamepadAxisType::LeftStickX, 0.1).is_none()); // resting in the deadzone
+        assert!(matches!(gamepads.axis_moved(GamepadAxisType::LeftStickX, -0.52), Some(GamepadInputType::Axis { value: -0.5, .. })));
+        assert!(gamepads.axis_moved(GamepadAxisType::LeftStickX, -0.51).is_none()); // same step
+        assert!(matches!(gamepads.axis_moved(GamepadAxisType::RightTrigger, 1.2), Some(GamepadInputType::Axis { value: 1.0, .. })));
+        assert!(matches!(gamepads.axis_moved(GamepadAxisType::LeftStickX, 0.05), Some(GamepadInputType::Axis { value: 0.0, .. })));
+    }
+}
@@ -1,15 +1,19 @@
 use std::sync::Arc;
 use winit::window::Window;
-use crate::engine::renderer::texture;
+use crate::engine::renderer::{pipeline_cache::PipelineCache, render_targets::{RenderTargetId, TargetKind, RenderTargets, TargetSize}, texture};
 
 pub struct GraphicsHelper {
     pub surface: wgpu::Surface<'static>,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub config: wgpu::SurfaceConfiguration,
-    pub depth_texture: texture::Texture,
+    pub render_targets: RenderTargets, // everything sized from the window, recreated on resize
+    depth_target: RenderTargetId,
+    scene_target: RenderTargetId, // the scene is drawn here and upscaled when the render scale is below 1
+    render_scale: f32,
     pub format: wgpu::TextureFormat,
     pub adapter: wgpu::Adapter,
+    pub pipeline_cache: PipelineCache,
 }
 
 impl GraphicsHelper {
@@ -38,7 +42,8 @@ impl GraphicsHelper {
         let (device, queue) = adapter
             .request_device(&wgpu::DeviceDescriptor {
                 label: None,
-                required_features: wgpu::Features::empty(),
+                // timestamp queries are optional, only the debug HUD's GPU timings need them
+                required_features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
                 required_limits: if cfg!(target_arch = "wasm32") {
                     wgpu::Limits::downlevel_webgl2_defaults()
                 } else {
@@ -71,7 +76,9 @@ impl GraphicsHelper {
             desired_maximum_frame_latency: 2,
         };
 
-        let depth_texture = texture::Texture::create_depth_texture(&device, &config, "depth_texture");
+        let mut render_targets = RenderTargets::new();
+        let depth_target = render_targets.add(&device, &config, "depth_texture", TargetSize::Scaled(1.0), TargetKind::Depth);
+        let scene_target = render_targets.add(&device, &config, "scene_texture", TargetSize::Scaled(1.0), TargetKind::Colour(surface_format));
 
         surface.configure(&device, &config);
 
@@ -80,9 +87,13 @@ impl GraphicsHelper {
             device,
             queue,
             config,
-            depth_texture,
+            render_targets,
+            depth_target,
+            scene_target,
+            render_scale: 1.0,
             format: surface_format,
             adapter,
+            pipeline_cache: PipelineCache::new(),
         })
     }
 
@@ -91,7 +102,81 @@ impl GraphicsHelper {
             self.config.width = width;
             self.config.height = height;
             self.surface.configure(&self.device, &self.config);
-            self.depth_texture = texture::Texture::create_depth_texture(&self.device, &self.config, "depth_texture");
+            self.render_targets.resize(&self.device, &self.config);
         }
     }
+
+    /// The main view's depth buffer, the size of the window times the render scale
+    pub fn depth_texture(&self) -> &texture::Texture {
+        self.render_targets.get(self.depth_target)
+    }
+
+    /// Where the scene is drawn when the render scale is below 1, and its generation for anything sampling it
+    pub fn scene_texture(&self) -> (&texture::Texture, u32) {
+        (self.render_targets.get(self.scene_target), self.render_targets.generation(self.scene_target))
+    }
+
+    /// Copy the scene target back from the GPU, waiting for it. Call once a frame that drew the scene into it has been
+    /// submitted. None if the surface format isn't 8 bit RGBA or BGRA, or the copy failed.
+    pub fn read_scene(&self) -> Option<image::RgbaImage> {
+        let bgra = match self.format {
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            _ => return None,
+        };
+        let (width, height) = self.render_targets.size(self.scene_target);
+        // rows in a copy have to start on a multiple of 256 bytes
+        let row_bytes = width * 4;
+        let padded_row_bytes = row_bytes.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Scene Readback Buffer"),
+            size: padded_row_bytes as wgpu::BufferAddress * height as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Scene Readback Encoder") });
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.render_targets.get(self.scene_target).texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout { offset: 0, bytes_per_row: Some(padded_row_bytes), rows_per_image: Some(height) },
+            },
+            wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::PollType::Wait { submission_index: None, timeout: None }).ok()?;
+        receiver.recv().ok()?.ok()?;
+
+        let mut pixels = Vec::with_capacity((row_bytes * height) as usize);
+        for row in buffer.slice(..).get_mapped_range().chunks(padded_row_bytes as usize) {
+            pixels.extend_from_slice(&row[..row_bytes as usize]);
+        }
+        buffer.unmap();
+        if bgra {
+            pixels.chunks_exact_mut(4).for_each(|pixel| pixel.swap(0, 2));
+        }
+        image::RgbaImage::from_raw(width, height, pixels)
+    }
+
+    pub fn render_scale(&self) -> f32 {
+        self.render_scale
+    }
+
+    /// Render the scene at a fraction of the window's resolution, the depth buffer follows it
+    pub fn set_render_scale(&mut self, scale: f32) {
+        self.render_scale = scale;
+        self.render_targets.set_size(&self.device, &self.config, self.depth_target, TargetSize::Scaled(scale));
+        self.render_targets.set_size(&self.device, &self.config, self.scene_target, TargetSize::Scaled(scale));
+    }
 }
@@ -1,8 +1,15 @@
+#[cfg(feature = "client")]
 pub mod app;
 pub mod camera;
+#[cfg(feature = "client")]
 pub mod graphics_helper;
+#[cfg(feature = "client")]
 pub mod window_helper;
 pub mod event_system;
+#[cfg(feature = "client")]
 pub mod ui_helper;
+pub mod gamepad_helper;
+#[cfg(feature = "client")]
 pub mod context;
+#[cfg(feature = "client")]
 pub mod game_loop;
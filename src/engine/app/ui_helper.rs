@@ -1,9 +1,10 @@
 use iced_wgpu::graphics::{Shell, Viewport};
 use iced_wgpu::{Engine, Renderer};
 use iced_winit::clipboard::Clipboard;
-use iced_winit::core::{mouse, Font, Pixels, Size, Theme};
+use iced_winit::core::{event, mouse, widget::{operation::Focusable, Id, Operation}, window::RedrawRequest, Event, Font, Pixels, Rectangle, Size, Theme};
 use iced_winit::runtime::user_interface::{self, UserInterface};
 use iced_winit::winit;
+use std::time::{Duration, Instant};
 use crate::engine::app::graphics_helper::GraphicsHelper;
 use crate::engine::app::window_helper::WindowHelper;
 
@@ -14,6 +15,55 @@ pub struct UIHelper {
     pub cursor: mouse::Cursor,
     pub clipboard: Clipboard,
     pub events: Vec<iced_winit::core::Event>,
+    pub theme: Theme, // for the widgets that don't set their own colours
+    pub refresh: UiRefresh,
+    pub text_focused: bool, // a text box has the keyboard, as of the last draw
+    pub click_captured: bool, // a widget took the last mouse press, so it isn't for the world underneath
+}
+
+/// Looks through the widgets for one with the keyboard
+#[derive(Default)]
+struct AnyFocused(bool);
+
+impl Operation for AnyFocused {
+    fn traverse(&mut self, operate: &mut dyn FnMut(&mut dyn Operation)) {
+        operate(self);
+    }
+
+    fn focusable(&mut self, _id: Option<&Id>, _bounds: Rectangle, state: &mut dyn Focusable) {
+        self.0 |= state.is_focused();
+    }
+}
+
+/// Most times a second the view is rebuilt for changed values. Input is always handled straight away.
+pub const UI_MAX_REFRESH_RATE: f32 = 60.0;
+
+/// Decides when the view needs building and laying out again. In between, the last built UI is presented as it was,
+/// so values that change every frame, eg. debug timings, don't cost a rebuild on every frame.
+#[derive(Debug, Default)]
+pub struct UiRefresh {
+    last_build: Option<Instant>,
+    redraw_at: Option<Instant>, // asked for by a widget, eg. to blink a text cursor
+    outdated: bool, // the window was resized
+}
+
+impl UiRefresh {
+    /// Whether to rebuild the view. Dirty is whether something the view shows has changed since it was last built.
+    pub fn needs_rebuild(&self, dirty: bool, has_events: bool, now: Instant) -> bool {
+        let Some(last_build) = self.last_build else { return true };
+        let refresh_due = now.duration_since(last_build) >= Duration::from_secs_f32(1.0 / UI_MAX_REFRESH_RATE);
+        self.outdated || has_events || self.redraw_at.is_some_and(|at| now >= at) || (dirty && refresh_due)
+    }
+
+    fn built(&mut self, now: Instant, redraw_request: RedrawRequest) {
+        self.last_build = Some(now);
+        self.outdated = false;
+        self.redraw_at = match redraw_request {
+            RedrawRequest::NextFrame => Some(now),
+            RedrawRequest::At(at) => Some(at),
+            RedrawRequest::Wait => None,
+        };
+    }
 }
 
 impl UIHelper {
@@ -45,6 +95,10 @@ impl UIHelper {
             cursor: mouse::Cursor::Unavailable,
             clipboard,
             events: Vec::new(),
+            theme: Theme::Dark,
+            refresh: UiRefresh::default(),
+            text_focused: false,
+            click_captured: false,
         }
     }
 
@@ -53,6 +107,12 @@ impl UIHelper {
             Size::new(width, height),
             scale_factor as f32,
         );
+        self.refresh.outdated = true;
+    }
+
+    /// Whether the view has to be built this frame, see UiRefresh. Otherwise call redraw.
+    pub fn needs_rebuild(&self, dirty: bool) -> bool {
+        self.refresh.needs_rebuild(dirty, !self.events.is_empty(), Instant::now())
     }
     
     pub fn handle_event(&mut self, event: &winit::event::WindowEvent, scale_factor: f64) {
@@ -86,23 +146,35 @@ impl UIHelper {
         );
 
         let mut messages = Vec::new();
-        let (_state, _) = user_interface.update(
+        let (state, statuses) = user_interface.update(
             &self.events,
             self.cursor,
             &mut self.renderer,
             &mut self.clipboard,
             &mut messages,
         );
+        let mut presses = self.events.iter().zip(statuses).filter(|(event, _)| matches!(event, Event::Mouse(mouse::Event::ButtonPressed(_))));
+        if let Some((_, status)) = presses.next_back() {
+            self.click_captured = status == event::Status::Captured;
+        }
+        let mut focused = AnyFocused::default();
+        user_interface.operate(&self.renderer, &mut focused);
+        self.text_focused = focused.0;
 
         user_interface.draw(
             &mut self.renderer,
-            &Theme::Dark,
+            &self.theme,
             &Default::default(),
             self.cursor,
         );
 
         self.cache = user_interface.into_cache();
         self.events.clear();
+        let redraw_request = match state {
+            user_interface::State::Updated { redraw_request, .. } => redraw_request,
+            user_interface::State::Outdated => RedrawRequest::NextFrame,
+        };
+        self.refresh.built(Instant::now(), redraw_request);
 
         self.renderer.present(
             None,
@@ -113,4 +185,42 @@ impl UIHelper {
 
         messages
     }
+
+    /// Present the UI last built by draw again, without building or laying it out
+    pub fn redraw(&mut self, graphics: &GraphicsHelper, target_view: &wgpu::TextureView) {
+        self.renderer.present(
+            None,
+            graphics.config.format,
+            target_view,
+            &self.viewport,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_are_rebuilt_at_a_capped_rate() {
+        let start = Instant::now();
+        let mut refresh = UiRefresh::default();
+        assert!(refresh.needs_rebuild(false, false, start));
+        refresh.built(start, RedrawRequest::Wait);
+
+        // nothing changed, so the last UI is presented again
+        let later = start + Duration::from_secs(1);
+        assert!(!refresh.needs_rebuild(false, false, later));
+
+        // changes wait for the refresh interval, input doesn't
+        let soon = start + Duration::from_millis(5);
+        assert!(!refresh.needs_rebuild(true, false, soon));
+        assert!(refresh.needs_rebuild(true, false, later));
+        assert!(refresh.needs_rebuild(false, true, soon));
+
+        // widgets can ask to be redrawn, eg. a blinking cursor
+        refresh.built(start, RedrawRequest::At(start + Duration::from_millis(500)));
+        assert!(!refresh.needs_rebuild(false, false, start + Duration::from_millis(100)));
+        assert!(refresh.needs_rebuild(false, false, later));
+    }
 }
@@ -0,0 +1,174 @@
+#[cfg(feature = "audio")]
+use std::{sync::{atomic::{AtomicU32, Ordering}, Arc}, time::{Duration, Instant}};
+
+use crate::engine::audio::{spatial::StereoGain, synth::AmbientSound};
+#[cfg(feature = "audio")]
+use crate::{core::math::random::Random, engine::audio::synth::{ambient_loop, engine_loop, thud, SAMPLE_RATE}};
+
+/// Sounds closer together than this are dropped, a bumpy landing makes dozens of contacts in a few steps
+#[cfg(feature = "audio")]
+const MIN_ONE_SHOT_GAP: Duration = Duration::from_millis(60);
+
+/// Fraction of the way an ambient loop's volume moves to where it was set each sample, so it doesn't click when it jumps
+#[cfg(feature = "audio")]
+const GAIN_EASING: f32 = 0.001;
+
+/// Plays the game's sounds. Built with the audio feature this uses rodio, without it or without an output device
+/// everything is silently ignored so the game plays the same.
+pub struct AudioManager {
+    #[cfg(feature = "audio")]
+    output: Option<AudioOutput>,
+    pub master_volume: f32, // 0 to 1, all of the below are scaled by this
+    pub effects_volume: f32, // collisions and other one shot sounds
+    pub engine_volume: f32,
+}
+
+#[cfg(feature = "audio")]
+struct AudioOutput {
+    _stream: rodio::OutputStream, // stops playing when dropped
+    handle: rodio::OutputStreamHandle,
+    engine: rodio::Sink, // the engine loop, always playing and turned up and down by set_engine
+    ambient: Vec<(AmbientSound, Arc<[AtomicU32; 2]>)>, // each loop's left and right volume as f32 bits, set by set_ambient
+    last_one_shot: Option<Instant>,
+    rng: rand_pcg::Pcg64, // for the noise in the one shot sounds
+}
+
+#[cfg(feature = "audio")]
+impl AudioOutput {
+    fn new() -> Result<Self, String> {
+        use rodio::Source;
+
+        let (stream, handle) = rodio::OutputStream::try_default().map_err(|e| e.to_string())?;
+        let engine = rodio::Sink::try_new(&handle).map_err(|e| e.to_string())?;
+        engine.set_volume(0.0);
+        engine.append(rodio::buffer::SamplesBuffer::new(1, SAMPLE_RATE, engine_loop()).repeat_infinite());
+        let mut rng = Random::seed_from_str("audio");
+        let ambient = AmbientSound::ALL.iter().map(|sound| {
+            let target = Arc::new([AtomicU32::new(0), AtomicU32::new(0)]);
+            let samples = ambient_loop(*sound, &mut rng);
+            handle.play_raw(PannedLoop { samples, idx: 0, right: false, target: target.clone(), gain: [0.0; 2] }).map_err(|e| e.to_string())?;
+            Ok((*sound, target))
+        }).collect::<Result<_, String>>()?;
+        Ok(Self { _stream: stream, handle, engine, ambient, last_one_shot: None, rng })
+    }
+
+    /// Whether a one shot sound played now is heard, or dropped for following the last one too closely
+    fn one_shot_due(&mut self) -> bool {
+        let now = Instant::now();
+        if self.last_one_shot.is_some_and(|last| now - last < MIN_ONE_SHOT_GAP) {
+            return false;
+        }
+        self.last_one_shot = Some(now);
+        true
+    }
+
+    fn play_one_shot(&self, samples: Vec<f32>, volume: f32) {
+        use rodio::Source;
+
+        let _ = self.handle.play_raw(rodio::buffer::SamplesBuffer::new(1, SAMPLE_RATE, samples).amplify(volume));
+    }
+}
+
+/// A mono loop played in stereo forever, with each side's volume set from the game thread while it plays
+#[cfg(feature = "audio")]
+struct PannedLoop {
+    samples: Vec<f32>,
+    idx: usize,
+    right: bool, // which side the next sample is for, they're interleaved left then right
+    target: Arc<[AtomicU32; 2]>,
+    gain: [f32; 2], // eased towards target
+}
+
+#[cfg(feature = "audio")]
+impl Iterator for PannedLoop {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let side = self.right as usize;
+        let target = f32::from_bits(self.target[side].load(Ordering::Relaxed));
+        self.gain[side] += (target - self.gain[side]) * GAIN_EASING;
+        let sample = self.samples[self.idx] * self.gain[side];
+        if self.right {
+            self.idx = (self.idx + 1) % self.samples.len();
+        }
+        self.right = !self.right;
+        Some(sample)
+    }
+}
+
+#[cfg(feature = "audio")]
+impl rodio::Source for PannedLoop {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        2
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl Default for AudioManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioManager {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(feature = "audio")]
+            output: AudioOutput::new().map_err(|e| println!("Sound unavailable: {}", e)).ok(),
+            master_volume: 1.0,
+            effects_volume: 1.0,
+            engine_volume: 1.0,
+        }
+    }
+
+    /// Something hit the car, strength from 0 to 1
+    #[cfg(not(feature = "audio"))]
+    pub fn play_collision(&mut self, _strength: f32) {}
+
+    #[cfg(feature = "audio")]
+    pub fn play_collision(&mut self, strength: f32) {
+        let volume = self.master_volume * self.effects_volume;
+        let Some(output) = self.output.as_mut().filter(|_| strength > 0.0 && volume > 0.0) else { return };
+        if !output.one_shot_due() {
+            return;
+        }
+        let samples = thud(strength, &mut output.rng);
+        output.play_one_shot(samples, volume);
+    }
+
+    /// Run the engine loop at pitch times its normal speed, loudness from 0 (off) to 1
+    #[cfg(not(feature = "audio"))]
+    pub fn set_engine(&mut self, _pitch: f32, _loudness: f32) {}
+
+    #[cfg(feature = "audio")]
+    pub fn set_engine(&mut self, pitch: f32, loudness: f32) {
+        let Some(output) = &self.output else { return };
+        output.engine.set_speed(pitch.max(0.01));
+        output.engine.set_volume(loudness.clamp(0.0, 1.0) * self.master_volume * self.engine_volume);
+    }
+
+    /// Turn an ambient loop up and down in each speaker for where the things making it are. Zero to stop hearing it.
+    #[cfg(not(feature = "audio"))]
+    pub fn set_ambient(&mut self, _sound: AmbientSound, _gain: StereoGain) {}
+
+    #[cfg(feature = "audio")]
+    pub fn set_ambient(&mut self, sound: AmbientSound, gain: StereoGain) {
+        let Some(output) = &self.output else { return };
+        let gain = gain.scale(self.master_volume * self.effects_volume);
+        if let Some((_, target)) = output.ambient.iter().find(|(s, _)| *s == sound) {
+            target[0].store(gain.left.to_bits(), Ordering::Relaxed);
+            target[1].store(gain.right.to_bits(), Ordering::Relaxed);
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub mod audio_manager;
+pub mod synth;
+pub mod spatial;
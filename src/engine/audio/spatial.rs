@@ -0,0 +1,93 @@
+use crate::core::math::{aabb2d::Aabb2d, vec2::Vec2};
+
+/// Sounds are at full volume this close to the listener, then fade out
+const FULL_VOLUME_DISTANCE: f32 = 4.0;
+/// and can't be heard at all past this
+const SILENT_DISTANCE: f32 = 30.0;
+
+/// Where sounds in the world are heard from, the middle of the screen. Something at the screen's edge is panned hard to that side.
+#[derive(Debug, Clone, Copy)]
+pub struct Listener {
+    pub pos: Vec2,
+    pub half_width: f32, // world units from the middle of the screen to its left or right edge
+}
+
+impl Listener {
+    pub fn new(pos: Vec2, half_width: f32) -> Self {
+        Self { pos, half_width: half_width.max(f32::EPSILON) }
+    }
+
+    /// How loud something at the given distance is, 1 close up to 0 out of earshot
+    pub fn attenuation(distance: f32) -> f32 {
+        ((SILENT_DISTANCE - distance) / (SILENT_DISTANCE - FULL_VOLUME_DISTANCE)).clamp(0.0, 1.0)
+    }
+
+    /// Left and right volume for a sound filling area at the given loudness from 0 to 1.
+    /// It's heard from the nearest point of the area, so standing in a wide waterfall's spray is as loud as it gets.
+    pub fn hear(&self, area: &Aabb2d, loudness: f32) -> StereoGain {
+        let pos = area.closest_point(self.pos);
+        let volume = loudness.clamp(0.0, 1.0) * Self::attenuation((pos - self.pos).magnitude());
+        let pan = ((pos.x - self.pos.x) / self.half_width).clamp(-1.0, 1.0);
+        StereoGain::panned(volume, pan)
+    }
+}
+
+/// Volume of each speaker, 0 to 1
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StereoGain {
+    pub left: f32,
+    pub right: f32,
+}
+
+impl StereoGain {
+    /// Equal power panning, so a sound crossing the screen doesn't dip in the middle. pan from -1 (left) to 1 (right)
+    pub fn panned(volume: f32, pan: f32) -> Self {
+        let angle = (pan.clamp(-1.0, 1.0) + 1.0) * std::f32::consts::FRAC_PI_4;
+        Self { left: volume * angle.cos(), right: volume * angle.sin() }
+    }
+
+    /// Both speakers together, capped so a dozen emitters of one sound don't get any louder than a close one
+    pub fn combine(self, other: StereoGain) -> Self {
+        Self { left: (self.left + other.left).min(1.0), right: (self.right + other.right).min(1.0) }
+    }
+
+    pub fn scale(self, volume: f32) -> Self {
+        Self { left: self.left * volume, right: self.right * volume }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sounds_fade_and_pan_with_position() {
+        let listener = Listener::new(Vec2::new(10.0, 0.0), 8.0);
+        let point = |x: f32, y: f32| Aabb2d::new(Vec2::new(x, y), Vec2::zero());
+
+        // right in front it's full volume in both ears, and no louder than one ear would be on its own
+        let centre = listener.hear(&point(10.0, 2.0), 1.0);
+        assert!((centre.left - centre.right).abs() < 1e-6);
+        assert!((centre.left * centre.left + centre.right * centre.right - 1.0).abs() < 1e-5);
+
+        // off to the right it's panned right, past the screen's edge only the right speaker plays
+        let right = listener.hear(&point(14.0, 0.0), 1.0);
+        assert!(right.right > right.left);
+        let edge = listener.hear(&point(19.0, 0.0), 1.0);
+        assert!(edge.left < 1e-6 && edge.right > 0.0);
+        let left = listener.hear(&point(4.0, 0.0), 1.0);
+        assert!(left.left > left.right);
+
+        // and further away it's quieter until it can't be heard
+        let near = listener.hear(&point(10.0, 10.0), 1.0);
+        let far = listener.hear(&point(10.0, 20.0), 1.0);
+        assert!(far.left < near.left && far.left > 0.0);
+        assert_eq!(listener.hear(&point(10.0, 40.0), 1.0), StereoGain::default());
+
+        // inside an area it's heard from where the listener is
+        let area = Aabb2d::new(Vec2::new(0.0, 0.0), Vec2::new(20.0, 5.0));
+        assert_eq!(listener.hear(&area, 0.5), StereoGain::panned(0.5, 0.0));
+
+        assert_eq!(StereoGain::panned(1.0, 1.0).combine(StereoGain::panned(1.0, 1.0)).right, 1.0);
+    }
+}
@@ -0,0 +1,137 @@
+use rand::Rng;
+
+/// Every sound is made at this rate, mono
+pub const SAMPLE_RATE: u32 = 44100;
+
+/// The engine loop's pitch when played at normal speed. A whole number of cycles fit in the loop so it repeats without a click.
+pub const ENGINE_HZ: f32 = 50.0;
+const ENGINE_HARMONICS: usize = 8;
+
+const MIN_THUD_SECONDS: f32 = 0.04;
+const MAX_THUD_SECONDS: f32 = 0.2;
+const THUD_DECAY_RATE: f32 = 30.0; // per second
+
+const AMBIENT_SECONDS: f32 = 2.0; // length of each ambient loop
+const CROSSFADE_SECONDS: f32 = 0.1; // of the loop's end into its start, so noise repeats without a click
+const MACHINERY_HZ: f32 = 60.0; // a whole number of cycles in AMBIENT_SECONDS
+
+/// The sounds that come from somewhere in the world and loop while they're in earshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AmbientSound {
+    Water, // rivers and falls, a steady bright hiss
+    Wind, // low rushing that swells and dies back
+    Machinery, // lifts, teleporters and the like, a hum with a rattle on top
+}
+
+impl AmbientSound {
+    pub const ALL: [AmbientSound; 3] = [AmbientSound::Water, AmbientSound::Wind, AmbientSound::Machinery];
+}
+
+/// One cycle of a buzzy engine note at ENGINE_HZ, louder low harmonics with every other one knocked back
+/// so it sounds more like firing cylinders than a plain tone. Loop it and change the playback speed for the revs.
+pub fn engine_loop() -> Vec<f32> {
+    let len = (SAMPLE_RATE as f32 / ENGINE_HZ).round() as usize;
+    let mut samples: Vec<f32> = (0..len).map(|i| {
+        let phase = i as f32 / len as f32 * std::f32::consts::TAU;
+        (1..=ENGINE_HARMONICS).map(|n| {
+            let amplitude = if n % 2 == 0 { 0.5 } else { 1.0 } / n as f32;
+            (phase * n as f32).sin() * amplitude
+        }).sum()
+    }).collect();
+    normalize(&mut samples, 0.8);
+    samples
+}
+
+/// A dull knock for something hitting the car, strength from 0 to 1 makes it longer and louder.
+/// Noise with the top taken off, dying away quickly.
+pub fn thud(strength: f32, rng: &mut impl Rng) -> Vec<f32> {
+    let strength = strength.clamp(0.0, 1.0);
+    let seconds = MIN_THUD_SECONDS + (MAX_THUD_SECONDS - MIN_THUD_SECONDS) * strength;
+    let len = (seconds * SAMPLE_RATE as f32) as usize;
+    let mut samples: Vec<f32> = noise(len, 0.15, rng).into_iter().enumerate().map(|(i, s)| {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        s * (-THUD_DECAY_RATE * t).exp()
+    }).collect();
+    normalize(&mut samples, strength);
+    samples
+}
+
+/// A loop of the sound, AMBIENT_SECONDS long. Play it panned and turned up and down for where it's coming from.
+pub fn ambient_loop(sound: AmbientSound, rng: &mut impl Rng) -> Vec<f32> {
+    let len = (AMBIENT_SECONDS * SAMPLE_RATE as f32) as usize;
+    let fade = (CROSSFADE_SECONDS * SAMPLE_RATE as f32) as usize;
+    let mut samples = match sound {
+        AmbientSound::Water => noise(len + fade, 0.5, rng),
+        AmbientSound::Wind => {
+            let mut samples = noise(len + fade, 0.04, rng);
+            // one swell per loop, a whole cycle so it carries on round
+            for (i, s) in samples.iter_mut().enumerate() {
+                let phase = i as f32 / len as f32 * std::f32::consts::TAU;
+                *s *= 0.65 - 0.35 * phase.cos();
+            }
+            samples
+        }
+        AmbientSound::Machinery => {
+            let rattle = noise(len + fade, 0.3, rng);
+            rattle.iter().enumerate().map(|(i, r)| {
+                let phase = i as f32 / SAMPLE_RATE as f32 * MACHINERY_HZ * std::f32::consts::TAU;
+                phase.sin() + 0.5 * (phase * 2.0).sin() + 0.3 * r
+            }).collect()
+        }
+    };
+    // the start fades in what came after the end, so the end runs straight on into it
+    for i in 0..fade {
+        let t = i as f32 / fade as f32;
+        samples[i] = samples[i] * t + samples[len + i] * (1.0 - t);
+    }
+    samples.truncate(len);
+    normalize(&mut samples, 0.6);
+    samples
+}
+
+/// Noise with the top taken off by a one pole low pass, smoothing from 0 to 1 where lower is duller
+fn noise(len: usize, smoothing: f32, rng: &mut impl Rng) -> Vec<f32> {
+    let mut filtered = 0.0;
+    (0..len).map(|_| {
+        filtered += (rng.random_range(-1.0..1.0) - filtered) * smoothing;
+        filtered
+    }).collect()
+}
+
+/// Scale so the loudest sample is peak
+fn normalize(samples: &mut [f32], peak: f32) {
+    let max = samples.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+    if max > 0.0 {
+        for s in samples.iter_mut() {
+            *s *= peak / max;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::math::random::Random;
+
+    #[test]
+    fn test_sounds() {
+        let engine = engine_loop();
+        assert_eq!(engine.len(), 882);
+        assert!((engine[0] - engine[engine.len() - 1]).abs() < 0.05); // loops without a jump
+        assert!(engine.iter().all(|s| s.abs() <= 0.8 + f32::EPSILON));
+
+        let mut rng = Random::seed_from_str("thud");
+        let soft = thud(0.0, &mut rng);
+        let hard = thud(1.0, &mut rng);
+        assert!(hard.len() > soft.len() * 4);
+        assert!((hard.iter().fold(0.0f32, |max, s| max.max(s.abs())) - 1.0).abs() < 1e-5);
+        assert!(soft.iter().all(|s| *s == 0.0));
+
+        for sound in AmbientSound::ALL {
+            let samples = ambient_loop(sound, &mut rng);
+            assert_eq!(samples.len(), 88200);
+            assert!((samples.iter().fold(0.0f32, |max, s| max.max(s.abs())) - 0.6).abs() < 1e-5);
+            assert!((samples[0] - samples[samples.len() - 1]).abs() < 0.1, "{:?} jumps where it loops", sound);
+        }
+    }
+}
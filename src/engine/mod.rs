@@ -1,2 +1,4 @@
+#[cfg(feature = "client")]
 pub mod renderer;
 pub mod app;
+pub mod audio;
@@ -0,0 +1,106 @@
+/// Steps the render scale moves in, so the targets aren't recreated for every small wobble in frame time
+pub const SCALE_STEP: f32 = 0.125;
+
+const SMOOTHING: f32 = 0.1; // weight of each new frame in the smoothed frame time
+const OVER_FRAMES: u32 = 20; // frames over budget in a row before dropping the scale
+const UNDER_FRAMES: u32 = 180; // frames with headroom in a row before raising it again, slower so it doesn't flicker
+const HEADROOM: f32 = 0.7; // fraction of the budget frames have to come in under to count as headroom
+
+/// Lowers the resolution the scene is rendered at when frames take longer than the budget, and raises it again once
+/// there's headroom. Only the render is scaled, the simulation keeps stepping at its fixed rate either way.
+#[derive(Debug, Clone)]
+pub struct DynamicResolution {
+    pub min_scale: f32,
+    pub max_scale: f32,
+    pub budget_ms: f32,
+    scale: f32,
+    smoothed_ms: Option<f32>,
+    over: u32, // frames in a row over budget
+    under: u32, // frames in a row with headroom
+}
+
+impl DynamicResolution {
+    pub fn new(min_scale: f32, max_scale: f32, budget_ms: f32) -> Self {
+        let max_scale = max_scale.clamp(SCALE_STEP, 1.0);
+        Self {
+            min_scale: min_scale.clamp(SCALE_STEP, max_scale),
+            max_scale,
+            budget_ms,
+            scale: max_scale,
+            smoothed_ms: None,
+            over: 0,
+            under: 0,
+        }
+    }
+
+    /// Fraction of the window's resolution to render at
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Feed in how long the last frame's work took. True if the scale changed and the targets need resizing.
+    pub fn update(&mut self, frame_ms: f32) -> bool {
+        let smoothed_ms = match self.smoothed_ms {
+            Some(smoothed_ms) => smoothed_ms + (frame_ms - smoothed_ms) * SMOOTHING,
+            None => frame_ms,
+        };
+        self.smoothed_ms = Some(smoothed_ms);
+
+        self.over = if smoothed_ms > self.budget_ms { self.over + 1 } else { 0 };
+        self.under = if smoothed_ms < self.budget_ms * HEADROOM { self.under + 1 } else { 0 };
+
+        let scale = if self.over >= OVER_FRAMES {
+            (self.scale - SCALE_STEP).max(self.min_scale)
+        } else if self.under >= UNDER_FRAMES {
+            (self.scale + SCALE_STEP).min(self.max_scale)
+        } else {
+            return false;
+        };
+        // start counting again at the new scale, the smoothed time will take a while to catch up
+        self.over = 0;
+        self.under = 0;
+        self.smoothed_ms = None;
+        let changed = scale != self.scale;
+        self.scale = scale;
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale_follows_frame_time() {
+        let mut resolution = DynamicResolution::new(0.5, 1.0, 16.0);
+        assert_eq!(resolution.scale(), 1.0);
+
+        // a spike doesn't change anything
+        assert!(!resolution.update(40.0));
+        for _ in 0..5 {
+            resolution.update(10.0);
+        }
+        assert_eq!(resolution.scale(), 1.0);
+
+        // a sustained overload steps down to the minimum and no further
+        let changes = (0..OVER_FRAMES * 10).filter(|_| resolution.update(25.0)).count();
+        assert_eq!(changes, 4);
+        assert_eq!(resolution.scale(), 0.5);
+
+        // within budget but without headroom it stays put
+        assert!((0..UNDER_FRAMES * 2).all(|_| !resolution.update(14.0)));
+
+        // and with headroom it comes back up, more slowly, once the smoothed time has dropped under the headroom
+        let frames = (0..).position(|_| resolution.update(8.0)).unwrap() + 1;
+        assert!(frames > UNDER_FRAMES as usize && frames < UNDER_FRAMES as usize * 2);
+        assert_eq!(resolution.scale(), 0.5 + SCALE_STEP);
+    }
+
+    #[test]
+    fn test_bounds_are_kept_sane() {
+        let resolution = DynamicResolution::new(0.0, 2.0, 16.0);
+        assert_eq!((resolution.min_scale, resolution.max_scale), (SCALE_STEP, 1.0));
+        let resolution = DynamicResolution::new(0.9, 0.75, 16.0);
+        assert_eq!((resolution.min_scale, resolution.max_scale, resolution.scale()), (0.75, 0.75, 0.75));
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use crate::core::math::vec2::Vec2;
+
+pub const GLYPH_WIDTH: u32 = 5;
+pub const GLYPH_HEIGHT: u32 = 7;
+const CELL_WIDTH: u32 = GLYPH_WIDTH + 2; // a pixel of padding all round so neighbouring glyphs don't bleed in when filtered
+const CELL_HEIGHT: u32 = GLYPH_HEIGHT + 2;
+const COLUMNS: u32 = 8;
+
+// 5x7 bitmap font, one byte per row from the top with the leftmost pixel in bit 4.
+// Capitals only, text is drawn upper case.
+const FONT: &[(char, [u8; 7])] = &[
+    (' ', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]),
+    ('0', [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E]),
+    ('1', [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('2', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F]),
+    ('3', [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E]),
+    ('4', [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02]),
+    ('5', [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E]),
+    ('6', [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E]),
+    ('7', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E]),
+    ('9', [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C]),
+    ('A', [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('B', [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E]),
+    ('C', [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E]),
+    ('D', [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C]),
+    ('E', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F]),
+    ('F', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10]),
+    ('G', [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F]),
+    ('H', [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('I', [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('J', [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C]),
+    ('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
+    ('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F]),
+    ('M', [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11]),
+    ('N', [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+    ('O', [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('P', [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10]),
+    ('Q', [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D]),
+    ('R', [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11]),
+    ('S', [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E]),
+    ('T', [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04]),
+    ('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A]),
+    ('X', [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11]),
+    ('Y', [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04]),
+    ('Z', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F]),
+    ('.', [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C]),
+    (',', [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08]),
+    ('-', [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00]),
+    ('_', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F]),
+    ('+', [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00]),
+    (':', [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00]),
+    ('!', [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04]),
+    ('?', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04]),
+    ('/', [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00]),
+    ('#', [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A]),
+    ('\'', [0x04, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00]),
+    ('(', [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02]),
+    (')', [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08]),
+];
+
+/// The bitmap for a character, upper cased, one byte per row as in FONT. Characters without a glyph are drawn as '?'
+pub fn glyph(c: char) -> [u8; 7] {
+    let c = c.to_ascii_uppercase();
+    FONT.iter().find(|(f, _)| *f == c).or_else(|| FONT.iter().find(|(f, _)| *f == '?')).map(|(_, bitmap)| *bitmap).unwrap_or_default()
+}
+
+/// The built in font drawn into a texture, with where each glyph ended up
+pub struct GlyphAtlas {
+    pub image: image::RgbaImage,
+    uv_rects: HashMap<char, [f32; 4]>, // min u, min v, max u, max v
+}
+
+impl GlyphAtlas {
+    pub fn new() -> Self {
+        let rows = (FONT.len() as u32).div_ceil(COLUMNS);
+        let (width, height) = (COLUMNS * CELL_WIDTH, rows * CELL_HEIGHT);
+        let mut image = image::RgbaImage::new(width, height);
+        let mut uv_rects = HashMap::new();
+
+        for (i, (c, bitmap)) in FONT.iter().enumerate() {
+            let x0 = (i as u32 % COLUMNS) * CELL_WIDTH + 1;
+            let y0 = (i as u32 / COLUMNS) * CELL_HEIGHT + 1;
+            for (y, row) in bitmap.iter().enumerate() {
+                for x in 0..GLYPH_WIDTH {
+                    if row & (1 << (GLYPH_WIDTH - 1 - x)) != 0 {
+                        image.put_pixel(x0 + x, y0 + y as u32, image::Rgba([255, 255, 255, 255]));
+                    }
+                }
+            }
+            uv_rects.insert(*c, [
+                x0 as f32 / width as f32,
+                y0 as f32 / height as f32,
+                (x0 + GLYPH_WIDTH) as f32 / width as f32,
+                (y0 + GLYPH_HEIGHT) as f32 / height as f32,
+            ]);
+        }
+
+        Self { image, uv_rects }
+    }
+
+    /// Characters without a glyph are drawn as '?'
+    pub fn uv_rect(&self, c: char) -> [f32; 4] {
+        let c = c.to_ascii_uppercase();
+        self.uv_rects.get(&c).or_else(|| self.uv_rects.get(&'?')).copied().unwrap_or_default()
+    }
+}
+
+impl Default for GlyphAtlas {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Width of a line of text in world units, where size is the glyph height
+pub fn text_width(text: &str, size: f32) -> f32 {
+    let num_chars = text.chars().count() as f32;
+    if num_chars == 0.0 {
+        return 0.0;
+    }
+    let pixel = size / GLYPH_HEIGHT as f32;
+    (num_chars * (GLYPH_WIDTH + 1) as f32 - 1.0) * pixel
+}
+
+/// Centre of each glyph for a line of text centred on position
+pub fn layout_text(text: &str, position: Vec2, size: f32) -> impl Iterator<Item = (char, Vec2)> + '_ {
+    let pixel = size / GLYPH_HEIGHT as f32;
+    let advance = (GLYPH_WIDTH + 1) as f32 * pixel;
+    let first_x = position.x - text_width(text, size) * 0.5 + GLYPH_WIDTH as f32 * pixel * 0.5;
+    text.chars()
+        .enumerate()
+        .filter(|(_, c)| *c != ' ')
+        .map(move |(i, c)| (c, Vec2::new(first_x + i as f32 * advance, position.y)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_glyph_is_drawn() {
+        let atlas = GlyphAtlas::new();
+        for (c, _) in FONT.iter().filter(|(c, _)| *c != ' ') {
+            let [u0, v0, u1, v1] = atlas.uv_rect(*c);
+            let (width, height) = atlas.image.dimensions();
+            let (x0, y0) = ((u0 * width as f32).round() as u32, (v0 * height as f32).round() as u32);
+            let (x1, y1) = ((u1 * width as f32).round() as u32, (v1 * height as f32).round() as u32);
+            let lit = (x0..x1).flat_map(|x| (y0..y1).map(move |y| (x, y))).any(|(x, y)| atlas.image.get_pixel(x, y)[3] > 0);
+            assert!(lit, "glyph '{}' is empty", c);
+        }
+        assert_eq!(atlas.uv_rect('a'), atlas.uv_rect('A'));
+        assert_eq!(atlas.uv_rect('~'), atlas.uv_rect('?'));
+        assert_eq!(glyph('a'), glyph('A'));
+        assert_eq!(glyph('~'), glyph('?'));
+    }
+
+    #[test]
+    fn test_layout_text() {
+        let glyphs: Vec<(char, Vec2)> = layout_text("A B", Vec2::new(10.0, 2.0), 7.0).collect();
+        // 17 pixels wide, glyphs are 5 wide with 1 between
+        assert_eq!(text_width("A B", 7.0), 17.0);
+        assert_eq!(glyphs, vec![('A', Vec2::new(4.0, 2.0)), ('B', Vec2::new(16.0, 2.0))]);
+        assert_eq!(layout_text("", Vec2::new(0.0, 0.0), 1.0).count(), 0);
+    }
+}
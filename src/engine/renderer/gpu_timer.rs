@@ -0,0 +1,148 @@
+use std::sync::{Arc, OnceLock};
+
+/// Timestamp queries for the passes of one frame, with the buffers to read them back through
+struct Queries {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+}
+
+/// Measures how long each render pass takes on the GPU with timestamp queries, as the CPU render time only covers
+/// encoding and submitting. Timings arrive a frame or so late, once the GPU is done and the readback buffer maps.
+/// Does nothing on adapters without timestamp queries.
+pub struct GpuTimer {
+    queries: Option<Queries>,
+    period: f32, // nanoseconds per timestamp tick
+    pass_names: Vec<&'static str>, // passes timed this frame, in query order
+    pending: Option<(Vec<&'static str>, Arc<OnceLock<bool>>)>, // passes in the readback buffer, and whether it mapped once it's done
+    pub timings: Vec<(&'static str, f32)>, // latest GPU time of each pass in ms
+}
+
+impl GpuTimer {
+    pub const MAX_PASSES: u32 = 4;
+
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let queries = device.features().contains(wgpu::Features::TIMESTAMP_QUERY).then(|| {
+            let size = (Self::MAX_PASSES * 2) as wgpu::BufferAddress * wgpu::QUERY_SIZE as wgpu::BufferAddress;
+            Queries {
+                query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                    label: Some("GPU Timer Queries"),
+                    ty: wgpu::QueryType::Timestamp,
+                    count: Self::MAX_PASSES * 2,
+                }),
+                resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("GPU Timer Resolve Buffer"),
+                    size,
+                    usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                    mapped_at_creation: false,
+                }),
+                readback_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("GPU Timer Readback Buffer"),
+                    size,
+                    usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }),
+            }
+        });
+
+        Self {
+            queries,
+            period: queue.get_timestamp_period(),
+            pass_names: vec![],
+            pending: None,
+            timings: vec![],
+        }
+    }
+
+    /// Timestamp writes for a render pass. None when unsupported, or when the last frame's timings are still being read
+    /// back, in which case this frame just isn't timed.
+    pub fn timestamp_writes(&mut self, pass_name: &'static str) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        let queries = self.queries.as_ref()?;
+        if self.pending.is_some() || self.pass_names.len() >= Self::MAX_PASSES as usize {
+            return None;
+        }
+
+        let index = self.pass_names.len() as u32 * 2;
+        self.pass_names.push(pass_name);
+        Some(wgpu::RenderPassTimestampWrites {
+            query_set: &queries.query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1),
+        })
+    }
+
+    /// Copy this frame's timestamps somewhere they can be read, after the last timed pass
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        let Some(queries) = &self.queries else { return };
+        if self.pass_names.is_empty() {
+            return;
+        }
+
+        let count = self.pass_names.len() as u32 * 2;
+        encoder.resolve_query_set(&queries.query_set, 0..count, &queries.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(&queries.resolve_buffer, 0, &queries.readback_buffer, 0, count as wgpu::BufferAddress * wgpu::QUERY_SIZE as wgpu::BufferAddress);
+    }
+
+    /// Start reading back this frame's timestamps, once the encoder with the resolve has been submitted
+    pub fn after_submit(&mut self) {
+        let Some(queries) = &self.queries else { return };
+        if self.pass_names.is_empty() {
+            return;
+        }
+
+        let mapped = Arc::new(OnceLock::new());
+        let mapped_in_callback = mapped.clone();
+        let size = self.pass_names.len() as wgpu::BufferAddress * 2 * wgpu::QUERY_SIZE as wgpu::BufferAddress;
+        queries.readback_buffer.map_async(wgpu::MapMode::Read, 0..size, move |result| {
+            let _ = mapped_in_callback.set(result.is_ok());
+        });
+        self.pending = Some((std::mem::take(&mut self.pass_names), mapped));
+    }
+
+    /// Pick up timings that have finished reading back, without waiting for the GPU
+    pub fn poll(&mut self, device: &wgpu::Device) {
+        let Some(queries) = &self.queries else { return };
+        let Some((pass_names, mapped)) = &self.pending else { return };
+
+        let _ = device.poll(wgpu::PollType::Poll);
+        match mapped.get() {
+            None => return,
+            // try again next frame
+            Some(false) => {
+                self.pending = None;
+                return;
+            }
+            Some(true) => {}
+        }
+
+        let size = pass_names.len() as wgpu::BufferAddress * 2 * wgpu::QUERY_SIZE as wgpu::BufferAddress;
+        {
+            let data = queries.readback_buffer.slice(0..size).get_mapped_range();
+            let timestamps: &[u64] = bytemuck::cast_slice(&data);
+            self.timings = pass_timings(pass_names, timestamps, self.period);
+        }
+        queries.readback_buffer.unmap();
+        self.pending = None;
+    }
+}
+
+/// Milliseconds between each pass's begin and end timestamps
+fn pass_timings(pass_names: &[&'static str], timestamps: &[u64], period: f32) -> Vec<(&'static str, f32)> {
+    pass_names.iter().zip(timestamps.chunks_exact(2))
+        .map(|(name, pair)| (*name, pair[1].saturating_sub(pair[0]) as f32 * period / 1_000_000.0))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pass_timings() {
+        let timings = pass_timings(&["ghost camera", "scene"], &[1000, 501_000, 600_000, 2_600_000], 2.0);
+        assert_eq!(timings, vec![("ghost camera", 1.0), ("scene", 4.0)]);
+
+        // a timestamp that went backwards, eg. the GPU clock reset, reads as zero rather than wrapping
+        assert_eq!(pass_timings(&["scene"], &[2000, 1000], 1.0), vec![("scene", 0.0)]);
+    }
+}
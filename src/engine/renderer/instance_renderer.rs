@@ -60,7 +60,7 @@ pub const QUAD_INDICES: &[u16] = &[3, 1, 0, 3, 2, 1];
 #[derive(Debug, Copy, Clone)]
 pub struct Instance {
     pub position: cgmath::Vector3<f32>,
-    pub rotation: cgmath::Quaternion<f32>,
+    pub angle: f32, // radians around z, particles are circles so this only turns the texture
     pub colour: Vec4,
     pub radius: f32,
 }
@@ -68,21 +68,22 @@ pub struct Instance {
 impl Instance {
     fn to_raw(&self) -> InstanceRaw {
         InstanceRaw {
-            model: (cgmath::Matrix4::from_translation(self.position)
-                * cgmath::Matrix4::from(self.rotation))
-            .into(),
-            colour: self.colour.0.into(),
+            position: self.position.into(),
+            angle: self.angle,
+            colour: self.colour.to_rgba8(),
             radius: self.radius,
         }
     }
 }
 
+/// What gets uploaded per particle every frame, kept small as there can be tens of thousands of them.
+/// 24 bytes, rather than 84 for a full model matrix and a float colour.
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct InstanceRaw {
-    #[allow(dead_code)]
-    model: [[f32; 4]; 4],
-    colour: [f32; 4],
+    position: [f32; 3],
+    angle: f32,
+    colour: u32, // rgba8, unpacked in the shader
     radius: f32,
 }
 
@@ -96,38 +97,20 @@ impl InstanceRaw {
             // instance when the shader starts processing a new instance
             step_mode: wgpu::VertexStepMode::Instance,
             attributes: &[
-                wgpu::VertexAttribute {
+                wgpu::VertexAttribute { // position and angle
                     offset: 0,
-                    // While our vertex shader only uses locations 0, and 1 now, in later tutorials we'll
-                    // be using 2, 3, and 4, for Vertex. We'll start at slot 5 not conflict with them later
+                    // slots from 5, out of the way of the Vertex slots
                     shader_location: 5,
                     format: wgpu::VertexFormat::Float32x4,
                 },
-                // A mat4 takes up 4 vertex slots as it is technically 4 vec4s. We need to define a slot
-                // for each vec4. We don't have to do this in code though.
-                wgpu::VertexAttribute {
+                wgpu::VertexAttribute { // Colour
                     offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
                     shader_location: 6,
-                    format: wgpu::VertexFormat::Float32x4,
-                },
-                wgpu::VertexAttribute {
-                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
-                    shader_location: 7,
-                    format: wgpu::VertexFormat::Float32x4,
-                },
-                wgpu::VertexAttribute {
-                    offset: mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
-                    shader_location: 8,
-                    format: wgpu::VertexFormat::Float32x4,
-                },
-                wgpu::VertexAttribute { // Colour
-                    offset: mem::size_of::<[f32; 16]>() as wgpu::BufferAddress,
-                    shader_location: 9,
-                    format: wgpu::VertexFormat::Float32x4,
+                    format: wgpu::VertexFormat::Uint32,
                 },
                 wgpu::VertexAttribute { // radius
-                    offset: mem::size_of::<[f32; 20]>() as wgpu::BufferAddress,
-                    shader_location: 10,
+                    offset: mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 7,
                     format: wgpu::VertexFormat::Float32,
                 },
             ],
@@ -175,6 +158,7 @@ pub struct InstanceRenderer {
     num_instances: usize,
     #[allow(dead_code)]
     instance_buffer: wgpu::Buffer,
+    instance_data: Vec<InstanceRaw>, // reused each update so uploading doesn't allocate every frame
 
     //pub camera_uniform: CameraUniform,
     //pub camera_buffer: wgpu::Buffer,
@@ -357,6 +341,7 @@ impl InstanceRenderer {
             
             num_instances: instances.len(),
             instance_buffer,
+            instance_data,
 
             //camera_uniform,
             //camera_buffer,
@@ -364,20 +349,23 @@ impl InstanceRenderer {
         }
     }
 
-    pub fn update_instances(&mut self, instances: &Vec<Instance>, queue: &wgpu::Queue, device: &wgpu::Device) {
-        let instance_data = instances.iter().map(Instance::to_raw).collect::<Vec<_>>();
+    pub fn update_instances(&mut self, instances: &[Instance], queue: &wgpu::Queue, device: &wgpu::Device) {
+        // the scratch buffer keeps its capacity between frames
+        let instance_data = &mut self.instance_data;
+        instance_data.clear();
+        instance_data.extend(instances.iter().map(Instance::to_raw));
         
         let instance_data_size = instance_data.len() * std::mem::size_of::<InstanceRaw>();
         let instance_buffer_size = self.instance_buffer.size() as usize;
 
         if instance_data_size <= instance_buffer_size {
             // Write directly to the existing buffer
-            queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instance_data));
+            queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(instance_data));
         } else {
             // We need a new instance buffer
             self.instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Instance Buffer"),
-                contents: bytemuck::cast_slice(&instance_data),
+                contents: bytemuck::cast_slice(instance_data),
                 usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
             });
         }
@@ -406,4 +394,20 @@ impl InstanceRenderer {
         render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
         render_pass.draw_indexed(0..self.num_indices, 0, 0..self.num_instances as u32);
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instance_packing() {
+        assert_eq!(std::mem::size_of::<InstanceRaw>(), 24);
+
+        let instance = Instance { position: cgmath::Vector3::new(1.0, 2.0, 0.0), angle: 0.5, colour: Vec4::new(1.0, 0.0, 0.5, 1.0), radius: 0.1 };
+        let raw = instance.to_raw();
+        assert_eq!(raw.position, [1.0, 2.0, 0.0]);
+        assert_eq!(raw.colour, 0xff80_00ff);
+        assert_eq!(Vec4::new(2.0, -1.0, 0.0, 0.0).to_rgba8(), 0x0000_00ff);
+    }
+}
@@ -3,3 +3,10 @@ pub mod model;
 pub mod shader;
 pub mod texture;
 pub mod sort;
+pub mod glyph_atlas;
+pub mod text_renderer;
+pub mod pipeline_cache;
+pub mod gpu_timer;
+pub mod render_targets;
+pub mod dynamic_resolution;
+pub mod upscale;
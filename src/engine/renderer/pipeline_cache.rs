@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use crate::engine::renderer::shader::BindGroupType;
+
+/// A vertex buffer layout in a form that can be hashed
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VertexLayoutKey {
+    pub array_stride: wgpu::BufferAddress,
+    pub step_mode: wgpu::VertexStepMode,
+    pub attributes: Vec<wgpu::VertexAttribute>,
+}
+
+impl From<&wgpu::VertexBufferLayout<'_>> for VertexLayoutKey {
+    fn from(layout: &wgpu::VertexBufferLayout<'_>) -> Self {
+        Self {
+            array_stride: layout.array_stride,
+            step_mode: layout.step_mode,
+            attributes: layout.attributes.to_vec(),
+        }
+    }
+}
+
+/// Everything that makes one render pipeline different from another.
+/// Bind groups are listed by type in group index order, the actual buffers and textures don't matter to the pipeline.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    pub shader: String,
+    pub bind_groups: Vec<BindGroupType>,
+    pub vertex_layouts: Vec<VertexLayoutKey>,
+    pub format: wgpu::TextureFormat,
+    pub blend: wgpu::BlendState,
+}
+
+impl PipelineKey {
+    pub fn new(shader: &str, bind_groups: Vec<BindGroupType>, buffers: &[wgpu::VertexBufferLayout], format: wgpu::TextureFormat, blend: wgpu::BlendState) -> Self {
+        Self {
+            shader: shader.to_owned(),
+            bind_groups,
+            vertex_layouts: buffers.iter().map(VertexLayoutKey::from).collect(),
+            format,
+            blend,
+        }
+    }
+}
+
+/// Render pipelines that have already been built, so a pass that turns up part way through a run
+/// (eg. the ghost camera) reuses one that was compiled while loading instead of hitching on first use.
+#[derive(Default)]
+pub struct PipelineCache {
+    pipelines: HashMap<PipelineKey, wgpu::RenderPipeline>,
+}
+
+impl PipelineCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_or_insert_with(&mut self, key: PipelineKey, create: impl FnOnce() -> wgpu::RenderPipeline) -> wgpu::RenderPipeline {
+        self.pipelines.entry(key).or_insert_with(create).clone()
+    }
+
+    pub fn contains(&self, key: &PipelineKey) -> bool {
+        self.pipelines.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pipelines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pipelines.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::renderer::instance_renderer::{InstanceRaw, Vertex};
+
+    #[test]
+    fn test_keys_match_only_the_same_permutation() {
+        let format = wgpu::TextureFormat::Bgra8UnormSrgb;
+        let key = |shader: &str, blend| PipelineKey::new(shader, vec![BindGroupType::Diffuse, BindGroupType::Camera], &[Vertex::desc(), InstanceRaw::desc()], format, blend);
+
+        assert_eq!(key("particle_shader.wgsl", wgpu::BlendState::REPLACE), key("particle_shader.wgsl", wgpu::BlendState::REPLACE));
+        assert_ne!(key("particle_shader.wgsl", wgpu::BlendState::REPLACE), key("line_shader.wgsl", wgpu::BlendState::REPLACE));
+        assert_ne!(key("particle_shader.wgsl", wgpu::BlendState::REPLACE), key("particle_shader.wgsl", wgpu::BlendState::ALPHA_BLENDING));
+
+        let mut keys = std::collections::HashSet::new();
+        keys.insert(key("particle_shader.wgsl", wgpu::BlendState::REPLACE));
+        assert!(keys.contains(&key("particle_shader.wgsl", wgpu::BlendState::REPLACE)));
+        assert!(!keys.contains(&PipelineKey::new("particle_shader.wgsl", vec![BindGroupType::Diffuse, BindGroupType::Camera], &[Vertex::desc()], format, wgpu::BlendState::REPLACE)));
+    }
+}
@@ -0,0 +1,132 @@
+use crate::engine::renderer::texture::Texture;
+
+/// How big a render target is, worked out again from the window size every resize
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetSize {
+    Window,
+    Scaled(f32), // a fraction of the window, eg. 0.5 for a half resolution post process pass
+    Fixed(u32, u32), // doesn't follow the window, eg. a minimap
+}
+
+impl TargetSize {
+    /// The size in pixels for a window of width x height, never 0 so wgpu accepts it
+    pub fn resolve(&self, width: u32, height: u32) -> (u32, u32) {
+        let (width, height) = match *self {
+            TargetSize::Window => (width, height),
+            TargetSize::Scaled(scale) => ((width as f32 * scale).round() as u32, (height as f32 * scale).round() as u32),
+            TargetSize::Fixed(width, height) => (width, height),
+        };
+        (width.max(1), height.max(1))
+    }
+}
+
+/// What gets rendered into a target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetKind {
+    Depth,
+    Colour(wgpu::TextureFormat), // eg. the surface format, or HDR_FORMAT for lighting before tone mapping
+}
+
+impl TargetKind {
+    pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+}
+
+/// Handle to a target in RenderTargets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderTargetId(usize);
+
+struct RenderTarget {
+    label: String,
+    size: TargetSize,
+    kind: TargetKind,
+    resolved: (u32, u32),
+    texture: Texture,
+    generation: u32,
+}
+
+/// Every texture whose size depends on the window, so a resize recreates all of them in one place instead of each
+/// pass having to remember to. Passes keep a RenderTargetId and look the texture up each frame. Anything that holds
+/// on to a target's view (eg. a bind group sampling it) should compare generation to know when to rebuild.
+pub struct RenderTargets {
+    targets: Vec<RenderTarget>,
+}
+
+impl RenderTargets {
+    pub fn new() -> Self {
+        Self { targets: vec![] }
+    }
+
+    /// Make a new target sized for the current surface
+    pub fn add(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, label: &str, size: TargetSize, kind: TargetKind) -> RenderTargetId {
+        let resolved = size.resolve(config.width, config.height);
+        self.targets.push(RenderTarget {
+            label: label.to_owned(),
+            size,
+            kind,
+            resolved,
+            texture: Self::create(device, config, label, kind, resolved),
+            generation: 0,
+        });
+        RenderTargetId(self.targets.len() - 1)
+    }
+
+    pub fn get(&self, id: RenderTargetId) -> &Texture {
+        &self.targets[id.0].texture
+    }
+
+    /// Size in pixels as of the last resize
+    pub fn size(&self, id: RenderTargetId) -> (u32, u32) {
+        self.targets[id.0].resolved
+    }
+
+    /// Goes up each time the target is recreated
+    pub fn generation(&self, id: RenderTargetId) -> u32 {
+        self.targets[id.0].generation
+    }
+
+    /// Change how a target is sized, eg. the render scale, recreating it if that changes its size in pixels
+    pub fn set_size(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, id: RenderTargetId, size: TargetSize) {
+        self.targets[id.0].size = size;
+        self.resize(device, config);
+    }
+
+    /// Recreate the targets whose size changed with the surface. Fixed targets are left alone.
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        for target in &mut self.targets {
+            let resolved = target.size.resolve(config.width, config.height);
+            if resolved == target.resolved {
+                continue;
+            }
+            target.resolved = resolved;
+            target.texture = Self::create(device, config, &target.label, target.kind, resolved);
+            target.generation += 1;
+        }
+    }
+
+    fn create(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, label: &str, kind: TargetKind, (width, height): (u32, u32)) -> Texture {
+        match kind {
+            TargetKind::Depth => Texture::create_depth_texture(device, &wgpu::SurfaceConfiguration { width, height, ..config.clone() }, label),
+            TargetKind::Colour(format) => Texture::create_render_target(device, &wgpu::SurfaceConfiguration { width, height, format, ..config.clone() }, label),
+        }
+    }
+}
+
+impl Default for RenderTargets {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_size() {
+        assert_eq!(TargetSize::Window.resolve(1280, 720), (1280, 720));
+        assert_eq!(TargetSize::Scaled(0.5).resolve(1281, 720), (641, 360));
+        assert_eq!(TargetSize::Fixed(320, 180).resolve(1280, 720), (320, 180));
+        // a minimised window still gets a texture wgpu will make
+        assert_eq!(TargetSize::Scaled(0.25).resolve(2, 0), (1, 1));
+    }
+}
@@ -1,7 +1,7 @@
 use wgpu::{BindGroup, BindGroupLayout, ShaderModule};
 use std::collections::HashMap;
 
-use crate::engine::{app::camera::Camera, renderer::texture::{self, Texture}};
+use crate::engine::{app::camera::Camera, renderer::{pipeline_cache::{PipelineCache, PipelineKey}, texture::{self, Texture}}};
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum BindGroupType {
@@ -16,8 +16,10 @@ const BIND_GROUP_MAPPINGS: &[(&str, BindGroupType)] = &[
 ];
 
 pub struct ShaderBuilder<'a> {
+    file_name: String,
     shader_module: ShaderModule,
     device: &'a wgpu::Device,
+    blend: wgpu::BlendState,
     mappings: HashMap<BindGroupType, u32>,
     bind_groups_map: HashMap<u32, (BindGroupLayout, BindGroup)>,
 }
@@ -28,7 +30,7 @@ impl<'a> ShaderBuilder<'a> {
 
         let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
             .join("res")
-            .join(file_name.clone());
+            .join(&file_name);
 
         let shader_source = fs::read_to_string(path)
             .expect("Failed to read shader file");
@@ -41,8 +43,10 @@ impl<'a> ShaderBuilder<'a> {
         });
 
         Self {
+            file_name,
             device,
             shader_module,
+            blend: wgpu::BlendState::REPLACE,
             mappings,
             bind_groups_map: HashMap::new(),
         }
@@ -92,11 +96,23 @@ impl<'a> ShaderBuilder<'a> {
         mappings
     }
 
-    pub fn diffuse_texture(&mut self, diffuse_texture: &Texture) -> &mut Self {
-        let group_index = *self.mappings.get(&BindGroupType::Diffuse).expect("Shader does not have a 'diffuse' bind group");
-
-        let texture_bind_group_layout =
-            self.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+    /// The layout for a kind of bind group, the same for every shader so pipelines built with it can be shared
+    fn bind_group_layout(device: &wgpu::Device, bind_group_type: BindGroupType) -> BindGroupLayout {
+        match bind_group_type {
+            BindGroupType::Camera => device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("camera_bind_group_layout"),
+            }),
+            BindGroupType::Diffuse => device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[
                     wgpu::BindGroupLayoutEntry {
                         binding: 0,
@@ -116,7 +132,19 @@ impl<'a> ShaderBuilder<'a> {
                     },
                 ],
                 label: Some("texture_bind_group_layout"),
-            });
+            }),
+        }
+    }
+
+    pub fn blend(&mut self, blend: wgpu::BlendState) -> &mut Self {
+        self.blend = blend;
+        self
+    }
+
+    pub fn diffuse_texture(&mut self, diffuse_texture: &Texture) -> &mut Self {
+        let group_index = *self.mappings.get(&BindGroupType::Diffuse).expect("Shader does not have a 'diffuse' bind group");
+
+        let texture_bind_group_layout = Self::bind_group_layout(self.device, BindGroupType::Diffuse);
 
         let diffuse_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &texture_bind_group_layout,
@@ -141,20 +169,7 @@ impl<'a> ShaderBuilder<'a> {
     pub fn camera(&mut self, camera: &Camera) -> &mut Self {
         let group_index = *self.mappings.get(&BindGroupType::Camera).expect("Shader does not have a 'camera' bind group");
 
-        let camera_bind_group_layout =
-            self.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-                label: Some("camera_bind_group_layout"),
-            });
+        let camera_bind_group_layout = Self::bind_group_layout(self.device, BindGroupType::Camera);
 
         let camera_buffer = match &camera.camera_buffer {
             Some(c) => c,
@@ -175,7 +190,8 @@ impl<'a> ShaderBuilder<'a> {
         self
     }
 
-    pub fn build(&mut self, buffers: &'a [wgpu::VertexBufferLayout<'a>], format: wgpu::TextureFormat) -> Shader {
+    /// The bind groups set up so far, with their layouts, in group index order
+    fn take_bind_groups(&mut self) -> (Vec<BindGroupLayout>, Vec<BindGroup>) {
         let mut bind_groups_map = std::mem::take(&mut self.bind_groups_map);
         let max_index = bind_groups_map.keys().max().copied().unwrap_or(0);
         
@@ -204,17 +220,18 @@ impl<'a> ShaderBuilder<'a> {
             }
         }
 
-        let bind_group_layout_refs: Vec<&BindGroupLayout> = bind_group_layouts.iter().collect();
-        let bind_group_layouts_slice = bind_group_layout_refs.as_slice();
+        (bind_group_layouts, bind_groups)
+    }
 
+    fn create_pipeline(&self, bind_group_layouts: &[&BindGroupLayout], buffers: &[wgpu::VertexBufferLayout], format: wgpu::TextureFormat) -> wgpu::RenderPipeline {
         let render_pipeline_layout =
             self.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
-                bind_group_layouts: bind_group_layouts_slice,
+                bind_group_layouts,
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = self.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        self.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("Render Pipeline"),
             layout: Some(&render_pipeline_layout),
             vertex: wgpu::VertexState {
@@ -228,10 +245,7 @@ impl<'a> ShaderBuilder<'a> {
                 entry_point: Some("fs_main"),
                 targets: &[Some(wgpu::ColorTargetState {
                     format: format,
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent::REPLACE,
-                        alpha: wgpu::BlendComponent::REPLACE,
-                    }),
+                    blend: Some(self.blend),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
                 compilation_options: Default::default(),
@@ -259,13 +273,55 @@ impl<'a> ShaderBuilder<'a> {
             },
             multiview: None,
             cache: None,
-        });
+        })
+    }
+
+    /// Bind group types in group index order, the part of the pipeline key that comes from the shader source
+    fn bind_group_types(&self) -> Vec<BindGroupType> {
+        let mut mappings = self.mappings.iter().map(|(bind_group_type, index)| (*index, *bind_group_type)).collect::<Vec<_>>();
+        mappings.sort_by_key(|(index, _)| *index);
+        mappings.into_iter().map(|(_, bind_group_type)| bind_group_type).collect()
+    }
+
+    fn pipeline_key(&self, buffers: &[wgpu::VertexBufferLayout], format: wgpu::TextureFormat) -> PipelineKey {
+        PipelineKey::new(&self.file_name, self.bind_group_types(), buffers, format, self.blend)
+    }
+
+    pub fn build(&mut self, buffers: &'a [wgpu::VertexBufferLayout<'a>], format: wgpu::TextureFormat) -> Shader {
+        let (bind_group_layouts, bind_groups) = self.take_bind_groups();
+        let bind_group_layout_refs: Vec<&BindGroupLayout> = bind_group_layouts.iter().collect();
+        let render_pipeline = self.create_pipeline(&bind_group_layout_refs, buffers, format);
 
         Shader {
             render_pipeline,
             bind_groups,
         }
     }
+
+    /// Like build, but reuses the pipeline from the cache if this permutation has been built before
+    pub fn build_cached(&mut self, cache: &mut PipelineCache, buffers: &'a [wgpu::VertexBufferLayout<'a>], format: wgpu::TextureFormat) -> Shader {
+        let (bind_group_layouts, bind_groups) = self.take_bind_groups();
+        let bind_group_layout_refs: Vec<&BindGroupLayout> = bind_group_layouts.iter().collect();
+        let key = self.pipeline_key(buffers, format);
+        let render_pipeline = cache.get_or_insert_with(key, || self.create_pipeline(&bind_group_layout_refs, buffers, format));
+
+        Shader {
+            render_pipeline,
+            bind_groups,
+        }
+    }
+
+    /// Compile the pipeline into the cache without any bind groups, so it's ready for the first build_cached that needs it.
+    /// Bind group layouts are the same for every shader, so they are made from the types the shader source asks for.
+    pub fn precompile(&self, cache: &mut PipelineCache, buffers: &[wgpu::VertexBufferLayout], format: wgpu::TextureFormat) {
+        let key = self.pipeline_key(buffers, format);
+        if cache.contains(&key) {
+            return;
+        }
+        let bind_group_layouts = key.bind_groups.iter().map(|bind_group_type| Self::bind_group_layout(self.device, *bind_group_type)).collect::<Vec<_>>();
+        let bind_group_layout_refs: Vec<&BindGroupLayout> = bind_group_layouts.iter().collect();
+        cache.get_or_insert_with(key, || self.create_pipeline(&bind_group_layout_refs, buffers, format));
+    }
 }
 
 
@@ -0,0 +1,173 @@
+use wgpu::util::DeviceExt;
+
+use crate::{
+    core::math::{vec2::Vec2, vec4::Vec4},
+    engine::renderer::{
+        glyph_atlas::{layout_text, GlyphAtlas, GLYPH_HEIGHT, GLYPH_WIDTH},
+        instance_renderer::{QUAD_INDICES, QUAD_VERTICES},
+        texture::Texture,
+    },
+};
+
+// just in front of the particles so labels aren't hidden behind the ground
+const LABEL_DEPTH: f32 = 0.05;
+
+/// A line of text placed in the world, eg. a distance marker or a ghost's name.
+/// Size is the height of the letters in metres.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorldLabel {
+    pub text: String,
+    pub position: Vec2, // centre of the text
+    pub size: f32,
+    pub colour: Vec4,
+}
+
+impl WorldLabel {
+    pub fn new(text: String, position: Vec2, size: f32, colour: Vec4) -> Self {
+        Self { text, position, size, colour }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GlyphInstanceRaw {
+    model: [[f32; 4]; 4],
+    colour: [f32; 4],
+    uv_rect: [f32; 4],
+}
+
+impl GlyphInstanceRaw {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<GlyphInstanceRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                // model matrix, slots from 5 like InstanceRaw
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
+                    shader_location: 8,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute { // Colour
+                    offset: mem::size_of::<[f32; 16]>() as wgpu::BufferAddress,
+                    shader_location: 9,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute { // glyph rect in the atlas
+                    offset: mem::size_of::<[f32; 20]>() as wgpu::BufferAddress,
+                    shader_location: 10,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+/// Draws world labels as one instanced quad per glyph, textured from the glyph atlas
+pub struct TextRenderer {
+    atlas: GlyphAtlas,
+    texture: Texture,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+    instance_buffer: wgpu::Buffer,
+    num_instances: usize,
+    instance_data: Vec<GlyphInstanceRaw>, // reused each update so labels don't allocate every frame
+}
+
+impl TextRenderer {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let atlas = GlyphAtlas::new();
+        let texture = Texture::from_image(device, queue, &image::DynamicImage::ImageRgba8(atlas.image.clone()), Some("Glyph Atlas"))
+            .expect("Failed to create glyph atlas texture");
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Text Vertex Buffer"),
+            contents: bytemuck::cast_slice(QUAD_VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Text Index Buffer"),
+            contents: bytemuck::cast_slice(QUAD_INDICES),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Text Instance Buffer"),
+            size: std::mem::size_of::<GlyphInstanceRaw>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            atlas,
+            texture,
+            vertex_buffer,
+            index_buffer,
+            num_indices: QUAD_INDICES.len() as u32,
+            instance_buffer,
+            num_instances: 0,
+            instance_data: vec![],
+        }
+    }
+
+    /// The atlas texture, for the text shader's diffuse bind group
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn update_labels<'a>(&mut self, labels: impl IntoIterator<Item = &'a WorldLabel>, queue: &wgpu::Queue, device: &wgpu::Device) {
+        let instance_data = &mut self.instance_data;
+        instance_data.clear();
+        for label in labels {
+            let pixel = label.size / GLYPH_HEIGHT as f32;
+            let scale = cgmath::Matrix4::from_nonuniform_scale(GLYPH_WIDTH as f32 * pixel, label.size, 1.0);
+            for (c, pos) in layout_text(&label.text, label.position, label.size) {
+                let translation = cgmath::Matrix4::from_translation(cgmath::Vector3::new(pos.x, pos.y, LABEL_DEPTH));
+                instance_data.push(GlyphInstanceRaw {
+                    model: (translation * scale).into(),
+                    colour: label.colour.0.into(),
+                    uv_rect: self.atlas.uv_rect(c),
+                });
+            }
+        }
+
+        let instance_data_size = std::mem::size_of_val(instance_data.as_slice());
+        if instance_data_size <= self.instance_buffer.size() as usize {
+            queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(instance_data));
+        } else {
+            self.instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Text Instance Buffer"),
+                contents: bytemuck::cast_slice(instance_data),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            });
+        }
+
+        self.num_instances = instance_data.len();
+    }
+
+    pub fn render(&self, render_pass: &mut wgpu::RenderPass) {
+        if self.num_instances == 0 {
+            return;
+        }
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(0..self.num_indices, 0, 0..self.num_instances as u32);
+    }
+}
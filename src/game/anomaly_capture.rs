@@ -0,0 +1,232 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::{
+    engine::app::event_system::{FramedEvent, GameEvent},
+    game::scene_file::SceneFile,
+    simulation::particles::simulation::Simulation,
+};
+
+/// Where captures of physics blowups go, a folder for each
+pub const ANOMALIES_DIR: &str = "anomalies";
+pub const MAX_SNAPSHOTS: usize = 10; // the last N states kept before an anomaly
+const SNAPSHOT_INTERVAL_FRAMES: u128 = 10; // a snapshot every frame would cost too much with a level's worth of particles
+const MAX_SPEED: f32 = 500.0; // m/s, nothing in a level goes anywhere near this unless the solver has blown up
+const RESIDUAL_SPIKE: f32 = 50.0; // times the recent residual
+const MIN_SPIKE_RESIDUAL: f32 = 0.05; // m, a spike from nearly nothing to still nearly nothing isn't worth a report
+const RESIDUAL_WARMUP_FRAMES: u32 = 60; // a new level settles for a while, so spikes only count after this
+const RESIDUAL_SMOOTHING: f32 = 0.05; // weight of each frame in the recent residual
+
+/// Something in the simulation that shouldn't happen, eg. a particle sent to NaN
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind")]
+pub enum Anomaly {
+    NanPosition { particle: usize },
+    VelocityExplosion { particle: usize, speed: f32 }, // m/s
+    ResidualSpike { residual: f32, typical: f32 }, // m, the last iteration's largest correction against the recent average
+    Quarantined { particle: usize }, // went NaN or infinite and was frozen by the solver before it spread
+}
+
+impl Anomaly {
+    pub fn describe(&self) -> String {
+        match self {
+            Anomaly::NanPosition { particle } => format!("particle {} has a NaN position", particle),
+            Anomaly::VelocityExplosion { particle, speed } => format!("particle {} is moving at {:.0}m/s", particle, speed),
+            Anomaly::ResidualSpike { residual, typical } => format!("solver residual spiked to {:.1}mm from {:.2}mm", residual * 1000.0, typical * 1000.0),
+            Anomaly::Quarantined { particle } => format!("particle {} went non-finite and was frozen", particle),
+        }
+    }
+}
+
+/// A clean state from shortly before the anomaly
+struct Snapshot {
+    frame: u128,
+    scene: SceneFile,
+}
+
+#[derive(Serialize)]
+struct AnomalyReport<'a> {
+    frame: u128,
+    anomaly: &'a Anomaly,
+    description: String,
+    snapshot_frames: Vec<u128>,
+    header: Option<serde_json::Value>, // how the level was built, as in a recording
+}
+
+/// Watches the simulation after every step for NaNs, quarantined particles, exploding velocities and solver residual spikes. Keeps the
+/// last few states and inputs, so when something goes wrong they can be written out to reproduce it from.
+/// Only the first anomaly in a run is reported, once something blows up it tends to keep on going off.
+pub struct AnomalyCapture {
+    snapshots: VecDeque<Snapshot>, // oldest first
+    inputs: VecDeque<FramedEvent>, // since the oldest snapshot
+    typical_residual: f32,
+    frames_checked: u32,
+    reported: bool,
+}
+
+impl AnomalyCapture {
+    pub fn new() -> Self {
+        Self {
+            snapshots: VecDeque::new(),
+            inputs: VecDeque::new(),
+            typical_residual: 0.0,
+            frames_checked: 0,
+            reported: false,
+        }
+    }
+
+    /// Start watching a new run
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Keep this frame's inputs, the same kinds of event a recording keeps
+    pub fn record_inputs<'a>(&mut self, frame: u128, events: impl Iterator<Item = &'a GameEvent>) {
+        for event in events {
+            if matches!(event, GameEvent::MouseInput { .. } | GameEvent::KeyboardInput { .. } | GameEvent::CursorMoved { .. } | GameEvent::GamepadInput { .. }) {
+                self.inputs.push_back(FramedEvent { frame, event: *event });
+            }
+        }
+    }
+
+    /// Look over the simulation after a step. Returns the anomaly if this is the first one of the run,
+    /// otherwise the state is clean and kept as a snapshot if one is due.
+    pub fn check(&mut self, frame: u128, simulation: &Simulation) -> Option<Anomaly> {
+        if let Some(anomaly) = self.find_anomaly(simulation) {
+            let first = !self.reported;
+            self.reported = true;
+            return first.then_some(anomaly);
+        }
+
+        if frame.is_multiple_of(SNAPSHOT_INTERVAL_FRAMES) {
+            if self.snapshots.len() >= MAX_SNAPSHOTS {
+                self.snapshots.pop_front();
+            }
+            self.snapshots.push_back(Snapshot { frame, scene: SceneFile::from_simulation(simulation) });
+            let oldest = self.snapshots.front().map_or(frame, |snapshot| snapshot.frame);
+            while self.inputs.front().is_some_and(|input| input.frame < oldest) {
+                self.inputs.pop_front();
+            }
+        }
+        None
+    }
+
+    fn find_anomaly(&mut self, simulation: &Simulation) -> Option<Anomaly> {
+        if let Some(&particle) = simulation.quarantined.first() {
+            return Some(Anomaly::Quarantined { particle });
+        }
+        for (particle, p) in simulation.particles.iter().enumerate() {
+            if !p.pos.x.is_finite() || !p.pos.y.is_finite() {
+                return Some(Anomaly::NanPosition { particle });
+            }
+            let speed = p.vel.magnitude();
+            if speed > MAX_SPEED || !speed.is_finite() {
+                return Some(Anomaly::VelocityExplosion { particle, speed });
+            }
+        }
+
+        let residual = simulation.solver_stats.residual;
+        let typical = self.typical_residual;
+        self.frames_checked += 1;
+        self.typical_residual += (residual - typical) * RESIDUAL_SMOOTHING;
+        let spiked = self.frames_checked > RESIDUAL_WARMUP_FRAMES && residual > MIN_SPIKE_RESIDUAL && residual > typical * RESIDUAL_SPIKE;
+        spiked.then_some(Anomaly::ResidualSpike { residual, typical })
+    }
+
+    /// Write the anomaly, the snapshots as scene files and the inputs since the oldest one to a new folder in dir.
+    /// The snapshots open in the sandbox with demo --file.
+    pub fn save(&self, dir: &Path, frame: u128, anomaly: &Anomaly, header: Option<serde_json::Value>, now: chrono::DateTime<chrono::Local>) -> Result<PathBuf, String> {
+        let path = dir.join(format!("anomaly-{}-frame{}", now.format("%Y%m%d-%H%M%S"), frame));
+        fs::create_dir_all(&path).map_err(|e| format!("can't create {}: {}", path.display(), e))?;
+
+        for snapshot in &self.snapshots {
+            snapshot.scene.save(&path.join(format!("frame{}.json", snapshot.frame)))?;
+        }
+        let report = AnomalyReport {
+            frame,
+            anomaly,
+            description: anomaly.describe(),
+            snapshot_frames: self.snapshots.iter().map(|snapshot| snapshot.frame).collect(),
+            header,
+        };
+        let write = |name: &str, json: serde_json::Result<String>| {
+            let json = json.map_err(|e| format!("can't write {}: {}", name, e))?;
+            fs::write(path.join(name), json).map_err(|e| format!("can't write {}: {}", name, e))
+        };
+        write("anomaly.json", serde_json::to_string_pretty(&report))?;
+        write("inputs.json", serde_json::to_string_pretty(&self.inputs))?;
+        Ok(path)
+    }
+}
+
+impl Default for AnomalyCapture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::math::{random::Random, vec2::Vec2},
+        engine::app::event_system::{ElementStateType, KeyCodeType},
+        simulation::particles::simulation_demos::SimulationDemos,
+    };
+
+    const TIME_DELTA: f32 = 0.005;
+
+    #[test]
+    fn test_capture_a_blowup() {
+        let mut simulation = Simulation::new(Random::seed_from_str("anomaly"));
+        assert!(SimulationDemos::init("pendulum", &mut simulation));
+        let mut capture = AnomalyCapture::new();
+        let key = GameEvent::KeyboardInput { key_code: KeyCodeType::KeyA, state: ElementStateType::Pressed };
+        for frame in 1..=150 {
+            simulation.step(TIME_DELTA);
+            capture.record_inputs(frame, [key, GameEvent::RedrawRequested].iter());
+            assert_eq!(capture.check(frame, &simulation), None, "frame {}", frame);
+        }
+        // only the last few states, and the inputs from the oldest on
+        assert_eq!(capture.snapshots.iter().map(|snapshot| snapshot.frame).collect::<Vec<_>>(), (6..=15).map(|i| i * 10).collect::<Vec<_>>());
+        assert_eq!(capture.inputs.len(), 91);
+
+        let particle = simulation.particles.len() - 1;
+        simulation.particles[particle].vel = Vec2::new(0.0, 1000.0);
+        let anomaly = capture.check(151, &simulation).unwrap();
+        assert_eq!(anomaly, Anomaly::VelocityExplosion { particle, speed: 1000.0 });
+        simulation.particles[particle].pos = Vec2::new(f32::NAN, 0.0);
+        assert_eq!(capture.check(152, &simulation), None);
+
+        let dir = std::env::temp_dir().join(format!("planck_anomalies_{}", std::process::id()));
+        let path = capture.save(&dir, 151, &anomaly, None, chrono::Local::now()).unwrap();
+        let report: serde_json::Value = serde_json::from_str(&fs::read_to_string(path.join("anomaly.json")).unwrap()).unwrap();
+        assert_eq!(report["anomaly"]["kind"], "VelocityExplosion");
+        assert_eq!(report["snapshot_frames"].as_array().unwrap().len(), MAX_SNAPSHOTS);
+        assert!(SceneFile::load(&path.join("frame150.json")).is_ok());
+        let inputs: Vec<FramedEvent> = serde_json::from_str(&fs::read_to_string(path.join("inputs.json")).unwrap()).unwrap();
+        assert_eq!(inputs.first().map(|input| input.frame), Some(60));
+        let _ = fs::remove_dir_all(&dir);
+
+        // a new run gets a report of its own
+        capture.reset();
+        assert_eq!(capture.check(1, &simulation), Some(Anomaly::NanPosition { particle }));
+    }
+
+    #[test]
+    fn test_residual_spike() {
+        let mut simulation = Simulation::new(Random::seed_from_str("anomaly"));
+        let mut capture = AnomalyCapture::new();
+        simulation.solver_stats.residual = 0.00001;
+        for frame in 1..=RESIDUAL_WARMUP_FRAMES as u128 * 2 {
+            assert_eq!(capture.check(frame, &simulation), None);
+        }
+        simulation.solver_stats.residual = 0.02; // a big jump, but from nearly nothing to still not much
+        assert_eq!(capture.check(1000, &simulation), None);
+        simulation.solver_stats.residual = 0.5;
+        assert!(matches!(capture.check(1001, &simulation), Some(Anomaly::ResidualSpike { .. })));
+    }
+}
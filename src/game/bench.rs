@@ -0,0 +1,153 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::{
+    core::math::random::Random,
+    game::headless_run::TIME_DELTA,
+    simulation::particles::{simulation::Simulation, simulation_demos::SimulationDemos},
+};
+
+pub const DEFAULT_BENCH_SCENE: &str = "fluid";
+pub const DEFAULT_BENCH_SECONDS: f32 = 30.0;
+
+/// Which demo to run and for how long, in simulated time
+#[derive(Debug, Clone)]
+pub struct BenchConfig {
+    pub scene: String, // one of SimulationDemos::NAMES
+    pub seconds: f32,
+    pub time_delta: f32, // fixed, so every run of a scene does the same work
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self {
+            scene: DEFAULT_BENCH_SCENE.to_owned(),
+            seconds: DEFAULT_BENCH_SECONDS,
+            time_delta: TIME_DELTA,
+        }
+    }
+}
+
+impl BenchConfig {
+    /// bench [scene] [--seconds <s>] [--dt <s>]
+    pub fn from_args(args: &[String]) -> Self {
+        let value = |flag: &str| args.iter().position(|arg| arg == flag).and_then(|i| args.get(i + 1)).and_then(|value| value.parse::<f32>().ok());
+        let defaults = Self::default();
+        Self {
+            scene: args.first().filter(|arg| !arg.starts_with("--")).cloned().unwrap_or(defaults.scene),
+            seconds: value("--seconds").unwrap_or(defaults.seconds),
+            time_delta: value("--dt").filter(|dt| *dt > 0.0).unwrap_or(defaults.time_delta),
+        }
+    }
+}
+
+/// Time spent in one phase of the step, in milliseconds
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct PhaseTiming {
+    pub total_ms: f64,
+    pub mean_ms: f64, // per step
+    pub max_ms: f64, // the slowest step
+}
+
+impl PhaseTiming {
+    fn add(&mut self, duration: Duration) {
+        let ms = duration.as_secs_f64() * 1000.0;
+        self.total_ms += ms;
+        self.max_ms = self.max_ms.max(ms);
+    }
+
+    fn finish(&mut self, steps: u32) {
+        self.mean_ms = if steps > 0 { self.total_ms / steps as f64 } else { 0.0 };
+    }
+}
+
+/// Solver iterations over the run, the adaptive solver does more of them on harder steps
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct IterationCounts {
+    pub total: u64,
+    pub mean: f64, // per step
+    pub max: i32,
+    pub mean_iteration_ms: f64, // solve time over the number of iterations
+}
+
+/// Where the time went stepping a demo, written out as JSON to track performance between builds
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BenchReport {
+    pub scene: String,
+    pub steps: u32,
+    pub time_delta: f32,
+    pub particles: usize, // at the end, emitters add more as it runs
+    pub pre_solve: PhaseTiming, // includes the broadphase
+    pub broadphase: PhaseTiming, // finding neighbours and contacts
+    pub solve: PhaseTiming,
+    pub post_solve: PhaseTiming,
+    pub step: PhaseTiming, // all of the above
+    pub solver_iterations: IterationCounts,
+}
+
+impl BenchReport {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+/// Step a demo scene without a window the same way the game does, timing each phase
+pub fn run_bench(config: &BenchConfig) -> Result<BenchReport, String> {
+    let mut simulation = Simulation::new(Random::seed_from_str("bench"));
+    if !SimulationDemos::init(&config.scene, &mut simulation) {
+        return Err(format!("there's no '{}' demo, pick one of {}", config.scene, SimulationDemos::NAMES.join(", ")));
+    }
+
+    let mut report = BenchReport {
+        scene: config.scene.clone(),
+        steps: (config.seconds / config.time_delta).round().max(1.0) as u32,
+        time_delta: config.time_delta,
+        ..BenchReport::default()
+    };
+    for _ in 0..report.steps {
+        let step_start = Instant::now();
+        simulation.pre_solve(config.time_delta);
+        let pre_solve_end = Instant::now();
+        let stats = simulation.solve_adaptive(config.time_delta, |_| {});
+        let solve_end = Instant::now();
+        simulation.post_solve(config.time_delta);
+        let post_solve_end = Instant::now();
+
+        report.pre_solve.add(pre_solve_end - step_start);
+        report.broadphase.add(simulation.broadphase_time);
+        report.solve.add(solve_end - pre_solve_end);
+        report.post_solve.add(post_solve_end - solve_end);
+        report.step.add(post_solve_end - step_start);
+        report.solver_iterations.total += stats.iterations as u64;
+        report.solver_iterations.max = report.solver_iterations.max.max(stats.iterations);
+    }
+
+    for phase in [&mut report.pre_solve, &mut report.broadphase, &mut report.solve, &mut report.post_solve, &mut report.step] {
+        phase.finish(report.steps);
+    }
+    let iterations = &mut report.solver_iterations;
+    iterations.mean = iterations.total as f64 / report.steps as f64;
+    iterations.mean_iteration_ms = if iterations.total > 0 { report.solve.total_ms / iterations.total as f64 } else { 0.0 };
+    report.particles = simulation.particles.len();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_a_demo() {
+        let config = BenchConfig::from_args(&["pendulum".to_owned(), "--seconds".to_owned(), "0.1".to_owned()]);
+        assert_eq!(config.time_delta, TIME_DELTA);
+        let report = run_bench(&config).unwrap();
+        assert_eq!(report.steps, 20);
+        assert!(report.particles > 0);
+        assert!(report.solver_iterations.total >= 20);
+        assert!(report.step.total_ms >= report.solve.total_ms && report.pre_solve.total_ms >= report.broadphase.total_ms);
+        assert!(report.to_json().contains("\"scene\": \"pendulum\""));
+
+        assert!(run_bench(&BenchConfig { scene: "nothing".to_owned(), ..config }).is_err());
+    }
+}
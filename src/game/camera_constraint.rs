@@ -0,0 +1,115 @@
+use crate::{core::math::{aabb2d::Aabb2d, vec2::Vec2}, engine::app::camera::Camera, simulation::particles::{particle_vec::ParticleVec, spatial_hash::SpatialHash}};
+
+/// Keeps the car clear of level geometry when the camera follows it into tunnels and under bridges.
+/// Static particles overhead push the view towards the open space around the car and zoom in, so the car stays
+/// the focus of the frame instead of sitting tucked under the roof.
+pub struct CameraConstraint {
+    pub offset: Vec2, // applied to the camera target, eased towards the wanted offset
+    pub zoom: f32, // scales the field of view, 1 for no change, smaller to zoom in
+    base_fovy: f32,
+    spatial_hash: SpatialHash<usize, 1>, // kept between frames to save reallocating it
+}
+
+impl CameraConstraint {
+    pub const PROBE_RADIUS: f32 = 3.0; // metres around the car to look for geometry
+    pub const CLEARANCE: f32 = 0.5; // geometry lower than this above the car is ground, not a roof
+    pub const MAX_OFFSET: f32 = 1.5; // metres
+    pub const MIN_ZOOM: f32 = 0.7;
+    pub const EASE_RATE: f32 = 4.0; // per second, how quickly the camera settles on the wanted offset and zoom
+
+    pub fn new(camera: &Camera) -> Self {
+        Self {
+            offset: Vec2::zero(),
+            zoom: 1.0,
+            base_fovy: camera.fovy,
+            spatial_hash: SpatialHash::new(),
+        }
+    }
+
+    /// Work out the offset and zoom wanted for the car at `car_pos`, without any easing
+    pub fn solve(&mut self, particles: &ParticleVec, car_pos: Vec2) -> (Vec2, f32) {
+        self.spatial_hash.soft_clear();
+        for (i, p) in particles.iter().enumerate() {
+            if p.imass == 0.0 {
+                self.spatial_hash.insert_point(p.pos, i);
+            }
+        }
+
+        let mut push = Vec2::zero();
+        let mut crowding: f32 = 0.0;
+        for i in self.spatial_hash.aabb_iter(Aabb2d::new(car_pos, Vec2::new(Self::PROBE_RADIUS, Self::PROBE_RADIUS))) {
+            let p = &particles[i];
+            let delta = car_pos - p.pos;
+            let dist = delta.magnitude();
+            if p.pos.y < car_pos.y + Self::CLEARANCE || dist >= Self::PROBE_RADIUS {
+                continue;
+            }
+
+            // closer geometry gets more say
+            let weight = 1.0 - dist / Self::PROBE_RADIUS;
+            push += delta / dist * weight;
+            crowding = crowding.max(weight);
+        }
+
+        let offset = if push.magnitude() > 0.0 { push.normalize() * Self::MAX_OFFSET * crowding } else { Vec2::zero() };
+        let zoom = 1.0 - (1.0 - Self::MIN_ZOOM) * crowding;
+        (offset, zoom)
+    }
+
+    /// Ease towards the wanted offset and zoom and apply them to the camera, which should already be looking at the car
+    pub fn update(&mut self, particles: &ParticleVec, camera: &mut Camera, car_pos: Vec2, time_delta: f32) {
+        let (offset, zoom) = self.solve(particles, car_pos);
+        let t = 1.0 - (-Self::EASE_RATE * time_delta).exp();
+        self.offset += (offset - self.offset) * t;
+        self.zoom += (zoom - self.zoom) * t;
+
+        let target = car_pos + self.offset;
+        camera.target = cgmath::Point3::new(target.x, target.y, 0.0);
+        camera.fovy = self.base_fovy * self.zoom;
+    }
+
+    pub fn reset(&mut self) {
+        self.offset = Vec2::zero();
+        self.zoom = 1.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::particles::particle::Particle;
+
+    fn line(particles: &mut ParticleVec, y: f32) {
+        for i in -10..=10 {
+            particles.push(*Particle::default().set_pos(Vec2::new(i as f32 * 0.2, y)).set_static(true));
+        }
+    }
+
+    #[test]
+    fn test_ground_alone_leaves_the_camera_alone() {
+        let mut particles = ParticleVec::new();
+        line(&mut particles, -0.5);
+        let mut constraint = CameraConstraint::new(&Camera::new_headless(1.0));
+        assert_eq!(constraint.solve(&particles, Vec2::zero()), (Vec2::zero(), 1.0));
+    }
+
+    #[test]
+    fn test_low_roof_zooms_in_and_pushes_the_view_away() {
+        let mut particles = ParticleVec::new();
+        line(&mut particles, -0.5);
+        line(&mut particles, 1.0);
+        let mut camera = Camera::new_headless(1.0);
+        let mut constraint = CameraConstraint::new(&camera);
+
+        let (offset, zoom) = constraint.solve(&particles, Vec2::zero());
+        assert!(offset.y < 0.0);
+        assert!(offset.x.abs() < 1e-4);
+        assert!(zoom < 1.0 && zoom >= CameraConstraint::MIN_ZOOM);
+
+        // eases in rather than snapping
+        constraint.update(&particles, &mut camera, Vec2::zero(), 0.1);
+        assert!(constraint.offset.y < 0.0 && constraint.offset.y > offset.y);
+        assert!(camera.fovy < 45.0 && camera.fovy > 45.0 * zoom);
+        assert_eq!(camera.target.y, constraint.offset.y);
+    }
+}
@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{core::math::vec2::Vec2, engine::app::camera::Camera, game::entity::entities::camera_entity::CameraEntitySystem};
+
+/// Where the camera watches the car from, cycled with V and remembered in the settings
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CameraMode {
+    #[default]
+    Free, // the original camera, stays put and turns to follow the car, moved with the camera keys
+    Chase, // behind the car looking the way it's going
+    Trackside, // fixed cameras placed by the level blocks, cutting between them like a broadcast when watching a replay
+}
+
+impl CameraMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CameraMode::Free => "Free",
+            CameraMode::Chase => "Chase",
+            CameraMode::Trackside => "Trackside",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            CameraMode::Free => CameraMode::Chase,
+            CameraMode::Chase => CameraMode::Trackside,
+            CameraMode::Trackside => CameraMode::Free,
+        }
+    }
+}
+
+/// Moves the camera for the chase and trackside modes. Runs after the car and CameraConstraint have pointed
+/// the camera at the car, and works from that target so the constraint still keeps tunnels clear.
+pub struct CameraDirector {
+    pub mode: CameraMode,
+    heading: Vec2, // direction of travel the chase camera is lined up on, eased so bumps don't shake it
+    shot: Option<usize>, // trackside camera in use
+    shot_time: f32, // seconds since the last cut
+    free_eye: Option<cgmath::Point3<f32>>, // where the free camera was left, put back when it's picked again
+}
+
+impl CameraDirector {
+    pub const CHASE_DISTANCE: f32 = 4.0; // metres behind the car
+    pub const CHASE_HEIGHT: f32 = 1.5; // metres above the car
+    pub const CHASE_Z: f32 = 8.0; // how far back from the level the chase eye is
+    pub const CHASE_LEAD: f32 = 2.0; // metres ahead of the car it looks
+    pub const MIN_CHASE_SPEED: f32 = 0.5; // m/s, slower than this the heading is kept so it doesn't spin round when stopped
+    pub const HEADING_RATE: f32 = 3.0; // per second, how quickly the chase camera swings round to a new heading
+    pub const MIN_SHOT: f32 = 2.0; // seconds a cinematic shot is held before cutting to the next camera
+    pub const SHOT_VIEW_HEIGHT: f32 = 8.0; // metres of the level around the car a cinematic shot zooms to fit
+    pub const MIN_FOVY: f32 = 10.0;
+    pub const MAX_FOVY: f32 = 60.0;
+
+    pub fn new(mode: CameraMode) -> Self {
+        Self {
+            mode,
+            heading: Vec2::new(1.0, 0.0),
+            shot: None,
+            shot_time: 0.0,
+            free_eye: None,
+        }
+    }
+
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        self.mode = mode;
+        self.shot = None;
+    }
+
+    /// Place the camera for the current mode, with the car at car_pos going car_vel.
+    /// Cinematic is for replays, where trackside shots are held for a while and zoom in on the car.
+    pub fn update(&mut self, camera: &mut Camera, cameras: &CameraEntitySystem, car_pos: Vec2, car_vel: Vec2, cinematic: bool, time_delta: f32) {
+        let target = Vec2::new(camera.target.x, camera.target.y);
+        match self.mode {
+            CameraMode::Free => {
+                if let Some(eye) = self.free_eye.take() {
+                    camera.eye = eye;
+                }
+            }
+            CameraMode::Chase => {
+                self.free_eye.get_or_insert(camera.eye);
+                if car_vel.magnitude() > Self::MIN_CHASE_SPEED {
+                    // turn by angle, blending the vectors would never come round when the car reverses
+                    let t = 1.0 - (-Self::HEADING_RATE * time_delta).exp();
+                    let angle = self.heading.y.atan2(self.heading.x);
+                    let turn = (car_vel.y.atan2(car_vel.x) - angle + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI;
+                    let angle = angle + turn * t;
+                    self.heading = Vec2::new(angle.cos(), angle.sin());
+                }
+                let eye = target - self.heading * Self::CHASE_DISTANCE + Vec2::new(0.0, Self::CHASE_HEIGHT);
+                let look_at = target + self.heading * Self::CHASE_LEAD;
+                camera.eye = cgmath::Point3::new(eye.x, eye.y, Self::CHASE_Z);
+                camera.target = cgmath::Point3::new(look_at.x, look_at.y, 0.0);
+            }
+            CameraMode::Trackside => {
+                let Some(watching) = cameras.watching(car_pos) else { return };
+                self.free_eye.get_or_insert(camera.eye);
+
+                self.shot_time += time_delta;
+                let current = self.shot.filter(|&shot| shot < cameras.entities.len());
+                if current != Some(watching) && (!cinematic || current.is_none() || self.shot_time >= Self::MIN_SHOT) {
+                    self.shot = Some(watching);
+                    self.shot_time = 0.0;
+                }
+                let Some(shot) = self.shot.and_then(|shot| cameras.entities.get(shot)) else { return };
+                camera.eye = cgmath::Point3::new(shot.eye.x, shot.eye.y, shot.distance);
+
+                if cinematic {
+                    let distance = cgmath::InnerSpace::magnitude(camera.target - camera.eye);
+                    let fovy = 2.0 * (Self::SHOT_VIEW_HEIGHT * 0.5 / distance).atan().to_degrees();
+                    camera.fovy = fovy.clamp(Self::MIN_FOVY, Self::MAX_FOVY);
+                }
+            }
+        }
+    }
+
+    /// Back to the first shot and heading, for a new run
+    pub fn reset(&mut self) {
+        self.heading = Vec2::new(1.0, 0.0);
+        self.shot = None;
+        self.shot_time = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::math::aabb2d::Aabb2d, game::entity::entities::camera_entity::CameraEntity};
+
+    fn two_cameras() -> CameraEntitySystem {
+        let mut cameras = CameraEntitySystem::new();
+        cameras.push(CameraEntity::for_block(&Aabb2d::new(Vec2::new(0.0, 0.0), Vec2::new(5.0, 1.0))));
+        cameras.push(CameraEntity::for_block(&Aabb2d::new(Vec2::new(10.0, 0.0), Vec2::new(5.0, 1.0))));
+        cameras
+    }
+
+    /// Points the camera at the car like the car entity does, then lets the director move it
+    fn update(director: &mut CameraDirector, camera: &mut Camera, cameras: &CameraEntitySystem, car_pos: Vec2, car_vel: Vec2, cinematic: bool, time_delta: f32) {
+        camera.target = cgmath::Point3::new(car_pos.x, car_pos.y, 0.0);
+        director.update(camera, cameras, car_pos, car_vel, cinematic, time_delta);
+    }
+
+    #[test]
+    fn test_chase_sits_behind_the_car() {
+        let mut camera = Camera::new_headless(1.0);
+        let free_eye = camera.eye;
+        let mut director = CameraDirector::new(CameraMode::Chase);
+        let cameras = CameraEntitySystem::new();
+
+        // driving left for long enough swings it round to look left
+        for _ in 0..200 {
+            update(&mut director, &mut camera, &cameras, Vec2::new(3.0, 0.0), Vec2::new(-5.0, 0.0), false, 0.01);
+        }
+        assert!(camera.eye.x > 3.0 + CameraDirector::CHASE_DISTANCE * 0.9);
+        assert!(camera.target.x < 3.0);
+
+        // stopped, it stays where it is
+        let eye = camera.eye;
+        update(&mut director, &mut camera, &cameras, Vec2::new(3.0, 0.0), Vec2::new(0.0, 0.1), false, 0.01);
+        assert_eq!(camera.eye, eye);
+
+        // and the free camera comes back where it was left
+        director.set_mode(CameraMode::Free);
+        update(&mut director, &mut camera, &cameras, Vec2::new(3.0, 0.0), Vec2::zero(), false, 0.01);
+        assert_eq!(camera.eye, free_eye);
+    }
+
+    #[test]
+    fn test_trackside_cuts_between_cameras() {
+        let cameras = two_cameras();
+        assert_eq!(cameras.watching(Vec2::new(12.0, 0.0)), Some(1));
+        assert_eq!(cameras.watching(Vec2::new(-20.0, 0.0)), Some(0));
+
+        // live it cuts as soon as the car reaches the next block
+        let mut camera = Camera::new_headless(1.0);
+        let mut director = CameraDirector::new(CameraMode::Trackside);
+        update(&mut director, &mut camera, &cameras, Vec2::new(0.0, 0.0), Vec2::zero(), false, 0.1);
+        assert_eq!(camera.eye.x, 0.0);
+        update(&mut director, &mut camera, &cameras, Vec2::new(6.0, 0.0), Vec2::zero(), false, 0.1);
+        assert_eq!(camera.eye.x, 10.0);
+
+        // in a replay the shot is held a while first, and zooms in on the car
+        let mut director = CameraDirector::new(CameraMode::Trackside);
+        update(&mut director, &mut camera, &cameras, Vec2::new(0.0, 0.0), Vec2::zero(), true, 0.1);
+        update(&mut director, &mut camera, &cameras, Vec2::new(6.0, 0.0), Vec2::zero(), true, 0.1);
+        assert_eq!(camera.eye.x, 0.0);
+        assert!(camera.fovy < 45.0);
+        update(&mut director, &mut camera, &cameras, Vec2::new(6.0, 0.0), Vec2::zero(), true, CameraDirector::MIN_SHOT);
+        assert_eq!(camera.eye.x, 10.0);
+    }
+}
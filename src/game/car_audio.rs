@@ -0,0 +1,89 @@
+use std::collections::HashSet;
+
+use crate::{engine::audio::audio_manager::AudioManager, game::entity::entities::car_entity::CarEntity, simulation::particles::simulation::Simulation};
+
+const MIN_HIT_SPEED: f32 = 1.0; // m/s a contact has to close at to be heard
+const FULL_HIT_SPEED: f32 = 8.0; // m/s for the loudest knock
+const IDLE_PITCH: f32 = 0.8; // the engine loop's playback speed with the wheels still
+const PITCH_PER_SPIN: f32 = 0.05; // added for each rad/s the wheels turn
+const MAX_PITCH: f32 = 3.0;
+const IDLE_LOUDNESS: f32 = 0.3; // the engine off the throttle, it gets to full on it
+
+/// How fast the engine loop plays for wheels turning at wheel_spin rad/s
+pub fn engine_pitch(wheel_spin: f32) -> f32 {
+    (IDLE_PITCH + wheel_spin.abs() * PITCH_PER_SPIN).min(MAX_PITCH)
+}
+
+/// Collision knocks and the engine note for my car. Hits are picked up each simulation step and played once a frame.
+#[derive(Default)]
+pub struct CarAudio {
+    hit: f32, // the hardest hit since the sounds were last played, 0 to 1
+}
+
+impl CarAudio {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Note the car's new contacts, between pre_solve and post_solve while the simulation has them
+    pub fn collect_hits(&mut self, car: &CarEntity, sim: &Simulation) {
+        if sim.contact_events.is_empty() {
+            return;
+        }
+        let car_particles: HashSet<usize> = car.particle_handles().collect();
+        for event in &sim.contact_events {
+            // the car's own particles bumping each other isn't a collision
+            if car_particles.contains(&event.i1) == car_particles.contains(&event.i2) {
+                continue;
+            }
+            let strength = ((event.speed - MIN_HIT_SPEED) / (FULL_HIT_SPEED - MIN_HIT_SPEED)).clamp(0.0, 1.0);
+            self.hit = self.hit.max(strength);
+        }
+    }
+
+    /// Play the hits collected since last time and set the engine for the car. Quiet when not racing.
+    pub fn play(&mut self, audio: &mut AudioManager, car: Option<&CarEntity>, racing: bool) {
+        let hit = std::mem::take(&mut self.hit);
+        let Some(car) = car.filter(|_| racing) else {
+            audio.set_engine(IDLE_PITCH, 0.0);
+            return;
+        };
+        if hit > 0.0 {
+            audio.play_collision(hit);
+        }
+        let loudness = IDLE_LOUDNESS + (1.0 - IDLE_LOUDNESS) * car.input_direction().abs();
+        audio.set_engine(engine_pitch(car.wheel_spin()), loudness);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::math::{random::Random, vec2::Vec2},
+        simulation::particles::{particle::Particle, particle_vec::ParticleVec, simulation::ContactEvent},
+    };
+
+    #[test]
+    fn test_hits_on_the_car() {
+        let mut sim = Simulation::new(Random::seed_from_str("car audio"));
+        let car = CarEntity::new(&mut ParticleVec::new(), &mut sim, Vec2::new(0.0, 2.0));
+        let ground = sim.particles.len();
+        sim.add_particle(*Particle::default().set_static(true));
+        let wheel = car.surface_particle_handles().next().unwrap();
+        let other_wheel = car.surface_particle_handles().last().unwrap();
+
+        let mut car_audio = CarAudio::new();
+        sim.contact_events = vec![
+            ContactEvent { i1: ground, i2: wheel, speed: 4.5 },
+            ContactEvent { i1: wheel, i2: other_wheel, speed: 20.0 }, // the car hitting itself
+        ];
+        car_audio.collect_hits(&car, &sim);
+        assert_eq!(car_audio.hit, 0.5);
+
+        car_audio.play(&mut AudioManager::new(), Some(&car), true);
+        assert_eq!(car_audio.hit, 0.0);
+        assert_eq!(engine_pitch(0.0), IDLE_PITCH);
+        assert_eq!(engine_pitch(-1000.0), MAX_PITCH);
+    }
+}
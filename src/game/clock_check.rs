@@ -0,0 +1,130 @@
+use std::net::UdpSocket;
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::game::seed_policy::daily_seed;
+
+pub const DEFAULT_TIME_SERVER: &str = "pool.ntp.org:123";
+
+const TIMEOUT: StdDuration = StdDuration::from_secs(5);
+const NTP_PACKET_LEN: usize = 48;
+const NTP_TO_UNIX_SECONDS: i64 = 2_208_988_800; // from 1900, the NTP epoch, to 1970
+
+/// Further off than this and the player is told, a few seconds either way doesn't matter to a daily
+pub const MAX_CLOCK_SKEW: Duration = Duration::seconds(60);
+
+/// The 64 bit NTP timestamp at offset in an NTP packet
+fn read_timestamp(packet: &[u8], offset: usize) -> Option<DateTime<Utc>> {
+    let seconds = u32::from_be_bytes(packet.get(offset..offset + 4)?.try_into().ok()?) as i64;
+    let fraction = u32::from_be_bytes(packet.get(offset + 4..offset + 8)?.try_into().ok()?) as u64;
+    DateTime::from_timestamp(seconds - NTP_TO_UNIX_SECONDS, ((fraction * 1_000_000_000) >> 32) as u32)
+}
+
+/// How far the local clock is behind the server from an SNTP reply (negative when it's ahead),
+/// given when the request went out and the reply came back by the local clock
+pub fn offset_from_reply(reply: &[u8], sent: DateTime<Utc>, received: DateTime<Utc>) -> Result<Duration, String> {
+    if reply.len() < NTP_PACKET_LEN || reply[0] & 0x7 != 4 {
+        return Err(String::from("not an NTP server reply"));
+    }
+    let (Some(server_received), Some(server_sent)) = (read_timestamp(reply, 32), read_timestamp(reply, 40)) else {
+        return Err(String::from("bad NTP timestamp"));
+    };
+    Ok(((server_received - sent) + (server_sent - received)) / 2)
+}
+
+/// Ask an SNTP server what time it is. Blocks for up to TIMEOUT.
+pub fn query_offset(server: &str) -> Result<Duration, String> {
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| e.to_string())?;
+    socket.set_read_timeout(Some(TIMEOUT)).map_err(|e| e.to_string())?;
+    socket.connect(server).map_err(|e| e.to_string())?;
+
+    let mut request = [0u8; NTP_PACKET_LEN];
+    request[0] = 0x23; // version 4, client
+    let sent = Utc::now();
+    socket.send(&request).map_err(|e| e.to_string())?;
+    let mut reply = [0u8; NTP_PACKET_LEN];
+    let len = socket.recv(&mut reply).map_err(|e| e.to_string())?;
+    offset_from_reply(&reply[..len], sent, Utc::now())
+}
+
+/// What to tell the player when their clock is offset from the real time, None if it's close enough
+pub fn clock_warning(now: DateTime<Utc>, offset: Duration) -> Option<String> {
+    if offset.abs() <= MAX_CLOCK_SKEW {
+        return None;
+    }
+    let fast_or_slow = if offset < Duration::zero() { "fast" } else { "slow" };
+    let minutes = offset.num_minutes().abs().max(1);
+    let amount = if minutes >= 120 { format!("{} hours", minutes / 60) } else { format!("{} min", minutes) };
+    let (local_seed, seed) = (daily_seed(now), daily_seed(now + offset));
+    if local_seed != seed {
+        Some(format!("Your clock is {} {}, so this is the {} daily and not today's ({}). Set the clock right to race today's level.", amount, fast_or_slow, local_seed, seed))
+    } else {
+        Some(format!("Your clock is {} {}, the daily will roll over at the wrong time", amount, fast_or_slow))
+    }
+}
+
+/// Checks the local clock against a time server on a background thread so startup isn't held up.
+/// The daily seed and the seed tags on leaderboard times come from the local clock, a skewed one races the wrong day.
+pub struct ClockCheck {
+    receiver: Option<Receiver<Result<Duration, String>>>,
+}
+
+impl ClockCheck {
+    /// Start checking against server, an empty server turns the check off
+    pub fn start(server: &str) -> Self {
+        if server.is_empty() {
+            return Self { receiver: None };
+        }
+        let (sender, receiver) = channel();
+        let server = server.to_owned();
+        std::thread::spawn(move || {
+            let _ = sender.send(query_offset(&server));
+        });
+        Self { receiver: Some(receiver) }
+    }
+
+    /// The offset once the server has answered, only returned once
+    pub fn poll(&mut self) -> Option<Result<Duration, String>> {
+        let result = self.receiver.as_ref()?.try_recv().ok()?;
+        self.receiver = None;
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn write_timestamp(packet: &mut [u8], offset: usize, time: DateTime<Utc>) {
+        let seconds = (time.timestamp() + NTP_TO_UNIX_SECONDS) as u32;
+        let fraction = (((time.timestamp_subsec_nanos() as u64) << 32) / 1_000_000_000) as u32;
+        packet[offset..offset + 4].copy_from_slice(&seconds.to_be_bytes());
+        packet[offset + 4..offset + 8].copy_from_slice(&fraction.to_be_bytes());
+    }
+
+    #[test]
+    fn test_offset_from_reply() {
+        // the local clock is 10 minutes fast and the round trip takes 200ms
+        let sent = Utc.with_ymd_and_hms(2025, 6, 1, 12, 10, 0).unwrap();
+        let mut reply = [0u8; NTP_PACKET_LEN];
+        reply[0] = 0x24; // version 4, server
+        write_timestamp(&mut reply, 32, sent - Duration::minutes(10) + Duration::milliseconds(100));
+        write_timestamp(&mut reply, 40, sent - Duration::minutes(10) + Duration::milliseconds(100));
+        let offset = offset_from_reply(&reply, sent, sent + Duration::milliseconds(200)).unwrap();
+        assert!((offset + Duration::minutes(10)).abs() < Duration::milliseconds(1));
+
+        reply[0] = 0x23; // a client request echoed back
+        assert!(offset_from_reply(&reply, sent, sent).is_err());
+    }
+
+    #[test]
+    fn test_clock_warning() {
+        let now = Utc.with_ymd_and_hms(2025, 6, 2, 0, 5, 0).unwrap();
+        assert_eq!(clock_warning(now, Duration::seconds(-30)), None);
+        assert_eq!(clock_warning(now, Duration::minutes(-10)).unwrap(), "Your clock is 10 min fast, so this is the 2025-06-02 daily and not today's (2025-06-01). Set the clock right to race today's level.");
+        assert_eq!(clock_warning(now, Duration::minutes(3)).unwrap(), "Your clock is 3 min slow, the daily will roll over at the wrong time");
+    }
+}
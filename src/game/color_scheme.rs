@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+/// The built in colour schemes, picked in settings or cycled with F10
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorScheme {
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+}
+
+impl ColorScheme {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorScheme::Dark => "Dark",
+            ColorScheme::Light => "Light",
+            ColorScheme::HighContrast => "High contrast",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            ColorScheme::Dark => ColorScheme::Light,
+            ColorScheme::Light => ColorScheme::HighContrast,
+            ColorScheme::HighContrast => ColorScheme::Dark,
+        }
+    }
+}
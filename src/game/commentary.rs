@@ -0,0 +1,144 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+
+use crate::{
+    core::math::vec2::Vec2,
+    game::{
+        entity::{entities::checkpoint_entity::CheckpointEntity, gameplay_events::GameplayEvent},
+        telemetry::TelemetryRecording,
+    },
+};
+
+pub const COMMENTARY_LOG_PATH: &str = "commentary.log";
+const LEVEL_WITH_PB: f32 = 0.05; // seconds, closer than this reads as 0.0s either way
+
+/// Narrates a run from the gameplay events, a line per notable event, for streaming overlays to pick up.
+/// Every line goes to a log file and, if there is one, an IRC channel.
+#[derive(Debug, Clone)]
+pub struct Commentary {
+    pub channel: Option<String>, // IRC channel the lines are also posted to
+    log_path: String,
+    pb_splits: Vec<Option<f32>>, // my best time on this seed at each checkpoint
+    pb_time: Option<f32>,
+    start: Vec2, // where the car set off, crashes are told by how far along from here they were
+}
+
+impl Commentary {
+    pub fn new(channel: Option<String>, log_path: &str) -> Self {
+        Self {
+            channel,
+            log_path: log_path.to_owned(),
+            pb_splits: vec![],
+            pb_time: None,
+            start: Vec2::new(0.0, 0.0),
+        }
+    }
+
+    /// Get ready for a new run on a level, pb is my finished best on it if there is one
+    pub fn start_run(&mut self, pb: Option<&TelemetryRecording>, checkpoints: &[CheckpointEntity], start: Vec2) {
+        self.pb_splits = pb.map_or(vec![], |pb| pb.checkpoint_times(checkpoints));
+        self.pb_time = pb.and_then(|pb| pb.total_time());
+        self.start = start;
+    }
+
+    /// A line saying what happened, eg. "Crossed checkpoint 2 at 21.3s, 0.8s ahead of PB"
+    pub fn describe(&self, event: &GameplayEvent) -> String {
+        match event {
+            GameplayEvent::CheckpointReached { checkpoint, time } => {
+                let pb_split = self.pb_splits.get(*checkpoint).copied().flatten();
+                format!("Crossed checkpoint {} at {:.1}s{}", checkpoint + 1, time, compare_to_pb(*time, pb_split))
+            }
+            GameplayEvent::LapCompleted { lap, lap_time } => format!("Lap {} in {:.2}s", lap, lap_time),
+            GameplayEvent::Finished { time } => match self.pb_time {
+                Some(pb_time) if *time < pb_time => format!("Finished in {:.3}s, a new PB by {:.3}s!", time, pb_time - time),
+                Some(pb_time) => format!("Finished in {:.3}s, {:.3}s off PB", time, time - pb_time),
+                None => format!("Finished in {:.3}s", time),
+            },
+            GameplayEvent::Crashed { pos, speed, time } => {
+                format!("Crashed at {:.0} m/s at {:.1}s, {:.0}m into the course", speed, time, (pos.x - self.start.x).abs())
+            }
+        }
+    }
+
+    /// Describe the events from an update and add them to the log, returning the lines for posting elsewhere
+    pub fn narrate(&self, events: &[GameplayEvent]) -> Vec<String> {
+        let lines: Vec<String> = events.iter().map(|event| self.describe(event)).collect();
+        if !lines.is_empty() {
+            if let Err(e) = self.append_to_log(&lines) {
+                eprintln!("Failed to write commentary to {}: {}", self.log_path, e);
+            }
+        }
+        lines
+    }
+
+    fn append_to_log(&self, lines: &[String]) -> io::Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.log_path)?;
+        let timestamp = chrono::Local::now().format("%H:%M:%S");
+        for line in lines {
+            writeln!(file, "[{}] {}", timestamp, line)?;
+        }
+        Ok(())
+    }
+}
+
+/// ", 0.8s ahead of PB" for a time against the PB's time at the same point, empty without one
+fn compare_to_pb(time: f32, pb_time: Option<f32>) -> String {
+    let Some(pb_time) = pb_time else {
+        return String::new();
+    };
+    let difference = pb_time - time;
+    if difference.abs() < LEVEL_WITH_PB {
+        String::from(", level with PB")
+    } else if difference > 0.0 {
+        format!(", {:.1}s ahead of PB", difference)
+    } else {
+        format!(", {:.1}s behind PB", -difference)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::math::aabb2d::Aabb2d, game::telemetry::TelemetryFrame};
+
+    fn frame(time: f32, x: f32) -> TelemetryFrame {
+        TelemetryFrame { frame: 0, time, x, y: 1.0, speed: 10.0, distance: x, input: -1.0, airborne: false, airtime: 0.0, contacts: 1, slip: 0.0 }
+    }
+
+    #[test]
+    fn test_narrate_a_run() {
+        let log_path = std::env::temp_dir().join(format!("planck_commentary_{}.log", std::process::id()));
+        let log_path = log_path.to_str().unwrap();
+        let mut commentary = Commentary::new(None, log_path);
+        let checkpoints = [
+            CheckpointEntity::new(Aabb2d::new(Vec2::new(10.0, 1.0), Vec2::new(1.0, 2.0))),
+            CheckpointEntity::new(Aabb2d::new(Vec2::new(20.0, 1.0), Vec2::new(1.0, 2.0))),
+            CheckpointEntity::new(Aabb2d::new(Vec2::new(90.0, 1.0), Vec2::new(1.0, 2.0))), // the pb never got here
+        ];
+        let pb = TelemetryRecording { seed: "a".to_owned(), finished: true, frames: (0..=30).map(|i| frame(i as f32, i as f32)).collect() };
+        commentary.start_run(Some(&pb), &checkpoints, Vec2::new(-5.0, 1.0));
+
+        let lines = commentary.narrate(&[
+            GameplayEvent::CheckpointReached { checkpoint: 0, time: 8.2 },
+            GameplayEvent::CheckpointReached { checkpoint: 1, time: 21.3 },
+            GameplayEvent::CheckpointReached { checkpoint: 2, time: 40.0 },
+            GameplayEvent::Crashed { pos: Vec2::new(25.0, 3.0), speed: 14.2, time: 22.0 },
+            GameplayEvent::Finished { time: 29.5 },
+        ]);
+        assert_eq!(lines, vec![
+            "Crossed checkpoint 1 at 8.2s, 0.8s ahead of PB",
+            "Crossed checkpoint 2 at 21.3s, 2.3s behind PB",
+            "Crossed checkpoint 3 at 40.0s",
+            "Crashed at 14 m/s at 22.0s, 30m into the course",
+            "Finished in 29.500s, a new PB by 0.500s!",
+        ]);
+        let log = std::fs::read_to_string(log_path).unwrap();
+        assert_eq!(log.lines().count(), 5);
+        assert!(log.lines().nth(1).unwrap().ends_with("] Crossed checkpoint 2 at 21.3s, 2.3s behind PB"));
+
+        commentary.start_run(None, &checkpoints, Vec2::new(0.0, 1.0));
+        assert_eq!(commentary.describe(&GameplayEvent::CheckpointReached { checkpoint: 0, time: 8.2 }), "Crossed checkpoint 1 at 8.2s");
+        assert_eq!(commentary.describe(&GameplayEvent::LapCompleted { lap: 1, lap_time: 31.456 }), "Lap 1 in 31.46s");
+        let _ = std::fs::remove_file(log_path);
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::{
+    core::math::{vec2::Vec2, vec4::Vec4},
+    engine::renderer::instance_renderer::Instance,
+    simulation::particles::particle_vec::ParticleVec,
+};
+
+/// Oldest marks are dropped past this, so a long run doesn't keep growing the instance buffer
+pub const MAX_DECALS: usize = 2000;
+
+const FADE_TIME: f32 = 30.0; // seconds for a mark to fade away
+const DEPTH: f32 = 0.01; // just in front of the ground, marks sit inside its surface so they don't cover the car
+const SKID_SPEED: f32 = 1.5; // how fast a wheel particle slides along the ground before it leaves a mark, m/s
+const IMPACT_SPEED: f32 = 4.0; // how fast a wheel particle hits the ground to leave a scuff, m/s
+const SKID_SPACING: f32 = 0.08; // closest a skid mark is left to another recent one
+const SKID_RADIUS: f32 = 0.06;
+const IMPACT_RADIUS: f32 = 0.15;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decal {
+    pub pos: Vec2,
+    pub angle: f32, // along the ground
+    pub radius: f32,
+    pub colour: Vec4,
+    pub age: f32,
+}
+
+/// Skid marks where the wheels slide and scuffs where they land hard, left on the ground so it's clear
+/// where the car lost grip. Marks take a darker shade of the surface they are on and fade away over time.
+#[derive(Default)]
+pub struct Decals {
+    pub decals: VecDeque<Decal>,
+    touching: HashSet<usize>, // wheel particles that were touching the ground last step
+    spare_touching: HashSet<usize>, // the set from the step before, refilled next step so it isn't reallocated
+}
+
+impl Decals {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.decals.clear();
+        self.touching.clear();
+    }
+
+    /// Age the marks and leave new ones from this step's contacts, each pair is a wheel particle and what it's touching
+    pub fn update(&mut self, contact_pairs: &[(usize, usize)], particle_vec: &ParticleVec, time_delta: f32) {
+        for decal in self.decals.iter_mut() {
+            decal.age += time_delta;
+        }
+        while self.decals.front().is_some_and(|decal| decal.age >= FADE_TIME) {
+            self.decals.pop_front();
+        }
+
+        let mut touching = std::mem::take(&mut self.spare_touching);
+        touching.clear();
+        for &(wheel, ground) in contact_pairs {
+            let wheel_particle = &particle_vec[wheel];
+            let ground_particle = &particle_vec[ground];
+            if ground_particle.mass != 0.0 {
+                continue; // marks on things that move would be left floating
+            }
+            let normal = (wheel_particle.pos - ground_particle.pos).normalize();
+            let contact = ground_particle.pos + normal * ground_particle.radius * 0.5;
+            let tangent = Vec2::new(-normal.y, normal.x);
+            let angle = tangent.y.atan2(tangent.x);
+            let colour = ground_particle.colour * 0.3;
+
+            let relative_vel = wheel_particle.vel - ground_particle.vel;
+            let landed = !self.touching.contains(&wheel) && -relative_vel.dot(normal) >= IMPACT_SPEED;
+            let slip = relative_vel.dot(tangent).abs();
+            touching.insert(wheel);
+
+            if landed {
+                self.push(Decal { pos: contact, angle, radius: IMPACT_RADIUS, colour: Vec4::new(colour.x, colour.y, colour.z, 0.8), age: 0.0 });
+            } else if slip >= SKID_SPEED && !self.has_recent_near(contact) {
+                // harder slides leave darker marks
+                let alpha = (slip / (SKID_SPEED * 4.0)).clamp(0.25, 0.7);
+                self.push(Decal { pos: contact, angle, radius: SKID_RADIUS, colour: Vec4::new(colour.x, colour.y, colour.z, alpha), age: 0.0 });
+            }
+        }
+        self.spare_touching = std::mem::replace(&mut self.touching, touching);
+    }
+
+    fn has_recent_near(&self, pos: Vec2) -> bool {
+        self.decals.iter().rev().take(16).any(|decal| (decal.pos - pos).magnitude() < SKID_SPACING)
+    }
+
+    fn push(&mut self, decal: Decal) {
+        if self.decals.len() >= MAX_DECALS {
+            self.decals.pop_front();
+        }
+        self.decals.push_back(decal);
+    }
+
+    /// Add an instance for each mark, faded by its age
+    pub fn extend_instances(&self, instances: &mut Vec<Instance>) {
+        instances.extend(self.decals.iter().map(|decal| {
+            let fade = 1.0 - decal.age / FADE_TIME;
+            let colour = Vec4::new(decal.colour.x, decal.colour.y, decal.colour.z, decal.colour.w * fade);
+            Instance { position: cgmath::Vector3 { x: decal.pos.x, y: decal.pos.y, z: DEPTH }, angle: decal.angle, colour, radius: decal.radius }
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::particles::particle::Particle;
+
+    #[test]
+    fn test_skids_and_impacts() {
+        let mut particle_vec = ParticleVec::new();
+        particle_vec.push(*Particle::default().set_radius(0.1).set_static(true).set_pos(Vec2::new(0.0, 0.0)));
+        particle_vec.push(*Particle::default().set_radius(0.08).set_pos(Vec2::new(0.0, 0.18)).set_vel(Vec2::new(0.5, -6.0)));
+        let mut decals = Decals::new();
+
+        // landing hard leaves a scuff, staying on the ground doesn't leave another
+        decals.update(&[(1, 0)], &particle_vec, 0.005);
+        assert_eq!(decals.decals.len(), 1);
+        assert_eq!(decals.decals[0].radius, IMPACT_RADIUS);
+        decals.update(&[(1, 0)], &particle_vec, 0.005);
+        assert_eq!(decals.decals.len(), 1);
+
+        // sliding leaves skid marks, spaced out along the ground
+        particle_vec[1].vel = Vec2::new(5.0, 0.0);
+        decals.update(&[(1, 0)], &particle_vec, 0.005);
+        assert_eq!(decals.decals.len(), 1);
+        particle_vec[1].pos = Vec2::new(0.1, 0.18);
+        particle_vec.push(*Particle::default().set_radius(0.1).set_static(true).set_pos(Vec2::new(0.1, 0.0)));
+        decals.update(&[(1, 2)], &particle_vec, 0.005);
+        assert_eq!(decals.decals.len(), 2);
+        assert_eq!(decals.decals[1].radius, SKID_RADIUS);
+
+        // then fade away
+        decals.update(&[], &particle_vec, FADE_TIME);
+        assert!(decals.decals.is_empty());
+    }
+}
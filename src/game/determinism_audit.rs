@@ -0,0 +1,242 @@
+use std::fmt;
+
+use crate::{
+    game::headless_run::{HeadlessRun, TIME_DELTA},
+    simulation::particles::simulation::{Simulation, SolverGroup},
+};
+
+pub const DEFAULT_AUDIT_FRAMES: u32 = 2000;
+
+/// A step of the game update that can change particle state
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuditStage {
+    PreSolve,
+    Solve { iteration: i32, group: SolverGroup },
+    Elevators { iteration: i32 },
+    Quarantine { iteration: i32 },
+    PostSolve,
+    Entities,
+}
+
+impl fmt::Display for AuditStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditStage::PreSolve => write!(f, "pre solve"),
+            AuditStage::Solve { iteration, group } => write!(f, "solve iteration {} {:?} constraints", iteration, group),
+            AuditStage::Elevators { iteration } => write!(f, "solve iteration {} elevator constraints", iteration),
+            AuditStage::Quarantine { iteration } => write!(f, "solve iteration {} quarantine", iteration),
+            AuditStage::PostSolve => write!(f, "post solve"),
+            AuditStage::Entities => write!(f, "entity update"),
+        }
+    }
+}
+
+/// The stages of one solver iteration, in the same order as Simulation::solve_adaptive
+fn iteration_stages(iteration: i32) -> Vec<AuditStage> {
+    let mut stages: Vec<AuditStage> = SolverGroup::ALL.iter().map(|group| AuditStage::Solve { iteration, group: *group }).collect();
+    stages.push(AuditStage::Elevators { iteration });
+    stages.push(AuditStage::Quarantine { iteration });
+    stages
+}
+
+/// Where two runs of the same seed first disagreed
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    pub frame: u32,
+    pub stage: AuditStage,
+    pub particle: Option<usize>, // first particle whose state differs, None if only the particle count differs
+    pub touched_by: Vec<String>, // constraints and entities that reference that particle
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Runs diverged at frame {} during {}", self.frame, self.stage)?;
+        match self.particle {
+            Some(particle) => {
+                write!(f, ", first divergent particle {}", particle)?;
+                if self.touched_by.is_empty() {
+                    write!(f, " (not referenced by any constraint or entity)")
+                } else {
+                    write!(f, " touched by: {}", self.touched_by.join(", "))
+                }
+            }
+            None => write!(f, ", particle counts differ"),
+        }
+    }
+}
+
+/// Step a single stage, see HeadlessRun::step for the whole frame
+fn run_stage(run: &mut HeadlessRun, stage: AuditStage) {
+    match stage {
+        AuditStage::PreSolve => {
+            run.simulation.pre_solve(TIME_DELTA);
+            run.entity_system.elevator_entity_system.update_counts(&mut run.simulation);
+        }
+        AuditStage::Solve { group, .. } => run.simulation.solve_group(group, TIME_DELTA),
+        AuditStage::Elevators { .. } => run.entity_system.elevator_entity_system.solve_constraints(&mut run.simulation, TIME_DELTA),
+        AuditStage::Quarantine { .. } => run.simulation.quarantine_non_finite(true),
+        AuditStage::PostSolve => run.simulation.post_solve(TIME_DELTA),
+        AuditStage::Entities => run.update_entities(),
+    }
+}
+
+/// Constraints and entities that reference a particle, to narrow down what moved it
+fn touched_by(run: &HeadlessRun, index: usize) -> Vec<String> {
+    let sim = &run.simulation;
+    let mut touched_by = Vec::new();
+    for (i, c) in sim.distance_constraints.0.iter().enumerate() {
+        if c.i1 == index || c.i2 == index {
+            touched_by.push(format!("distance constraint {}", i));
+        }
+    }
+    for (i, c) in sim.spring_constraints.0.iter().enumerate() {
+        if c.i1 == index || c.i2 == index {
+            touched_by.push(format!("spring constraint {}", i));
+        }
+    }
+    for (i, c) in sim.volume_constraints.0.iter().enumerate() {
+        if c.particle_indices.contains(&index) {
+            touched_by.push(format!("volume constraint {}", i));
+        }
+    }
+    for (i, c) in sim.global_standard_total_fluid_constraints.0.iter().enumerate() {
+        if c.ps.contains(&index) {
+            touched_by.push(format!("fluid constraint {}", i));
+        }
+    }
+    for (i, c) in sim.global_standard_gas_constraints.0.iter().enumerate() {
+        if c.ps.contains(&index) {
+            touched_by.push(format!("gas constraint {}", i));
+        }
+    }
+    for (i, c) in sim.kinematic_constraints.0.iter().enumerate() {
+        if c.particle_indices.contains(&index) {
+            touched_by.push(format!("kinematic constraint {}", i));
+        }
+    }
+    for (i, c) in sim.contact_rigid_contact_constraints.0.iter().enumerate() {
+        if c.i1 == index || c.i2 == index {
+            touched_by.push(format!("rigid contact {} ({} - {})", i, c.i1, c.i2));
+        }
+    }
+    for (i, c) in sim.contact_contact_constraints.0.iter().enumerate() {
+        if c.i1 == index || c.i2 == index {
+            touched_by.push(format!("contact {} ({} - {})", i, c.i1, c.i2));
+        }
+    }
+    for (i, c) in sim.contact_boundary_constraints.0.iter().enumerate() {
+        if c.index == index {
+            touched_by.push(format!("boundary constraint {}", i));
+        }
+    }
+    if let Some(particle) = sim.particles.0.get(index) {
+        if particle.body >= 0 {
+            touched_by.push(format!("body {}", particle.body));
+        }
+    }
+    for (i, car) in run.entity_system.car_entity_system.0.iter().enumerate() {
+        if car.particle_handles().any(|handle| handle == index) {
+            touched_by.push(format!("car {}", i));
+        }
+    }
+    if let Some(i) = run.entity_system.elevator_entity_system.elevator_for_particle(index) {
+        touched_by.push(format!("elevator {}", i));
+    }
+    touched_by
+}
+
+/// Index of the first particle whose state differs bit for bit
+fn first_divergent_particle(a: &Simulation, b: &Simulation) -> Option<usize> {
+    a.particles.0.iter().zip(b.particles.0.iter()).position(|(pa, pb)| {
+        [pa.pos.x, pa.pos.y, pa.pos_guess.x, pa.pos_guess.y, pa.vel.x, pa.vel.y].map(f32::to_bits)
+            != [pb.pos.x, pb.pos.y, pb.pos_guess.x, pb.pos_guess.y, pb.vel.x, pb.vel.y].map(f32::to_bits)
+    })
+}
+
+/// Step both runs through a stage and compare them
+fn run_stage_in_lockstep(a: &mut HeadlessRun, b: &mut HeadlessRun, frame: u32, stage: AuditStage) -> Result<(), Divergence> {
+    run_stage(a, stage);
+    run_stage(b, stage);
+    if a.simulation.state_checksum() != b.simulation.state_checksum() {
+        let particle = first_divergent_particle(&a.simulation, &b.simulation);
+        let touched_by = particle.map(|index| touched_by(a, index)).unwrap_or_default();
+        return Err(Divergence { frame, stage, particle, touched_by });
+    }
+    Ok(())
+}
+
+/// One frame in the same order as Game::step_simulation followed by the entity update.
+/// The runs match after every stage, so the solver stops after the same number of iterations in both.
+fn run_frame_in_lockstep(a: &mut HeadlessRun, b: &mut HeadlessRun, frame: u32) -> Result<(), Divergence> {
+    run_stage_in_lockstep(a, b, frame, AuditStage::PreSolve)?;
+
+    let settings = a.simulation.solver_settings;
+    let mut guesses = Vec::new();
+    let mut iteration = 0;
+    loop {
+        a.simulation.guess_positions(&mut guesses);
+        for stage in iteration_stages(iteration) {
+            run_stage_in_lockstep(a, b, frame, stage)?;
+        }
+        iteration += 1;
+        if settings.converged(iteration, a.simulation.max_correction(&guesses)) {
+            break;
+        }
+    }
+
+    run_stage_in_lockstep(a, b, frame, AuditStage::PostSolve)?;
+    run_stage_in_lockstep(a, b, frame, AuditStage::Entities)
+}
+
+/// Run today's level twice in lockstep, comparing checksums after every stage of every frame.
+/// Returns the first divergence, or None if the runs matched for all frames.
+pub fn run_audit(num_frames: u32) -> Option<Divergence> {
+    let mut a = HeadlessRun::new();
+    let mut b = HeadlessRun::new();
+
+    for frame in 0..num_frames {
+        a.apply_bot_input(frame);
+        b.apply_bot_input(frame);
+        if let Err(divergence) = run_frame_in_lockstep(&mut a, &mut b, frame) {
+            return Some(divergence);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_iteration_stages() {
+        let stages = iteration_stages(2);
+        assert_eq!(stages.len(), SolverGroup::ALL.len() + 2);
+        assert_eq!(stages.first(), Some(&AuditStage::Solve { iteration: 2, group: SolverGroup::Kinematic }));
+        assert_eq!(stages[stages.len() - 2], AuditStage::Elevators { iteration: 2 });
+        assert_eq!(stages.last(), Some(&AuditStage::Quarantine { iteration: 2 }));
+    }
+
+    // stepping stage by stage has to end up in the same place as HeadlessRun::step
+    #[test]
+    fn test_lockstep_frame_matches_step() {
+        let mut a = HeadlessRun::new();
+        let mut b = HeadlessRun::new();
+        let mut c = HeadlessRun::new();
+        for frame in 0..20 {
+            a.apply_bot_input(frame);
+            b.apply_bot_input(frame);
+            c.apply_bot_input(frame);
+            run_frame_in_lockstep(&mut a, &mut b, frame).unwrap();
+            c.step();
+            assert_eq!(a.simulation.state_checksum(), c.simulation.state_checksum());
+        }
+    }
+
+    #[test]
+    fn test_short_run_is_deterministic() {
+        if let Some(divergence) = run_audit(50) {
+            panic!("{}", divergence);
+        }
+    }
+}
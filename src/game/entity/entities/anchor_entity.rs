@@ -0,0 +1,44 @@
+use crate::{core::math::vec2::Vec2, simulation::particles::particle_vec::{ParticleHandle, ParticleVec}};
+
+/// A fixed point the car's winch can fire a rope at, placed by level blocks.
+/// The position comes from its particle, so it's mirrored along with the rest of the level.
+#[derive(Clone)]
+pub struct AnchorEntity {
+    pub particle_handle: ParticleHandle,
+    pub range: f32, // how close the car has to be to reach it, metres
+}
+
+impl AnchorEntity {
+    pub fn new(particle_handle: ParticleHandle, range: f32) -> Self {
+        Self {
+            particle_handle,
+            range,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct AnchorEntitySystem {
+    pub entities: Vec<AnchorEntity>,
+}
+
+impl AnchorEntitySystem {
+    pub fn new() -> Self {
+        Self {
+            entities: vec![],
+        }
+    }
+
+    pub fn push(&mut self, entity: AnchorEntity) {
+        self.entities.push(entity);
+    }
+
+    /// The closest anchor that can reach `pos`, if any
+    pub fn nearest_in_range(&self, pos: Vec2, particles: &ParticleVec) -> Option<&AnchorEntity> {
+        self.entities.iter()
+            .map(|anchor| (anchor, (particles[anchor.particle_handle].pos - pos).magnitude()))
+            .filter(|(anchor, distance)| *distance <= anchor.range)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(anchor, _)| anchor)
+    }
+}
@@ -1,22 +1,68 @@
-// use winit::keyboard::KeyCode;
+use crate::core::math::{aabb2d::Aabb2d, vec2::Vec2};
 
-// use crate::game::entity::entity::{Entity, UpdateContext};
+/// A fixed trackside camera, placed by the level builder for each level block (or by the block itself).
+/// It stays where it is and turns to follow the car while the car is in the part of the level it covers, see CameraMode::Trackside.
+#[derive(Clone)]
+pub struct CameraEntity {
+    pub eye: Vec2, // where it stands in the level
+    pub distance: f32, // how far back from the level it stands, the eye's z
+    pub aabb: Aabb2d, // the part of the level it watches
+}
 
-// pub struct CameraEntity {
-// }
+impl CameraEntity {
+    pub const HEIGHT: f32 = 2.0; // metres above a block a default camera stands
+    pub const MIN_DISTANCE: f32 = 8.0;
 
-// impl CameraEntity {
-//     pub fn new() -> Self {
-//         Self {
-//         }
-//     }
-// }
+    pub fn new(eye: Vec2, distance: f32, aabb: Aabb2d) -> Self {
+        Self {
+            eye,
+            distance,
+            aabb,
+        }
+    }
 
-// impl Entity for CameraEntity {
-//     fn update(&mut self, context: &mut UpdateContext) {
-//         // todo:
-//     }
+    /// A camera standing over the middle of a block, far enough back to see most of it
+    pub fn for_block(aabb: &Aabb2d) -> Self {
+        let centre = (aabb.min + aabb.max) * 0.5;
+        let width = aabb.max.x - aabb.min.x;
+        Self::new(Vec2::new(centre.x, aabb.max.y + Self::HEIGHT), (width * 0.6).max(Self::MIN_DISTANCE), aabb.clone())
+    }
 
-//     // ughly!
-//     fn handle_key(&mut self, key: KeyCode, is_pressed: bool) -> bool { false }
-// }
\ No newline at end of file
+    /// How far pos is outside the area this camera watches, 0 inside it
+    fn distance_to(&self, pos: Vec2) -> f32 {
+        let closest = Vec2::new(pos.x.clamp(self.aabb.min.x, self.aabb.max.x), pos.y.clamp(self.aabb.min.y, self.aabb.max.y));
+        (pos - closest).magnitude()
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct CameraEntitySystem {
+    pub entities: Vec<CameraEntity>,
+}
+
+impl CameraEntitySystem {
+    pub fn new() -> Self {
+        Self {
+            entities: vec![],
+        }
+    }
+
+    pub fn push(&mut self, entity: CameraEntity) {
+        self.entities.push(entity);
+    }
+
+    pub fn mirror_x(&mut self) {
+        for e in self.entities.iter_mut() {
+            e.eye.x = -e.eye.x;
+            e.aabb = e.aabb.mirror_x();
+        }
+    }
+
+    /// The camera that should be watching pos: the first whose area it's in, or the nearest one if it's in none of them
+    pub fn watching(&self, pos: Vec2) -> Option<usize> {
+        self.entities.iter().enumerate()
+            .map(|(i, e)| (i, e.distance_to(pos)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i)
+    }
+}
@@ -1,11 +1,53 @@
-use crate::{core::math::{unit_conversions::cm_to_m, vec2::Vec2, vec4::Vec4}, engine::app::event_system::KeyCodeType, game::{entity::{entities::finish_entity::FinishEntitySystem, entity_system::UpdateContext}}, simulation::{constraints::{spring_constraint::SpringConstraint, volume_constraint::VolumeConstraint}, particles::{particle::Particle, particle_manipulator::ParticleManipulator, particle_vec::{ParticleHandle, ParticleVec}, shape_builder::{adjacent_sticks::AdjacentSticks, circle::{Circle, SpaceDistribution}, shape_builder::ShapeBuilder}, simulation::Simulation}}};
+use std::collections::{HashMap, HashSet};
+
+use crate::{core::math::{unit_conversions::cm_to_m, vec2::Vec2, vec4::Vec4}, engine::app::event_system::{GamepadAxisType, KeyCodeType}, game::{entity::{entities::{anchor_entity::AnchorEntitySystem, finish_entity::FinishEntitySystem, winch::Winch}, entity_system::UpdateContext, gameplay_events::GameplayEvent}}, simulation::{constraints::{spring_constraint::SpringConstraint, volume_constraint::VolumeConstraint}, particles::{activity_region::ActivityRegion, particle::Particle, particle_manipulator::ParticleManipulator, particle_vec::{ParticleHandle, ParticleVec}, shape_builder::{adjacent_sticks::AdjacentSticks, circle::{Circle, SpaceDistribution}, shape_builder::ShapeBuilder}, simulation::Simulation}}};
+
+const HUB_PARTICLE_RADIUS: f32 = cm_to_m(6.0);
+const SURFACE_CIRCLE_RADIUS: f32 = cm_to_m(35.0); // around a typical car tyre size - 17-18" (once you account for particle radius)
+const SURFACE_PARTICLE_RADIUS: f32 = cm_to_m(8.0);
+
+pub const PEAK_SLIP: f32 = 0.2; // tyres grip best slipping a little, past this the drive starts to fade
+pub const SPINNING_SLIP: f32 = 0.6; // by here the tyre is spinning freely
+const SPINNING_TRACTION: f32 = 0.4; // fraction of the drive that still reaches the ground when spinning freely
+const MIN_SLIP_SPEED: f32 = 0.5; // m/s, slip is never measured against a slower speed than this so creeping along isn't all slip
+const AIR_SPIN_ACCELERATION: f32 = 6.0; // rad/s^2 from leaning in the air
+const MAX_AIR_SPIN: f32 = 3.0; // rad/s, leaning won't turn the car any faster than this
+pub const STANDARD_CAR: &str = "standard"; // the only car so far, its name goes with times on the leaderboard
+const MAX_SWELL: f32 = 0.15; // fraction the tyre's surface particles grow by on the softest ground
+const MAX_SHRINK: f32 = 0.1; // and shrink by on the hardest
+const RADIUS_RATE: f32 = 4.0; // 1/s, how quickly the particles ease towards the size the ground under them wants
+const GRANULAR_SOFTNESS: f32 = 0.5; // loose grains like sand count as this soft, see SurfaceMaterial::softness
+const CRASH_SPEED: f32 = 8.0; // m/s a new contact with the level has to close at to count as a crash
+const CRASH_COOLDOWN: f32 = 2.0; // seconds after a crash before another is reported
+const SLIP_SMOOTHING: f32 = 0.2; // how far slip moves towards each new measurement, contacts come and go every step on bumpy ground
+
+/// How much of the drive torque reaches the ground at a given slip. Holding the throttle down on a steep hill spins the
+/// wheels and loses drive, easing off to keep the slip near the peak climbs better.
+pub fn traction(slip: f32) -> f32 {
+    let t = ((slip - PEAK_SLIP) / (SPINNING_SLIP - PEAK_SLIP)).clamp(0.0, 1.0);
+    1.0 + (SPINNING_TRACTION - 1.0) * t
+}
 
+/// How soft the ground particle is for the tyres, from its surface material or as a grain of loose ground
+fn ground_softness(sim: &Simulation, ground_handle: ParticleHandle) -> f32 {
+    let softness = sim.materials.get(sim.particles[ground_handle].material).softness;
+    if sim.granular_terrain.contains(ground_handle) {
+        softness.max(GRANULAR_SOFTNESS)
+    } else {
+        softness
+    }
+}
+
+#[derive(Clone)]
 pub struct CarWheel {
     hub_particle_handle: ParticleHandle,
     surface_particle_handles: Vec<ParticleHandle>,
     spring_constraint_ids: Vec<usize>,
     volume_constraint_ids: Vec<usize>,
     surface_constraint_ids: Vec<usize>,
+    pub slip: f32, // wheelspin, 0 rolling cleanly (or turning slower than the ground goes by) to 1 spinning on the spot
+    pub spin: f32, // how fast the tyre turned around the hub at the last update, rad/s ccw
+    pub radius_scale: f32, // of the surface particles against their built size, see update_radius
 }
 
 impl CarWheel {
@@ -15,7 +57,7 @@ impl CarWheel {
         // wheel hub - this is on mask layer zero which is a special no collisions layer
         let hub_particle_handle = {
             //let mask = 0x0;
-            let particle_radius = cm_to_m(6.0);
+            let particle_radius = HUB_PARTICLE_RADIUS;
             let mut builder = ShapeBuilder::from_particle_template(
                 Particle::default().set_mass(particle_mass).set_radius(particle_radius).set_colour(Vec4::GREEN).clone()
             );
@@ -29,8 +71,8 @@ impl CarWheel {
         let (surface_particle_handles, surface_constraint_ids) = {
             //let mask = 0x1;
             //let divisions = 20;
-            let circle_radius = cm_to_m(35.0); // around a typical car tyre size - 17-18" (once you account for particle radius)
-            let particle_radius = cm_to_m(8.0);
+            let circle_radius = SURFACE_CIRCLE_RADIUS;
+            let particle_radius = SURFACE_PARTICLE_RADIUS;
             
             let mut particle_template = Particle::default().set_mass(particle_mass).set_radius(particle_radius).set_colour(Vec4::GREEN).clone();
             particle_template.k_friction = 0.9;
@@ -71,13 +113,16 @@ impl CarWheel {
             spring_constraint_ids,
             volume_constraint_ids,
             surface_constraint_ids,
+            slip: 0.0,
+            spin: 0.0,
+            radius_scale: 1.0,
         }
     }
 
     fn rotate(&mut self, direction: f32, particle_vec: &mut ParticleVec) {
         let hub_particle = particle_vec[self.hub_particle_handle];
         let centre = hub_particle.pos;
-        let torque = 0.05; // Nm
+        let torque = 0.05 * traction(self.slip); // Nm
 
         let particle_manipulator = ParticleManipulator::new();
 
@@ -86,6 +131,67 @@ impl CarWheel {
         particle_manipulator.add_torque_around_point(particle_vec, &self.surface_particle_handles, centre, torque * direction);
     }
 
+    /// Compare how fast the tyre surface is turning with how fast the ground under it is going by.
+    /// ground_contacts maps surface particles touching the ground to a ground particle they touch. Settles back to 0 in the air.
+    fn update_slip(&mut self, ground_contacts: &HashMap<ParticleHandle, ParticleHandle>, particle_vec: &ParticleVec) {
+        let hub = &particle_vec[self.hub_particle_handle];
+        let mut spin = 0.0; // rad/s ccw
+        let mut contact_dir = Vec2::zero();
+        let mut contact_radius = 0.0;
+        let mut ground_vel = Vec2::zero();
+        let mut num_contacts = 0;
+        for particle_handle in &self.surface_particle_handles {
+            let particle = &particle_vec[*particle_handle];
+            let r = particle.pos - hub.pos;
+            let v = particle.vel - hub.vel;
+            spin += (r.x * v.y - r.y * v.x) / r.dot(r);
+            if let Some(ground_handle) = ground_contacts.get(particle_handle) {
+                contact_dir += r;
+                contact_radius += r.magnitude();
+                ground_vel += particle_vec[*ground_handle].vel;
+                num_contacts += 1;
+            }
+        }
+
+        spin /= self.surface_particle_handles.len() as f32;
+        self.spin = spin;
+
+        let slip = if num_contacts == 0 {
+            0.0
+        } else {
+            let contact_dir = contact_dir.normalize();
+            let tangent = Vec2::new(-contact_dir.y, contact_dir.x); // the way the car rolls when the wheel turns clockwise
+            let ground_speed = (hub.vel - ground_vel / num_contacts as f32).dot(tangent);
+            // the tyre squashes where it touches, so it rolls on less than its full radius
+            let wheel_speed = -spin * contact_radius / num_contacts as f32;
+            // only the tyre running ahead of the ground counts, a wheel turning slower than the car is going wants more drive not less
+            let spin_excess = (wheel_speed - ground_speed) * wheel_speed.signum();
+            (spin_excess / wheel_speed.abs().max(ground_speed.abs()).max(MIN_SLIP_SPEED)).clamp(0.0, 1.0)
+        };
+        self.slip += (slip - self.slip) * SLIP_SMOOTHING;
+    }
+
+    /// Swell the tyre into soft ground and shrink it on hard, for more or less grip. The surface particles ease towards a
+    /// target radius from the average softness of the ground they touch, and hold their size in the air.
+    fn update_radius(&mut self, ground_contacts: &HashMap<ParticleHandle, ParticleHandle>, sim: &mut Simulation, time_delta: f32) {
+        let softnesses: Vec<f32> = self.surface_particle_handles.iter()
+            .filter_map(|particle_handle| ground_contacts.get(particle_handle))
+            .map(|&ground_handle| ground_softness(sim, ground_handle))
+            .collect();
+        if softnesses.is_empty() {
+            return;
+        }
+        let softness = (softnesses.iter().sum::<f32>() / softnesses.len() as f32).clamp(-1.0, 1.0);
+        let target = 1.0 + softness * if softness > 0.0 { MAX_SWELL } else { MAX_SHRINK };
+        if self.radius_scale == target {
+            return; // ordinary ground, nothing changes
+        }
+        self.radius_scale += (target - self.radius_scale) * (RADIUS_RATE * time_delta).min(1.0);
+        for particle_handle in &self.surface_particle_handles {
+            sim.particles[*particle_handle].radius = SURFACE_PARTICLE_RADIUS * self.radius_scale;
+        }
+    }
+
     fn disable_constraints(&mut self, sim: &mut Simulation) {
         for &id in &self.spring_constraint_ids {
             sim.spring_constraints.0[id].enabled = false;
@@ -97,16 +203,51 @@ impl CarWheel {
             sim.distance_constraints.0[id].enabled = false;
         }
     }
+
+    fn particle_handles(&self) -> impl Iterator<Item = &ParticleHandle> {
+        std::iter::once(&self.hub_particle_handle).chain(self.surface_particle_handles.iter())
+    }
+
+    /// Where the hub is and how far the wheel has turned, going by the first surface particle
+    pub fn pose(&self, particle_vec: &ParticleVec) -> (Vec2, f32) {
+        let hub = particle_vec[self.hub_particle_handle].pos;
+        let surface = particle_vec[self.surface_particle_handles[0]].pos - hub;
+        (hub, surface.y.atan2(surface.x))
+    }
+
+    /// Offset from the hub and radius of each particle of an undeformed wheel with a pose angle of zero, hub first
+    pub fn shape() -> Vec<(Vec2, f32)> {
+        let mut builder = ShapeBuilder::from_particle_template(*Particle::default().set_radius(SURFACE_PARTICLE_RADIUS));
+        builder.apply_operation(Circle::new(Vec2::zero(), SURFACE_CIRCLE_RADIUS, SpaceDistribution::SpaceBetweenParticles));
+
+        let first = builder.particles[0].pos;
+        let angle = first.y.atan2(first.x);
+        std::iter::once((Vec2::zero(), HUB_PARTICLE_RADIUS))
+            .chain(builder.particles.iter().map(|p| (Vec2::rotate_rad(p.pos, -angle), p.radius)))
+            .collect()
+    }
 }
 
 const NUM_WHEELS: usize = 2;
 
+#[derive(Clone)]
 pub struct CarEntity {
     pub wheels: [CarWheel; NUM_WHEELS],
     is_left_pressed: bool,
     is_right_pressed: bool,
+    is_lean_left_pressed: bool,
+    is_lean_right_pressed: bool,
+    throttle: f32, // gamepad right trigger, 0 to 1
+    reverse: f32, // gamepad left trigger, 0 to 1
+    lean_stick: f32, // gamepad left stick, -1 (left) to 1 (right)
     axle_constraint_id: usize,
     pub game_ended: bool,
+    spawn_origin: Vec2,
+    pub num_laps: u32,
+    pub lap_times: Vec<f32>,
+    lap_start_time: f32,
+    pub winch: Winch,
+    last_crash_time: Option<f32>, // so one crash bouncing about isn't reported over and over
 }
 
 impl CarEntity {
@@ -126,13 +267,53 @@ impl CarEntity {
             sim.add_spring_constraint(SpringConstraint::new(dist, 2000.0, wheel_1.hub_particle_handle, wheel_2.hub_particle_handle, false))
         };
 
-        Self {
+        let car = Self {
             wheels: [wheel_1, wheel_2],
             is_left_pressed: false,
             is_right_pressed: false,
+            is_lean_left_pressed: false,
+            is_lean_right_pressed: false,
+            throttle: 0.0,
+            reverse: 0.0,
+            lean_stick: 0.0,
             axle_constraint_id,
             game_ended: false,
+            spawn_origin: origin,
+            num_laps: 1,
+            lap_times: vec![],
+            lap_start_time: 0.0,
+            winch: Winch::default(),
+            last_crash_time: None,
+        };
+        car.update_activity_region(sim);
+        car
+    }
+
+    /// Surface particles that were touching something other than the car in the last simulation step,
+    /// each with the lowest index particle it touched so it comes out the same whatever order the pairs are in
+    fn ground_contacts(&self, sim: &Simulation) -> HashMap<ParticleHandle, ParticleHandle> {
+        let car_particles: HashSet<ParticleHandle> = self.particle_handles().collect();
+        let surface_particles: HashSet<ParticleHandle> = self.surface_particle_handles().collect();
+
+        let mut ground_contacts: HashMap<ParticleHandle, ParticleHandle> = HashMap::new();
+        for (i1, i2) in sim.contact_cache.pairs() {
+            let (surface, ground) = if surface_particles.contains(&i1) { (i1, i2) } else { (i2, i1) };
+            if !surface_particles.contains(&surface) || car_particles.contains(&ground) {
+                continue;
+            }
+            ground_contacts.entry(surface).and_modify(|g| *g = (*g).min(ground)).or_insert(ground);
         }
+        ground_contacts
+    }
+
+    /// The worst slip of the wheels on the ground
+    pub fn slip(&self) -> f32 {
+        self.wheels.iter().map(|wheel| wheel.slip).fold(0.0, f32::max)
+    }
+
+    /// How fast the faster wheel is turning either way, rad/s
+    pub fn wheel_spin(&self) -> f32 {
+        self.wheels.iter().map(|wheel| wheel.spin.abs()).fold(0.0, f32::max)
     }
 
     fn rotate_wheels(&mut self, direction: f32, particle_vec: &mut ParticleVec) {
@@ -153,47 +334,239 @@ impl CarEntity {
         pos
     }
 
-    fn update(&mut self, context: &mut UpdateContext, finish_entity_system: &FinishEntitySystem) {
+    /// Every particle that makes up the car
+    pub fn particle_handles(&self) -> impl Iterator<Item = ParticleHandle> + '_ {
+        self.wheels.iter().flat_map(|wheel| wheel.particle_handles().copied())
+    }
+
+    /// Only the wheel surface particles, these are what touch the ground
+    pub fn surface_particle_handles(&self) -> impl Iterator<Item = ParticleHandle> + '_ {
+        self.wheels.iter().flat_map(|wheel| wheel.surface_particle_handles.iter().copied())
+    }
+
+    /// Average velocity of the wheel hubs
+    pub fn velocity(&self, particle_vec: &ParticleVec) -> Vec2 {
+        let mut vel = Vec2::new(0.0, 0.0);
+        for wheel in self.wheels.iter() {
+            vel += particle_vec[wheel.hub_particle_handle].vel;
+        }
+        vel / NUM_WHEELS as f32
+    }
+
+    /// 1.0 when driving ccw, -1.0 when driving clockwise, 0.0 with no input.
+    /// The keys win over the gamepad triggers, which give anything in between.
+    pub fn input_direction(&self) -> f32 {
+        if !self.is_left_pressed && !self.is_right_pressed {
+            return self.trigger_direction();
+        }
+        let mut direction = 0.0;
+        if self.is_left_pressed {
+            direction += 1.0;
+        }
+        if self.is_right_pressed {
+            direction -= 1.0;
+        }
+        direction
+    }
+
+    /// The right trigger drives forward (clockwise), the left one backwards
+    fn trigger_direction(&self) -> f32 {
+        self.reverse - self.throttle
+    }
+
+    /// 1.0 when leaning ccw, -1.0 when leaning clockwise, 0.0 with no input.
+    /// The keys win over the gamepad stick.
+    pub fn lean_direction(&self) -> f32 {
+        if !self.is_lean_left_pressed && !self.is_lean_right_pressed {
+            return -self.lean_stick;
+        }
+        let mut direction = 0.0;
+        if self.is_lean_left_pressed {
+            direction += 1.0;
+        }
+        if self.is_lean_right_pressed {
+            direction -= 1.0;
+        }
+        direction
+    }
+
+    /// How fast the car is turning, from the wheel hubs. Radians per second ccw
+    fn spin(&self, particle_vec: &ParticleVec) -> f32 {
+        let hub_1 = &particle_vec[self.wheels[0].hub_particle_handle];
+        let hub_2 = &particle_vec[self.wheels[1].hub_particle_handle];
+        let axle = hub_1.pos - hub_2.pos;
+        let vel = hub_1.vel - hub_2.vel;
+        (axle.x * vel.y - axle.y * vel.x) / axle.dot(axle)
+    }
+
+    /// Turn the whole car while it's in the air so it can be lined up for the landing. The car is spun around its centre
+    /// without changing how it's moving, so leaning can't be used to fly or to push off the ground.
+    /// Every particle of the car weighs the same so the centre is the centre of mass.
+    fn lean(&mut self, ground_contacts: &HashMap<ParticleHandle, ParticleHandle>, particle_vec: &mut ParticleVec, time_delta: f32) {
+        let direction = self.lean_direction();
+        if direction == 0.0 || !ground_contacts.is_empty() {
+            return;
+        }
+
+        let spin_delta = (AIR_SPIN_ACCELERATION * time_delta).min(MAX_AIR_SPIN - self.spin(particle_vec) * direction);
+        if spin_delta <= 0.0 {
+            return;
+        }
+
+        let particle_handles: Vec<ParticleHandle> = self.particle_handles().collect();
+        let mut centre = Vec2::zero();
+        for particle_handle in &particle_handles {
+            centre += particle_vec[*particle_handle].pos;
+        }
+        centre /= particle_handles.len() as f32;
+
+        for particle_handle in &particle_handles {
+            let particle = &mut particle_vec[*particle_handle];
+            let r = particle.pos - centre;
+            particle.vel += Vec2::new(-r.y, r.x) * spin_delta * direction;
+        }
+    }
+
+    /// The lap currently being driven (1 based). Stays on the last lap once the game has ended.
+    pub fn current_lap(&self) -> u32 {
+        (self.lap_times.len() as u32 + 1).min(self.num_laps)
+    }
+
+    pub fn best_lap(&self) -> Option<f32> {
+        self.lap_times.iter().copied().reduce(f32::min)
+    }
+
+    /// Move the car back to where it spawned, keeping its shape but losing all its speed.
+    fn return_to_spawn(&mut self, sim: &mut Simulation) {
+        let offset = self.spawn_origin - self.get_camera_look_at_position(&sim.particles);
+        for wheel in &self.wheels {
+            for &particle_handle in wheel.particle_handles() {
+                let particle = &mut sim.particles[particle_handle];
+                let pos = particle.pos + offset;
+                particle.set_pos(pos).set_vel(Vec2::new(0.0, 0.0));
+                particle.pos_guess = pos;
+            }
+        }
+        self.update_activity_region(sim);
+    }
+
+    /// Only simulate the level at full rate around the car, see ActivityRegion
+    fn update_activity_region(&self, sim: &mut Simulation) {
+        let center = self.get_camera_look_at_position(&sim.particles);
+        sim.activity_region.get_or_insert_with(ActivityRegion::default).center = center;
+    }
+
+    /// Where the winch rope runs from and to, for drawing it
+    pub fn rope_ends(&self, particle_vec: &ParticleVec) -> Option<(Vec2, Vec2)> {
+        let rope = self.winch.rope.as_ref()?;
+        Some((particle_vec[rope.car_particle_handle].pos, particle_vec[rope.anchor_particle_handle].pos))
+    }
+
+    fn update(&mut self, context: &mut UpdateContext, finish_entity_system: &FinishEntitySystem, anchor_entity_system: &AnchorEntitySystem) {
         if self.game_ended {
             return;
         }
 
         // Apply input to wheels
+        let ground_contacts = self.ground_contacts(context.sim);
+        for wheel in self.wheels.iter_mut() {
+            wheel.update_slip(&ground_contacts, &context.sim.particles);
+            wheel.update_radius(&ground_contacts, context.sim, context.time_delta);
+        }
+        self.lean(&ground_contacts, &mut context.sim.particles, context.time_delta);
         if self.is_left_pressed {
             self.rotate_wheels(1.0, &mut context.sim.particles); // ccw
         }
         if self.is_right_pressed {
             self.rotate_wheels(-1.0, &mut context.sim.particles); // clockwise
         }
+        if !self.is_left_pressed && !self.is_right_pressed && self.trigger_direction() != 0.0 {
+            let direction = self.trigger_direction();
+            self.rotate_wheels(direction, &mut context.sim.particles);
+        }
+
+        // the rope is tied to a wheel hub so it pulls the whole wheel
+        let look_at_pos = self.get_camera_look_at_position(&context.sim.particles);
+        let hub_particle_handles = self.wheels.iter().map(|wheel| wheel.hub_particle_handle).collect::<Vec<_>>();
+        self.winch.update(context.sim, anchor_entity_system, look_at_pos, &hub_particle_handles, context.time_delta);
+
+        self.check_crash(context);
 
         // Update the camera to follow the car
-        let look_at_pos = self.get_camera_look_at_position(&mut context.sim.particles);
         context.camera.target = cgmath::Point3::new(look_at_pos.x, look_at_pos.y, 0.0);
+        self.update_activity_region(context.sim);
 
         // Check for finish 
         // - todo: check against wheel hub centres so we only need to check 2 points instead of every wheel surface
         //      and/or check against the simulation spatial partition.
+        let mut reached_finish = false;
         for finish_entity in &finish_entity_system.entities {
             for wheel in &self.wheels {
                 for particle_handle in &wheel.surface_particle_handles {
                     let particle = &context.sim.particles[*particle_handle];
                     if particle.pos.x >= finish_entity.aabb.min.x && particle.pos.x <= finish_entity.aabb.max.x &&
                        particle.pos.y >= finish_entity.aabb.min.y && particle.pos.y <= finish_entity.aabb.max.y {
-                        self.game_ended = true;
+                        reached_finish = true;
                     }
                 }
             }
+        }
 
-            if self.game_ended {
-                println!("Game Finished! Time: {:.2}s", context.total_time);
+        if !reached_finish {
+            return;
+        }
 
-                // Break the car apart!
-                context.sim.spring_constraints.0[self.axle_constraint_id].enabled = false;
-                for wheel in self.wheels.iter_mut() {
-                    wheel.disable_constraints(context.sim);
-                }
-            }
+        let lap_time = context.total_time - self.lap_start_time;
+        self.lap_times.push(lap_time);
+        self.lap_start_time = context.total_time;
+
+        if (self.lap_times.len() as u32) < self.num_laps {
+            println!("Lap {} of {}: {:.2}s", self.lap_times.len(), self.num_laps, lap_time);
+            context.events.push(GameplayEvent::LapCompleted { lap: self.lap_times.len(), lap_time });
+            self.return_to_spawn(context.sim);
+            return;
+        }
+
+        self.game_ended = true;
+        println!("Game Finished! Time: {:.2}s", context.total_time);
+        context.events.push(GameplayEvent::Finished { time: context.total_time });
+        self.winch.release(context.sim);
+
+        // Break the car apart!
+        context.sim.spring_constraints.0[self.axle_constraint_id].enabled = false;
+        for wheel in self.wheels.iter_mut() {
+            wheel.disable_constraints(context.sim);
+        }
+    }
+
+    /// Report the hardest new contact with the level this step if it was hard enough to count as a crash
+    fn check_crash(&mut self, context: &mut UpdateContext) {
+        if self.last_crash_time.is_some_and(|time| context.total_time - time < CRASH_COOLDOWN) {
+            return;
+        }
+        let car_particles: HashSet<ParticleHandle> = self.particle_handles().collect();
+        let hardest = context.sim.contact_events.iter()
+            .filter(|event| car_particles.contains(&event.i1) != car_particles.contains(&event.i2))
+            .map(|event| event.speed)
+            .fold(0.0, f32::max);
+        if hardest < CRASH_SPEED {
+            return;
         }
+        self.last_crash_time = Some(context.total_time);
+        let pos = self.get_camera_look_at_position(&context.sim.particles);
+        context.events.push(GameplayEvent::Crashed { pos, speed: hardest, time: context.total_time });
+    }
+
+    /// Take the held keys from another car, so restoring a save state doesn't leave the car spinning
+    pub fn copy_input_from(&mut self, other: &CarEntity) {
+        self.is_left_pressed = other.is_left_pressed;
+        self.is_right_pressed = other.is_right_pressed;
+        self.is_lean_left_pressed = other.is_lean_left_pressed;
+        self.is_lean_right_pressed = other.is_lean_right_pressed;
+        self.throttle = other.throttle;
+        self.reverse = other.reverse;
+        self.lean_stick = other.lean_stick;
+        self.winch.is_pressed = other.winch.is_pressed;
     }
 
     fn handle_key(&mut self, key: KeyCodeType, is_pressed: bool) -> bool {
@@ -206,13 +579,36 @@ impl CarEntity {
                 self.is_right_pressed = is_pressed;
                 true
             }
+            KeyCodeType::KeyQ => {
+                self.is_lean_left_pressed = is_pressed;
+                true
+            }
+            KeyCodeType::KeyE => {
+                self.is_lean_right_pressed = is_pressed;
+                true
+            }
+            KeyCodeType::KeyC => {
+                self.winch.is_pressed = is_pressed;
+                true
+            }
             _ => false,
         }
     }
+
+    fn handle_axis(&mut self, axis: GamepadAxisType, value: f32) -> bool {
+        match axis {
+            GamepadAxisType::RightTrigger => self.throttle = value.clamp(0.0, 1.0),
+            GamepadAxisType::LeftTrigger => self.reverse = value.clamp(0.0, 1.0),
+            GamepadAxisType::LeftStickX => self.lean_stick = value.clamp(-1.0, 1.0),
+            _ => return false,
+        }
+        true
+    }
 }
 
 
 
+#[derive(Clone)]
 pub struct CarEntitySystem(pub Vec<CarEntity>);
 
 impl CarEntitySystem {
@@ -224,9 +620,9 @@ impl CarEntitySystem {
         self.0.push(c);
     }
 
-    pub fn update(&mut self, context: &mut UpdateContext, finish_entity_system: &FinishEntitySystem) {
+    pub fn update(&mut self, context: &mut UpdateContext, finish_entity_system: &FinishEntitySystem, anchor_entity_system: &AnchorEntitySystem) {
         for e in self.0.iter_mut() {
-            e.update(context, finish_entity_system);
+            e.update(context, finish_entity_system, anchor_entity_system);
         }
     }
 
@@ -235,4 +631,120 @@ impl CarEntitySystem {
             e.handle_key(key, is_pressed);
         }
     }
+
+    pub fn handle_axis(&mut self, axis: GamepadAxisType, value: f32) {
+        for e in self.0.iter_mut() {
+            e.handle_axis(axis, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::math::random::Random, simulation::particles::surface_material::MaterialTable};
+
+    /// Set the wheel moving at vel while turning at spin (rad/s ccw), touching the ground at its lowest particle
+    fn move_wheel(wheel: &CarWheel, sim: &mut Simulation, vel: Vec2, spin: f32) -> HashMap<ParticleHandle, ParticleHandle> {
+        let hub_pos = sim.particles[wheel.hub_particle_handle].pos;
+        sim.particles[wheel.hub_particle_handle].vel = vel;
+        for &particle_handle in &wheel.surface_particle_handles {
+            let r = sim.particles[particle_handle].pos - hub_pos;
+            sim.particles[particle_handle].vel = vel + Vec2::new(-r.y, r.x) * spin;
+        }
+        let lowest = *wheel.surface_particle_handles.iter().min_by(|a, b| sim.particles[**a].pos.y.total_cmp(&sim.particles[**b].pos.y)).unwrap();
+        HashMap::from([(lowest, sim.particles.len() - 1)])
+    }
+
+    #[test]
+    fn test_wheel_slip() {
+        let mut sim = Simulation::new(Random::seed_from_str("slip"));
+        let mut particle_vec = ParticleVec::new();
+        let mut wheel = CarWheel::new(Vec2::new(0.0, 1.0), &mut particle_vec, &mut sim);
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(0.0, 0.0)).set_static(true));
+        let settle = |wheel: &mut CarWheel, ground_contacts: &HashMap<ParticleHandle, ParticleHandle>, particle_vec: &ParticleVec| {
+            for _ in 0..100 {
+                wheel.update_slip(ground_contacts, particle_vec);
+            }
+        };
+
+        // rolling along the ground
+        let ground_contacts = move_wheel(&wheel, &mut sim, Vec2::new(2.0, 0.0), -2.0 / SURFACE_CIRCLE_RADIUS);
+        settle(&mut wheel, &ground_contacts, &sim.particles);
+        assert!(wheel.slip < 0.05);
+        assert_eq!(traction(wheel.slip), 1.0);
+
+        // spinning on the spot
+        let ground_contacts = move_wheel(&wheel, &mut sim, Vec2::new(0.0, 0.0), -2.0 / SURFACE_CIRCLE_RADIUS);
+        settle(&mut wheel, &ground_contacts, &sim.particles);
+        assert!(wheel.slip > 0.95);
+        assert!((traction(wheel.slip) - SPINNING_TRACTION).abs() < 0.001);
+
+        // sliding along faster than the wheel turns isn't wheelspin
+        let ground_contacts = move_wheel(&wheel, &mut sim, Vec2::new(4.0, 0.0), -1.0 / SURFACE_CIRCLE_RADIUS);
+        settle(&mut wheel, &ground_contacts, &sim.particles);
+        assert!(wheel.slip < 0.05);
+
+        // and in the air it settles back to nothing
+        let ground_contacts = move_wheel(&wheel, &mut sim, Vec2::new(0.0, 0.0), -2.0 / SURFACE_CIRCLE_RADIUS);
+        settle(&mut wheel, &ground_contacts, &sim.particles);
+        settle(&mut wheel, &HashMap::new(), &sim.particles);
+        assert!(wheel.slip < 0.01);
+    }
+
+    #[test]
+    fn test_wheel_radius_follows_the_ground() {
+        let mut sim = Simulation::new(Random::seed_from_str("wheel radius"));
+        let mut particle_vec = ParticleVec::new();
+        let mut wheel = CarWheel::new(Vec2::new(0.0, 1.0), &mut particle_vec, &mut sim);
+        let ground = sim.particles.len();
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(0.0, 0.0)).set_static(true));
+        let radius = |sim: &Simulation, wheel: &CarWheel| sim.particles[wheel.surface_particle_handles[0]].radius;
+        let on_ground = |wheel: &CarWheel| wheel.surface_particle_handles.iter().take(3).map(|&handle| (handle, ground)).collect::<HashMap<_, _>>();
+
+        // plain ground leaves the tyre exactly as it was built
+        for _ in 0..100 {
+            wheel.update_radius(&on_ground(&wheel), &mut sim, 0.005);
+        }
+        assert_eq!(radius(&sim, &wheel), SURFACE_PARTICLE_RADIUS);
+
+        // swells into mud, and holds that in the air
+        sim.particles[ground].material = MaterialTable::MUD;
+        for _ in 0..1000 {
+            wheel.update_radius(&on_ground(&wheel), &mut sim, 0.005);
+        }
+        assert!((radius(&sim, &wheel) - SURFACE_PARTICLE_RADIUS * (1.0 + MAX_SWELL)).abs() < 1e-4);
+        wheel.update_radius(&HashMap::new(), &mut sim, 0.005);
+        assert!(radius(&sim, &wheel) > SURFACE_PARTICLE_RADIUS * (1.0 + MAX_SWELL * 0.99));
+
+        // shrinks on rock, easing there rather than jumping
+        sim.particles[ground].material = MaterialTable::ROCK;
+        wheel.update_radius(&on_ground(&wheel), &mut sim, 0.005);
+        assert!(radius(&sim, &wheel) > SURFACE_PARTICLE_RADIUS);
+        for _ in 0..1000 {
+            wheel.update_radius(&on_ground(&wheel), &mut sim, 0.005);
+        }
+        assert!((radius(&sim, &wheel) - SURFACE_PARTICLE_RADIUS * (1.0 - MAX_SHRINK)).abs() < 1e-4);
+        assert!(wheel.surface_particle_handles.iter().all(|&handle| sim.particles[handle].radius == radius(&sim, &wheel)));
+    }
+
+    #[test]
+    fn test_lean_only_turns_the_car_in_the_air() {
+        let mut sim = Simulation::new(Random::seed_from_str("lean"));
+        let mut particle_vec = ParticleVec::new();
+        let mut car = CarEntity::new(&mut particle_vec, &mut sim, Vec2::new(0.0, 1.0));
+        car.handle_key(KeyCodeType::KeyQ, true);
+
+        // on the ground nothing happens
+        let ground_contacts = HashMap::from([(car.wheels[0].surface_particle_handles[0], sim.particles.len())]);
+        car.lean(&ground_contacts, &mut sim.particles, 0.005);
+        assert_eq!(car.spin(&sim.particles), 0.0);
+
+        // in the air it spins up to the limit, without the car going anywhere
+        for _ in 0..1000 {
+            car.lean(&HashMap::new(), &mut sim.particles, 0.005);
+        }
+        assert!((car.spin(&sim.particles) - MAX_AIR_SPIN).abs() < 0.01);
+        assert!(car.velocity(&sim.particles).magnitude() < 0.001);
+    }
 }
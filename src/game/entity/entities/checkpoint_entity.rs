@@ -0,0 +1,61 @@
+use crate::{core::math::aabb2d::Aabb2d, game::entity::{entities::car_entity::CarEntitySystem, entity_system::UpdateContext, gameplay_events::GameplayEvent}};
+
+/// A gate the car drives through, placed by the level builder between blocks.
+#[derive(Clone)]
+pub struct CheckpointEntity {
+    pub aabb: Aabb2d,
+    pub reached: bool,
+}
+
+impl CheckpointEntity {
+    pub fn new(aabb: Aabb2d) -> Self {
+        Self {
+            aabb,
+            reached: false,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct CheckpointEntitySystem {
+    pub entities: Vec<CheckpointEntity>,
+}
+
+impl CheckpointEntitySystem {
+    pub fn new() -> Self {
+        Self {
+            entities: vec![],
+        }
+    }
+
+    pub fn push(&mut self, entity: CheckpointEntity) {
+        self.entities.push(entity);
+    }
+
+    pub fn mirror_x(&mut self) {
+        for e in self.entities.iter_mut() {
+            e.aabb = e.aabb.mirror_x();
+        }
+    }
+
+    pub fn num_reached(&self) -> usize {
+        self.entities.iter().filter(|e| e.reached).count()
+    }
+
+    pub fn update(&mut self, context: &mut UpdateContext, car_entity_system: &CarEntitySystem) {
+        for car in car_entity_system.0.iter() {
+            if car.game_ended {
+                continue;
+            }
+
+            let car_pos = car.get_camera_look_at_position(&context.sim.particles);
+            for (checkpoint, entity) in self.entities.iter_mut().enumerate() {
+                if !entity.reached && entity.aabb.contains_point(car_pos) {
+                    entity.reached = true;
+                    println!("Checkpoint reached! Time: {:.2}s", context.total_time);
+                    context.events.push(GameplayEvent::CheckpointReached { checkpoint, time: context.total_time });
+                }
+            }
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use crate::core::math::aabb2d::Aabb2d;
 
+#[derive(Clone)]
 pub struct FinishEntity {
     pub aabb: Aabb2d,
 }
@@ -12,6 +13,7 @@ impl FinishEntity {
     }
 }
 
+#[derive(Clone)]
 pub struct FinishEntitySystem {
     pub entities: Vec<FinishEntity>,
 }
@@ -26,4 +28,10 @@ impl FinishEntitySystem {
     pub fn push(&mut self, entity: FinishEntity) {
         self.entities.push(entity);
     }
+
+    pub fn mirror_x(&mut self) {
+        for e in self.entities.iter_mut() {
+            e.aabb = e.aabb.mirror_x();
+        }
+    }
 }
@@ -2,4 +2,7 @@ pub mod fixed_point_spring_vec_entity;
 pub mod camera_entity;
 pub mod car_entity;
 pub mod stick_vec_entity;
-pub mod finish_entity;
\ No newline at end of file
+pub mod finish_entity;
+pub mod checkpoint_entity;
+pub mod anchor_entity;
+pub mod winch;
\ No newline at end of file
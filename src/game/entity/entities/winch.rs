@@ -0,0 +1,110 @@
+use crate::{core::math::vec2::Vec2, game::entity::entities::anchor_entity::AnchorEntitySystem, simulation::{constraints::distance_constraint::DistanceConstraint, particles::{particle_vec::ParticleHandle, simulation::Simulation}}};
+
+/// A rope between the car and an anchor
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rope {
+    pub constraint_id: usize,
+    pub car_particle_handle: ParticleHandle,
+    pub anchor_particle_handle: ParticleHandle,
+}
+
+/// Fires a rope at the nearest anchor while the winch key is held and reels it in, letting go when the key is released.
+/// Ropes are only ever disabled, never removed, as other entities hold on to constraint ids. The next rope reuses the slot.
+#[derive(Clone, Debug, Default)]
+pub struct Winch {
+    pub is_pressed: bool,
+    pub rope: Option<Rope>,
+    constraint_slot: Option<usize>,
+}
+
+impl Winch {
+    pub const REEL_SPEED: f32 = 1.5; // m/s
+    pub const MIN_LENGTH: f32 = 1.0; // metres, stops the car being dragged into the anchor
+
+    /// `car_particle_handles` are the particles the rope can be tied to, the closest one to the anchor is used
+    pub fn update(&mut self, sim: &mut Simulation, anchors: &AnchorEntitySystem, car_pos: Vec2, car_particle_handles: &[ParticleHandle], time_delta: f32) {
+        if !self.is_pressed {
+            self.release(sim);
+            return;
+        }
+
+        if let Some(rope) = &self.rope {
+            let constraint = &mut sim.distance_constraints.0[rope.constraint_id];
+            constraint.d = (constraint.d - Self::REEL_SPEED * time_delta).max(Self::MIN_LENGTH);
+            return;
+        }
+
+        let Some(anchor) = anchors.nearest_in_range(car_pos, &sim.particles) else { return };
+        let anchor_pos = sim.particles[anchor.particle_handle].pos;
+        let Some(&car_particle_handle) = car_particle_handles.iter()
+            .min_by(|&&a, &&b| (sim.particles[a].pos - anchor_pos).magnitude().total_cmp(&(sim.particles[b].pos - anchor_pos).magnitude())) else { return };
+
+        let length = (sim.particles[car_particle_handle].pos - anchor_pos).magnitude();
+        let constraint = DistanceConstraint::rope(length, car_particle_handle, anchor.particle_handle);
+        let constraint_id = match self.constraint_slot {
+            Some(id) => {
+                sim.distance_constraints.0[id] = constraint;
+                id
+            }
+            None => sim.add_distance_constraint(constraint),
+        };
+        self.constraint_slot = Some(constraint_id);
+        self.rope = Some(Rope { constraint_id, car_particle_handle, anchor_particle_handle: anchor.particle_handle });
+    }
+
+    pub fn release(&mut self, sim: &mut Simulation) {
+        if let Some(rope) = self.rope.take() {
+            sim.distance_constraints.0[rope.constraint_id].enabled = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::math::random::Random, game::entity::entities::anchor_entity::AnchorEntity, simulation::particles::particle::Particle};
+
+    #[test]
+    fn test_winch_fires_reels_and_reuses_its_slot() {
+        let mut sim = Simulation::new(Random::seed_from_str("winch"));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(0.0, 0.0)).set_mass_2(1.0));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(0.0, 4.0)).set_static(true));
+        let mut anchors = AnchorEntitySystem::new();
+        anchors.push(AnchorEntity::new(1, 5.0));
+
+        let mut winch = Winch::default();
+        winch.update(&mut sim, &anchors, Vec2::new(0.0, 0.0), &[0], 0.1);
+        assert!(winch.rope.is_none());
+
+        winch.is_pressed = true;
+        winch.update(&mut sim, &anchors, Vec2::new(0.0, 0.0), &[0], 0.1);
+        let rope = winch.rope.clone().unwrap();
+        assert_eq!(sim.distance_constraints.0[rope.constraint_id].d, 4.0);
+
+        winch.update(&mut sim, &anchors, Vec2::new(0.0, 0.0), &[0], 0.1);
+        assert!(sim.distance_constraints.0[rope.constraint_id].d < 4.0);
+
+        winch.is_pressed = false;
+        winch.update(&mut sim, &anchors, Vec2::new(0.0, 0.0), &[0], 0.1);
+        assert!(winch.rope.is_none());
+        assert!(!sim.distance_constraints.0[rope.constraint_id].enabled);
+
+        winch.is_pressed = true;
+        winch.update(&mut sim, &anchors, Vec2::new(0.0, 0.0), &[0], 0.1);
+        assert_eq!(sim.distance_constraints.0.len(), 1);
+        assert!(sim.distance_constraints.0[rope.constraint_id].enabled);
+    }
+
+    #[test]
+    fn test_out_of_range_anchors_are_ignored() {
+        let mut sim = Simulation::new(Random::seed_from_str("winch"));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(0.0, 0.0)).set_mass_2(1.0));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(0.0, 10.0)).set_static(true));
+        let mut anchors = AnchorEntitySystem::new();
+        anchors.push(AnchorEntity::new(1, 5.0));
+
+        let mut winch = Winch { is_pressed: true, ..Winch::default() };
+        winch.update(&mut sim, &anchors, Vec2::new(0.0, 0.0), &[0], 0.1);
+        assert!(winch.rope.is_none());
+    }
+}
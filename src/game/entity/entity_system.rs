@@ -1,43 +1,373 @@
-use crate::{engine::app::{camera::Camera, event_system::KeyCodeType}, game::{entity::entities::{car_entity::CarEntitySystem, finish_entity::FinishEntitySystem}, level::level_blocks::elevator::ElevatorEntitySystem}, simulation::particles::{particle_vec::ParticleVec, simulation::Simulation}};
+use crate::{engine::app::{camera::Camera, event_system::{ElementStateType, GamepadInputType, KeyCodeType}}, game::{entity::{entities::{anchor_entity::AnchorEntitySystem, camera_entity::CameraEntitySystem, car_entity::CarEntitySystem, checkpoint_entity::CheckpointEntitySystem, finish_entity::FinishEntitySystem}, gameplay_events::GameplayEvent}, level::level_blocks::{boost_pad::BoostPadEntitySystem, elevator::ElevatorEntitySystem, moving_platform::MovingPlatformEntitySystem, teleporter::TeleporterEntitySystem, wind_zone::WindZoneEntitySystem}}, simulation::particles::{particle_vec::ParticleVec, simulation::Simulation}};
 
 pub struct UpdateContext<'a> {
     pub particle_vec: &'a mut ParticleVec,
     pub sim: &'a mut Simulation,
     pub time_delta: f32,
     pub total_time: f32,
-    pub camera: &'a mut Camera
+    pub camera: &'a mut Camera,
+    pub events: &'a mut Vec<GameplayEvent>,
 }
 
 
+#[derive(Clone)]
 pub struct EntitySystem {
     pub elevator_entity_system: ElevatorEntitySystem,
+    pub moving_platform_entity_system: MovingPlatformEntitySystem,
+    pub wind_zone_entity_system: WindZoneEntitySystem,
+    pub teleporter_entity_system: TeleporterEntitySystem,
+    pub boost_pad_entity_system: BoostPadEntitySystem,
     pub car_entity_system: CarEntitySystem,
     pub finish_entity_system: FinishEntitySystem,
+    pub checkpoint_entity_system: CheckpointEntitySystem,
+    pub anchor_entity_system: AnchorEntitySystem,
+    pub camera_entity_system: CameraEntitySystem,
+    pub events: Vec<GameplayEvent>, // what happened in the last update
 }
 
 impl EntitySystem {
     pub fn new() -> Self {
         Self {
             elevator_entity_system: ElevatorEntitySystem::new(),
+            moving_platform_entity_system: MovingPlatformEntitySystem::new(),
+            wind_zone_entity_system: WindZoneEntitySystem::new(),
+            teleporter_entity_system: TeleporterEntitySystem::new(),
+            boost_pad_entity_system: BoostPadEntitySystem::new(),
             car_entity_system: CarEntitySystem::new(),
             finish_entity_system: FinishEntitySystem::new(),
+            checkpoint_entity_system: CheckpointEntitySystem::new(),
+            anchor_entity_system: AnchorEntitySystem::new(),
+            camera_entity_system: CameraEntitySystem::new(),
+            events: vec![],
         }
     }
 
     pub fn update(&mut self, particle_vec: &mut ParticleVec, sim: &mut Simulation, camera: &mut Camera, time_delta: f32, total_time: f32) {
+        self.events.clear();
         let mut context = UpdateContext {
             time_delta,
             total_time,
             particle_vec,
             sim,
             camera,
+            events: &mut self.events,
         };
 
-        self.elevator_entity_system.update(&mut context);
-        self.car_entity_system.update(&mut context, &self.finish_entity_system);
+        self.elevator_entity_system.update(&mut context, &self.car_entity_system);
+        self.moving_platform_entity_system.update(&mut context);
+        self.wind_zone_entity_system.update(&mut context);
+        self.teleporter_entity_system.update(&mut context, &self.car_entity_system);
+        self.boost_pad_entity_system.update(&mut context, &self.car_entity_system);
+        self.car_entity_system.update(&mut context, &self.finish_entity_system, &self.anchor_entity_system);
+        self.checkpoint_entity_system.update(&mut context, &self.car_entity_system);
+    }
+
+    /// Mirror the level entities horizontally, used for mirror mode. Cars should be added after this.
+    pub fn mirror_x(&mut self) {
+        self.elevator_entity_system.mirror_x();
+        self.moving_platform_entity_system.mirror_x();
+        self.wind_zone_entity_system.mirror_x();
+        self.teleporter_entity_system.mirror_x();
+        self.boost_pad_entity_system.mirror_x();
+        self.finish_entity_system.mirror_x();
+        self.checkpoint_entity_system.mirror_x();
+        self.camera_entity_system.mirror_x();
     }
 
     pub fn handle_key(&mut self, key: KeyCodeType, pressed: bool) {
         self.car_entity_system.handle_key(key, pressed);
+        self.elevator_entity_system.handle_key(key, pressed);
+    }
+
+    /// Sticks and triggers drive the car, buttons do the same as the key they stand in for
+    pub fn handle_gamepad(&mut self, input: GamepadInputType) {
+        match input {
+            GamepadInputType::Axis { axis, value } => self.car_entity_system.handle_axis(axis, value),
+            GamepadInputType::Button { button, state } => {
+                if let Some(key) = button.key_equivalent() {
+                    self.handle_key(key, matches!(state, ElementStateType::Pressed));
+                }
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::math::{aabb2d::Aabb2d, random::Random, vec2::Vec2},
+        game::{
+            entity::entities::{car_entity::{self, CarEntity}, checkpoint_entity::CheckpointEntity, finish_entity::FinishEntity},
+            headless_run::{HeadlessRun, TIME_DELTA},
+            level::{level_blocks::{boost_pad::{BoostPadOperation, BoostPadParams}, breakable_bridge::{BreakableBridgeOperation, BreakableBridgeParams}, elevator::{ElevatorOperation, ElevatorParams}, moving_platform::{MovingPlatformOperation, MovingPlatformParams}, mud_pit::{MudPitOperation, MudPitParams}, teleporter::{PortalExit, TeleporterOperation, TeleporterParams}, wind_zone::{WindDirection, WindZoneOperation, WindZoneParams}}, level_builder::LevelBuilderContext, level_builder_operation::LevelBuilderOperation},
+        },
+        simulation::particles::{particle::Particle, shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}},
+    };
+
+    /// Flat static ground along y, like the level blocks build it
+    fn add_ground(run: &mut HeadlessRun, from_x: f32, to_x: f32, y: f32) {
+        ShapeBuilder::from_particle_template(*Particle::default().set_radius(0.1).set_static(true))
+            .apply_operation(LineSegment::new(Vec2::new(from_x, y), Vec2::new(to_x, y)))
+            .create_in_simulation(&mut run.simulation);
+    }
+
+    fn add_car(run: &mut HeadlessRun, pos: Vec2) {
+        let car = CarEntity::new(&mut run.particle_vec, &mut run.simulation, pos);
+        run.entity_system.car_entity_system.push(car);
+    }
+
+    /// A 3m wide elevator at the origin going 2m up with a stop half way, built the way the level builder does
+    fn add_elevator(run: &mut HeadlessRun) {
+        let mut rng = Random::seed_from_str("elevator");
+        let mut context = LevelBuilderContext::new(&mut run.entity_system, &mut run.particle_vec, &mut run.simulation, &mut rng);
+        let params = ElevatorParams { width: 3.0, height: 2.0, speed: 2.0, wait_time: 1.0, call_delay: 0.5, stops: vec![1.0] };
+        ElevatorOperation { params: Some(params) }.execute(&mut context);
+    }
+
+    fn car(run: &HeadlessRun) -> &CarEntity {
+        &run.entity_system.car_entity_system.0[0]
+    }
+
+    fn elevator_height(run: &HeadlessRun) -> f32 {
+        run.entity_system.elevator_entity_system.0[0].pos().y
     }
-}
\ No newline at end of file
+
+    /// Step until the condition holds, false if it doesn't within the time
+    fn step_until(run: &mut HeadlessRun, seconds: f32, condition: impl Fn(&HeadlessRun) -> bool) -> bool {
+        for _ in 0..(seconds / TIME_DELTA) as u32 {
+            if condition(run) {
+                return true;
+            }
+            run.step();
+        }
+        condition(run)
+    }
+
+    fn step_for(run: &mut HeadlessRun, seconds: f32) {
+        step_until(run, seconds, |_| false);
+    }
+
+    #[test]
+    fn test_elevator_reaches_its_stops() {
+        let mut run = HeadlessRun::empty();
+        add_ground(&mut run, -10.0, -0.2, 0.0);
+        add_elevator(&mut run);
+
+        // nobody on it, so it waits at the bottom
+        step_for(&mut run, 1.0);
+        assert_eq!(elevator_height(&run), 0.0);
+
+        // the car stops on the platform, which takes it to the middle stop and waits there for a choice
+        add_car(&mut run, Vec2::new(1.5, 1.0));
+        assert!(step_until(&mut run, 5.0, |run| elevator_height(run) == 1.0));
+        step_for(&mut run, 0.5);
+        assert_eq!(elevator_height(&run), 1.0);
+
+        // nobody picked a direction, so on up to the top
+        assert!(step_until(&mut run, 5.0, |run| elevator_height(run) == 2.0));
+        let car_pos = car(&run).get_camera_look_at_position(&run.simulation.particles);
+        assert!(car_pos.y > 2.0 && (car_pos.x - 1.5).abs() < 0.5);
+
+        // pressing down once the car has settled takes it back to the middle
+        step_for(&mut run, 2.0);
+        run.entity_system.handle_key(KeyCodeType::ArrowDown, true);
+        run.entity_system.handle_key(KeyCodeType::ArrowDown, false);
+        assert!(step_until(&mut run, 5.0, |run| elevator_height(run) == 1.0));
+    }
+
+    #[test]
+    fn test_moving_platform_carries_the_car() {
+        let mut run = HeadlessRun::empty();
+        add_ground(&mut run, -10.0, -0.2, 0.0);
+        let mut rng = Random::seed_from_str("moving platform");
+        let mut context = LevelBuilderContext::new(&mut run.entity_system, &mut run.particle_vec, &mut run.simulation, &mut rng);
+        let params = MovingPlatformParams { width: 3.0, distance: 2.0, vertical: true, period: 4.0 };
+        MovingPlatformOperation { params: Some(params) }.execute(&mut context);
+        add_car(&mut run, Vec2::new(1.5, 1.0));
+
+        // half a trip later the platform is up at the ledge, with the car still on it
+        step_for(&mut run, 2.0);
+        let platform = run.simulation.kinematic_constraints.0[0].target;
+        assert!((platform - Vec2::new(1.5, 2.0)).magnitude() < 0.01, "platform at {:?}", platform);
+        let first = run.simulation.kinematic_constraints.0[0].particle_indices[0];
+        assert!((run.simulation.particles[first].pos - Vec2::new(0.0, 2.0)).magnitude() < 0.01);
+        let car_pos = car(&run).get_camera_look_at_position(&run.simulation.particles);
+        assert!(car_pos.y > 2.0 && (car_pos.x - 1.5).abs() < 1.0, "car at {:?}", car_pos);
+
+        // and back down again
+        step_for(&mut run, 2.0);
+        assert!(run.simulation.kinematic_constraints.0[0].target.y < 0.01);
+    }
+
+    #[test]
+    fn test_breakable_bridge_gives_way_under_the_car() {
+        let mut run = HeadlessRun::empty();
+        let mut rng = Random::seed_from_str("breakable bridge");
+        let mut context = LevelBuilderContext::new(&mut run.entity_system, &mut run.particle_vec, &mut run.simulation, &mut rng);
+        let params = BreakableBridgeParams { width: 4.0, max_strain: 0.3 };
+        BreakableBridgeOperation { params: Some(params) }.execute(&mut context);
+        let intact = |run: &HeadlessRun| run.simulation.distance_constraints.0.iter().filter(|c| c.max_strain.is_some() && c.enabled).count();
+        let sticks = intact(&run);
+
+        // it holds up its own weight, bouncing as it first sags
+        step_for(&mut run, 2.0);
+        assert_eq!(intact(&run), sticks);
+
+        // but not the car's
+        add_car(&mut run, Vec2::new(2.0, 1.0));
+        assert!(step_until(&mut run, 2.0, |run| intact(run) < sticks));
+    }
+
+    #[test]
+    fn test_wind_zone_blows_the_car_back() {
+        let mut run = HeadlessRun::empty();
+        add_ground(&mut run, -10.0, -0.2, 0.0);
+        let mut rng = Random::seed_from_str("wind zone");
+        let mut context = LevelBuilderContext::new(&mut run.entity_system, &mut run.particle_vec, &mut run.simulation, &mut rng);
+        let params = WindZoneParams { width: 8.0, height: 3.0, direction: WindDirection::Headwind, strength: 5.0 };
+        WindZoneOperation { params: Some(params) }.execute(&mut context);
+        add_car(&mut run, Vec2::new(6.0, 0.6));
+
+        // without any input the car rolls back against the direction of travel
+        step_for(&mut run, 1.0);
+        let car_pos = car(&run).get_camera_look_at_position(&run.simulation.particles);
+        assert!(car_pos.x < 5.5, "car at {:?}", car_pos);
+
+        // the streaks drift the same way and stay in the zone
+        let zone = &run.entity_system.wind_zone_entity_system.0[0];
+        assert_eq!(zone.direction(), Vec2::new(-1.0, 0.0));
+        assert!(zone.streaks.iter().all(|streak| zone.region.contains_point(*streak)));
+    }
+
+    #[test]
+    fn test_mud_pit_slows_the_car() {
+        // the same drive over flat ground and through the mud
+        let drive = |mud: bool| {
+            let mut run = HeadlessRun::empty();
+            add_ground(&mut run, -10.0, -0.2, 0.0);
+            if mud {
+                let mut rng = Random::seed_from_str("mud pit");
+                let mut context = LevelBuilderContext::new(&mut run.entity_system, &mut run.particle_vec, &mut run.simulation, &mut rng);
+                MudPitOperation { params: Some(MudPitParams { width: 4.0, depth: 0.4, drag: 1.6 }) }.execute(&mut context);
+            } else {
+                add_ground(&mut run, 0.0, 5.6, 0.0);
+            }
+            add_ground(&mut run, 5.8, 30.0, 0.0);
+            add_car(&mut run, Vec2::new(-6.0, 0.6));
+            run.entity_system.handle_key(KeyCodeType::KeyX, true);
+            step_for(&mut run, 4.0);
+            car(&run).get_camera_look_at_position(&run.simulation.particles).x
+        };
+        let (flat, mud) = (drive(false), drive(true));
+        assert!(mud < flat - 2.0, "{} through the mud, {} on the flat", mud, flat);
+        assert!(mud > 0.0, "stuck in the mud at {}", mud);
+    }
+
+    #[test]
+    fn test_boost_pad_kicks_the_car_once() {
+        let mut run = HeadlessRun::empty();
+        add_ground(&mut run, -10.0, -0.2, 0.0);
+        let mut rng = Random::seed_from_str("boost pad");
+        let mut context = LevelBuilderContext::new(&mut run.entity_system, &mut run.particle_vec, &mut run.simulation, &mut rng);
+        BoostPadOperation { params: Some(BoostPadParams { run_up: 4.0, length: 2.5, rise: 0.0, boost: 5.0 }) }.execute(&mut context);
+        add_car(&mut run, Vec2::new(-6.0, 0.6));
+
+        // coast onto the pad so only the kick changes the speed
+        run.entity_system.handle_key(KeyCodeType::KeyX, true);
+        assert!(step_until(&mut run, 6.0, |run| car(run).get_camera_look_at_position(&run.simulation.particles).x > 2.0));
+        run.entity_system.handle_key(KeyCodeType::KeyX, false);
+        assert!(step_until(&mut run, 3.0, |run| car(run).get_camera_look_at_position(&run.simulation.particles).x > 3.5));
+        let before = car(&run).velocity(&run.simulation.particles).x;
+
+        assert!(step_until(&mut run, 3.0, |run| car(run).get_camera_look_at_position(&run.simulation.particles).x > 6.0));
+        let after = car(&run).velocity(&run.simulation.particles).x;
+        assert!(after > before + 4.0, "{} before the pad and {} after", before, after);
+
+        // the rest of the way over the pad it doesn't kick again
+        step_for(&mut run, 0.1);
+        assert!(car(&run).velocity(&run.simulation.particles).x < after + 0.2);
+    }
+
+    #[test]
+    fn test_teleporter_sends_the_car_on() {
+        let mut run = HeadlessRun::empty();
+        add_ground(&mut run, -10.0, -0.2, 0.0);
+        let mut rng = Random::seed_from_str("teleporter");
+        let mut context = LevelBuilderContext::new(&mut run.entity_system, &mut run.particle_vec, &mut run.simulation, &mut rng);
+        let params = TeleporterParams { run_up: 4.0, distance: 6.0, height: 1.0, exit: PortalExit::Rising };
+        TeleporterOperation { params: Some(params) }.execute(&mut context);
+        add_car(&mut run, Vec2::new(-6.0, 0.6));
+
+        // drive into the entrance, it comes out past the wall going up and on at about the same speed
+        run.entity_system.handle_key(KeyCodeType::KeyX, true);
+        assert!(step_until(&mut run, 6.0, |run| car(run).get_camera_look_at_position(&run.simulation.particles).x > 8.0));
+        let teleporter = &run.entity_system.teleporter_entity_system.0[0];
+        let car_pos = car(&run).get_camera_look_at_position(&run.simulation.particles);
+        assert!(teleporter.exit.contains_point(car_pos), "car at {:?}", car_pos);
+        let vel = car(&run).velocity(&run.simulation.particles);
+        assert!(vel.y > 1.0 && vel.x > 1.0, "car going {:?}", vel);
+
+        // and comes down on the landing
+        run.entity_system.handle_key(KeyCodeType::KeyX, false);
+        assert!(step_until(&mut run, 3.0, |run| car(run).get_camera_look_at_position(&run.simulation.particles).y < 1.5));
+        let car_pos = car(&run).get_camera_look_at_position(&run.simulation.particles);
+        assert!(car_pos.x > 11.5 && car_pos.x < 23.5 && car_pos.y > 1.0, "car at {:?}", car_pos);
+    }
+
+    #[test]
+    fn test_car_drives_up_to_speed() {
+        let mut run = HeadlessRun::empty();
+        add_ground(&mut run, -50.0, 50.0, 0.0);
+        add_car(&mut run, Vec2::new(0.0, 0.6));
+        step_for(&mut run, 1.0);
+        // without any input it stays put
+        assert!(car(&run).get_camera_look_at_position(&run.simulation.particles).x.abs() < 0.1);
+
+        // clockwise drives to the right, picking up speed without much wheelspin on the flat
+        run.entity_system.handle_key(KeyCodeType::KeyX, true);
+        assert!(step_until(&mut run, 3.0, |run| car(run).velocity(&run.simulation.particles).x >= 5.0));
+        assert!(car(&run).slip() < car_entity::SPINNING_SLIP);
+
+        // counter clockwise brakes and then drives it back to the left
+        run.entity_system.handle_key(KeyCodeType::KeyX, false);
+        run.entity_system.handle_key(KeyCodeType::KeyZ, true);
+        assert!(step_until(&mut run, 6.0, |run| car(run).velocity(&run.simulation.particles).x <= -2.0));
+    }
+
+    #[test]
+    fn test_checkpoint_is_reached_once() {
+        let mut run = HeadlessRun::empty();
+        add_ground(&mut run, -10.0, 10.0, 0.0);
+        run.entity_system.checkpoint_entity_system.push(CheckpointEntity::new(Aabb2d::new(Vec2::new(0.0, 2.0), Vec2::new(1.0, 2.0))));
+        run.entity_system.checkpoint_entity_system.push(CheckpointEntity::new(Aabb2d::new(Vec2::new(8.0, 2.0), Vec2::new(0.5, 2.0))));
+        add_car(&mut run, Vec2::new(0.0, 1.0));
+
+        run.step();
+        assert_eq!(run.entity_system.checkpoint_entity_system.num_reached(), 1);
+        assert!(matches!(run.entity_system.events[..], [GameplayEvent::CheckpointReached { checkpoint: 0, .. }]));
+        step_for(&mut run, 0.5);
+        assert_eq!(run.entity_system.checkpoint_entity_system.num_reached(), 1);
+        assert!(!run.entity_system.checkpoint_entity_system.entities[1].reached);
+    }
+
+    #[test]
+    fn test_finish_counts_each_lap_once() {
+        let mut run = HeadlessRun::empty();
+        add_ground(&mut run, -10.0, 20.0, 0.0);
+        run.entity_system.finish_entity_system.push(FinishEntity::new(Aabb2d::new(Vec2::new(6.0, 2.0), Vec2::new(1.0, 2.0))));
+        add_car(&mut run, Vec2::new(0.0, 1.0));
+        run.entity_system.car_entity_system.0[0].num_laps = 2;
+        run.entity_system.handle_key(KeyCodeType::KeyX, true);
+
+        // the first lap sends the car back to the start
+        assert!(step_until(&mut run, 10.0, |run| car(run).lap_times.len() == 1));
+        assert!(!car(&run).game_ended);
+        assert!(car(&run).get_camera_look_at_position(&run.simulation.particles).x < 2.0);
+
+        // the last one ends the run, sitting in the finish doesn't add laps
+        assert!(step_until(&mut run, 10.0, |run| car(run).game_ended));
+        assert!(matches!(run.entity_system.events[..], [GameplayEvent::Finished { .. }]));
+        step_for(&mut run, 0.5);
+        assert_eq!(car(&run).lap_times.len(), 2);
+    }
+}
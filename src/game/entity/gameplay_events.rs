@@ -0,0 +1,11 @@
+use crate::core::math::vec2::Vec2;
+
+/// Something notable that happened to a car during a step, collected by the EntitySystem for anything
+/// outside the simulation that wants to react to the run (eg. the commentary)
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameplayEvent {
+    CheckpointReached { checkpoint: usize, time: f32 }, // checkpoint is the index in course order
+    LapCompleted { lap: usize, lap_time: f32 }, // lap counts from 1, not sent for the last lap
+    Finished { time: f32 },
+    Crashed { pos: Vec2, speed: f32, time: f32 }, // speed in m/s the car hit the level at
+}
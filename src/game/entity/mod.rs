@@ -1,2 +1,3 @@
 pub mod entities;
-pub mod entity_system;
\ No newline at end of file
+pub mod entity_system;
+pub mod gameplay_events;
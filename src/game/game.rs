@@ -1,118 +1,1650 @@
-use std::{env, time::Instant};
+use std::{collections::{HashMap, HashSet}, env, path::{Path, PathBuf}, time::Instant};
 
 use crate::{
-    core::math::vec2::Vec2,
+    core::math::{deterministic::MathMode, random::Random, vec2::Vec2, vec4::Vec4},
     engine::{
         app::{
             camera::{Camera, CameraController},
             context::Context,
+            graphics_helper::GraphicsHelper,
             game_loop::GameLoop,
         },
         renderer::{
+            dynamic_resolution::DynamicResolution,
+            upscale::Upscaler,
             instance_renderer::{Instance, InstanceRaw, InstanceRenderer, QUAD_INDICES, QUAD_VERTICES, Vertex},
+            gpu_timer::GpuTimer,
             model::{Material, Mesh},
             shader::{Shader, ShaderBuilder},
+            text_renderer::{GlyphInstanceRaw, TextRenderer, WorldLabel},
         },
     },
     game::{
-        entity::{entities::car_entity::CarEntity, entity_system::EntitySystem},
-        level::level_builder::LevelBuilder,
+        camera_constraint::CameraConstraint,
+        entity::{entities::car_entity::{CarEntity, STANDARD_CAR}, entity_system::EntitySystem},
+        level::{level_builder::LevelBuilder, level_generation_info::LevelGenerationInfo, level_pack::{LevelPack, LevelPackLibrary, PackLevel, LEVEL_PACKS_DIR}},
+        level_pack_browser::{LevelPackBrowser, LevelPackBrowserEvent, LEVEL_PACKS_CHANNEL},
         irc::irc_manager::{IrcManager, IrcEvent},
-        leaderboard::Leaderboard,
+        leaderboard::{answers_request, parse_request as parse_leaderboard_request, request_message as leaderboard_request_message, Leaderboard, ScoreAnnotations, Verification},
         game_state::GameState,
+        game_mode::{GameMode, TIME_ATTACK_CHECKPOINT_EXTENSION, TIME_ATTACK_START_TIME},
+        nickname::{random_nickname, validate_nickname},
+        session_attempts::SessionAttempts,
+        seed_policy::{custom_seed, daily_seed, CUSTOM_SEED_PREFIX, time_until_rollover, SeedRolloverWatcher},
         settings::Settings,
+        telemetry::{TelemetryRecorder, TelemetryRecording},
+        rating::{DailyPlacement, RatingHistory, INITIAL_RATING, RATING_PATH},
+        practice::{SaveState, SaveStateRing},
+        training::{training_blocks, training_levels},
+        ghost::{Ghost, GhostReplay, ReplayIssue, StateChecksum, MAX_GHOSTS, PERSONAL_BEST_GHOST},
+        ghost_track::{GhostTrack, GhostTrackRecorder},
+        decals::Decals,
+        level_name::level_name,
+        sandbox::{Sandbox, SandboxTool},
+        scene_file::SceneFile,
+        ghost_camera::GhostCamera,
+        replay_sharing::{chunk_messages, ghost_targets, parse_request, request_message, ReplayAssembler, MAX_REQUESTS_PER_SEED, REPLAYS_CHANNEL},
+        world_labels::{distance_markers, update_level_labels},
     },
-    simulation::particles::{particle_vec::ParticleVec, simulation::Simulation, simulation_demos::SimulationDemos},
+    simulation::{gpu::gpu_solver::GpuSolver, particles::{energy_diagnostics::EnergyDiagnostics, particle_vec::ParticleVec, simulation::Simulation, simulation_demos::SimulationDemos}},
 };
-use crate::engine::app::event_system::{GameEvent, ElementStateType, KeyCodeType};
-use cgmath::Rotation3;
+use crate::engine::app::event_system::{GameEvent, ElementStateType, GamepadButtonType, GamepadInputType, InputContext, KeyCodeType, MouseButtonType};
+use crate::game::ui::ghost_camera::GhostCameraInfo;
+use crate::game::ui::game_ui::{AttemptInfo, EnergyInfo, GhostOffer, LapInfo, LevelEditorInfo, LevelPackSummary, PresenceInfo, RatingInfo, SandboxInfo, SolverInfo, TelemetryAnalysis, TimeAttackInfo};
+use crate::game::plugins::plugin_host::plugins;
+use crate::game::physics_tuning::PhysicsTuning;
+use crate::game::camera_mode::{CameraDirector, CameraMode};
+use crate::game::rumble::{Rumble, RumbleIntensity};
+use crate::game::car_audio::CarAudio;
+use crate::game::world_audio;
+use crate::game::result_card::{ResultCard, RESULT_CARDS_DIR};
+use crate::game::clock_check::{clock_warning, ClockCheck};
+use crate::game::offline::{Connection, OfflineStore, OFFLINE_PATH};
+use crate::game::verify::{recording_header, RECORDING_PATH};
+use crate::game::commentary::{Commentary, COMMENTARY_LOG_PATH};
+use crate::game::splits::{RunSplits, SplitHistory, SPLITS_PATH};
+use crate::game::stream_overlay::{LiveRun, StreamOverlay, OVERLAY_PATH};
+use crate::game::level_editor::{LevelEditor, EDITOR_LEVEL_PATH};
+use crate::game::anomaly_capture::{Anomaly, AnomalyCapture, ANOMALIES_DIR};
+
+const PB_TELEMETRY_PATH: &str = "pb.telemetry.json";
+const TOAST_DURATION: f32 = 4.0; // seconds
+const ENERGY_SAMPLES: usize = 600; // 3 seconds of steps
+const ENERGY_GRAPH_BARS: usize = 60;
+const REPLAY_SEND_COOLDOWN: f32 = 30.0; // seconds, everyone in the channel sees the chunks so one send answers all requests
+
+/// Connect to the global channels plus the private channel from settings (if any)
+fn connect_irc(nickname: String, settings: &Settings) -> IrcManager {
+    let mut channels = vec!["#planck-global".to_owned(), "#planck-leaderboard".to_owned(), LEVEL_PACKS_CHANNEL.to_owned(), REPLAYS_CHANNEL.to_owned()];
+    let mut channel_keys = HashMap::new();
+    if let Some(private_channel) = settings.private_channel() {
+        if let Some(key) = settings.private_channel_key.as_ref().filter(|key| !key.is_empty()) {
+            channel_keys.insert(private_channel.clone(), key.clone());
+        }
+        channels.push(private_channel);
+    }
+    if let Some(commentary_channel) = settings.commentary_channel() {
+        channels.push(commentary_channel);
+    }
+    let mut irc = IrcManager::new("irc.libera.chat".to_owned(), nickname, channels, channel_keys);
+    irc.set_ignored(settings.ignored_players.as_deref().unwrap_or_default());
+    irc
+}
+
+fn find_pack_level<'a>(level_packs: &'a LevelPackLibrary, pack_level: &Option<(String, usize)>) -> Option<(&'a LevelPack, usize)> {
+    let (id, level_index) = pack_level.as_ref()?;
+    let pack = level_packs.find(id)?;
+    (*level_index < pack.levels.len()).then_some((pack, *level_index))
+}
+
+/// Compile every shader permutation the game draws with while starting up, so a pass that first appears mid run
+/// (eg. the ghost camera) doesn't hitch building its pipeline
+fn precompile_shaders(graphics: &mut GraphicsHelper) {
+    let format = graphics.config.format;
+    let instance_layouts = [Vertex::desc(), InstanceRaw::desc()];
+    let glyph_layouts = [Vertex::desc(), GlyphInstanceRaw::desc()];
+    let permutations: [(&str, &[wgpu::VertexBufferLayout], wgpu::BlendState); 4] = [
+        ("particle_shader.wgsl", &instance_layouts, wgpu::BlendState::REPLACE),
+        ("line_shader.wgsl", &instance_layouts, wgpu::BlendState::REPLACE),
+        ("decal_shader.wgsl", &instance_layouts, wgpu::BlendState::ALPHA_BLENDING),
+        ("text_shader.wgsl", &glyph_layouts, wgpu::BlendState::REPLACE),
+    ];
+    for (shader, buffers, blend) in permutations {
+        ShaderBuilder::from_file(shader.to_owned(), &graphics.device).blend(blend).precompile(&mut graphics.pipeline_cache, buffers, format);
+    }
+}
+
+/// A level builder for the daily's variations, mirrored and with a headwind
+fn level_builder(mirrored: bool, headwind: f32) -> LevelBuilder {
+    let mut level_builder = LevelBuilder::default();
+    level_builder.mirrored = mirrored;
+    level_builder.headwind = headwind;
+    level_builder
+}
+
+/// Build a level saved from the editor, or being edited. None if it can't be built, the caller builds the daily instead.
+fn generate_level_file(level: &PackLevel, mut level_builder: LevelBuilder, entity_system: &mut EntitySystem, particle_vec: &mut ParticleVec, simulation: &mut Simulation) -> Option<LevelGenerationInfo> {
+    level_builder.generate_from_operations(&level.file_seed(), &level.operations, entity_system, particle_vec, simulation)
+        .map_err(|e| println!("Failed to build the level file, playing the daily instead: {}", e))
+        .ok()
+}
+
+/// Build the selected level pack level, or the custom seed's level, or the daily if neither is selected.
+/// If the pack level can't be built the selection is cleared and the daily is built instead.
+fn generate_level(level_packs: &LevelPackLibrary, pack_level: &mut Option<(String, usize)>, custom_seed: Option<&str>, mut level_builder: LevelBuilder, entity_system: &mut EntitySystem, particle_vec: &mut ParticleVec, simulation: &mut Simulation) -> LevelGenerationInfo {
+    if pack_level.is_some() {
+        let result = match find_pack_level(level_packs, pack_level) {
+            Some((pack, level_index)) => level_builder.generate_from_operations(&pack.level_seed(level_index), &pack.levels[level_index].operations, entity_system, particle_vec, simulation),
+            None => Err(String::from("level pack not installed")),
+        };
+        match result {
+            Ok(level_info) => return level_info,
+            Err(e) => {
+                println!("Failed to build level pack level, playing the daily instead: {}", e);
+                *pack_level = None;
+            }
+        }
+    }
+
+    match custom_seed {
+        Some(seed) => level_builder.generate_level_for_seed(seed, entity_system, particle_vec, simulation),
+        None => level_builder.generate_level_based_on_date(entity_system, particle_vec, simulation),
+    }
+}
 
 pub struct Game {
     camera: Camera,
     camera_controller: CameraController,
+    camera_constraint: CameraConstraint,
+    camera_director: CameraDirector, // chase and trackside cameras, see CameraMode
     particle_vec: ParticleVec,
     particle_instance_renderer: InstanceRenderer,
     quad_mesh: Mesh,
     material: Material,
     particle_shader: Shader,
     line_shader: Shader,
+    text_renderer: TextRenderer,
+    text_shader: Shader,
+    gpu_timer: GpuTimer,
+    dynamic_resolution: DynamicResolution, // drops the render scale when frames run long
+    upscaler: Upscaler, // stretches the scene over the window when it's rendered below full resolution
+    distance_markers: Vec<WorldLabel>, // worked out once per level, see world_labels
     frame_idx: u128,
     entity_system: EntitySystem,
     simulation: Simulation,
     total_time: f32,
     game_state: GameState,
     irc_manager: Option<IrcManager>,
+    connection: Connection, // whether irc_manager has got through, times are held in offline_store until it has
+    offline_store: OfflineStore,
     current_nickname: String,
     leaderboard: Leaderboard,
+    private_channel: Option<String>, // optional passworded channel with its own leaderboard
+    private_leaderboard: Leaderboard,
+    friends: Vec<String>, // local friends list, saved in settings
+    toast_expiry: Option<Instant>,
+    session_attempts: SessionAttempts,
+    seed_rollover: SeedRolloverWatcher,
+    clock_check: ClockCheck, // the daily seed comes from the local clock, so it's checked against a time server
+    rating: RatingHistory, // daily placements for the skill rating
     ui: crate::game::ui::game_ui::GameUI,
+    level_info: Option<LevelGenerationInfo>,
+    game_mode: GameMode,
+    best_lap: Option<f32>, // best lap this session, for multi-lap mode
+    time_remaining: f32, // time attack countdown
+    checkpoints_reached: usize,
+    mirrored: bool, // mirror mode, the daily level flipped horizontally
+    headwind: f32, // wind modifier in m/s, negative for a tailwind
+    practice: bool, // practice mode allows save states, so runs aren't recorded or posted to the leaderboard
+    save_states: SaveStateRing<SaveState>,
+    level_packs: LevelPackLibrary, // installed community level packs
+    level_pack_browser: LevelPackBrowser,
+    pack_level: Option<(String, usize)>, // pack id and level index when playing a level pack instead of the daily
+    custom_seed: Option<String>, // from --seed or the seed field, plays that level instead of the daily
+    level_file: Option<PackLevel>, // from --level, a level saved from the editor
+    level_editor: Option<LevelEditor>, // only in the editor scene
+    editor_click: Option<MouseButtonType>, // waits a frame for the UI to say whether the click was on one of its widgets
+    ghosts_enabled: bool, // off for demo scenes and replays, there's no run to share or race
+    ghosts: Vec<Ghost>, // other players' runs racing alongside mine
+    ghost_seed: String, // seed the ghost state below is for
+    ghost_targets: Vec<String>, // players whose runs are offered before the race
+    ghost_replays: HashMap<String, GhostReplay>, // downloaded runs by player
+    ghost_tracks: HashMap<String, GhostTrack>, // tracks friends have sent, by player
+    selected_ghosts: Vec<String>,
+    requested_ghosts: Vec<String>, // players already asked for their run
+    show_ghost_camera: bool, // picture in picture from the first ghost, toggled with G
+    ghost_camera: Option<GhostCamera>, // made the first time it is shown
+    replay_assembler: ReplayAssembler,
+    last_replay_sent: Option<Instant>,
+    telemetry: TelemetryRecorder,
+    split_history: SplitHistory, // my splits and sector bests on the boards I've played
+    run_splits: RunSplits, // when this run crossed each checkpoint
+    ghost_track: GhostTrackRecorder, // my car's poses this run, to export as a ghost track
+    state_checksums: Vec<StateChecksum>, // of the simulation this run, kept with the ghost replay so it can be verified
+    math_mode: MathMode, // how the simulation works out sin, cos etc., from the settings
+    energy_diagnostics: Option<EnergyDiagnostics>, // --diagnostics, energy and momentum drift in the debug info
+    physics_tuning: Option<PhysicsTuning>, // --tuning, solver parameters changed from the tuning panel
+    sandbox: Option<Sandbox>, // select and brush tools, only in demo scenes
+    gpu_solver: Option<GpuSolver>, // --gpu-solver, only in demo scenes and when the GPU can run it
+    car_contacts: u32, // wheel particles touching something, counted during the last simulation step
+    car_contact_pairs: Vec<(usize, usize)>, // (wheel particle, other particle) from the last simulation step, see update_car_contacts
+    car_particles: HashSet<usize>, // scratch for update_car_contacts, kept so each step doesn't allocate
+    car_surface_particles: HashSet<usize>, // scratch for update_car_contacts
+    decals: Decals, // skid marks and scuffs left on the ground
+    rumble: Rumble, // how the gamepad should shake from what my car goes through
+    car_audio: CarAudio, // collision and engine sounds for my car
+    commentary: Option<Commentary>, // narrates my runs when turned on in the settings
+    stream_overlay: Option<StreamOverlay>, // the live run for streaming overlays when turned on in the settings
+    anomaly_capture: AnomalyCapture, // recent states and inputs, saved when the physics blows up
+    rumble_intensity: RumbleIntensity, // cycled with the gamepad's Select button
+    result_card: bool, // save the result card's picture at the end of a run
+    result_card_clipboard: bool, // put the result card's picture on the clipboard at the end of a run
+    pending_result_card: Option<ResultCard>, // finished next render, with the scene read back for its background
+    image_clipboard: Option<arboard::Clipboard>, // kept open, on linux the picture is only on the clipboard while it is
+    decal_instance_renderer: InstanceRenderer,
+    decal_shader: Shader,
+    instances: Vec<Instance>, // reused each frame to upload the particle and decal instances
+    level_labels: Vec<WorldLabel>, // reused each frame, see update_level_labels
 }
 
-impl Game {
-    fn update_particle_instances(&mut self, queue: &wgpu::Queue, device: &wgpu::Device) {
-        let mut instances: Vec<Instance> = vec![]; 
-        let particles = &self.simulation.particles;
+impl Game {
+    fn update_particle_instances(&mut self, queue: &wgpu::Queue, device: &wgpu::Device) {
+        let instances = &mut self.instances;
+        instances.clear();
+        let particles = &self.simulation.particles;
+
+        for i in 0..particles.len() {
+            let position = cgmath::Vector3 {
+                x: particles[i].pos[0],
+                y: particles[i].pos[1],
+                z: 0.0,
+            };
+
+            let colour = particles[i].colour;
+            let radius = particles[i].radius;
+
+            instances.push(Instance { position, angle: 0.0, colour, radius });
+        }
+        if let Some(sandbox) = &self.sandbox {
+            sandbox.extend_instances(instances);
+        }
+
+        // ghost cars are drawn translucent, they aren't part of my simulation
+        let colour = Vec4::new(0.6, 0.8, 1.0, 0.4);
+        for ghost in &self.ghosts {
+            for (pos, radius) in ghost.car_particles() {
+                instances.push(Instance { position: cgmath::Vector3 { x: pos.x, y: pos.y, z: 0.0 }, angle: 0.0, colour, radius });
+            }
+        }
+
+        // winch ropes are drawn as a dotted line that isn't part of the simulation
+        let radius = 0.03;
+        for car in &self.entity_system.car_entity_system.0 {
+            let Some((from, to)) = car.rope_ends(particles) else { continue };
+            let length = (to - from).magnitude();
+            let num_dots = (length / (radius * 4.0)) as usize;
+            for i in 0..num_dots {
+                let pos = from + (to - from) * (i as f32 / num_dots as f32);
+                instances.push(Instance { position: cgmath::Vector3 { x: pos.x, y: pos.y, z: 0.0 }, angle: 0.0, colour: Vec4::new(0.8, 0.6, 0.3, 1.0), radius });
+            }
+        }
+
+        // wind zone streaks are drawn as short trails fading out behind them, they aren't part of the simulation either
+        let radius = 0.03;
+        for zone in &self.entity_system.wind_zone_entity_system.0 {
+            let direction = zone.direction();
+            for streak in &zone.streaks {
+                for i in 0..4 {
+                    let pos = *streak - direction * (i as f32 * radius * 3.0);
+                    let colour = Vec4::new(0.9, 0.95, 1.0, 0.5 - i as f32 * 0.1);
+                    instances.push(Instance { position: cgmath::Vector3 { x: pos.x, y: pos.y, z: 0.0 }, angle: 0.0, colour, radius });
+                }
+            }
+        }
+
+        // teleporter portals are rings of particles that aren't part of the simulation, the entrance violet and the exit orange
+        let radius = 0.05;
+        for teleporter in &self.entity_system.teleporter_entity_system.0 {
+            for (aabb, colour) in [(&teleporter.entrance, Vec4::new(0.6, 0.2, 1.0, 0.8)), (&teleporter.exit, Vec4::new(1.0, 0.6, 0.1, 0.8))] {
+                let centre = (aabb.min + aabb.max) * 0.5;
+                let half_size = (aabb.max - aabb.min) * 0.5;
+                for i in 0..24 {
+                    let angle = i as f32 / 24.0 * std::f32::consts::TAU + self.total_time;
+                    let pos = centre + Vec2::new(half_size.x * angle.cos(), half_size.y * angle.sin());
+                    instances.push(Instance { position: cgmath::Vector3 { x: pos.x, y: pos.y, z: 0.0 }, angle: 0.0, colour, radius });
+                }
+            }
+        }
+
+        // chevrons sliding along each boost pad the way it kicks
+        for pad in &self.entity_system.boost_pad_entity_system.0 {
+            let direction = pad.direction();
+            let normal = Vec2::new(-direction.y, direction.x);
+            for k in 0..3 {
+                let along = ((k as f32 + self.total_time * 1.5) / 3.0).fract();
+                let tip = pad.start + (pad.end - pad.start) * along + normal * 0.3;
+                for j in -2..=2 {
+                    let pos = tip - direction * (j as f32).abs() * 0.08 + normal * j as f32 * 0.08;
+                    instances.push(Instance { position: cgmath::Vector3 { x: pos.x, y: pos.y, z: 0.0 }, angle: 0.0, colour: Vec4::new(0.6, 1.0, 1.0, 0.8), radius });
+                }
+            }
+        }
+
+        // checkpoint gates are only shown in time attack, drawn as a column of particles that aren't part of the simulation
+        if self.game_mode == GameMode::TimeAttack {
+                let radius = 0.05;
+            for checkpoint in &self.entity_system.checkpoint_entity_system.entities {
+                let colour = if checkpoint.reached { Vec4::new(0.0, 1.0, 0.0, 0.5) } else { Vec4::new(1.0, 0.8, 0.0, 0.8) };
+                let x = (checkpoint.aabb.min.x + checkpoint.aabb.max.x) * 0.5;
+                let mut y = checkpoint.aabb.min.y;
+                while y <= checkpoint.aabb.max.y {
+                    instances.push(Instance { position: cgmath::Vector3 { x, y, z: 0.0 }, angle: 0.0, colour, radius });
+                    y += radius * 4.0;
+                }
+            }
+        }
+        self.particle_instance_renderer.update_instances(instances, queue, device);
+
+        instances.clear();
+        self.decals.extend_instances(instances);
+        self.decal_instance_renderer.update_instances(instances, queue, device);
+    }
+
+    fn update_world_labels(&mut self, queue: &wgpu::Queue, device: &wgpu::Device) {
+        update_level_labels(&mut self.level_labels, &self.entity_system, self.game_mode, &self.ghosts);
+        self.text_renderer.update_labels(self.distance_markers.iter().chain(self.level_labels.iter()), queue, device);
+    }
+
+    /// Distance markers from the spawn to the finish, none for demo scenes as they have no finish
+    fn update_distance_markers(&mut self) {
+        self.distance_markers = match self.entity_system.finish_entity_system.entities.first() {
+            Some(finish) => distance_markers(&self.simulation.particles, Vec2::new(0.0, 1.0), (finish.aabb.min.x + finish.aabb.max.x) * 0.5),
+            None => vec![],
+        };
+    }
+    pub fn reset(&mut self, ctx: &mut Context) {
+        self.total_time = 0.0;
+        self.game_state = GameState::Playing;
+        self.frame_idx = 0;
+        self.ghosts.clear();
+        self.camera_constraint.reset();
+        self.camera_director.reset();
+        if let Some(energy_diagnostics) = &mut self.energy_diagnostics {
+            energy_diagnostics.reset();
+        }
+        
+        // Re-initialize systems
+        self.entity_system = EntitySystem::new();
+        self.particle_vec = ParticleVec::new();
+        
+        // a new simulation also means fresh granular terrain, ruts are only carried over between practice save states
+        let rng = crate::core::math::random::Random::seed_from_beginning_of_day();
+        self.simulation = Simulation::with_math_mode(rng, self.math_mode);
+        
+        // Re-generate level
+        let level_file = self.level_editor.as_ref().map(|level_editor| &level_editor.level).or(self.level_file.as_ref());
+        let level_info = match level_file.and_then(|level| generate_level_file(level, level_builder(self.mirrored, self.headwind), &mut self.entity_system, &mut self.particle_vec, &mut self.simulation)) {
+            Some(level_info) => level_info,
+            None => generate_level(&self.level_packs, &mut self.pack_level, self.custom_seed.as_deref(), level_builder(self.mirrored, self.headwind), &mut self.entity_system, &mut self.particle_vec, &mut self.simulation),
+        };
+        if let Some(level_editor) = &mut self.level_editor {
+            level_editor.level_built(&level_info.operations);
+        }
+        self.leaderboard.set_level_hash(&level_info.seed, Some(level_info.level_hash.clone()));
+        self.private_leaderboard.set_level_hash(&level_info.seed, Some(level_info.level_hash.clone()));
+        ctx.event_system.set_recording_header(recording_header(&level_info, self.math_mode, &[]));
+        self.level_info = Some(level_info);
+        self.update_level_name();
+        let mut car = CarEntity::new(&mut self.particle_vec, &mut self.simulation, Vec2::new(0.0, 1.0));
+        car.num_laps = self.game_mode.num_laps();
+        self.entity_system.car_entity_system.push(car);
+        // the new level is built untuned
+        if let Some(physics_tuning) = self.physics_tuning {
+            physics_tuning.apply(&PhysicsTuning::default(), &mut self.simulation);
+        }
+        self.update_distance_markers();
+        
+        // Update UI
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateGameState(GameState::Playing));
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateTime(0.0));
+        self.update_lap_info();
+        self.time_remaining = TIME_ATTACK_START_TIME;
+        self.checkpoints_reached = 0;
+        self.update_time_attack_info(false);
+        self.telemetry = TelemetryRecorder::new(self.leaderboard_seed());
+        self.run_splits = RunSplits::new(self.leaderboard_seed(), self.entity_system.checkpoint_entity_system.entities.len());
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateSplit(None));
+        self.start_following_run();
+        self.ghost_track = GhostTrackRecorder::new();
+        self.state_checksums.clear();
+        self.anomaly_capture.reset();
+        self.decals.clear();
+        self.rumble.clear();
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateGhostExported(None));
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateTelemetryAnalysis(None));
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateShowAnalysis(false));
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateShowLevelPacks(false));
+        if self.ghosts_available() {
+            self.game_state = GameState::PreRace;
+            self.ui.update(crate::game::ui::game_ui::Message::UpdateGameState(GameState::PreRace));
+            self.update_ghost_targets();
+        }
+        
+        // Reset recording if necessary
+        let args: Vec<String> = env::args().collect();
+        let scene = if args.len() >= 2 { args[1].clone() } else { String::from("") };
+        let is_demo_scene = scene == "demo" || SimulationDemos::NAMES.contains(&scene.as_str());
+        
+        if !is_demo_scene && !self.practice && self.level_editor.is_none() {
+            ctx.event_system.start_recording();
+        }
+        
+        self.update_particle_instances(&ctx.graphics.queue, &ctx.graphics.device);
+        self.update_world_labels(&ctx.graphics.queue, &ctx.graphics.device);
+        self.update_level_editor_info();
+    }
+
+    /// Leaderboard key for the current level and mode
+    fn leaderboard_seed(&self) -> String {
+        let seed = match &self.level_info {
+            Some(level_info) => level_info.seed.clone(),
+            None => daily_seed(chrono::Utc::now()),
+        };
+        let mirror_suffix = if self.mirrored { "-mirror" } else { "" };
+        let wind_suffix = if self.headwind != 0.0 { format!("-wind{:+}", self.headwind) } else { String::new() };
+        format!("{}{}{}{}", seed, self.game_mode.leaderboard_seed_suffix(), mirror_suffix, wind_suffix)
+    }
+
+    /// Ghosts are only offered on the standard daily, the level every shared run was driven on.
+    /// Practice runs are left out as save states would put the ghosts out of step.
+    fn ghosts_available(&self) -> bool {
+        self.ghosts_enabled && self.game_mode == GameMode::Standard && !self.mirrored && self.headwind == 0.0 && !self.practice && self.pack_level.is_none() && self.custom_seed.is_none()
+            && self.level_file.is_none() && self.level_editor.is_none()
+    }
+
+    /// Pick who to race and ask for any runs we don't have yet.
+    /// Called again when the leaderboard changes, as today's players show up over the day.
+    fn update_ghost_targets(&mut self) {
+        if !self.ghosts_available() {
+            return;
+        }
+
+        let seed = self.leaderboard_seed();
+        if self.ghost_seed != seed {
+            self.ghost_replays.clear();
+            self.selected_ghosts.clear();
+            self.requested_ghosts.clear();
+            self.replay_assembler.retain_seed(&seed);
+            self.ghost_seed = seed.clone();
+        }
+
+        self.update_personal_best_ghost(&seed);
+
+        // tracks from a different level would just float about, so they're left out
+        let level_hash = self.level_info.as_ref().map(|level_info| level_info.level_hash.clone());
+        self.ghost_tracks = GhostTrack::load_all(&seed).into_iter()
+            .filter(|track| track.user != self.current_nickname)
+            .filter(|track| track.level_hash.is_none() || level_hash.is_none() || track.level_hash == level_hash)
+            .map(|track| (track.user.clone(), track))
+            .collect();
+
+        // yesterday's placement is a rough guide to who is around my level
+        let yesterday = daily_seed(chrono::Utc::now() - chrono::Duration::days(1));
+        let yesterday_rank = self.rating.placements.iter().find(|placement| placement.seed == yesterday).map(|placement| placement.rank);
+        let entries = self.leaderboard.get_leaderboard_entries(&seed, &self.current_nickname, None);
+        self.ghost_targets = ghost_targets(&entries, yesterday_rank);
+
+        if let Some(irc) = &self.irc_manager {
+            for name in &self.ghost_targets {
+                let requested = self.requested_ghosts.contains(name) || self.ghost_replays.contains_key(name);
+                if !requested && self.requested_ghosts.len() < MAX_REQUESTS_PER_SEED {
+                    irc.send_message(REPLAYS_CHANNEL.to_owned(), request_message(&seed, name));
+                    self.requested_ghosts.push(name.clone());
+                }
+            }
+        }
+        self.update_ghost_offers();
+    }
+
+    /// Offer my best run on the seed as a ghost, picked straight away the first time it's seen.
+    /// It's loaded again each time as a run since the last race may have beaten it.
+    fn update_personal_best_ghost(&mut self, seed: &str) {
+        let personal_best = GhostReplay::load(seed).filter(|best| self.ghost_replay_issue(best).is_none());
+        let Some(mut personal_best) = personal_best else {
+            self.ghost_replays.remove(PERSONAL_BEST_GHOST);
+            self.selected_ghosts.retain(|name| name != PERSONAL_BEST_GHOST);
+            return;
+        };
+
+        personal_best.user = PERSONAL_BEST_GHOST.to_owned();
+        let is_new = self.ghost_replays.insert(PERSONAL_BEST_GHOST.to_owned(), personal_best).is_none();
+        if is_new && self.selected_ghosts.len() < MAX_GHOSTS {
+            self.selected_ghosts.push(PERSONAL_BEST_GHOST.to_owned());
+        }
+    }
+
+    fn update_ghost_offers(&mut self) {
+        let entries = self.leaderboard.get_leaderboard_entries(&self.leaderboard_seed(), &self.current_nickname, None);
+        // keep showing selected ghosts that have dropped out of the targets, and offer any tracks friends have sent
+        let personal_best = self.ghost_replays.get_key_value(PERSONAL_BEST_GHOST).map(|(name, _)| name);
+        let mut names: Vec<&String> = personal_best.into_iter().chain(self.ghost_targets.iter()).collect();
+        let mut tracks: Vec<&GhostTrack> = self.ghost_tracks.values().collect();
+        tracks.sort_by(|a, b| a.time.total_cmp(&b.time));
+        for name in self.selected_ghosts.iter().chain(tracks.iter().map(|track| &track.user)) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        let offers = names.into_iter().map(|name| GhostOffer {
+            name: name.clone(),
+            time: entries.iter().find(|entry| &entry.name == name).map(|entry| entry.time)
+                .or_else(|| self.ghost_tracks.get(name).map(|track| track.time))
+                .or_else(|| self.ghost_replays.get(name).map(|replay| replay.time)),
+            ready: self.ghost_replays.contains_key(name) || self.ghost_tracks.contains_key(name),
+            selected: self.selected_ghosts.contains(name),
+            // a track plays back whatever the replay's issue
+            issue: self.ghost_replays.get(name)
+                .filter(|_| !self.ghost_tracks.contains_key(name))
+                .and_then(|replay| self.ghost_replay_issue(replay))
+                .map(|issue| issue.to_string()),
+        }).collect();
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateGhostOffers(offers));
+    }
+
+    /// Why a ghost wouldn't play back the same on this level, if it wouldn't
+    fn ghost_replay_issue(&self, replay: &GhostReplay) -> Option<ReplayIssue> {
+        let level_hash = self.level_info.as_ref().map(|level_info| level_info.level_hash.as_str());
+        replay.compatibility(level_hash).err()
+    }
+
+    /// Keep a downloaded run, racing it straight away if there is room
+    fn add_ghost_replay(&mut self, replay: GhostReplay) {
+        if replay.seed != self.ghost_seed || !self.ghost_targets.contains(&replay.user) {
+            return;
+        }
+        if let Some(issue) = self.ghost_replay_issue(&replay) {
+            println!("Can't race {}'s ghost, {}", replay.user, issue);
+        } else if !self.selected_ghosts.contains(&replay.user) && self.selected_ghosts.len() < MAX_GHOSTS {
+            self.selected_ghosts.push(replay.user.clone());
+        }
+        self.ghost_replays.insert(replay.user.clone(), replay);
+        self.update_ghost_offers();
+    }
+
+    /// A player's replay if it can be raced, otherwise a track they sent
+    fn ghost_for(&self, name: &str) -> Option<Ghost> {
+        match self.ghost_replays.get(name) {
+            Some(replay) if self.ghost_replay_issue(replay).is_none() => Some(Ghost::new(replay.clone())),
+            _ => self.ghost_tracks.get(name).map(|track| Ghost::from_track(track.clone())),
+        }
+    }
+
+    fn toggle_ghost(&mut self, name: String) {
+        if let Some(i) = self.selected_ghosts.iter().position(|selected| *selected == name) {
+            self.selected_ghosts.remove(i);
+        } else if self.ghost_for(&name).is_some() && self.selected_ghosts.len() < MAX_GHOSTS {
+            self.selected_ghosts.push(name);
+        }
+        self.update_ghost_offers();
+    }
+
+    /// Leave the pre-race screen. The frame count and recording restart here so my run lines up with the ghosts.
+    fn start_race(&mut self, ctx: &mut Context) {
+        if self.game_state != GameState::PreRace {
+            return;
+        }
+
+        self.frame_idx = 0;
+        ctx.event_system.set_frame(0);
+        if ctx.event_system.is_recording() {
+            ctx.event_system.start_recording();
+        }
+        self.ghosts = self.selected_ghosts.iter()
+            .filter_map(|name| self.ghost_for(name))
+            .collect();
+
+        self.game_state = GameState::Playing;
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateGameState(GameState::Playing));
+    }
+
+    fn toggle_ghost_camera(&mut self) {
+        self.show_ghost_camera = !self.show_ghost_camera;
+        let mut settings = Settings::load();
+        settings.show_ghost_camera = Some(self.show_ghost_camera);
+        let _ = settings.save();
+    }
+
+    /// Keep the input context in step with the game state, so keys typed into a text box never reach the car or camera.
+    /// The car can still be driven around once finished, so that's gameplay too, and the editor drives it as well.
+    fn update_input_context(&self, ctx: &mut Context) {
+        let base = match self.game_state {
+            GameState::NameEntry | GameState::PreRace => InputContext::Menu,
+            GameState::Playing | GameState::Finished => InputContext::Gameplay,
+        };
+        ctx.event_system.set_base_context(base);
+
+        if self.level_editor.is_some() && base == InputContext::Gameplay {
+            ctx.event_system.push_context(InputContext::Editor);
+        } else {
+            ctx.event_system.pop_context(InputContext::Editor);
+        }
+
+        if self.game_state == GameState::NameEntry || self.ui.leaderboard_search_active || self.ui.seed_entry_active || ctx.ui.text_focused {
+            ctx.event_system.push_context(InputContext::TextEntry);
+        } else {
+            ctx.event_system.pop_context(InputContext::TextEntry);
+        }
+    }
+
+    fn toggle_camera_projection(&mut self) {
+        self.camera.projection = self.camera.projection.next();
+        let mut settings = Settings::load();
+        settings.camera_projection = Some(self.camera.projection);
+        let _ = settings.save();
+        self.show_toast(format!("{} camera", self.camera.projection.name()));
+    }
+
+    fn cycle_rumble_intensity(&mut self) {
+        self.rumble_intensity = self.rumble_intensity.next();
+        let mut settings = Settings::load();
+        settings.rumble_intensity = Some(self.rumble_intensity);
+        let _ = settings.save();
+        self.show_toast(format!("Rumble {}", self.rumble_intensity.name().to_lowercase()));
+    }
+
+    fn cycle_camera_mode(&mut self) {
+        let mode = self.camera_director.mode.next();
+        self.camera_director.set_mode(mode);
+        let mut settings = Settings::load();
+        settings.camera_mode = Some(mode);
+        let _ = settings.save();
+        self.show_toast(format!("{} camera", mode.name()));
+    }
+
+    /// Point the picture in picture camera at the first ghost, or hide it when there's no ghost or it's turned off
+    fn update_ghost_camera(&mut self, ctx: &mut Context) {
+        let followed = self.ghosts.first()
+            .filter(|_| self.show_ghost_camera)
+            .and_then(|ghost| Some((&ghost.name, ghost.camera_target()?)));
+        let Some((name, target)) = followed else {
+            if self.ui.ghost_camera.is_some() {
+                self.ui.update(crate::game::ui::game_ui::Message::UpdateGhostCamera(None));
+            }
+            return;
+        };
+
+        let ghost_camera = self.ghost_camera.get_or_insert_with(|| GhostCamera::new(&mut ctx.graphics, &self.material));
+        ghost_camera.update(target, self.camera.projection, &ctx.graphics.queue);
+        if self.ui.ghost_camera.as_ref().is_none_or(|info| &info.name != name) {
+            self.ui.update(crate::game::ui::game_ui::Message::UpdateGhostCamera(Some(GhostCameraInfo {
+                name: name.clone(),
+                view: ghost_camera.view(),
+                width: GhostCamera::WIDTH,
+                height: GhostCamera::HEIGHT,
+            })));
+        }
+    }
+
+    /// Keep my best run for the seed so other players can race it
+    fn save_ghost_replay(&self, ctx: &Context) {
+        let seed = self.leaderboard_seed();
+        if GhostReplay::load(&seed).is_some_and(|best| best.time <= self.total_time) {
+            return;
+        }
+        let level_hash = self.level_info.as_ref().map(|level_info| level_info.level_hash.clone());
+        let replay = GhostReplay {
+            math_mode: self.math_mode,
+            checksums: self.state_checksums.clone(),
+            ..GhostReplay::from_recorded_events(self.current_nickname.clone(), seed, self.total_time, level_hash, ctx.event_system.recorded_events())
+        };
+        if let Err(e) = replay.save() {
+            eprintln!("Failed to save ghost replay: {}", e);
+        }
+    }
+
+    /// Write this run out as a ghost track to send to friends
+    fn export_ghost(&mut self) {
+        let level_hash = self.level_info.as_ref().map(|level_info| level_info.level_hash.clone());
+        let track = self.ghost_track.to_track(self.current_nickname.clone(), self.leaderboard_seed(), self.total_time, level_hash);
+        match track.save() {
+            Ok(path) => {
+                self.show_toast(format!("Ghost exported to {}", path.display()));
+                self.ui.update(crate::game::ui::game_ui::Message::UpdateGhostExported(Some(true)));
+            }
+            Err(e) => self.show_toast(format!("Failed to export ghost: {}", e)),
+        }
+    }
+
+    /// Practice mode: save the full simulation state so a section can be replayed from here
+    fn quicksave(&mut self) {
+        self.save_states.push(SaveState {
+            simulation: self.simulation.clone(),
+            particle_vec: self.particle_vec.clone(),
+            entity_system: self.entity_system.clone(),
+            total_time: self.total_time,
+            time_remaining: self.time_remaining,
+            checkpoints_reached: self.checkpoints_reached,
+            physics_tuning: self.physics_tuning.unwrap_or_default(),
+        });
+        self.show_toast(format!("Saved state {}/{}", self.save_states.selected_index(), self.save_states.len()));
+    }
+
+    /// Practice mode: restore the selected save state, optionally stepping back to an older one first
+    fn quickload(&mut self, older: bool) {
+        let save_state = if older { self.save_states.select_older() } else { self.save_states.selected() };
+        let Some(save_state) = save_state.cloned() else {
+            self.show_toast(String::from("No saved state, press F5 to save"));
+            return;
+        };
+
+        let mut entity_system = save_state.entity_system;
+        for (car, current_car) in entity_system.car_entity_system.0.iter_mut().zip(self.entity_system.car_entity_system.0.iter()) {
+            car.copy_input_from(current_car);
+        }
+        // the sand keeps the ruts from this attempt, only a reset gives fresh ground
+        let mut simulation = save_state.simulation;
+        simulation.carry_over_granular(&self.simulation);
+        // keep the physics as it's tuned now rather than as it was when saved
+        if let Some(physics_tuning) = self.physics_tuning {
+            physics_tuning.apply(&save_state.physics_tuning, &mut simulation);
+        }
+        self.simulation = simulation;
+        self.particle_vec = save_state.particle_vec;
+        if let Some(sandbox) = &mut self.sandbox {
+            sandbox.selection.clear(); // the particles may not be there any more
+            self.update_sandbox_info();
+        }
+        self.entity_system = entity_system;
+        self.total_time = save_state.total_time;
+        self.time_remaining = save_state.time_remaining;
+        self.checkpoints_reached = save_state.checkpoints_reached;
+        if let Some(energy_diagnostics) = &mut self.energy_diagnostics {
+            energy_diagnostics.reset();
+        }
+
+        self.game_state = GameState::Playing;
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateGameState(GameState::Playing));
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateTime(self.total_time));
+        self.update_lap_info();
+        self.update_time_attack_info(false);
+        self.show_toast(format!("Loaded state {}/{}", self.save_states.selected_index(), self.save_states.len()));
+    }
+
+    /// Apply new solver parameters from the tuning panel to the running simulation
+    fn tune_physics(&mut self, physics_tuning: PhysicsTuning) {
+        let Some(previous) = self.physics_tuning else { return };
+        physics_tuning.apply(&previous, &mut self.simulation);
+        self.physics_tuning = Some(physics_tuning);
+        self.ui.update(crate::game::ui::game_ui::Message::UpdatePhysicsTuning(Some(physics_tuning)));
+    }
+
+    /// Save the sandbox as a new scene file, which F7 then loads again
+    fn save_scene(&mut self) {
+        let Some(sandbox) = &mut self.sandbox else { return };
+        let path = SceneFile::new_path(chrono::Local::now());
+        let toast = match SceneFile::from_simulation(&self.simulation).save(&path) {
+            Ok(()) => {
+                let toast = format!("Saved scene to {}", path.display());
+                sandbox.scene_path = Some(path);
+                toast
+            }
+            Err(e) => format!("Failed to save scene: {}", e),
+        };
+        self.show_toast(toast);
+    }
+
+    /// Start the sandbox over from the scene file it was last saved to or loaded from
+    fn load_scene(&mut self) {
+        let Some(path) = self.sandbox.as_ref().and_then(|sandbox| sandbox.scene_path.clone()) else {
+            self.show_toast(String::from("No scene file, press F6 to save one"));
+            return;
+        };
+        match SceneFile::load(&path) {
+            Ok(scene_file) => {
+                self.simulation = Simulation::with_math_mode(crate::core::math::random::Random::seed_from_beginning_of_day(), self.math_mode);
+                scene_file.build(&mut self.simulation);
+                if let Some(sandbox) = &mut self.sandbox {
+                    sandbox.selection.clear();
+                }
+                self.show_toast(format!("Loaded scene from {}", path.display()));
+            }
+            Err(e) => self.show_toast(format!("Failed to load scene: {}", e)),
+        }
+    }
+
+    fn update_sandbox_info(&mut self) {
+        let sandbox_info = self.sandbox.as_ref().map(|sandbox| SandboxInfo {
+            brush: sandbox.tool == SandboxTool::Brush,
+            selected: sandbox.selection.len(),
+            material: self.simulation.materials.get(sandbox.material).name.clone(),
+            scene_file: sandbox.scene_path.as_ref().map(|path| path.display().to_string()),
+        });
+        if self.ui.sandbox_info != sandbox_info {
+            self.ui.update(crate::game::ui::game_ui::Message::UpdateSandboxInfo(sandbox_info));
+        }
+    }
+
+    /// The editor panel's block list, and the selected block's params to edit
+    fn update_level_editor_info(&mut self) {
+        let Some(level_editor) = &self.level_editor else { return };
+        let level_editor_info = LevelEditorInfo {
+            path: level_editor.path.display().to_string(),
+            blocks: level_editor.level.operations.iter().map(|block| block.type_name.clone()).collect(),
+            selected: level_editor.selected,
+            block_type: level_editor.block_types.get(level_editor.block_type).cloned().unwrap_or_default(),
+        };
+        let params = level_editor.selected_params();
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateLevelEditorInfo(Some(level_editor_info)));
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateEditorParams(params));
+    }
+
+    /// Make a change in the editor, building the level again if it changed the blocks
+    fn edit_level(&mut self, ctx: &mut Context, edit: impl FnOnce(&mut LevelEditor) -> bool) {
+        let Some(level_editor) = &mut self.level_editor else { return };
+        if edit(level_editor) {
+            self.reset(ctx);
+        } else {
+            self.update_level_editor_info();
+        }
+    }
+
+    fn apply_editor_params(&mut self, ctx: &mut Context) {
+        let Some(level_editor) = &mut self.level_editor else { return };
+        match level_editor.set_selected_params(&self.ui.editor_params) {
+            Ok(()) => self.reset(ctx),
+            Err(e) => self.show_toast(format!("Couldn't change the block: {}", e)),
+        }
+    }
+
+    fn save_editor_level(&mut self) {
+        let Some(level_editor) = &self.level_editor else { return };
+        let message = match level_editor.save() {
+            Ok(()) => format!("Saved the level to {}, play it with --level", level_editor.path.display()),
+            Err(e) => format!("Failed to save the level: {}", e),
+        };
+        self.show_toast(message);
+    }
+
+    fn update_energy_info(&mut self) {
+        let Some(energy_diagnostics) = &self.energy_diagnostics else { return };
+        let Some(latest) = energy_diagnostics.latest() else { return };
+
+        // average the history down to one value per bar
+        let history = energy_diagnostics.energy_drift_history();
+        let bucket_size = history.len().div_ceil(ENERGY_GRAPH_BARS).max(1);
+        let drift_history = history.chunks(bucket_size).map(|chunk| chunk.iter().sum::<f32>() / chunk.len() as f32).collect();
+
+        let momentum_drift = energy_diagnostics.momentum_drift();
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateEnergyInfo(Some(EnergyInfo {
+            kinetic: latest.kinetic,
+            potential: latest.potential,
+            energy_drift: energy_diagnostics.energy_drift(),
+            relative_energy_drift: energy_diagnostics.relative_energy_drift(),
+            momentum_drift: (momentum_drift.x, momentum_drift.y),
+            drift_history,
+        })));
+    }
+
+    /// Feed the last frame's time to dynamic resolution, going by the scene's GPU time when the adapter can time passes
+    /// and otherwise the CPU time of the update and render. The simulation steps at its fixed rate whatever the scale.
+    fn update_render_scale(&mut self, ctx: &mut Context) {
+        let frame_ms = self.gpu_timer.timings.iter()
+            .find(|(pass, _)| *pass == "scene")
+            .map_or(self.ui.update_time_ms + self.ui.render_time_ms, |(_, time)| *time);
+        if self.dynamic_resolution.update(frame_ms) {
+            ctx.graphics.set_render_scale(self.dynamic_resolution.scale());
+        }
+        if self.ui.render_scale != ctx.graphics.render_scale() {
+            self.ui.update(crate::game::ui::game_ui::Message::UpdateRenderScale(ctx.graphics.render_scale()));
+        }
+    }
+
+    /// How long this frame's update took for the debug info, in ms
+    fn update_update_time(&mut self, start: Instant) {
+        let elapsed = start.elapsed().as_secs_f32() * 1000.0;
+        if self.ui.update_time_ms != elapsed {
+            self.ui.update(crate::game::ui::game_ui::Message::UpdateUpdateTime(elapsed));
+        }
+    }
+
+    fn show_toast(&mut self, toast: String) {
+        self.toast_expiry = Some(Instant::now() + std::time::Duration::from_secs_f32(TOAST_DURATION));
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateToast(Some(toast)));
+    }
+
+    /// Write out what led up to a physics blowup so it can be reproduced, with the whole recording of the run
+    /// when there is one, as a replay gets to the same state
+    fn save_anomaly(&mut self, ctx: &mut Context, anomaly: &Anomaly) {
+        eprintln!("Physics anomaly at frame {}: {}", self.frame_idx, anomaly.describe());
+        let header = self.level_info.as_ref().and_then(|level_info| recording_header(level_info, self.math_mode, &self.state_checksums));
+        match self.anomaly_capture.save(Path::new(ANOMALIES_DIR), self.frame_idx, anomaly, header, chrono::Local::now()) {
+            Ok(path) => {
+                if ctx.event_system.is_recording() {
+                    let _ = ctx.event_system.export_recording(&path.join(RECORDING_PATH).to_string_lossy());
+                }
+                self.show_toast(format!("Physics glitch: {}, saved to {} for a bug report", anomaly.describe(), path.display()));
+            }
+            Err(e) => eprintln!("Failed to save the anomaly: {}", e),
+        }
+    }
+
+    /// Publish the live run to the stream overlay
+    fn update_stream_overlay(&mut self) {
+        if self.stream_overlay.is_none() {
+            return;
+        }
+        let seed = self.leaderboard_seed();
+        let placement = self.leaderboard.placement(&seed, &self.current_nickname);
+        let run = LiveRun {
+            player: self.current_nickname.clone(),
+            level_name: self.ui.level_name.clone(),
+            state: self.game_state,
+            time: self.total_time,
+            time_remaining: (self.game_mode == GameMode::TimeAttack).then_some(self.time_remaining.max(0.0)),
+            speed: self.entity_system.car_entity_system.0.first().map_or(0.0, |car| car.velocity(&self.simulation.particles).magnitude()),
+            leaderboard_position: placement.map(|(rank, _)| rank),
+            leaderboard_players: self.leaderboard.best_scores(&seed).len(),
+            seed,
+        };
+        if let Some(stream_overlay) = &mut self.stream_overlay {
+            stream_overlay.update(&run, Instant::now());
+        }
+    }
+
+    /// Play the level for the seed typed into the seed field, on its own board
+    fn play_seed(&mut self, ctx: &mut Context) {
+        let Some(seed) = custom_seed(&self.ui.seed_input) else {
+            self.show_toast(String::from("Type a seed to play, letters, numbers, - and _"));
+            return;
+        };
+        if self.ui.seed_entry_active {
+            self.ui.update(crate::game::ui::game_ui::Message::ToggleSeedEntry);
+        }
+        self.pack_level = None;
+        self.level_file = None;
+        self.custom_seed = Some(seed);
+        self.reset(ctx);
+    }
+
+    /// Tick the next daily countdown, and offer the new daily when the seed rolls over unless we're already on it
+    fn update_daily_rollover(&mut self) {
+        let now = chrono::Utc::now();
+        let next_daily_in = time_until_rollover(now);
+        // the countdown only shows whole seconds
+        if next_daily_in.num_seconds() != self.ui.next_daily_in.num_seconds() {
+            self.ui.update(crate::game::ui::game_ui::Message::UpdateNextDailyIn(next_daily_in));
+        }
+
+        if let Some(event) = self.seed_rollover.check(now) {
+            println!("Daily rolled over from {} to {}", event.previous_seed, event.seed);
+            let on_new_daily = self.pack_level.is_none() && self.custom_seed.is_none() && self.level_file.is_none() && self.level_editor.is_none() && self.level_info.as_ref().is_some_and(|level_info| level_info.seed == event.seed);
+            if !on_new_daily {
+                self.ui.update(crate::game::ui::game_ui::Message::UpdateNewDailyAvailable(true));
+            }
+        }
+    }
+
+    /// Warn once the time server answers if the local clock is far enough off to pick the wrong daily
+    fn poll_clock_check(&mut self) {
+        match self.clock_check.poll() {
+            Some(Ok(offset)) => {
+                let warning = clock_warning(chrono::Utc::now(), offset);
+                if let Some(warning) = &warning {
+                    println!("{}", warning);
+                }
+                self.ui.update(crate::game::ui::game_ui::Message::UpdateClockWarning(warning));
+            }
+            Some(Err(e)) => println!("Couldn't check the clock: {}", e),
+            None => {}
+        }
+    }
+
+    /// Switch to the next colour scheme and save it to settings, keeping any accent colour
+    fn cycle_color_scheme(&mut self, ctx: &mut Context) {
+        let mut settings = Settings::load();
+        settings.color_scheme = Some(self.ui.theme.scheme.next());
+        let _ = settings.save();
+        let theme = settings.ui_theme();
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateTheme(theme));
+        ctx.ui.theme = theme.iced_theme();
+        self.show_toast(format!("{} theme", theme.scheme.name()));
+    }
+
+    /// Add or remove a nickname from the friends list and save it to settings
+    fn toggle_friend(&mut self, name: String) {
+        if let Some(i) = self.friends.iter().position(|friend| friend.eq_ignore_ascii_case(&name)) {
+            self.friends.remove(i);
+        } else {
+            self.friends.push(name);
+        }
+        let mut settings = Settings::load();
+        settings.friends = Some(self.friends.clone());
+        let _ = settings.save();
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateFriends(self.friends.clone()));
+    }
+
+    /// Record today's placement on the global leaderboard for the skill rating.
+    /// Only the standard daily counts so the rating stays comparable across days.
+    fn update_rating(&mut self) {
+        if self.game_mode == GameMode::Standard && !self.mirrored && self.headwind == 0.0 && self.pack_level.is_none() && self.level_file.is_none() {
+            let seed = self.leaderboard_seed();
+            if let Some((rank, num_players)) = self.leaderboard.placement(&seed, &self.current_nickname) {
+                let field_rating = self.leaderboard.field_rating(&seed, &self.current_nickname, INITIAL_RATING);
+                if self.rating.record(DailyPlacement { seed, rank, num_players, field_rating }) {
+                    if let Err(e) = self.rating.save(RATING_PATH) {
+                        eprintln!("Failed to save rating: {}", e);
+                    }
+                }
+            }
+        }
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateRatingInfo(Some(RatingInfo {
+            rating: self.rating.rating(),
+            change: self.rating.last_change(),
+            days: self.rating.placements.len(),
+        })));
+    }
+
+    /// The level file's or level pack level's name, otherwise the name of the daily's seed. Demo scenes have neither.
+    fn update_level_name(&mut self) {
+        let level_file = self.level_editor.as_ref().map(|level_editor| &level_editor.level).or(self.level_file.as_ref());
+        let name = match (level_file, find_pack_level(&self.level_packs, &self.pack_level)) {
+            (Some(level), _) => Some(if level.name.is_empty() { String::from("Custom level") } else { level.name.clone() }),
+            (None, Some((pack, level_index))) => {
+                let level_name = &pack.levels[level_index].name;
+                Some(if level_name.is_empty() { format!("{} #{}", pack.name, level_index + 1) } else { format!("{} #{}: {}", pack.name, level_index + 1, level_name) })
+            }
+            // a custom seed is shown so it can be passed on
+            (None, None) => self.level_info.as_ref().map(|level_info| match level_info.seed.strip_prefix(CUSTOM_SEED_PREFIX) {
+                Some(seed) => format!("{} (seed {})", level_name(&level_info.seed), seed),
+                None => level_name(&level_info.seed),
+            }),
+        };
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateLevelName(name));
+    }
+
+    fn update_level_packs(&mut self) {
+        let summaries = self.level_packs.packs.iter().map(|pack| LevelPackSummary {
+            id: pack.id(),
+            name: pack.name.clone(),
+            author: pack.author.clone(),
+            level_names: pack.levels.iter().map(|level| level.name.clone()).collect(),
+        }).collect();
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateLevelPacks(summaries));
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateTrainingBlocks(training_blocks(&LevelBuilder::default())));
+    }
+
+    /// Play one variant of a block on its own, as a level file so it gets a leaderboard of its own
+    fn play_training(&mut self, ctx: &mut Context, block: &str, variant: usize) {
+        match training_levels(block).map(|mut levels| levels.swap_remove(variant)) {
+            Ok(level) => {
+                self.pack_level = None;
+                self.custom_seed = None;
+                self.level_file = Some(level);
+                self.reset(ctx);
+            }
+            Err(e) => self.show_toast(format!("Can't build the training level: {}", e)),
+        }
+    }
+
+    /// Add the level I'm playing to my own pack, so it can be exported and shared
+    fn save_level_to_my_levels(&mut self) {
+        let Some(level_info) = &self.level_info else { return };
+        let level = PackLevel { name: level_info.seed.clone(), operations: level_info.operations.clone() };
+        match self.level_packs.add_to_my_levels(&self.current_nickname, level) {
+            Ok(()) => self.show_toast(String::from("Saved to My Levels")),
+            Err(e) => self.show_toast(format!("Couldn't save level: {}", e)),
+        }
+        self.update_level_packs();
+    }
+
+    /// Pick up finished index and pack downloads
+    fn poll_level_pack_browser(&mut self) {
+        for event in self.level_pack_browser.poll() {
+            match event {
+                LevelPackBrowserEvent::IndexLoaded(Ok(_)) => {
+                    self.ui.update(crate::game::ui::game_ui::Message::UpdateLevelPackListings(self.level_pack_browser.listings.clone()));
+                }
+                LevelPackBrowserEvent::IndexLoaded(Err(e)) => println!("Failed to load level pack index: {}", e),
+                LevelPackBrowserEvent::Downloaded(Ok(pack)) => {
+                    let name = pack.name.clone();
+                    match self.level_packs.install(pack) {
+                        Ok(()) => self.show_toast(format!("Installed {}", name)),
+                        Err(e) => self.show_toast(format!("Couldn't install {}: {}", name, e)),
+                    }
+                    self.update_level_packs();
+                }
+                LevelPackBrowserEvent::Downloaded(Err(e)) => self.show_toast(format!("Download failed: {}", e)),
+            }
+        }
+    }
+
+    fn update_lap_info(&mut self) {
+        if self.game_mode.num_laps() <= 1 {
+            self.ui.update(crate::game::ui::game_ui::Message::UpdateLapInfo(None));
+            return;
+        }
+
+        let Some(car) = self.entity_system.car_entity_system.0.first() else { return };
+        if let Some(best_lap) = car.best_lap() {
+            self.best_lap = Some(self.best_lap.map_or(best_lap, |b| b.min(best_lap)));
+        }
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateLapInfo(Some(LapInfo {
+            current_lap: car.current_lap(),
+            num_laps: car.num_laps,
+            lap_times: car.lap_times.clone(),
+            best_lap: self.best_lap,
+        })));
+    }
+
+    fn update_time_attack_info(&mut self, out_of_time: bool) {
+        let time_attack_info = (self.game_mode == GameMode::TimeAttack).then_some(TimeAttackInfo {
+            time_remaining: self.time_remaining,
+            checkpoints_reached: self.checkpoints_reached as u32,
+            num_checkpoints: self.entity_system.checkpoint_entity_system.entities.len() as u32,
+            out_of_time,
+        });
+        if self.ui.time_attack_info != time_attack_info {
+            self.ui.update(crate::game::ui::game_ui::Message::UpdateTimeAttackInfo(time_attack_info));
+        }
+    }
+
+    /// Time attack scores rank on checkpoints first. Reaching the finish counts as one more checkpoint so finished runs beat unfinished ones.
+    fn time_attack_checkpoints(&self, finished: bool) -> Option<u32> {
+        if self.game_mode != GameMode::TimeAttack {
+            return None;
+        }
+        Some(self.checkpoints_reached as u32 + if finished { 1 } else { 0 })
+    }
+
+    /// Stop recording and submit the score. Called when the car crosses the finish or the time attack clock runs out.
+    fn end_run(&mut self, ctx: &mut Context, checkpoints: Option<u32>) {
+        self.game_state = GameState::Finished;
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateGameState(GameState::Finished));
+
+        // practice runs can be save stated, and the editor's level changes between runs, so they don't count
+        if self.practice || self.level_editor.is_some() {
+            let seed = self.leaderboard_seed();
+            let entries = self.leaderboard.get_leaderboard_entries(&seed, &self.current_nickname, None);
+            self.ui.update(crate::game::ui::game_ui::Message::UpdateLeaderboardResults(entries));
+            self.ui.update(crate::game::ui::game_ui::Message::UpdateAttemptInfo(None));
+            return;
+        }
+        
+        let finished = self.entity_system.car_entity_system.0.iter().any(|car| car.game_ended);
+        self.telemetry.recording.finished = finished;
+        // the card compares against the sector bests from before this run
+        let best_sectors = self.split_history.level(&self.run_splits.seed).map(|level| level.best_sectors.clone()).unwrap_or_default();
+        if self.split_history.record(&self.run_splits) {
+            if let Err(e) = self.split_history.save(SPLITS_PATH) {
+                eprintln!("Failed to save splits: {}", e);
+            }
+        }
+
+        if ctx.event_system.is_recording() {
+            ctx.event_system.stop_recording();
+            // the checksums let verify tell where a re-simulation drifts, the last is on the frame the run ended
+            let frame = self.frame_idx as u32;
+            let mut checksums = self.state_checksums.clone();
+            if checksums.last().is_none_or(|checksum| checksum.frame != frame) {
+                checksums.push(StateChecksum::new(frame, &self.simulation));
+            }
+            if let Some(level_info) = &self.level_info {
+                ctx.event_system.set_recording_header(recording_header(level_info, self.math_mode, &checksums));
+            }
+            let _ = ctx.event_system.export_recording(RECORDING_PATH);
+            if let Err(e) = self.telemetry.recording.save(&TelemetryRecording::sidecar_path(RECORDING_PATH)) {
+                eprintln!("Failed to save telemetry: {}", e);
+            }
+        }
+        self.update_telemetry_analysis();
+        if finished && self.ghosts_available() {
+            self.save_ghost_replay(ctx);
+            self.ui.update(crate::game::ui::game_ui::Message::UpdateGhostExported(Some(false)));
+        }
+        
+        // only post the run if it beats what we've already posted this session, slower runs can be submitted from the finish screen
+        let seed = self.leaderboard_seed();
+        if self.session_attempts.record(&seed, self.total_time, checkpoints) {
+            self.submit_latest_attempt();
+        } else {
+            let entries = self.leaderboard.get_leaderboard_entries(&seed, &self.current_nickname, None);
+            self.ui.update(crate::game::ui::game_ui::Message::UpdateLeaderboardResults(entries));
+        }
+        self.update_attempt_info();
+        self.save_result_card(best_sectors);
+    }
+
+    /// Make the run's card, for render to put the scene behind and finish with finish_result_card
+    fn save_result_card(&mut self, best_sectors: Vec<Option<f32>>) {
+        if !self.result_card && !self.result_card_clipboard {
+            return;
+        }
+        let Some(latest) = self.session_attempts.latest() else { return };
+        let seed = self.leaderboard_seed();
+        let card = ResultCard {
+            level_name: self.ui.level_name.clone().unwrap_or_else(|| level_name(&seed)),
+            time: latest.time,
+            checkpoints: latest.checkpoints,
+            rank: latest.submitted.then(|| self.leaderboard.placement(&seed, &self.current_nickname)).flatten(),
+            sectors: (0..self.run_splits.times.len()).map(|sector| self.run_splits.sector_time(sector)).collect(),
+            best_sectors,
+            ground: self.simulation.particles.iter().filter(|p| p.imass == 0.0).map(|p| (p.pos, p.radius)).collect(),
+            attempts: self.session_attempts.attempts.len(),
+            session_best: self.session_attempts.best().map(|best| best.time),
+            scene: None,
+        };
+        self.pending_result_card = Some(card);
+    }
+
+    /// Save the card into result_cards/ for sharing and copy its picture, each if it's turned on in the settings
+    fn finish_result_card(&mut self, card: ResultCard) {
+        if self.result_card {
+            match card.save(Path::new(RESULT_CARDS_DIR)) {
+                Ok(path) => println!("Result card saved to {}", path.display()),
+                Err(e) => eprintln!("Failed to save the result card: {}", e),
+            }
+        }
+        if self.result_card_clipboard {
+            let image = card.render();
+            let image_data = arboard::ImageData {
+                width: image.width() as usize,
+                height: image.height() as usize,
+                bytes: image.into_raw().into(),
+            };
+            let copied = match &mut self.image_clipboard {
+                Some(clipboard) => clipboard.set_image(image_data),
+                None => arboard::Clipboard::new().and_then(|clipboard| self.image_clipboard.insert(clipboard).set_image(image_data)),
+            };
+            match copied {
+                Ok(()) => self.show_toast(String::from("Result card copied to the clipboard")),
+                Err(e) => eprintln!("Failed to copy the result card: {}", e),
+            }
+        }
+    }
+
+    /// Send the latest attempt to the leaderboard channels and add it to our own boards
+    fn submit_latest_attempt(&mut self) {
+        let Some(attempt) = self.session_attempts.latest().filter(|attempt| !attempt.submitted).cloned() else {
+            return;
+        };
+        self.session_attempts.mark_latest_submitted();
+        let (time, checkpoints) = (attempt.time, attempt.checkpoints);
+
+        let seed = self.leaderboard_seed();
+        let mut msg = format!("BEST_TIME seed={} time={:.3} user={}", seed, time, self.current_nickname);
+        if let Some(level_info) = &self.level_info {
+            msg.push_str(&format!(" hash={}", level_info.level_hash));
+        }
+        if let Some(checkpoints) = checkpoints {
+            msg.push_str(&format!(" checkpoints={}", checkpoints));
+        }
+        msg.push_str(&format!(" rating={:.0}", self.rating.rating()));
+        let annotations = self.run_annotations();
+        for field in annotations.to_fields() {
+            msg.push_str(&format!(" {}", field));
+        }
+        let online = self.connection.is_online();
+        if let Some(irc) = &mut self.irc_manager {
+            if online {
+                irc.send_message("#planck-leaderboard".to_owned(), msg.clone());
+                if let Some(private_channel) = &self.private_channel {
+                    irc.send_message(private_channel.clone(), msg.clone());
+                }
+            }
+            // we don't hear our own messages, so add ourselves to the recent finishers directly
+            irc.presence_mut().handle_message(&self.current_nickname, &msg, Instant::now());
+        }
+        if !online {
+            // posted when the channels are joined again, see update_network
+            self.offline_store.queue("#planck-leaderboard".to_owned(), msg.clone());
+            if let Some(private_channel) = &self.private_channel {
+                self.offline_store.queue(private_channel.clone(), msg.clone());
+            }
+        }
+        
+        self.leaderboard.add_annotated_score(seed.clone(), self.current_nickname.clone(), time, checkpoints, annotations.clone());
+
+        let entries = self.leaderboard.get_leaderboard_entries(&seed, &self.current_nickname, Some(time));
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateLeaderboardResults(entries));
+        self.update_rating();
 
-        for i in 0..particles.len() {
-            let position = cgmath::Vector3 {
-                x: particles[i].pos[0],
-                y: particles[i].pos[1],
-                z: 0.0,
-            };
+        if self.private_channel.is_some() {
+            self.private_leaderboard.add_annotated_score(seed.clone(), self.current_nickname.clone(), time, checkpoints, annotations);
+            let entries = self.private_leaderboard.get_leaderboard_entries(&seed, &self.current_nickname, Some(time));
+            self.ui.update(crate::game::ui::game_ui::Message::UpdatePrivateLeaderboardResults(entries));
+        }
+        self.store_offline_boards(&seed);
+        self.save_offline_store();
 
-            let rotation = cgmath::Quaternion::from_axis_angle(
-                cgmath::Vector3::unit_z(),
-                cgmath::Deg(0.0),
-            );
+        if let Some(top10) = self.leaderboard.get_top_10(&seed).filter(|_| online) {
+            if let Some(irc) = &self.irc_manager {
+                irc.send_message("#planck-global".to_owned(), top10);
+            }
+        }
+    }
 
-            let colour = particles[i].colour;
-            let radius = particles[i].radius;
+    /// What the run was set with, for the badges on the leaderboard
+    fn run_annotations(&self) -> ScoreAnnotations {
+        let mut modifiers = vec![];
+        match self.game_mode {
+            GameMode::Standard => {}
+            GameMode::MultiLap { num_laps } => modifiers.push(format!("laps{}", num_laps)),
+            GameMode::TimeAttack => modifiers.push(String::from("timeattack")),
+        }
+        if self.mirrored {
+            modifiers.push(String::from("mirror"));
+        }
+        if self.headwind > 0.0 {
+            modifiers.push(String::from("headwind"));
+        } else if self.headwind < 0.0 {
+            modifiers.push(String::from("tailwind"));
+        }
+        ScoreAnnotations {
+            car: Some(STANDARD_CAR.to_owned()),
+            modifiers,
+            ghost: self.ghosts_available(), // the ghost was saved when the run finished
+            verification: Verification::Unchecked,
+            set_at: Some(chrono::Utc::now().timestamp()),
+        }
+    }
 
-            instances.push(Instance { position, rotation, colour, radius });
+    /// Show the boards for the current level, eg. the ones saved from last time on starting up
+    fn update_leaderboard_results(&mut self) {
+        let seed = self.leaderboard_seed();
+        let entries = self.leaderboard.get_leaderboard_entries(&seed, &self.current_nickname, None);
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateLeaderboardResults(entries));
+        if self.private_channel.is_some() {
+            let entries = self.private_leaderboard.get_leaderboard_entries(&seed, &self.current_nickname, None);
+            self.ui.update(crate::game::ui::game_ui::Message::UpdatePrivateLeaderboardResults(entries));
         }
-        self.particle_instance_renderer.update_instances(&instances, queue, device);
     }
-    pub fn reset(&mut self, ctx: &mut Context) {
-        self.total_time = 0.0;
-        self.game_state = GameState::Playing;
-        self.frame_idx = 0;
-        
-        // Re-initialize systems
-        self.entity_system = EntitySystem::new();
-        self.particle_vec = ParticleVec::new();
-        
-        let rng = crate::core::math::random::Random::seed_from_beginning_of_day();
-        self.simulation = Simulation::new(rng);
-        
-        // Re-generate level
-        LevelBuilder::default().generate_level_based_on_date(&mut self.entity_system, &mut self.particle_vec, &mut self.simulation);
-        let car = CarEntity::new(&mut self.particle_vec, &mut self.simulation, Vec2::new(0.0, 1.0));
-        self.entity_system.car_entity_system.push(car);
-        
-        // Update UI
-        self.ui.update(crate::game::ui::game_ui::Message::UpdateGameState(GameState::Playing));
-        self.ui.update(crate::game::ui::game_ui::Message::UpdateTime(0.0));
-        
-        // Reset recording if necessary
-        let args: Vec<String> = env::args().collect();
-        let scene = if args.len() >= 2 { args[1].clone() } else { String::from("") };
-        let is_demo_scene = matches!(scene.as_str(), "friction" | "granular" | "sdf" | "boxes" | "wall" | "pendulum" | "rope" | "fluid" | "fluid_solid" | "gas" | "water_balloon" | "newtons_cradle" | "smoke_open" | "smoke_closed" | "rope_gas" | "volcano" | "wrecking_ball");
-        
-        if !is_demo_scene {
-            ctx.event_system.start_recording();
+
+    /// Keep the boards for seed to show while offline, returns true if they changed and need saving
+    fn store_offline_boards(&mut self, seed: &str) -> bool {
+        let mut changed = self.offline_store.save_board("#planck-leaderboard", seed, self.leaderboard.serialize_sync(seed));
+        if let Some(private_channel) = &self.private_channel {
+            changed |= self.offline_store.save_board(private_channel, seed, self.private_leaderboard.serialize_sync(seed));
         }
-        
-        self.update_particle_instances(&ctx.graphics.queue, &ctx.graphics.device);
+        changed
+    }
+
+    fn save_offline_store(&self) {
+        if let Err(e) = self.offline_store.save(OFFLINE_PATH) {
+            eprintln!("Failed to save the offline leaderboard: {}", e);
+        }
+    }
+
+    fn update_attempt_info(&mut self) {
+        let attempt_info = self.session_attempts.latest().map(|latest| AttemptInfo {
+            attempt: self.session_attempts.attempts.len(),
+            submitted: latest.submitted,
+            best_time: self.session_attempts.best().map(|best| best.time),
+        });
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateAttemptInfo(attempt_info));
+    }
+
+    /// Summarise the run for the analysis view, comparing against the personal best for this seed.
+    /// The personal best telemetry is replaced if this run was faster.
+    fn update_telemetry_analysis(&mut self) {
+        let recording = &self.telemetry.recording;
+        if recording.frames.is_empty() {
+            self.ui.update(crate::game::ui::game_ui::Message::UpdateTelemetryAnalysis(None));
+            return;
+        }
+
+        let pb = TelemetryRecording::load(PB_TELEMETRY_PATH).ok()
+            .filter(|pb| pb.seed == recording.seed && pb.finished);
+
+        let num_buckets = 40;
+        let max_distance = recording.total_distance().max(pb.as_ref().map_or(0.0, |pb| pb.total_distance()));
+        let analysis = TelemetryAnalysis {
+            speeds: recording.speed_over_distance(max_distance, num_buckets),
+            pb_speeds: pb.as_ref().map_or(vec![], |pb| pb.speed_over_distance(max_distance, num_buckets)),
+            top_speed: recording.top_speed(),
+            distance: recording.total_distance(),
+            airtime: recording.total_airtime(self.time_delta()),
+            pb_time: pb.as_ref().and_then(|pb| pb.total_time()),
+        };
+
+        let is_new_pb = recording.finished && match (recording.total_time(), analysis.pb_time) {
+            (Some(time), Some(pb_time)) => time < pb_time,
+            _ => true,
+        };
+        if is_new_pb {
+            if let Err(e) = recording.save(PB_TELEMETRY_PATH) {
+                eprintln!("Failed to save personal best telemetry: {}", e);
+            }
+        }
+
+        self.ui.update(crate::game::ui::game_ui::Message::UpdateTelemetryAnalysis(Some(analysis)));
+    }
+
+    /// Set the commentary and stream overlay up for a new run, comparing it against my personal best on this level
+    fn start_following_run(&mut self) {
+        if self.commentary.is_none() && self.stream_overlay.is_none() {
+            return;
+        }
+        let seed = self.leaderboard_seed();
+        let pb = TelemetryRecording::load(PB_TELEMETRY_PATH).ok()
+            .filter(|pb| pb.seed == seed && pb.finished);
+        let checkpoints = &self.entity_system.checkpoint_entity_system.entities;
+        if let Some(commentary) = &mut self.commentary {
+            let start = self.entity_system.car_entity_system.0.first().map_or(Vec2::new(0.0, 1.0), |car| car.get_camera_look_at_position(&self.simulation.particles));
+            commentary.start_run(pb.as_ref(), checkpoints, start);
+        }
+        if let Some(stream_overlay) = &mut self.stream_overlay {
+            stream_overlay.start_run(pb.as_ref().map_or(vec![], |pb| pb.checkpoint_times(checkpoints)), pb.as_ref().and_then(|pb| pb.total_time()));
+        }
+    }
+
+    /// Pass the gameplay events from the last entity update on to the split times, stream overlay and commentary,
+    /// also posting the commentary to its channel when online
+    fn handle_gameplay_events(&mut self) {
+        for event in &self.entity_system.events {
+            if let Some(split) = self.run_splits.handle_event(event, &self.split_history) {
+                self.ui.update(crate::game::ui::game_ui::Message::UpdateSplit(Some(split)));
+            }
+        }
+        if let Some(stream_overlay) = &mut self.stream_overlay {
+            stream_overlay.handle_events(&self.entity_system.events);
+        }
+        let Some(commentary) = &self.commentary else { return };
+        let lines = commentary.narrate(&self.entity_system.events);
+        let (Some(channel), Some(irc_manager)) = (&commentary.channel, &self.irc_manager) else { return };
+        if !self.connection.is_online() {
+            return;
+        }
+        for line in lines {
+            irc_manager.send_message(channel.clone(), format!("{}: {}", self.current_nickname, line));
+        }
+    }
+
+    /// Find the wheel particles touching something that isn't part of the car, as (wheel particle, other particle),
+    /// and count the wheel particles. Has to happen while the contact constraints exist, between pre_solve and post_solve.
+    fn update_car_contacts(&mut self) {
+        self.car_particles.clear();
+        self.car_surface_particles.clear();
+        self.car_contact_pairs.clear();
+        self.car_contacts = 0;
+        let Some(car) = self.entity_system.car_entity_system.0.first() else { return };
+        self.car_particles.extend(car.particle_handles());
+        self.car_surface_particles.extend(car.surface_particle_handles());
+
+        for (i1, i2) in self.simulation.contact_pairs() {
+            if self.car_surface_particles.contains(&i1) && !self.car_particles.contains(&i2) {
+                self.car_contact_pairs.push((i1, i2));
+            } else if self.car_surface_particles.contains(&i2) && !self.car_particles.contains(&i1) {
+                self.car_contact_pairs.push((i2, i1));
+            }
+        }
+        // each wheel particle is only in the set once, so taking them out counts them once
+        for (wheel, _) in &self.car_contact_pairs {
+            self.car_contacts += self.car_surface_particles.remove(wheel) as u32;
+        }
+    }
+
+    /// Leaderboard sync, presence, level pack and ghost messages from IRC, plus finished downloads
+    fn update_network(&mut self) {
+        self.poll_level_pack_browser();
+
+        let seed = self.leaderboard_seed();
+        let mut toasts = Vec::new();
+        let mut leaderboard_changed = false;
+        let mut private_leaderboard_changed = false;
+        let mut pending_sent = false;
+        let mut level_pack_listings_changed = false;
+        let mut ghost_replays = Vec::new();
+        if let Some(irc) = &mut self.irc_manager {
+            for event in irc.process_events() {
+                match event {
+                    IrcEvent::MessageReceived { target, sender, message } => {
+                        // an unsubmitted run isn't on the board to highlight
+                        let run_submitted = self.session_attempts.latest().is_some_and(|attempt| attempt.submitted);
+                        let current_run_time = if self.game_state == GameState::Finished && run_submitted { Some(self.total_time) } else { None };
+                        let is_leaderboard_channel = target == "#planck-leaderboard" || self.private_channel.as_ref().is_some_and(|channel| channel.eq_ignore_ascii_case(&target));
+                        if let Some(request_seed) = parse_leaderboard_request(&message).filter(|_| is_leaderboard_channel) {
+                            if answers_request(&self.current_nickname, &sender, &irc.presence().players_on(&request_seed, Instant::now())) {
+                                let board = if target == "#planck-leaderboard" { &self.leaderboard } else { &self.private_leaderboard };
+                                for sync_msg in board.serialize_sync(&request_seed) {
+                                    irc.send_message(target.clone(), sync_msg);
+                                }
+                            }
+                        } else if target == "#planck-leaderboard" {
+                            let is_best_time = message.starts_with("BEST_TIME");
+                            let friends_ahead = self.leaderboard.friends_ahead(&seed, &self.current_nickname, &self.friends);
+                            for sync_msg in self.leaderboard.handle_message(&message, &seed) {
+                                irc.send_message(target.clone(), sync_msg);
+                            }
+                            // rivalry, let me know when a friend posts a time that beats mine
+                            if is_best_time {
+                                for friend in self.leaderboard.friends_ahead(&seed, &self.current_nickname, &self.friends) {
+                                    if !friends_ahead.contains(&friend) {
+                                        toasts.push(format!("{} just beat your time!", friend));
+                                    }
+                                }
+                            }
+                            let entries = self.leaderboard.get_leaderboard_entries(&seed, &self.current_nickname, current_run_time);
+                            self.ui.update(crate::game::ui::game_ui::Message::UpdateLeaderboardResults(entries));
+                            leaderboard_changed = true;
+                        } else if self.private_channel.as_ref().is_some_and(|channel| channel.eq_ignore_ascii_case(&target)) {
+                            for sync_msg in self.private_leaderboard.handle_message(&message, &seed) {
+                                irc.send_message(target.clone(), sync_msg);
+                            }
+                            let entries = self.private_leaderboard.get_leaderboard_entries(&seed, &self.current_nickname, current_run_time);
+                            self.ui.update(crate::game::ui::game_ui::Message::UpdatePrivateLeaderboardResults(entries));
+                            private_leaderboard_changed = true;
+                        } else if target == LEVEL_PACKS_CHANNEL {
+                            level_pack_listings_changed |= self.level_pack_browser.handle_message(&sender, &message);
+                        } else if target == REPLAYS_CHANNEL {
+                            if let Some((request_seed, user)) = parse_request(&message) {
+                                let cooled_down = self.last_replay_sent.is_none_or(|sent| sent.elapsed().as_secs_f32() >= REPLAY_SEND_COOLDOWN);
+                                if user == self.current_nickname && cooled_down {
+                                    if let Some(chunks) = GhostReplay::load(&request_seed).as_ref().and_then(chunk_messages) {
+                                        for chunk in chunks {
+                                            irc.send_message(target.clone(), chunk);
+                                        }
+                                        self.last_replay_sent = Some(Instant::now());
+                                    }
+                                }
+                            } else if let Some(replay) = self.replay_assembler.handle_message(&message) {
+                                ghost_replays.push(replay);
+                            }
+                        }
+                    },
+                    // catch up on the board if I've started after the others finished
+                    IrcEvent::JoinedChannel(channel) if channel == "#planck-leaderboard" || self.private_channel.as_ref().is_some_and(|private_channel| private_channel.eq_ignore_ascii_case(&channel)) => {
+                        if channel == "#planck-leaderboard" {
+                            self.connection.handle_joined(Instant::now());
+                        }
+                        // times set while offline
+                        let pending = self.offline_store.take_pending(&channel);
+                        if channel == "#planck-leaderboard" && !pending.is_empty() {
+                            toasts.push(format!("Back online, posted {} saved time{}", pending.len(), if pending.len() == 1 { "" } else { "s" }));
+                        }
+                        for msg in pending {
+                            irc.send_message(channel.clone(), msg);
+                            pending_sent = true;
+                        }
+                        irc.send_message(channel, leaderboard_request_message(&seed));
+                    }
+                    IrcEvent::Disconnected => self.connection.handle_disconnected(Instant::now()),
+                    _ => {}
+                }
+            }
+
+            irc.update_presence(&seed, self.game_state == GameState::Playing);
+
+            // refresh the presence info about once a second
+            if self.frame_idx.is_multiple_of(60) {
+                let now = Instant::now();
+                let presence = irc.presence();
+                let presence_info = PresenceInfo {
+                    online: presence.online_count(now),
+                    racing: presence.racing_count(&seed, now),
+                    recent_finishers: presence.recent_finishers().map(|f| (f.user.clone(), f.time)).collect(),
+                };
+                self.ui.update(crate::game::ui::game_ui::Message::UpdatePresenceInfo(Some(presence_info)));
+            }
+        }
+
+        // keep retrying in the background while offline, unless built without IRC, then it never gets through
+        let now = Instant::now();
+        if cfg!(feature = "irc") && self.irc_manager.is_some() && self.connection.reconnect_due(now) {
+            println!("Reconnecting to IRC");
+            self.irc_manager = Some(connect_irc(self.current_nickname.clone(), &Settings::load()));
+            self.connection = Connection::connecting(now);
+        }
+        let offline = (self.irc_manager.is_some() && self.connection.is_offline(now)).then(|| self.offline_store.num_pending("#planck-leaderboard"));
+        if self.ui.offline != offline {
+            self.ui.update(crate::game::ui::game_ui::Message::UpdateOffline(offline));
+        }
+
+        let boards_changed = (leaderboard_changed || private_leaderboard_changed) && self.store_offline_boards(&seed);
+        if boards_changed || pending_sent {
+            self.save_offline_store();
+        }
+        for toast in toasts {
+            self.show_toast(toast);
+        }
+        // later finishers can push me down today's placings
+        if leaderboard_changed {
+            self.update_rating();
+            if self.game_state == GameState::PreRace {
+                self.update_ghost_targets();
+            }
+        }
+        for replay in ghost_replays {
+            self.add_ghost_replay(replay);
+        }
+        if level_pack_listings_changed {
+            self.ui.update(crate::game::ui::game_ui::Message::UpdateLevelPackListings(self.level_pack_browser.listings.clone()));
+        }
+    }
+
+    /// Length of a simulation step, the physics tuning panel can change it
+    fn time_delta(&self) -> f32 {
+        self.physics_tuning.map_or(0.005, |physics_tuning| physics_tuning.time_delta)
     }
 
     pub fn step_simulation(&mut self, time_delta: f32) -> f32 {
         let start = Instant::now();
         
         self.simulation.pre_solve(time_delta);
+        self.update_car_contacts();
+        self.decals.update(&self.car_contact_pairs, &self.simulation.particles, time_delta);
+        let slip = self.entity_system.car_entity_system.0.first().map_or(0.0, |car| car.slip());
+        self.rumble.update(&self.car_contact_pairs, &self.simulation.particles, slip, time_delta);
+        if let Some(car) = self.entity_system.car_entity_system.0.first() {
+            self.car_audio.collect_hits(car, &self.simulation);
+        }
         self.entity_system.elevator_entity_system.update_counts(&mut self.simulation);
 
-        for i in 0..3 {
-            self.simulation.solve(time_delta, 3, i);
-            self.entity_system.elevator_entity_system.solve_constraints(&mut self.simulation, time_delta);
+        // falls back to the CPU for scenes the GPU solver doesn't support
+        let solved_on_gpu = self.entity_system.elevator_entity_system.0.is_empty()
+            && self.gpu_solver.as_mut().is_some_and(|gpu_solver| gpu_solver.solve(&mut self.simulation));
+        if !solved_on_gpu {
+            let elevators = &mut self.entity_system.elevator_entity_system;
+            self.simulation.solve_adaptive(time_delta, |simulation| elevators.solve_constraints(simulation, time_delta));
         }
         self.simulation.post_solve(time_delta);
 
@@ -127,26 +1659,67 @@ impl GameLoop for Game {
         let mut particle_vec = ParticleVec::new();
         
         let rng = crate::core::math::random::Random::seed_from_beginning_of_day();
-        let mut simulation = Simulation::new(rng);
+        let math_mode = Settings::load().math_mode.unwrap_or_default();
+        let mut simulation = Simulation::with_math_mode(rng, math_mode);
 
         let particle_instance_renderer = InstanceRenderer::new(&ctx.graphics.device, &ctx.graphics.queue, &ctx.graphics.config);
         let quad_mesh = Mesh::from_verticies_and_indicies("Quad".to_owned(), &ctx.graphics.device, QUAD_VERTICES, QUAD_INDICES);
         let material = Material::from_file("marble.png".to_owned(), &ctx.graphics.device, &ctx.graphics.queue);
-        let camera = Camera::new(&ctx.graphics.device, ctx.graphics.config.width as f32 / ctx.graphics.config.height as f32);
+        let mut camera = Camera::new(&ctx.graphics.device, ctx.graphics.config.width as f32 / ctx.graphics.config.height as f32);
+        let camera_constraint = CameraConstraint::new(&camera);
         
         let diffuse_texture = &material.diffuse_texture;
 
+        precompile_shaders(&mut ctx.graphics);
+
         let particle_shader = ShaderBuilder::from_file("particle_shader.wgsl".to_owned(), &ctx.graphics.device)
             .camera(&camera)
             .diffuse_texture(diffuse_texture)
-            .build(&[Vertex::desc(), InstanceRaw::desc()], ctx.graphics.config.format);
+            .build_cached(&mut ctx.graphics.pipeline_cache, &[Vertex::desc(), InstanceRaw::desc()], ctx.graphics.config.format);
         
         let line_shader = ShaderBuilder::from_file("line_shader.wgsl".to_owned(), &ctx.graphics.device)
             .camera(&camera)
-            .build(&[Vertex::desc(), InstanceRaw::desc()], ctx.graphics.config.format);
+            .build_cached(&mut ctx.graphics.pipeline_cache, &[Vertex::desc(), InstanceRaw::desc()], ctx.graphics.config.format);
+
+        let decal_instance_renderer = InstanceRenderer::new(&ctx.graphics.device, &ctx.graphics.queue, &ctx.graphics.config);
+        let decal_shader = ShaderBuilder::from_file("decal_shader.wgsl".to_owned(), &ctx.graphics.device)
+            .blend(wgpu::BlendState::ALPHA_BLENDING)
+            .camera(&camera)
+            .build_cached(&mut ctx.graphics.pipeline_cache, &[Vertex::desc(), InstanceRaw::desc()], ctx.graphics.config.format);
+
+        let text_renderer = TextRenderer::new(&ctx.graphics.device, &ctx.graphics.queue);
+        let text_shader = ShaderBuilder::from_file("text_shader.wgsl".to_owned(), &ctx.graphics.device)
+            .camera(&camera)
+            .diffuse_texture(text_renderer.texture())
+            .build_cached(&mut ctx.graphics.pipeline_cache, &[Vertex::desc(), GlyphInstanceRaw::desc()], ctx.graphics.config.format);
+        let gpu_timer = GpuTimer::new(&ctx.graphics.device, &ctx.graphics.queue);
 
         let args: Vec<String> = env::args().collect();
         let scene = if args.len() >= 2 { args[1].clone() } else { String::from("") };
+        let game_mode = GameMode::from_args(&args);
+        let mirrored = args.iter().any(|arg| arg == "--mirror");
+        let headwind = args.iter().position(|arg| arg == "--headwind").and_then(|i| args.get(i + 1)).and_then(|speed| speed.parse::<f32>().ok()).unwrap_or(0.0);
+        // tuned physics plays differently, so those runs are practice and never posted
+        let practice = args.iter().any(|arg| arg == "--practice" || arg == "--tuning");
+        let energy_diagnostics = args.iter().any(|arg| arg == "--diagnostics").then(|| EnergyDiagnostics::new(ENERGY_SAMPLES));
+        let physics_tuning = args.iter().any(|arg| arg == "--tuning").then(PhysicsTuning::default);
+        // --pack <pack id> [--pack-level <n>] plays an installed level pack level instead of the daily
+        let level_packs = LevelPackLibrary::load(Path::new(LEVEL_PACKS_DIR));
+        let mut pack_level = args.iter().position(|arg| arg == "--pack").and_then(|i| args.get(i + 1)).map(|id| {
+            let level = args.iter().position(|arg| arg == "--pack-level").and_then(|i| args.get(i + 1)).and_then(|n| n.parse::<usize>().ok()).unwrap_or(1);
+            (id.clone(), level.max(1) - 1)
+        });
+        // --seed <anything> plays that seed's level on its own board instead of the daily
+        let custom_seed = args.iter().position(|arg| arg == "--seed").and_then(|i| args.get(i + 1)).and_then(|seed| custom_seed(seed));
+        // editor [--level <level file>] puts a level together block by block, --level on its own plays a saved one
+        let level_path = args.iter().position(|arg| arg == "--level").and_then(|i| args.get(i + 1)).map(PathBuf::from);
+        let mut level_editor = if scene == "editor" {
+            let path = level_path.clone().unwrap_or_else(|| PathBuf::from(EDITOR_LEVEL_PATH));
+            LevelEditor::open(&path, LevelBuilder::default().registry()).map_err(|e| eprintln!("Failed to open {} in the editor: {}", path.display(), e)).ok()
+        } else {
+            None
+        };
+        let level_file = level_path.filter(|_| scene != "editor").and_then(|path| PackLevel::load(&path).map_err(|e| eprintln!("Failed to load level {}: {}", path.display(), e)).ok());
         
         let replay_file = if args.len() >= 3 && args[1] == "replay" {
             Some(args[2].clone())
@@ -154,27 +1727,33 @@ impl GameLoop for Game {
             None
         };
         
+        let ghosts_enabled = replay_file.is_none();
+        let scene_path = args.iter().position(|arg| arg == "--file").and_then(|i| args.get(i + 1)).map(PathBuf::from);
+        let mut level_info = None;
         let is_demo_scene = match scene.as_str() {
-            "friction" => { SimulationDemos::init_friction(&mut simulation); true }
-            "granular" => { SimulationDemos::init_granular(&mut simulation); true }
-            "sdf" => { SimulationDemos::init_sdf(&mut simulation); true }
-            "boxes" => { SimulationDemos::init_boxes(&mut simulation); true }
-            "wall" => { SimulationDemos::init_wall(&mut simulation); true }
-            "pendulum" => { SimulationDemos::init_pendulum(&mut simulation); true }
-            "rope" => { SimulationDemos::init_rope(&mut simulation); true }
-            "fluid" => { SimulationDemos::init_fluid(&mut simulation); true }
-            "fluid_solid" => { SimulationDemos::init_fluid_solid(&mut simulation); true }
-            "gas" => { SimulationDemos::init_gas(&mut simulation); true }
-            "water_balloon" => { SimulationDemos::init_water_balloon(&mut simulation); true }
-            "newtons_cradle" => { SimulationDemos::init_newtons_cradle(&mut simulation); true }
-            "smoke_open" => { SimulationDemos::init_smoke_open(&mut simulation); true }
-            "smoke_closed" => { SimulationDemos::init_smoke_closed(&mut simulation); true }
-            "rope_gas" => { SimulationDemos::init_rope_gas(&mut simulation); true }
-            "volcano" => { SimulationDemos::init_volcano(&mut simulation); true }
-            "wrecking_ball" => { SimulationDemos::init_wrecking_ball(&mut simulation); true }
+            name if SimulationDemos::init(name, &mut simulation) => true,
+            // demo --file <scene file> plays a scene saved from the sandbox
+            "demo" => {
+                if let Some(path) = &scene_path {
+                    match SceneFile::load(path) {
+                        Ok(scene_file) => scene_file.build(&mut simulation),
+                        Err(e) => eprintln!("Failed to load scene: {}", e),
+                    }
+                }
+                true
+            }
             "replay" | _ => {
-                LevelBuilder::default().generate_level_based_on_date(&mut entity_system, &mut particle_vec, &mut simulation);
-                let car = CarEntity::new(&mut particle_vec, &mut simulation, Vec2::new(0.0, 1.0));
+                let level = level_editor.as_ref().map(|level_editor| &level_editor.level).or(level_file.as_ref());
+                let info = match level.and_then(|level| generate_level_file(level, level_builder(mirrored, headwind), &mut entity_system, &mut particle_vec, &mut simulation)) {
+                    Some(info) => info,
+                    None => generate_level(&level_packs, &mut pack_level, custom_seed.as_deref(), level_builder(mirrored, headwind), &mut entity_system, &mut particle_vec, &mut simulation),
+                };
+                if let Some(level_editor) = &mut level_editor {
+                    level_editor.level_built(&info.operations);
+                }
+                level_info = Some(info);
+                let mut car = CarEntity::new(&mut particle_vec, &mut simulation, Vec2::new(0.0, 1.0));
+                car.num_laps = game_mode.num_laps();
                 entity_system.car_entity_system.push(car);
                 false
             }
@@ -184,25 +1763,50 @@ impl GameLoop for Game {
             if let Err(e) = ctx.event_system.load_replay(&replay_path) {
                 eprintln!("Failed to load replay file '{}': {}", replay_path, e);
             } else {
+                // warn if the replay was recorded on a different level, it will most likely play back incorrectly
+                let replay_level_hash = ctx.event_system.replay_header()
+                    .and_then(|header| header.get("level_hash"))
+                    .and_then(|hash| hash.as_str());
+                let level_hash = level_info.as_ref().map(|info| info.level_hash.as_str());
+                if let (Some(replay_level_hash), Some(level_hash)) = (replay_level_hash, level_hash) {
+                    if replay_level_hash != level_hash {
+                        eprintln!("Replay was recorded on a different level (level hash {} vs {})", replay_level_hash, level_hash);
+                    }
+                }
                 ctx.event_system.start_replay();
             }
-        } else if !is_demo_scene {
+        } else if !is_demo_scene && !practice && level_editor.is_none() {
+            ctx.event_system.set_recording_header(level_info.as_ref().and_then(|info| recording_header(info, math_mode, &[])));
             ctx.event_system.start_recording();
         }
 
+        // load any plugins up front so a broken one shows up in the log at startup, not mid level generation
+        let num_plugin_operations = plugins().operations.len();
+        if num_plugin_operations > 0 || !plugins().force_fields.is_empty() {
+            println!("Plugins registered {} level blocks and {} force fields", num_plugin_operations, plugins().force_fields.len());
+        }
+
         let settings = Settings::load();
-        let (game_state, nickname) = if let Some(name) = settings.player_name {
+        camera.projection = settings.camera_projection.unwrap_or_default();
+
+        let dynamic_resolution = DynamicResolution::new(settings.render_scale_min.unwrap_or(0.5), settings.render_scale_max.unwrap_or(1.0), settings.frame_budget_ms.unwrap_or(16.0));
+        ctx.graphics.set_render_scale(dynamic_resolution.scale());
+        let upscaler = Upscaler::new(&ctx.graphics.device, ctx.graphics.config.format);
+
+        // dailies always solve on the CPU, the GPU solver doesn't give the same results
+        let use_gpu_solver = is_demo_scene && (args.iter().any(|arg| arg == "--gpu-solver") || settings.gpu_solver.unwrap_or(false));
+        let gpu_solver = use_gpu_solver.then(|| GpuSolver::new(&ctx.graphics.device, &ctx.graphics.queue, &ctx.graphics.adapter)).flatten();
+        if use_gpu_solver && gpu_solver.is_none() {
+            println!("The GPU can't run compute shaders, solving on the CPU");
+        }
+        let (game_state, nickname) = if let Some(name) = settings.player_name.clone() {
             (GameState::Playing, name)
         } else {
-            (GameState::NameEntry, format!("Player{}", chrono::Utc::now().timestamp_subsec_micros()))
+            (GameState::NameEntry, random_nickname(&mut Random::seed_from_now()))
         };
 
         let irc_manager = if game_state == GameState::Playing {
-            Some(IrcManager::new(
-                 "irc.libera.chat".to_owned(),
-                 nickname.clone(),
-                 vec!["#planck-global".to_owned(), "#planck-leaderboard".to_owned()]
-            ))
+            Some(connect_irc(nickname.clone(), &settings))
         } else {
             None
         };
@@ -210,28 +1814,153 @@ impl GameLoop for Game {
         let mut ui = crate::game::ui::game_ui::GameUI::new();
         ui.update(crate::game::ui::game_ui::Message::UpdateGameState(game_state));
         ui.update(crate::game::ui::game_ui::Message::UpdateShowDebugInfo(settings.show_debug_info.unwrap_or(true)));
+        let private_channel = settings.private_channel();
+        ui.update(crate::game::ui::game_ui::Message::UpdatePrivateChannel(private_channel.clone()));
+        let friends = settings.friends.clone().unwrap_or_default();
+        ui.update(crate::game::ui::game_ui::Message::UpdateFriends(friends.clone()));
+        ui.update(crate::game::ui::game_ui::Message::UpdatePractice(practice));
+        ui.update(crate::game::ui::game_ui::Message::UpdatePhysicsTuning(physics_tuning));
+        let theme = settings.ui_theme();
+        ui.update(crate::game::ui::game_ui::Message::UpdateTheme(theme));
+        ctx.ui.theme = theme.iced_theme();
+        ctx.audio.master_volume = settings.master_volume.unwrap_or(1.0).clamp(0.0, 1.0);
+        ctx.audio.effects_volume = settings.effects_volume.unwrap_or(1.0).clamp(0.0, 1.0);
+        ctx.audio.engine_volume = settings.engine_volume.unwrap_or(1.0).clamp(0.0, 1.0);
+
+        let mut leaderboard = Leaderboard::new();
+        let mut private_leaderboard = Leaderboard::new();
+        if let Some(level_info) = &level_info {
+            leaderboard.set_level_hash(&level_info.seed, Some(level_info.level_hash.clone()));
+            private_leaderboard.set_level_hash(&level_info.seed, Some(level_info.level_hash.clone()));
+        }
+        // the boards as they were last seen, until IRC catches us up or if it can't be reached
+        let offline_store = OfflineStore::load(OFFLINE_PATH);
+        for sync_msg in offline_store.boards_for("#planck-leaderboard") {
+            leaderboard.parse_sync_message(sync_msg);
+        }
+        if let Some(private_channel) = &private_channel {
+            for sync_msg in offline_store.boards_for(private_channel) {
+                private_leaderboard.parse_sync_message(sync_msg);
+            }
+        }
 
         let mut game = Self {
             camera,
             camera_controller,
+            camera_constraint,
+            camera_director: CameraDirector::new(settings.camera_mode.unwrap_or(CameraMode::Free)),
             particle_vec,
             particle_instance_renderer,
             quad_mesh,
             material,
             particle_shader,
             line_shader,
+            text_renderer,
+            text_shader,
+            gpu_timer,
+            dynamic_resolution,
+            upscaler,
+            distance_markers: vec![],
             frame_idx: 0,
             entity_system,
             simulation,
             total_time: 0.0,
             game_state,
             irc_manager,
+            connection: Connection::connecting(Instant::now()),
+            offline_store,
             current_nickname: nickname,
-            leaderboard: Leaderboard::new(),
+            leaderboard,
+            private_channel,
+            private_leaderboard,
+            friends,
+            toast_expiry: None,
+            session_attempts: SessionAttempts::default(),
+            seed_rollover: SeedRolloverWatcher::new(chrono::Utc::now()),
+            clock_check: ClockCheck::start(settings.time_server()),
+            rating: RatingHistory::load(RATING_PATH),
             ui,
+            level_info,
+            game_mode,
+            best_lap: None,
+            time_remaining: TIME_ATTACK_START_TIME,
+            checkpoints_reached: 0,
+            mirrored,
+            headwind,
+            practice,
+            save_states: SaveStateRing::new(),
+            level_packs,
+            level_pack_browser: LevelPackBrowser::new(),
+            pack_level,
+            custom_seed,
+            level_file,
+            level_editor,
+            editor_click: None,
+            ghosts_enabled: ghosts_enabled && !is_demo_scene,
+            ghosts: vec![],
+            ghost_seed: String::new(),
+            ghost_targets: vec![],
+            ghost_replays: HashMap::new(),
+            ghost_tracks: HashMap::new(),
+            selected_ghosts: vec![],
+            requested_ghosts: vec![],
+            show_ghost_camera: settings.show_ghost_camera.unwrap_or(false),
+            ghost_camera: None,
+            replay_assembler: ReplayAssembler::new(),
+            last_replay_sent: None,
+            telemetry: TelemetryRecorder::new(String::new()),
+            split_history: SplitHistory::load(SPLITS_PATH),
+            run_splits: RunSplits::default(),
+            ghost_track: GhostTrackRecorder::new(),
+            state_checksums: Vec::new(),
+            math_mode,
+            energy_diagnostics,
+            physics_tuning,
+            sandbox: is_demo_scene.then(|| Sandbox::new(scene_path)),
+            gpu_solver,
+            car_contacts: 0,
+            car_contact_pairs: vec![],
+            car_particles: HashSet::new(),
+            car_surface_particles: HashSet::new(),
+            decals: Decals::new(),
+            rumble: Rumble::new(),
+            car_audio: CarAudio::new(),
+            commentary: (settings.commentary.unwrap_or(false) && !is_demo_scene).then(|| Commentary::new(settings.commentary_channel(), COMMENTARY_LOG_PATH)),
+            stream_overlay: (settings.stream_overlay.unwrap_or(false) && !is_demo_scene).then(|| StreamOverlay::new(Some(OVERLAY_PATH), settings.stream_overlay_port)),
+            anomaly_capture: AnomalyCapture::new(),
+            rumble_intensity: settings.rumble_intensity.unwrap_or_default(),
+            result_card: settings.result_card.unwrap_or(false),
+            result_card_clipboard: settings.result_card_clipboard.unwrap_or(false),
+            pending_result_card: None,
+            image_clipboard: None,
+            decal_instance_renderer,
+            decal_shader,
+            instances: vec![],
+            level_labels: vec![],
         };
+        game.telemetry = TelemetryRecorder::new(game.leaderboard_seed());
+        game.run_splits = RunSplits::new(game.leaderboard_seed(), game.entity_system.checkpoint_entity_system.entities.len());
+        game.start_following_run();
 
+        game.update_lap_info();
+        game.update_time_attack_info(false);
+        game.update_rating();
+        game.update_leaderboard_results();
+        game.update_level_packs();
+        game.update_level_name();
+        game.update_sandbox_info();
+        game.update_level_editor_info();
+        if game.game_state == GameState::Playing && game.ghosts_available() {
+            game.game_state = GameState::PreRace;
+            game.ui.update(crate::game::ui::game_ui::Message::UpdateGameState(GameState::PreRace));
+            game.update_ghost_targets();
+        }
+        if let Some(index_url) = settings.level_pack_index_url.as_ref().filter(|url| !url.is_empty()) {
+            game.level_pack_browser.refresh_index(index_url.clone());
+        }
+        game.update_distance_markers();
         game.update_particle_instances(&ctx.graphics.queue, &ctx.graphics.device);
+        game.update_world_labels(&ctx.graphics.queue, &ctx.graphics.device);
         game
     }
 
@@ -239,23 +1968,112 @@ impl GameLoop for Game {
         let start = Instant::now();
         let dt = if ctx.dt <= 0.0 { 1.0 / 60.0 } else { ctx.dt };
         let fps = (1.0 / dt).round() as i32;
-        self.ui.update(crate::game::ui::game_ui::Message::UpdateFps(fps));
+        if self.ui.fps != fps {
+            self.ui.update(crate::game::ui::game_ui::Message::UpdateFps(fps));
+        }
 
         self.frame_idx += 1;
         ctx.event_system.set_frame(self.frame_idx);
         ctx.event_system.process_events();
 
         let mut should_reset = false;
+        let mut should_start_race = false;
+        let mut should_quicksave = false;
+        let mut should_quickload = None; // Some(true) to step back to an older save
+        let mut should_cycle_color_scheme = false;
+        let mut should_toggle_ghost_camera = false;
+        let mut should_toggle_camera_projection = false;
+        let mut should_cycle_camera_mode = false;
+        let mut should_cycle_rumble_intensity = false;
+        let mut should_save_scene = false;
+        let mut should_load_scene = false;
+        let mut should_delete_editor_block = false;
+        // last frame's click, now the UI has had it too
+        let editor_click = self.editor_click.take().filter(|_| !ctx.ui.click_captured);
         for event in ctx.event_system.events.iter() {
-            match event {
+            match &event.event {
                 GameEvent::KeyboardInput { key_code, state } => {
                     let is_pressed = matches!(state, ElementStateType::Pressed);
-                    self.camera_controller.handle_key(*key_code, is_pressed);
-                    self.entity_system.handle_key(*key_code, is_pressed);
+                    if event.context.drives_car() {
+                        self.camera_controller.handle_key(*key_code, is_pressed);
+                        self.entity_system.handle_key(*key_code, is_pressed);
+                    }
+                    // the car waits while ghosts are being picked
+                    if self.game_state == GameState::PreRace && event.context == InputContext::Menu {
+                        should_start_race |= *key_code == KeyCodeType::Space && is_pressed;
+                    }
                     
-                    if *key_code == KeyCodeType::KeyR && is_pressed && self.game_state == GameState::Finished {
+                    // ignore hotkeys while typing, eg. in the leaderboard search box
+                    let hotkeys_enabled = self.game_state == GameState::Finished && !event.context.is_text_entry();
+                    if *key_code == KeyCodeType::KeyR && is_pressed && hotkeys_enabled {
                         should_reset = true;
                     }
+                    if *key_code == KeyCodeType::KeyT && is_pressed && hotkeys_enabled {
+                        self.ui.update(crate::game::ui::game_ui::Message::UpdateShowAnalysis(!self.ui.show_analysis));
+                    }
+                    if *key_code == KeyCodeType::KeyP && is_pressed && hotkeys_enabled {
+                        self.ui.update(crate::game::ui::game_ui::Message::UpdateShowLevelPacks(!self.ui.show_level_packs));
+                    }
+
+                    // practice save states: F5 save, F9 load, F8 load an older save
+                    if self.practice && is_pressed && matches!(self.game_state, GameState::Playing | GameState::Finished) {
+                        match key_code {
+                            KeyCodeType::F5 if self.game_state == GameState::Playing => should_quicksave = true,
+                            KeyCodeType::F9 => should_quickload = Some(false),
+                            KeyCodeType::F8 => should_quickload = Some(true),
+                            _ => {}
+                        }
+                    }
+
+                    should_cycle_color_scheme |= *key_code == KeyCodeType::F10 && is_pressed;
+                    should_toggle_ghost_camera |= *key_code == KeyCodeType::KeyG && is_pressed && self.game_state == GameState::Playing;
+                    should_toggle_camera_projection |= *key_code == KeyCodeType::KeyO && is_pressed && !event.context.is_text_entry();
+                    should_cycle_camera_mode |= *key_code == KeyCodeType::KeyV && is_pressed && !event.context.is_text_entry();
+
+                    should_delete_editor_block |= self.level_editor.is_some() && *key_code == KeyCodeType::Delete && is_pressed && event.context == InputContext::Editor;
+
+                    if let Some(sandbox) = self.sandbox.as_mut().filter(|_| is_pressed && event.context == InputContext::Gameplay) {
+                        match key_code {
+                            KeyCodeType::KeyB => sandbox.toggle_tool(),
+                            KeyCodeType::Delete => { sandbox.delete(&mut self.simulation); }
+                            KeyCodeType::KeyF => sandbox.freeze(&mut self.simulation),
+                            KeyCodeType::KeyM => sandbox.next_material(&mut self.simulation),
+                            KeyCodeType::KeyI => sandbox.impulse(&mut self.simulation),
+                            KeyCodeType::F6 => should_save_scene = true,
+                            KeyCodeType::F7 => should_load_scene = true,
+                            _ => {}
+                        }
+                    }
+                }
+                GameEvent::GamepadInput { input } => {
+                    if event.context.drives_car() {
+                        self.entity_system.handle_gamepad(*input);
+                    }
+                    // Start does what space and R do from the keyboard
+                    if let GamepadInputType::Button { button, state: ElementStateType::Pressed } = input {
+                        if *button == GamepadButtonType::Start {
+                            should_start_race |= self.game_state == GameState::PreRace && event.context == InputContext::Menu;
+                            should_reset |= self.game_state == GameState::Finished && !event.context.is_text_entry();
+                        }
+                        should_cycle_rumble_intensity |= *button == GamepadButtonType::Select && !event.context.is_text_entry();
+                    }
+                }
+                GameEvent::CursorMoved { x, y } if event.context.drives_car() => {
+                    let screen_size = Vec2::new(ctx.graphics.config.width as f32, ctx.graphics.config.height as f32);
+                    if let (Some(sandbox), Some(cursor)) = (&mut self.sandbox, self.camera.screen_to_world(Vec2::new(*x, *y), screen_size)) {
+                        sandbox.cursor_moved(cursor, &mut self.simulation);
+                    }
+                    if let (Some(level_editor), Some(cursor)) = (&mut self.level_editor, self.camera.screen_to_world(Vec2::new(*x, *y), screen_size)) {
+                        level_editor.cursor = cursor;
+                    }
+                }
+                GameEvent::MouseInput { button: MouseButtonType::Left, state } if event.context == InputContext::Gameplay => {
+                    if let Some(sandbox) = &mut self.sandbox {
+                        sandbox.button(matches!(state, ElementStateType::Pressed), &mut self.simulation);
+                    }
+                }
+                GameEvent::MouseInput { button: button @ (MouseButtonType::Left | MouseButtonType::Right), state: ElementStateType::Pressed } if event.context == InputContext::Editor => {
+                    self.editor_click = Some(*button);
                 }
                 _ => {}
             }
@@ -264,17 +2082,112 @@ impl GameLoop for Game {
         if should_reset {
             self.reset(ctx);
         }
+        if should_start_race {
+            self.start_race(ctx);
+        }
+        if should_quicksave {
+            self.quicksave();
+        }
+        if let Some(older) = should_quickload {
+            self.quickload(older);
+        }
+        if should_cycle_color_scheme {
+            self.cycle_color_scheme(ctx);
+        }
+        if should_toggle_ghost_camera {
+            self.toggle_ghost_camera();
+        }
+        if should_toggle_camera_projection {
+            self.toggle_camera_projection();
+        }
+        if should_cycle_camera_mode {
+            self.cycle_camera_mode();
+        }
+        if should_cycle_rumble_intensity {
+            self.cycle_rumble_intensity();
+        }
+        if should_save_scene {
+            self.save_scene();
+        }
+        if should_load_scene {
+            self.load_scene();
+        }
+        if should_delete_editor_block {
+            self.edit_level(ctx, LevelEditor::delete_selected);
+        }
+        // left click picks the block under the mouse, right click puts a new block in after it, or before the finish
+        if let Some(button) = editor_click {
+            let block_aabbs = self.level_info.as_ref().map(|level_info| level_info.block_aabbs.clone()).unwrap_or_default();
+            self.edit_level(ctx, |level_editor| {
+                let block = level_editor.block_at(level_editor.cursor, &block_aabbs);
+                match button {
+                    MouseButtonType::Right => level_editor.place(block).is_some(),
+                    _ => {
+                        if block.is_some() {
+                            level_editor.selected = block;
+                        }
+                        false
+                    }
+                }
+            });
+        }
+        self.anomaly_capture.record_inputs(self.frame_idx, ctx.event_system.events_in(InputContext::Gameplay));
         ctx.event_system.clear_events();
+        self.update_input_context(ctx);
+        self.update_sandbox_info();
+
+        self.update_daily_rollover();
+        self.poll_clock_check();
+        self.update_stream_overlay();
+
+        // only my own driving shakes the gamepad, not a replay being watched or a finished run
+        let rumble_scale = if self.game_state == GameState::Playing && !ctx.event_system.is_replaying() { self.rumble_intensity.scale() } else { 0.0 };
+        ctx.gamepads.rumble(self.rumble.strong * rumble_scale, self.rumble.weak * rumble_scale);
+        self.car_audio.play(&mut ctx.audio, self.entity_system.car_entity_system.0.first(), self.game_state == GameState::Playing);
+        world_audio::play(&mut ctx.audio, &self.camera, &self.entity_system, &self.simulation, self.game_state == GameState::Playing);
 
         if self.game_state == GameState::NameEntry {
-            let elapsed = start.elapsed().as_secs_f32() * 1000.0;
-            self.ui.update(crate::game::ui::game_ui::Message::UpdateUpdateTime(elapsed));
+            self.update_update_time(start);
             return;
         }
 
-        let time_delta: f32 = 0.005;
+        // keep listening for ghost runs while they are being picked
+        if self.game_state == GameState::PreRace {
+            self.update_network();
+            self.update_update_time(start);
+            return;
+        }
+
+        let time_delta = self.time_delta();
         let sim_time = self.step_simulation(time_delta);
-        self.ui.update(crate::game::ui::game_ui::Message::UpdateSimulationTime(sim_time));
+        if self.ui.simulation_time_ms != sim_time {
+            self.ui.update(crate::game::ui::game_ui::Message::UpdateSimulationTime(sim_time));
+        }
+        if let Some(anomaly) = self.anomaly_capture.check(self.frame_idx, &self.simulation) {
+            self.save_anomaly(ctx, &anomaly);
+        }
+        let solver_stats = self.simulation.solver_stats;
+        let solver_info = SolverInfo {
+            iterations: solver_stats.iterations,
+            max_iterations: self.simulation.solver_settings.max_iterations,
+            initial_residual: solver_stats.initial_residual,
+            residual: solver_stats.residual,
+            clamped: solver_stats.clamped,
+            quarantined: self.simulation.quarantined.len(),
+        };
+        if self.ui.solver_info != solver_info {
+            self.ui.update(crate::game::ui::game_ui::Message::UpdateSolverInfo(solver_info));
+        }
+        for ghost in &mut self.ghosts {
+            ghost.step();
+        }
+        self.update_ghost_camera(ctx);
+        if let Some(energy_diagnostics) = &mut self.energy_diagnostics {
+            energy_diagnostics.record(&self.simulation);
+            if self.frame_idx.is_multiple_of(10) {
+                self.update_energy_info();
+            }
+        }
         
         self.camera_controller.update_camera(&mut self.camera);
 
@@ -283,86 +2196,111 @@ impl GameLoop for Game {
             self.ui.update(crate::game::ui::game_ui::Message::UpdateTime(self.total_time));
         }
         self.entity_system.update(&mut self.particle_vec, &mut self.simulation, &mut self.camera, time_delta, self.total_time);
+        if let Some(car) = self.entity_system.car_entity_system.0.first() {
+            let car_pos = car.get_camera_look_at_position(&self.simulation.particles);
+            self.camera_constraint.update(&self.simulation.particles, &mut self.camera, car_pos, time_delta);
+            let car_vel = car.velocity(&self.simulation.particles);
+            self.camera_director.update(&mut self.camera, &self.entity_system.camera_entity_system, car_pos, car_vel, ctx.event_system.is_replaying(), time_delta);
+        }
+
+        if self.game_state == GameState::Playing && !ctx.event_system.is_replaying() {
+            self.handle_gameplay_events();
+        }
+
+        if self.game_state == GameState::Playing {
+            if let Some(car) = self.entity_system.car_entity_system.0.first() {
+                self.telemetry.record(self.frame_idx, self.total_time, time_delta, car, &self.simulation.particles, self.car_contacts);
+                self.ghost_track.record(self.frame_idx, car, &self.simulation.particles);
+                self.state_checksums.extend(StateChecksum::due(self.frame_idx as u32, &self.simulation));
+                let slip = car.slip();
+                if self.ui.slip != slip {
+                    self.ui.update(crate::game::ui::game_ui::Message::UpdateSlip(slip));
+                }
+            }
+        }
+        let replay_input = ctx.event_system.is_replaying().then(|| ctx.event_system.replay_input().clone());
+        if self.ui.replay_input != replay_input {
+            self.ui.update(crate::game::ui::game_ui::Message::UpdateReplayInput(replay_input));
+        }
+
+        // refresh the lap info when a lap is completed
+        if let Some(lap_info) = &self.ui.lap_info {
+            let laps_completed = self.entity_system.car_entity_system.0.first().map_or(0, |car| car.lap_times.len());
+            if laps_completed != lap_info.lap_times.len() {
+                self.update_lap_info();
+            }
+        }
 
         if self.game_state == GameState::Playing {
             let game_finished = self.entity_system.car_entity_system.0.iter().any(|car| car.game_ended);
             if game_finished {
-                self.game_state = GameState::Finished;
-                self.ui.update(crate::game::ui::game_ui::Message::UpdateGameState(GameState::Finished));
-                
-                if ctx.event_system.is_recording() {
-                    ctx.event_system.stop_recording();
-                    let filename = "recording.json";
-                    let _ = ctx.event_system.export_recording(&filename);
-                }
-                
-                let seed = chrono::Utc::now().format("%Y-%m-%d").to_string();
-                let msg = format!("BEST_TIME seed={} time={:.3} user={}", seed, self.total_time, self.current_nickname);
-                if let Some(irc) = &self.irc_manager {
-                    irc.send_message("#planck-leaderboard".to_owned(), msg);
+                let checkpoints = self.time_attack_checkpoints(true);
+                self.end_run(ctx, checkpoints);
+            } else if self.game_mode == GameMode::TimeAttack {
+                // each newly reached checkpoint extends the clock
+                let checkpoints_reached = self.entity_system.checkpoint_entity_system.num_reached();
+                if checkpoints_reached > self.checkpoints_reached {
+                    self.time_remaining += (checkpoints_reached - self.checkpoints_reached) as f32 * TIME_ATTACK_CHECKPOINT_EXTENSION;
+                    self.checkpoints_reached = checkpoints_reached;
                 }
-                
-                self.leaderboard.add_score(seed.clone(), self.current_nickname.clone(), self.total_time);
 
-                let entries = self.leaderboard.get_leaderboard_entries(&seed, &self.current_nickname, Some(self.total_time));
-                self.ui.update(crate::game::ui::game_ui::Message::UpdateLeaderboardResults(entries));
-
-                if let Some(top10) = self.leaderboard.get_top_10(&seed) {
-                    if let Some(irc) = &self.irc_manager {
-                        irc.send_message("#planck-global".to_owned(), top10);
-                    }
+                self.time_remaining -= time_delta;
+                let out_of_time = self.time_remaining <= 0.0;
+                self.update_time_attack_info(out_of_time);
+                if out_of_time {
+                    println!("Out of time! Checkpoints: {}", self.checkpoints_reached);
+                    let checkpoints = self.time_attack_checkpoints(false);
+                    self.end_run(ctx, checkpoints);
                 }
             }
         }
 
         self.camera.update_camera_uniform(&ctx.graphics.queue);
         self.update_particle_instances(&ctx.graphics.queue, &ctx.graphics.device);
+        self.update_world_labels(&ctx.graphics.queue, &ctx.graphics.device);
 
-        if let Some(irc) = &self.irc_manager {
-            for event in irc.process_events() {
-                match event {
-                    IrcEvent::MessageReceived { target, message, .. } => {
-                        if target == "#planck-leaderboard" {
-                            let seed = chrono::Utc::now().format("%Y-%m-%d").to_string();
-                            if message.starts_with("BEST_TIME") {
-                                self.leaderboard.parse_message(&message);
-                                if let Some(sync_msg) = self.leaderboard.serialize_sync(&seed) {
-                                    irc.send_message("#planck-leaderboard".to_owned(), sync_msg);
-                                }
-                            } else if message.starts_with("LEADERBOARD_SYNC") {
-                                self.leaderboard.parse_sync_message(&message);
-                            }
-                            let current_run_time = if self.game_state == GameState::Finished { Some(self.total_time) } else { None };
-                            let entries = self.leaderboard.get_leaderboard_entries(&seed, &self.current_nickname, current_run_time);
-                            self.ui.update(crate::game::ui::game_ui::Message::UpdateLeaderboardResults(entries));
-                        }
-                    },
-                    _ => {}
-                }
-            }
+        if self.toast_expiry.is_some_and(|expiry| Instant::now() >= expiry) {
+            self.toast_expiry = None;
+            self.ui.update(crate::game::ui::game_ui::Message::UpdateToast(None));
         }
 
-        let elapsed = start.elapsed().as_secs_f32() * 1000.0;
-        self.ui.update(crate::game::ui::game_ui::Message::UpdateUpdateTime(elapsed));
+        self.update_network();
+
+        self.update_update_time(start);
     }
 
     fn render(&mut self, ctx: &mut Context) {
         let start = Instant::now();
+        self.gpu_timer.poll(&ctx.graphics.device);
+        if self.ui.gpu_timings != self.gpu_timer.timings {
+            self.ui.update(crate::game::ui::game_ui::Message::UpdateGpuTimings(self.gpu_timer.timings.clone()));
+        }
+        self.update_render_scale(ctx);
         let output = match ctx.graphics.surface.get_current_texture() {
             Ok(output) => output,
             Err(_) => return,
         };
         let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // below full resolution the scene goes to its own target first and is stretched over the window after.
+        // It does for a result card too, so the scene can be read back without the UI over it.
+        let upscale = ctx.graphics.render_scale() < 1.0 || self.pending_result_card.is_some();
+        let scene_view = if upscale { &ctx.graphics.scene_texture().0.view } else { &view };
 
         let mut encoder = ctx.graphics.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("Render Encoder"),
         });
 
+        if self.ui.ghost_camera.is_some() {
+            if let Some(ghost_camera) = &self.ghost_camera {
+                ghost_camera.render(&mut encoder, &self.particle_instance_renderer, &self.material, self.gpu_timer.timestamp_writes("ghost camera"));
+            }
+        }
+
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view: scene_view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color { r: 0.1, g: 0.2, b: 0.3, a: 1.0 }),
@@ -371,7 +2309,7 @@ impl GameLoop for Game {
                     depth_slice: None,
                 })],
                 depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &ctx.graphics.depth_texture.view,
+                    view: &ctx.graphics.depth_texture().view,
                     depth_ops: Some(wgpu::Operations {
                         load: wgpu::LoadOp::Clear(1.0),
                         store: wgpu::StoreOp::Store,
@@ -379,47 +2317,124 @@ impl GameLoop for Game {
                     stencil_ops: None,
                 }),
                 occlusion_query_set: None,
-                timestamp_writes: None,
+                timestamp_writes: self.gpu_timer.timestamp_writes("scene"),
             });
 
             self.particle_shader.bind(&mut render_pass);
             self.material.bind(&mut render_pass, 0);
             self.particle_instance_renderer.render(&mut render_pass);
             self.quad_mesh.render(&mut render_pass, 0..1);
+
+            // after the particles, marks are blended over the ground
+            self.decal_shader.bind(&mut render_pass);
+            self.decal_instance_renderer.render(&mut render_pass);
+            self.quad_mesh.render(&mut render_pass, 0..1);
+
+            self.text_shader.bind(&mut render_pass);
+            self.text_renderer.render(&mut render_pass);
+        }
+        if upscale {
+            let (scene_texture, generation) = ctx.graphics.scene_texture();
+            self.upscaler.render(&ctx.graphics.device, &mut encoder, scene_texture, generation, &view);
         }
+        self.gpu_timer.resolve(&mut encoder);
         
         ctx.graphics.queue.submit(std::iter::once(encoder.finish()));
+        self.gpu_timer.after_submit();
+        if let Some(mut card) = self.pending_result_card.take() {
+            card.scene = ctx.graphics.read_scene();
+            self.finish_result_card(card);
+        }
 
         // Use UI Helper for rendering
-        let ui_messages = ctx.ui.draw(self.ui.view(), &ctx.graphics, &view);
+        // the view is only built again when something on it changed, otherwise last frame's UI is presented as it was
+        let ui_messages = if ctx.ui.needs_rebuild(self.ui.is_dirty()) {
+            self.ui.clear_dirty();
+            ctx.ui.draw(self.ui.view(), &ctx.graphics, &view)
+        } else {
+            ctx.ui.redraw(&ctx.graphics, &view);
+            vec![]
+        };
 
         for msg in ui_messages {
             match msg {
                 crate::game::ui::game_ui::Message::SubmitName => {
-                    if !self.ui.name_input.trim().is_empty() {
+                    if validate_nickname(self.ui.name_input.trim()).is_ok() {
                         self.current_nickname = self.ui.name_input.trim().to_string();
-                        let settings = Settings {
-                            player_name: Some(self.current_nickname.clone()),
-                            show_debug_info: Some(self.ui.show_debug_info),
-                        };
+                        let mut settings = Settings::load();
+                        settings.player_name = Some(self.current_nickname.clone());
+                        settings.show_debug_info = Some(self.ui.show_debug_info);
                         let _ = settings.save();
 
-                        self.irc_manager = Some(IrcManager::new(
-                             "irc.libera.chat".to_owned(),
-                             self.current_nickname.clone(),
-                             vec!["#planck-global".to_owned(), "#planck-leaderboard".to_owned()]
-                        ));
+                        self.irc_manager = Some(connect_irc(self.current_nickname.clone(), &settings));
+                        self.connection = Connection::connecting(Instant::now());
 
-                        self.game_state = GameState::Playing;
-                        self.ui.update(crate::game::ui::game_ui::Message::UpdateGameState(GameState::Playing));
+                        if custom_seed(&self.ui.seed_input).is_some() {
+                            self.play_seed(ctx);
+                        } else {
+                            let game_state = if self.ghosts_available() { GameState::PreRace } else { GameState::Playing };
+                            self.game_state = game_state;
+                            self.ui.update(crate::game::ui::game_ui::Message::UpdateGameState(game_state));
+                            self.update_ghost_targets();
+                        }
                     }
                 }
+                crate::game::ui::game_ui::Message::ToggleGhost(name) => self.toggle_ghost(name),
+                crate::game::ui::game_ui::Message::StartRace => self.start_race(ctx),
+                crate::game::ui::game_ui::Message::ToggleFriend(name) => self.toggle_friend(name),
+                crate::game::ui::game_ui::Message::DownloadLevelPack(listing) => {
+                    self.level_pack_browser.download(&listing);
+                    self.show_toast(format!("Downloading {}...", listing.name));
+                }
+                crate::game::ui::game_ui::Message::PlayPackLevel(id, level_index) => {
+                    self.pack_level = Some((id, level_index));
+                    self.custom_seed = None;
+                    self.level_file = None;
+                    self.reset(ctx);
+                }
+                crate::game::ui::game_ui::Message::PlayTraining(block, variant) => self.play_training(ctx, &block, variant),
+                crate::game::ui::game_ui::Message::PlayDaily => {
+                    self.pack_level = None;
+                    self.custom_seed = None;
+                    self.level_file = None;
+                    self.reset(ctx);
+                }
+                crate::game::ui::game_ui::Message::PlayNewDaily => {
+                    self.ui.update(crate::game::ui::game_ui::Message::UpdateNewDailyAvailable(false));
+                    self.pack_level = None;
+                    self.custom_seed = None;
+                    self.level_file = None;
+                    self.reset(ctx);
+                }
+                crate::game::ui::game_ui::Message::PlaySeed => self.play_seed(ctx),
+                crate::game::ui::game_ui::Message::SaveLevelToMyLevels => self.save_level_to_my_levels(),
+                crate::game::ui::game_ui::Message::SubmitRun => {
+                    self.submit_latest_attempt();
+                    self.update_attempt_info();
+                }
+                crate::game::ui::game_ui::Message::ExportGhost => self.export_ghost(),
+                crate::game::ui::game_ui::Message::TunePhysics(physics_tuning) => self.tune_physics(physics_tuning),
+                crate::game::ui::game_ui::Message::SelectEditorBlock(index) => self.edit_level(ctx, |level_editor| {
+                    level_editor.selected = Some(index);
+                    false
+                }),
+                crate::game::ui::game_ui::Message::MoveEditorBlock(offset) => self.edit_level(ctx, |level_editor| level_editor.move_selected(offset)),
+                crate::game::ui::game_ui::Message::DeleteEditorBlock => self.edit_level(ctx, LevelEditor::delete_selected),
+                crate::game::ui::game_ui::Message::CycleEditorBlockType(step) => self.edit_level(ctx, |level_editor| {
+                    level_editor.next_block_type(step);
+                    false
+                }),
+                crate::game::ui::game_ui::Message::AddEditorBlock => self.edit_level(ctx, |level_editor| level_editor.place(level_editor.selected).is_some()),
+                crate::game::ui::game_ui::Message::ApplyEditorParams => self.apply_editor_params(ctx),
+                crate::game::ui::game_ui::Message::SaveEditorLevel => self.save_editor_level(),
                 _ => self.ui.update(msg),
             }
         }
 
         let elapsed = start.elapsed().as_secs_f32() * 1000.0;
-        self.ui.update(crate::game::ui::game_ui::Message::UpdateRenderTime(elapsed));
+        if self.ui.render_time_ms != elapsed {
+            self.ui.update(crate::game::ui::game_ui::Message::UpdateRenderTime(elapsed));
+        }
 
         output.present();
     }
@@ -0,0 +1,49 @@
+/// Seconds on the clock at the start of a time attack run
+pub const TIME_ATTACK_START_TIME: f32 = 15.0;
+
+/// Seconds added to the clock for each checkpoint reached in time attack
+pub const TIME_ATTACK_CHECKPOINT_EXTENSION: f32 = 8.0;
+
+/// Which variant of the daily challenge is being played. Each mode gets its own leaderboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    Standard,
+    /// Crossing the finish teleports the car back to the spawn until all laps are done.
+    MultiLap { num_laps: u32 },
+    /// A countdown clock that is extended at each checkpoint. Running out of time ends the run.
+    TimeAttack,
+}
+
+impl GameMode {
+    /// Pick the mode from the command line, eg. "--laps 3" or "--time-attack"
+    pub fn from_args(args: &[String]) -> Self {
+        if args.iter().any(|arg| arg == "--time-attack") {
+            return GameMode::TimeAttack;
+        }
+
+        if let Some(idx) = args.iter().position(|arg| arg == "--laps") {
+            match args.get(idx + 1).and_then(|n| n.parse::<u32>().ok()) {
+                Some(num_laps) if num_laps > 1 => return GameMode::MultiLap { num_laps },
+                Some(_) => {}
+                None => eprintln!("--laps expects a number of laps, eg. --laps 3"),
+            }
+        }
+        GameMode::Standard
+    }
+
+    pub fn num_laps(&self) -> u32 {
+        match self {
+            GameMode::MultiLap { num_laps } => *num_laps,
+            _ => 1,
+        }
+    }
+
+    /// Appended to the level seed so times from different modes don't end up on the same leaderboard.
+    pub fn leaderboard_seed_suffix(&self) -> String {
+        match self {
+            GameMode::Standard => String::new(),
+            GameMode::MultiLap { num_laps } => format!("-laps{}", num_laps),
+            GameMode::TimeAttack => String::from("-timeattack"),
+        }
+    }
+}
@@ -1,6 +1,10 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum GameState {
     NameEntry,
+    PreRace, // choosing ghosts before the daily starts
     Playing,
     Finished,
 }
@@ -0,0 +1,446 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use chrono::Utc;
+
+use crate::{
+    core::math::{deterministic::MathMode, vec2::Vec2},
+    engine::app::event_system::{ElementStateType, FramedEvent, GameEvent, GamepadAxisType, GamepadInputType, KeyCodeType, GAMEPAD_AXIS_STEPS},
+    game::{ghost_track::{GhostTrack, GhostTrackPlayer}, headless_run::HeadlessRun},
+    simulation::particles::simulation::Simulation,
+};
+
+pub const GHOSTS_DIR: &str = "ghosts";
+pub const MAX_GHOSTS: usize = 3;
+
+/// My own best run is raced under this name, nicknames can't have spaces so no player can take it
+pub const PERSONAL_BEST_GHOST: &str = "My best";
+
+/// Bumped whenever the replay format changes, older versions are still read by GhostReplay::from_json.
+/// 1: just the inputs. 2: adds the game version and level hash, so a replay from another generator isn't raced.
+/// 3: adds gamepad stick and trigger inputs. 4: adds the math mode and state checksums.
+pub const REPLAY_FORMAT_VERSION: u32 = 4;
+
+/// A checksum of the simulation is kept every this many frames of a run, a second of it
+pub const CHECKSUM_INTERVAL_FRAMES: u32 = 200;
+
+/// A key press or release, on the frame the game loop handled it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GhostInput {
+    pub frame: u32,
+    pub key_code: KeyCodeType,
+    pub pressed: bool,
+}
+
+/// A gamepad stick or trigger moving, on the frame the game loop handled it. Gamepad buttons are kept as the key they stand in for.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GhostAxisInput {
+    pub frame: u32,
+    pub axis: GamepadAxisType,
+    pub value: f32, // always a whole number of GAMEPAD_AXIS_STEPS, so it survives being sent as text
+}
+
+/// Simulation::state_checksum partway through a run, to check a re-simulation of it is still in step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateChecksum {
+    pub frame: u32,
+    pub checksum: String, // hex
+}
+
+impl StateChecksum {
+    pub fn new(frame: u32, simulation: &Simulation) -> Self {
+        Self { frame, checksum: format!("{:016x}", simulation.state_checksum()) }
+    }
+
+    /// The checksum to keep for this frame of a run, if one is due
+    pub fn due(frame: u32, simulation: &Simulation) -> Option<Self> {
+        frame.is_multiple_of(CHECKSUM_INTERVAL_FRAMES).then(|| Self::new(frame, simulation))
+    }
+}
+
+/// The inputs of a finished run, enough to re-simulate it on the same level
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GhostReplay {
+    pub user: String,
+    pub seed: String,
+    pub time: f32,
+    pub inputs: Vec<GhostInput>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub axis_inputs: Vec<GhostAxisInput>,
+    pub format_version: u32,
+    pub game_version: String, // of the game that recorded it, empty if it was too old to say
+    pub level_hash: Option<String>, // of the level it was recorded on, see LevelGenerationInfo::compute_level_hash
+    #[serde(default)]
+    pub math_mode: MathMode, // it has to be re-simulated the same way
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checksums: Vec<StateChecksum>, // every CHECKSUM_INTERVAL_FRAMES, empty for older replays and ones shared over IRC
+}
+
+/// Version 1 replays, from before the format was versioned
+#[derive(Deserialize)]
+struct GhostReplayV1 {
+    user: String,
+    seed: String,
+    time: f32,
+    inputs: Vec<GhostInput>,
+}
+
+impl From<GhostReplayV1> for GhostReplay {
+    fn from(replay: GhostReplayV1) -> Self {
+        Self {
+            user: replay.user,
+            seed: replay.seed,
+            time: replay.time,
+            inputs: replay.inputs,
+            axis_inputs: vec![],
+            format_version: 1,
+            game_version: String::new(),
+            level_hash: None,
+            math_mode: MathMode::Native,
+            checksums: vec![],
+        }
+    }
+}
+
+/// Why a replay can't be raced here, rather than letting it desync part way through
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayIssue {
+    NewerFormat { game_version: String },
+    LevelGeneratorDiffers { game_version: String },
+}
+
+impl fmt::Display for ReplayIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let recorded_with = |game_version: &str| if game_version.is_empty() { String::from("an older version") } else { format!("v{}", game_version) };
+        match self {
+            ReplayIssue::NewerFormat { game_version } => write!(f, "recorded with {}, update the game to race it", recorded_with(game_version)),
+            ReplayIssue::LevelGeneratorDiffers { game_version } => write!(f, "recorded with {}, level generator differs", recorded_with(game_version)),
+        }
+    }
+}
+
+impl GhostReplay {
+    /// Keep only the keyboard and gamepad events from a recording, nothing else affects the car
+    pub fn from_recorded_events(user: String, seed: String, time: f32, level_hash: Option<String>, events: &[FramedEvent]) -> Self {
+        let inputs = events.iter().filter_map(|framed_event| {
+            let (key_code, state) = match &framed_event.event {
+                GameEvent::KeyboardInput { key_code, state } => (*key_code, state),
+                GameEvent::GamepadInput { input: GamepadInputType::Button { button, state } } => (button.key_equivalent()?, state),
+                _ => return None,
+            };
+            Some(GhostInput { frame: framed_event.frame as u32, key_code, pressed: matches!(state, ElementStateType::Pressed) })
+        }).collect();
+        let axis_inputs = events.iter().filter_map(|framed_event| match &framed_event.event {
+            GameEvent::GamepadInput { input: GamepadInputType::Axis { axis, value } } => Some(GhostAxisInput { frame: framed_event.frame as u32, axis: *axis, value: *value }),
+            _ => None,
+        }).collect();
+        Self {
+            user,
+            seed,
+            time,
+            inputs,
+            axis_inputs,
+            format_version: REPLAY_FORMAT_VERSION,
+            game_version: env!("CARGO_PKG_VERSION").to_owned(),
+            level_hash,
+            math_mode: MathMode::default(),
+            checksums: vec![],
+        }
+    }
+
+    /// Read a saved replay of any format version. Newer versions are read as far as possible so
+    /// compatibility can say why they can't be raced.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let value: serde_json::Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let format_version = value.get("format_version").and_then(|v| v.as_u64()).unwrap_or(1);
+        if format_version == 1 {
+            return serde_json::from_value::<GhostReplayV1>(value).map(Self::from).map_err(|e| e.to_string());
+        }
+        serde_json::from_value(value).map_err(|e| e.to_string())
+    }
+
+    /// Whether this replay will play back the same on a level with the given hash.
+    /// Version 1 replays don't know their level so they are given the benefit of the doubt.
+    pub fn compatibility(&self, level_hash: Option<&str>) -> Result<(), ReplayIssue> {
+        if self.format_version > REPLAY_FORMAT_VERSION {
+            return Err(ReplayIssue::NewerFormat { game_version: self.game_version.clone() });
+        }
+        if let (Some(replay_level_hash), Some(level_hash)) = (&self.level_hash, level_hash) {
+            if replay_level_hash != level_hash {
+                return Err(ReplayIssue::LevelGeneratorDiffers { game_version: self.game_version.clone() });
+            }
+        }
+        Ok(())
+    }
+
+    /// Compact text form for sending over IRC, eg. "12.KeyX.d,80.KeyX.u". Gamepad sticks and triggers follow the keys
+    /// with their value in steps, eg. "40.RightTrigger.15" is the trigger pulled to 0.75
+    pub fn encode_inputs(&self) -> String {
+        let keys = self.inputs.iter().map(|input| {
+            let key_code = serde_json::to_string(&input.key_code).unwrap_or_default();
+            format!("{}.{}.{}", input.frame, key_code.trim_matches('"'), if input.pressed { "d" } else { "u" })
+        });
+        let axes = self.axis_inputs.iter().map(|input| {
+            let axis = serde_json::to_string(&input.axis).unwrap_or_default();
+            format!("{}.{}.{}", input.frame, axis.trim_matches('"'), (input.value * GAMEPAD_AXIS_STEPS).round() as i32)
+        });
+        keys.chain(axes).collect::<Vec<_>>().join(",")
+    }
+
+    pub fn decode_inputs(data: &str) -> Result<(Vec<GhostInput>, Vec<GhostAxisInput>), String> {
+        let (mut inputs, mut axis_inputs) = (vec![], vec![]);
+        if data.is_empty() {
+            return Ok((inputs, axis_inputs));
+        }
+        for token in data.split(',') {
+            let mut parts = token.split('.');
+            let (Some(frame), Some(name), Some(state), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+                return Err(format!("bad ghost input '{}'", token));
+            };
+            let frame = frame.parse().map_err(|_| format!("bad ghost input frame '{}'", frame))?;
+            // a number where the key state would be is a stick or trigger
+            if let Ok(steps) = state.parse::<i32>() {
+                axis_inputs.push(GhostAxisInput {
+                    frame,
+                    axis: serde_json::from_str(&format!("\"{}\"", name)).map_err(|_| format!("unknown axis '{}'", name))?,
+                    value: steps as f32 / GAMEPAD_AXIS_STEPS,
+                });
+                continue;
+            }
+            inputs.push(GhostInput {
+                frame,
+                key_code: serde_json::from_str(&format!("\"{}\"", name)).map_err(|_| format!("unknown key '{}'", name))?,
+                pressed: state == "d",
+            });
+        }
+        Ok((inputs, axis_inputs))
+    }
+
+    /// Step a headless run of the replay's level one frame, handing it the inputs due by then.
+    /// frame counts the frames stepped so far and next_input the inputs handed over, both start at 0
+    pub fn step_run(&self, run: &mut HeadlessRun, frame: &mut u32, next_input: &mut usize) {
+        *frame += 1;
+        // inputs are recorded with the frame before the one that handles them
+        while let Some(input) = self.inputs.get(*next_input) {
+            if input.frame >= *frame {
+                break;
+            }
+            run.entity_system.handle_key(input.key_code, input.pressed);
+            *next_input += 1;
+        }
+        // frame goes up one at a time, so these are due now and no sooner
+        let first = self.axis_inputs.partition_point(|input| input.frame + 1 < *frame);
+        for input in self.axis_inputs[first..].iter().take_while(|input| input.frame + 1 == *frame) {
+            run.entity_system.handle_gamepad(GamepadInputType::Axis { axis: input.axis, value: input.value });
+        }
+        run.step();
+    }
+
+    /// Whether a re-simulation is still in step on this frame, None if no checksum was kept for it
+    pub fn checksum_matches(&self, frame: u32, simulation: &Simulation) -> Option<bool> {
+        let index = self.checksums.binary_search_by_key(&frame, |checksum| checksum.frame).ok()?;
+        Some(self.checksums[index] == StateChecksum::new(frame, simulation))
+    }
+
+    /// Re-simulate the run from the start of a headless run of its level, checking it against the recorded checksums.
+    /// Err with the frame of the first checksum that doesn't match, the runs drifted apart somewhere before it.
+    pub fn verify(&self, run: &mut HeadlessRun) -> Result<(), u32> {
+        let (mut frame, mut next_input) = (0, 0);
+        for expected in &self.checksums {
+            while frame < expected.frame && !run.finished() {
+                self.step_run(run, &mut frame, &mut next_input);
+            }
+            if self.checksum_matches(frame, &run.simulation) != Some(true) {
+                return Err(expected.frame);
+            }
+        }
+        Ok(())
+    }
+
+    /// Where my best run for a seed is kept, to share with other players
+    pub fn path(seed: &str) -> PathBuf {
+        PathBuf::from(GHOSTS_DIR).join(format!("{}.json", seed))
+    }
+
+    pub fn load(seed: &str) -> Option<Self> {
+        fs::read_to_string(Self::path(seed))
+            .ok()
+            .and_then(|json| Self::from_json(&json).ok())
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        fs::create_dir_all(GHOSTS_DIR)?;
+        let json = serde_json::to_string(self)?;
+        fs::write(Self::path(&self.seed), json)
+    }
+}
+
+/// Another player's run racing alongside mine, either re-simulated from their inputs or played back from a track they exported
+pub struct Ghost {
+    pub name: String,
+    source: GhostSource,
+}
+
+enum GhostSource {
+    /// It has its own copy of the level so it can't bump into my car
+    Replay { replay: GhostReplay, run: Box<HeadlessRun>, frame: u32, next_input: usize, in_step: bool },
+    Track(GhostTrackPlayer),
+}
+
+impl Ghost {
+    /// Builds today's level, so only works for daily seeds
+    pub fn new(replay: GhostReplay) -> Self {
+        Self {
+            name: replay.user.clone(),
+            source: GhostSource::Replay { run: Box::new(HeadlessRun::for_day_with_math_mode(Utc::now(), replay.math_mode)), replay, frame: 0, next_input: 0, in_step: true },
+        }
+    }
+
+    pub fn from_track(track: GhostTrack) -> Self {
+        Self { name: track.user.clone(), source: GhostSource::Track(GhostTrackPlayer::new(track)) }
+    }
+
+    /// Step one frame, in step with the game loop frame counter
+    pub fn step(&mut self) {
+        match &mut self.source {
+            GhostSource::Replay { replay, run, frame, next_input, in_step } => {
+                if run.finished() {
+                    return;
+                }
+
+                replay.step_run(run, frame, next_input);
+                if *in_step && replay.checksum_matches(*frame, &run.simulation) == Some(false) {
+                    println!("Ghost {} drifted from the run it recorded by frame {}, it won't finish in {:.3}s", self.name, frame, replay.time);
+                    *in_step = false;
+                }
+            }
+            GhostSource::Track(player) => player.step(),
+        }
+    }
+
+    /// Where the ghost's own camera is looking, for the picture in picture view
+    pub fn camera_target(&self) -> Option<Vec2> {
+        match &self.source {
+            GhostSource::Replay { run, .. } => {
+                let car = run.entity_system.car_entity_system.0.first()?;
+                Some(car.get_camera_look_at_position(&run.simulation.particles))
+            }
+            GhostSource::Track(player) => {
+                let wheel_poses = player.track.wheel_poses(player.frame())?;
+                Some(wheel_poses.iter().fold(Vec2::zero(), |sum, pose| sum + pose.hub) / wheel_poses.len() as f32)
+            }
+        }
+    }
+
+    /// Position and radius of each of the ghost car's particles
+    pub fn car_particles(&self) -> Vec<(Vec2, f32)> {
+        match &self.source {
+            GhostSource::Replay { run, .. } => {
+                let particles = &run.simulation.particles;
+                run.entity_system.car_entity_system.0.iter()
+                    .flat_map(|car| car.particle_handles())
+                    .map(|i| (particles[i].pos, particles[i].radius))
+                    .collect()
+            }
+            GhostSource::Track(player) => player.car_particles(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{engine::app::event_system::GamepadButtonType, game::entity::entities::car_entity::CarEntity};
+
+    #[test]
+    fn test_inputs_round_trip() {
+        let events = vec![
+            FramedEvent { frame: 12, event: GameEvent::KeyboardInput { key_code: KeyCodeType::KeyX, state: ElementStateType::Pressed } },
+            FramedEvent { frame: 30, event: GameEvent::CursorMoved { x: 1.0, y: 2.0 } },
+            FramedEvent { frame: 80, event: GameEvent::KeyboardInput { key_code: KeyCodeType::KeyX, state: ElementStateType::Released } },
+        ];
+        let replay = GhostReplay::from_recorded_events(String::from("bob"), String::from("2025-01-01"), 10.0, None, &events);
+        assert_eq!(replay.inputs.len(), 2);
+
+        let encoded = replay.encode_inputs();
+        assert_eq!(encoded, "12.KeyX.d,80.KeyX.u");
+        assert_eq!(GhostReplay::decode_inputs(&encoded), Ok((replay.inputs, vec![])));
+        assert_eq!(GhostReplay::decode_inputs(""), Ok((vec![], vec![])));
+        assert!(GhostReplay::decode_inputs("12.KeyJ.d").is_err());
+        assert!(GhostReplay::decode_inputs("12.KeyX").is_err());
+    }
+
+    #[test]
+    fn test_gamepad_inputs_round_trip() {
+        let pad = |frame, input| FramedEvent { frame, event: GameEvent::GamepadInput { input } };
+        let events = vec![
+            pad(5, GamepadInputType::Button { button: GamepadButtonType::South, state: ElementStateType::Pressed }),
+            pad(6, GamepadInputType::Button { button: GamepadButtonType::Start, state: ElementStateType::Pressed }),
+            pad(10, GamepadInputType::Axis { axis: GamepadAxisType::RightTrigger, value: 0.75 }),
+            pad(20, GamepadInputType::Axis { axis: GamepadAxisType::LeftStickX, value: -0.35 }),
+        ];
+        let replay = GhostReplay::from_recorded_events(String::from("bob"), String::from("2025-01-01"), 10.0, None, &events);
+        assert_eq!(replay.inputs, vec![GhostInput { frame: 5, key_code: KeyCodeType::KeyC, pressed: true }]);
+        assert_eq!(replay.axis_inputs.len(), 2);
+
+        let encoded = replay.encode_inputs();
+        assert_eq!(encoded, "5.KeyC.d,10.RightTrigger.15,20.LeftStickX.-7");
+        assert_eq!(GhostReplay::decode_inputs(&encoded), Ok((replay.inputs.clone(), replay.axis_inputs.clone())));
+
+        let json = serde_json::to_string(&replay).unwrap();
+        assert_eq!(GhostReplay::from_json(&json), Ok(replay));
+    }
+
+    #[test]
+    fn test_older_and_newer_formats() {
+        let v1 = GhostReplay::from_json(r#"{"user":"bob","seed":"2025-01-01","time":10.0,"inputs":[{"frame":12,"key_code":"KeyX","pressed":true}]}"#).unwrap();
+        assert_eq!(v1.format_version, 1);
+        assert_eq!(v1.inputs.len(), 1);
+        assert_eq!(v1.compatibility(Some("aaaa")), Ok(()));
+
+        let current = GhostReplay::from_recorded_events(String::from("bob"), String::from("2025-01-01"), 10.0, Some(String::from("aaaa")), &[]);
+        let json = serde_json::to_string(&current).unwrap();
+        assert_eq!(GhostReplay::from_json(&json), Ok(current.clone()));
+        assert_eq!(current.compatibility(Some("aaaa")), Ok(()));
+
+        let old_generator = GhostReplay { game_version: String::from("0.3"), ..current.clone() };
+        let issue = old_generator.compatibility(Some("bbbb")).unwrap_err();
+        assert_eq!(issue.to_string(), "recorded with v0.3, level generator differs");
+
+        let newer = GhostReplay { format_version: REPLAY_FORMAT_VERSION + 1, ..current };
+        assert!(matches!(newer.compatibility(Some("aaaa")), Err(ReplayIssue::NewerFormat { .. })));
+    }
+
+    #[test]
+    fn test_checksums_verify_a_replay() {
+        let new_run = || {
+            let mut run = HeadlessRun::empty();
+            let car = CarEntity::new(&mut run.particle_vec, &mut run.simulation, Vec2::new(0.0, 1.0));
+            run.entity_system.car_entity_system.push(car);
+            run
+        };
+        let events = vec![FramedEvent { frame: 10, event: GameEvent::KeyboardInput { key_code: KeyCodeType::KeyX, state: ElementStateType::Pressed } }];
+        let mut replay = GhostReplay::from_recorded_events(String::from("bob"), String::from("2025-01-01"), 10.0, None, &events);
+
+        // keep checksums as the game does
+        let mut run = new_run();
+        let (mut frame, mut next_input) = (0, 0);
+        while frame < CHECKSUM_INTERVAL_FRAMES * 2 {
+            replay.step_run(&mut run, &mut frame, &mut next_input);
+            replay.checksums.extend(StateChecksum::due(frame, &run.simulation));
+        }
+        assert_eq!(replay.checksums.len(), 2);
+        assert_eq!(replay.verify(&mut new_run()), Ok(()));
+
+        let late = GhostReplay { inputs: vec![GhostInput { frame: 11, ..replay.inputs[0] }], ..replay.clone() };
+        assert_eq!(late.verify(&mut new_run()), Err(CHECKSUM_INTERVAL_FRAMES));
+    }
+
+    #[test]
+    fn test_personal_best_name_cant_be_a_nickname() {
+        assert!(crate::game::nickname::validate_nickname(PERSONAL_BEST_GHOST).is_err());
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{
+    core::math::vec2::Vec2,
+    engine::{
+        app::{camera::{Camera, Projection}, graphics_helper::GraphicsHelper},
+        renderer::{instance_renderer::{InstanceRaw, InstanceRenderer, Vertex}, model::Material, shader::{Shader, ShaderBuilder}, texture::Texture},
+    },
+};
+
+/// The picture in picture view from a ghost car. The particles are drawn a second time from the ghost's point of view
+/// into a small offscreen texture, which the UI then shows in a corner.
+pub struct GhostCamera {
+    camera: Camera,
+    particle_shader: Shader,
+    colour_texture: Texture,
+    depth_texture: Texture,
+}
+
+impl GhostCamera {
+    pub const WIDTH: u32 = 320;
+    pub const HEIGHT: u32 = 180;
+
+    pub fn new(graphics: &mut GraphicsHelper, material: &Material) -> Self {
+        let config = wgpu::SurfaceConfiguration { width: Self::WIDTH, height: Self::HEIGHT, ..graphics.config.clone() };
+        let camera = Camera::new(&graphics.device, Self::WIDTH as f32 / Self::HEIGHT as f32);
+        let particle_shader = ShaderBuilder::from_file("particle_shader.wgsl".to_owned(), &graphics.device)
+            .camera(&camera)
+            .diffuse_texture(&material.diffuse_texture)
+            .build_cached(&mut graphics.pipeline_cache, &[Vertex::desc(), InstanceRaw::desc()], config.format);
+
+        Self {
+            camera,
+            particle_shader,
+            colour_texture: Texture::create_render_target(&graphics.device, &config, "ghost_camera_texture"),
+            depth_texture: Texture::create_depth_texture(&graphics.device, &config, "ghost_camera_depth_texture"),
+        }
+    }
+
+    /// What the UI draws, it stays the same texture for as long as this lives
+    pub fn view(&self) -> wgpu::TextureView {
+        self.colour_texture.view.clone()
+    }
+
+    /// Follows the target with the same projection as the main view
+    pub fn update(&mut self, target: Vec2, projection: Projection, queue: &wgpu::Queue) {
+        self.camera.projection = projection;
+        self.camera.target = cgmath::Point3::new(target.x, target.y, 0.0);
+        self.camera.update_camera_uniform(queue);
+    }
+
+    /// Draws the same particle instances as the main view, so the ghost cars show up translucent here too
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, instance_renderer: &InstanceRenderer, material: &Material, timestamp_writes: Option<wgpu::RenderPassTimestampWrites>) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Ghost Camera Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.colour_texture.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color { r: 0.1, g: 0.2, b: 0.3, a: 1.0 }),
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_texture.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes,
+        });
+
+        self.particle_shader.bind(&mut render_pass);
+        material.bind(&mut render_pass, 0);
+        instance_renderer.render(&mut render_pass);
+    }
+}
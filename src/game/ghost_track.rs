@@ -0,0 +1,254 @@
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::{
+    core::math::vec2::Vec2,
+    game::{entity::entities::car_entity::{CarEntity, CarWheel}, ghost::GHOSTS_DIR},
+    simulation::particles::particle_vec::ParticleVec,
+};
+
+/// Exported runs go here, and tracks friends have sent are picked up from here
+pub const GHOST_TRACKS_DIR: &str = "tracks";
+
+/// Frames between pose samples, 10 a second at 5ms a frame
+pub const TRACK_SAMPLE_FRAMES: u32 = 20;
+
+/// Where one wheel was on a sample frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WheelPose {
+    pub hub: Vec2,
+    pub angle: f32, // radians
+}
+
+impl WheelPose {
+    fn lerp(&self, other: &WheelPose, t: f32) -> WheelPose {
+        // turn the short way round
+        let mut delta = (other.angle - self.angle) % (2.0 * PI);
+        if delta > PI {
+            delta -= 2.0 * PI;
+        } else if delta < -PI {
+            delta += 2.0 * PI;
+        }
+        WheelPose {
+            hub: self.hub + (other.hub - self.hub) * t,
+            angle: self.angle + delta * t,
+        }
+    }
+}
+
+/// A run as a handful of car poses a second. It's a few kilobytes and plays back without simulating anything,
+/// so unlike a GhostReplay it can be raced whatever build the friend it was sent to has.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GhostTrack {
+    pub user: String,
+    pub seed: String,
+    pub time: f32,
+    pub level_hash: Option<String>, // of the level it was recorded on, see LevelGenerationInfo::compute_level_hash
+    pub sample_frames: u32,
+    #[serde(with = "pose_encoding")]
+    pub poses: Vec<Vec<WheelPose>>, // each sample has a pose for every wheel
+}
+
+impl GhostTrack {
+    pub fn path(seed: &str, user: &str) -> PathBuf {
+        PathBuf::from(GHOSTS_DIR).join(GHOST_TRACKS_DIR).join(format!("{}-{}.json", seed, user))
+    }
+
+    /// Every track for a seed, whoever they are from
+    pub fn load_all(seed: &str) -> Vec<Self> {
+        let Ok(entries) = fs::read_dir(PathBuf::from(GHOSTS_DIR).join(GHOST_TRACKS_DIR)) else {
+            return vec![];
+        };
+
+        let mut tracks: Vec<Self> = entries.flatten()
+            .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+            .filter_map(|json| serde_json::from_str::<Self>(&json).ok())
+            .filter(|track| track.seed == seed)
+            .collect();
+        tracks.sort_by(|a, b| a.time.total_cmp(&b.time));
+        tracks
+    }
+
+    pub fn save(&self) -> io::Result<PathBuf> {
+        let path = Self::path(&self.seed, &self.user);
+        fs::create_dir_all(PathBuf::from(GHOSTS_DIR).join(GHOST_TRACKS_DIR))?;
+        let json = serde_json::to_string(self)?;
+        fs::write(&path, json)?;
+        Ok(path)
+    }
+
+    /// The wheels on any frame, in between samples they are blended. It stays put after the last sample.
+    pub fn wheel_poses(&self, frame: u32) -> Option<Vec<WheelPose>> {
+        let sample = (frame / self.sample_frames) as usize;
+        let t = (frame % self.sample_frames) as f32 / self.sample_frames as f32;
+        let (Some(from), Some(to)) = (self.poses.get(sample), self.poses.get(sample + 1)) else {
+            return self.poses.last().cloned();
+        };
+        Some(from.iter().zip(to.iter()).map(|(from, to)| from.lerp(to, t)).collect())
+    }
+}
+
+/// Samples my car as the run goes, to export as a GhostTrack
+#[derive(Default)]
+pub struct GhostTrackRecorder {
+    pub poses: Vec<Vec<WheelPose>>,
+}
+
+impl GhostTrackRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, frame: u128, car: &CarEntity, particle_vec: &ParticleVec) {
+        if !frame.is_multiple_of(TRACK_SAMPLE_FRAMES as u128) {
+            return;
+        }
+        self.poses.push(car.wheels.iter()
+            .map(|wheel| {
+                let (hub, angle) = wheel.pose(particle_vec);
+                WheelPose { hub, angle }
+            })
+            .collect());
+    }
+
+    pub fn to_track(&self, user: String, seed: String, time: f32, level_hash: Option<String>) -> GhostTrack {
+        GhostTrack { user, seed, time, level_hash, sample_frames: TRACK_SAMPLE_FRAMES, poses: self.poses.clone() }
+    }
+}
+
+/// Plays a GhostTrack back in step with the game loop frame counter
+pub struct GhostTrackPlayer {
+    pub track: GhostTrack,
+    wheel_shape: Vec<(Vec2, f32)>,
+    frame: u32,
+}
+
+impl GhostTrackPlayer {
+    pub fn new(track: GhostTrack) -> Self {
+        Self { track, wheel_shape: CarWheel::shape(), frame: 0 }
+    }
+
+    pub fn step(&mut self) {
+        self.frame += 1;
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Position and radius of each particle of the car, with the wheels drawn undeformed
+    pub fn car_particles(&self) -> Vec<(Vec2, f32)> {
+        let Some(wheel_poses) = self.track.wheel_poses(self.frame) else {
+            return vec![];
+        };
+        wheel_poses.iter()
+            .flat_map(|pose| self.wheel_shape.iter().map(move |(offset, radius)| (pose.hub + Vec2::rotate_rad(*offset, pose.angle), *radius)))
+            .collect()
+    }
+}
+
+/// Poses are written as text to keep the files small: whole centimetres for the hubs, each one relative to the same wheel
+/// in the sample before, and whole degrees for the angle. Wheels are split by ',' and samples by ';', eg. "120.35.90,0.35.270;4.-1.85,4.0.265"
+mod pose_encoding {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::WheelPose;
+    use crate::core::math::vec2::Vec2;
+
+    pub fn serialize<S: Serializer>(poses: &[Vec<WheelPose>], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&encode(poses))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Vec<WheelPose>>, D::Error> {
+        let data = String::deserialize(deserializer)?;
+        decode(&data).map_err(serde::de::Error::custom)
+    }
+
+    pub fn encode(poses: &[Vec<WheelPose>]) -> String {
+        let mut last: Vec<(i32, i32)> = vec![];
+        poses.iter().map(|sample| {
+            last.resize(sample.len(), (0, 0));
+            sample.iter().zip(last.iter_mut()).map(|(pose, last)| {
+                let x = (pose.hub.x * 100.0).round() as i32;
+                let y = (pose.hub.y * 100.0).round() as i32;
+                let angle = pose.angle.to_degrees().round().rem_euclid(360.0) as i32;
+                let encoded = format!("{}.{}.{}", x - last.0, y - last.1, angle);
+                *last = (x, y);
+                encoded
+            }).collect::<Vec<_>>().join(",")
+        }).collect::<Vec<_>>().join(";")
+    }
+
+    pub fn decode(data: &str) -> Result<Vec<Vec<WheelPose>>, String> {
+        if data.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut last: Vec<(i32, i32)> = vec![];
+        data.split(';').map(|sample| {
+            let wheels: Vec<&str> = sample.split(',').collect();
+            last.resize(wheels.len(), (0, 0));
+            wheels.iter().zip(last.iter_mut()).map(|(wheel, last)| {
+                let values: Vec<i32> = wheel.split('.').map(|v| v.parse().map_err(|_| format!("bad ghost track pose '{}'", wheel))).collect::<Result<_, _>>()?;
+                let [dx, dy, angle] = values[..] else {
+                    return Err(format!("bad ghost track pose '{}'", wheel));
+                };
+                *last = (last.0 + dx, last.1 + dy);
+                Ok(WheelPose {
+                    hub: Vec2::new(last.0 as f32 * 0.01, last.1 as f32 * 0.01),
+                    angle: (angle as f32).to_radians(),
+                })
+            }).collect()
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pose(x: f32, y: f32, angle_deg: f32) -> WheelPose {
+        WheelPose { hub: Vec2::new(x, y), angle: angle_deg.to_radians() }
+    }
+
+    #[test]
+    fn test_poses_round_trip() {
+        let poses = vec![
+            vec![pose(1.2, 0.35, 90.0), pose(0.0, 0.35, -90.0)],
+            vec![pose(1.24, 0.34, 85.0), pose(0.04, 0.35, 265.0)],
+        ];
+        let encoded = pose_encoding::encode(&poses);
+        assert_eq!(encoded, "120.35.90,0.35.270;4.-1.85,4.0.265");
+
+        let decoded = pose_encoding::decode(&encoded).unwrap();
+        for (sample, decoded_sample) in poses.iter().zip(decoded.iter()) {
+            for (pose, decoded_pose) in sample.iter().zip(decoded_sample.iter()) {
+                assert!((pose.hub - decoded_pose.hub).magnitude() < 0.001);
+                assert!((pose.angle.sin() - decoded_pose.angle.sin()).abs() < 0.001);
+            }
+        }
+        assert!(pose_encoding::decode("1.2").is_err());
+        assert_eq!(pose_encoding::decode(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_wheel_poses_blend_between_samples() {
+        let track = GhostTrack {
+            user: String::from("bob"),
+            seed: String::from("2025-01-01"),
+            time: 1.0,
+            level_hash: None,
+            sample_frames: 10,
+            poses: vec![vec![pose(0.0, 0.0, 350.0)], vec![pose(1.0, 0.0, 10.0)]],
+        };
+        let halfway = track.wheel_poses(5).unwrap();
+        assert!((halfway[0].hub.x - 0.5).abs() < 0.001);
+        // the short way round, through zero
+        assert!(halfway[0].angle.sin().abs() < 0.001 && halfway[0].angle.cos() > 0.0);
+
+        assert_eq!(track.wheel_poses(100).unwrap(), vec![pose(1.0, 0.0, 10.0)]);
+    }
+}
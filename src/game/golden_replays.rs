@@ -0,0 +1,145 @@
+use std::fmt;
+
+use crate::{
+    core::math::deterministic::MathMode,
+    game::{
+        ghost::{GhostInput, GhostReplay, REPLAY_FORMAT_VERSION},
+        headless_run::{HeadlessRun, TIME_DELTA},
+        seed_policy::seed_day,
+    },
+};
+
+/// Runs that haven't finished by now are counted as not finishing
+pub const MAX_GOLDEN_FRAMES: u32 = 12000; // 60 seconds
+
+/// How far a finish time can move before it counts as drift. Runs are deterministic, so this only covers the time being written to 3 places
+pub const GOLDEN_TIME_EPSILON: f32 = 0.001;
+
+/// A bot run on a pinned daily with the time it finished in. Played back by the tests so a change to the
+/// level generator or solver that changes how a daily plays is caught, rather than quietly breaking everyone's ghosts.
+/// If a change is meant to do that, record them again with `planck-time-trials golden_replay <seed>`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GoldenReplay {
+    pub seed: &'static str, // a daily seed, eg. "2025-03-14"
+    pub level_hash: &'static str, // see LevelGenerationInfo::compute_level_hash
+    pub inputs: &'static str, // see GhostReplay::encode_inputs
+    pub time: f32,
+}
+
+/// Short dailies the bot can finish, so they are quick enough to play on every test run
+pub const GOLDEN_REPLAYS: &[GoldenReplay] = &[
+    GoldenReplay { seed: "2025-07-07", level_hash: "bbf3cdcedece11d9", inputs: "0.KeyX.d,350.KeyX.u,400.KeyX.d,750.KeyX.u,800.KeyX.d,1150.KeyX.u,1200.KeyX.d,1550.KeyX.u,1600.KeyX.d,1950.KeyX.u,2000.KeyX.d", time: 10.985 },
+    GoldenReplay { seed: "2025-08-03", level_hash: "b0e398174e6f9631", inputs: "0.KeyX.d,350.KeyX.u,400.KeyX.d,750.KeyX.u,800.KeyX.d,1150.KeyX.u,1200.KeyX.d,1550.KeyX.u,1600.KeyX.d,1950.KeyX.u,2000.KeyX.d,2350.KeyX.u,2400.KeyX.d,2750.KeyX.u,2800.KeyX.d", time: 14.620 },
+];
+
+/// Why a golden replay no longer plays back the same
+#[derive(Debug, Clone, PartialEq)]
+pub enum GoldenDrift {
+    LevelChanged { level_hash: String },
+    DidNotFinish,
+    TimeChanged { time: f32 },
+}
+
+impl fmt::Display for GoldenDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GoldenDrift::LevelChanged { level_hash } => write!(f, "the level generator builds a different level, its hash is now {}", level_hash),
+            GoldenDrift::DidNotFinish => write!(f, "the run no longer finishes within {} seconds", MAX_GOLDEN_FRAMES as f32 * TIME_DELTA),
+            GoldenDrift::TimeChanged { time } => write!(f, "the run now finishes in {:.3}s", time),
+        }
+    }
+}
+
+fn bot_replay(seed: &str, time: f32, inputs: Vec<GhostInput>, level_hash: String) -> GhostReplay {
+    GhostReplay {
+        user: String::from("golden"),
+        seed: seed.to_owned(),
+        time,
+        inputs,
+        axis_inputs: vec![],
+        format_version: REPLAY_FORMAT_VERSION,
+        game_version: env!("CARGO_PKG_VERSION").to_owned(),
+        level_hash: Some(level_hash),
+        math_mode: MathMode::Native,
+        checksums: vec![],
+    }
+}
+
+/// A daily with its level hash, see LevelGenerationInfo::compute_level_hash
+fn build(seed: &str) -> Result<(HeadlessRun, String), String> {
+    let run = HeadlessRun::for_day(seed_day(seed)?);
+    let level_hash = run.level_info.as_ref().map(|level_info| level_info.level_hash.clone()).unwrap_or_default();
+    Ok((run, level_hash))
+}
+
+/// Play inputs the way a ghost does, returning the finish time if it finished
+fn play(run: &mut HeadlessRun, replay: &GhostReplay) -> Option<f32> {
+    let mut frame = 0;
+    let mut next_input = 0;
+    while frame < MAX_GOLDEN_FRAMES && !run.finished() {
+        replay.step_run(run, &mut frame, &mut next_input);
+    }
+    run.finished().then_some(run.total_time)
+}
+
+impl GoldenReplay {
+    /// Check it's still the same level and the run finishes in the same time on it
+    pub fn check(&self) -> Result<(), GoldenDrift> {
+        let (inputs, _) = GhostReplay::decode_inputs(self.inputs).expect("golden replay inputs should decode");
+        let (mut run, level_hash) = build(self.seed).expect("golden replay seed should be a daily");
+        if level_hash != self.level_hash {
+            return Err(GoldenDrift::LevelChanged { level_hash });
+        }
+        match play(&mut run, &bot_replay(self.seed, self.time, inputs, level_hash)) {
+            None => Err(GoldenDrift::DidNotFinish),
+            Some(time) if (time - self.time).abs() > GOLDEN_TIME_EPSILON => Err(GoldenDrift::TimeChanged { time }),
+            Some(_) => Ok(()),
+        }
+    }
+}
+
+/// Record the scripted bot driving a daily, trimmed to the inputs it used before finishing
+pub fn record_bot_run(seed: &str) -> Result<GhostReplay, String> {
+    let mut inputs: Vec<GhostInput> = vec![];
+    for frame in 0..MAX_GOLDEN_FRAMES {
+        let (key_code, pressed) = HeadlessRun::bot_input(frame);
+        let held = inputs.iter().rev().find(|input| input.key_code == key_code).is_some_and(|input| input.pressed);
+        if pressed != held {
+            inputs.push(GhostInput { frame, key_code, pressed });
+        }
+    }
+
+    let (mut run, level_hash) = build(seed)?;
+    let time = play(&mut run, &bot_replay(seed, 0.0, inputs.clone(), level_hash.clone())).ok_or_else(|| format!("the bot doesn't finish {} within {} seconds", seed, MAX_GOLDEN_FRAMES as f32 * TIME_DELTA))?;
+    let frames = (time / TIME_DELTA).round() as u32;
+    inputs.retain(|input| input.frame < frames);
+    Ok(bot_replay(seed, time, inputs, level_hash))
+}
+
+/// Written out the way GOLDEN_REPLAYS lists them, to paste in
+pub fn golden_replay_source(replay: &GhostReplay) -> String {
+    format!(
+        "GoldenReplay {{ seed: \"{}\", level_hash: \"{}\", inputs: \"{}\", time: {:.3} }},",
+        replay.seed, replay.level_hash.as_deref().unwrap_or_default(), replay.encode_inputs(), replay.time
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_golden_replays_reproduce() {
+        for golden in GOLDEN_REPLAYS {
+            if let Err(drift) = golden.check() {
+                panic!("golden replay of {} drifted from {:.3}s: {}", golden.seed, golden.time, drift);
+            }
+        }
+    }
+
+    #[test]
+    fn test_level_change_is_reported() {
+        let golden = GOLDEN_REPLAYS[0];
+        assert_eq!(GoldenReplay { level_hash: "0", ..golden }.check(), Err(GoldenDrift::LevelChanged { level_hash: golden.level_hash.to_owned() }));
+    }
+}
@@ -0,0 +1,100 @@
+use chrono::{DateTime, Utc};
+
+use crate::{
+    core::math::{deterministic::MathMode, random::Random, vec2::Vec2},
+    engine::app::{camera::Camera, event_system::KeyCodeType},
+    game::{entity::{entities::car_entity::CarEntity, entity_system::EntitySystem}, level::{level_builder::LevelBuilder, level_generation_info::LevelGenerationInfo}},
+    simulation::particles::{particle_vec::ParticleVec, simulation::Simulation},
+};
+
+pub const TIME_DELTA: f32 = 0.005; // same as the game loop
+
+/// A daily level and a car, stepped without a window. Used by the developer tools (determinism audit, soak test), ghosts and the golden replays.
+pub struct HeadlessRun {
+    pub simulation: Simulation,
+    pub particle_vec: ParticleVec,
+    pub entity_system: EntitySystem,
+    pub camera: Camera,
+    pub total_time: f32,
+    pub level_info: Option<LevelGenerationInfo>, // None if no level was generated
+}
+
+impl HeadlessRun {
+    pub fn new() -> Self {
+        Self::for_day(Utc::now())
+    }
+
+    /// The daily level of the day the time is in, built the same way the game builds it
+    pub fn for_day(day: DateTime<Utc>) -> Self {
+        Self::for_day_with_math_mode(day, MathMode::default())
+    }
+
+    /// Same as for_day with the simulation doing its maths the given way, to re-simulate a run recorded in that mode
+    pub fn for_day_with_math_mode(day: DateTime<Utc>, math_mode: MathMode) -> Self {
+        let mut entity_system = EntitySystem::new();
+        let mut particle_vec = ParticleVec::new();
+        let rng = Random::seed_from_day(day);
+        let mut simulation = Simulation::with_math_mode(rng, math_mode);
+
+        let mut level_builder = LevelBuilder::default();
+        let level_info = level_builder.generate_level_for_day(day, &mut entity_system, &mut particle_vec, &mut simulation);
+        let car = CarEntity::new(&mut particle_vec, &mut simulation, Vec2::new(0.0, 1.0));
+        entity_system.car_entity_system.push(car);
+
+        Self {
+            simulation,
+            particle_vec,
+            entity_system,
+            camera: Camera::new_headless(1.0),
+            total_time: 0.0,
+            level_info: Some(level_info),
+        }
+    }
+
+    /// Nothing in it, not even a car, for tests to build just the entities they need
+    pub fn empty() -> Self {
+        Self {
+            simulation: Simulation::new(Random::seed_from_str("headless")),
+            particle_vec: ParticleVec::new(),
+            entity_system: EntitySystem::new(),
+            camera: Camera::new_headless(1.0),
+            total_time: 0.0,
+            level_info: None,
+        }
+    }
+
+    /// Scripted bot input so runs drive the same way: hold clockwise with a short release every 2 seconds
+    pub fn bot_input(frame: u32) -> (KeyCodeType, bool) {
+        (KeyCodeType::KeyX, frame % 400 < 350)
+    }
+
+    pub fn apply_bot_input(&mut self, frame: u32) {
+        let (key_code, pressed) = Self::bot_input(frame);
+        self.entity_system.handle_key(key_code, pressed);
+    }
+
+    /// Step one frame in the same order as the game loop: Game::step_simulation followed by the entity update
+    pub fn step(&mut self) {
+        self.simulation.pre_solve(TIME_DELTA);
+        self.entity_system.elevator_entity_system.update_counts(&mut self.simulation);
+        let elevators = &mut self.entity_system.elevator_entity_system;
+        self.simulation.solve_adaptive(TIME_DELTA, |simulation| elevators.solve_constraints(simulation, TIME_DELTA));
+        self.simulation.post_solve(TIME_DELTA);
+        self.update_entities();
+    }
+
+    pub fn update_entities(&mut self) {
+        self.total_time += TIME_DELTA;
+        self.entity_system.update(&mut self.particle_vec, &mut self.simulation, &mut self.camera, TIME_DELTA, self.total_time);
+    }
+
+    pub fn finished(&self) -> bool {
+        self.entity_system.car_entity_system.0.iter().any(|car| car.game_ended)
+    }
+}
+
+impl Default for HeadlessRun {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,11 +1,20 @@
 use std::sync::mpsc::{self, Receiver};
+#[cfg(feature = "irc")]
 use std::thread;
+#[cfg(feature = "irc")]
 use irc::client::prelude::*;
+#[cfg(feature = "irc")]
 use tokio::runtime::Runtime;
+#[cfg(feature = "irc")]
 use futures::prelude::*;
+#[cfg(feature = "irc")]
 use tokio::sync::mpsc::UnboundedSender;
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashMap};
 use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
+use super::message_filter::MessageFilter;
+use super::presence_tracker::PresenceTracker;
 
 #[derive(Debug, Clone)]
 pub enum IrcCommand {
@@ -18,24 +27,35 @@ pub enum IrcEvent {
     Connected,
     MessageReceived { target: String, sender: String, message: String },
     UserJoined(String),
+    JoinedChannel(String), // we joined, rather than someone else
     UserLeft(String),
     UserList(Vec<String>),
     Disconnected,
 }
 
+/// The connection to the IRC server, run on its own thread. Built without the irc feature it never connects,
+/// so the game plays offline and the bot has nothing to watch.
 pub struct IrcManager {
+    #[cfg(feature = "irc")]
     command_sender: UnboundedSender<IrcCommand>,
     event_receiver: Receiver<IrcEvent>,
     users: Arc<RwLock<BTreeSet<String>>>,
+    nickname: String,
+    presence: PresenceTracker,
+    filter: MessageFilter,
 }
 
 impl IrcManager {
-    pub fn new(server: String, nickname: String, channels: Vec<String>) -> Self {
+    /// channel_keys maps channel name to password for any passworded channels
+    pub fn new(server: String, nickname: String, channels: Vec<String>, channel_keys: HashMap<String, String>) -> Self {
+        #[cfg(feature = "irc")]
         let (command_sender, mut command_receiver) = tokio::sync::mpsc::unbounded_channel::<IrcCommand>();
         let (event_sender, event_receiver) = mpsc::channel();
         let users = Arc::new(RwLock::new(BTreeSet::new()));
         let users_clone = users.clone();
+        let own_nickname = nickname.clone();
 
+        #[cfg(feature = "irc")]
         thread::spawn(move || {
             let rt = Runtime::new().unwrap();
             rt.block_on(async move {
@@ -43,6 +63,7 @@ impl IrcManager {
                     nickname: Some(nickname.clone()),
                     server: Some(server.clone()),
                     channels: channels.clone(),
+                    channel_keys,
                     ..Config::default()
                 };
 
@@ -50,6 +71,7 @@ impl IrcManager {
                     Ok(c) => c,
                     Err(e) => {
                         eprintln!("Failed to create IRC client: {}", e);
+                        let _ = event_sender.send(IrcEvent::Disconnected);
                         return;
                     }
                 };
@@ -63,7 +85,8 @@ impl IrcManager {
                     Ok(s) => s,
                     Err(e) => {
                         eprintln!("Failed to get stream: {}", e);
-                        return; 
+                        let _ = event_sender.send(IrcEvent::Disconnected);
+                        return;
                     }
                 };
                 
@@ -89,8 +112,11 @@ impl IrcManager {
                                                  message: content,
                                              });
                                         }
-                                    } else if let Command::JOIN(_channel, _, _) = msg.command {
+                                    } else if let Command::JOIN(channel, _, _) = msg.command {
                                         if let Some(Prefix::Nickname(n, _, _)) = msg.prefix {
+                                            if n.eq_ignore_ascii_case(&nickname) {
+                                                let _ = event_sender.send(IrcEvent::JoinedChannel(channel));
+                                            }
                                             if let Ok(mut u) = users_clone.write() {
                                                 u.insert(n.clone());
                                                 let _ = event_sender.send(IrcEvent::UserJoined(n));
@@ -153,11 +179,20 @@ impl IrcManager {
                  let _ = event_sender.send(IrcEvent::Disconnected);
             });
         });
+        #[cfg(not(feature = "irc"))]
+        {
+            let _ = (server, channels, channel_keys, users_clone);
+            let _ = event_sender.send(IrcEvent::Disconnected);
+        }
 
         Self {
-            command_sender, 
+            #[cfg(feature = "irc")]
+            command_sender,
             event_receiver,
             users,
+            nickname: own_nickname,
+            presence: PresenceTracker::new(),
+            filter: MessageFilter::new(),
         }
     }
 
@@ -170,18 +205,59 @@ impl IrcManager {
     }
     
     pub fn send_message(&self, target: String, message: String) {
-        let _ = self.command_sender.send(IrcCommand::SendMessage { target, message });
+        self.send_command(IrcCommand::SendMessage { target, message });
     }
     
     pub fn join_channel(&self, channel: String) {
-         let _ = self.command_sender.send(IrcCommand::JoinChannel(channel));
+        self.send_command(IrcCommand::JoinChannel(channel));
+    }
+
+    fn send_command(&self, command: IrcCommand) {
+        #[cfg(feature = "irc")]
+        let _ = self.command_sender.send(command);
+        #[cfg(not(feature = "irc"))]
+        let _ = command;
+    }
+    
+    /// Players whose messages and times are dropped, see MessageFilter
+    pub fn set_ignored(&mut self, ignored: &[String]) {
+        self.filter.set_ignored(ignored);
     }
     
-    pub fn process_events(&self) -> Vec<IrcEvent> {
+    /// Events since the last call, with messages that don't pass the filter already dropped
+    pub fn process_events(&mut self) -> Vec<IrcEvent> {
+        let now = Instant::now();
         let mut events = Vec::new();
         while let Ok(event) = self.event_receiver.try_recv() {
+            let event = match event {
+                IrcEvent::MessageReceived { target, sender, message } => {
+                    let Some(message) = self.filter.filter(&sender, &message, now) else { continue };
+                    IrcEvent::MessageReceived { target, sender, message }
+                }
+                event => event,
+            };
+            match &event {
+                IrcEvent::MessageReceived { sender, message, .. } => self.presence.handle_message(sender, message, now),
+                IrcEvent::UserLeft(nick) => self.presence.handle_user_left(nick),
+                _ => {}
+            }
             events.push(event);
         }
         events
     }
+
+    /// Let everyone know which seed we are on and if we are racing. Only actually sends a ping every so often, or when something changes.
+    pub fn update_presence(&mut self, seed: &str, racing: bool) {
+        if let Some(ping) = self.presence.update_own_presence(&self.nickname, seed, racing, Instant::now()) {
+            self.send_message("#planck-global".to_owned(), ping);
+        }
+    }
+
+    pub fn presence(&self) -> &PresenceTracker {
+        &self.presence
+    }
+
+    pub fn presence_mut(&mut self) -> &mut PresenceTracker {
+        &mut self.presence
+    }
 }
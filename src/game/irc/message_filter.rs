@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::game::{leaderboard::sync_checksum, nickname::has_offensive_word};
+
+/// The IRC line limit, anything longer didn't come from a game client
+pub const MAX_MESSAGE_LENGTH: usize = 512;
+
+// each nick can send a burst of messages then a steady trickle, enough for a whole ghost replay's chunks (see replay_sharing)
+const RATE_LIMIT_BURST: f32 = 70.0;
+const RATE_LIMIT_PER_SECOND: f32 = 2.0;
+
+/// How many messages a nick can still send right now
+struct Allowance {
+    messages: f32,
+    updated: Instant,
+}
+
+/// Checks incoming IRC messages before the game sees them. Drops messages that are too long, nicks that send too many,
+/// and anything from or about an ignored player or one with an offensive name, so they never show up on the boards.
+#[derive(Default)]
+pub struct MessageFilter {
+    ignored: Vec<String>, // lowercase
+    allowances: HashMap<String, Allowance>, // keyed by lowercase nick
+}
+
+impl MessageFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_ignored(&mut self, ignored: &[String]) {
+        self.ignored = ignored.iter().map(|name| name.to_lowercase()).collect();
+    }
+
+    fn is_blocked(&self, name: &str) -> bool {
+        self.ignored.contains(&name.to_lowercase()) || has_offensive_word(name)
+    }
+
+    /// Spend one of the nick's messages, false if it has run out
+    fn take_allowance(&mut self, sender: &str, now: Instant) -> bool {
+        // nicks back at a full allowance are the same as ones never seen, forget them so the map doesn't grow forever
+        self.allowances.retain(|_, allowance| allowance.messages + now.duration_since(allowance.updated).as_secs_f32() * RATE_LIMIT_PER_SECOND < RATE_LIMIT_BURST);
+
+        let allowance = self.allowances.entry(sender.to_lowercase()).or_insert(Allowance { messages: RATE_LIMIT_BURST, updated: now });
+        let elapsed = now.duration_since(allowance.updated).as_secs_f32();
+        allowance.messages = (allowance.messages + elapsed * RATE_LIMIT_PER_SECOND).min(RATE_LIMIT_BURST);
+        allowance.updated = now;
+        if allowance.messages < 1.0 {
+            return false;
+        }
+        allowance.messages -= 1.0;
+        true
+    }
+
+    /// The message to pass on, or None to drop it.
+    /// Leaderboard syncs are passed on without the blocked players' times, as they also carry everyone else's.
+    pub fn filter(&mut self, sender: &str, message: &str, now: Instant) -> Option<String> {
+        if message.len() > MAX_MESSAGE_LENGTH || self.is_blocked(sender) || !self.take_allowance(sender, now) {
+            return None;
+        }
+
+        // BEST_TIME, REPLAY_CHUNK etc. relayed on behalf of a user
+        let user = message.split_whitespace().find_map(|part| part.strip_prefix("user="));
+        if user.is_some_and(|user| self.is_blocked(user)) {
+            return None;
+        }
+
+        if message.starts_with("LEADERBOARD_SYNC") {
+            if let Some((head, data)) = message.split_once(" data=") {
+                // a version 2 part that doesn't match its checksum would be dropped anyway, don't sum it again and make it pass
+                let sum = head.split_whitespace().find_map(|field| field.strip_prefix("sum="));
+                if sum.is_some_and(|sum| sum != sync_checksum(data)) {
+                    return None;
+                }
+                let kept: Vec<bool> = data.split(',').map(|entry| !self.is_blocked(entry.split(':').next().unwrap_or_default())).collect();
+                let keep = |list: &str| list.split(',').zip(&kept).filter(|(_, keep)| **keep).map(|(entry, _)| entry).collect::<Vec<&str>>().join(",");
+                let data = keep(data);
+                // version 2 parts carry a checksum of the data, it has to match what's left, and the notes line up with the data
+                let head: Vec<String> = head.split_whitespace()
+                    .map(|field| if field.starts_with("sum=") {
+                        format!("sum={}", sync_checksum(&data))
+                    } else if let Some(notes) = field.strip_prefix("notes=") {
+                        format!("notes={}", keep(notes))
+                    } else {
+                        field.to_owned()
+                    })
+                    .collect();
+                return Some(format!("{} data={}", head.join(" "), data));
+            }
+        }
+        Some(message.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_blocked_players_and_long_messages_are_dropped() {
+        let mut filter = MessageFilter::new();
+        filter.set_ignored(&[String::from("Pest")]);
+        let now = Instant::now();
+
+        assert!(filter.filter("bob", "BEST_TIME seed=2025-01-01 time=12.5 user=bob", now).is_some());
+        assert_eq!(filter.filter("pest", "BEST_TIME seed=2025-01-01 time=12.5 user=pest", now), None);
+        assert_eq!(filter.filter("bob", "BEST_TIME seed=2025-01-01 time=12.5 user=Sh1tHead", now), None);
+        assert!(filter.filter("Yamashita", "BEST_TIME seed=2025-01-01 time=12.5 user=Yamashita", now).is_some());
+        assert_eq!(filter.filter("bob", &"x".repeat(MAX_MESSAGE_LENGTH + 1), now), None);
+
+        assert_eq!(
+            filter.filter("bob", "LEADERBOARD_SYNC seed=2025-01-01 data=bob:12.5,PEST:13,carol:14", now),
+            Some(String::from("LEADERBOARD_SYNC seed=2025-01-01 data=bob:12.5,carol:14"))
+        );
+        assert_eq!(
+            filter.filter("bob", &format!("LEADERBOARD_SYNC seed=2025-01-01 version=2 id=1 part=1/1 sum={} data=bob:12.5,PEST:13", sync_checksum("bob:12.5,PEST:13")), now),
+            Some(format!("LEADERBOARD_SYNC seed=2025-01-01 version=2 id=1 part=1/1 sum={} data=bob:12.5", sync_checksum("bob:12.5")))
+        );
+        assert_eq!(
+            filter.filter("bob", &format!("LEADERBOARD_SYNC seed=2025-01-01 version=2 id=1 part=1/1 sum={} notes=ghost=1,car=x,verified=1 data=bob:12.5,PEST:13,carol:14", sync_checksum("bob:12.5,PEST:13,carol:14")), now),
+            Some(format!("LEADERBOARD_SYNC seed=2025-01-01 version=2 id=1 part=1/1 sum={} notes=ghost=1,verified=1 data=bob:12.5,carol:14", sync_checksum("bob:12.5,carol:14")))
+        );
+        // a part that was changed on the way isn't summed again, with or without blocked players in it
+        assert_eq!(filter.filter("bob", "LEADERBOARD_SYNC seed=2025-01-01 version=2 id=1 part=1/1 sum=0123abcd data=bob:12.5,PEST:13", now), None);
+        assert_eq!(filter.filter("bob", "LEADERBOARD_SYNC seed=2025-01-01 version=2 id=1 part=1/1 sum=0123abcd data=bob:12.5", now), None);
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let mut filter = MessageFilter::new();
+        let now = Instant::now();
+        let sent = (0..100).filter(|_| filter.filter("spammer", "PRESENCE seed=2025-01-01 state=idle", now).is_some()).count();
+        assert_eq!(sent, RATE_LIMIT_BURST as usize);
+
+        // other nicks aren't held back, and the spammer gets more allowance as time passes
+        assert!(filter.filter("bob", "PRESENCE seed=2025-01-01 state=idle", now).is_some());
+        assert!(filter.filter("spammer", "PRESENCE seed=2025-01-01 state=idle", now + Duration::from_secs(1)).is_some());
+    }
+}
@@ -1 +1,3 @@
 pub mod irc_manager;
+pub mod message_filter;
+pub mod presence_tracker;
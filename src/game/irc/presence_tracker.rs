@@ -0,0 +1,175 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// How often we tell everyone what we are up to
+pub const PRESENCE_PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Players we haven't heard from in this long are assumed to have stopped playing
+const PRESENCE_TIMEOUT: Duration = Duration::from_secs(90);
+
+const MAX_RECENT_FINISHERS: usize = 5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecentFinisher {
+    pub user: String,
+    pub seed: String,
+    pub time: f32,
+}
+
+struct Presence {
+    seed: String,
+    racing: bool,
+    last_seen: Instant,
+}
+
+/// Tracks who is online and racing from channel membership and PRESENCE pings,
+/// plus recent finishers from BEST_TIME messages for the HUD ticker.
+///
+/// Ping format: "PRESENCE seed={} state=racing|idle"
+#[derive(Default)]
+pub struct PresenceTracker {
+    presence: HashMap<String, Presence>, // keyed by lowercase nick
+    recent_finishers: VecDeque<RecentFinisher>,
+    last_ping: Option<(Instant, String, bool)>, // when we last pinged, and what we said
+}
+
+impl PresenceTracker {
+    pub fn new() -> Self {
+        Self {
+            presence: HashMap::new(),
+            recent_finishers: VecDeque::new(),
+            last_ping: None,
+        }
+    }
+
+    fn parse_field<'a>(message: &'a str, key: &str) -> Option<&'a str> {
+        message.split_whitespace().find_map(|part| part.strip_prefix(key)?.strip_prefix('='))
+    }
+
+    pub fn handle_message(&mut self, sender: &str, message: &str, now: Instant) {
+        // forget players that timed out, otherwise everyone that ever pinged stays in the map
+        self.presence.retain(|_, presence| now.duration_since(presence.last_seen) < PRESENCE_TIMEOUT);
+
+        if message.starts_with("PRESENCE") {
+            if let Some(seed) = Self::parse_field(message, "seed") {
+                let racing = Self::parse_field(message, "state") == Some("racing");
+                self.presence.insert(sender.to_lowercase(), Presence { seed: seed.to_string(), racing, last_seen: now });
+            }
+        } else if message.starts_with("BEST_TIME") {
+            let user = Self::parse_field(message, "user");
+            let seed = Self::parse_field(message, "seed");
+            let time = Self::parse_field(message, "time").and_then(|t| t.parse::<f32>().ok());
+            if let (Some(user), Some(seed), Some(time)) = (user, seed, time) {
+                self.add_finisher(RecentFinisher { user: user.to_string(), seed: seed.to_string(), time });
+                // finishing counts as being seen, but they are no longer racing
+                self.presence.insert(user.to_lowercase(), Presence { seed: seed.to_string(), racing: false, last_seen: now });
+            }
+        }
+    }
+
+    pub fn add_finisher(&mut self, finisher: RecentFinisher) {
+        self.recent_finishers.push_front(finisher);
+        self.recent_finishers.truncate(MAX_RECENT_FINISHERS);
+    }
+
+    pub fn handle_user_left(&mut self, nick: &str) {
+        self.presence.remove(&nick.to_lowercase());
+    }
+
+    /// Returns the ping message if it is time to send one, either because the interval has passed or our state changed.
+    /// We don't hear our own messages from the server, so we record our own presence here too.
+    pub fn update_own_presence(&mut self, nick: &str, seed: &str, racing: bool, now: Instant) -> Option<String> {
+        let due = match &self.last_ping {
+            Some((last_time, last_seed, last_racing)) => {
+                now.duration_since(*last_time) >= PRESENCE_PING_INTERVAL || last_seed != seed || *last_racing != racing
+            }
+            None => true,
+        };
+        if !due {
+            return None;
+        }
+
+        self.last_ping = Some((now, seed.to_string(), racing));
+        self.presence.insert(nick.to_lowercase(), Presence { seed: seed.to_string(), racing, last_seen: now });
+        Some(format!("PRESENCE seed={} state={}", seed, if racing { "racing" } else { "idle" }))
+    }
+
+    fn is_active(&self, presence: &Presence, now: Instant) -> bool {
+        now.duration_since(presence.last_seen) < PRESENCE_TIMEOUT
+    }
+
+    /// Players that have pinged recently
+    pub fn online_count(&self, now: Instant) -> usize {
+        self.presence.values().filter(|p| self.is_active(p, now)).count()
+    }
+
+    /// Players currently racing the given seed
+    pub fn racing_count(&self, seed: &str, now: Instant) -> usize {
+        self.presence.values().filter(|p| p.racing && p.seed == seed && self.is_active(p, now)).count()
+    }
+
+    /// Lowercase nicks of the players on the given seed, racing or not, including me once I've pinged
+    pub fn players_on(&self, seed: &str, now: Instant) -> Vec<String> {
+        let mut players: Vec<String> = self.presence.iter()
+            .filter(|(_, p)| p.seed == seed && self.is_active(p, now))
+            .map(|(nick, _)| nick.clone())
+            .collect();
+        players.sort();
+        players
+    }
+
+    /// Players we are holding presence for, including any that have timed out but not been pruned yet
+    pub fn num_tracked(&self) -> usize {
+        self.presence.len()
+    }
+
+    pub fn recent_finishers(&self) -> impl Iterator<Item = &RecentFinisher> {
+        self.recent_finishers.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use super::PresenceTracker;
+
+    #[test]
+    fn counts_racers_on_seed_until_timeout() {
+        let now = Instant::now();
+        let mut tracker = PresenceTracker::new();
+        tracker.handle_message("alice", "PRESENCE seed=2025-01-01 state=racing", now);
+        tracker.handle_message("bob", "PRESENCE seed=2025-01-01 state=idle", now);
+        tracker.handle_message("carol", "PRESENCE seed=2025-01-01-mirror state=racing", now);
+
+        assert_eq!(tracker.online_count(now), 3);
+        assert_eq!(tracker.racing_count("2025-01-01", now), 1);
+        assert_eq!(tracker.players_on("2025-01-01", now), vec!["alice", "bob"]);
+
+        let later = now + Duration::from_secs(120);
+        assert_eq!(tracker.racing_count("2025-01-01", later), 0);
+    }
+
+    #[test]
+    fn best_time_adds_finisher_and_stops_racing() {
+        let now = Instant::now();
+        let mut tracker = PresenceTracker::new();
+        tracker.handle_message("alice", "PRESENCE seed=2025-01-01 state=racing", now);
+        tracker.handle_message("alice", "BEST_TIME seed=2025-01-01 time=12.500 user=alice", now);
+
+        assert_eq!(tracker.racing_count("2025-01-01", now), 0);
+        let finisher = tracker.recent_finishers().next().unwrap();
+        assert_eq!(finisher.user, "alice");
+        assert_eq!(finisher.time, 12.5);
+    }
+
+    #[test]
+    fn own_presence_pings_on_interval_or_change() {
+        let now = Instant::now();
+        let mut tracker = PresenceTracker::new();
+        assert!(tracker.update_own_presence("me", "2025-01-01", true, now).is_some());
+        assert!(tracker.update_own_presence("me", "2025-01-01", true, now + Duration::from_secs(1)).is_none());
+        assert!(tracker.update_own_presence("me", "2025-01-01", false, now + Duration::from_secs(2)).is_some());
+        assert!(tracker.update_own_presence("me", "2025-01-01", false, now + Duration::from_secs(40)).is_some());
+        assert_eq!(tracker.racing_count("2025-01-01", now), 0);
+    }
+}
@@ -1,9 +1,175 @@
 use std::collections::HashMap;
 
+use chrono::{DateTime, Duration, Utc};
+
+use crate::core::hash::Fnv1aHasher;
+
+// Boards kept in memory, the least recently updated board is dropped first. The level being played is never dropped.
+const MAX_SEEDS: usize = 16;
+const SYNC_CHUNK_DATA_LEN: usize = 300; // keeps each message well inside the IRC line limit
+const MAX_SYNC_CHUNKS: usize = 16; // a full board of 50 scores needs about 5
+const MAX_PARTIAL_SYNCS: usize = 8;
+
+// Sync protocol:
+// LEADERBOARD_SYNC seed={} data={}                                    version 1, the whole board in one message.
+//                                                                     Long boards went over the line limit and were cut off.
+// LEADERBOARD_SYNC seed={} version=2 id={} part={}/{} sum={} data={}  version 2, the board split between scores into parts.
+//                                                                     id ties the parts of one sync together and sum is
+//                                                                     sync_checksum of the part's data, a part that doesn't
+//                                                                     match it is dropped. Version 1 clients ignore the extra
+//                                                                     fields and take each part as a board of its own.
+// LEADERBOARD_REQUEST seed={}                                         sent on joining the channel, so a player starting after
+//                                                                     the others have finished sees the board straight away.
+//                                                                     One player answers with a sync, see answers_request.
+// VERIFIED seed={} user={} time={} result=pass|fail                   posted by whoever re-simulated the run with the verify
+//                                                                     command, marks the time verified or rejected.
+//
+// Annotations, what a time was set with, go in BEST_TIME as extra fields: "car={} mods={}+{} ghost=1 at={}", where at is when
+// it was set in seconds since the Unix epoch. A BEST_TIME without one was set as it arrived. In a version 2
+// sync they're in a notes field before the data, one entry per score in the part's data separated by commas, each entry
+// the fields separated by semicolons: "notes=car=standard;ghost=1,,verified=1". The data is last so a line cut short
+// loses data rather than notes, and sum only covers the data. Unknown annotation fields are ignored, and clients that
+// don't know about notes ignore the whole field.
+
+/// Whether a re-simulation of a time has been done, and whether it matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Verification {
+    #[default]
+    Unchecked,
+    Verified,
+    Rejected, // the re-simulation didn't finish in the time posted
+}
+
+/// What a time was set with, shown as badges on the board
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ScoreAnnotations {
+    pub car: Option<String>, // older clients don't send it
+    pub modifiers: Vec<String>, // eg. "mirror", "headwind", "laps3"
+    pub ghost: bool, // the player kept a ghost of the run that can be raced
+    pub verification: Verification,
+    pub set_at: Option<i64>, // seconds since the Unix epoch, None for times synced from older clients
+}
+
+impl ScoreAnnotations {
+    /// The annotations as "key=value" fields, leaving out the ones not set
+    pub fn to_fields(&self) -> Vec<String> {
+        // the separators of the wire format can't go in a value
+        let clean = |value: &str| value.chars().filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_').collect::<String>();
+        let mut fields = vec![];
+        if let Some(car) = &self.car {
+            fields.push(format!("car={}", clean(car)));
+        }
+        if !self.modifiers.is_empty() {
+            fields.push(format!("mods={}", self.modifiers.iter().map(|modifier| clean(modifier)).collect::<Vec<_>>().join("+")));
+        }
+        if self.ghost {
+            fields.push(String::from("ghost=1"));
+        }
+        match self.verification {
+            Verification::Unchecked => {}
+            Verification::Verified => fields.push(String::from("verified=1")),
+            Verification::Rejected => fields.push(String::from("verified=0")),
+        }
+        if let Some(set_at) = self.set_at {
+            fields.push(format!("at={}", set_at));
+        }
+        fields
+    }
+
+    /// Read one "key=value" field, returns false if it isn't an annotation so newer fields are skipped over
+    pub fn parse_field(&mut self, field: &str) -> bool {
+        let Some((key, value)) = field.split_once('=') else {
+            return false;
+        };
+        match key {
+            "car" => self.car = Some(value.to_owned()).filter(|car| !car.is_empty()),
+            "mods" => self.modifiers = value.split('+').filter(|modifier| !modifier.is_empty()).map(str::to_owned).collect(),
+            "ghost" => self.ghost = value == "1",
+            "verified" => self.verification = if value == "1" { Verification::Verified } else { Verification::Rejected },
+            "at" => self.set_at = value.parse().ok(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// The entry for a sync's notes field
+    fn to_note(&self) -> String {
+        self.to_fields().join(";")
+    }
+
+    fn from_note(note: &str) -> Self {
+        let mut annotations = Self::default();
+        for field in note.split(';') {
+            annotations.parse_field(field);
+        }
+        annotations
+    }
+
+    /// Fill in what other has that these don't, eg. the same time synced from a player who saw it verified
+    fn merge(&mut self, other: ScoreAnnotations) {
+        if self.car.is_none() {
+            self.car = other.car;
+        }
+        if self.modifiers.is_empty() {
+            self.modifiers = other.modifiers;
+        }
+        self.ghost |= other.ghost;
+        if self.verification == Verification::Unchecked {
+            self.verification = other.verification;
+        }
+        if self.set_at.is_none() {
+            self.set_at = other.set_at;
+        }
+    }
+
+    /// Whether the time was set at or after since, times without a set_at are taken as old
+    pub fn set_since(&self, since: DateTime<Utc>) -> bool {
+        self.set_at.is_some_and(|set_at| set_at >= since.timestamp())
+    }
+}
+
+pub fn request_message(seed: &str) -> String {
+    format!("LEADERBOARD_REQUEST seed={}", seed)
+}
+
+/// The seed a LEADERBOARD_REQUEST asks for
+pub fn parse_request(message: &str) -> Option<String> {
+    if !message.starts_with("LEADERBOARD_REQUEST") {
+        return None;
+    }
+    message.split_whitespace().find_map(|part| part.strip_prefix("seed=")).filter(|seed| !seed.is_empty()).map(str::to_owned)
+}
+
+/// Whether I answer a LEADERBOARD_REQUEST. Everyone on the seed has the board, so rather than all of them replying
+/// the player with the lowest nick answers, leaving out whoever asked. players are the ones on the seed going by presence,
+/// which everyone in the channel sees the same.
+pub fn answers_request(me: &str, requester: &str, players: &[String]) -> bool {
+    let me = me.to_lowercase();
+    let requester = requester.to_lowercase();
+    players.iter()
+        .map(|player| player.to_lowercase())
+        .chain(std::iter::once(me.clone()))
+        .filter(|player| *player != requester)
+        .min()
+        .is_some_and(|responder| responder == me)
+}
+
+/// What the verify command's caller posts once a time has been re-simulated
+pub fn verified_message(seed: &str, user: &str, time: f32, verified: bool) -> String {
+    format!("VERIFIED seed={} user={} time={:.3} result={}", seed, user, time, if verified { "pass" } else { "fail" })
+}
+
+/// Checksum of the data in a version 2 sync part
+pub fn sync_checksum(data: &str) -> String {
+    Fnv1aHasher::new().write_str(data).finish_hex()
+}
+
 #[derive(Debug, Clone)]
 pub struct Score {
     pub user: String,
     pub time: f32,
+    pub checkpoints: Option<u32>, // time attack ranks by checkpoints reached first
+    pub annotations: ScoreAnnotations,
 }
 
 #[derive(Debug, Clone)]
@@ -11,35 +177,135 @@ pub struct LeaderboardEntry {
     pub rank: usize,
     pub name: String,
     pub time: f32,
+    pub checkpoints: Option<u32>,
     pub is_current_run: bool,
+    pub is_me: bool, // any of my runs, not just the current one
+    pub beats_my_best: bool, // ranked above my best run on this board and set since yesterday
+    pub annotations: ScoreAnnotations,
 }
 
 pub struct Leaderboard {
     // Map from seed -> sorted list of scores
     scores: HashMap<String, Vec<Score>>,
+    // Seed and hash of the level we generated. Times for it from clients with a different hash were set on a different level
+    level_seed: String,
+    level_hash: Option<String>,
+    // Seeds in the order they were last updated, oldest first
+    seed_order: Vec<String>,
+    // Skill ratings other players have sent with their times
+    ratings: HashMap<String, f32>,
+    // Version 2 syncs still waiting on parts, oldest first
+    partial_syncs: Vec<PartialSync>,
+}
+
+struct PartialSync {
+    seed: String,
+    id: String,
+    parts: Vec<Option<(String, String)>>, // data and notes
 }
 
 impl Leaderboard {
     pub fn new() -> Self {
         Self {
             scores: HashMap::new(),
+            level_seed: String::new(),
+            level_hash: None,
+            seed_order: Vec::new(),
+            ratings: HashMap::new(),
+            partial_syncs: Vec::new(),
         }
     }
 
-    pub fn add_score(&mut self, seed: String, user: String, time: f32) {
+    /// The level we are playing. Mode variants share the level seed as a prefix (eg. "-mirror") so they are checked too.
+    pub fn set_level_hash(&mut self, level_seed: &str, level_hash: Option<String>) {
+        self.level_seed = level_seed.to_owned();
+        self.level_hash = level_hash;
+    }
+
+    pub fn add_score(&mut self, seed: String, user: String, time: f32, checkpoints: Option<u32>) {
+        self.add_annotated_score(seed, user, time, checkpoints, ScoreAnnotations::default());
+    }
+
+    /// Add a score with what it was set with. A score that's already on the board gets the annotations it was missing instead.
+    pub fn add_annotated_score(&mut self, seed: String, user: String, time: f32, checkpoints: Option<u32>, annotations: ScoreAnnotations) {
+        self.seed_order.retain(|s| *s != seed);
+        self.seed_order.push(seed.clone());
         let entry = self.scores.entry(seed).or_insert(Vec::new());
-        entry.push(Score { user, time });
-        // Sort by time ascending (lowest time is best)
-        entry.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap_or(std::cmp::Ordering::Equal));
+        if let Some(score) = entry.iter_mut().find(|score| score.user == user && score.time == time && score.checkpoints == checkpoints) {
+            score.annotations.merge(annotations);
+            return;
+        }
+        entry.push(Score { user, time, checkpoints, annotations });
+        // Sort by checkpoints descending (only set for time attack), then time ascending (lowest time is best)
+        entry.sort_by(|a, b| b.checkpoints.cmp(&a.checkpoints).then(a.time.partial_cmp(&b.time).unwrap_or(std::cmp::Ordering::Equal)));
         // Keep top 10? Handled when displaying, but good to prune to avoid memory leak if running long?
         // Let's keep all for now, or prune to top 50.
         if entry.len() > 50 {
             entry.truncate(50);
         }
+
+        // a game left running for days would otherwise keep every old board. Times for other seeds coming in
+        // mustn't push out the board being shown, so the level being played is skipped over
+        while self.scores.len() > MAX_SEEDS {
+            let Some(oldest) = self.seed_order.iter().position(|seed| !self.is_level_seed(seed)) else {
+                break;
+            };
+            let oldest = self.seed_order.remove(oldest);
+            self.scores.remove(&oldest);
+        }
+    }
+
+    /// Whether the seed is for the level being played, including its mode variants
+    fn is_level_seed(&self, seed: &str) -> bool {
+        !self.level_seed.is_empty() && seed.starts_with(&self.level_seed)
+    }
+
+    pub fn num_seeds(&self) -> usize {
+        self.scores.len()
+    }
+
+    /// Total scores across all seeds
+    pub fn num_scores(&self) -> usize {
+        self.scores.values().map(|scores| scores.len()).sum()
+    }
+
+    /// Handle a message from a leaderboard channel.
+    /// Returns the LEADERBOARD_SYNC parts for the seed to reply with when someone posts a new time.
+    pub fn handle_message(&mut self, message: &str, seed: &str) -> Vec<String> {
+        if message.starts_with("BEST_TIME") {
+            self.parse_message(message);
+            return self.serialize_sync(seed);
+        } else if message.starts_with("LEADERBOARD_SYNC") {
+            self.parse_sync_message(message);
+        } else if message.starts_with("VERIFIED") {
+            self.parse_verified_message(message);
+        }
+        Vec::new()
+    }
+
+    /// Mark the time a VERIFIED message is for as verified or rejected. A time that isn't on the board is left alone.
+    pub fn parse_verified_message(&mut self, message: &str) {
+        if !message.starts_with("VERIFIED") {
+            return;
+        }
+        let field = |name: &str| message.split_whitespace().find_map(|part| part.strip_prefix(name)?.strip_prefix('='));
+        let (Some(seed), Some(user), Some(time), Some(result)) = (field("seed"), field("user"), field("time").and_then(|time| time.parse::<f32>().ok()), field("result")) else {
+            return;
+        };
+        let verification = match result {
+            "pass" => Verification::Verified,
+            "fail" => Verification::Rejected,
+            _ => return,
+        };
+        // the time was rounded to 3 places for the message
+        for score in self.scores.get_mut(seed).into_iter().flatten().filter(|score| score.user == user && (score.time - time).abs() < 0.0005) {
+            score.annotations.verification = verification;
+        }
     }
 
     pub fn parse_message(&mut self, message: &str) {
-        // Expected format: "BEST_TIME seed={} time={} user={}"
+        // Expected format: "BEST_TIME seed={} time={} user={} hash={} checkpoints={} rating={}" followed by the annotations
+        // hash, rating and annotations are optional as older clients don't send them, checkpoints is only sent for time attack
         if !message.starts_with("BEST_TIME") {
             return;
         }
@@ -48,6 +314,10 @@ impl Leaderboard {
         let mut seed = None;
         let mut time = None;
         let mut user = None;
+        let mut hash = None;
+        let mut checkpoints = None;
+        let mut rating = None;
+        let mut annotations = ScoreAnnotations::default();
 
         for part in parts {
             if part.starts_with("seed=") {
@@ -58,55 +328,159 @@ impl Leaderboard {
                 }
             } else if part.starts_with("user=") {
                 user = Some(part.trim_start_matches("user=").to_string());
+            } else if part.starts_with("hash=") {
+                hash = Some(part.trim_start_matches("hash=").to_string());
+            } else if part.starts_with("checkpoints=") {
+                checkpoints = part.trim_start_matches("checkpoints=").parse::<u32>().ok();
+            } else if part.starts_with("rating=") {
+                rating = part.trim_start_matches("rating=").parse::<f32>().ok();
+            } else {
+                annotations.parse_field(part);
             }
         }
+        // players can't vouch for their own times, that comes from a VERIFIED message
+        annotations.verification = Verification::Unchecked;
+        if annotations.set_at.is_none() {
+            annotations.set_at = Some(Utc::now().timestamp());
+        }
+
+        if let (Some(s), Some(hash), Some(level_hash)) = (&seed, &hash, &self.level_hash) {
+            if s.starts_with(&self.level_seed) && hash != level_hash {
+                println!("Ignoring BEST_TIME from a client with a different level generator (level hash {} vs {})", hash, level_hash);
+                return;
+            }
+        }
+
+        if let (Some(u), Some(r)) = (&user, rating) {
+            self.ratings.insert(u.clone(), r);
+        }
 
         if let (Some(s), Some(t), Some(u)) = (seed, time, user) {
-            self.add_score(s, u, t);
+            self.add_annotated_score(s, u, t, checkpoints, annotations);
         }
     }
 
-    pub fn serialize_sync(&self, seed: &str) -> Option<String> {
-        if let Some(scores) = self.scores.get(seed) {
-            let mut data = String::new();
-            for (i, score) in scores.iter().enumerate() {
-                if i > 0 {
-                    data.push(',');
+    /// The board for the seed as version 2 sync parts, empty if there is no board
+    pub fn serialize_sync(&self, seed: &str) -> Vec<String> {
+        let Some(scores) = self.scores.get(seed) else {
+            return Vec::new();
+        };
+        // parts are split between scores so each one still reads as a board to version 1 clients.
+        // Each part is its scores' data and notes, and both count towards its length
+        let mut chunks: Vec<(Vec<String>, Vec<String>)> = vec![(vec![], vec![])];
+        for score in scores {
+            let mut entry = format!("{}:{}", score.user, score.time);
+            if let Some(checkpoints) = score.checkpoints {
+                entry.push_str(&format!(":{}", checkpoints));
+            }
+            let note = score.annotations.to_note();
+            let (data, notes) = chunks.last().unwrap();
+            let len = data.iter().chain(notes).map(|s| s.len() + 1).sum::<usize>();
+            if !data.is_empty() && len + entry.len() + note.len() + 2 > SYNC_CHUNK_DATA_LEN {
+                if chunks.len() == MAX_SYNC_CHUNKS {
+                    break; // scores are sorted so only the slowest are left out
                 }
-                data.push_str(&format!("{}:{}", score.user, score.time));
+                chunks.push((vec![], vec![]));
             }
-            Some(format!("LEADERBOARD_SYNC seed={} data={}", seed, data))
-        } else {
-            None
+            let (data, notes) = chunks.last_mut().unwrap();
+            data.push(entry);
+            notes.push(note);
         }
+        let chunks: Vec<(String, String)> = chunks.into_iter().map(|(data, notes)| (data.join(","), notes.join(","))).collect();
+        let id = &sync_checksum(&chunks.iter().map(|(data, _)| data.as_str()).collect::<String>())[..8];
+        chunks.iter().enumerate().map(|(i, (data, notes))| {
+            // no notes field when none of the part's scores have any
+            let notes = if notes.chars().all(|c| c == ',') { String::new() } else { format!(" notes={}", notes) };
+            format!("LEADERBOARD_SYNC seed={} version=2 id={} part={}/{} sum={}{} data={}", seed, id, i + 1, chunks.len(), sync_checksum(data), notes, data)
+        }).collect()
     }
 
     pub fn parse_sync_message(&mut self, message: &str) {
         // Expected format: "LEADERBOARD_SYNC seed={} data=user1:time1,user2:time2,..."
+        // time attack entries have a third field: user1:time1:checkpoints1
+        // version 2 adds "version=2 id={} part={}/{} sum={}" and optionally "notes={}" before the data, see the protocol at the top
         if !message.starts_with("LEADERBOARD_SYNC") {
             return;
         }
 
-        let parts: Vec<&str> = message.split_whitespace().collect();
         let mut seed = None;
-        let mut data = None;
+        let mut version = 1;
+        let mut id = None;
+        let mut part = None;
+        let mut sum = None;
+        let mut notes = String::new();
+        // stop at the data, it is always last
+        for field in message.split(" data=").next().unwrap_or_default().split_whitespace() {
+            if let Some(s) = field.strip_prefix("seed=") {
+                seed = Some(s.to_string());
+            } else if let Some(v) = field.strip_prefix("version=") {
+                version = v.parse().unwrap_or(0);
+            } else if let Some(i) = field.strip_prefix("id=") {
+                id = Some(i.to_string());
+            } else if let Some(p) = field.strip_prefix("part=") {
+                part = p.split_once('/').and_then(|(i, n)| Some((i.parse::<usize>().ok()?, n.parse::<usize>().ok()?)));
+            } else if let Some(s) = field.strip_prefix("sum=") {
+                sum = Some(s.to_string());
+            } else if let Some(n) = field.strip_prefix("notes=") {
+                notes = n.to_string();
+            }
+        }
+        let (Some(seed), Some((_, data))) = (seed, message.split_once(" data=")) else {
+            return;
+        };
+        let data = data.trim();
 
-        for part in parts {
-            if part.starts_with("seed=") {
-                seed = Some(part.trim_start_matches("seed=").to_string());
-            } else if part.starts_with("data=") {
-                data = Some(part.trim_start_matches("data=").to_string());
+        if version == 1 {
+            self.add_sync_data(&seed, data, "");
+            return;
+        }
+        let (Some(id), Some((index, num_parts)), Some(sum)) = (id, part, sum) else {
+            return;
+        };
+        if version != 2 || index == 0 || index > num_parts || num_parts > MAX_SYNC_CHUNKS {
+            return;
+        }
+        if sum != sync_checksum(data) {
+            println!("Ignoring LEADERBOARD_SYNC part {}/{} for {} with a bad checksum", index, num_parts, seed);
+            return;
+        }
+
+        let position = match self.partial_syncs.iter().position(|partial| partial.seed == seed && partial.id == id) {
+            Some(position) => position,
+            None => {
+                if self.partial_syncs.len() == MAX_PARTIAL_SYNCS {
+                    self.partial_syncs.remove(0); // lost a part, it's not coming now
+                }
+                self.partial_syncs.push(PartialSync { seed, id, parts: vec![None; num_parts] });
+                self.partial_syncs.len() - 1
             }
+        };
+        let partial = &mut self.partial_syncs[position];
+        if partial.parts.len() != num_parts {
+            return;
+        }
+        partial.parts[index - 1] = Some((data.to_string(), notes));
+        if partial.parts.iter().any(|p| p.is_none()) {
+            return;
         }
 
-        if let (Some(s), Some(d)) = (seed, data) {
-            for entry in d.split(',') {
-                let subparts: Vec<&str> = entry.split(':').collect();
-                if subparts.len() == 2 {
-                    let user = subparts[0].to_string();
-                    if let Ok(time) = subparts[1].parse::<f32>() {
-                        self.add_score(s.clone(), user, time);
-                    }
+        let partial = self.partial_syncs.remove(position);
+        for (data, notes) in partial.parts.into_iter().flatten() {
+            self.add_sync_data(&partial.seed, &data, &notes);
+        }
+    }
+
+    /// Add the scores in a sync's data, "user1:time1,user2:time2:checkpoints2,...", with the annotations in its notes
+    fn add_sync_data(&mut self, seed: &str, data: &str, notes: &str) {
+        let mut notes = notes.split(',');
+        for entry in data.split(',') {
+            let annotations = ScoreAnnotations::from_note(notes.next().unwrap_or_default());
+            let subparts: Vec<&str> = entry.split(':').collect();
+            if subparts.len() == 2 || subparts.len() == 3 {
+                let user = subparts[0].to_string();
+                let checkpoints = subparts.get(2).and_then(|c| c.parse::<u32>().ok());
+                if let Ok(time) = subparts[1].parse::<f32>() {
+                    self.add_annotated_score(seed.to_owned(), user, time, checkpoints, annotations);
                 }
             }
         }
@@ -120,7 +494,10 @@ impl Leaderboard {
                 if i > 0 {
                     output.push_str(", ");
                 }
-                output.push_str(&format!("{}. {} ({:.3}s)", i + 1, scores[i].user, scores[i].time));
+                match scores[i].checkpoints {
+                    Some(checkpoints) => output.push_str(&format!("{}. {} ({} cp, {:.3}s)", i + 1, scores[i].user, checkpoints, scores[i].time)),
+                    None => output.push_str(&format!("{}. {} ({:.3}s)", i + 1, scores[i].user, scores[i].time)),
+                }
             }
             Some(output)
         } else {
@@ -128,79 +505,274 @@ impl Leaderboard {
         }
     }
 
+    /// Each player's best score on this seed, in rank order
+    pub fn best_scores(&self, seed: &str) -> Vec<&Score> {
+        let mut best: Vec<&Score> = Vec::new();
+        for score in self.scores.get(seed).into_iter().flatten() {
+            if !best.iter().any(|other| other.user == score.user) {
+                best.push(score); // scores are sorted, so the first is the best
+            }
+        }
+        best
+    }
+
+    /// My best rank on this seed and the number of different players on the board
+    pub fn placement(&self, seed: &str, current_user: &str) -> Option<(usize, usize)> {
+        let scores = self.scores.get(seed)?;
+        let mut players: Vec<&str> = Vec::new();
+        let mut rank = None;
+        for score in scores {
+            if !players.contains(&score.user.as_str()) {
+                players.push(&score.user);
+                if score.user == current_user {
+                    rank = Some(players.len());
+                }
+            }
+        }
+        rank.map(|rank| (rank, players.len()))
+    }
+
+    /// Average rating of the other players on this seed, players that haven't sent a rating count as default_rating
+    pub fn field_rating(&self, seed: &str, current_user: &str, default_rating: f32) -> f32 {
+        let Some(scores) = self.scores.get(seed) else {
+            return default_rating;
+        };
+        let mut players: Vec<&str> = scores.iter().map(|score| score.user.as_str()).filter(|user| *user != current_user).collect();
+        players.sort();
+        players.dedup();
+        if players.is_empty() {
+            return default_rating;
+        }
+        players.iter().map(|user| self.ratings.get(*user).copied().unwrap_or(default_rating)).sum::<f32>() / players.len() as f32
+    }
+
+    /// Friends ranked above my best on this seed. Empty if I haven't set a time yet.
+    pub fn friends_ahead(&self, seed: &str, current_user: &str, friends: &[String]) -> Vec<String> {
+        let Some(scores) = self.scores.get(seed) else {
+            return Vec::new();
+        };
+        let Some(my_best_rank) = scores.iter().position(|score| score.user == current_user) else {
+            return Vec::new();
+        };
+        let mut ahead: Vec<String> = Vec::new();
+        for score in &scores[..my_best_rank] {
+            if friends.iter().any(|friend| friend.eq_ignore_ascii_case(&score.user)) && !ahead.contains(&score.user) {
+                ahead.push(score.user.clone());
+            }
+        }
+        ahead
+    }
+
+    /// All entries for the seed in rank order, flagging the current run, my own entries and those set since yesterday that beat my best.
+    /// The current run is added at the end if it didn't make the board.
     pub fn get_leaderboard_entries(&self, seed: &str, current_user: &str, current_run_time: Option<f32>) -> Vec<LeaderboardEntry> {
+        let yesterday = Utc::now() - Duration::days(1);
         let mut entries = Vec::new();
         if let Some(scores) = self.scores.get(seed) {
+            // scores are sorted so my first score is my best
+            let my_best_rank = scores.iter().position(|score| score.user == current_user);
             let mut current_run_found = false;
 
-            // Get top 10
-            let count = std::cmp::min(scores.len(), 10);
-            for i in 0..count {
-                let is_current_run = scores[i].user == current_user && Some(scores[i].time) == current_run_time;
+            for (i, score) in scores.iter().enumerate() {
+                let is_me = score.user == current_user;
+                let is_current_run = is_me && !current_run_found && current_run_time.is_some_and(|run_time| (score.time - run_time).abs() < 0.0001);
                 if is_current_run {
                     current_run_found = true;
                 }
-                
-
 
                 entries.push(LeaderboardEntry {
                     rank: i + 1,
-                    name: scores[i].user.clone(),
-                    time: scores[i].time,
+                    name: score.user.clone(),
+                    time: score.time,
+                    checkpoints: score.checkpoints,
                     is_current_run,
+                    is_me,
+                    beats_my_best: my_best_rank.is_some_and(|rank| i < rank) && score.annotations.set_since(yesterday),
+                    annotations: score.annotations.clone(),
                 });
             }
 
-            // If current run not in top 10, find it (or the current user's best if we want to show their rank)
-            // But the request is to highlight only the current turn's score.
-            // If the current run didn't make it to top 10, we still might want to show it at the bottom.
+            // the run is so bad it's not even in the top 50 (truncated in add_score) but we still want to show it
             if !current_run_found {
                 if let Some(run_time) = current_run_time {
-                    // Find actual rank for the current run
-                    let mut found_run = false;
-                    for (i, score) in scores.iter().enumerate() {
-                        if score.user == current_user && (score.time - run_time).abs() < 0.0001 {
-                            entries.push(LeaderboardEntry {
-                                rank: i + 1,
-                                name: score.user.clone(),
-                                time: score.time,
-                                is_current_run: true,
-                            });
-                            found_run = true;
-                            break;
-                        }
-                    }
-                    
-                    // If the run is so bad it's not even in the top 50 (truncated)
-                    // but we still want to show it? The truncate happened in add_score.
-                    if !found_run {
-                         entries.push(LeaderboardEntry {
-                            rank: 999, // Unknown high rank
-                            name: current_user.to_string(),
-                            time: run_time,
-                            is_current_run: true,
-                        });
-                    }
-                } else {
-                    // If no current run time provided, maybe just show current user's best?
-                    // Previous code showed current user's best in top 10 or separately.
-                    // Let's stick to the current user's best if not in top 10, but not highlighted.
-                    for (i, score) in scores.iter().enumerate() {
-                        if score.user == current_user {
-                            if i >= 10 { // Only add if not already in top 10
-                                entries.push(LeaderboardEntry {
-                                    rank: i + 1,
-                                    name: score.user.clone(),
-                                    time: score.time,
-                                    is_current_run: false,
-                                });
-                            }
-                            break;
-                        }
-                    }
+                    entries.push(LeaderboardEntry {
+                        rank: 999, // Unknown high rank
+                        name: current_user.to_string(),
+                        time: run_time,
+                        checkpoints: None,
+                        is_current_run: true,
+                        is_me: true,
+                        beats_my_best: false,
+                        annotations: ScoreAnnotations::default(),
+                    });
                 }
             }
         }
         entries
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leaderboard_entries_flags() {
+        let mut leaderboard = Leaderboard::new();
+        leaderboard.parse_message("BEST_TIME seed=seed time=10.0 user=alice"); // set as it arrived
+        leaderboard.add_score("seed".to_owned(), "me".to_owned(), 12.0, None);
+        leaderboard.parse_message("BEST_TIME seed=seed time=10.5 user=carol at=1741910400"); // beat me long ago
+        leaderboard.add_score("seed".to_owned(), "bob".to_owned(), 11.0, None); // synced from an older client, so no idea when
+        leaderboard.add_score("seed".to_owned(), "me".to_owned(), 14.0, None);
+
+        let entries = leaderboard.get_leaderboard_entries("seed", "me", Some(14.0));
+        assert_eq!(entries.len(), 5);
+        // only the times set since yesterday are flagged
+        assert!(entries[0].beats_my_best && !entries[1].beats_my_best && !entries[2].beats_my_best);
+        assert!(entries[3].is_me && !entries[3].is_current_run && !entries[3].beats_my_best);
+        assert!(entries[4].is_me && entries[4].is_current_run);
+    }
+
+    #[test]
+    fn test_placement_and_field_rating() {
+        let mut leaderboard = Leaderboard::new();
+        leaderboard.parse_message("BEST_TIME seed=seed time=10.0 user=alice rating=1600");
+        leaderboard.parse_message("BEST_TIME seed=seed time=11.0 user=alice rating=1600");
+        leaderboard.add_score("seed".to_owned(), "me".to_owned(), 12.0, None);
+        leaderboard.add_score("seed".to_owned(), "bob".to_owned(), 13.0, None);
+
+        assert_eq!(leaderboard.placement("seed", "me"), Some((2, 3)));
+        assert_eq!(leaderboard.placement("seed", "carol"), None);
+        assert_eq!(leaderboard.field_rating("seed", "me", 1500.0), 1550.0);
+    }
+
+    #[test]
+    fn test_old_seeds_are_pruned() {
+        let mut leaderboard = Leaderboard::new();
+        for day in 0..MAX_SEEDS + 3 {
+            leaderboard.add_score(format!("2025-01-{:02}", day), "me".to_owned(), 10.0, None);
+        }
+        assert_eq!(leaderboard.num_seeds(), MAX_SEEDS);
+        assert_eq!(leaderboard.num_scores(), MAX_SEEDS);
+        assert!(leaderboard.get_top_10("2025-01-00").is_none());
+        assert!(leaderboard.get_top_10(&format!("2025-01-{:02}", MAX_SEEDS + 2)).is_some());
+
+        // times for other seeds don't push out the board for the level being played
+        leaderboard.set_level_hash("2025-02-01", None);
+        leaderboard.add_score("2025-02-01".to_owned(), "me".to_owned(), 10.0, None);
+        leaderboard.add_score("2025-02-01-mirror".to_owned(), "me".to_owned(), 10.0, None);
+        for day in 0..MAX_SEEDS {
+            leaderboard.parse_message(&format!("BEST_TIME seed=2025-03-{:02} time=10.0 user=bob", day));
+        }
+        assert_eq!(leaderboard.num_seeds(), MAX_SEEDS);
+        assert!(leaderboard.get_top_10("2025-02-01").is_some());
+        assert!(leaderboard.get_top_10("2025-02-01-mirror").is_some());
+    }
+
+    #[test]
+    fn test_level_hash_only_checked_for_our_level() {
+        let mut leaderboard = Leaderboard::new();
+        leaderboard.set_level_hash("2025-01-01", Some("aaaa".to_owned()));
+        leaderboard.parse_message("BEST_TIME seed=2025-01-01-mirror time=10.0 user=bob hash=bbbb");
+        leaderboard.parse_message("BEST_TIME seed=pack-jumps-001 time=12.0 user=bob hash=cccc");
+        assert!(leaderboard.get_top_10("2025-01-01-mirror").is_none());
+        assert!(leaderboard.get_top_10("pack-jumps-001").is_some());
+    }
+
+    #[test]
+    fn test_friends_ahead() {
+        let mut leaderboard = Leaderboard::new();
+        let friends = vec!["Bob".to_owned()];
+        leaderboard.add_score("seed".to_owned(), "bob".to_owned(), 13.0, None);
+        assert!(leaderboard.friends_ahead("seed", "me", &friends).is_empty()); // no time set yet
+
+        leaderboard.add_score("seed".to_owned(), "me".to_owned(), 12.0, None);
+        assert!(leaderboard.friends_ahead("seed", "me", &friends).is_empty());
+
+        leaderboard.parse_message("BEST_TIME seed=seed time=11.5 user=bob");
+        leaderboard.add_score("seed".to_owned(), "alice".to_owned(), 10.0, None);
+        assert_eq!(leaderboard.friends_ahead("seed", "me", &friends), vec!["bob".to_owned()]);
+    }
+
+    #[test]
+    fn test_sync_is_split_into_parts_and_reassembled() {
+        let mut leaderboard = Leaderboard::new();
+        for i in 0..50 {
+            leaderboard.add_score("seed".to_owned(), format!("player_with_a_long_name{}", i), 10.0 + i as f32 * 0.123, None);
+        }
+        let parts = leaderboard.serialize_sync("seed");
+        assert!(parts.len() > 1);
+        assert!(parts.iter().all(|part| part.len() < 450));
+
+        let mut other = Leaderboard::new();
+        other.parse_sync_message("LEADERBOARD_SYNC seed=seed data=old:9.5,client:9.6"); // version 1 peers still work
+        assert_eq!(other.num_scores(), 2);
+
+        // parts can arrive in any order, nothing is added until the last one arrives
+        for part in parts[1..].iter().rev() {
+            other.parse_sync_message(part);
+        }
+        // and a part with a bad checksum is dropped
+        other.parse_sync_message(&parts[0].replace("player_with_a_long_name0:10", "player_with_a_long_name0:1"));
+        assert_eq!(other.num_scores(), 2);
+        other.parse_sync_message(&parts[0]);
+        assert_eq!(other.num_scores(), 50); // the slowest two fall off the board
+        assert_eq!(other.best_scores("seed")[2].user, "player_with_a_long_name0");
+
+        assert!(leaderboard.serialize_sync("no board").is_empty());
+    }
+
+    #[test]
+    fn test_annotations_are_sent_and_synced() {
+        let mut leaderboard = Leaderboard::new();
+        leaderboard.parse_message("BEST_TIME seed=seed time=10.0 user=alice car=standard mods=mirror+headwind ghost=1 verified=1 at=1741910400 future=field");
+        leaderboard.parse_message("BEST_TIME seed=seed time=11.0 user=bob at=1741914000");
+        leaderboard.parse_verified_message(&verified_message("seed", "bob", 11.0, true));
+        leaderboard.parse_verified_message(&verified_message("seed", "carol", 12.0, false)); // not on the board
+
+        let entries = leaderboard.get_leaderboard_entries("seed", "me", None);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].annotations, ScoreAnnotations {
+            car: Some("standard".to_owned()),
+            modifiers: vec!["mirror".to_owned(), "headwind".to_owned()],
+            ghost: true,
+            verification: Verification::Unchecked, // alice can't verify her own time
+            set_at: Some(1741910400),
+        });
+        assert_eq!(entries[1].annotations.verification, Verification::Verified);
+
+        // the annotations go along with the sync, and an old client's entries still read
+        let parts = leaderboard.serialize_sync("seed");
+        assert_eq!(parts.len(), 1);
+        assert!(parts[0].contains(" notes=car=standard;mods=mirror+headwind;ghost=1;at=1741910400,verified=1;at=1741914000 data="));
+        let mut other = Leaderboard::new();
+        other.parse_sync_message(&parts[0]);
+        other.parse_sync_message(&parts[0].replace(" notes=car=standard;mods=mirror+headwind;ghost=1;at=1741910400,verified=1;at=1741914000", ""));
+        let synced = other.get_leaderboard_entries("seed", "me", None);
+        assert_eq!(synced.len(), 2); // the repeated sync doesn't add them again
+        assert_eq!(synced[0].annotations, entries[0].annotations);
+        assert_eq!(synced[1].annotations, entries[1].annotations);
+
+        // a newer client's notes with fields this one doesn't know about
+        other.parse_sync_message("LEADERBOARD_SYNC seed=new data=dave:9.5,erin:9.6");
+        let newer = format!("LEADERBOARD_SYNC seed=new version=2 id=1 part=1/1 sum={} notes=car=buggy;wheels=6,colour=red data=dave:9.5,erin:9.6", sync_checksum("dave:9.5,erin:9.6"));
+        other.parse_sync_message(&newer);
+        let synced = other.get_leaderboard_entries("new", "me", None);
+        assert_eq!(synced.len(), 2);
+        assert_eq!(synced[0].annotations.car.as_deref(), Some("buggy"));
+        assert_eq!(synced[1].annotations, ScoreAnnotations::default());
+    }
+
+    #[test]
+    fn test_one_player_answers_a_request() {
+        assert_eq!(parse_request(&request_message("2025-01-01")), Some(String::from("2025-01-01")));
+        assert_eq!(parse_request("LEADERBOARD_REQUEST seed="), None);
+
+        let players = vec![String::from("alice"), String::from("bob"), String::from("carol")];
+        let answering: Vec<&String> = players.iter().filter(|me| answers_request(me, "Alice", &players)).collect();
+        assert_eq!(answering, vec!["bob"]);
+        // before my presence ping has gone out
+        assert!(answers_request("Aaron", "alice", &players));
+        assert!(!answers_request("alice", "alice", &players));
+    }
+}
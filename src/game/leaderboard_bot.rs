@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::game::{
+    clock_check::{clock_warning, query_offset},
+    irc::irc_manager::{IrcEvent, IrcManager},
+    leaderboard::Leaderboard,
+    level_name::level_name,
+    metrics::Metrics,
+    seed_policy::{daily_seed, SeedRolloverWatcher},
+    settings::Settings,
+};
+
+pub const DEFAULT_BOT_NICKNAME: &str = "planck-digest";
+const LEADERBOARD_CHANNEL: &str = "#planck-leaderboard";
+const DIGEST_SIZE: usize = 10;
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// The day's results on one board, posted when the daily rolls over
+#[derive(Debug, Clone, PartialEq)]
+pub struct Digest {
+    pub seed: String,
+    pub top: Vec<(String, f32)>, // best time of the top players
+    pub players: usize,
+    pub runs: usize, // times posted during the day, including ones that didn't make the board
+    pub median: f32, // of each player's best
+}
+
+impl Digest {
+    /// None if nobody set a time on the seed
+    pub fn new(leaderboard: &Leaderboard, seed: &str, runs: usize) -> Option<Self> {
+        let best = leaderboard.best_scores(seed);
+        if best.is_empty() {
+            return None;
+        }
+
+        let median = if best.len() % 2 == 1 {
+            best[best.len() / 2].time
+        } else {
+            (best[best.len() / 2 - 1].time + best[best.len() / 2].time) / 2.0
+        };
+        Some(Self {
+            seed: seed.to_owned(),
+            top: best.iter().take(DIGEST_SIZE).map(|score| (score.user.clone(), score.time)).collect(),
+            players: best.len(),
+            runs: runs.max(best.len()), // times synced from before the bot joined weren't counted
+            median,
+        })
+    }
+
+    /// One line of stats, with the level's name so it can be talked about, and one of the top times. Each is short enough for an IRC message
+    pub fn to_messages(&self) -> Vec<String> {
+        let players = if self.players == 1 { String::from("1 player") } else { format!("{} players", self.players) };
+        let runs = if self.runs == 1 { String::from("1 run") } else { format!("{} runs", self.runs) };
+        let top = self.top.iter().enumerate()
+            .map(|(i, (user, time))| format!("{}. {} {:.3}s", i + 1, user, time))
+            .collect::<Vec<_>>()
+            .join(", ");
+        vec![
+            format!("DIGEST {} ({}): {}, {}, median {:.3}s", self.seed, level_name(&self.seed), players, runs, self.median),
+            format!("DIGEST {} top {}: {}", self.seed, self.top.len(), top),
+        ]
+    }
+}
+
+/// A channel the bot keeps a board for
+struct ChannelBoard {
+    channel: String,
+    leaderboard: Leaderboard,
+    runs: HashMap<String, usize>, // BEST_TIME messages seen per seed
+}
+
+impl ChannelBoard {
+    fn new(channel: String) -> Self {
+        Self { channel, leaderboard: Leaderboard::new(), runs: HashMap::new() }
+    }
+
+    /// Keep the board up to date, replying to new times with the board like a game client would
+    fn handle_message(&mut self, message: &str, seed: &str) -> Vec<String> {
+        if message.starts_with("BEST_TIME") {
+            if let Some(run_seed) = message.split_whitespace().find_map(|part| part.strip_prefix("seed=")) {
+                *self.runs.entry(run_seed.to_owned()).or_insert(0) += 1;
+            }
+        }
+        self.leaderboard.handle_message(message, seed)
+    }
+
+    fn digest(&mut self, seed: &str) -> Option<Digest> {
+        let runs = self.runs.remove(seed).unwrap_or(0);
+        Digest::new(&self.leaderboard, seed, runs)
+    }
+}
+
+fn connect(nickname: &str, settings: &Settings) -> IrcManager {
+    let mut channels = vec![LEADERBOARD_CHANNEL.to_owned()];
+    let mut channel_keys = HashMap::new();
+    if let Some(private_channel) = settings.private_channel() {
+        if let Some(key) = settings.private_channel_key.as_ref().filter(|key| !key.is_empty()) {
+            channel_keys.insert(private_channel.clone(), key.clone());
+        }
+        channels.push(private_channel);
+    }
+    let mut irc = IrcManager::new("irc.libera.chat".to_owned(), nickname.to_owned(), channels, channel_keys);
+    irc.set_ignored(settings.ignored_players.as_deref().unwrap_or_default());
+    irc
+}
+
+/// Idle in the leaderboard channel (and the private channel from settings), keeping the day's boards,
+/// and post a digest of each at rollover. Runs until the process is killed.
+pub fn run_bot(nickname: String, metrics_path: Option<PathBuf>) {
+    let settings = Settings::load();
+    let mut boards = vec![ChannelBoard::new(LEADERBOARD_CHANNEL.to_owned())];
+    if let Some(private_channel) = settings.private_channel() {
+        boards.push(ChannelBoard::new(private_channel));
+    }
+
+    // digests are posted at rollover by the local clock, so say if it's wrong
+    if !settings.time_server().is_empty() {
+        match query_offset(settings.time_server()) {
+            Ok(offset) => {
+                if let Some(warning) = clock_warning(chrono::Utc::now(), offset) {
+                    println!("{}", warning);
+                }
+            }
+            Err(e) => println!("Couldn't check the clock: {}", e),
+        }
+    }
+
+    let mut irc = connect(&nickname, &settings);
+    let mut rollover_watcher = SeedRolloverWatcher::new(chrono::Utc::now());
+    let mut metrics = Metrics::new(metrics_path);
+    println!("Leaderboard bot {} watching {}", nickname, boards.iter().map(|board| board.channel.as_str()).collect::<Vec<_>>().join(", "));
+
+    loop {
+        let seed = daily_seed(chrono::Utc::now());
+        let mut disconnected = false;
+        for event in irc.process_events() {
+            match event {
+                IrcEvent::MessageReceived { target, message, .. } => {
+                    metrics.increment("messages_received");
+                    if message.starts_with("BEST_TIME") {
+                        metrics.increment("best_times");
+                    }
+                    let Some(board) = boards.iter_mut().find(|board| board.channel.eq_ignore_ascii_case(&target)) else { continue };
+                    for sync_msg in board.handle_message(&message, &seed) {
+                        irc.send_message(target.clone(), sync_msg);
+                    }
+                }
+                IrcEvent::Disconnected => disconnected = true,
+                _ => {}
+            }
+        }
+
+        if let Some(rollover) = rollover_watcher.check(chrono::Utc::now()) {
+            for board in &mut boards {
+                let Some(digest) = board.digest(&rollover.previous_seed) else { continue };
+                for message in digest.to_messages() {
+                    irc.send_message(board.channel.clone(), message);
+                }
+                metrics.increment("digests_posted");
+            }
+        }
+
+        if disconnected {
+            println!("Disconnected, reconnecting in {} seconds", RECONNECT_DELAY.as_secs());
+            thread::sleep(RECONNECT_DELAY);
+            irc = connect(&nickname, &settings);
+            metrics.increment("irc_reconnects");
+        }
+        metrics.set("leaderboard_scores", boards.iter().map(|board| board.leaderboard.num_scores() as u64).sum());
+        metrics.update(Instant::now());
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_digest() {
+        let mut board = ChannelBoard::new(LEADERBOARD_CHANNEL.to_owned());
+        for (user, time) in [("alice", 42.0), ("bob", 40.5), ("alice", 39.25), ("carol", 50.0), ("dave", 45.0)] {
+            board.handle_message(&format!("BEST_TIME seed=2025-03-14 time={} user={}", time, user), "2025-03-14");
+        }
+        // another day's board doesn't count
+        board.handle_message("BEST_TIME seed=2025-03-13 time=30 user=erin", "2025-03-14");
+
+        let digest = board.digest("2025-03-14").unwrap();
+        assert_eq!(digest.players, 4);
+        assert_eq!(digest.runs, 5);
+        assert_eq!(digest.median, 42.75);
+        assert_eq!(digest.to_messages(), vec![
+            format!("DIGEST 2025-03-14 ({}): 4 players, 5 runs, median 42.750s", level_name("2025-03-14")),
+            String::from("DIGEST 2025-03-14 top 4: 1. alice 39.250s, 2. bob 40.500s, 3. dave 45.000s, 4. carol 50.000s"),
+        ]);
+
+        assert_eq!(board.digest("2025-03-15"), None);
+    }
+}
@@ -0,0 +1,40 @@
+use std::ops::Range;
+
+use crate::{core::math::{aabb2d::Aabb2d, vec2::Vec2}, simulation::particles::particle::Particle};
+
+/// Where a level block ended up, recorded as it's built so passes over the whole level can see how the blocks meet.
+#[derive(Debug, Clone)]
+pub struct LevelBlockBounds {
+    pub aabb: Aabb2d, // around the block's ground and its entry and exit
+    pub entry: Vec2, // cursor before the block
+    pub exit: Vec2, // cursor after the block
+    pub x_direction: f32, // which way the cursor was pointing when the block finished
+    pub particles: Range<usize>, // particles the block added to the simulation
+}
+
+impl LevelBlockBounds {
+    pub fn new(entry: Vec2, exit: Vec2, x_direction: f32, particles: Range<usize>, all_particles: &[Particle]) -> Self {
+        let mut points = vec![entry, exit];
+        points.extend(all_particles[particles.clone()].iter().filter(|p| p.mass == 0.0).map(|p| p.pos));
+
+        Self {
+            aabb: Aabb2d::from_point_cloud(&points),
+            entry,
+            exit,
+            x_direction,
+            particles,
+        }
+    }
+
+    /// Height of the top of this block's ground at x (particle centres, like the lines blocks are built from), looking no further than max_dy above or below near_y.
+    /// None if the block has no ground there, eg. a gap or the far side of a cliff.
+    pub fn surface_height(&self, all_particles: &[Particle], x: f32, near_y: f32, max_dy: f32) -> Option<f32> {
+        all_particles[self.particles.clone()].iter()
+            .filter(|p| p.mass == 0.0)
+            // ground is sampled a couple of diameters apart at most (hills leave a gap for grip)
+            .filter(|p| (p.pos.x - x).abs() <= p.radius * 2.0 && (p.pos.y - near_y).abs() <= max_dy)
+            // the nearest one, or the top of a wall
+            .max_by(|a, b| (b.pos.x - x).abs().total_cmp(&(a.pos.x - x).abs()).then(a.pos.y.total_cmp(&b.pos.y)))
+            .map(|p| p.pos.y)
+    }
+}
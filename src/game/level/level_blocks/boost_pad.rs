@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{core::math::{aabb2d::Aabb2d, vec2::Vec2, vec4::Vec4}, game::{entity::{entities::car_entity::CarEntitySystem, entity_system::UpdateContext}, level::{level_builder::LevelBuilderContext, level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}}}, simulation::particles::shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}};
+
+const PAD_REACH: f32 = 1.5; // how far above the pad the car is still over it, the car is under a metre across
+const RUN_OUT: f32 = 6.0; // flat ground after the pad to pick up the speed on
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoostPadParams {
+    /// Flat ground before the pad.
+    pub run_up: f32,
+    /// Length of the pad along the ground.
+    pub length: f32,
+    /// How much higher the far end of the pad is than the near end, a raised pad kicks the car up as well as along.
+    pub rise: f32,
+    /// Speed in m/s the car gains going over the pad.
+    pub boost: f32,
+}
+
+impl LevelBuilderOperationParams for BoostPadParams {
+    fn random(level_builder_context: &mut LevelBuilderContext) -> Self {
+        Self {
+            run_up: level_builder_context.random_quantized(3.0, 5.0, 1.0),
+            length: level_builder_context.random_quantized(2.0, 3.0, 0.5),
+            rise: level_builder_context.random_quantized(0.0, 1.0, 0.5),
+            boost: level_builder_context.random_quantized(3.0, 6.0, 1.0),
+        }
+    }
+}
+
+/// A strip of ground that kicks the car forward along it as it drives over, then a flat run out.
+/// It has no spawn chance of its own yet, so the generator only uses it once level_gen.toml gives it one.
+#[derive(Default, Clone)]
+pub struct BoostPadOperation {
+    pub params: Option<BoostPadParams>,
+}
+
+impl LevelBuilderOperation for BoostPadOperation {
+    fn type_name(&self) -> &str {"BoostPadOperation"}
+
+    fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.params.as_ref()?).ok()
+    }
+
+    fn box_clone_with_params(&self, params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(Box::new(BoostPadOperation { params: Some(serde_json::from_value(params)?) }))
+    }
+
+    fn default_spawn_chance(&self) -> f32 {
+        0.0
+    }
+
+    fn default_difficulty(&self) -> f32 {
+        1.0
+    }
+
+    fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
+        let params = level_builder_context.resolve_params(&self.params);
+        let x_direction = level_builder_context.x_direction;
+
+        let cursor_start = level_builder_context.cursor;
+        let pad_start = cursor_start + Vec2::new(params.run_up * x_direction, 0.0);
+        let pad_end = pad_start + Vec2::new(params.length * x_direction, params.rise);
+        let cursor_end = pad_end + Vec2::new(RUN_OUT * x_direction, 0.0);
+
+        let pad = Vec4::new(0.1, 0.8, 0.9, 1.0);
+        ShapeBuilder::from_particle_template(*level_builder_context.particle_template.clone().set_static(true))
+            .apply_operation(LineSegment::new(cursor_start, pad_start))
+            .apply_operation(LineSegment::new(pad_end, cursor_end))
+            .create_in_simulation(level_builder_context.sim);
+        ShapeBuilder::from_particle_template(*level_builder_context.particle_template.clone().set_static(true).set_colour(pad))
+            .apply_operation(LineSegment::new(pad_start, pad_end))
+            .create_in_simulation(level_builder_context.sim);
+
+        level_builder_context.entity_system.boost_pad_entity_system.push(BoostPadEntity::new(pad_start, pad_end, params.boost));
+
+        level_builder_context.cursor = cursor_end;
+    }
+}
+
+/// Kicks a car along the pad once each time it drives over, when its centre crosses the middle so the whole car is over the pad
+#[derive(Clone)]
+pub struct BoostPadEntity {
+    pub start: Vec2, // the pad's surface, start to end in the direction it boosts
+    pub end: Vec2,
+    pub boost: f32,
+    region: Aabb2d, // over the pad, the parts of the car in here are kicked
+    armed: bool, // off after a kick until the car leaves the pad
+}
+
+impl BoostPadEntity {
+    pub fn new(start: Vec2, end: Vec2, boost: f32) -> Self {
+        let mut e = Self { start, end, boost, region: Aabb2d { min: Vec2::zero(), max: Vec2::zero() }, armed: true };
+        e.update_region();
+        e
+    }
+
+    /// Unit length, along the pad's surface
+    pub fn direction(&self) -> Vec2 {
+        (self.end - self.start).normalize()
+    }
+
+    pub fn middle(&self) -> Vec2 {
+        (self.start + self.end) * 0.5
+    }
+
+    fn update_region(&mut self) {
+        let (min, max) = (Vec2::min(self.start, self.end), Vec2::max(self.start, self.end));
+        self.region = Aabb2d { min, max: max + Vec2::new(0.0, PAD_REACH) };
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct BoostPadEntitySystem(pub Vec<BoostPadEntity>);
+
+impl BoostPadEntitySystem {
+    pub fn new() -> Self {
+        Self(vec![])
+    }
+
+    pub fn push(&mut self, e: BoostPadEntity) {
+        self.0.push(e);
+    }
+
+    pub fn mirror_x(&mut self) {
+        for e in self.0.iter_mut() {
+            e.start.x = -e.start.x;
+            e.end.x = -e.end.x;
+            e.update_region();
+        }
+    }
+
+    /// Kick any car heading the pad's way over its middle, the simulation picks up the new speed next step.
+    /// Only the car's own particles are kicked, not water, debris or another car that happens to be on the pad.
+    pub fn update(&mut self, context: &mut UpdateContext, car_entity_system: &CarEntitySystem) {
+        for e in self.0.iter_mut() {
+            let direction = e.direction();
+            let mut on_pad = false;
+            for car in car_entity_system.0.iter() {
+                let car_pos = car.get_camera_look_at_position(&context.sim.particles);
+                if !e.region.contains_point(car_pos) {
+                    continue;
+                }
+                on_pad = true;
+                let past_middle = (car_pos - e.middle()).dot(direction) >= 0.0;
+                if e.armed && past_middle && car.velocity(&context.sim.particles).dot(direction) > 0.0 {
+                    context.sim.apply_impulse_in_region(car.particle_handles(), &e.region, direction * e.boost);
+                    e.armed = false;
+                }
+            }
+            if !on_pad {
+                e.armed = true;
+            }
+        }
+    }
+}
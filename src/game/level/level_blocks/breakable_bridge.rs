@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{core::math::{vec2::Vec2, vec4::Vec4}, game::level::{level_builder::LevelBuilderContext, level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}}, simulation::{constraints::distance_constraint::DistanceConstraint, particles::shape_builder::{rectangle::Rectangle, rectangle_stick_grid::RectangleStickGrid, shape_builder::ShapeBuilder}}};
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BreakableBridgeParams {
+    /// Length of the bridge between the two fixed ends.
+    pub width: f32,
+    /// How far a stick can stretch, as a fraction of its length, before it snaps.
+    /// The bridge bounces up to about 0.2 as it first sags, and the car stretches it well past that.
+    pub max_strain: f32,
+}
+
+impl LevelBuilderOperationParams for BreakableBridgeParams {
+    fn random(level_builder_context: &mut LevelBuilderContext) -> Self {
+        Self {
+            width: level_builder_context.random_quantized(2.0, 5.0, 0.5),
+            max_strain: level_builder_context.random_quantized(0.25, 0.35, 0.05),
+        }
+    }
+}
+
+/// A saggy bridge with brittle sticks: it holds its own weight but gives way under the car, so cross it fast.
+/// Spawn chance is 0 until level_gen.toml turns it on, which keeps the daily levels unchanged.
+#[derive(Default, Clone)]
+pub struct BreakableBridgeOperation {
+    pub params: Option<BreakableBridgeParams>,
+}
+
+impl LevelBuilderOperation for BreakableBridgeOperation {
+    fn type_name(&self) -> &str {"BreakableBridgeOperation"}
+
+    fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.params.as_ref()?).ok()
+    }
+
+    fn box_clone_with_params(&self, params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(Box::new(BreakableBridgeOperation { params: Some(serde_json::from_value(params)?) }))
+    }
+
+    fn default_spawn_chance(&self) -> f32 {
+        0.0
+    }
+
+    fn default_difficulty(&self) -> f32 {
+        2.0
+    }
+
+    fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
+        let params = level_builder_context.resolve_params(&self.params);
+
+        let rect_height = level_builder_context.particle_template.radius * 8.0;
+
+        let cursor_start = level_builder_context.cursor;
+        let cursor_end = cursor_start + Vec2::new(params.width * level_builder_context.x_direction, 0.0);
+
+        let offset = Vec2::new(0.0, level_builder_context.particle_template.radius * 2.0);
+        let rectangle = Rectangle::from_corners(cursor_start + offset, cursor_end + Vec2::new(0.0, -rect_height) + offset);
+
+        let particle_vec_start_index = level_builder_context.sim.particles.len();
+
+        // light planks so the car's weight is what stretches the sticks, not the bridge's own
+        let brown = Vec4::new(0.6, 0.4, 0.2, 1.0);
+
+        let mut sb = ShapeBuilder::from_particle_template(*level_builder_context.particle_template.clone().set_colour(brown).set_mass(0.2).set_static(false));
+        sb.apply_operation(rectangle.clone());
+
+        // the end columns are anchored, everything between hangs off them
+        let aabb = sb.get_aabb();
+        sb.particles.iter_mut().for_each(|particle| {
+            if particle.pos.x == aabb.min.x || particle.pos.x == aabb.max.x {
+                particle.set_mass(0.0);
+            }
+        });
+
+        sb.create_in_simulation(level_builder_context.sim);
+
+        RectangleStickGrid::from_rectangle(rectangle)
+            .compute_particle_pairs(sb.particle_radius(), particle_vec_start_index)
+            .iter()
+            .for_each(|particle_handles| {
+                let sim = &mut *level_builder_context.sim;
+                let d = (sim.particles[particle_handles[0]].pos - sim.particles[particle_handles[1]].pos).magnitude();
+                sim.add_distance_constraint(DistanceConstraint::breakable(d, particle_handles[0], particle_handles[1], params.max_strain));
+            });
+
+        level_builder_context.cursor = cursor_end;
+    }
+}
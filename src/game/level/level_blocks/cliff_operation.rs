@@ -1,15 +1,39 @@
-use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::{core::math::vec2::Vec2, game::level::{level_builder::LevelBuilderContext, level_builder_operation::LevelBuilderOperation}, simulation::particles::shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}};
+use crate::{core::math::{aabb2d::Aabb2d, vec2::Vec2}, game::{entity::entities::camera_entity::CameraEntity, level::{level_builder::LevelBuilderContext, level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}}}, simulation::particles::shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}};
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CliffParams {
+    /// How far the ground drops (negative is down).
+    pub height: f32,
+}
+
+impl LevelBuilderOperationParams for CliffParams {
+    fn random(level_builder_context: &mut LevelBuilderContext) -> Self {
+        Self {
+            height: level_builder_context.random_quantized(-2.0, -0.5, 0.5),
+        }
+    }
+}
+
+#[derive(Default, Clone)]
 pub struct CliffOperation {
+    pub params: Option<CliffParams>,
 }
 
 impl LevelBuilderOperation for CliffOperation {
     fn type_name(&self) -> &str {"CliffOperation"}
 
     fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
-        Box::new(CliffOperation {})
+        Box::new(self.clone())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.params.as_ref()?).ok()
+    }
+
+    fn box_clone_with_params(&self, params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(Box::new(CliffOperation { params: Some(serde_json::from_value(params)?) }))
     }
 
     fn default_spawn_chance(&self) -> f32 {
@@ -17,10 +41,10 @@ impl LevelBuilderOperation for CliffOperation {
     }
 
     fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
-        let rng = &mut level_builder_context.rng;
+        let params = level_builder_context.resolve_params(&self.params);
 
         let width = 0.0;
-        let height = rng.random_range(-2.0..=-0.5);
+        let height = params.height;
 
         let cursor_start = level_builder_context.cursor;
         let cursor_end = cursor_start + Vec2::new(width * level_builder_context.x_direction, height);
@@ -30,6 +54,11 @@ impl LevelBuilderOperation for CliffOperation {
             .apply_operation(LineSegment::new(cursor_end, cursor_end))
             .create_in_simulation(level_builder_context.sim); //.create_in_particle_vec(level_builder_context.particle_vec);
 
+        // a trackside camera down at the bottom, looking up at the car coming over the edge
+        let landing = cursor_end + Vec2::new(3.0 * level_builder_context.x_direction, 0.5);
+        let watching = Aabb2d::new((cursor_start + cursor_end) * 0.5, Vec2::new(4.0, 3.0));
+        level_builder_context.entity_system.camera_entity_system.push(CameraEntity::new(landing, 6.0, watching));
+
         level_builder_context.cursor = cursor_end;
     }
 }
@@ -1,26 +1,56 @@
-use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::{core::math::vec2::Vec2, game::level::{level_builder::LevelBuilderContext, level_builder_operation::LevelBuilderOperation}, simulation::particles::shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}};
+use crate::{core::math::vec2::Vec2, game::level::{level_builder::LevelBuilderContext, level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}}, simulation::particles::shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}};
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DropDirectionReverseParams {
+    pub width: f32,
+    /// How far we drop down before heading back the other way (negative is down).
+    pub height: f32,
+}
+
+impl LevelBuilderOperationParams for DropDirectionReverseParams {
+    fn random(level_builder_context: &mut LevelBuilderContext) -> Self {
+        Self {
+            width: 3.0,
+            height: level_builder_context.random_quantized(-4.5, -2.5, 0.5),
+        }
+    }
+}
+
+#[derive(Default, Clone)]
 pub struct DropDirectionReverse {
+    pub params: Option<DropDirectionReverseParams>,
 }
 
 impl LevelBuilderOperation for DropDirectionReverse {
     fn type_name(&self) -> &str {"DropDirectionReverse"}
 
     fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
-        Box::new(DropDirectionReverse {})
+        Box::new(self.clone())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.params.as_ref()?).ok()
+    }
+
+    fn box_clone_with_params(&self, params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(Box::new(DropDirectionReverse { params: Some(serde_json::from_value(params)?) }))
     }
 
     fn default_spawn_chance(&self) -> f32 {
         0.3
     }
 
+    fn default_difficulty(&self) -> f32 {
+        1.5
+    }
+
     fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
-        let rng = &mut level_builder_context.rng;
+        let params = level_builder_context.resolve_params(&self.params);
 
-        let width = 3.0;
-        let height = rng.random_range(-4.5..=-2.5);
+        let width = params.width;
+        let height = params.height;
 
         let cursor_start = level_builder_context.cursor;
         let cursor_end = cursor_start + Vec2::new(level_builder_context.x_direction * width, height);
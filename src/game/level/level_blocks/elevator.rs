@@ -1,26 +1,77 @@
-use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::{core::math::{vec2::Vec2, vec4::Vec4}, game::{entity::entity_system::UpdateContext, level::{level_builder::LevelBuilderContext, level_builder_operation::LevelBuilderOperation}}, simulation::particles::shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}};
+use crate::{core::math::{vec2::Vec2, vec4::Vec4}, engine::app::event_system::KeyCodeType, game::{entity::{entities::car_entity::CarEntitySystem, entity_system::UpdateContext}, level::{level_builder::LevelBuilderContext, level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}}}, simulation::particles::shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}};
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ElevatorParams {
+    pub width: f32,
+    /// How high the elevator lifts the car.
+    pub height: f32,
+    pub speed: f32,
+    /// How long the elevator waits at a middle stop for a choice, or with nobody on it before going back down (seconds).
+    pub wait_time: f32,
+    /// How long after the car stops on the platform the elevator starts moving (seconds).
+    #[serde(default = "default_call_delay")]
+    pub call_delay: f32,
+    /// Heights of any stops between the bottom and the top.
+    #[serde(default)]
+    pub stops: Vec<f32>,
+}
+
+fn default_call_delay() -> f32 {
+    0.5
+}
+
+impl LevelBuilderOperationParams for ElevatorParams {
+    fn random(level_builder_context: &mut LevelBuilderContext) -> Self {
+        let height = level_builder_context.random_quantized(1.0, 4.0, 0.5);
+        let speed = level_builder_context.random_quantized(1.0, 2.0, 0.25);
+        // tall shafts get a stop half way up
+        let stops = if height >= 3.0 { vec![height * 0.5] } else { vec![] };
+        Self {
+            width: 2.0,
+            height,
+            speed,
+            wait_time: 2.0,
+            call_delay: default_call_delay(),
+            stops,
+        }
+    }
+}
+
+#[derive(Default, Clone)]
 pub struct ElevatorOperation {
+    pub params: Option<ElevatorParams>,
 }
 
 impl LevelBuilderOperation for ElevatorOperation {
     fn type_name(&self) -> &str {"ElevatorOperation"}
 
     fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
-        Box::new(ElevatorOperation {})
+        Box::new(self.clone())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.params.as_ref()?).ok()
+    }
+
+    fn box_clone_with_params(&self, params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(Box::new(ElevatorOperation { params: Some(serde_json::from_value(params)?) }))
     }
 
     fn default_spawn_chance(&self) -> f32 {
         0.5
     }
 
+    fn default_difficulty(&self) -> f32 {
+        2.0
+    }
+
     fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
-        let rng = &mut level_builder_context.rng;
+        let params = level_builder_context.resolve_params(&self.params);
 
-        let width = 2.0;
-        let height = rng.random_range(1.0..=4.0);
+        let width = params.width;
+        let height = params.height;
 
         let horizontal_movement = Vec2::new(width * level_builder_context.x_direction, 0.0);
         let vertical_movement = Vec2::new(0.0, height);
@@ -45,45 +96,148 @@ impl LevelBuilderOperation for ElevatorOperation {
         platform.apply_operation(LineSegment::new(cursor_start, cursor_start + horizontal_movement))
             .create_in_simulation(level_builder_context.sim);
 
-        let first_particle_offset = platform.particles[0].pos - elevator_start;
+        let particle_offsets = platform.particles.iter().map(|p| p.pos - elevator_start).collect();
+
+        let mut stops = vec![elevator_start];
+        stops.extend(params.stops.iter().filter(|h| **h > 0.0 && **h < height).map(|h| elevator_start + Vec2::new(0.0, *h)));
+        stops.push(elevator_end);
 
         level_builder_context.entity_system.elevator_entity_system.push(ElevatorEntity {
-            start: elevator_start,
-            end: elevator_end,
-            speed: rng.random_range(1.0..=2.0),
-            state: ElevatorState::MovingUp,
+            stops,
+            half_width: width * 0.5,
+            speed: params.speed,
+            state: ElevatorState::AtStop { stop: 0, timer: params.call_delay },
             pos: elevator_start,
+            heading: 1,
+            requested: None,
+            car_on_plate: false,
             particle_indicies: platform.particle_handles,
-            first_particle_offset,
-            wait_time: 2.0,
-            wait_timer: 0.0,
-            particle_radius: level_builder_context.particle_template.radius,
+            particle_offsets,
+            call_delay: params.call_delay,
+            wait_time: params.wait_time,
+            idle_timer: 0.0,
         });
 
         level_builder_context.cursor = cursor_end;
     }
 }
 
+/// How far above the platform the car still counts as standing on it
+const PLATE_HEIGHT: f32 = 1.5;
+
+/// The car has to be nearly still to press the plate, rolling across it doesn't call the elevator
+const PLATE_MAX_SPEED: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum ElevatorState {
-    MovingUp,
-    AtTopWaiting,
-    MovingDown,
-    AtBottomWaiting,
+    /// Stopped at one of the stops. timer counts down the call delay while the car is on the platform
+    AtStop { stop: usize, timer: f32 },
+    MovingTo { stop: usize },
 }
 
+#[derive(Clone)]
 pub struct ElevatorEntity {
-    start: Vec2,
-    end: Vec2,
+    stops: Vec<Vec2>, // bottom to top
+    half_width: f32,
     speed: f32,
     state: ElevatorState,
     pos: Vec2,
+    heading: i32, // 1 going up, -1 going down
+    requested: Option<i32>, // direction the player picked with the up/down keys
+    car_on_plate: bool,
     particle_indicies: Vec<usize>,
-    first_particle_offset: Vec2,
+    particle_offsets: Vec<Vec2>, // where each platform particle sits relative to pos
+    call_delay: f32,
     wait_time: f32,
-    wait_timer: f32,
-    particle_radius: f32,
+    idle_timer: f32, // how long it has been at a stop with nobody on it
+}
+
+impl ElevatorEntity {
+    /// Middle of the platform
+    pub fn pos(&self) -> Vec2 {
+        self.pos
+    }
+
+    pub fn is_moving(&self) -> bool {
+        matches!(self.state, ElevatorState::MovingTo { .. })
+    }
+
+    fn velocity(&self) -> Vec2 {
+        match self.state {
+            ElevatorState::AtStop { .. } => Vec2::zero(),
+            ElevatorState::MovingTo { stop } => Vec2::new(0.0, self.speed * (self.stops[stop].y - self.pos.y).signum()),
+        }
+    }
+
+    /// The platform doubles as a pressure plate
+    fn is_on_plate(&self, pos: Vec2, vel: Vec2) -> bool {
+        let above = pos.y - self.pos.y;
+        (pos.x - self.pos.x).abs() <= self.half_width && (0.0..=PLATE_HEIGHT).contains(&above) && (vel - self.velocity()).magnitude() <= PLATE_MAX_SPEED
+    }
+
+    /// Where to go from a stop once the call delay is up, if anywhere
+    fn next_stop(&mut self, stop: usize, waited: f32) -> Option<usize> {
+        let top = self.stops.len() - 1;
+        let direction = match self.requested.take() {
+            Some(direction) => direction,
+            None if stop == 0 => 1,
+            // the car drives off at the top, pressing down takes it back
+            None if stop == top => return None,
+            // nobody picked a direction at a middle stop, carry on the way we were going
+            None if waited >= self.wait_time => self.heading,
+            None => return None,
+        };
+
+        let next = stop as i32 + direction;
+        if next < 0 || next > top as i32 {
+            return None;
+        }
+        self.heading = direction;
+        Some(next as usize)
+    }
+
+    fn update(&mut self, time_delta: f32) {
+        match self.state {
+            ElevatorState::AtStop { stop, timer } => {
+                if !self.car_on_plate {
+                    self.requested = None;
+                    self.state = ElevatorState::AtStop { stop, timer: self.call_delay };
+
+                    // head back down so the elevator is there for the car next time
+                    self.idle_timer += time_delta;
+                    if stop != 0 && self.idle_timer >= self.wait_time {
+                        self.heading = -1;
+                        self.state = ElevatorState::MovingTo { stop: 0 };
+                    }
+                    return;
+                }
+
+                self.idle_timer = 0.0;
+                let timer = timer - time_delta;
+                self.state = ElevatorState::AtStop { stop, timer };
+                if timer <= 0.0 {
+                    if let Some(next) = self.next_stop(stop, -timer) {
+                        self.state = ElevatorState::MovingTo { stop: next };
+                    }
+                }
+            }
+
+            ElevatorState::MovingTo { stop } => {
+                let target = self.stops[stop];
+                let step = self.speed * time_delta;
+                if (target.y - self.pos.y).abs() <= step {
+                    self.pos = target;
+                    self.idle_timer = 0.0;
+                    self.state = ElevatorState::AtStop { stop, timer: self.call_delay };
+                } else {
+                    self.pos += Vec2::new(0.0, step * (target.y - self.pos.y).signum());
+                }
+            }
+        }
+    }
 }
 
+#[derive(Clone)]
 pub struct ElevatorEntitySystem(pub Vec<ElevatorEntity>);
 
 impl ElevatorEntitySystem {
@@ -95,44 +249,46 @@ impl ElevatorEntitySystem {
         self.0.push(c);
     }
 
-    pub fn update(&mut self, context: &mut UpdateContext) {
+    /// Which elevator (if any) moves this particle
+    pub fn elevator_for_particle(&self, particle_index: usize) -> Option<usize> {
+        self.0.iter().position(|e| e.particle_indicies.contains(&particle_index))
+    }
+
+    pub fn mirror_x(&mut self) {
         for e in self.0.iter_mut() {
-            // todo: support horizontal movement too
-            match e.state {
-                ElevatorState::MovingUp => {
-                    if e.pos.y >= e.end.y {
-                        e.state = ElevatorState::AtTopWaiting;
-                        e.wait_timer = e.wait_time;
-                        e.pos = e.end;
-                    } else {
-                        e.pos += Vec2::new(0.0, e.speed) * context.time_delta;
-                    }
-                }
+            for stop in e.stops.iter_mut() {
+                stop.x = -stop.x;
+            }
+            e.pos.x = -e.pos.x;
+            for offset in e.particle_offsets.iter_mut() {
+                offset.x = -offset.x;
+            }
+        }
+    }
 
-                ElevatorState::AtTopWaiting => {
-                    e.wait_timer -= context.time_delta;
-                    if e.wait_timer <= 0.0 {
-                        e.state = ElevatorState::MovingDown;
-                    }
-                }
+    /// Up and down pick which way the elevator the car is standing on goes next
+    pub fn handle_key(&mut self, key: KeyCodeType, is_pressed: bool) {
+        let direction = match key {
+            KeyCodeType::ArrowUp => 1,
+            KeyCodeType::ArrowDown => -1,
+            _ => return,
+        };
+        if !is_pressed {
+            return;
+        }
 
-                ElevatorState::MovingDown => {
-                    if e.pos.y <= e.start.y {
-                        e.state = ElevatorState::AtBottomWaiting;
-                        e.wait_timer = e.wait_time;
-                        e.pos = e.start;
-                    } else {
-                        e.pos += Vec2::new(0.0, -e.speed) * context.time_delta;
-                    }
-                }
-                
-                ElevatorState::AtBottomWaiting => {
-                    e.wait_timer -= context.time_delta;
-                    if e.wait_timer <= 0.0 {
-                        e.state = ElevatorState::MovingUp;
-                    }
-                },
-            };
+        for e in self.0.iter_mut().filter(|e| e.car_on_plate) {
+            e.requested = Some(direction);
+        }
+    }
+
+    pub fn update(&mut self, context: &mut UpdateContext, car_entity_system: &CarEntitySystem) {
+        for e in self.0.iter_mut() {
+            // todo: support horizontal movement too
+            e.car_on_plate = car_entity_system.0.iter()
+                .filter(|car| !car.game_ended)
+                .any(|car| e.is_on_plate(car.get_camera_look_at_position(&context.sim.particles), car.velocity(&context.sim.particles)));
+            e.update(context.time_delta);
         }
     }
 
@@ -160,9 +316,9 @@ impl ElevatorEntitySystem {
         // }
 
         for e in self.0.iter_mut() {
-            for (idx, pi) in e.particle_indicies.iter().enumerate() {
+            for (pi, particle_offset) in e.particle_indicies.iter().zip(e.particle_offsets.iter()) {
                 let pos_guess = sim.particles[*pi].pos_guess;
-                let where_particle_pos_guess_should_be = e.pos + e.first_particle_offset + Vec2::new(e.particle_radius * 2.0 * (idx as f32), 0.0);
+                let where_particle_pos_guess_should_be = e.pos + *particle_offset;
 
                 let offset = where_particle_pos_guess_should_be - pos_guess;
 
@@ -1,13 +1,41 @@
-use crate::{core::math::{aabb2d::Aabb2d, unit_conversions::cm_to_m, vec2::Vec2}, game::{entity::entities::finish_entity::FinishEntity, level::{level_builder::LevelBuilderContext, level_builder_operation::LevelBuilderOperation}}, simulation::particles::shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}};
+use serde::{Deserialize, Serialize};
 
+use crate::{core::math::{aabb2d::Aabb2d, unit_conversions::cm_to_m, vec2::Vec2}, game::{entity::entities::finish_entity::FinishEntity, level::{level_builder::LevelBuilderContext, level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}}}, simulation::particles::shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FinishParams {
+    pub width: f32,
+    /// Height of the wall (and finish trigger) at the end of the level.
+    pub wall_height: f32,
+}
+
+impl LevelBuilderOperationParams for FinishParams {
+    fn random(_level_builder_context: &mut LevelBuilderContext) -> Self {
+        Self {
+            width: 3.0,
+            wall_height: 1.5,
+        }
+    }
+}
+
+#[derive(Default, Clone)]
 pub struct FinishOperation {
+    pub params: Option<FinishParams>,
 }
 
 impl LevelBuilderOperation for FinishOperation {
     fn type_name(&self) -> &str {"FinishOperation"}
 
     fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
-        Box::new(FinishOperation {})
+        Box::new(self.clone())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.params.as_ref()?).ok()
+    }
+
+    fn box_clone_with_params(&self, params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(Box::new(FinishOperation { params: Some(serde_json::from_value(params)?) }))
     }
 
     fn prepare(&self, level_builder_context: &mut LevelBuilderContext, level_builder_operations: &mut Vec<(f32, Box<dyn LevelBuilderOperation + Send + Sync>)>) {
@@ -29,7 +57,8 @@ impl LevelBuilderOperation for FinishOperation {
     }
 
     fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
-        let width = 3.0;
+        let params = level_builder_context.resolve_params(&self.params);
+        let width = params.width;
         let height = 0.0;
 
         // this must match what is in car_entity.rs
@@ -37,14 +66,14 @@ impl LevelBuilderOperation for FinishOperation {
 
         let cursor_start = level_builder_context.cursor;
         let cursor_end = cursor_start + Vec2::new(width * level_builder_context.x_direction, height);
-        let finish_start = cursor_end - Vec2::new(car_wheel_particle_radius * 2.0 * level_builder_context.x_direction, 0.0) + Vec2::new(0.0, 1.5);
+        let finish_start = cursor_end - Vec2::new(car_wheel_particle_radius * 2.0 * level_builder_context.x_direction, 0.0) + Vec2::new(0.0, params.wall_height);
 
         // Get the current length of particle_vec, as we are about to push on more particles
         // let particle_vec_start_index = level_builder_context.particle_vec.len();
         
         ShapeBuilder::from_particle_template(*level_builder_context.particle_template.clone().set_mass(0.0))
             .apply_operation(LineSegment::new(level_builder_context.cursor, cursor_end)) 
-            .apply_operation(LineSegment::new(cursor_end, cursor_end + Vec2::new(0.0, 1.5)))
+            .apply_operation(LineSegment::new(cursor_end, cursor_end + Vec2::new(0.0, params.wall_height)))
             .create_in_simulation(level_builder_context.sim); //.create_in_particle_vec(level_builder_context.particle_vec);
         
         // Now we have pushed in more particles that have proper particle indicies, we take a slice of the new particles
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{core::math::{vec2::Vec2, vec4::Vec4}, game::level::{level_builder::LevelBuilderContext, level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}}, simulation::particles::{particle::Particle, particle_vec::ParticleVec, sdf_data::SdfData, shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}}};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FloatingPlatformsParams {
+    /// Width of the pool floor.
+    pub width: f32,
+    /// How far the pool floor is below the cursor.
+    pub depth: f32,
+    /// Number of planks floating on the pool.
+    pub platforms: u32,
+    /// Mass of each particle in a plank, lighter planks sit higher in the water.
+    pub platform_particle_mass: f32,
+}
+
+impl LevelBuilderOperationParams for FloatingPlatformsParams {
+    fn random(level_builder_context: &mut LevelBuilderContext) -> Self {
+        let width = level_builder_context.random_quantized(4.0, 8.0, 1.0);
+        Self {
+            width,
+            depth: level_builder_context.random_quantized(1.0, 1.6, 0.2),
+            // sometimes there are gaps to skim across
+            platforms: (width / level_builder_context.random_quantized(2.0, 3.0, 0.5)).floor() as u32,
+            platform_particle_mass: level_builder_context.random_quantized(0.2, 0.4, 0.1),
+        }
+    }
+}
+
+/// A pool of still water with planks floating on it to drive across
+#[derive(Default, Clone)]
+pub struct FloatingPlatforms {
+    pub params: Option<FloatingPlatformsParams>,
+}
+
+impl LevelBuilderOperation for FloatingPlatforms {
+    fn type_name(&self) -> &str {"FloatingPlatforms"}
+
+    fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.params.as_ref()?).ok()
+    }
+
+    fn box_clone_with_params(&self, params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(Box::new(FloatingPlatforms { params: Some(serde_json::from_value(params)?) }))
+    }
+
+    fn default_spawn_chance(&self) -> f32 {
+        0.3
+    }
+
+    fn default_difficulty(&self) -> f32 {
+        2.5
+    }
+
+    fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
+        let params = level_builder_context.resolve_params(&self.params);
+        let x_direction = level_builder_context.x_direction;
+
+        let bank_width = params.depth;
+        let cursor_start = level_builder_context.cursor;
+        let floor_start = cursor_start + Vec2::new(bank_width * x_direction, -params.depth);
+        let floor_end = floor_start + Vec2::new(params.width * x_direction, 0.0);
+        let cursor_end = floor_end + Vec2::new(bank_width * x_direction, params.depth);
+
+        ShapeBuilder::from_particle_template(*level_builder_context.particle_template.clone().set_static(true))
+            .apply_operation(LineSegment::new(cursor_start, floor_start))
+            .apply_operation(LineSegment::new(floor_start, floor_end))
+            .apply_operation(LineSegment::new(floor_end, cursor_end))
+            .create_in_simulation(level_builder_context.sim);
+
+        let particle_radius = level_builder_context.particle_template.radius;
+        let water_radius = particle_radius * 0.85;
+        let water_level = floor_start.y + params.depth * 0.8;
+        let (floor_min_x, floor_max_x) = (floor_start.x.min(floor_end.x), floor_start.x.max(floor_end.x));
+        let mut water = ParticleVec::new();
+        let mut y = floor_start.y + particle_radius + water_radius;
+        while y < water_level {
+            let mut x = floor_min_x + water_radius;
+            while x < floor_max_x - water_radius {
+                water.push(*Particle::default().set_radius(water_radius).set_pos(Vec2::new(x, y)).set_mass(1.0).set_colour(Vec4::new(0.1, 0.4, 0.9, 1.0)));
+                x += water_radius * 2.0;
+            }
+            y += water_radius * 2.0;
+        }
+        level_builder_context.sim.create_fluid(&water, 1.75);
+
+        // two rows of particles held rigid, the sdf points out of the plank from each particle like the friction demo
+        let columns = 8;
+        let root2 = f32::sqrt(2.0);
+        let mut sdf_data = vec![];
+        for x in 0..columns {
+            let end = if x == 0 { -1.0 } else if x == columns - 1 { 1.0 } else { 0.0 };
+            for y in [-1.0, 1.0] {
+                let distance = if end == 0.0 { particle_radius } else { particle_radius * root2 };
+                sdf_data.push(SdfData::new(Vec2::new(end, y).normalize(), distance));
+            }
+        }
+
+        let plank_width = particle_radius * 2.0 * columns as f32;
+        let spacing = params.width / params.platforms.max(1) as f32;
+        for i in 0..params.platforms {
+            let centre_x = floor_min_x + spacing * (i as f32 + 0.5);
+            let mut plank = ParticleVec::new();
+            for x in 0..columns {
+                let x_pos = centre_x - plank_width * 0.5 + particle_radius * (2 * x + 1) as f32;
+                for y in 0..2 {
+                    let y_pos = water_level + particle_radius * (2 * y) as f32;
+                    plank.push(*Particle::default().set_radius(particle_radius).set_pos(Vec2::new(x_pos, y_pos)).set_mass(params.platform_particle_mass).set_colour(Vec4::new(0.6, 0.4, 0.2, 1.0)));
+                }
+            }
+            level_builder_context.sim.create_rigid_body(&mut plank, &sdf_data);
+        }
+
+        level_builder_context.cursor = cursor_end;
+    }
+}
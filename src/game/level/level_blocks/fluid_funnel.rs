@@ -1,21 +1,56 @@
 
-use crate::{game::level::{level_builder::LevelBuilderContext, level_builder_operation::LevelBuilderOperation}, core::math::{unit_conversions::g_to_kg, vec2::Vec2, vec4::Vec4}, simulation::particles::{particle::Particle, shape_builder::{line_segment::LineSegment, rectangle::Rectangle, shape_builder::ShapeBuilder}}};
+use serde::{Deserialize, Serialize};
 
+use crate::{game::level::{level_builder::LevelBuilderContext, level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}}, core::math::{unit_conversions::g_to_kg, vec2::Vec2, vec4::Vec4}, simulation::particles::{particle::Particle, shape_builder::{line_segment::LineSegment, rectangle::Rectangle, shape_builder::ShapeBuilder}}};
 
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FluidFunnelParams {
+    /// Height of the funnel mouth above the cursor.
+    pub funnel_height: f32,
+    /// Size of the block of liquid dropped into the funnel, in particles.
+    pub liquid_columns: u32,
+    pub liquid_rows: u32,
+}
+
+impl LevelBuilderOperationParams for FluidFunnelParams {
+    fn random(_level_builder_context: &mut LevelBuilderContext) -> Self {
+        Self {
+            funnel_height: 3.0,
+            liquid_columns: 20,
+            liquid_rows: 15,
+        }
+    }
+}
+
+#[derive(Default, Clone)]
 pub struct FluidFunnel {
+    pub params: Option<FluidFunnelParams>,
 }
 
 impl LevelBuilderOperation for FluidFunnel {
     fn type_name(&self) -> &str {"FluidFunnel"}
 
     fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
-        Box::new(FluidFunnel {})
+        Box::new(self.clone())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.params.as_ref()?).ok()
+    }
+
+    fn box_clone_with_params(&self, params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(Box::new(FluidFunnel { params: Some(serde_json::from_value(params)?) }))
     }
 
     fn default_spawn_chance(&self) -> f32 {
         0.5
     }
 
+    fn default_difficulty(&self) -> f32 {
+        2.0
+    }
+
     fn prepare(&self, level_builder_context: &mut LevelBuilderContext, level_builder_operations: &mut Vec<(f32, Box<dyn LevelBuilderOperation + Send + Sync>)>) {
         // once the level changes direction, do not spawn a fluid funnel as it takes up to much vertical space and can interfere with stuff above us
         if level_builder_context.x_direction_changed {
@@ -28,7 +63,7 @@ impl LevelBuilderOperation for FluidFunnel {
     }
 
     fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
-        let _rng = &mut level_builder_context.rng;
+        let params = level_builder_context.resolve_params(&self.params);
 
         // let width = 0.0;
         // let height = rng.random_range(-2.0..=-0.5);
@@ -42,7 +77,7 @@ impl LevelBuilderOperation for FluidFunnel {
         let liquid_particle_radius = particle_radius * 0.85;
         let liquid_particle_mass = g_to_kg(20.0);
 
-        let funnel_height = 3.0;
+        let funnel_height = params.funnel_height;
         
         let funnel_particle_radius = liquid_particle_radius * 0.75;
 
@@ -53,8 +88,8 @@ impl LevelBuilderOperation for FluidFunnel {
 
         let origin = cursor_start;
 
-        let width = liquid_particle_radius * 2.0 * 20.0;
-        let height = liquid_particle_radius * 2.0 * 15.0;
+        let width = liquid_particle_radius * 2.0 * params.liquid_columns as f32;
+        let height = liquid_particle_radius * 2.0 * params.liquid_rows as f32;
 
         // Liquid
         ShapeBuilder::from_particle_template(Particle::default().set_mass(liquid_particle_mass).set_radius(liquid_particle_radius).set_colour(Vec4::new(0.0, 0.0, 1.0, 1.0)).clone())
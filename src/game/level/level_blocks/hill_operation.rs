@@ -1,4 +1,4 @@
-use rand::Rng;
+use serde::{Deserialize, Serialize};
 use crate::{
     core::math::{
         vec2::Vec2, 
@@ -6,27 +6,60 @@ use crate::{
     }, 
     game::level::{
         level_builder::LevelBuilderContext, 
-        level_builder_operation::LevelBuilderOperation
+        level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}
     }, 
     simulation::particles::
         shape_builder::shape_builder::ShapeBuilder
     
 };
 
-pub struct HillOperation;
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HillParams {
+    pub total_width: f32,
+    /// Height of the end of each segment relative to the start of the hill. One entry per segment.
+    pub segment_heights: Vec<f32>,
+}
+
+impl LevelBuilderOperationParams for HillParams {
+    fn random(level_builder_context: &mut LevelBuilderContext) -> Self {
+        let total_width = level_builder_context.random_quantized(5.0, 15.0, 1.0);
+        let num_segments = level_builder_context.random_choice(&[2, 3, 4]);
+        // Randomize height, but keep it somewhat relative to start y to avoid huge cliffs
+        // Maybe trend upwards or downwards or oscillate
+        let segment_heights = (0..num_segments).map(|_| level_builder_context.random_quantized(-2.5, 2.5, 0.5)).collect();
+
+        Self {
+            total_width,
+            segment_heights,
+        }
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct HillOperation {
+    pub params: Option<HillParams>,
+}
 
 impl LevelBuilderOperation for HillOperation {
     fn type_name(&self) -> &str { "HillOperation" }
 
     fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
-        Box::new(HillOperation)
+        Box::new(self.clone())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.params.as_ref()?).ok()
+    }
+
+    fn box_clone_with_params(&self, params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(Box::new(HillOperation { params: Some(serde_json::from_value(params)?) }))
     }
 
     fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
-        let rng = &mut level_builder_context.rng;
+        let params = level_builder_context.resolve_params(&self.params);
 
-        let total_width = rng.random_range(5.0..=15.0);
-        let num_segments = rng.random_range(2..=4);
+        let total_width = params.total_width;
+        let num_segments = params.segment_heights.len().max(1);
         let segment_width = total_width / num_segments as f32;
         let direction = level_builder_context.x_direction;
 
@@ -38,9 +71,7 @@ impl LevelBuilderOperation for HillOperation {
 
         for i in 0..num_segments {
             let next_x = current_pos.x + segment_width * direction;
-            // Randomize height, but keep it somewhat relative to start y to avoid huge cliffs
-            // Maybe trend upwards or downwards or oscillate
-            let height_variation = rng.random_range(-2.5..=2.5);
+            let height_variation = params.segment_heights.get(i).copied().unwrap_or(0.0);
             let next_y = start_pos.y + height_variation; 
             
             // If it's the last segment, maybe bring it back to a standard level? 
@@ -8,4 +8,16 @@ pub mod straight_level_block;
 pub mod finish_operation;
 pub mod elevator;
 pub mod water_balloon_drop;
-pub mod hill_operation;
\ No newline at end of file
+pub mod hill_operation;
+pub mod river_crossing;
+pub mod floating_platforms;
+pub mod sand_hill;
+pub mod swing_gap;
+pub mod wall_ride;
+pub mod trampoline;
+pub mod moving_platform;
+pub mod breakable_bridge;
+pub mod wind_zone;
+pub mod mud_pit;
+pub mod teleporter;
+pub mod boost_pad;
@@ -0,0 +1,157 @@
+use std::f32::consts::TAU;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{core::math::{deterministic::MathMode, vec2::Vec2, vec4::Vec4}, game::{entity::entity_system::UpdateContext, level::{level_builder::LevelBuilderContext, level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}}}, simulation::{constraints::kinematic_constraint::KinematicConstraint, particles::shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}}};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MovingPlatformParams {
+    pub width: f32,
+    /// How far the platform travels from one end to the other.
+    pub distance: f32,
+    /// Up and down between the ground and a ledge, otherwise back and forth across a pit.
+    pub vertical: bool,
+    /// Time for a trip there and back (seconds).
+    pub period: f32,
+}
+
+impl LevelBuilderOperationParams for MovingPlatformParams {
+    fn random(level_builder_context: &mut LevelBuilderContext) -> Self {
+        let vertical = level_builder_context.random_choice(&[false, true]);
+        let distance = if vertical {
+            level_builder_context.random_quantized(1.0, 3.0, 0.5)
+        } else {
+            level_builder_context.random_quantized(2.0, 5.0, 1.0)
+        };
+        Self {
+            width: 2.0,
+            distance,
+            vertical,
+            period: level_builder_context.random_quantized(3.0, 6.0, 1.0),
+        }
+    }
+}
+
+/// A platform that keeps moving on its own, across a pit or up to a ledge. Time the jump on and ride it over.
+/// It isn't picked for dailies unless level_gen.toml gives it a spawn chance, so existing dailies stay as they are.
+#[derive(Default, Clone)]
+pub struct MovingPlatformOperation {
+    pub params: Option<MovingPlatformParams>,
+}
+
+impl LevelBuilderOperation for MovingPlatformOperation {
+    fn type_name(&self) -> &str {"MovingPlatformOperation"}
+
+    fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.params.as_ref()?).ok()
+    }
+
+    fn box_clone_with_params(&self, params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(Box::new(MovingPlatformOperation { params: Some(serde_json::from_value(params)?) }))
+    }
+
+    fn default_spawn_chance(&self) -> f32 {
+        0.0
+    }
+
+    fn default_difficulty(&self) -> f32 {
+        2.0
+    }
+
+    fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
+        let params = level_builder_context.resolve_params(&self.params);
+        let x_direction = level_builder_context.x_direction;
+
+        let across = Vec2::new(params.width * x_direction, 0.0);
+        let cursor_start = level_builder_context.cursor;
+        let (travel, cursor_end) = if params.vertical {
+            (Vec2::new(0.0, params.distance), cursor_start + across * 2.0 + Vec2::new(0.0, params.distance))
+        } else {
+            let travel = Vec2::new(params.distance * x_direction, 0.0);
+            (travel, cursor_start + across * 2.0 + travel)
+        };
+
+        let diameter = Vec2::new(0.0, level_builder_context.particle_template.radius * 2.0);
+        let mut ground = ShapeBuilder::from_particle_template(*level_builder_context.particle_template.clone().set_static(true));
+        if params.vertical {
+            // a floor under the platform's lowest point, the ledge at the top and the wall under it
+            let ledge_start = cursor_start + across + travel;
+            ground.apply_operation(LineSegment::new(cursor_start - diameter, cursor_start + across - diameter))
+                .apply_operation(LineSegment::new(cursor_start + across, ledge_start))
+                .apply_operation(LineSegment::new(ledge_start, cursor_end));
+        } else {
+            // the pit the platform crosses, with the far side to drive off onto
+            let pit_depth = Vec2::new(0.0, -3.0);
+            let far_side = cursor_end - across;
+            ground.apply_operation(LineSegment::new(cursor_start - diameter, cursor_start + pit_depth))
+                .apply_operation(LineSegment::new(cursor_start + pit_depth, far_side + pit_depth))
+                .apply_operation(LineSegment::new(far_side + pit_depth, far_side - diameter))
+                .apply_operation(LineSegment::new(far_side, cursor_end));
+        }
+        ground.create_in_simulation(level_builder_context.sim);
+
+        let origin = cursor_start + across * 0.5;
+        let mut platform = ShapeBuilder::from_particle_template(*level_builder_context.particle_template.clone().set_static(true).set_colour(Vec4::GREEN));
+        platform.apply_operation(LineSegment::new(cursor_start, cursor_start + across))
+            .create_in_simulation(level_builder_context.sim);
+        let constraint = level_builder_context.sim.add_kinematic_constraint(KinematicConstraint::from_particles(platform.particle_handles, origin, &level_builder_context.sim.particles));
+
+        level_builder_context.entity_system.moving_platform_entity_system.push(MovingPlatformEntity {
+            constraint,
+            origin,
+            travel,
+            period: params.period,
+        });
+
+        level_builder_context.cursor = cursor_end;
+    }
+}
+
+/// Moves a platform's kinematic constraint back and forth between origin and origin + travel
+#[derive(Clone)]
+pub struct MovingPlatformEntity {
+    constraint: usize, // in Simulation::kinematic_constraints
+    origin: Vec2, // middle of the platform at the start of its travel
+    travel: Vec2,
+    period: f32,
+}
+
+impl MovingPlatformEntity {
+    /// Middle of the platform at the given time, easing in and out at each end so the car isn't jolted off
+    pub fn pos_at(&self, total_time: f32, math_mode: MathMode) -> Vec2 {
+        let along = 0.5 - 0.5 * math_mode.cos(total_time / self.period * TAU);
+        self.origin + self.travel * along
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct MovingPlatformEntitySystem(pub Vec<MovingPlatformEntity>);
+
+impl MovingPlatformEntitySystem {
+    pub fn new() -> Self {
+        Self(vec![])
+    }
+
+    pub fn push(&mut self, e: MovingPlatformEntity) {
+        self.0.push(e);
+    }
+
+    pub fn mirror_x(&mut self) {
+        for e in self.0.iter_mut() {
+            e.origin.x = -e.origin.x;
+            e.travel.x = -e.travel.x;
+        }
+    }
+
+    /// Put each platform where it should be next step
+    pub fn update(&mut self, context: &mut UpdateContext) {
+        for e in self.0.iter() {
+            let pos = e.pos_at(context.total_time, context.sim.math_mode);
+            context.sim.kinematic_constraints.0[e.constraint].target = pos;
+        }
+    }
+}
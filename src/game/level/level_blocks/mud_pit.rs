@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{core::math::{aabb2d::Aabb2d, vec2::Vec2, vec4::Vec4}, game::level::{level_builder::LevelBuilderContext, level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}}, simulation::particles::shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}};
+
+const MUD_HEIGHT: f32 = 0.6; // how far above the bed the mud reaches, about up to the car's axles
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MudPitParams {
+    /// Width of the bed of the pit, not counting the banks.
+    pub width: f32,
+    /// How far the bed is below the cursor.
+    pub depth: f32,
+    /// Fraction of its speed the car loses each second in the mud.
+    pub drag: f32,
+}
+
+impl LevelBuilderOperationParams for MudPitParams {
+    fn random(level_builder_context: &mut LevelBuilderContext) -> Self {
+        Self {
+            width: level_builder_context.random_quantized(3.0, 6.0, 1.0),
+            depth: level_builder_context.random_quantized(0.3, 0.6, 0.1),
+            drag: level_builder_context.random_quantized(0.8, 1.6, 0.2),
+        }
+    }
+}
+
+/// A shallow pit of mud that drags at the car, so it has to hit it with speed or ease through without spinning the wheels.
+/// New blocks start with no spawn chance, set one in level_gen.toml to see it in generated levels.
+#[derive(Default, Clone)]
+pub struct MudPitOperation {
+    pub params: Option<MudPitParams>,
+}
+
+impl LevelBuilderOperation for MudPitOperation {
+    fn type_name(&self) -> &str {"MudPitOperation"}
+
+    fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.params.as_ref()?).ok()
+    }
+
+    fn box_clone_with_params(&self, params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(Box::new(MudPitOperation { params: Some(serde_json::from_value(params)?) }))
+    }
+
+    fn default_spawn_chance(&self) -> f32 {
+        0.0
+    }
+
+    fn default_difficulty(&self) -> f32 {
+        1.5
+    }
+
+    fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
+        let params = level_builder_context.resolve_params(&self.params);
+        let x_direction = level_builder_context.x_direction;
+
+        // gentle banks, the car is slow enough climbing out
+        let bank_width = params.depth * 2.0;
+        let cursor_start = level_builder_context.cursor;
+        let bed_start = cursor_start + Vec2::new(bank_width * x_direction, -params.depth);
+        let bed_end = bed_start + Vec2::new(params.width * x_direction, 0.0);
+        let cursor_end = bed_end + Vec2::new(bank_width * x_direction, params.depth);
+
+        let mud = Vec4::new(0.35, 0.25, 0.15, 1.0);
+        ShapeBuilder::from_particle_template(*level_builder_context.particle_template.clone().set_static(true))
+            .apply_operation(LineSegment::new(cursor_start, bed_start))
+            .apply_operation(LineSegment::new(bed_end, cursor_end))
+            .create_in_simulation(level_builder_context.sim);
+        ShapeBuilder::from_particle_template(*level_builder_context.particle_template.clone().set_static(true).set_colour(mud))
+            .apply_operation(LineSegment::new(bed_start, bed_end))
+            .create_in_simulation(level_builder_context.sim);
+
+        // the mud covers the banks below ground level too, so the car is still dragged while it climbs out
+        let region = Aabb2d {
+            min: Vec2::new(cursor_start.x.min(cursor_end.x), bed_start.y),
+            max: Vec2::new(cursor_start.x.max(cursor_end.x), bed_start.y + MUD_HEIGHT),
+        };
+        level_builder_context.sim.add_drag_region(region, params.drag);
+
+        level_builder_context.cursor = cursor_end;
+    }
+}
@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{core::math::{aabb2d::Aabb2d, vec2::Vec2, vec4::Vec4}, game::level::{level_builder::LevelBuilderContext, level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}}, simulation::particles::{flow_zone::FlowZone, particle::Particle, particle_vec::ParticleVec, shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}}};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiverCrossingParams {
+    /// Width of the river bed, not counting the banks.
+    pub width: f32,
+    /// How far the river bed is below the cursor.
+    pub depth: f32,
+    /// Speed of the current in m/s, flowing back against the direction of travel.
+    pub current: f32,
+}
+
+impl LevelBuilderOperationParams for RiverCrossingParams {
+    fn random(level_builder_context: &mut LevelBuilderContext) -> Self {
+        Self {
+            width: level_builder_context.random_quantized(4.0, 7.0, 1.0),
+            depth: level_builder_context.random_quantized(0.8, 1.4, 0.2),
+            current: level_builder_context.random_quantized(1.0, 3.0, 0.5),
+        }
+    }
+}
+
+/// A dip in the ground filled with water flowing back towards the start, so the car has to fight the current to cross
+#[derive(Default, Clone)]
+pub struct RiverCrossing {
+    pub params: Option<RiverCrossingParams>,
+}
+
+impl LevelBuilderOperation for RiverCrossing {
+    fn type_name(&self) -> &str {"RiverCrossing"}
+
+    fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.params.as_ref()?).ok()
+    }
+
+    fn box_clone_with_params(&self, params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(Box::new(RiverCrossing { params: Some(serde_json::from_value(params)?) }))
+    }
+
+    fn default_spawn_chance(&self) -> f32 {
+        0.3
+    }
+
+    fn default_difficulty(&self) -> f32 {
+        2.0
+    }
+
+    fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
+        let params = level_builder_context.resolve_params(&self.params);
+        let x_direction = level_builder_context.x_direction;
+
+        // banks gentle enough to drive out of
+        let bank_width = params.depth * 1.5;
+        let cursor_start = level_builder_context.cursor;
+        let bed_start = cursor_start + Vec2::new(bank_width * x_direction, -params.depth);
+        let bed_end = bed_start + Vec2::new(params.width * x_direction, 0.0);
+        let cursor_end = bed_end + Vec2::new(bank_width * x_direction, params.depth);
+
+        ShapeBuilder::from_particle_template(*level_builder_context.particle_template.clone().set_static(true))
+            .apply_operation(LineSegment::new(cursor_start, bed_start))
+            .apply_operation(LineSegment::new(bed_start, bed_end))
+            .apply_operation(LineSegment::new(bed_end, cursor_end))
+            .create_in_simulation(level_builder_context.sim);
+
+        // fill the bed most of the way up to the banks
+        let particle_radius = level_builder_context.particle_template.radius;
+        let water_radius = particle_radius * 0.85;
+        let water_depth = params.depth * 0.75;
+        let (bed_min_x, bed_max_x) = (bed_start.x.min(bed_end.x), bed_start.x.max(bed_end.x));
+        let mut water = ParticleVec::new();
+        let mut y = bed_start.y + particle_radius + water_radius;
+        while y < bed_start.y + water_depth {
+            let mut x = bed_min_x + water_radius;
+            while x < bed_max_x - water_radius {
+                water.push(*Particle::default().set_radius(water_radius).set_pos(Vec2::new(x, y)).set_mass(1.0).set_colour(Vec4::new(0.1, 0.4, 0.9, 1.0)));
+                x += water_radius * 2.0;
+            }
+            y += water_radius * 2.0;
+        }
+        level_builder_context.sim.create_fluid(&water, 1.75);
+
+        // the current covers the banks too so the car is still pushed back while climbing out
+        let (min_x, max_x) = (cursor_start.x.min(cursor_end.x), cursor_start.x.max(cursor_end.x));
+        let zone = Aabb2d { min: Vec2::new(min_x, bed_start.y), max: Vec2::new(max_x, cursor_start.y.max(cursor_end.y) + 0.5) };
+        level_builder_context.sim.flow_zones.push(FlowZone::new(zone, Vec2::new(-params.current * x_direction, 0.0)));
+
+        level_builder_context.cursor = cursor_end;
+    }
+}
+
@@ -1,22 +1,50 @@
-use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::{core::math::{vec2::Vec2, vec4::Vec4}, game::level::{level_builder::LevelBuilderContext, level_builder_operation::LevelBuilderOperation}, simulation::{constraints::distance_constraint::DistanceConstraint, particles::shape_builder::{rectangle::Rectangle, rectangle_stick_grid::RectangleStickGrid, shape_builder::ShapeBuilder}}};
+use crate::{core::math::{vec2::Vec2, vec4::Vec4}, game::level::{level_builder::LevelBuilderContext, level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}}, simulation::{constraints::distance_constraint::DistanceConstraint, particles::shape_builder::{rectangle::Rectangle, rectangle_stick_grid::RectangleStickGrid, shape_builder::ShapeBuilder}}};
 
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaggyBridgeParams {
+    /// Length of the bridge between the two fixed ends.
+    pub width: f32,
+}
+
+impl LevelBuilderOperationParams for SaggyBridgeParams {
+    fn random(level_builder_context: &mut LevelBuilderContext) -> Self {
+        Self {
+            width: level_builder_context.random_quantized(2.0, 5.0, 0.5),
+        }
+    }
+}
+
+#[derive(Default, Clone)]
 pub struct SaggyBridgeOperation {
+    pub params: Option<SaggyBridgeParams>,
 }
 
 impl LevelBuilderOperation for SaggyBridgeOperation {
     fn type_name(&self) -> &str {"SaggyBridgeOperation"}
 
     fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
-        Box::new(SaggyBridgeOperation {})
+        Box::new(self.clone())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.params.as_ref()?).ok()
+    }
+
+    fn box_clone_with_params(&self, params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(Box::new(SaggyBridgeOperation { params: Some(serde_json::from_value(params)?) }))
+    }
+
+    fn default_difficulty(&self) -> f32 {
+        1.5
     }
 
     fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
-        let rng = &mut level_builder_context.rng;
+        let params = level_builder_context.resolve_params(&self.params);
 
-        let width = rng.random_range(2.0..=5.0);
+        let width = params.width;
         let height = 0.0;
 
         let rect_height = level_builder_context.particle_template.radius * 8.0; //4.0; 4 = 2 particle thickness
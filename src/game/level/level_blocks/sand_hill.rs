@@ -0,0 +1,95 @@
+use std::f32::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{core::math::{vec2::Vec2, vec4::Vec4}, game::level::{level_builder::LevelBuilderContext, level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}}, simulation::particles::{particle::Particle, particle_vec::ParticleVec, shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}}};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandHillParams {
+    pub width: f32,
+    /// Height of the sand at the middle of the hill.
+    pub height: f32,
+}
+
+impl LevelBuilderOperationParams for SandHillParams {
+    fn random(level_builder_context: &mut LevelBuilderContext) -> Self {
+        Self {
+            width: level_builder_context.random_quantized(4.0, 8.0, 1.0),
+            height: level_builder_context.random_quantized(0.4, 1.0, 0.2),
+        }
+    }
+}
+
+/// A mound of loose sand on flat ground. The wheels dig ruts into it, which stay until the level is reset
+#[derive(Default, Clone)]
+pub struct SandHill {
+    pub params: Option<SandHillParams>,
+}
+
+impl LevelBuilderOperation for SandHill {
+    fn type_name(&self) -> &str {"SandHill"}
+
+    fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.params.as_ref()?).ok()
+    }
+
+    fn box_clone_with_params(&self, params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(Box::new(SandHill { params: Some(serde_json::from_value(params)?) }))
+    }
+
+    fn default_spawn_chance(&self) -> f32 {
+        0.3
+    }
+
+    fn default_difficulty(&self) -> f32 {
+        1.5
+    }
+
+    fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
+        let params = level_builder_context.resolve_params(&self.params);
+        let x_direction = level_builder_context.x_direction;
+
+        let cursor_start = level_builder_context.cursor;
+        let cursor_end = cursor_start + Vec2::new(params.width * x_direction, 0.0);
+
+        ShapeBuilder::from_particle_template(*level_builder_context.particle_template.clone().set_static(true))
+            .apply_operation(LineSegment::new(cursor_start, cursor_end))
+            .create_in_simulation(level_builder_context.sim);
+
+        // stack the grains in offset rows under a half sine, so they start out packed and settle quickly
+        let particle_radius = level_builder_context.particle_template.radius;
+        let grain_radius = particle_radius * 0.8;
+        let row_height = grain_radius * f32::sqrt(3.0);
+        let min_x = cursor_start.x.min(cursor_end.x);
+        let mut sand = ParticleVec::new();
+        let mut row = 0;
+        loop {
+            let y = cursor_start.y + particle_radius + grain_radius + row as f32 * row_height;
+            let offset = if row % 2 == 0 { grain_radius } else { grain_radius * 2.0 };
+            let mut placed = false;
+            let mut x = min_x + offset;
+            while x < min_x + params.width - grain_radius {
+                let surface = cursor_start.y + params.height * (PI * (x - min_x) / params.width).sin();
+                if y < surface {
+                    let mut grain = *Particle::default().set_radius(grain_radius).set_pos(Vec2::new(x, y)).set_mass(0.5).set_colour(Vec4::new(0.85, 0.7, 0.45, 1.0));
+                    grain.s_friction = 0.35;
+                    grain.k_friction = 0.3;
+                    sand.push(grain);
+                    placed = true;
+                }
+                x += grain_radius * 2.0;
+            }
+            if !placed {
+                break;
+            }
+            row += 1;
+        }
+        level_builder_context.sim.create_granular(&sand);
+
+        level_builder_context.cursor = cursor_end;
+    }
+}
@@ -1,14 +1,42 @@
-use crate::{core::math::vec2::Vec2, game::level::{level_builder::LevelBuilderContext, level_builder_operation::LevelBuilderOperation}, simulation::particles::shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}};
+use serde::{Deserialize, Serialize};
 
+use crate::{core::math::vec2::Vec2, game::level::{level_builder::LevelBuilderContext, level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}}, simulation::particles::shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}};
 
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpawnParams {
+    pub width: f32,
+    /// Height of the wall behind the car.
+    pub wall_height: f32,
+}
+
+impl LevelBuilderOperationParams for SpawnParams {
+    fn random(_level_builder_context: &mut LevelBuilderContext) -> Self {
+        Self {
+            width: 4.0,
+            wall_height: 1.5,
+        }
+    }
+}
+
+#[derive(Default, Clone)]
 pub struct SpawnOperation {
+    pub params: Option<SpawnParams>,
 }
 
 impl LevelBuilderOperation for SpawnOperation {
     fn type_name(&self) -> &str {"SpawnOperation"}
 
     fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
-        Box::new(SpawnOperation {})
+        Box::new(self.clone())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.params.as_ref()?).ok()
+    }
+
+    fn box_clone_with_params(&self, params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(Box::new(SpawnOperation { params: Some(serde_json::from_value(params)?) }))
     }
 
     fn prepare(&self, level_builder_context: &mut LevelBuilderContext, level_builder_operations: &mut Vec<(f32, Box<dyn LevelBuilderOperation + Send + Sync>)>) {
@@ -30,7 +58,8 @@ impl LevelBuilderOperation for SpawnOperation {
     }
 
     fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
-        let width = 4.0;
+        let params = level_builder_context.resolve_params(&self.params);
+        let width = params.width;
         let height = 0.0;
 
         let cursor_start = level_builder_context.cursor - Vec2::new(level_builder_context.x_direction * (width * 0.5), 0.0);
@@ -40,7 +69,7 @@ impl LevelBuilderOperation for SpawnOperation {
         //let particle_vec_start_index = level_builder_context.particle_vec.len();
         
         ShapeBuilder::from_particle_template(level_builder_context.particle_template.set_mass(0.0).clone())
-            .apply_operation(LineSegment::new(cursor_start + Vec2::new(0.0, 1.5), cursor_start))
+            .apply_operation(LineSegment::new(cursor_start + Vec2::new(0.0, params.wall_height), cursor_start))
             .apply_operation(LineSegment::new(cursor_start, cursor_end)) 
             //.create_in_particle_vec(level_builder_context.particle_vec);
             .create_in_simulation(level_builder_context.sim);
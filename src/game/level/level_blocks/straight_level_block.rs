@@ -1,15 +1,45 @@
-use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::{core::math::vec2::Vec2, game::level::{level_builder::LevelBuilderContext, level_builder_operation::LevelBuilderOperation}, simulation::particles::shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}};
+use crate::{core::math::vec2::Vec2, game::level::{level_builder::LevelBuilderContext, level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}}, simulation::particles::shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}};
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StraightLevelBlockParams {
+    pub width: f32,
+    /// Height change from the start to the end of the block.
+    pub height: f32,
+}
+
+impl LevelBuilderOperationParams for StraightLevelBlockParams {
+    fn random(level_builder_context: &mut LevelBuilderContext) -> Self {
+        Self {
+            width: level_builder_context.random_quantized(5.0, 10.0, 1.0),
+            height: level_builder_context.random_quantized(-1.5, 1.5, 0.5),
+        }
+    }
+}
+
+#[derive(Default, Clone)]
 pub struct StraightLevelBlock {
+    pub params: Option<StraightLevelBlockParams>,
 }
 
 impl LevelBuilderOperation for StraightLevelBlock {
     fn type_name(&self) -> &str {"StraightLevelBlock"}
 
     fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
-        Box::new(StraightLevelBlock {})
+        Box::new(self.clone())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.params.as_ref()?).ok()
+    }
+
+    fn box_clone_with_params(&self, params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(Box::new(StraightLevelBlock { params: Some(serde_json::from_value(params)?) }))
+    }
+
+    fn default_difficulty(&self) -> f32 {
+        0.5
     }
 
     fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
@@ -30,8 +60,9 @@ impl LevelBuilderOperation for StraightLevelBlock {
         //     1.0,
         // );
 
-        let width = level_builder_context.rng.random_range(5.0..=10.0);
-        let height = level_builder_context.rng.random_range(-1.5..=1.5);
+        let params = level_builder_context.resolve_params(&self.params);
+        let width = params.width;
+        let height = params.height;
  
  /* 
         // todo: https://github.com/bevyengine/bevy/discussions/15280
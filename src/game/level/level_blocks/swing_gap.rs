@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{core::math::{vec2::Vec2, vec4::Vec4}, game::{entity::entities::anchor_entity::AnchorEntity, level::{level_builder::LevelBuilderContext, level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}}}, simulation::particles::shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwingGapParams {
+    pub gap_width: f32,
+    /// How far the pit floor is below the cursor.
+    pub depth: f32,
+    /// Height of the anchor above the cursor, over the middle of the gap.
+    pub anchor_height: f32,
+}
+
+impl LevelBuilderOperationParams for SwingGapParams {
+    fn random(level_builder_context: &mut LevelBuilderContext) -> Self {
+        Self {
+            gap_width: level_builder_context.random_quantized(3.0, 5.0, 0.5),
+            depth: level_builder_context.random_quantized(2.0, 3.0, 0.5),
+            anchor_height: level_builder_context.random_quantized(3.0, 4.0, 0.5),
+        }
+    }
+}
+
+/// A pit with an anchor hanging over it to winch across on. Drop in and there's a steep climb out the far side
+#[derive(Default, Clone)]
+pub struct SwingGap {
+    pub params: Option<SwingGapParams>,
+}
+
+impl LevelBuilderOperation for SwingGap {
+    fn type_name(&self) -> &str {"SwingGap"}
+
+    fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.params.as_ref()?).ok()
+    }
+
+    fn box_clone_with_params(&self, params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(Box::new(SwingGap { params: Some(serde_json::from_value(params)?) }))
+    }
+
+    fn default_spawn_chance(&self) -> f32 {
+        0.2
+    }
+
+    fn default_difficulty(&self) -> f32 {
+        3.0
+    }
+
+    fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
+        let params = level_builder_context.resolve_params(&self.params);
+        let x_direction = level_builder_context.x_direction;
+
+        let cursor_start = level_builder_context.cursor;
+        let floor_start = cursor_start + Vec2::new(0.0, -params.depth);
+        let floor_end = floor_start + Vec2::new(params.gap_width * x_direction, 0.0);
+        // the far wall leans back a little so it can be climbed with enough run up
+        let cursor_end = floor_end + Vec2::new(params.depth * 0.5 * x_direction, params.depth);
+
+        ShapeBuilder::from_particle_template(*level_builder_context.particle_template.clone().set_static(true))
+            .apply_operation(LineSegment::new(cursor_start, floor_start))
+            .apply_operation(LineSegment::new(floor_start, floor_end))
+            .apply_operation(LineSegment::new(floor_end, cursor_end))
+            .create_in_simulation(level_builder_context.sim);
+
+        let anchor_pos = cursor_start + Vec2::new(params.gap_width * 0.5 * x_direction, params.anchor_height);
+        let mut anchor = ShapeBuilder::from_particle_template(*level_builder_context.particle_template.clone().set_static(true).set_colour(Vec4::new(1.0, 0.5, 0.0, 1.0)));
+        anchor.add_particle_at_position(anchor_pos).create_in_simulation(level_builder_context.sim);
+        // in reach from the edge of the pit
+        let range = (anchor_pos - cursor_start).magnitude() + 1.0;
+        level_builder_context.entity_system.anchor_entity_system.push(AnchorEntity::new(anchor.particle_handles[0], range));
+
+        level_builder_context.cursor = cursor_end;
+    }
+}
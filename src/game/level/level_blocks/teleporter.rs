@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{core::math::{aabb2d::Aabb2d, vec2::Vec2}, game::{entity::{entities::car_entity::CarEntitySystem, entity_system::UpdateContext}, level::{level_builder::LevelBuilderContext, level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}}}, simulation::particles::{shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}, simulation::Relocation}};
+
+const PORTAL_WIDTH: f32 = 1.5;
+const PORTAL_HEIGHT: f32 = 2.0;
+const WALL_HEIGHT: f32 = 3.0; // behind the entrance, so the only way on is through it
+const LANDING_WIDTH: f32 = 12.0; // long enough to come down on after a rising exit
+const RISING: f32 = std::f32::consts::FRAC_1_SQRT_2; // cos and sin of 45 degrees
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PortalExit {
+    Forward, // out the way the car went in
+    Rising, // turned 45 degrees up, like coming off a ramp
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TeleporterParams {
+    /// Flat ground before the entrance.
+    pub run_up: f32,
+    /// How far on from the entrance the exit is.
+    pub distance: f32,
+    /// Height of the landing the exit is over, above the cursor.
+    pub height: f32,
+    pub exit: PortalExit,
+}
+
+impl LevelBuilderOperationParams for TeleporterParams {
+    fn random(level_builder_context: &mut LevelBuilderContext) -> Self {
+        Self {
+            run_up: level_builder_context.random_quantized(3.0, 6.0, 1.0),
+            distance: level_builder_context.random_quantized(4.0, 10.0, 2.0),
+            height: level_builder_context.random_quantized(-2.0, 4.0, 1.0),
+            exit: level_builder_context.random_choice(&[PortalExit::Forward, PortalExit::Rising]),
+        }
+    }
+}
+
+/// A pair of portals: drive into the entrance in front of a wall and come out of the exit over a landing further on,
+/// at the same speed. Not in the generator's mix until level_gen.toml gives it a spawn chance.
+#[derive(Default, Clone)]
+pub struct TeleporterOperation {
+    pub params: Option<TeleporterParams>,
+}
+
+impl LevelBuilderOperation for TeleporterOperation {
+    fn type_name(&self) -> &str {"TeleporterOperation"}
+
+    fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.params.as_ref()?).ok()
+    }
+
+    fn box_clone_with_params(&self, params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(Box::new(TeleporterOperation { params: Some(serde_json::from_value(params)?) }))
+    }
+
+    fn default_spawn_chance(&self) -> f32 {
+        0.0
+    }
+
+    fn default_difficulty(&self) -> f32 {
+        1.0
+    }
+
+    fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
+        let params = level_builder_context.resolve_params(&self.params);
+        let x_direction = level_builder_context.x_direction;
+
+        let cursor_start = level_builder_context.cursor;
+        let entrance_start = cursor_start + Vec2::new(params.run_up * x_direction, 0.0);
+        let wall_base = entrance_start + Vec2::new(PORTAL_WIDTH * x_direction, 0.0);
+        let landing_start = wall_base + Vec2::new(params.distance * x_direction, params.height);
+        let cursor_end = landing_start + Vec2::new(LANDING_WIDTH * x_direction, 0.0);
+
+        ShapeBuilder::from_particle_template(*level_builder_context.particle_template.clone().set_static(true))
+            .apply_operation(LineSegment::new(cursor_start, wall_base))
+            .apply_operation(LineSegment::new(wall_base, wall_base + Vec2::new(0.0, WALL_HEIGHT)))
+            .apply_operation(LineSegment::new(landing_start, cursor_end))
+            .create_in_simulation(level_builder_context.sim);
+
+        let portal = |corner: Vec2, lift: f32| {
+            let (x1, x2) = (corner.x, corner.x + PORTAL_WIDTH * x_direction);
+            Aabb2d { min: Vec2::new(x1.min(x2), corner.y + lift), max: Vec2::new(x1.max(x2), corner.y + lift + PORTAL_HEIGHT) }
+        };
+        let exit_direction = match params.exit {
+            PortalExit::Forward => Vec2::new(x_direction, 0.0),
+            PortalExit::Rising => Vec2::new(RISING * x_direction, RISING),
+        };
+        level_builder_context.entity_system.teleporter_entity_system.push(TeleporterEntity {
+            entrance: portal(entrance_start, 0.0),
+            exit: portal(landing_start, 0.5), // up off the landing so the car drops out rather than into the ground
+            entrance_direction: Vec2::new(x_direction, 0.0),
+            exit_direction,
+        });
+
+        level_builder_context.cursor = cursor_end;
+    }
+}
+
+/// Sends a car that gets its centre into the entrance out of the exit. The exit only goes one way.
+#[derive(Clone)]
+pub struct TeleporterEntity {
+    pub entrance: Aabb2d,
+    pub exit: Aabb2d,
+    pub entrance_direction: Vec2, // unit length, which way through the portal is
+    pub exit_direction: Vec2,
+}
+
+impl TeleporterEntity {
+    /// The car's move from where it is in the entrance to the middle of the exit, turned by the angle between their directions
+    pub fn relocation(&self, particles: Vec<usize>, from: Vec2) -> Relocation {
+        let (a, b) = (self.entrance_direction, self.exit_direction);
+        Relocation {
+            particles,
+            from,
+            to: (self.exit.min + self.exit.max) * 0.5,
+            rotation: Vec2::new(a.dot(b), a.x * b.y - a.y * b.x),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct TeleporterEntitySystem(pub Vec<TeleporterEntity>);
+
+impl TeleporterEntitySystem {
+    pub fn new() -> Self {
+        Self(vec![])
+    }
+
+    pub fn push(&mut self, e: TeleporterEntity) {
+        self.0.push(e);
+    }
+
+    pub fn mirror_x(&mut self) {
+        for e in self.0.iter_mut() {
+            e.entrance = e.entrance.mirror_x();
+            e.exit = e.exit.mirror_x();
+            e.entrance_direction.x = -e.entrance_direction.x;
+            e.exit_direction.x = -e.exit_direction.x;
+        }
+    }
+
+    /// Send any car in an entrance through to its exit, the simulation moves it at the end of the next step
+    pub fn update(&mut self, context: &mut UpdateContext, car_entity_system: &CarEntitySystem) {
+        for car in car_entity_system.0.iter() {
+            let car_pos = car.get_camera_look_at_position(&context.sim.particles);
+            if let Some(e) = self.0.iter().find(|e| e.entrance.contains_point(car_pos)) {
+                context.sim.queue_relocation(e.relocation(car.particle_handles().collect(), car_pos));
+            }
+        }
+    }
+}
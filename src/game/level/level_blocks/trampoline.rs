@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{core::math::vec2::Vec2, game::level::{level_builder::LevelBuilderContext, level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}}, simulation::particles::{shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}, surface_material::MaterialTable}};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrampolineParams {
+    pub width: f32,
+    /// How far the bouncy floor is below the cursor.
+    pub depth: f32,
+    /// How much lower the far side is than the near side. Bounces lose a little height, so this has to be enough to get out.
+    pub drop: f32,
+}
+
+impl LevelBuilderOperationParams for TrampolineParams {
+    fn random(level_builder_context: &mut LevelBuilderContext) -> Self {
+        let depth = level_builder_context.random_quantized(1.0, 2.0, 0.5);
+        Self {
+            width: level_builder_context.random_quantized(3.0, 5.0, 1.0),
+            depth,
+            drop: depth * 0.5,
+        }
+    }
+}
+
+/// A pit with a bouncy floor. Drop in and bounce out onto the lower far side
+#[derive(Default, Clone)]
+pub struct Trampoline {
+    pub params: Option<TrampolineParams>,
+}
+
+impl LevelBuilderOperation for Trampoline {
+    fn type_name(&self) -> &str {"Trampoline"}
+
+    fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.params.as_ref()?).ok()
+    }
+
+    fn box_clone_with_params(&self, params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(Box::new(Trampoline { params: Some(serde_json::from_value(params)?) }))
+    }
+
+    fn default_spawn_chance(&self) -> f32 {
+        0.3
+    }
+
+    fn default_difficulty(&self) -> f32 {
+        1.5
+    }
+
+    fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
+        let params = level_builder_context.resolve_params(&self.params);
+        let x_direction = level_builder_context.x_direction;
+
+        let cursor_start = level_builder_context.cursor;
+        let floor_start = cursor_start + Vec2::new(0.0, -params.depth);
+        let floor_end = floor_start + Vec2::new(params.width * x_direction, 0.0);
+        let cursor_end = cursor_start + Vec2::new(params.width * x_direction, -params.drop);
+
+        ShapeBuilder::from_particle_template(*level_builder_context.particle_template.clone().set_static(true))
+            .apply_operation(LineSegment::new(cursor_start, floor_start))
+            .apply_operation(LineSegment::new(floor_end, cursor_end))
+            .create_in_simulation(level_builder_context.sim);
+
+        let bouncy_colour = level_builder_context.sim.materials.get(MaterialTable::BOUNCY).colour;
+        ShapeBuilder::from_particle_template(*level_builder_context.particle_template.clone().set_static(true).set_material(MaterialTable::BOUNCY).set_colour(bouncy_colour))
+            .apply_operation(LineSegment::new(floor_start, floor_end))
+            .create_in_simulation(level_builder_context.sim);
+
+        level_builder_context.cursor = cursor_end;
+    }
+}
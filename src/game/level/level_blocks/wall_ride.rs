@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{core::math::vec2::Vec2, game::level::{level_builder::LevelBuilderContext, level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}}, simulation::particles::{shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}, surface_material::MaterialTable}};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WallRideParams {
+    /// Flat ground before the wall to build up speed on.
+    pub run_up: f32,
+    pub wall_height: f32,
+}
+
+impl LevelBuilderOperationParams for WallRideParams {
+    fn random(level_builder_context: &mut LevelBuilderContext) -> Self {
+        Self {
+            run_up: level_builder_context.random_quantized(2.0, 4.0, 1.0),
+            wall_height: level_builder_context.random_quantized(1.5, 3.0, 0.5),
+        }
+    }
+}
+
+/// A sheer step up with a sticky face, so the car can drive straight up it and over the lip
+#[derive(Default, Clone)]
+pub struct WallRide {
+    pub params: Option<WallRideParams>,
+}
+
+impl LevelBuilderOperation for WallRide {
+    fn type_name(&self) -> &str {"WallRide"}
+
+    fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.params.as_ref()?).ok()
+    }
+
+    fn box_clone_with_params(&self, params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(Box::new(WallRide { params: Some(serde_json::from_value(params)?) }))
+    }
+
+    fn default_spawn_chance(&self) -> f32 {
+        0.3
+    }
+
+    fn default_difficulty(&self) -> f32 {
+        2.0
+    }
+
+    fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
+        let params = level_builder_context.resolve_params(&self.params);
+        let x_direction = level_builder_context.x_direction;
+
+        let cursor_start = level_builder_context.cursor;
+        let wall_bottom = cursor_start + Vec2::new(params.run_up * x_direction, 0.0);
+        let wall_top = wall_bottom + Vec2::new(0.0, params.wall_height);
+        let cursor_end = wall_top + Vec2::new(2.0 * x_direction, 0.0);
+
+        ShapeBuilder::from_particle_template(*level_builder_context.particle_template.clone().set_static(true))
+            .apply_operation(LineSegment::new(cursor_start, wall_bottom))
+            .apply_operation(LineSegment::new(wall_top, cursor_end))
+            .create_in_simulation(level_builder_context.sim);
+
+        let sticky_colour = level_builder_context.sim.materials.get(MaterialTable::STICKY).colour;
+        ShapeBuilder::from_particle_template(*level_builder_context.particle_template.clone().set_static(true).set_material(MaterialTable::STICKY).set_colour(sticky_colour))
+            .apply_operation(LineSegment::new(wall_bottom, wall_top))
+            .create_in_simulation(level_builder_context.sim);
+
+        level_builder_context.cursor = cursor_end;
+    }
+}
@@ -1,14 +1,43 @@
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     core::math::{vec2::Vec2, vec4::Vec4},
-    game::level::{level_builder::LevelBuilderContext, level_builder_operation::LevelBuilderOperation},
+    game::level::{level_builder::LevelBuilderContext, level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}},
     simulation::
         particles::{particle::Particle, particle_vec::ParticleVec, shape_builder::{adjacent_sticks::AdjacentSticks, circle::{Circle, SpaceDistribution}, shape_builder::ShapeBuilder}, simulation::Simulation}
     ,
 };
 
-pub struct WaterBalloonDrop {}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaterBalloonDropParams {
+    pub balloon_radius: f32,
+    /// Where the balloon is spawned relative to the cursor.
+    pub x_offset: f32,
+    pub y_offset: f32,
+}
+
+impl LevelBuilderOperationParams for WaterBalloonDropParams {
+    fn random(level_builder_context: &mut LevelBuilderContext) -> Self {
+        // Randomize balloon size (smaller than original)
+        let balloon_radius = level_builder_context.random_quantized(0.5, 1.0, 0.25);
+        
+        // Randomize position offset from cursor
+        let x_offset = level_builder_context.random_quantized(-1.0, 1.0, 0.5);
+        let y_offset = 2.0;
+
+        Self {
+            balloon_radius,
+            x_offset,
+            y_offset,
+        }
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct WaterBalloonDrop {
+    pub params: Option<WaterBalloonDropParams>,
+}
 
 impl WaterBalloonDrop {
     /// Creates a filled water balloon at the specified position
@@ -72,25 +101,32 @@ impl LevelBuilderOperation for WaterBalloonDrop {
     }
 
     fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
-        Box::new(WaterBalloonDrop {})
+        Box::new(self.clone())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.params.as_ref()?).ok()
+    }
+
+    fn box_clone_with_params(&self, params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(Box::new(WaterBalloonDrop { params: Some(serde_json::from_value(params)?) }))
     }
 
     fn default_spawn_chance(&self) -> f32 {
         0.3
     }
 
+    fn default_difficulty(&self) -> f32 {
+        1.5
+    }
+
     fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
+        let params = level_builder_context.resolve_params(&self.params);
         let rng = &mut level_builder_context.rng;
         let particle_rad = level_builder_context.particle_template.radius;
+        let balloon_radius = params.balloon_radius;
         
-        // Randomize balloon size (smaller than original)
-        let balloon_radius = rng.random_range(0.5..=1.0);
-        
-        // Randomize position offset from cursor
-        let x_offset = rng.random_range(-1.0..=1.0);
-        let y_offset = rng.random_range(2.0..=2.0);
-        
-        let balloon_center = level_builder_context.cursor + Vec2::new(x_offset, y_offset);
+        let balloon_center = level_builder_context.cursor + Vec2::new(params.x_offset, params.y_offset);
         
         // Create first balloon
         Self::create_water_balloon(
@@ -0,0 +1,148 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{core::math::{aabb2d::Aabb2d, vec2::Vec2}, game::{entity::entity_system::UpdateContext, level::{level_builder::LevelBuilderContext, level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}}}, simulation::particles::shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}};
+
+const STREAK_AREA: f32 = 1.5; // square metres of zone per streak
+const STREAK_SPEED: f32 = 1.5; // streaks move this many times faster than the wind's acceleration, so a stronger wind looks stronger
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum WindDirection {
+    Headwind, // against the direction of travel
+    Tailwind,
+    Updraft,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindZoneParams {
+    pub width: f32,
+    /// How high above the ground the wind reaches.
+    pub height: f32,
+    pub direction: WindDirection,
+    /// Acceleration of anything in the zone (m/s^2).
+    pub strength: f32,
+}
+
+impl LevelBuilderOperationParams for WindZoneParams {
+    fn random(level_builder_context: &mut LevelBuilderContext) -> Self {
+        Self {
+            width: level_builder_context.random_quantized(4.0, 8.0, 1.0),
+            height: 3.0,
+            direction: level_builder_context.random_choice(&[WindDirection::Headwind, WindDirection::Tailwind, WindDirection::Updraft]),
+            strength: level_builder_context.random_quantized(3.0, 7.0, 1.0),
+        }
+    }
+}
+
+/// A stretch of flat ground with a gust blowing over it, pushing the car back, along or up.
+/// Turned off for the generator by default, give it a spawn chance in level_gen.toml to try it.
+#[derive(Default, Clone)]
+pub struct WindZoneOperation {
+    pub params: Option<WindZoneParams>,
+}
+
+impl LevelBuilderOperation for WindZoneOperation {
+    fn type_name(&self) -> &str {"WindZoneOperation"}
+
+    fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn params(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self.params.as_ref()?).ok()
+    }
+
+    fn box_clone_with_params(&self, params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(Box::new(WindZoneOperation { params: Some(serde_json::from_value(params)?) }))
+    }
+
+    fn default_spawn_chance(&self) -> f32 {
+        0.0
+    }
+
+    fn default_difficulty(&self) -> f32 {
+        1.5
+    }
+
+    fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
+        let params = level_builder_context.resolve_params(&self.params);
+        let x_direction = level_builder_context.x_direction;
+
+        let cursor_start = level_builder_context.cursor;
+        let cursor_end = cursor_start + Vec2::new(params.width * x_direction, 0.0);
+        ShapeBuilder::from_particle_template(*level_builder_context.particle_template.clone().set_static(true))
+            .apply_operation(LineSegment::new(cursor_start, cursor_end))
+            .create_in_simulation(level_builder_context.sim);
+
+        let region = Aabb2d {
+            min: Vec2::new(cursor_start.x.min(cursor_end.x), cursor_start.y),
+            max: Vec2::new(cursor_start.x.max(cursor_end.x), cursor_start.y + params.height),
+        };
+        let vector = match params.direction {
+            WindDirection::Headwind => Vec2::new(-params.strength * x_direction, 0.0),
+            WindDirection::Tailwind => Vec2::new(params.strength * x_direction, 0.0),
+            WindDirection::Updraft => Vec2::new(0.0, params.strength),
+        };
+        level_builder_context.sim.add_force_field(region.clone(), vector);
+
+        let num_streaks = ((params.width * params.height) / STREAK_AREA).ceil() as usize;
+        let streaks = (0..num_streaks).map(|_| {
+            let (r1, r2): (f32, f32) = (level_builder_context.rng.random(), level_builder_context.rng.random());
+            Vec2::new(region.min.x + (region.max.x - region.min.x) * r1, region.min.y + (region.max.y - region.min.y) * r2)
+        }).collect();
+        level_builder_context.entity_system.wind_zone_entity_system.push(WindZoneEntity { region, vector, streaks });
+
+        level_builder_context.cursor = cursor_end;
+    }
+}
+
+/// The streaks that show which way a wind zone blows. They're only for show, the force is a field in the simulation.
+#[derive(Clone)]
+pub struct WindZoneEntity {
+    pub region: Aabb2d,
+    pub vector: Vec2,
+    pub streaks: Vec<Vec2>, // where each streak is now, drifting with the wind and wrapping round the region
+}
+
+impl WindZoneEntity {
+    /// Which way the streaks are heading, unit length
+    pub fn direction(&self) -> Vec2 {
+        self.vector / self.vector.magnitude()
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct WindZoneEntitySystem(pub Vec<WindZoneEntity>);
+
+impl WindZoneEntitySystem {
+    pub fn new() -> Self {
+        Self(vec![])
+    }
+
+    pub fn push(&mut self, e: WindZoneEntity) {
+        self.0.push(e);
+    }
+
+    pub fn mirror_x(&mut self) {
+        for e in self.0.iter_mut() {
+            e.region = e.region.mirror_x();
+            e.vector.x = -e.vector.x;
+            for streak in e.streaks.iter_mut() {
+                streak.x = -streak.x;
+            }
+        }
+    }
+
+    /// Drift the streaks along with the wind
+    pub fn update(&mut self, context: &mut UpdateContext) {
+        for e in self.0.iter_mut() {
+            let step = e.vector * (STREAK_SPEED * context.time_delta);
+            let (min, max) = (e.region.min, e.region.max);
+            let wrap = |v: f32, min: f32, max: f32| if v < min { v + (max - min) } else if v > max { v - (max - min) } else { v };
+            for streak in e.streaks.iter_mut() {
+                *streak += step;
+                *streak = Vec2::new(wrap(streak.x, min.x, max.x), wrap(streak.y, min.y, max.y));
+            }
+        }
+    }
+}
@@ -1,16 +1,24 @@
 use rand_pcg::Pcg64;
 use rand::Rng;
+use chrono::{DateTime, Utc};
 
-use crate::{core::math::{random::Random, unit_conversions::cm_to_m, vec2::Vec2}, game::{entity::entity_system::EntitySystem, level::{level_blocks::{cliff_operation::CliffOperation, drop_direction_reverse::DropDirectionReverse, elevator::ElevatorOperation, finish_operation::FinishOperation, fluid_funnel::FluidFunnel, hill_operation::HillOperation, saggy_bridge_operation::SaggyBridgeOperation, spawn_operation::SpawnOperation, straight_level_block::StraightLevelBlock, water_balloon_drop::WaterBalloonDrop}, level_builder_operation::LevelBuilderOperation, level_builder_operation_registry::LevelBuilderOperationRegistry}}, simulation::particles::{particle::Particle, particle_vec::ParticleVec, simulation::Simulation}};
+use crate::{core::math::{aabb2d::Aabb2d, random::Random, unit_conversions::cm_to_m, vec2::Vec2}, game::{entity::{entities::{camera_entity::CameraEntity, checkpoint_entity::CheckpointEntity}, entity_system::EntitySystem}, level::{level_blocks::{boost_pad::BoostPadOperation, breakable_bridge::BreakableBridgeOperation, cliff_operation::CliffOperation, drop_direction_reverse::DropDirectionReverse, elevator::ElevatorOperation, finish_operation::FinishOperation, floating_platforms::FloatingPlatforms, fluid_funnel::FluidFunnel, hill_operation::HillOperation, moving_platform::MovingPlatformOperation, mud_pit::MudPitOperation, river_crossing::RiverCrossing, saggy_bridge_operation::SaggyBridgeOperation, sand_hill::SandHill, spawn_operation::SpawnOperation, straight_level_block::StraightLevelBlock, swing_gap::SwingGap, teleporter::TeleporterOperation, trampoline::Trampoline, wall_ride::WallRide, water_balloon_drop::WaterBalloonDrop, wind_zone::WindZoneOperation}, level_builder_operation::{LevelBuilderOperation, LevelBuilderOperationParams}, level_block_bounds::LevelBlockBounds, level_builder_operation_registry::LevelBuilderOperationRegistry, level_gen_config::LevelGenConfig, level_generation_info::{LevelDescriptor, LevelGenerationInfo, LevelOperationInfo}, level_smoothing::smooth_block_transitions}, plugins::plugin_host::plugins}, simulation::particles::{particle::Particle, particle_vec::ParticleVec, simulation::Simulation}};
+use crate::game::seed_policy::daily_seed;
 
 pub struct LevelBuilder {
     level_builder_operations_registry: LevelBuilderOperationRegistry,
+    pub config: LevelGenConfig,
+    pub mirrored: bool, // flip the level horizontally once generated, for mirror mode
+    pub headwind: f32, // m/s of wind against the direction of travel, negative for a tailwind
 }
 
 impl LevelBuilder {
     pub fn new(level_builder_operations_registry: LevelBuilderOperationRegistry) -> Self {
         Self {
             level_builder_operations_registry,
+            config: LevelGenConfig::default(),
+            mirrored: false,
+            headwind: 0.0,
         }
     }
 }
@@ -27,11 +35,16 @@ pub struct LevelBuilderContext<'a> {
     pub rng: &'a mut Pcg64,
     pub entity_system: &'a mut EntitySystem,
     pub sim: &'a mut Simulation,
+    pub resolved_params: Option<serde_json::Value>, // params used by the operation currently executing
+    pub block_bounds: Vec<LevelBlockBounds>, // one for each operation executed so far
+    pub rng_states: Vec<Pcg64>, // the generator as each operation executed so far had it once its params were picked
+    pub restore_rng: Option<Pcg64>, // put back once the current operation's params are picked, to build a described level exactly
 }
 
 impl<'a> LevelBuilderContext<'a> {
     pub fn new(entity_system: &'a mut EntitySystem, particle_vec: &'a mut ParticleVec, sim: &'a mut Simulation, rng: &'a mut Pcg64) -> Self {
         let particle_radius = cm_to_m(10.0); // was 4.0
+        sim.broadphase.tune(particle_radius); // most of a level is made of these
 
         Self {
             particle_vec, //particle_sim,
@@ -44,20 +57,125 @@ impl<'a> LevelBuilderContext<'a> {
             is_last: false,
             rng,
             entity_system,
-            sim
+            sim,
+            resolved_params: None,
+            block_bounds: vec![],
+            rng_states: vec![],
+            restore_rng: None,
         }
     }
+
+    /// Pick a random value between min and max (inclusive) that lands on a multiple of step from min.
+    /// eg. random_quantized(0.5, 2.0, 0.5) picks one of 0.5, 1.0, 1.5 or 2.0.
+    /// Fewer distinct values gives more readable, learnable level geometry than a continuous range.
+    pub fn random_quantized(&mut self, min: f32, max: f32, step: f32) -> f32 {
+        debug_assert!(step > 0.0);
+        let num_steps = ((max - min) / step).round().max(0.0) as i32;
+        min + self.rng.random_range(0..=num_steps) as f32 * step
+    }
+
+    /// Pick one of the given values at random.
+    pub fn random_choice<T: Copy>(&mut self, choices: &[T]) -> T {
+        debug_assert!(!choices.is_empty());
+        choices[self.rng.random_range(0..choices.len())]
+    }
+
+    /// Use the explicitly set parameters if we have them, otherwise pick random ones.
+    /// The resolved params are remembered so the level can be described and reproduced later, along with the
+    /// generator for anything else the operation draws from it.
+    pub fn resolve_params<P: LevelBuilderOperationParams>(&mut self, params: &Option<P>) -> P {
+        let params = match params {
+            Some(params) => params.clone(),
+            None => P::random(self),
+        };
+        self.resolved_params = serde_json::to_value(&params).ok();
+        if let Some(rng) = self.restore_rng.take() {
+            *self.rng = rng;
+        }
+        if let Some(rng_state) = self.rng_states.last_mut() {
+            *rng_state = self.rng.clone();
+        }
+        params
+    }
 }
 
 impl LevelBuilder {
-    pub fn generate_level_based_on_date(&mut self, entity_system: &mut EntitySystem, particle_vec: &mut ParticleVec, sim: &mut Simulation) {
+    /// The blocks this builder can place
+    pub fn registry(&self) -> &LevelBuilderOperationRegistry {
+        &self.level_builder_operations_registry
+    }
+
+    pub fn generate_level_based_on_date(&mut self, entity_system: &mut EntitySystem, particle_vec: &mut ParticleVec, sim: &mut Simulation) -> LevelGenerationInfo {
         // set a random seed used for level generation based on todays date. Each day we get a new map to try
-        let mut rng = Random::seed_from_beginning_of_day(); //seed_from_beginning_of_week(); //car_scene.rng;
-        
-        let mut level_builder_context = LevelBuilderContext::new(entity_system, particle_vec, sim, &mut rng);
-        self.generate(&mut level_builder_context, 10); //10); //10);
+        self.generate_level_for_day(Utc::now(), entity_system, particle_vec, sim)
+    }
+
+    /// The daily level for the day the time is in, eg. to replay an old daily.
+    /// A local level_gen.toml is ignored here, everyone has to get the same daily to share its board.
+    pub fn generate_level_for_day(&mut self, day: DateTime<Utc>, entity_system: &mut EntitySystem, particle_vec: &mut ParticleVec, sim: &mut Simulation) -> LevelGenerationInfo {
+        let local_config = std::mem::take(&mut self.config);
+        let level_info = self.generate_level_for_day_with_config(day, entity_system, particle_vec, sim);
+        self.config = local_config;
+        level_info
+    }
+
+    /// The day's level as this builder's config would build it, eg. to see how a config changes a daily.
+    /// This isn't the shared daily unless the config is the default.
+    pub fn generate_level_for_day_with_config(&mut self, day: DateTime<Utc>, entity_system: &mut EntitySystem, particle_vec: &mut ParticleVec, sim: &mut Simulation) -> LevelGenerationInfo {
+        let mut rng = Random::seed_from_day(day); //seed_from_beginning_of_week(); //car_scene.rng;
+        self.generate_level_from_rng(daily_seed(day), &mut rng, entity_system, particle_vec, sim)
+    }
+
+    /// A level from any seed, eg. a custom seed picked instead of the daily
+    pub fn generate_level_for_seed(&mut self, seed: &str, entity_system: &mut EntitySystem, particle_vec: &mut ParticleVec, sim: &mut Simulation) -> LevelGenerationInfo {
+        let mut rng = Random::seed_from_str(seed);
+        self.generate_level_from_rng(seed.to_owned(), &mut rng, entity_system, particle_vec, sim)
+    }
 
-        // todo: we should push the seed and # level blocks into the event system
+    fn generate_level_from_rng(&mut self, seed: String, rng: &mut Pcg64, entity_system: &mut EntitySystem, particle_vec: &mut ParticleVec, sim: &mut Simulation) -> LevelGenerationInfo {
+        let num_blocks = self.config.num_blocks();
+
+        let mut level_builder_context = LevelBuilderContext::new(entity_system, particle_vec, sim, rng);
+        self.generate(&mut level_builder_context, num_blocks); //10); //10);
+
+        self.finish_level(seed, num_blocks, &mut level_builder_context)
+    }
+
+    /// Apply the passes that run over the whole level once the blocks are built, and describe the result
+    fn finish_level(&self, seed: String, num_blocks: i32, level_builder_context: &mut LevelBuilderContext) -> LevelGenerationInfo {
+        smooth_block_transitions(level_builder_context);
+        level_builder_context.sim.force_fields.extend(plugins().force_fields.iter().cloned());
+
+        let operations: Vec<LevelOperationInfo> = level_builder_context.operations.iter()
+            .map(|op| LevelOperationInfo { type_name: op.type_name().to_owned(), params: op.params() })
+            .collect();
+
+        // the level runs left to right until it's mirrored, which flips the wind with it
+        if self.headwind != 0.0 {
+            level_builder_context.sim.aerodynamics.ambient_wind = Some(Vec2::new(-self.headwind, 0.0));
+        }
+
+        // mirroring is a transform pass over the output so the same seed gives the same blocks, just flipped
+        if self.mirrored {
+            level_builder_context.sim.mirror_x();
+            level_builder_context.entity_system.mirror_x();
+        }
+
+        let level_hash = LevelGenerationInfo::compute_level_hash(&operations, level_builder_context.sim);
+        let block_aabbs = level_builder_context.block_bounds.iter()
+            .map(|bounds| if self.mirrored { bounds.aabb.mirror_x() } else { bounds.aabb.clone() })
+            .collect();
+
+        LevelGenerationInfo {
+            seed,
+            num_blocks,
+            operations,
+            level_hash,
+            mirrored: self.mirrored,
+            headwind: self.headwind,
+            block_aabbs,
+            rng_states: level_builder_context.rng_states.clone(),
+        }
     }
 
     pub fn generate(&mut self, level_builder_context: &mut LevelBuilderContext, num_blocks: i32) -> &mut Self {
@@ -73,7 +191,7 @@ impl LevelBuilder {
             // 1. Create a pair of "spawn change" and a operation.
             let mut spawn_chance_operations = vec![];
             for op in self.level_builder_operations_registry.iter() {
-                spawn_chance_operations.push((op.as_ref().default_spawn_chance(), op.as_ref().box_clone()))
+                spawn_chance_operations.push((self.config.spawn_chance(op.as_ref()), op.as_ref().box_clone()))
             }
 
             // 2. Give each operation a chance to mutate "spawn_chance_operations".
@@ -97,8 +215,7 @@ impl LevelBuilder {
                 spawn_value -= chance;
                 if spawn_value <= 0.0 {
                     // pick this item!
-                    level_builder_context.operations.push(operation.box_clone());
-                    operation.execute(level_builder_context);
+                    Self::execute_operation(level_builder_context, operation.as_ref(), bi, num_blocks);
                     break;
                 }
             }
@@ -109,6 +226,85 @@ impl LevelBuilder {
 
         self
     }
+
+    /// Build a level from a list of operations recorded earlier, eg. from a level pack.
+    /// Operations without params are randomised from the seed.
+    pub fn generate_from_operations(&mut self, seed: &str, operations: &[LevelOperationInfo], entity_system: &mut EntitySystem, particle_vec: &mut ParticleVec, sim: &mut Simulation) -> Result<LevelGenerationInfo, String> {
+        let operations: Vec<&LevelOperationInfo> = operations.iter().collect();
+        self.build_operations(seed, &operations, &[], entity_system, particle_vec, sim)
+    }
+
+    /// Build the level a descriptor describes, mirrored and with wind if it was. Each block carries on from the
+    /// generator state it was first built with, so the level comes out exactly the same.
+    pub fn build_from_descriptor(&mut self, descriptor: &LevelDescriptor, entity_system: &mut EntitySystem, particle_vec: &mut ParticleVec, sim: &mut Simulation) -> Result<LevelGenerationInfo, String> {
+        self.mirrored = descriptor.mirrored;
+        self.headwind = descriptor.headwind;
+        let operations: Vec<&LevelOperationInfo> = descriptor.blocks.iter().map(|block| &block.operation).collect();
+        let rng_states: Vec<Pcg64> = descriptor.blocks.iter().map(|block| block.rng.clone()).collect();
+        self.build_operations(&descriptor.seed, &operations, &rng_states, entity_system, particle_vec, sim)
+    }
+
+    /// Run the operations in order, restoring the generator for each one that has a state to restore
+    fn build_operations(&mut self, seed: &str, operations: &[&LevelOperationInfo], rng_states: &[Pcg64], entity_system: &mut EntitySystem, particle_vec: &mut ParticleVec, sim: &mut Simulation) -> Result<LevelGenerationInfo, String> {
+        // look everything up first so a pack using a block we don't have fails before building half a level
+        let mut resolved = vec![];
+        for info in operations {
+            let operation = self.level_builder_operations_registry.find(&info.type_name)
+                .ok_or_else(|| format!("unknown level block '{}'", info.type_name))?;
+            let operation = match &info.params {
+                Some(params) => operation.box_clone_with_params(params.clone())
+                    .map_err(|e| format!("bad params for level block '{}': {}", info.type_name, e))?,
+                None => operation.box_clone(),
+            };
+            resolved.push(operation);
+        }
+
+        let mut rng = Random::seed_from_str(seed);
+        let num_blocks = resolved.len() as i32;
+        let mut level_builder_context = LevelBuilderContext::new(entity_system, particle_vec, sim, &mut rng);
+        for (bi, operation) in resolved.iter().enumerate() {
+            let bi = bi as i32;
+            level_builder_context.is_first = bi == 0;
+            level_builder_context.is_last = bi == (num_blocks - 1);
+            level_builder_context.restore_rng = rng_states.get(bi as usize).cloned();
+            Self::execute_operation(&mut level_builder_context, operation.as_ref(), bi, num_blocks);
+        }
+
+        Ok(self.finish_level(seed.to_owned(), num_blocks, &mut level_builder_context))
+    }
+
+    /// Run an operation, keeping a copy with the params it used and where it ended up in the context, then place a checkpoint after it
+    fn execute_operation(level_builder_context: &mut LevelBuilderContext, operation: &dyn LevelBuilderOperation, bi: i32, num_blocks: i32) {
+        level_builder_context.operations.push(operation.box_clone());
+        level_builder_context.rng_states.push(level_builder_context.rng.clone());
+        level_builder_context.resolved_params = None;
+        let entry = level_builder_context.cursor;
+        let particles_start = level_builder_context.sim.particles.len();
+        let cameras_start = level_builder_context.entity_system.camera_entity_system.entities.len();
+        operation.execute(level_builder_context);
+        level_builder_context.restore_rng = None;
+
+        let particles = particles_start..level_builder_context.sim.particles.len();
+        let bounds = LevelBlockBounds::new(entry, level_builder_context.cursor, level_builder_context.x_direction, particles, level_builder_context.sim.particles.as_slice());
+        // blocks can place their own trackside cameras, the rest get one looking over the whole block
+        if level_builder_context.entity_system.camera_entity_system.entities.len() == cameras_start {
+            level_builder_context.entity_system.camera_entity_system.push(CameraEntity::for_block(&bounds.aabb));
+        }
+        level_builder_context.block_bounds.push(bounds);
+
+        // swap in a copy that has the params it actually used, so the operation list fully describes the level
+        if let Some(params) = level_builder_context.resolved_params.take() {
+            if let Ok(resolved) = operation.box_clone_with_params(params) {
+                *level_builder_context.operations.last_mut().unwrap() = resolved;
+            }
+        }
+
+        // put a checkpoint gate between blocks, skipping the ones right next to the spawn and finish
+        if bi > 0 && bi < num_blocks - 2 {
+            let aabb = Aabb2d::new(level_builder_context.cursor + Vec2::new(0.0, 1.0), Vec2::new(0.5, 2.0));
+            level_builder_context.entity_system.checkpoint_entity_system.push(CheckpointEntity::new(aabb));
+        }
+    }
 }
 
 
@@ -126,24 +322,131 @@ impl Default for LevelBuilder {
         // - a steep incline with toothed or flexible ground to give you grip to get up step. (or change the car tyres to be spiked)
         // - some cloth you need to drive under/tear through
         //
-        // instead of picking random numbers in a range, pick a random integer and just quantize the number eg. pick a number and then * by 0.5 to get 0.5, 1.0, 1.5, 2.0 as random distances. this might provide more "variety" through less choice.
-        // we should keep a bounding box for each operation applied to help work out if a block can be used instead of using x_direction_changed for example
-        registry.register(SpawnOperation {});
-        registry.register(FinishOperation {});
-        registry.register(HillOperation {});
-
-
-        registry.register(WaterBalloonDrop {});
-        registry.register(SaggyBridgeOperation {});
-        registry.register(StraightLevelBlock {});
-        registry.register(CliffOperation {});
-        registry.register(FluidFunnel {});
-        registry.register(DropDirectionReverse {});
-        registry.register(ElevatorOperation {});
+        // the bounds kept for each operation applied (block_bounds) could help work out if a block can be used instead of using x_direction_changed for example
+        registry.register(SpawnOperation::default());
+        registry.register(FinishOperation::default());
+        registry.register(HillOperation::default());
+
+
+        registry.register(WaterBalloonDrop::default());
+        registry.register(SaggyBridgeOperation::default());
+        registry.register(StraightLevelBlock::default());
+        registry.register(CliffOperation::default());
+        registry.register(FluidFunnel::default());
+        registry.register(DropDirectionReverse::default());
+        registry.register(ElevatorOperation::default());
+        registry.register(RiverCrossing::default());
+        registry.register(FloatingPlatforms::default());
+        registry.register(SandHill::default());
+        registry.register(SwingGap::default());
+        registry.register(WallRide::default());
+        registry.register(Trampoline::default());
+        registry.register(MovingPlatformOperation::default());
+        registry.register(BreakableBridgeOperation::default());
+        registry.register(WindZoneOperation::default());
+        registry.register(MudPitOperation::default());
+        registry.register(TeleporterOperation::default());
+        registry.register(BoostPadOperation::default());
         
 
         //registry.register(JellyCube {});
+
+        // community level blocks
+        for operation in plugins().operations.iter() {
+            registry.register(operation.clone());
+        }
  
-        LevelBuilder::new(registry)
+        let mut level_builder = LevelBuilder::new(registry);
+        level_builder.config = LevelGenConfig::load();
+        level_builder
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::{core::math::random::Random, game::{entity::entity_system::EntitySystem, level::{level_builder::{LevelBuilder, LevelBuilderContext}, level_gen_config::LevelGenConfig, level_generation_info::LevelDescriptor}}, simulation::particles::{particle_vec::ParticleVec, simulation::Simulation}};
+
+    #[test]
+    fn random_quantized_lands_on_steps() {
+        let mut entity_system = EntitySystem::new();
+        let mut particle_vec = ParticleVec::new();
+        let mut sim = Simulation::new(Random::seed_from_str("random quantized"));
+        let mut rng = Random::seed_from_str("random quantized");
+        let mut context = LevelBuilderContext::new(&mut entity_system, &mut particle_vec, &mut sim, &mut rng);
+
+        for _ in 0..100 {
+            let v = context.random_quantized(0.5, 2.0, 0.5);
+            assert!([0.5, 1.0, 1.5, 2.0].contains(&v));
+        }
+    }
+
+    #[test]
+    fn random_choice_picks_from_choices() {
+        let mut entity_system = EntitySystem::new();
+        let mut particle_vec = ParticleVec::new();
+        let mut sim = Simulation::new(Random::seed_from_str("random choice"));
+        let mut rng = Random::seed_from_str("random choice");
+        let mut context = LevelBuilderContext::new(&mut entity_system, &mut particle_vec, &mut sim, &mut rng);
+
+        for _ in 0..100 {
+            let v = context.random_choice(&[2, 3, 4]);
+            assert!((2..=4).contains(&v));
+        }
+    }
+
+    #[test]
+    fn custom_seed_builds_the_same_level() {
+        let level_hash = |seed: &str| {
+            let mut sim = Simulation::new(Random::seed_from_str(seed));
+            let level_info = LevelBuilder::default().generate_level_for_seed(seed, &mut EntitySystem::new(), &mut ParticleVec::new(), &mut sim);
+            assert_eq!(level_info.seed, seed);
+            level_info.level_hash
+        };
+        assert_eq!(level_hash("custom-a"), level_hash("custom-a"));
+        assert_ne!(level_hash("custom-a"), level_hash("custom-b"));
+    }
+
+    #[test]
+    fn daily_ignores_local_config() {
+        let day = Utc.with_ymd_and_hms(2026, 3, 14, 12, 0, 0).unwrap();
+        let level_hash = |config: LevelGenConfig| {
+            let mut builder = LevelBuilder::default();
+            builder.config = config;
+            let mut sim = Simulation::new(Random::seed_from_day(day));
+            let level_info = builder.generate_level_for_day(day, &mut EntitySystem::new(), &mut ParticleVec::new(), &mut sim);
+            (level_info.level_hash, level_info.num_blocks, builder.config.num_blocks())
+        };
+        let local = LevelGenConfig::from_toml_str("num_blocks = 3\ndifficulty_bias = -1.0").unwrap();
+        let (local_hash, local_blocks, kept_blocks) = level_hash(local);
+        let (default_hash, default_blocks, _) = level_hash(LevelGenConfig::default());
+        assert_eq!((local_hash, local_blocks), (default_hash, default_blocks));
+        // and it's still there for custom seeds
+        assert_eq!(kept_blocks, 3);
+    }
+
+    #[test]
+    fn descriptor_builds_the_same_level() {
+        // water balloons draw their jitter from the generator, so this needs the generator states as well as the params
+        let seed = (0..50).map(|i| format!("descriptor-{}", i)).find(|seed| {
+            let mut sim = Simulation::new(Random::seed_from_str(seed));
+            let level_info = LevelBuilder::default().generate_level_for_seed(seed, &mut EntitySystem::new(), &mut ParticleVec::new(), &mut sim);
+            level_info.operations.iter().any(|op| op.type_name == "WaterBalloonDrop")
+        }).unwrap();
+
+        let mut builder = LevelBuilder::default();
+        builder.mirrored = true;
+        let mut sim = Simulation::new(Random::seed_from_str(&seed));
+        let level_info = builder.generate_level_for_seed(&seed, &mut EntitySystem::new(), &mut ParticleVec::new(), &mut sim);
+        let descriptor = level_info.descriptor();
+        assert_eq!(descriptor.blocks.len(), level_info.operations.len());
+
+        let json = serde_json::to_string(&descriptor).unwrap();
+        let descriptor: LevelDescriptor = serde_json::from_str(&json).unwrap();
+        let mut rebuilt_sim = Simulation::new(Random::seed_from_str("something else"));
+        let rebuilt = LevelBuilder::default().build_from_descriptor(&descriptor, &mut EntitySystem::new(), &mut ParticleVec::new(), &mut rebuilt_sim).unwrap();
+        assert!(rebuilt.mirrored);
+        assert_eq!(rebuilt.level_hash, level_info.level_hash);
+        assert_eq!(rebuilt.descriptor(), descriptor);
+    }
+}
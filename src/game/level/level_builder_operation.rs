@@ -1,3 +1,5 @@
+use serde::{de::DeserializeOwned, Serialize};
+
 use super::level_builder::LevelBuilderContext;
 
 
@@ -10,8 +12,30 @@ pub trait LevelBuilderOperation {
         1.0
     }
 
+    /// How hard this block is relative to a plain bit of ground, used to reweight spawn chances (see LevelGenConfig).
+    fn default_difficulty(&self) -> f32 {
+        1.0
+    }
+
     fn prepare(&self, _level_builder_context: &mut LevelBuilderContext, _level_builder_operations: &mut Vec<(f32, Box<dyn LevelBuilderOperation + Send + Sync>)>) {
     }
 
     fn execute(&self, level_builder_context: &mut LevelBuilderContext);
-}
\ No newline at end of file
+
+    /// The explicitly set parameters for this operation (if any) in serialized form.
+    /// None means the parameters will be randomised when the operation is executed.
+    fn params(&self) -> Option<serde_json::Value> {
+        None
+    }
+
+    /// Clone this operation with explicitly set parameters, for example from a level file or the editor.
+    fn box_clone_with_params(&self, _params: serde_json::Value) -> serde_json::Result<Box<dyn LevelBuilderOperation + Send + Sync>> {
+        Ok(self.box_clone())
+    }
+}
+
+/// Parameters that shape a level block (hill height, bridge length, cliff drop etc).
+/// Operations pick random parameters within their ranges unless they have been set explicitly.
+pub trait LevelBuilderOperationParams: Serialize + DeserializeOwned + Clone {
+    fn random(level_builder_context: &mut LevelBuilderContext) -> Self;
+}
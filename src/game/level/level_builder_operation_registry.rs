@@ -25,6 +25,10 @@ impl LevelBuilderOperationRegistry {
         c
     }
 
+    pub fn find(&self, type_name: &str) -> Option<&dyn LevelBuilderOperation> {
+        self.0.iter().find(|operation| operation.type_name() == type_name).map(|operation| operation.as_ref())
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &Box<dyn LevelBuilderOperation>> {
         self.0.iter()
     }
@@ -0,0 +1,126 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::level_builder_operation::LevelBuilderOperation;
+
+const MAX_DIFFICULTY_WEIGHT: f32 = 100.0; // most the difficulty bias can scale a spawn chance up by
+
+/// Tuning for the level generator, loaded from level_gen.toml so level pacing can be tweaked without recompiling.
+/// Anything not set falls back to the built in defaults on each operation. eg.
+///
+/// ```toml
+/// num_blocks = 12
+/// difficulty_bias = 0.5
+///
+/// [operations.ElevatorOperation]
+/// spawn_chance = 1.0
+///
+/// [operations.FluidFunnel]
+/// spawn_chance = 0.0
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LevelGenConfig {
+    /// Number of blocks in a level, including the spawn and finish.
+    pub num_blocks: Option<i32>,
+
+    /// Scales spawn chances by difficulty ^ difficulty_bias.
+    /// Positive favours harder blocks, negative favours easier blocks and 0 leaves the spawn chances alone.
+    pub difficulty_bias: Option<f32>,
+
+    /// Overrides keyed by operation type name.
+    #[serde(default)]
+    pub operations: HashMap<String, LevelGenOperationConfig>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LevelGenOperationConfig {
+    pub spawn_chance: Option<f32>,
+    pub difficulty: Option<f32>,
+}
+
+impl LevelGenConfig {
+    pub fn load() -> Self {
+        let path = Path::new("level_gen.toml");
+        if path.exists() {
+            if let Ok(content) = fs::read_to_string(path) {
+                match Self::from_toml_str(&content) {
+                    Ok(config) => return config,
+                    Err(e) => eprintln!("Failed to parse level_gen.toml, using defaults: {}", e),
+                }
+            }
+        }
+        LevelGenConfig::default()
+    }
+
+    pub fn from_toml_str(content: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(content)
+    }
+
+    pub fn num_blocks(&self) -> i32 {
+        self.num_blocks.unwrap_or(10).max(2) // need room for spawn and finish
+    }
+
+    pub fn difficulty(&self, op: &dyn LevelBuilderOperation) -> f32 {
+        self.operations.get(op.type_name())
+            .and_then(|c| c.difficulty)
+            .unwrap_or_else(|| op.default_difficulty())
+    }
+
+    /// The chance of the operation being picked, before operations get to adjust it in prepare.
+    pub fn spawn_chance(&self, op: &dyn LevelBuilderOperation) -> f32 {
+        let spawn_chance = self.operations.get(op.type_name())
+            .and_then(|c| c.spawn_chance)
+            .unwrap_or_else(|| op.default_spawn_chance());
+
+        let difficulty_bias = self.difficulty_bias.unwrap_or(0.0);
+        if difficulty_bias == 0.0 {
+            return spawn_chance.max(0.0);
+        }
+        // a difficulty 0 block with a negative bias would be infinitely likely
+        let weight = self.difficulty(op).max(0.0).powf(difficulty_bias).min(MAX_DIFFICULTY_WEIGHT);
+        (spawn_chance * weight).max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::level::{level_blocks::{cliff_operation::CliffOperation, elevator::ElevatorOperation, straight_level_block::StraightLevelBlock}, level_builder_operation::LevelBuilderOperation, level_gen_config::LevelGenConfig};
+
+    #[test]
+    fn empty_config_uses_defaults() {
+        let config = LevelGenConfig::from_toml_str("").unwrap();
+        assert_eq!(config.num_blocks(), 10);
+        assert_eq!(config.spawn_chance(&CliffOperation::default()), 0.5);
+    }
+
+    #[test]
+    fn overrides_spawn_chance_by_type_name() {
+        let config = LevelGenConfig::from_toml_str("
+            [operations.CliffOperation]
+            spawn_chance = 2.0
+        ").unwrap();
+        assert_eq!(config.spawn_chance(&CliffOperation::default()), 2.0);
+        assert_eq!(config.spawn_chance(&ElevatorOperation::default()), 0.5);
+    }
+
+    #[test]
+    fn difficulty_bias_favours_harder_blocks() {
+        let config = LevelGenConfig::from_toml_str("difficulty_bias = 1.0").unwrap();
+        let easy = StraightLevelBlock::default();
+        let hard = ElevatorOperation::default();
+        let easy_scale = config.spawn_chance(&easy) / easy.default_spawn_chance();
+        let hard_scale = config.spawn_chance(&hard) / hard.default_spawn_chance();
+        assert!(hard_scale > easy_scale);
+    }
+
+    #[test]
+    fn negative_bias_stays_finite_at_difficulty_zero() {
+        let config = LevelGenConfig::from_toml_str("
+            difficulty_bias = -1.0
+            [operations.CliffOperation]
+            difficulty = 0.0
+        ").unwrap();
+        assert_eq!(config.spawn_chance(&CliffOperation::default()), 0.5 * 100.0);
+    }
+}
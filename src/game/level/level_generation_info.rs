@@ -0,0 +1,124 @@
+use rand_pcg::Pcg64;
+use serde::{Deserialize, Serialize};
+
+use crate::{core::{hash::Fnv1aHasher, math::aabb2d::Aabb2d}, simulation::particles::{simulation::Simulation, surface_material::MaterialTable}};
+
+/// An operation that was executed while generating a level, along with the parameters it ended up using.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelOperationInfo {
+    pub type_name: String,
+    pub params: Option<serde_json::Value>,
+}
+
+/// A block of a LevelDescriptor. The generator is kept as the block had it after picking its params,
+/// for blocks that draw more than their params from it, eg. the jitter of the water balloon particles.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelBlockDescriptor {
+    #[serde(flatten)]
+    pub operation: LevelOperationInfo,
+    pub rng: Pcg64,
+}
+
+/// A level as data rather than a sequence of random draws: every block that was built, in order, with the params it used.
+/// Builds the exact same level with LevelBuilder::build_from_descriptor, whatever the generator would pick for the seed now.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelDescriptor {
+    pub seed: String,
+    pub blocks: Vec<LevelBlockDescriptor>,
+    #[serde(default)]
+    pub mirrored: bool,
+    #[serde(default)]
+    pub headwind: f32,
+}
+
+/// Describes how a level was generated. This goes into the recording header
+/// and the level_hash is sent with BEST_TIME so clients can detect when they are running a different generator.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LevelGenerationInfo {
+    pub seed: String,
+    pub num_blocks: i32,
+    pub operations: Vec<LevelOperationInfo>,
+    pub level_hash: String,
+    #[serde(default)]
+    pub mirrored: bool,
+    #[serde(default)]
+    pub headwind: f32,
+    #[serde(skip)]
+    pub block_aabbs: Vec<Aabb2d>, // where each block ended up, in the same order as operations
+    #[serde(skip)]
+    pub rng_states: Vec<Pcg64>, // the generator as each operation had it once its params were picked
+}
+
+impl LevelGenerationInfo {
+    pub fn descriptor(&self) -> LevelDescriptor {
+        LevelDescriptor {
+            seed: self.seed.clone(),
+            blocks: self.operations.iter().zip(&self.rng_states)
+                .map(|(operation, rng)| LevelBlockDescriptor { operation: operation.clone(), rng: rng.clone() })
+                .collect(),
+            mirrored: self.mirrored,
+            headwind: self.headwind,
+        }
+    }
+
+    /// Hash the operations and the particles they produced.
+    /// Hashing the particles catches changes in the blocks or the shape builder that don't show up in the params.
+    pub fn compute_level_hash(operations: &[LevelOperationInfo], sim: &Simulation) -> String {
+        let mut hasher = Fnv1aHasher::new();
+
+        hasher.write_u64(operations.len() as u64);
+        for op in operations {
+            hasher.write_str(&op.type_name);
+            // serde_json keeps object keys sorted, so this is stable
+            let params = op.params.as_ref().map(|p| p.to_string()).unwrap_or_default();
+            hasher.write_str(&params);
+        }
+
+        hasher.write_u64(sim.particles.len() as u64);
+        for particle in sim.particles.iter() {
+            hasher.write_f32(particle.pos.x);
+            hasher.write_f32(particle.pos.y);
+            hasher.write_f32(particle.radius);
+        }
+
+        // plugin force fields change how the level plays. Only hashed when present so plain levels keep their hash
+        if !sim.force_fields.is_empty() {
+            hasher.write_u64(sim.force_fields.len() as u64);
+            for field in &sim.force_fields {
+                hasher.write_str(&field.name);
+            }
+        }
+
+        // and surface materials, only for particles that have one so plain levels keep their hash
+        for (i, particle) in sim.particles.iter().enumerate().filter(|(_, p)| p.material != MaterialTable::DEFAULT) {
+            let material = sim.materials.get(particle.material);
+            hasher.write_u64(i as u64);
+            hasher.write_f32(material.restitution);
+            hasher.write_f32(material.adhesion);
+            // added later, so only when it's set to keep the hash of levels using the other materials
+            if material.softness != 0.0 {
+                hasher.write_f32(material.softness);
+            }
+        }
+
+        // drag regions too, when there are any
+        if !sim.drag_regions.is_empty() {
+            hasher.write_u64(sim.drag_regions.len() as u64);
+            for region in &sim.drag_regions {
+                hasher.write_f32(region.aabb.min.x);
+                hasher.write_f32(region.aabb.min.y);
+                hasher.write_f32(region.aabb.max.x);
+                hasher.write_f32(region.aabb.max.y);
+                hasher.write_f32(region.drag);
+            }
+        }
+
+        // as are wind modifiers
+        if let Some(wind) = sim.aerodynamics.ambient_wind {
+            hasher.write_f32(wind.x);
+            hasher.write_f32(wind.y);
+        }
+
+        hasher.finish_hex()
+    }
+}
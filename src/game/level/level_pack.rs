@@ -0,0 +1,300 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{core::hash::Fnv1aHasher, game::level::level_generation_info::LevelOperationInfo};
+
+pub const LEVEL_PACKS_DIR: &str = "level_packs";
+pub const MY_LEVELS_PACK_NAME: &str = "My Levels";
+const MAX_PACK_LEVELS: usize = 100;
+const MAX_LEVEL_BLOCKS: usize = 200;
+
+/// One level in a pack, described by the blocks that build it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackLevel {
+    pub name: String,
+    pub operations: Vec<LevelOperationInfo>,
+}
+
+impl PackLevel {
+    /// Load a level saved on its own, eg. from the level editor
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let level: PackLevel = serde_json::from_str(&json).map_err(|e| format!("not a level: {}", e))?;
+        level.validate()?;
+        Ok(level)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        self.validate()?;
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, json).map_err(|e| e.to_string())
+    }
+
+    /// Leaderboard seed for a level played from its own file. It's from the blocks, so everyone playing the same level shares a board.
+    pub fn file_seed(&self) -> String {
+        let operations = serde_json::to_string(&self.operations).unwrap_or_default();
+        format!("level-{}", LevelPack::content_hash(&operations))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.operations.is_empty() || self.operations.len() > MAX_LEVEL_BLOCKS {
+            return Err(format!("level '{}' must have between 1 and {} blocks", self.name, MAX_LEVEL_BLOCKS));
+        }
+        Ok(())
+    }
+}
+
+/// A community collection of levels, shared as a single json file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelPack {
+    pub name: String,
+    #[serde(default)]
+    pub author: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub version: u32,
+    pub levels: Vec<PackLevel>,
+}
+
+impl LevelPack {
+    pub fn new(name: String, author: String) -> Self {
+        Self { name, author, description: String::new(), version: 1, levels: vec![] }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let pack: LevelPack = serde_json::from_str(json).map_err(|e| format!("not a level pack: {}", e))?;
+        pack.validate()?;
+        Ok(pack)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// File name and leaderboard safe id, eg. "Desert Jumps" -> "desert-jumps"
+    pub fn id(&self) -> String {
+        let mut id = String::new();
+        for c in self.name.trim().chars() {
+            if c.is_ascii_alphanumeric() {
+                id.push(c.to_ascii_lowercase());
+            } else if !id.ends_with('-') {
+                id.push('-');
+            }
+        }
+        id.trim_matches('-').to_string()
+    }
+
+    /// Leaderboard seed for a level in this pack, so pack times don't mix with the daily.
+    /// Zero padded so one level's seed is never a prefix of another's, see Leaderboard::set_level_hash.
+    pub fn level_seed(&self, level_index: usize) -> String {
+        format!("pack-{}-{:03}", self.id(), level_index + 1)
+    }
+
+    /// Stable hash of the pack contents, announced alongside a download url so clients can check they got the right file
+    pub fn content_hash(json: &str) -> String {
+        Fnv1aHasher::new().write_bytes(json.as_bytes()).finish_hex()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.id().is_empty() {
+            return Err(String::from("level pack needs a name"));
+        }
+        if self.levels.is_empty() || self.levels.len() > MAX_PACK_LEVELS {
+            return Err(format!("level pack must have between 1 and {} levels", MAX_PACK_LEVELS));
+        }
+        for level in &self.levels {
+            level.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// The level packs installed locally, one json file per pack in the level packs dir
+#[derive(Debug, Clone, Default)]
+pub struct LevelPackLibrary {
+    dir: PathBuf,
+    pub packs: Vec<LevelPack>,
+}
+
+impl LevelPackLibrary {
+    /// Load every pack in the dir, skipping (and logging) files that aren't valid packs
+    pub fn load(dir: &Path) -> Self {
+        let mut library = Self { dir: dir.to_path_buf(), packs: vec![] };
+        let Ok(entries) = fs::read_dir(dir) else {
+            return library;
+        };
+
+        let mut paths: Vec<PathBuf> = entries.filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            match fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|json| LevelPack::from_json(&json)) {
+                Ok(pack) => library.packs.push(pack),
+                Err(e) => println!("Skipping level pack {}: {}", path.display(), e),
+            }
+        }
+        library
+    }
+
+    pub fn find(&self, id: &str) -> Option<&LevelPack> {
+        self.packs.iter().find(|pack| pack.id() == id)
+    }
+
+    /// Add a pack to the library, replacing an older copy with the same id
+    pub fn install(&mut self, pack: LevelPack) -> Result<(), String> {
+        pack.validate()?;
+        fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        fs::write(self.pack_path(&pack), pack.to_json()).map_err(|e| e.to_string())?;
+
+        match self.packs.iter_mut().find(|p| p.id() == pack.id()) {
+            Some(existing) => *existing = pack,
+            None => self.packs.push(pack),
+        }
+        Ok(())
+    }
+
+    /// Install a pack file from anywhere on disk
+    pub fn import(&mut self, path: &Path) -> Result<&LevelPack, String> {
+        let json = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let pack = LevelPack::from_json(&json)?;
+        let id = pack.id();
+        self.install(pack)?;
+        Ok(self.find(&id).unwrap())
+    }
+
+    /// Write an installed pack to a file for sharing
+    pub fn export(&self, id: &str, path: &Path) -> Result<(), String> {
+        let pack = self.find(id).ok_or_else(|| format!("no level pack with id '{}'", id))?;
+        fs::write(path, pack.to_json()).map_err(|e| e.to_string())
+    }
+
+    /// Save a level into the local "My Levels" pack, so it can be exported and shared
+    pub fn add_to_my_levels(&mut self, author: &str, level: PackLevel) -> Result<(), String> {
+        let mut pack = self.packs.iter()
+            .find(|pack| pack.name == MY_LEVELS_PACK_NAME)
+            .cloned()
+            .unwrap_or_else(|| LevelPack::new(MY_LEVELS_PACK_NAME.to_owned(), author.to_owned()));
+        if pack.levels.iter().any(|l| l.operations == level.operations) {
+            return Err(String::from("level is already in My Levels"));
+        }
+        pack.levels.push(level);
+        pack.version += 1;
+        self.install(pack)
+    }
+
+    fn pack_path(&self, pack: &LevelPack) -> PathBuf {
+        self.dir.join(format!("{}.json", pack.id()))
+    }
+}
+
+/// Command line level pack management
+/// usage: level_pack list | import <file> | export <pack id> <file> | hash <file>
+pub fn run_level_pack_command(args: &[String]) -> Result<String, String> {
+    let mut library = LevelPackLibrary::load(Path::new(LEVEL_PACKS_DIR));
+    match (args.first().map(String::as_str), args.get(1), args.get(2)) {
+        (Some("list"), _, _) => Ok(library.packs.iter()
+            .map(|pack| format!("{} - {} ({} levels)", pack.id(), pack.name, pack.levels.len()))
+            .collect::<Vec<_>>()
+            .join("\n")),
+        (Some("import"), Some(path), _) => {
+            let pack = library.import(Path::new(path))?;
+            Ok(format!("Imported {} ({} levels)", pack.name, pack.levels.len()))
+        }
+        (Some("export"), Some(id), Some(path)) => {
+            library.export(id, Path::new(path))?;
+            Ok(format!("Exported {} to {}", id, path))
+        }
+        // the hash to put in a LEVEL_PACK announcement once the file is hosted somewhere
+        (Some("hash"), Some(path), _) => {
+            let json = fs::read_to_string(path).map_err(|e| e.to_string())?;
+            LevelPack::from_json(&json)?;
+            Ok(LevelPack::content_hash(&json))
+        }
+        _ => Err(String::from("usage: level_pack list | import <file> | export <pack id> <file> | hash <file>")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_pack() -> LevelPack {
+        let mut pack = LevelPack::new(String::from("Desert Jumps!"), String::from("bob"));
+        pack.levels.push(PackLevel {
+            name: String::from("First"),
+            operations: vec![
+                LevelOperationInfo { type_name: String::from("SpawnOperation"), params: None },
+                LevelOperationInfo { type_name: String::from("FinishOperation"), params: None },
+            ],
+        });
+        pack
+    }
+
+    #[test]
+    fn test_pack_id_and_seed() {
+        let pack = test_pack();
+        assert_eq!(pack.id(), "desert-jumps");
+        assert_eq!(pack.level_seed(0), "pack-desert-jumps-001");
+    }
+
+    #[test]
+    fn test_pack_round_trip_and_validation() {
+        let pack = test_pack();
+        assert_eq!(LevelPack::from_json(&pack.to_json()), Ok(pack.clone()));
+
+        let mut empty = pack.clone();
+        empty.levels.clear();
+        assert!(LevelPack::from_json(&empty.to_json()).is_err());
+        assert!(LevelPack::from_json("{\"name\": \"x\"}").is_err());
+    }
+
+    #[test]
+    fn test_level_file_round_trip() {
+        let file = std::env::temp_dir().join(format!("planck_level_{}.json", std::process::id()));
+        let level = test_pack().levels.remove(0);
+        level.save(&file).unwrap();
+        let loaded = PackLevel::load(&file).unwrap();
+        assert_eq!(loaded, level);
+        assert_eq!(loaded.file_seed(), level.file_seed());
+        assert!(level.file_seed().starts_with("level-"));
+
+        // renaming a level keeps its board, changing a block doesn't
+        let renamed = PackLevel { name: String::from("Renamed"), ..level.clone() };
+        assert_eq!(renamed.file_seed(), level.file_seed());
+        let mut changed = level.clone();
+        changed.operations[0].params = Some(serde_json::json!({ "width": 6.0, "wall_height": 1.5 }));
+        assert_ne!(changed.file_seed(), level.file_seed());
+
+        assert!(PackLevel { operations: vec![], ..level }.save(&file).is_err());
+        let _ = fs::remove_file(&file);
+    }
+
+    #[test]
+    fn test_library_import_export() {
+        let dir = std::env::temp_dir().join(format!("planck_level_packs_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let pack = test_pack();
+        let file = std::env::temp_dir().join(format!("planck_level_pack_{}.json", std::process::id()));
+        fs::write(&file, pack.to_json()).unwrap();
+
+        let mut library = LevelPackLibrary::load(&dir);
+        assert_eq!(library.import(&file).unwrap().name, pack.name);
+        // importing again replaces rather than duplicates
+        library.import(&file).unwrap();
+        assert_eq!(library.packs.len(), 1);
+
+        let reloaded = LevelPackLibrary::load(&dir);
+        assert_eq!(reloaded.packs, vec![pack]);
+
+        library.export("desert-jumps", &file).unwrap();
+        assert!(library.export("missing", &file).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+        let _ = fs::remove_file(&file);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{core::math::vec2::Vec2, game::level::{level_block_bounds::LevelBlockBounds, level_builder::LevelBuilderContext}, simulation::particles::{particle::Particle, shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}}};
+
+/// How far back from a join a transition ramp starts, and how far past it it ends
+pub const RAMP_LENGTH: f32 = 1.0;
+
+const MIN_STEP: f32 = 0.15; // steps smaller than this are just the ground sampling
+const MAX_STEP: f32 = 0.5; // bigger drops are meant to be there (cliffs, direction reversals)
+const MAX_SLOPE_CHANGE: f32 = 0.5; // sharpest dip the car can drive through without being thrown up
+
+/// Where to put a ramp between two neighbouring blocks, if their ground meets at a small step or a sharp dip.
+/// Both blocks need ground a ramp length either side of the join, so gaps and cliffs are left as they were built.
+/// Crests can't be smoothed by adding ground so they are left alone too.
+pub fn transition_ramp(prev: &LevelBlockBounds, next: &LevelBlockBounds, particles: &[Particle]) -> Option<(Vec2, Vec2)> {
+    if prev.x_direction != next.x_direction {
+        return None;
+    }
+
+    let x_direction = prev.x_direction;
+    let join = prev.exit;
+    let prev_edge = prev.surface_height(particles, join.x, join.y, MAX_STEP)?;
+    let next_edge = next.surface_height(particles, join.x, join.y, MAX_STEP)?;
+
+    let prev_far_x = join.x - RAMP_LENGTH * x_direction;
+    let next_far_x = join.x + RAMP_LENGTH * x_direction;
+    let prev_far = Vec2::new(prev_far_x, prev.surface_height(particles, prev_far_x, prev_edge, RAMP_LENGTH * 2.0)?);
+    let next_far = Vec2::new(next_far_x, next.surface_height(particles, next_far_x, next_edge, RAMP_LENGTH * 2.0)?);
+
+    let step = next_edge - prev_edge;
+    if step.abs() > MAX_STEP {
+        return None;
+    }
+    if step > MIN_STEP {
+        return Some((prev_far, Vec2::new(join.x, next_edge)));
+    }
+    if step < -MIN_STEP {
+        return Some((Vec2::new(join.x, prev_edge), next_far));
+    }
+
+    let slope_in = (prev_edge - prev_far.y) / RAMP_LENGTH;
+    let slope_out = (next_far.y - next_edge) / RAMP_LENGTH;
+    if slope_out - slope_in > MAX_SLOPE_CHANGE {
+        return Some((prev_far, next_far));
+    }
+
+    None
+}
+
+/// Add a short ramp wherever neighbouring blocks meet badly enough to launch the car.
+/// This has to run before the level is hashed, the ramps are part of the level.
+pub fn smooth_block_transitions(level_builder_context: &mut LevelBuilderContext) {
+    let ramps: Vec<(Vec2, Vec2)> = level_builder_context.block_bounds.windows(2)
+        .filter_map(|pair| transition_ramp(&pair[0], &pair[1], level_builder_context.sim.particles.as_slice()))
+        .collect();
+
+    for (start, end) in ramps {
+        ShapeBuilder::from_particle_template(*level_builder_context.particle_template.clone().set_static(true))
+            .apply_operation(LineSegment::new(start, end))
+            .create_in_simulation(level_builder_context.sim);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A block of ground particles along the given line, appended to particles
+    fn block(particles: &mut Vec<Particle>, entry: Vec2, exit: Vec2) -> LevelBlockBounds {
+        let start = particles.len();
+        let steps = 40;
+        for i in 0..=steps {
+            let pos = entry + (exit - entry) * (i as f32 / steps as f32);
+            particles.push(*Particle::default().set_radius(0.1).set_static(true).set_pos(pos));
+        }
+        LevelBlockBounds::new(entry, exit, 1.0, start..particles.len(), particles)
+    }
+
+    #[test]
+    fn test_transition_ramp() {
+        let mut particles = vec![];
+
+        // flat into flat doesn't need anything
+        let a = block(&mut particles, Vec2::new(0.0, 0.0), Vec2::new(4.0, 0.0));
+        let b = block(&mut particles, Vec2::new(4.0, 0.0), Vec2::new(8.0, 0.0));
+        assert!(transition_ramp(&a, &b, &particles).is_none());
+
+        // a steep run down into flat ground gets a chord across the dip
+        let c = block(&mut particles, Vec2::new(8.0, 0.0), Vec2::new(10.0, -2.0));
+        let d = block(&mut particles, Vec2::new(10.0, -2.0), Vec2::new(14.0, -2.0));
+        let (start, end) = transition_ramp(&c, &d, &particles).unwrap();
+        assert!((start.x - 9.0).abs() < 0.01 && (end.x - 11.0).abs() < 0.01);
+        assert!((start.y + 1.0).abs() < 0.1 && (end.y + 2.0).abs() < 0.1);
+
+        // a crest is left alone
+        let e = block(&mut particles, Vec2::new(14.0, -2.0), Vec2::new(16.0, 0.0));
+        let f = block(&mut particles, Vec2::new(16.0, 0.0), Vec2::new(20.0, 0.0));
+        assert!(transition_ramp(&e, &f, &particles).is_none());
+
+        // a small step up gets a ramp up to its edge
+        let g = block(&mut particles, Vec2::new(20.0, 0.3), Vec2::new(24.0, 0.3));
+        let (start, end) = transition_ramp(&f, &g, &particles).unwrap();
+        assert!((start.x - 19.0).abs() < 0.01 && start.y.abs() < 0.01);
+        assert!((end.x - 20.0).abs() < 0.01 && (end.y - 0.3).abs() < 0.01);
+    }
+}
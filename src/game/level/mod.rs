@@ -1,4 +1,9 @@
 pub mod level_builder;
 pub mod level_builder_operation;
 pub mod level_builder_operation_registry;
+pub mod level_gen_config;
 pub mod level_blocks;
+pub mod level_block_bounds;
+pub mod level_generation_info;
+pub mod level_pack;
+pub mod level_smoothing;
@@ -0,0 +1,195 @@
+use std::{fmt::Write, fs};
+
+use crate::{
+    core::math::{aabb2d::Aabb2d, random::Random, vec2::Vec2},
+    game::{entity::entity_system::EntitySystem, level::{level_builder::LevelBuilder, level_gen_config::LevelGenConfig}, seed_policy::seed_day},
+    simulation::particles::{particle_vec::ParticleVec, simulation::Simulation},
+};
+
+const SPAWN_BLOCK: &str = "SpawnOperation";
+const FINISH_BLOCK: &str = "FinishOperation";
+const SVG_SCALE: f32 = 20.0; // pixels per metre
+const SVG_MARGIN: f32 = 40.0; // pixels around each level, with room for its title
+
+/// One side of a comparison, the seed's level as a generator config builds it
+pub struct ComparedLevel {
+    pub config_name: String, // eg. the toml file it came from
+    pub blocks: Vec<(String, f32)>, // type name and difficulty of each block, as the config rates it
+    pub difficulty: f32, // sum of the block difficulties, leaving out the spawn and finish
+    pub length: f32, // metres from the start of the first block to the end of the last
+    pub level_hash: String,
+    block_aabbs: Vec<Aabb2d>,
+    particles: Vec<(Vec2, f32, [u8; 3])>, // position, radius, colour
+}
+
+impl ComparedLevel {
+    /// Build the level for the seed: a daily seed builds that day's daily with the config applied, anything else builds like a custom seed
+    pub fn build(seed: &str, config_name: &str, config: LevelGenConfig) -> Self {
+        let mut level_builder = LevelBuilder::default();
+        level_builder.config = config;
+        let mut sim = Simulation::new(Random::seed_from_str(seed));
+        let (mut entity_system, mut particle_vec) = (EntitySystem::new(), ParticleVec::new());
+        let level_info = match seed_day(seed) {
+            Ok(day) => level_builder.generate_level_for_day_with_config(day, &mut entity_system, &mut particle_vec, &mut sim),
+            Err(_) => level_builder.generate_level_for_seed(seed, &mut entity_system, &mut particle_vec, &mut sim),
+        };
+
+        let blocks: Vec<(String, f32)> = level_info.operations.iter()
+            .map(|op| {
+                let difficulty = level_builder.registry().find(&op.type_name).map_or(0.0, |operation| level_builder.config.difficulty(operation));
+                (op.type_name.clone(), difficulty)
+            })
+            .collect();
+        let difficulty = blocks.iter()
+            .filter(|(type_name, _)| type_name != SPAWN_BLOCK && type_name != FINISH_BLOCK)
+            .map(|(_, difficulty)| difficulty)
+            .sum();
+        let length = match (level_info.block_aabbs.first(), level_info.block_aabbs.last()) {
+            (Some(first), Some(last)) => (last.max.x - first.min.x).abs(),
+            _ => 0.0,
+        };
+        let colour = |c: f32| (c.clamp(0.0, 1.0) * 255.0) as u8;
+        let particles = sim.particles.iter()
+            .map(|p| (p.pos, p.radius, [colour(p.colour.x), colour(p.colour.y), colour(p.colour.z)]))
+            .collect();
+
+        Self {
+            config_name: config_name.to_owned(),
+            blocks,
+            difficulty,
+            length,
+            level_hash: level_info.level_hash,
+            block_aabbs: level_info.block_aabbs,
+            particles,
+        }
+    }
+
+    fn bounds(&self) -> Aabb2d {
+        let points: Vec<Vec2> = self.particles.iter().map(|(pos, _, _)| *pos).collect();
+        Aabb2d::from_point_cloud(&points)
+    }
+}
+
+/// The same seed built with two generator configs, eg. with and without a new block, to see what a change to the
+/// generator does to levels before it reaches a daily
+pub struct LevelComparison {
+    pub seed: String,
+    pub a: ComparedLevel,
+    pub b: ComparedLevel,
+}
+
+impl LevelComparison {
+    pub fn new(seed: &str, a: ComparedLevel, b: ComparedLevel) -> Self {
+        Self { seed: seed.to_owned(), a, b }
+    }
+
+    /// The two levels block by block side by side, with their difficulty estimates underneath
+    pub fn to_text(&self) -> String {
+        let width = self.a.blocks.iter().map(|(type_name, _)| type_name.len() + 8).max().unwrap_or(0).max(self.a.config_name.len()).max(24);
+        let mut text = String::new();
+        let _ = writeln!(text, "Seed {}", self.seed);
+        let _ = writeln!(text, "    {:<width$}  {}", self.a.config_name, self.b.config_name);
+        for i in 0..self.a.blocks.len().max(self.b.blocks.len()) {
+            let cell = |level: &ComparedLevel| level.blocks.get(i).map_or(String::new(), |(type_name, difficulty)| format!("{} ({:.1})", type_name, difficulty));
+            let (a, b) = (cell(&self.a), cell(&self.b));
+            let marker = if a == b { " " } else { "*" };
+            let _ = writeln!(text, "{}{:>2} {:<width$}  {}", marker, i + 1, a, b);
+        }
+        let (difficulty_a, length_a) = (format!("difficulty {:.1}", self.a.difficulty), format!("length {:.1} m", self.a.length));
+        let _ = writeln!(text, "    {:<width$}  difficulty {:.1}", difficulty_a, self.b.difficulty);
+        let _ = writeln!(text, "    {:<width$}  length {:.1} m", length_a, self.b.length);
+        if self.a.level_hash == self.b.level_hash {
+            let _ = writeln!(text, "Both configs build the same level");
+        }
+        text
+    }
+
+    /// Both levels drawn one above the other, A on top, to open in a browser
+    pub fn to_svg(&self) -> String {
+        let (bounds_a, bounds_b) = (self.a.bounds(), self.b.bounds());
+        let panel_size = |bounds: &Aabb2d| ((bounds.max.x - bounds.min.x) * SVG_SCALE + SVG_MARGIN * 2.0, (bounds.max.y - bounds.min.y) * SVG_SCALE + SVG_MARGIN * 2.0);
+        let (width_a, height_a) = panel_size(&bounds_a);
+        let (width_b, height_b) = panel_size(&bounds_b);
+
+        let mut svg = String::new();
+        let _ = writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" style=\"background:#202020\">", width_a.max(width_b), height_a + height_b);
+        Self::svg_panel(&mut svg, &self.a, &bounds_a, 0.0);
+        let _ = writeln!(svg, "<line x1=\"0\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#808080\"/>", height_a, width_a.max(width_b), height_a);
+        Self::svg_panel(&mut svg, &self.b, &bounds_b, height_a);
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    fn svg_panel(svg: &mut String, level: &ComparedLevel, bounds: &Aabb2d, top: f32) {
+        // world y goes up, svg y goes down
+        let to_svg = |pos: Vec2| (SVG_MARGIN + (pos.x - bounds.min.x) * SVG_SCALE, top + SVG_MARGIN + (bounds.max.y - pos.y) * SVG_SCALE);
+        let _ = writeln!(svg, "<text x=\"10\" y=\"{:.1}\" fill=\"#ffffff\" font-family=\"sans-serif\" font-size=\"16\">{} - difficulty {:.1}, length {:.1} m</text>", top + 20.0, level.config_name, level.difficulty, level.length);
+        for (aabb, (type_name, _)) in level.block_aabbs.iter().zip(&level.blocks) {
+            let (x, y) = to_svg(Vec2::new(aabb.min.x.min(aabb.max.x), aabb.max.y));
+            let (w, h) = ((aabb.max.x - aabb.min.x).abs() * SVG_SCALE, (aabb.max.y - aabb.min.y) * SVG_SCALE);
+            let _ = writeln!(svg, "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"none\" stroke=\"#606060\" stroke-dasharray=\"4\"><title>{}</title></rect>", x, y, w, h, type_name);
+        }
+        for (pos, radius, [r, g, b]) in &level.particles {
+            let (x, y) = to_svg(*pos);
+            let _ = writeln!(svg, "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" fill=\"rgb({},{},{})\"/>", x, y, radius * SVG_SCALE, r, g, b);
+        }
+    }
+}
+
+/// compare_levels <seed> <a.toml> <b.toml> [--svg <file>]
+pub fn run_compare_levels(args: &[String]) -> Result<String, String> {
+    let usage = || String::from("usage: planck-time-trials compare_levels <seed> <a.toml> <b.toml> [--svg <file>]");
+    let (seed, path_a, path_b) = match args {
+        [seed, a, b, ..] => (seed, a, b),
+        _ => return Err(usage()),
+    };
+    let load = |path: &str| -> Result<LevelGenConfig, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        LevelGenConfig::from_toml_str(&content).map_err(|e| format!("Failed to parse {}: {}", path, e))
+    };
+    let comparison = LevelComparison::new(seed, ComparedLevel::build(seed, path_a, load(path_a)?), ComparedLevel::build(seed, path_b, load(path_b)?));
+
+    let mut output = comparison.to_text();
+    if let Some(i) = args.iter().position(|arg| arg == "--svg") {
+        let path = args.get(i + 1).ok_or_else(usage)?;
+        fs::write(path, comparison.to_svg()).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+        let _ = writeln!(output, "Split view written to {}", path);
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_configs() {
+        let config = |toml: &str| LevelGenConfig::from_toml_str(toml).unwrap();
+        let seed = "2025-03-14";
+
+        // the same config builds the same level
+        let same = LevelComparison::new(seed, ComparedLevel::build(seed, "a", config("")), ComparedLevel::build(seed, "b", config("")));
+        assert_eq!(same.a.level_hash, same.b.level_hash);
+        assert!(same.to_text().contains("Both configs build the same level"));
+
+        // turning on a new block that isn't in the dailies yet, with nothing else allowed between the spawn and finish
+        let b = config("
+            [operations.StraightLevelBlock]
+            spawn_chance = 0.0
+            [operations.MovingPlatformOperation]
+            spawn_chance = 1000.0
+            difficulty = 3.0
+        ");
+        let comparison = LevelComparison::new(seed, ComparedLevel::build(seed, "a", config("")), ComparedLevel::build(seed, "b", b));
+        assert_ne!(comparison.a.level_hash, comparison.b.level_hash);
+        let moving_platforms = comparison.b.blocks.iter().filter(|(type_name, _)| type_name == "MovingPlatformOperation").count();
+        assert!(moving_platforms > 0);
+        assert!(comparison.b.blocks.iter().filter(|(type_name, _)| type_name == "MovingPlatformOperation").all(|(_, difficulty)| *difficulty == 3.0));
+        assert!(comparison.a.length > 0.0 && comparison.b.length > 0.0);
+        assert!(comparison.to_text().contains("MovingPlatformOperation (3.0)"));
+
+        let svg = comparison.to_svg();
+        assert!(svg.starts_with("<svg") && svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<circle").count(), comparison.a.particles.len() + comparison.b.particles.len());
+    }
+}
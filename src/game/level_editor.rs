@@ -0,0 +1,197 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    core::math::{aabb2d::Aabb2d, vec2::Vec2},
+    game::level::{level_builder_operation_registry::LevelBuilderOperationRegistry, level_generation_info::LevelOperationInfo, level_pack::PackLevel},
+};
+
+pub const EDITOR_LEVEL_PATH: &str = "level.json";
+const SPAWN_BLOCK: &str = "SpawnOperation";
+const FINISH_BLOCK: &str = "FinishOperation";
+const FIRST_BLOCK: &str = "StraightLevelBlock"; // what a new level has between the spawn and the finish
+
+/// The editor scene: a level put together block by block, that can be driven as it's built and saved to a file
+/// to play with --level. The spawn always stays first and the finish last, everything between can be added,
+/// moved, removed and have its params changed. The level is built again after every change, keeping the params
+/// each block was built with so changing one block doesn't roll new ones for the rest.
+pub struct LevelEditor {
+    pub level: PackLevel,
+    pub path: PathBuf, // where the level is saved
+    pub selected: Option<usize>,
+    pub block_types: Vec<String>, // what can be placed, every block in the registry besides the spawn and finish
+    pub block_type: usize, // index into block_types of the block that's placed next
+    pub cursor: Vec2, // world position under the mouse
+    registry: LevelBuilderOperationRegistry,
+}
+
+impl LevelEditor {
+    /// Edit the level saved at path, or start a new one if there's nothing there yet
+    pub fn open(path: &Path, registry: &LevelBuilderOperationRegistry) -> Result<Self, String> {
+        let level = if path.exists() { PackLevel::load(path)? } else { Self::new_level() };
+        let block_types: Vec<String> = registry.iter()
+            .map(|operation| operation.type_name().to_owned())
+            .filter(|type_name| type_name != SPAWN_BLOCK && type_name != FINISH_BLOCK)
+            .collect();
+        Ok(Self {
+            level,
+            path: path.to_path_buf(),
+            selected: None,
+            block_type: block_types.iter().position(|type_name| type_name == FIRST_BLOCK).unwrap_or_default(),
+            block_types,
+            cursor: Vec2::new(0.0, 0.0),
+            registry: registry.clone(),
+        })
+    }
+
+    fn new_level() -> PackLevel {
+        let block = |type_name: &str| LevelOperationInfo { type_name: type_name.to_owned(), params: None };
+        PackLevel { name: String::new(), operations: vec![block(SPAWN_BLOCK), block(FIRST_BLOCK), block(FINISH_BLOCK)] }
+    }
+
+    /// Take the params every block was built with, so they stay put from now on
+    pub fn level_built(&mut self, operations: &[LevelOperationInfo]) {
+        let same_blocks = operations.len() == self.level.operations.len()
+            && operations.iter().zip(&self.level.operations).all(|(built, block)| built.type_name == block.type_name);
+        if same_blocks {
+            self.level.operations = operations.to_vec();
+        }
+    }
+
+    /// The block the point is in, from where the blocks ended up when the level was last built
+    pub fn block_at(&self, point: Vec2, block_aabbs: &[Aabb2d]) -> Option<usize> {
+        block_aabbs.iter().position(|aabb| aabb.contains_point(point)).filter(|&index| index < self.level.operations.len())
+    }
+
+    /// Blocks between the spawn and the finish can be moved and removed
+    fn is_movable(&self, index: usize) -> bool {
+        index > 0 && index + 1 < self.level.operations.len()
+    }
+
+    pub fn next_block_type(&mut self, step: isize) {
+        if !self.block_types.is_empty() {
+            self.block_type = (self.block_type as isize + step).rem_euclid(self.block_types.len() as isize) as usize;
+        }
+    }
+
+    /// Put a block of the current type in after the given one, or before the finish, and select it
+    pub fn place(&mut self, after: Option<usize>) -> Option<usize> {
+        let type_name = self.block_types.get(self.block_type)?.clone();
+        let last = self.level.operations.len().saturating_sub(1);
+        let index = after.map_or(last, |after| after + 1).clamp(1, last.max(1));
+        self.level.operations.insert(index, LevelOperationInfo { type_name, params: None });
+        self.selected = Some(index);
+        Some(index)
+    }
+
+    /// Swap the selected block with the one before (-1) or after (1) it. False if it can't go there.
+    pub fn move_selected(&mut self, offset: isize) -> bool {
+        let Some(selected) = self.selected.filter(|&selected| self.is_movable(selected)) else { return false };
+        let target = selected as isize + offset;
+        if target < 0 || !self.is_movable(target as usize) {
+            return false;
+        }
+        self.level.operations.swap(selected, target as usize);
+        self.selected = Some(target as usize);
+        true
+    }
+
+    pub fn delete_selected(&mut self) -> bool {
+        let Some(selected) = self.selected.filter(|&selected| self.is_movable(selected)) else { return false };
+        self.level.operations.remove(selected);
+        self.selected = None;
+        true
+    }
+
+    /// The selected block's params as name and value pairs, for editing as text
+    pub fn selected_params(&self) -> Vec<(String, String)> {
+        let params = self.selected.and_then(|selected| self.level.operations.get(selected)).and_then(|block| block.params.as_ref());
+        match params {
+            Some(serde_json::Value::Object(params)) => params.iter().map(|(name, value)| (name.clone(), value.to_string())).collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Change the selected block's params from edited text. Values are read as JSON, anything else as a string.
+    /// The block is checked with its new params before they are kept, so a bad value can't break the level.
+    pub fn set_selected_params(&mut self, values: &[(String, String)]) -> Result<(), String> {
+        let Some(block) = self.selected.and_then(|selected| self.level.operations.get_mut(selected)) else {
+            return Err(String::from("no block selected"));
+        };
+        let mut params = match &block.params {
+            Some(serde_json::Value::Object(params)) => params.clone(),
+            _ => return Err(format!("{} has no params to change", block.type_name)),
+        };
+        for (name, value) in values {
+            let value = serde_json::from_str(value.trim()).unwrap_or_else(|_| serde_json::Value::String(value.clone()));
+            params.insert(name.clone(), value);
+        }
+        let params = serde_json::Value::Object(params);
+        let operation = self.registry.find(&block.type_name).ok_or_else(|| format!("unknown level block '{}'", block.type_name))?;
+        operation.box_clone_with_params(params.clone()).map_err(|e| format!("bad params for {}: {}", block.type_name, e))?;
+        block.params = Some(params);
+        Ok(())
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        self.level.save(&self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::math::random::Random, game::{entity::entity_system::EntitySystem, level::level_builder::LevelBuilder}, simulation::particles::{particle_vec::ParticleVec, simulation::Simulation}};
+
+    fn build(editor: &mut LevelEditor) -> Vec<Aabb2d> {
+        let seed = editor.level.file_seed();
+        let mut sim = Simulation::new(Random::seed_from_str(&seed));
+        let level_info = LevelBuilder::default().generate_from_operations(&seed, &editor.level.operations, &mut EntitySystem::new(), &mut ParticleVec::new(), &mut sim).unwrap();
+        editor.level_built(&level_info.operations);
+        level_info.block_aabbs
+    }
+
+    fn type_names(editor: &LevelEditor) -> Vec<&str> {
+        editor.level.operations.iter().map(|block| block.type_name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_edit_and_save_a_level() {
+        let path = std::env::temp_dir().join(format!("planck_editor_level_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut editor = LevelEditor::open(&path, LevelBuilder::default().registry()).unwrap();
+        assert_eq!(type_names(&editor), vec![SPAWN_BLOCK, FIRST_BLOCK, FINISH_BLOCK]);
+        assert!(!editor.block_types.iter().any(|type_name| type_name == SPAWN_BLOCK || type_name == FINISH_BLOCK));
+
+        // building pins the params, and blocks can be picked out by where they are
+        let block_aabbs = build(&mut editor);
+        assert!(editor.level.operations.iter().all(|block| block.params.is_some()));
+        let middle = &block_aabbs[1];
+        assert_eq!(editor.block_at(Vec2::new((middle.min.x + middle.max.x) * 0.5, (middle.min.y + middle.max.y) * 0.5), &block_aabbs), Some(1));
+        assert_eq!(editor.block_at(Vec2::new(0.0, 1000.0), &block_aabbs), None);
+
+        // the spawn and finish stay where they are
+        editor.block_type = editor.block_types.iter().position(|type_name| type_name == "HillOperation").unwrap();
+        assert_eq!(editor.place(None), Some(2));
+        assert_eq!(type_names(&editor), vec![SPAWN_BLOCK, FIRST_BLOCK, "HillOperation", FINISH_BLOCK]);
+        assert!(editor.move_selected(-1));
+        assert!(!editor.move_selected(-1));
+        assert_eq!(type_names(&editor), vec![SPAWN_BLOCK, "HillOperation", FIRST_BLOCK, FINISH_BLOCK]);
+        editor.selected = Some(0);
+        assert!(!editor.delete_selected());
+        build(&mut editor);
+
+        // params are checked before they are kept
+        editor.selected = Some(2);
+        let params = editor.selected_params();
+        assert!(!params.is_empty());
+        let (name, _) = params[0].clone();
+        assert!(editor.set_selected_params(&[(name.clone(), String::from("not a number"))]).is_err());
+        editor.set_selected_params(&[(name.clone(), String::from("2.5"))]).unwrap();
+        assert!(editor.selected_params().contains(&(name, String::from("2.5"))));
+
+        editor.save().unwrap();
+        let reopened = LevelEditor::open(&path, LevelBuilder::default().registry()).unwrap();
+        assert_eq!(reopened.level, editor.level);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,43 @@
+use rand::Rng;
+
+use crate::core::math::random::Random;
+
+const ADJECTIVES: &[&str] = &[
+    "Misty", "Rusty", "Golden", "Frozen", "Dusty", "Silent", "Crooked", "Windy", "Sunken", "Hollow",
+    "Crimson", "Mossy", "Stormy", "Lazy", "Jagged", "Twisted", "Sleepy", "Rolling", "Broken", "Wild",
+];
+
+const PLACES: &[&str] = &[
+    "Volcano", "Canyon", "Harbour", "Quarry", "Meadow", "Glacier", "Ridge", "Marsh", "Orchard", "Dunes",
+    "Valley", "Foundry", "Cliffs", "Lagoon", "Summit", "Forest", "Mesa", "Badlands", "Hills", "Gorge",
+];
+
+const ROUTES: &[&str] = &["Run", "Dash", "Descent", "Circuit", "Climb", "Trail", "Sprint", "Scramble", "Loop", "Pass"];
+
+/// A memorable name for a seed so players can talk about a day's level, eg. "Misty Volcano Run #412".
+/// The same seed always gets the same name.
+pub fn level_name(seed: &str) -> String {
+    let mut rng = Random::seed_from_str(&format!("level-name-{}", seed));
+    let adjective = ADJECTIVES[rng.random_range(0..ADJECTIVES.len())];
+    let place = PLACES[rng.random_range(0..PLACES.len())];
+    let route = ROUTES[rng.random_range(0..ROUTES.len())];
+    let number = rng.random_range(1..=999);
+    format!("{} {} {} #{}", adjective, place, route, number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_name() {
+        let name = level_name("2025-03-14");
+        assert_eq!(name, level_name("2025-03-14"));
+        assert_ne!(name, level_name("2025-03-15"));
+
+        let (words, number) = name.split_once(" #").unwrap();
+        let words: Vec<&str> = words.split(' ').collect();
+        assert!(ADJECTIVES.contains(&words[0]) && PLACES.contains(&words[1]) && ROUTES.contains(&words[2]));
+        assert!((1..=999).contains(&number.parse::<u32>().unwrap()));
+    }
+}
@@ -0,0 +1,214 @@
+use serde::Deserialize;
+use std::io::Read;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Duration;
+
+use crate::game::level::level_pack::LevelPack;
+
+/// Players announce packs here with: LEVEL_PACK url=<url> hash=<content hash> name=<pack name>
+pub const LEVEL_PACKS_CHANNEL: &str = "#planck-levels";
+const MAX_LISTINGS: usize = 100;
+const MAX_DOWNLOAD_BYTES: u64 = 4 * 1024 * 1024;
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A pack that can be downloaded, from the http index or announced in the level packs channel
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct LevelPackListing {
+    pub name: String,
+    #[serde(default)]
+    pub author: String,
+    pub url: String,
+    #[serde(default)]
+    pub hash: Option<String>, // LevelPack::content_hash of the file, checked after downloading
+}
+
+#[derive(Deserialize)]
+struct LevelPackIndex {
+    packs: Vec<LevelPackListing>,
+}
+
+impl LevelPackListing {
+    /// The index is a json file: { "packs": [ { "name": ..., "author": ..., "url": ..., "hash": ... } ] }
+    pub fn parse_index(json: &str) -> Result<Vec<Self>, String> {
+        let index: LevelPackIndex = serde_json::from_str(json).map_err(|e| format!("bad level pack index: {}", e))?;
+        Ok(index.packs.into_iter().filter(|listing| is_http_url(&listing.url)).take(MAX_LISTINGS).collect())
+    }
+
+    /// Parse a LEVEL_PACK announcement. The name goes last so it can contain spaces.
+    pub fn parse_announcement(sender: &str, message: &str) -> Option<Self> {
+        let rest = message.strip_prefix("LEVEL_PACK ")?;
+        let (fields, name) = rest.split_once("name=")?;
+
+        let mut url = None;
+        let mut hash = None;
+        for part in fields.split_whitespace() {
+            if let Some(u) = part.strip_prefix("url=") {
+                url = Some(u.to_string());
+            } else if let Some(h) = part.strip_prefix("hash=") {
+                hash = Some(h.to_string());
+            }
+        }
+
+        let url = url.filter(|url| is_http_url(url))?;
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+        Some(Self { name: name.to_string(), author: sender.to_string(), url, hash })
+    }
+
+    pub fn announcement(&self) -> String {
+        match &self.hash {
+            Some(hash) => format!("LEVEL_PACK url={} hash={} name={}", self.url, hash, self.name),
+            None => format!("LEVEL_PACK url={} name={}", self.url, self.name),
+        }
+    }
+}
+
+fn is_http_url(url: &str) -> bool {
+    url.starts_with("https://") || url.starts_with("http://")
+}
+
+/// Blocking http get with a size limit, run on a background thread
+fn http_get(url: &str) -> Result<String, String> {
+    let response = ureq::get(url)
+        .timeout(HTTP_TIMEOUT)
+        .call()
+        .map_err(|e| e.to_string())?;
+    let mut body = String::new();
+    response.into_reader()
+        .take(MAX_DOWNLOAD_BYTES)
+        .read_to_string(&mut body)
+        .map_err(|e| e.to_string())?;
+    Ok(body)
+}
+
+pub enum LevelPackBrowserEvent {
+    IndexLoaded(Result<Vec<LevelPackListing>, String>),
+    Downloaded(Result<LevelPack, String>),
+}
+
+/// Finds and downloads level packs without blocking the game loop. Results are collected with poll each frame.
+pub struct LevelPackBrowser {
+    pub listings: Vec<LevelPackListing>,
+    sender: Sender<LevelPackBrowserEvent>,
+    receiver: Receiver<LevelPackBrowserEvent>,
+}
+
+impl LevelPackBrowser {
+    pub fn new() -> Self {
+        let (sender, receiver) = channel();
+        Self { listings: vec![], sender, receiver }
+    }
+
+    pub fn refresh_index(&self, index_url: String) {
+        let sender = self.sender.clone();
+        std::thread::spawn(move || {
+            let result = http_get(&index_url).and_then(|json| LevelPackListing::parse_index(&json));
+            let _ = sender.send(LevelPackBrowserEvent::IndexLoaded(result));
+        });
+    }
+
+    pub fn download(&self, listing: &LevelPackListing) {
+        let sender = self.sender.clone();
+        let listing = listing.clone();
+        std::thread::spawn(move || {
+            let result = http_get(&listing.url).and_then(|json| {
+                if let Some(hash) = &listing.hash {
+                    let content_hash = LevelPack::content_hash(&json);
+                    if !content_hash.eq_ignore_ascii_case(hash) {
+                        return Err(format!("{} doesn't match its announced hash", listing.name));
+                    }
+                }
+                LevelPack::from_json(&json)
+            });
+            let _ = sender.send(LevelPackBrowserEvent::Downloaded(result));
+        });
+    }
+
+    /// Add a listing, replacing any existing listing with the same url. Returns true if the listings changed.
+    pub fn add_listing(&mut self, listing: LevelPackListing) -> bool {
+        match self.listings.iter_mut().find(|l| l.url == listing.url) {
+            Some(existing) if *existing == listing => false,
+            Some(existing) => {
+                *existing = listing;
+                true
+            }
+            None => {
+                self.listings.push(listing);
+                if self.listings.len() > MAX_LISTINGS {
+                    self.listings.remove(0);
+                }
+                true
+            }
+        }
+    }
+
+    /// Handle a message from the level packs channel. Returns true if the listings changed.
+    pub fn handle_message(&mut self, sender: &str, message: &str) -> bool {
+        match LevelPackListing::parse_announcement(sender, message) {
+            Some(listing) => self.add_listing(listing),
+            None => false,
+        }
+    }
+
+    pub fn poll(&mut self) -> Vec<LevelPackBrowserEvent> {
+        let mut events = vec![];
+        while let Ok(event) = self.receiver.try_recv() {
+            if let LevelPackBrowserEvent::IndexLoaded(Ok(listings)) = &event {
+                for listing in listings {
+                    self.add_listing(listing.clone());
+                }
+            }
+            events.push(event);
+        }
+        events
+    }
+}
+
+impl Default for LevelPackBrowser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_announcement() {
+        let listing = LevelPackListing::parse_announcement("bob", "LEVEL_PACK url=https://example.com/jumps.json hash=00ff name=Desert Jumps").unwrap();
+        assert_eq!(listing.name, "Desert Jumps");
+        assert_eq!(listing.author, "bob");
+        assert_eq!(listing.url, "https://example.com/jumps.json");
+        assert_eq!(listing.hash.as_deref(), Some("00ff"));
+        assert_eq!(LevelPackListing::parse_announcement("bob", &listing.announcement()), Some(listing));
+
+        assert!(LevelPackListing::parse_announcement("bob", "LEVEL_PACK url=file:///etc/passwd name=x").is_none());
+        assert!(LevelPackListing::parse_announcement("bob", "LEVEL_PACK url=https://example.com/x.json name= ").is_none());
+        assert!(LevelPackListing::parse_announcement("bob", "BEST_TIME seed=2025-01-01 time=1.0 user=bob").is_none());
+    }
+
+    #[test]
+    fn test_parse_index() {
+        let json = r#"{ "packs": [
+            { "name": "Jumps", "author": "bob", "url": "https://example.com/jumps.json", "hash": "00ff" },
+            { "name": "Local", "url": "/home/bob/local.json" }
+        ] }"#;
+        let listings = LevelPackListing::parse_index(json).unwrap();
+        assert_eq!(listings.len(), 1);
+        assert_eq!(listings[0].name, "Jumps");
+        assert!(LevelPackListing::parse_index("[]").is_err());
+    }
+
+    #[test]
+    fn test_announcements_replace_by_url() {
+        let mut browser = LevelPackBrowser::new();
+        assert!(browser.handle_message("bob", "LEVEL_PACK url=https://example.com/a.json name=A"));
+        assert!(!browser.handle_message("bob", "LEVEL_PACK url=https://example.com/a.json name=A"));
+        assert!(browser.handle_message("bob", "LEVEL_PACK url=https://example.com/a.json hash=01 name=A v2"));
+        assert_eq!(browser.listings.len(), 1);
+        assert_eq!(browser.listings[0].name, "A v2");
+    }
+}
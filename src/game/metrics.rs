@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+pub const METRICS_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Resident memory of this process from /proc, None where that isn't available
+pub fn resident_memory_kb() -> Option<u64> {
+    let statm = fs::read_to_string("/proc/self/statm").ok()?;
+    let pages = statm.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+    Some(pages * 4) // 4kb pages
+}
+
+/// Counters for a long running headless instance (the leaderboard bot, soak tests), dumped to a JSON file every so often
+/// so whoever runs it can keep an eye on it with their own tools, eg. a cron job or a node exporter textfile collector.
+/// Does nothing without a path.
+pub struct Metrics {
+    path: Option<PathBuf>,
+    started: Instant,
+    last_written: Option<Instant>,
+    values: BTreeMap<&'static str, u64>, // sorted so the dump is easy to diff
+}
+
+impl Metrics {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { path, started: Instant::now(), last_written: None, values: BTreeMap::new() }
+    }
+
+    /// The path after a "--metrics" argument
+    pub fn path_from_args(args: &[String]) -> Option<PathBuf> {
+        let index = args.iter().position(|arg| arg == "--metrics")?;
+        args.get(index + 1).map(PathBuf::from)
+    }
+
+    pub fn add(&mut self, name: &'static str, amount: u64) {
+        *self.values.entry(name).or_insert(0) += amount;
+    }
+
+    pub fn increment(&mut self, name: &'static str) {
+        self.add(name, 1);
+    }
+
+    /// For gauges, eg. how many scores are held right now
+    pub fn set(&mut self, name: &'static str, value: u64) {
+        self.values.insert(name, value);
+    }
+
+    pub fn get(&self, name: &str) -> u64 {
+        self.values.get(name).copied().unwrap_or(0)
+    }
+
+    pub fn to_json(&self, now: Instant) -> serde_json::Value {
+        let mut json = serde_json::Map::new();
+        json.insert(String::from("uptime_secs"), now.duration_since(self.started).as_secs().into());
+        json.insert(String::from("rss_kb"), resident_memory_kb().into());
+        for (name, value) in &self.values {
+            json.insert(name.to_string(), (*value).into());
+        }
+        serde_json::Value::Object(json)
+    }
+
+    /// Write the dump if it's been METRICS_INTERVAL since the last one
+    pub fn update(&mut self, now: Instant) {
+        if self.last_written.is_some_and(|written| now.duration_since(written) < METRICS_INTERVAL) {
+            return;
+        }
+        self.last_written = Some(now);
+        if let Err(e) = self.write(now) {
+            eprintln!("Failed to write metrics: {}", e);
+        }
+    }
+
+    /// Written to a temporary file first, so a reader never sees half a dump
+    pub fn write(&self, now: Instant) -> io::Result<()> {
+        let Some(path) = &self.path else { return Ok(()) };
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(&self.to_json(now))?)?;
+        fs::rename(temp_path, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_json() {
+        let args: Vec<String> = ["bot", "--metrics", "bot_metrics.json"].iter().map(|arg| arg.to_string()).collect();
+        assert_eq!(Metrics::path_from_args(&args), Some(PathBuf::from("bot_metrics.json")));
+        assert_eq!(Metrics::path_from_args(&args[..2]), None);
+
+        let mut metrics = Metrics::new(None);
+        metrics.increment("irc_reconnects");
+        metrics.add("messages_received", 3);
+        metrics.set("scores", 10);
+        metrics.set("scores", 7);
+
+        let json = metrics.to_json(metrics.started + Duration::from_secs(90));
+        assert_eq!(json["uptime_secs"], 90);
+        assert_eq!(json["irc_reconnects"], 1);
+        assert_eq!(json["messages_received"], 3);
+        assert_eq!(json["scores"], 7);
+        assert_eq!(metrics.get("desyncs_found"), 0);
+    }
+}
@@ -1,9 +1,61 @@
 pub mod level;
 pub mod entity;
+#[cfg(feature = "client")]
 pub mod introduction;
+#[cfg(feature = "client")]
 pub mod game;
 pub mod irc;
 pub mod leaderboard;
+pub mod leaderboard_bot;
+pub mod metrics;
+#[cfg(feature = "client")]
 pub mod ui;
 pub mod game_state;
-pub mod settings;
\ No newline at end of file
+pub mod settings;
+pub mod color_scheme;
+pub mod game_mode;
+pub mod telemetry;
+pub mod rating;
+pub mod practice;
+pub mod training;
+pub mod headless_run;
+pub mod determinism_audit;
+pub mod soak_test;
+pub mod plugins;
+#[cfg(feature = "client")]
+pub mod level_pack_browser;
+pub mod ghost;
+pub mod ghost_track;
+#[cfg(feature = "client")]
+pub mod decals;
+pub mod replay_sharing;
+#[cfg(feature = "client")]
+pub mod world_labels;
+pub mod nickname;
+pub mod level_name;
+pub mod seed_policy;
+pub mod session_attempts;
+pub mod camera_constraint;
+pub mod camera_mode;
+#[cfg(feature = "client")]
+pub mod ghost_camera;
+pub mod golden_replays;
+pub mod physics_tuning;
+#[cfg(feature = "client")]
+pub mod sandbox;
+#[cfg(feature = "client")]
+pub mod result_card;
+pub mod scene_file;
+pub mod rumble;
+pub mod clock_check;
+pub mod car_audio;
+pub mod world_audio;
+pub mod offline;
+pub mod verify;
+pub mod bench;
+pub mod commentary;
+pub mod stream_overlay;
+pub mod level_editor;
+pub mod anomaly_capture;
+pub mod splits;
+pub mod level_comparison;
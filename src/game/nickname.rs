@@ -0,0 +1,173 @@
+use std::fmt;
+
+use rand::Rng;
+
+pub const MAX_NICKNAME_LENGTH: usize = 16; // the lowest limit of the common IRC networks
+
+// services and staff names a network won't let us have, or that would confuse other players
+const RESERVED_NICKNAMES: &[&str] = &[
+    "nickserv", "chanserv", "memoserv", "operserv", "hostserv", "botserv",
+    "global", "root", "admin", "administrator", "moderator", "server", "system",
+];
+
+// not allowed anywhere in a name, after undoing common letter swaps, see is_offensive
+const OFFENSIVE_WORDS: &[&str] = &["fuck", "shit", "cunt", "nigg", "fagg", "whore", "bitch", "asshole", "retard", "rapist"];
+
+// IRC allows these anywhere in a nickname, and digits and '-' anywhere but the start
+const SPECIAL_CHARS: &str = "[]\\`_^{|}";
+
+const CONSONANTS: &[&str] = &["b", "d", "f", "g", "k", "l", "m", "n", "p", "r", "s", "t", "v", "z", "br", "dr", "kr", "st", "tr", "sh"];
+const VOWELS: &[&str] = &["a", "e", "i", "o", "u", "ai", "ou"];
+
+/// Why a nickname can't be used on IRC
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NicknameError {
+    Empty,
+    TooLong,
+    InvalidStart(char),
+    InvalidChar(char),
+    Reserved,
+    Offensive,
+}
+
+impl fmt::Display for NicknameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NicknameError::Empty => write!(f, "Enter a name"),
+            NicknameError::TooLong => write!(f, "Names can be at most {} characters", MAX_NICKNAME_LENGTH),
+            NicknameError::InvalidStart(c) => write!(f, "Names can't start with '{}'", c),
+            NicknameError::InvalidChar(' ') => write!(f, "Names can't contain spaces"),
+            NicknameError::InvalidChar(c) => write!(f, "Names can't contain '{}'", c),
+            NicknameError::Reserved => write!(f, "That name is reserved"),
+            NicknameError::Offensive => write!(f, "Pick a different name"),
+        }
+    }
+}
+
+/// Checks a nickname against the IRC rules, so the server doesn't reject it after we connect
+pub fn validate_nickname(name: &str) -> Result<(), NicknameError> {
+    let mut chars = name.chars();
+    let first = chars.next().ok_or(NicknameError::Empty)?;
+    if name.chars().count() > MAX_NICKNAME_LENGTH {
+        return Err(NicknameError::TooLong);
+    }
+    if !(first.is_ascii_alphabetic() || SPECIAL_CHARS.contains(first)) {
+        return Err(NicknameError::InvalidStart(first));
+    }
+    if let Some(c) = chars.find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || SPECIAL_CHARS.contains(*c))) {
+        return Err(NicknameError::InvalidChar(c));
+    }
+    if RESERVED_NICKNAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(name)) {
+        return Err(NicknameError::Reserved);
+    }
+    if is_offensive(name) {
+        return Err(NicknameError::Offensive);
+    }
+    Ok(())
+}
+
+/// Whether a name contains a slur or swear word, also when spelt with digits or separators, eg. "Sh1t" or "s_h_i_t".
+/// Strict, it's for picking my own name. Names coming in from other players go through has_offensive_word.
+pub fn is_offensive(name: &str) -> bool {
+    let normalised = normalise(name.chars().filter(|c| c.is_ascii_alphanumeric()));
+    OFFENSIVE_WORDS.iter().any(|word| normalised.contains(word))
+}
+
+/// Whether one of the words in a name starts with a slur or swear word, eg. "BigShit" or "fucker_99", but not
+/// "Yamashita" or "Scunthorpe". Words are split at separators and where a lowercase letter is followed by a capital.
+pub fn has_offensive_word(name: &str) -> bool {
+    let mut words: Vec<String> = vec![];
+    let mut previous = None;
+    for c in name.chars() {
+        let new_word = !c.is_ascii_alphanumeric() || previous.is_none_or(|previous: char| previous.is_ascii_lowercase() && c.is_ascii_uppercase());
+        if new_word {
+            words.push(String::new());
+        }
+        if let (true, Some(word)) = (c.is_ascii_alphanumeric(), words.last_mut()) {
+            word.push(c);
+        }
+        previous = Some(c);
+    }
+    words.iter()
+        .map(|word| normalise(word.chars()))
+        .any(|word| OFFENSIVE_WORDS.iter().any(|offensive| word.starts_with(offensive)))
+}
+
+/// Lowercase with common letter swaps undone, eg. "Sh1t" is "shit"
+fn normalise(chars: impl Iterator<Item = char>) -> String {
+    chars
+        .map(|c| match c.to_ascii_lowercase() {
+            '0' => 'o',
+            '1' => 'i',
+            '3' => 'e',
+            '4' => 'a',
+            '5' => 's',
+            '7' => 't',
+            c => c,
+        })
+        .collect()
+}
+
+/// A pronounceable name from a few consonant vowel syllables, eg. "Brikosa"
+pub fn random_nickname<R: Rng>(rng: &mut R) -> String {
+    loop {
+        let num_syllables = rng.random_range(2..=3);
+        let mut name = String::new();
+        for _ in 0..num_syllables {
+            name.push_str(CONSONANTS[rng.random_range(0..CONSONANTS.len())]);
+            name.push_str(VOWELS[rng.random_range(0..VOWELS.len())]);
+        }
+        // sometimes end on a consonant so names don't all sound alike
+        if rng.random_bool(0.5) {
+            name.push_str(CONSONANTS[rng.random_range(0..CONSONANTS.len())]);
+        }
+
+        let mut chars = name.chars();
+        let name: String = chars.next().map(|c| c.to_ascii_uppercase()).into_iter().chain(chars).collect();
+        if validate_nickname(&name).is_ok() {
+            return name;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::math::random::Random;
+
+    #[test]
+    fn test_validate_nickname() {
+        assert_eq!(validate_nickname("Speedy_1"), Ok(()));
+        assert_eq!(validate_nickname("[ghost]"), Ok(()));
+        assert_eq!(validate_nickname(""), Err(NicknameError::Empty));
+        assert_eq!(validate_nickname("abcdefghijklmnopq"), Err(NicknameError::TooLong));
+        assert_eq!(validate_nickname("1st"), Err(NicknameError::InvalidStart('1')));
+        assert_eq!(validate_nickname("-dash"), Err(NicknameError::InvalidStart('-')));
+        assert_eq!(validate_nickname("two words"), Err(NicknameError::InvalidChar(' ')));
+        assert_eq!(validate_nickname("a#b"), Err(NicknameError::InvalidChar('#')));
+        assert_eq!(validate_nickname("NickServ"), Err(NicknameError::Reserved));
+        assert_eq!(validate_nickname("Sh1t_Driver"), Err(NicknameError::Offensive));
+        assert_eq!(validate_nickname("Dickens"), Ok(()));
+    }
+
+    #[test]
+    fn test_offensive_words_in_other_players_names() {
+        assert!(has_offensive_word("Sh1t_Driver"));
+        assert!(has_offensive_word("BigShitDriver"));
+        assert!(has_offensive_word("fucker99"));
+        assert!(!has_offensive_word("Yamashita"));
+        assert!(!has_offensive_word("Kinoshita"));
+        assert!(!has_offensive_word("Scunthorpe"));
+        assert!(is_offensive("Scunthorpe"));
+    }
+
+    #[test]
+    fn test_random_nicknames_are_valid() {
+        let mut rng = Random::seed_from_str("nicknames");
+        for _ in 0..100 {
+            let name = random_nickname(&mut rng);
+            assert_eq!(validate_nickname(&name), Ok(()), "{}", name);
+            assert!(name.chars().next().unwrap().is_ascii_uppercase());
+        }
+    }
+}
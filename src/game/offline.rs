@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::time::{Duration, Instant};
+
+pub const OFFLINE_PATH: &str = "offline.json";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15); // still trying after this, but the player is told we're offline
+const RECONNECT_DELAY: Duration = Duration::from_secs(30);
+const CONNECT_GIVE_UP: Duration = Duration::from_secs(120); // a connection stuck this long isn't getting through, start again
+const MAX_PENDING: usize = 32; // oldest dropped first, a long way past what a session of racing offline would post
+const MAX_BOARDS: usize = 8;
+
+/// A message that couldn't be sent while offline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingMessage {
+    pub target: String,
+    pub message: String,
+}
+
+/// The last board seen on a channel for a seed, as the LEADERBOARD_SYNC messages that rebuild it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedBoard {
+    pub channel: String,
+    pub seed: String,
+    pub sync: Vec<String>,
+}
+
+/// Kept on disk for when IRC can't be reached, even across restarts: times still to be posted,
+/// and the boards as they were last seen so there is something to show
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OfflineStore {
+    pub pending: Vec<PendingMessage>,
+    #[serde(default)]
+    pub boards: Vec<SavedBoard>, // least recently saved first
+}
+
+impl OfflineStore {
+    pub fn load(path: &str) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)
+    }
+
+    /// Hold on to a message for target until take_pending
+    pub fn queue(&mut self, target: String, message: String) {
+        self.pending.push(PendingMessage { target, message });
+        if self.pending.len() > MAX_PENDING {
+            self.pending.remove(0);
+        }
+    }
+
+    /// The messages waiting for target, oldest first, removing them from the queue
+    pub fn take_pending(&mut self, target: &str) -> Vec<String> {
+        let (taken, kept) = std::mem::take(&mut self.pending).into_iter().partition(|pending| pending.target.eq_ignore_ascii_case(target));
+        self.pending = kept;
+        taken.into_iter().map(|pending| pending.message).collect()
+    }
+
+    /// Number of messages waiting for target
+    pub fn num_pending(&self, target: &str) -> usize {
+        self.pending.iter().filter(|pending| pending.target.eq_ignore_ascii_case(target)).count()
+    }
+
+    /// Replace the saved board for channel and seed. Returns true if anything changed.
+    pub fn save_board(&mut self, channel: &str, seed: &str, sync: Vec<String>) -> bool {
+        let board = SavedBoard { channel: channel.to_owned(), seed: seed.to_owned(), sync };
+        if self.boards.contains(&board) {
+            return false;
+        }
+        self.boards.retain(|saved| !(saved.channel.eq_ignore_ascii_case(channel) && saved.seed == seed));
+        if !board.sync.is_empty() {
+            self.boards.push(board);
+        }
+        if self.boards.len() > MAX_BOARDS {
+            self.boards.remove(0);
+        }
+        true
+    }
+
+    /// The sync messages for every board saved from channel
+    pub fn boards_for<'a>(&'a self, channel: &'a str) -> impl Iterator<Item = &'a String> + 'a {
+        self.boards.iter().filter(move |saved| saved.channel.eq_ignore_ascii_case(channel)).flat_map(|saved| saved.sync.iter())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConnectionState {
+    Connecting,
+    Online, // in the leaderboard channel
+    Offline,
+}
+
+/// Whether the leaderboard can be reached over IRC, and when to try again when it can't
+#[derive(Debug, Clone)]
+pub struct Connection {
+    state: ConnectionState,
+    since: Instant,
+}
+
+impl Connection {
+    /// Started connecting at now
+    pub fn connecting(now: Instant) -> Self {
+        Self { state: ConnectionState::Connecting, since: now }
+    }
+
+    /// Joined the leaderboard channel, messages sent from here are heard
+    pub fn handle_joined(&mut self, now: Instant) {
+        self.state = ConnectionState::Online;
+        self.since = now;
+    }
+
+    pub fn handle_disconnected(&mut self, now: Instant) {
+        self.state = ConnectionState::Offline;
+        self.since = now;
+    }
+
+    pub fn is_online(&self) -> bool {
+        self.state == ConnectionState::Online
+    }
+
+    /// Disconnected, or still connecting long after it should have
+    pub fn is_offline(&self, now: Instant) -> bool {
+        match self.state {
+            ConnectionState::Connecting => now - self.since >= CONNECT_TIMEOUT,
+            ConnectionState::Online => false,
+            ConnectionState::Offline => true,
+        }
+    }
+
+    /// Time to connect again, the last connection dropped or has been stuck connecting for too long
+    pub fn reconnect_due(&self, now: Instant) -> bool {
+        match self.state {
+            ConnectionState::Connecting => now - self.since >= CONNECT_GIVE_UP,
+            ConnectionState::Online => false,
+            ConnectionState::Offline => now - self.since >= RECONNECT_DELAY,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_and_boards() {
+        let mut store = OfflineStore::default();
+        store.queue("#planck-leaderboard".to_owned(), "BEST_TIME seed=a time=10.000 user=me".to_owned());
+        store.queue("#friends".to_owned(), "BEST_TIME seed=a time=10.000 user=me".to_owned());
+        assert_eq!(store.num_pending("#friends"), 1);
+        assert_eq!(store.take_pending("#Friends"), vec!["BEST_TIME seed=a time=10.000 user=me".to_owned()]);
+        assert_eq!(store.take_pending("#friends"), Vec::<String>::new());
+        assert_eq!(store.pending.len(), 1);
+        for i in 0..MAX_PENDING + 5 {
+            store.queue("#planck-leaderboard".to_owned(), format!("BEST_TIME seed=a time={}.000 user=me", i));
+        }
+        assert_eq!(store.num_pending("#planck-leaderboard"), MAX_PENDING);
+        assert_eq!(store.pending[0].message, "BEST_TIME seed=a time=5.000 user=me");
+
+        assert!(store.save_board("#planck-leaderboard", "a", vec!["sync a1".to_owned()]));
+        assert!(!store.save_board("#planck-leaderboard", "a", vec!["sync a1".to_owned()]));
+        assert!(store.save_board("#planck-leaderboard", "b", vec!["sync b1".to_owned()]));
+        assert!(store.save_board("#planck-leaderboard", "a", vec!["sync a2".to_owned()]));
+        assert!(store.save_board("#friends", "a", vec!["sync f1".to_owned()]));
+        assert_eq!(store.boards_for("#planck-leaderboard").collect::<Vec<_>>(), vec!["sync b1", "sync a2"]);
+        for i in 0..MAX_BOARDS {
+            store.save_board("#friends", &i.to_string(), vec![format!("sync {}", i)]);
+        }
+        assert_eq!(store.boards.len(), MAX_BOARDS);
+        assert_eq!(store.boards_for("#planck-leaderboard").count(), 0);
+    }
+
+    #[test]
+    fn test_connection() {
+        let start = Instant::now();
+        let mut connection = Connection::connecting(start);
+        assert!(!connection.is_offline(start + Duration::from_secs(1)));
+        assert!(connection.is_offline(start + CONNECT_TIMEOUT));
+        assert!(!connection.reconnect_due(start + RECONNECT_DELAY)); // still waiting to hear how it went
+        assert!(connection.reconnect_due(start + CONNECT_GIVE_UP));
+
+        connection.handle_joined(start);
+        assert!(connection.is_online() && !connection.is_offline(start + CONNECT_TIMEOUT));
+
+        connection.handle_disconnected(start);
+        assert!(!connection.is_online() && connection.is_offline(start));
+        assert!(!connection.reconnect_due(start + Duration::from_secs(1)));
+        assert!(connection.reconnect_due(start + RECONNECT_DELAY));
+    }
+}
@@ -0,0 +1,92 @@
+use std::ops::RangeInclusive;
+
+use crate::{core::math::vec2::Vec2, simulation::particles::simulation::{Simulation, SolverSettings}};
+
+/// Solver parameters that can be changed from the tuning panel while the game runs (--tuning),
+/// so tweaking the physics doesn't need a rebuild for every change
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicsTuning {
+    pub gravity: f32, // m/s^2, downwards
+    pub friction: f32, // scale on every particle's friction
+    pub stiffness: f32, // scale on spring stiffness, volume constraints get this much less compliant
+    pub max_iterations: i32,
+    pub time_delta: f32, // seconds simulated each frame
+}
+
+impl Default for PhysicsTuning {
+    fn default() -> Self {
+        Self {
+            gravity: 9.8,
+            friction: 1.0,
+            stiffness: 1.0,
+            max_iterations: SolverSettings::default().max_iterations,
+            time_delta: 0.005,
+        }
+    }
+}
+
+impl PhysicsTuning {
+    pub const GRAVITY_RANGE: RangeInclusive<f32> = 0.0..=30.0;
+    pub const SCALE_RANGE: RangeInclusive<f32> = 0.1..=4.0; // for friction and stiffness
+    pub const MAX_ITERATIONS_RANGE: RangeInclusive<i32> = 1..=20;
+    pub const TIME_DELTA_RANGE: RangeInclusive<f32> = 0.001..=0.02;
+
+    /// Change a simulation tuned with previous to be tuned with this instead. Friction and stiffness are
+    /// scaled from what they were, so the differences between particles and constraints are kept.
+    /// Things added to the simulation later start untuned, eg. a new level wants applying from the default.
+    pub fn apply(&self, previous: &PhysicsTuning, sim: &mut Simulation) {
+        sim.gravity = Vec2::new(sim.gravity.x, -self.gravity);
+
+        let friction = self.friction / previous.friction;
+        for particle in sim.particles.0.iter_mut() {
+            particle.s_friction *= friction;
+            particle.k_friction *= friction;
+        }
+
+        let stiffness = self.stiffness / previous.stiffness;
+        for spring in sim.spring_constraints.0.iter_mut() {
+            spring.stiffness *= stiffness;
+        }
+        for volume in sim.volume_constraints.0.iter_mut() {
+            volume.compliance /= stiffness;
+        }
+
+        sim.solver_settings.max_iterations = self.max_iterations; // it stops at the max even if the min is higher
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::math::random::Random,
+        simulation::{constraints::spring_constraint::SpringConstraint, particles::particle::Particle},
+    };
+
+    #[test]
+    fn test_apply_and_undo() {
+        let mut sim = Simulation::new(Random::seed_from_str("tuning"));
+        for x in [0.0, 1.0] {
+            let mut particle = *Particle::default().set_pos(Vec2::new(x, 0.0));
+            particle.s_friction = 0.4;
+            particle.k_friction = 0.2;
+            sim.particles.push(particle);
+        }
+        sim.add_spring_constraint(SpringConstraint::from_particles(0, 1, &sim.particles, 100.0));
+
+        let default = PhysicsTuning::default();
+        let tuning = PhysicsTuning { gravity: 20.0, friction: 2.0, stiffness: 0.5, max_iterations: 1, ..default };
+        tuning.apply(&default, &mut sim);
+        assert_eq!(sim.gravity, Vec2::new(0.0, -20.0));
+        assert_eq!((sim.particles[0].s_friction, sim.particles[0].k_friction), (0.8, 0.4));
+        assert_eq!(sim.spring_constraints.0[0].stiffness, 50.0);
+        assert_eq!(sim.solver_settings.max_iterations, 1);
+
+        // going back to the default puts it back as it was built
+        default.apply(&tuning, &mut sim);
+        assert_eq!(sim.gravity, Vec2::new(0.0, -9.8));
+        assert_eq!((sim.particles[0].s_friction, sim.particles[0].k_friction), (0.4, 0.2));
+        assert_eq!(sim.spring_constraints.0[0].stiffness, 100.0);
+        assert_eq!(sim.solver_settings.max_iterations, SolverSettings::default().max_iterations);
+    }
+}
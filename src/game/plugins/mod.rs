@@ -0,0 +1,2 @@
+pub mod plugin_abi;
+pub mod plugin_host;
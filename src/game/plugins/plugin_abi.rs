@@ -0,0 +1,69 @@
+//! The C ABI between the game and plugin dynamic libraries.
+//!
+//! A plugin is a `cdylib` that exports:
+//!
+//! `extern "C" fn planck_plugin_register(registrar: *const PlanckPluginRegistrar) -> bool`
+//!
+//! It should check `abi_version` matches the version it was built against, then call `register_operation`
+//! and `register_force_field` for each thing it provides. Strings are copied by the game during the call,
+//! but `user_data` must stay valid for as long as the game is running.
+//! Plugin callbacks must only depend on their inputs and the level rng so levels and replays stay deterministic.
+
+use std::ffi::{c_char, c_void};
+
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// Name of the function every plugin exports
+pub const PLUGIN_REGISTER_SYMBOL: &[u8] = b"planck_plugin_register\0";
+
+pub type PlanckPluginRegisterFn = unsafe extern "C" fn(registrar: *const PlanckPluginRegistrar) -> bool;
+
+/// Opaque handle to the level being built, only valid during an execute call
+#[repr(C)]
+pub struct PlanckLevelBuilder {
+    _private: [u8; 0],
+}
+
+/// Functions a plugin operation can call to build its part of the level
+#[repr(C)]
+pub struct PlanckLevelApi {
+    /// Where the next block starts
+    pub get_cursor: extern "C" fn(builder: *mut PlanckLevelBuilder, x: *mut f32, y: *mut f32),
+    /// Where the next block should start, set this to the end of your block
+    pub set_cursor: extern "C" fn(builder: *mut PlanckLevelBuilder, x: f32, y: f32),
+    /// 1.0 when the level heads right, -1.0 after a direction reversing block
+    pub x_direction: extern "C" fn(builder: *mut PlanckLevelBuilder) -> f32,
+    /// Random number in [0, 1) from the level rng
+    pub random: extern "C" fn(builder: *mut PlanckLevelBuilder) -> f32,
+    /// Add a line of static ground particles from (x0, y0) to (x1, y1)
+    pub add_ground_line: extern "C" fn(builder: *mut PlanckLevelBuilder, x0: f32, y0: f32, x1: f32, y1: f32),
+    /// Add a single particle, returns its index. A mass of 0 makes it static.
+    pub add_particle: extern "C" fn(builder: *mut PlanckLevelBuilder, x: f32, y: f32, radius: f32, mass: f32) -> usize,
+}
+
+/// A new level block
+#[repr(C)]
+pub struct PlanckOperationDesc {
+    pub type_name: *const c_char, // nul terminated, should be unique, eg. "MyPack.Ramp"
+    pub spawn_chance: f32,
+    pub difficulty: f32,
+    pub user_data: *mut c_void,
+    pub execute: extern "C" fn(user_data: *mut c_void, api: *const PlanckLevelApi, builder: *mut PlanckLevelBuilder),
+}
+
+/// A force applied to all dynamic particles, as an acceleration in m/s^2
+#[repr(C)]
+pub struct PlanckForceFieldDesc {
+    pub name: *const c_char, // nul terminated
+    pub user_data: *mut c_void,
+    pub force_at: extern "C" fn(user_data: *mut c_void, x: f32, y: f32, out_x: *mut f32, out_y: *mut f32),
+}
+
+/// Passed to planck_plugin_register
+#[repr(C)]
+pub struct PlanckPluginRegistrar {
+    pub abi_version: u32,
+    pub host: *mut c_void,
+    pub register_operation: extern "C" fn(host: *mut c_void, desc: *const PlanckOperationDesc),
+    pub register_force_field: extern "C" fn(host: *mut c_void, desc: *const PlanckForceFieldDesc),
+}
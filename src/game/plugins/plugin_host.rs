@@ -0,0 +1,282 @@
+use rand::Rng;
+use std::{ffi::{c_void, CStr}, sync::{Arc, OnceLock}};
+
+use crate::{core::math::vec2::Vec2, game::{level::{level_builder::LevelBuilderContext, level_builder_operation::LevelBuilderOperation}, plugins::plugin_abi::{PlanckForceFieldDesc, PlanckLevelApi, PlanckLevelBuilder, PlanckOperationDesc, PlanckPluginRegisterFn, PlanckPluginRegistrar, PLUGIN_ABI_VERSION}}, simulation::particles::{force_field::ForceField, shape_builder::{line_segment::LineSegment, shape_builder::ShapeBuilder}}};
+
+pub const PLUGINS_DIR: &str = "plugins";
+
+static PLUGINS: OnceLock<PluginSet> = OnceLock::new();
+
+/// Everything registered by plugins. Loaded once at startup, empty unless built with the "plugins" feature.
+pub fn plugins() -> &'static PluginSet {
+    PLUGINS.get_or_init(PluginSet::load)
+}
+
+/// Plugin user data, the plugin promises it is safe to share between threads
+#[derive(Clone, Copy)]
+struct PluginUserData(*mut c_void);
+
+unsafe impl Send for PluginUserData {}
+unsafe impl Sync for PluginUserData {}
+
+impl PluginUserData {
+    // a method rather than .0 so closures capture the whole Send + Sync wrapper
+    fn ptr(self) -> *mut c_void {
+        self.0
+    }
+}
+
+/// A level block provided by a plugin
+#[derive(Clone)]
+pub struct PluginOperation {
+    type_name: String,
+    spawn_chance: f32,
+    difficulty: f32,
+    user_data: PluginUserData,
+    execute: extern "C" fn(user_data: *mut c_void, api: *const PlanckLevelApi, builder: *mut PlanckLevelBuilder),
+}
+
+impl LevelBuilderOperation for PluginOperation {
+    fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    fn box_clone(&self) -> Box<dyn LevelBuilderOperation + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn default_spawn_chance(&self) -> f32 {
+        self.spawn_chance
+    }
+
+    fn default_difficulty(&self) -> f32 {
+        self.difficulty
+    }
+
+    fn execute(&self, level_builder_context: &mut LevelBuilderContext) {
+        let builder = level_builder_context as *mut LevelBuilderContext as *mut PlanckLevelBuilder;
+        (self.execute)(self.user_data.ptr(), &LEVEL_API, builder);
+    }
+}
+
+static LEVEL_API: PlanckLevelApi = PlanckLevelApi {
+    get_cursor: api_get_cursor,
+    set_cursor: api_set_cursor,
+    x_direction: api_x_direction,
+    random: api_random,
+    add_ground_line: api_add_ground_line,
+    add_particle: api_add_particle,
+};
+
+/// # Safety
+/// builder must be the pointer handed to the plugin by PluginOperation::execute
+unsafe fn context<'a>(builder: *mut PlanckLevelBuilder) -> &'a mut LevelBuilderContext<'a> {
+    unsafe { &mut *(builder as *mut LevelBuilderContext) }
+}
+
+extern "C" fn api_get_cursor(builder: *mut PlanckLevelBuilder, x: *mut f32, y: *mut f32) {
+    let ctx = unsafe { context(builder) };
+    unsafe {
+        *x = ctx.cursor.x;
+        *y = ctx.cursor.y;
+    }
+}
+
+extern "C" fn api_set_cursor(builder: *mut PlanckLevelBuilder, x: f32, y: f32) {
+    let ctx = unsafe { context(builder) };
+    ctx.cursor = Vec2::new(x, y);
+}
+
+extern "C" fn api_x_direction(builder: *mut PlanckLevelBuilder) -> f32 {
+    let ctx = unsafe { context(builder) };
+    ctx.x_direction
+}
+
+extern "C" fn api_random(builder: *mut PlanckLevelBuilder) -> f32 {
+    let ctx = unsafe { context(builder) };
+    ctx.rng.random_range(0.0..1.0)
+}
+
+extern "C" fn api_add_ground_line(builder: *mut PlanckLevelBuilder, x0: f32, y0: f32, x1: f32, y1: f32) {
+    let ctx = unsafe { context(builder) };
+    ShapeBuilder::from_particle_template(*ctx.particle_template.clone().set_mass(0.0))
+        .apply_operation(LineSegment::new(Vec2::new(x0, y0), Vec2::new(x1, y1)))
+        .create_in_simulation(ctx.sim);
+}
+
+extern "C" fn api_add_particle(builder: *mut PlanckLevelBuilder, x: f32, y: f32, radius: f32, mass: f32) -> usize {
+    let ctx = unsafe { context(builder) };
+    let index = ctx.sim.particles.len();
+    let particle = *ctx.particle_template.clone().set_pos(Vec2::new(x, y)).set_radius(radius).set_mass(mass);
+    ctx.sim.add_particle(particle);
+    index
+}
+
+#[derive(Default)]
+pub struct PluginSet {
+    pub operations: Vec<PluginOperation>,
+    pub force_fields: Vec<ForceField>,
+    #[cfg(feature = "plugins")]
+    libraries: Vec<libloading::Library>, // kept alive for as long as the callbacks are in use
+}
+
+impl PluginSet {
+    #[cfg(not(feature = "plugins"))]
+    fn load() -> Self {
+        Self::default()
+    }
+
+    /// Load every dynamic library in the plugins dir. Failures are logged and skipped so a bad mod can't stop the game starting.
+    #[cfg(feature = "plugins")]
+    fn load() -> Self {
+        let mut plugin_set = Self::default();
+
+        let Ok(dir) = std::fs::read_dir(PLUGINS_DIR) else {
+            return plugin_set;
+        };
+
+        // sort so plugins always register in the same order, the level generator depends on it
+        let mut paths: Vec<_> = dir.filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == std::env::consts::DLL_EXTENSION))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            match plugin_set.load_library(&path) {
+                Ok(()) => println!("Loaded plugin {}", path.display()),
+                Err(e) => println!("Failed to load plugin {}: {}", path.display(), e),
+            }
+        }
+
+        plugin_set
+    }
+
+    #[cfg(feature = "plugins")]
+    fn load_library(&mut self, path: &std::path::Path) -> Result<(), String> {
+        use crate::game::plugins::plugin_abi::PLUGIN_REGISTER_SYMBOL;
+
+        // Safety: running a plugin's code is inherently trusting it, that is the point of a plugin
+        let library = unsafe { libloading::Library::new(path) }.map_err(|e| e.to_string())?;
+        let register = unsafe { library.get::<PlanckPluginRegisterFn>(PLUGIN_REGISTER_SYMBOL) }.map_err(|e| e.to_string())?;
+        let register = *register;
+        if !unsafe { self.register_with(register) } {
+            return Err(String::from("plugin refused to register, it may have been built for a different game version"));
+        }
+        self.libraries.push(library);
+        Ok(())
+    }
+
+    /// Call a plugin's register function, adding whatever it registers to this set
+    ///
+    /// # Safety
+    /// register must follow the plugin ABI
+    pub unsafe fn register_with(&mut self, register: PlanckPluginRegisterFn) -> bool {
+        let registrar = PlanckPluginRegistrar {
+            abi_version: PLUGIN_ABI_VERSION,
+            host: self as *mut PluginSet as *mut c_void,
+            register_operation,
+            register_force_field,
+        };
+        unsafe { register(&registrar) }
+    }
+}
+
+/// # Safety
+/// ptr must be null or a valid nul terminated string
+unsafe fn string_from_ptr(ptr: *const std::ffi::c_char) -> Option<String> {
+    if ptr.is_null() {
+        return None;
+    }
+    Some(unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned())
+}
+
+extern "C" fn register_operation(host: *mut c_void, desc: *const PlanckOperationDesc) {
+    let plugin_set = unsafe { &mut *(host as *mut PluginSet) };
+    let Some(desc) = (unsafe { desc.as_ref() }) else {
+        return;
+    };
+    let Some(type_name) = (unsafe { string_from_ptr(desc.type_name) }) else {
+        return;
+    };
+
+    plugin_set.operations.push(PluginOperation {
+        type_name,
+        spawn_chance: desc.spawn_chance,
+        difficulty: desc.difficulty,
+        user_data: PluginUserData(desc.user_data),
+        execute: desc.execute,
+    });
+}
+
+extern "C" fn register_force_field(host: *mut c_void, desc: *const PlanckForceFieldDesc) {
+    let plugin_set = unsafe { &mut *(host as *mut PluginSet) };
+    let Some(desc) = (unsafe { desc.as_ref() }) else {
+        return;
+    };
+    let Some(name) = (unsafe { string_from_ptr(desc.name) }) else {
+        return;
+    };
+
+    let user_data = PluginUserData(desc.user_data);
+    let force_at = desc.force_at;
+    let force = move |pos: Vec2| {
+        let mut force = Vec2::new(0.0, 0.0);
+        force_at(user_data.ptr(), pos.x, pos.y, &mut force.x, &mut force.y);
+        force
+    };
+    plugin_set.force_fields.push(ForceField::new(name, Arc::new(force)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern "C" fn execute_ramp(_user_data: *mut c_void, _api: *const PlanckLevelApi, _builder: *mut PlanckLevelBuilder) {
+    }
+
+    extern "C" fn updraft(user_data: *mut c_void, _x: f32, y: f32, out_x: *mut f32, out_y: *mut f32) {
+        let strength = unsafe { *(user_data as *const f32) };
+        unsafe {
+            *out_x = 0.0;
+            *out_y = strength - y;
+        }
+    }
+
+    static UPDRAFT_STRENGTH: f32 = 10.0;
+
+    unsafe extern "C" fn register_test_plugin(registrar: *const PlanckPluginRegistrar) -> bool {
+        let registrar = unsafe { &*registrar };
+        if registrar.abi_version != PLUGIN_ABI_VERSION {
+            return false;
+        }
+
+        (registrar.register_operation)(registrar.host, &PlanckOperationDesc {
+            type_name: c"Test.Ramp".as_ptr(),
+            spawn_chance: 0.5,
+            difficulty: 2.0,
+            user_data: std::ptr::null_mut(),
+            execute: execute_ramp,
+        });
+        (registrar.register_force_field)(registrar.host, &PlanckForceFieldDesc {
+            name: c"Test.Updraft".as_ptr(),
+            user_data: &UPDRAFT_STRENGTH as *const f32 as *mut c_void,
+            force_at: updraft,
+        });
+        true
+    }
+
+    #[test]
+    fn test_register_plugin() {
+        let mut plugin_set = PluginSet::default();
+        assert!(unsafe { plugin_set.register_with(register_test_plugin) });
+
+        assert_eq!(plugin_set.operations.len(), 1);
+        assert_eq!(plugin_set.operations[0].type_name(), "Test.Ramp");
+        assert_eq!(plugin_set.operations[0].default_spawn_chance(), 0.5);
+        assert_eq!(plugin_set.operations[0].default_difficulty(), 2.0);
+
+        assert_eq!(plugin_set.force_fields.len(), 1);
+        assert_eq!(plugin_set.force_fields[0].name, "Test.Updraft");
+        assert_eq!(plugin_set.force_fields[0].force_at(Vec2::new(3.0, 4.0)), Vec2::new(0.0, 6.0));
+    }
+}
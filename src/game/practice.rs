@@ -0,0 +1,99 @@
+use std::collections::VecDeque;
+
+use crate::{game::{entity::entity_system::EntitySystem, physics_tuning::PhysicsTuning}, simulation::particles::{particle_vec::ParticleVec, simulation::Simulation}};
+
+/// How many quicksaves practice mode keeps before dropping the oldest
+pub const MAX_SAVE_STATES: usize = 5;
+
+/// Everything needed to put the run back exactly as it was when saved
+#[derive(Clone)]
+pub struct SaveState {
+    pub simulation: Simulation,
+    pub particle_vec: ParticleVec,
+    pub entity_system: EntitySystem,
+    pub total_time: f32,
+    pub time_remaining: f32,
+    pub checkpoints_reached: usize,
+    pub physics_tuning: PhysicsTuning, // what the simulation was tuned with when saved
+}
+
+/// Ring buffer of quicksaves. Loading uses the selected save, which is the newest until an older one is picked.
+pub struct SaveStateRing<T> {
+    saves: VecDeque<T>,
+    selected: usize,
+}
+
+impl<T> SaveStateRing<T> {
+    pub fn new() -> Self {
+        Self {
+            saves: VecDeque::new(),
+            selected: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.saves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.saves.is_empty()
+    }
+
+    /// 1 based position of the selected save, 1 is the oldest
+    pub fn selected_index(&self) -> usize {
+        self.selected + 1
+    }
+
+    pub fn push(&mut self, save: T) {
+        if self.saves.len() >= MAX_SAVE_STATES {
+            self.saves.pop_front();
+        }
+        self.saves.push_back(save);
+        self.selected = self.saves.len() - 1;
+    }
+
+    pub fn selected(&self) -> Option<&T> {
+        self.saves.get(self.selected)
+    }
+
+    /// Select the next older save, wrapping around to the newest
+    pub fn select_older(&mut self) -> Option<&T> {
+        if self.saves.is_empty() {
+            return None;
+        }
+        self.selected = if self.selected == 0 { self.saves.len() - 1 } else { self.selected - 1 };
+        self.selected()
+    }
+}
+
+impl<T> Default for SaveStateRing<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_state_ring() {
+        let mut ring = SaveStateRing::new();
+        assert!(ring.selected().is_none());
+        assert!(ring.select_older().is_none());
+
+        for i in 0..MAX_SAVE_STATES + 2 {
+            ring.push(i);
+        }
+        assert_eq!(ring.len(), MAX_SAVE_STATES);
+        assert_eq!(ring.selected(), Some(&(MAX_SAVE_STATES + 1)));
+        assert_eq!(ring.select_older(), Some(&MAX_SAVE_STATES));
+
+        // wraps from the oldest back round to the newest
+        for _ in 0..MAX_SAVE_STATES - 2 {
+            ring.select_older();
+        }
+        assert_eq!(ring.selected(), Some(&2));
+        assert_eq!(ring.select_older(), Some(&(MAX_SAVE_STATES + 1)));
+    }
+}
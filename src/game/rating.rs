@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+
+pub const RATING_PATH: &str = "rating.json";
+pub const INITIAL_RATING: f32 = 1500.0;
+const K_FACTOR: f32 = 32.0;
+
+/// Where I placed on one day's leaderboard
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DailyPlacement {
+    pub seed: String,
+    pub rank: usize, // 1 is first
+    pub num_players: usize,
+    pub field_rating: f32, // average rating of the other players, INITIAL_RATING for players we don't know
+}
+
+impl DailyPlacement {
+    /// Elo style update treating the placement as a result against the average of the field.
+    /// First place scores 1.0, last place 0.0.
+    pub fn rating_after(&self, rating: f32) -> f32 {
+        if self.num_players < 2 {
+            return rating;
+        }
+        let actual = (self.num_players - self.rank.min(self.num_players)) as f32 / (self.num_players - 1) as f32;
+        let expected = 1.0 / (1.0 + 10f32.powf((self.field_rating - rating) / 400.0));
+        rating + K_FACTOR * (actual - expected)
+    }
+}
+
+/// Daily placements stored locally, giving a rolling skill rating across days
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RatingHistory {
+    pub placements: Vec<DailyPlacement>,
+}
+
+impl RatingHistory {
+    pub fn load(path: &str) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)
+    }
+
+    /// Record the placement for a day, replacing any earlier placement for the same seed.
+    /// Returns true if anything changed.
+    pub fn record(&mut self, placement: DailyPlacement) -> bool {
+        match self.placements.iter_mut().find(|p| p.seed == placement.seed) {
+            Some(existing) if *existing == placement => false,
+            Some(existing) => {
+                *existing = placement;
+                true
+            }
+            None => {
+                self.placements.push(placement);
+                true
+            }
+        }
+    }
+
+    pub fn rating(&self) -> f32 {
+        self.placements.iter().fold(INITIAL_RATING, |rating, placement| placement.rating_after(rating))
+    }
+
+    /// How much the most recent placement moved the rating
+    pub fn last_change(&self) -> f32 {
+        match self.placements.split_last() {
+            Some((last, rest)) => {
+                let before = rest.iter().fold(INITIAL_RATING, |rating, placement| placement.rating_after(rating));
+                last.rating_after(before) - before
+            }
+            None => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placement(seed: &str, rank: usize, num_players: usize) -> DailyPlacement {
+        DailyPlacement { seed: seed.to_owned(), rank, num_players, field_rating: INITIAL_RATING }
+    }
+
+    #[test]
+    fn test_rating_after() {
+        assert_eq!(placement("a", 1, 1).rating_after(INITIAL_RATING), INITIAL_RATING);
+        assert_eq!(placement("a", 1, 2).rating_after(INITIAL_RATING), INITIAL_RATING + K_FACTOR * 0.5);
+        assert_eq!(placement("a", 3, 3).rating_after(INITIAL_RATING), INITIAL_RATING - K_FACTOR * 0.5);
+        assert_eq!(placement("a", 2, 3).rating_after(INITIAL_RATING), INITIAL_RATING);
+    }
+
+    #[test]
+    fn test_record_replaces_same_day() {
+        let mut history = RatingHistory::default();
+        assert!(history.record(placement("day1", 2, 2)));
+        assert!(history.record(placement("day1", 1, 2)));
+        assert!(!history.record(placement("day1", 1, 2)));
+        assert!(history.record(placement("day2", 1, 2)));
+        assert_eq!(history.placements.len(), 2);
+        assert!(history.rating() > INITIAL_RATING + K_FACTOR * 0.5);
+        assert!(history.last_change() > 0.0);
+    }
+}
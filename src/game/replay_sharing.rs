@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+
+use crate::{core::math::deterministic::MathMode, game::{ghost::{GhostReplay, MAX_GHOSTS}, leaderboard::LeaderboardEntry}};
+
+/// Players ask each other for their runs here so they can race them as ghosts
+pub const REPLAYS_CHANNEL: &str = "#planck-replays";
+const CHUNK_DATA_LEN: usize = 300; // keeps each message well inside the IRC line limit
+const MAX_CHUNKS: usize = 64;
+pub const MAX_REQUESTS_PER_SEED: usize = 10; // targets shift as the board fills up, don't keep asking forever
+
+// Protocol:
+// REPLAY_REQUEST seed={} user={}                             ask user for their best run on seed
+// REPLAY_CHUNK seed={} user={} time={} part={}/{} version={} game={} hash={} math={} data={}
+//                                                            one piece of the encoded inputs, see GhostReplay::encode_inputs.
+//                                                            version, game and hash are left out by format version 1 clients.
+//                                                            math is only sent for runs that weren't in the native math mode.
+//                                                            The state checksums stay behind, they'd more than double the size.
+
+pub fn request_message(seed: &str, user: &str) -> String {
+    format!("REPLAY_REQUEST seed={} user={}", seed, user)
+}
+
+/// The (seed, user) being asked for. The seed is used as a file name so only simple seeds are accepted.
+pub fn parse_request(message: &str) -> Option<(String, String)> {
+    if !message.starts_with("REPLAY_REQUEST") {
+        return None;
+    }
+    let mut seed = None;
+    let mut user = None;
+    for part in message.split_whitespace() {
+        if let Some(s) = part.strip_prefix("seed=") {
+            seed = Some(s.to_string());
+        } else if let Some(u) = part.strip_prefix("user=") {
+            user = Some(u.to_string());
+        }
+    }
+    let seed = seed.filter(|seed| !seed.is_empty() && seed.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))?;
+    Some((seed, user?))
+}
+
+/// Split a replay into messages. None if it is too long to send.
+pub fn chunk_messages(replay: &GhostReplay) -> Option<Vec<String>> {
+    let data = replay.encode_inputs();
+    // the encoding is ascii so it can be split anywhere
+    let chunks: Vec<&str> = if data.is_empty() {
+        vec![""]
+    } else {
+        data.as_bytes().chunks(CHUNK_DATA_LEN).map(|chunk| std::str::from_utf8(chunk).unwrap_or_default()).collect()
+    };
+    if chunks.len() > MAX_CHUNKS {
+        return None;
+    }
+    Some(chunks.iter().enumerate().map(|(i, chunk)| {
+        let hash = replay.level_hash.as_ref().map_or(String::new(), |hash| format!(" hash={}", hash));
+        let math = if replay.math_mode == MathMode::Native { String::new() } else { format!(" math={}", serde_json::to_string(&replay.math_mode).unwrap_or_default().trim_matches('"')) };
+        format!("REPLAY_CHUNK seed={} user={} time={:.3} part={}/{} version={} game={}{}{} data={}",
+            replay.seed, replay.user, replay.time, i + 1, chunks.len(), replay.format_version, replay.game_version, hash, math, chunk)
+    }).collect())
+}
+
+struct PartialReplay {
+    time: f32,
+    parts: Vec<Option<String>>,
+}
+
+/// Collects REPLAY_CHUNK messages until a replay is complete
+#[derive(Default)]
+pub struct ReplayAssembler {
+    partial: HashMap<(String, String), PartialReplay>, // (seed, user)
+}
+
+impl ReplayAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the replay once its last missing chunk arrives
+    pub fn handle_message(&mut self, message: &str) -> Option<GhostReplay> {
+        if !message.starts_with("REPLAY_CHUNK") {
+            return None;
+        }
+
+        let mut seed = None;
+        let mut user = None;
+        let mut time = None;
+        let mut part = None;
+        let mut format_version = 1;
+        let mut game_version = String::new();
+        let mut level_hash = None;
+        let mut math_mode = MathMode::Native;
+        // stop at the data, it is always last
+        for field in message.split(" data=").next().unwrap_or_default().split_whitespace() {
+            if let Some(s) = field.strip_prefix("seed=") {
+                seed = Some(s.to_string());
+            } else if let Some(u) = field.strip_prefix("user=") {
+                user = Some(u.to_string());
+            } else if let Some(t) = field.strip_prefix("time=") {
+                time = t.parse::<f32>().ok();
+            } else if let Some(p) = field.strip_prefix("part=") {
+                part = p.split_once('/').and_then(|(i, n)| Some((i.parse::<usize>().ok()?, n.parse::<usize>().ok()?)));
+            } else if let Some(v) = field.strip_prefix("version=") {
+                format_version = v.parse().ok()?;
+            } else if let Some(g) = field.strip_prefix("game=") {
+                game_version = g.to_string();
+            } else if let Some(h) = field.strip_prefix("hash=") {
+                level_hash = Some(h.to_string());
+            } else if let Some(m) = field.strip_prefix("math=") {
+                math_mode = serde_json::from_str(&format!("\"{}\"", m)).ok()?;
+            }
+        }
+        // data is last and may be empty
+        let data = message.split_once(" data=").map_or("", |(_, data)| data.trim());
+        let (seed, user, time, (index, num_parts)) = (seed?, user?, time?, part?);
+        if index == 0 || index > num_parts || num_parts > MAX_CHUNKS {
+            return None;
+        }
+
+        let key = (seed, user);
+        let partial = self.partial.entry(key.clone()).or_insert_with(|| PartialReplay { time, parts: vec![None; num_parts] });
+        // a different time or part count means they sent a newer run, start again
+        if partial.parts.len() != num_parts || partial.time != time {
+            *partial = PartialReplay { time, parts: vec![None; num_parts] };
+        }
+        partial.parts[index - 1] = Some(data.to_string());
+
+        if partial.parts.iter().any(|p| p.is_none()) {
+            return None;
+        }
+        let partial = self.partial.remove(&key)?;
+        let data: String = partial.parts.into_iter().flatten().collect();
+        let (inputs, axis_inputs) = GhostReplay::decode_inputs(&data).ok()?;
+        let (seed, user) = key;
+        Some(GhostReplay { user, seed, time: partial.time, inputs, axis_inputs, format_version, game_version, level_hash, math_mode, checksums: vec![] })
+    }
+
+    /// Forget half received replays for other seeds
+    pub fn retain_seed(&mut self, seed: &str) {
+        self.partial.retain(|(s, _), _| s == seed);
+    }
+}
+
+/// Who to race: the players just ahead of where I placed yesterday, as that is roughly my level.
+/// Falls back to the top of the board when I have no placement or not enough players are ahead of it.
+pub fn ghost_targets(entries: &[LeaderboardEntry], yesterday_rank: Option<usize>) -> Vec<String> {
+    let mut others: Vec<&LeaderboardEntry> = vec![];
+    for entry in entries.iter().filter(|entry| !entry.is_me) {
+        if !others.iter().any(|other| other.name == entry.name) {
+            others.push(entry); // entries are sorted, so this is each player's best
+        }
+    }
+
+    if let Some(rank) = yesterday_rank {
+        let ahead: Vec<&LeaderboardEntry> = others.iter().copied().filter(|entry| entry.rank < rank).collect();
+        if ahead.len() >= MAX_GHOSTS {
+            return ahead[ahead.len() - MAX_GHOSTS..].iter().map(|entry| entry.name.clone()).collect();
+        }
+    }
+    others.iter().take(MAX_GHOSTS).map(|entry| entry.name.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{engine::app::event_system::KeyCodeType, game::{ghost::{GhostInput, REPLAY_FORMAT_VERSION}, leaderboard::ScoreAnnotations}};
+
+    fn replay(num_inputs: u32) -> GhostReplay {
+        GhostReplay {
+            user: String::from("bob"),
+            seed: String::from("2025-01-01"),
+            time: 12.5,
+            inputs: (0..num_inputs).map(|i| GhostInput { frame: i * 10, key_code: KeyCodeType::KeyX, pressed: i % 2 == 0 }).collect(),
+            axis_inputs: vec![],
+            format_version: REPLAY_FORMAT_VERSION,
+            game_version: String::from("0.1.0"),
+            level_hash: Some(String::from("0123456789abcdef")),
+            math_mode: MathMode::Native,
+            checksums: vec![],
+        }
+    }
+
+    #[test]
+    fn test_request_round_trip() {
+        assert_eq!(parse_request(&request_message("2025-01-01", "bob")), Some((String::from("2025-01-01"), String::from("bob"))));
+        assert_eq!(parse_request("REPLAY_REQUEST seed=2025-01-01"), None);
+        assert_eq!(parse_request("REPLAY_REQUEST seed=../settings user=bob"), None);
+    }
+
+    #[test]
+    fn test_chunks_reassemble_in_any_order() {
+        let replay = replay(100);
+        let mut messages = chunk_messages(&replay).unwrap();
+        assert!(messages.len() > 1);
+        assert!(messages.iter().all(|message| message.len() < 450));
+        messages.reverse();
+
+        let mut assembler = ReplayAssembler::new();
+        let last = messages.pop().unwrap();
+        for message in &messages {
+            assert_eq!(assembler.handle_message(message), None);
+        }
+        assert_eq!(assembler.handle_message(&last), Some(replay));
+
+        let empty = GhostReplay { inputs: vec![], ..self::replay(0) };
+        let messages = chunk_messages(&empty).unwrap();
+        assert_eq!(ReplayAssembler::new().handle_message(&messages[0]), Some(empty));
+
+        let deterministic = GhostReplay { math_mode: MathMode::Deterministic, ..self::replay(0) };
+        let messages = chunk_messages(&deterministic).unwrap();
+        assert!(messages[0].contains(" math=deterministic "));
+        assert_eq!(ReplayAssembler::new().handle_message(&messages[0]), Some(deterministic));
+
+        // a format version 1 client only sends the inputs
+        let v1 = ReplayAssembler::new().handle_message("REPLAY_CHUNK seed=2025-01-01 user=bob time=12.500 part=1/1 data=12.KeyX.d").unwrap();
+        assert_eq!((v1.format_version, v1.game_version.as_str(), v1.level_hash), (1, "", None));
+        assert_eq!(v1.inputs.len(), 1);
+    }
+
+    fn entry(rank: usize, name: &str, is_me: bool) -> LeaderboardEntry {
+        LeaderboardEntry { rank, name: name.to_owned(), time: rank as f32, checkpoints: None, is_current_run: false, is_me, beats_my_best: false, annotations: ScoreAnnotations::default() }
+    }
+
+    #[test]
+    fn test_ghost_targets() {
+        let entries: Vec<LeaderboardEntry> = (1..=8).map(|rank| entry(rank, &format!("p{}", rank), rank == 5)).collect();
+        assert_eq!(ghost_targets(&entries, None), vec!["p1", "p2", "p3"]);
+        // placed 7th yesterday, race the three just ahead of that
+        assert_eq!(ghost_targets(&entries, Some(7)), vec!["p3", "p4", "p6"]);
+        // not enough players ahead, use the top
+        assert_eq!(ghost_targets(&entries, Some(2)), vec!["p1", "p2", "p3"]);
+    }
+}
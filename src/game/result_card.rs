@@ -0,0 +1,327 @@
+use std::path::{Path, PathBuf};
+
+use image::{imageops, Rgba, RgbaImage};
+
+use crate::{core::math::vec2::Vec2, engine::renderer::glyph_atlas::{glyph, GLYPH_HEIGHT, GLYPH_WIDTH}};
+
+pub const RESULT_CARDS_DIR: &str = "result_cards";
+pub const WIDTH: u32 = 640;
+pub const HEIGHT: u32 = 360;
+const PADDING: u32 = 24;
+const GAP: u32 = 12; // between the rows of the card
+const BAR_GAP: u32 = 4; // between the bars of the splits graph
+
+const TITLE_SCALE: u32 = 4; // screen pixels per font pixel
+const TIME_SCALE: u32 = 6;
+const RANK_SCALE: u32 = 4;
+const RECAP_SCALE: u32 = 2;
+const SILHOUETTE_HEIGHT: u32 = 60;
+const SILHOUETTE_BASE: u32 = 3; // pixels of ground under the lowest point so it still shows
+
+const BACKGROUND: Rgba<u8> = Rgba([20, 28, 40, 255]);
+const TEXT: Rgba<u8> = Rgba([235, 240, 245, 255]);
+const DIM: Rgba<u8> = Rgba([130, 140, 160, 255]);
+const ACCENT: Rgba<u8> = Rgba([255, 200, 60, 255]);
+const AHEAD: Rgba<u8> = Rgba([80, 200, 120, 255]); // a sector at least as quick as the best before this run
+const BEHIND: Rgba<u8> = Rgba([220, 90, 80, 255]);
+const GROUND: Rgba<u8> = Rgba([90, 110, 140, 255]);
+const SCENE_SHOWING: f32 = 0.35; // how much of the screenshot shows through the background, dim enough to read over
+
+/// A rectangle of the card in pixels from the top left
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// Shrunk by the same amount on every side
+    pub fn inset(self, by: u32) -> Self {
+        Self::new(self.x + by, self.y + by, self.width.saturating_sub(by * 2), self.height.saturating_sub(by * 2))
+    }
+
+    pub fn bottom(self) -> u32 {
+        self.y + self.height
+    }
+}
+
+/// How tall a row of the card is
+#[derive(Debug, Clone, Copy)]
+pub enum RowHeight {
+    Fixed(u32),
+    Fill, // an equal share of whatever the fixed rows leave
+}
+
+/// The card's layout engine, such as it is: rows stacked down the area with a gap between each
+pub fn stack(area: Rect, gap: u32, rows: &[RowHeight]) -> Vec<Rect> {
+    let fixed: u32 = rows.iter().map(|row| match row { RowHeight::Fixed(height) => *height, RowHeight::Fill => 0 }).sum();
+    let fills = rows.iter().filter(|row| matches!(row, RowHeight::Fill)).count() as u32;
+    let gaps = gap * (rows.len() as u32).saturating_sub(1);
+    let spare = area.height.saturating_sub(fixed + gaps);
+    let mut y = area.y;
+    rows.iter().map(|row| {
+        let height = match row {
+            RowHeight::Fixed(height) => *height,
+            RowHeight::Fill => spare / fills,
+        };
+        let rect = Rect::new(area.x, y, area.width, height);
+        y += height + gap;
+        rect
+    }).collect()
+}
+
+/// Width in pixels of a line of text drawn at scale
+pub fn text_width(text: &str, scale: u32) -> u32 {
+    ((GLYPH_WIDTH + 1) * text.chars().count() as u32).saturating_sub(1) * scale
+}
+
+fn fill_rect(image: &mut RgbaImage, rect: Rect, colour: Rgba<u8>) {
+    for y in rect.y..rect.bottom().min(image.height()) {
+        for x in rect.x..(rect.x + rect.width).min(image.width()) {
+            image.put_pixel(x, y, colour);
+        }
+    }
+}
+
+/// Text in the built in font with its top left at x, y, cut short to fit in max_width
+fn draw_text(image: &mut RgbaImage, x: u32, y: u32, scale: u32, text: &str, max_width: u32, colour: Rgba<u8>) {
+    let fits = (max_width / scale + 1) / (GLYPH_WIDTH + 1);
+    for (i, c) in text.chars().take(fits as usize).enumerate() {
+        let x0 = x + i as u32 * (GLYPH_WIDTH + 1) * scale;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                    fill_rect(image, Rect::new(x0 + column * scale, y + row as u32 * scale, scale, scale), colour);
+                }
+            }
+        }
+    }
+}
+
+/// The screenshot scaled to cover the card, cropped evenly off whichever sides overhang, and dimmed into the background
+fn draw_scene(image: &mut RgbaImage, scene: &RgbaImage) {
+    if scene.width() == 0 || scene.height() == 0 {
+        return;
+    }
+    let scale = (WIDTH as f32 / scene.width() as f32).max(HEIGHT as f32 / scene.height() as f32);
+    let (width, height) = (((scene.width() as f32 * scale).ceil() as u32).max(WIDTH), ((scene.height() as f32 * scale).ceil() as u32).max(HEIGHT));
+    let scaled = imageops::resize(scene, width, height, imageops::FilterType::Triangle);
+    let (left, top) = ((width - WIDTH) / 2, (height - HEIGHT) / 2);
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let shot = scaled.get_pixel(left + x, top + y);
+        for channel in 0..3 {
+            pixel[channel] = (BACKGROUND[channel] as f32 * (1.0 - SCENE_SHOWING) + shot[channel] as f32 * SCENE_SHOWING).round() as u8;
+        }
+    }
+}
+
+/// The picture to share after a run: the level, the time and where it placed, how each sector went,
+/// the level's outline and how the session's gone so far
+#[derive(Debug, Clone, Default)]
+pub struct ResultCard {
+    pub level_name: String,
+    pub time: f32,
+    pub checkpoints: Option<u32>, // time attack only
+    pub rank: Option<(usize, usize)>, // place on the leaderboard and how many are on it, if the run was posted
+    pub sectors: Vec<Option<f32>>, // this run's time through each sector, see RunSplits::sector_time
+    pub best_sectors: Vec<Option<f32>>, // fastest through each sector before this run
+    pub ground: Vec<(Vec2, f32)>, // position and radius of the level's static particles, for its outline
+    pub attempts: usize, // runs at this board this session
+    pub session_best: Option<f32>,
+    pub scene: Option<RgbaImage>, // the finish as it was drawn, read back from the scene target, for the background
+}
+
+impl ResultCard {
+    /// Rows for the level name, time and rank, splits graph, level outline and recap
+    fn layout() -> Vec<Rect> {
+        stack(Rect::new(0, 0, WIDTH, HEIGHT).inset(PADDING), GAP, &[
+            RowHeight::Fixed(GLYPH_HEIGHT * TITLE_SCALE),
+            RowHeight::Fixed(GLYPH_HEIGHT * TIME_SCALE),
+            RowHeight::Fill,
+            RowHeight::Fixed(SILHOUETTE_HEIGHT),
+            RowHeight::Fixed(GLYPH_HEIGHT * RECAP_SCALE),
+        ])
+    }
+
+    /// Drawn on the CPU in the built in font, over the screenshot of the scene when there is one
+    pub fn render(&self) -> RgbaImage {
+        let mut image = RgbaImage::from_pixel(WIDTH, HEIGHT, BACKGROUND);
+        if let Some(scene) = &self.scene {
+            draw_scene(&mut image, scene);
+        }
+        let rows = Self::layout();
+
+        draw_text(&mut image, rows[0].x, rows[0].y, TITLE_SCALE, &self.level_name, rows[0].width, TEXT);
+
+        // the time on the left and the rank lined up with its bottom on the right
+        let rank = self.rank.map(|(place, of)| format!("#{} of {}", place, of)).unwrap_or_default();
+        let rank_width = text_width(&rank, RANK_SCALE);
+        let time_width = rows[1].width.saturating_sub(rank_width + GAP);
+        draw_text(&mut image, rows[1].x, rows[1].y, TIME_SCALE, &format!("{:.3}", self.time), time_width, ACCENT);
+        let rank_x = rows[1].x + rows[1].width - rank_width;
+        draw_text(&mut image, rank_x, rows[1].bottom() - GLYPH_HEIGHT * RANK_SCALE, RANK_SCALE, &rank, rank_width, TEXT);
+
+        self.draw_splits(&mut image, rows[2]);
+        self.draw_silhouette(&mut image, rows[3]);
+        draw_text(&mut image, rows[4].x, rows[4].y, RECAP_SCALE, &self.recap(), rows[4].width, DIM);
+        image
+    }
+
+    /// A bar for each sector, green where it was at least as quick as the best before and red where it wasn't.
+    /// The best is marked across each bar.
+    fn draw_splits(&self, image: &mut RgbaImage, rect: Rect) {
+        let num_sectors = self.sectors.len() as u32;
+        if num_sectors == 0 {
+            return;
+        }
+        let longest = self.sectors.iter().chain(self.best_sectors.iter()).flatten().fold(0.0f32, |longest, t| longest.max(*t));
+        if longest <= 0.0 {
+            return;
+        }
+        let bar_width = (rect.width.saturating_sub(BAR_GAP * (num_sectors - 1)) / num_sectors).max(1);
+        let height_of = |time: f32| ((time / longest) * rect.height as f32) as u32;
+        for (i, time) in self.sectors.iter().enumerate() {
+            let x = rect.x + i as u32 * (bar_width + BAR_GAP);
+            let best = self.best_sectors.get(i).copied().flatten();
+            if let Some(time) = time {
+                let colour = if best.is_none_or(|best| *time <= best) { AHEAD } else { BEHIND };
+                let height = height_of(*time).max(1);
+                fill_rect(image, Rect::new(x, rect.bottom() - height, bar_width, height), colour);
+            }
+            if let Some(best) = best {
+                let y = rect.bottom() - height_of(best).max(2);
+                fill_rect(image, Rect::new(x, y, bar_width, 2), TEXT);
+            }
+        }
+        fill_rect(image, Rect::new(rect.x, rect.bottom() - 1, rect.width, 1), DIM);
+    }
+
+    /// The level side on, scaled to fit and filled in below its top surface
+    fn draw_silhouette(&self, image: &mut RgbaImage, rect: Rect) {
+        let Some((first, _)) = self.ground.first() else { return };
+        let (min, max) = self.ground.iter().fold((*first, *first), |(min, max), (p, _)| (Vec2::min(min, *p), Vec2::max(max, *p)));
+        let size = Vec2::max(max - min, Vec2::new(f32::EPSILON, f32::EPSILON));
+        let scale = (rect.width as f32 / size.x).min(rect.height.saturating_sub(SILHOUETTE_BASE) as f32 / size.y);
+        let left = (rect.width as f32 - size.x * scale) * 0.5;
+        let column_at = |x: f32| ((left + (x - min.x) * scale).max(0.0) as u32).min(rect.width.saturating_sub(1));
+
+        // the highest point in each column of pixels, each particle covering the columns it's as wide as
+        let mut tops: Vec<Option<u32>> = vec![None; rect.width as usize];
+        for (p, radius) in &self.ground {
+            let y = rect.bottom().saturating_sub(((p.y - min.y) * scale) as u32 + SILHOUETTE_BASE);
+            for column in column_at(p.x - radius)..=column_at(p.x + radius) {
+                let top = &mut tops[column as usize];
+                *top = Some(top.map_or(y, |top| top.min(y)));
+            }
+        }
+        for (column, top) in tops.iter().enumerate() {
+            if let Some(top) = top {
+                fill_rect(image, Rect::new(rect.x + column as u32, *top, 1, rect.bottom() - top), GROUND);
+            }
+        }
+    }
+
+    /// The line along the bottom of the card about the session so far
+    pub fn recap(&self) -> String {
+        let mut recap = format!("Attempt {} this session", self.attempts);
+        if let Some(best) = self.session_best {
+            recap.push_str(&format!(", best {:.3}", best));
+        }
+        if let Some(checkpoints) = self.checkpoints {
+            recap.push_str(&format!(", {} checkpoints", checkpoints));
+        }
+        recap
+    }
+
+    /// The card in a line of text, for pasting into chat
+    pub fn summary(&self) -> String {
+        let rank = self.rank.map(|(place, of)| format!(" (#{} of {})", place, of)).unwrap_or_default();
+        format!("{}: {:.3}s{} in Planck Time Trials", self.level_name, self.time, rank)
+    }
+
+    /// Level name and time, with anything that isn't a letter or number made a dash, eg. "misty-volcano-run-412_12.345s.png"
+    pub fn file_name(&self) -> String {
+        let mut name = String::new();
+        for c in self.level_name.to_lowercase().chars() {
+            if c.is_ascii_alphanumeric() {
+                name.push(c);
+            } else if !name.is_empty() && !name.ends_with('-') {
+                name.push('-');
+            }
+        }
+        format!("{}_{:.3}s.png", name.trim_end_matches('-'), self.time)
+    }
+
+    /// Render the card and save it as a png in dir, which is made if it isn't there. Returns where it went.
+    pub fn save(&self, dir: &Path) -> image::ImageResult<PathBuf> {
+        std::fs::create_dir_all(dir).map_err(image::ImageError::IoError)?;
+        let path = dir.join(self.file_name());
+        self.render().save(&path)?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stack() {
+        let rows = stack(Rect::new(10, 10, 100, 100), 5, &[RowHeight::Fixed(20), RowHeight::Fill, RowHeight::Fixed(10), RowHeight::Fill]);
+        // 100 less 30 fixed and 15 of gaps leaves 55, shared by the two fill rows
+        assert_eq!(rows, vec![Rect::new(10, 10, 100, 20), Rect::new(10, 35, 100, 27), Rect::new(10, 67, 100, 10), Rect::new(10, 82, 100, 27)]);
+        assert_eq!(Rect::new(0, 0, 10, 10).inset(6), Rect::new(6, 6, 0, 0));
+    }
+
+    #[test]
+    fn test_result_card() {
+        let card = ResultCard {
+            level_name: "Misty Volcano Run #412".to_owned(),
+            time: 42.5,
+            rank: Some((3, 17)),
+            sectors: vec![Some(10.0), Some(20.0), Some(12.5)],
+            best_sectors: vec![Some(11.0), Some(15.0), None],
+            ground: (0..200).map(|i| (Vec2::new(i as f32 * 0.5, if i < 100 { 0.0 } else { 5.0 }), 0.25)).collect(),
+            attempts: 4,
+            session_best: Some(41.0),
+            ..Default::default()
+        };
+        assert_eq!(card.file_name(), "misty-volcano-run-412_42.500s.png");
+        assert_eq!(card.summary(), "Misty Volcano Run #412: 42.500s (#3 of 17) in Planck Time Trials");
+        assert_eq!(card.recap(), "Attempt 4 this session, best 41.000");
+
+        let image = card.render();
+        assert_eq!(image.dimensions(), (WIDTH, HEIGHT));
+        let count = |colour: Rgba<u8>| image.pixels().filter(|p| **p == colour).count();
+        assert!(count(ACCENT) > 0 && count(TEXT) > 0 && count(DIM) > 0);
+        // the first and last sectors beat or set the best, the second didn't
+        assert!(count(AHEAD) > count(BEHIND) && count(BEHIND) > 0);
+        // the level steps up half way along, so the right half of the silhouette is taller
+        let silhouette = ResultCard::layout()[3];
+        let column_height = |x: u32| (silhouette.y..silhouette.bottom()).filter(|y| *image.get_pixel(x, *y) == GROUND).count();
+        assert!(column_height(WIDTH / 2 + 50) > column_height(WIDTH / 2 - 50) + 10);
+    }
+
+    #[test]
+    fn test_scene_behind_the_card() {
+        // a wide screenshot, red on the left half and green on the right
+        let scene = RgbaImage::from_fn(1920, 1080, |x, _| if x < 960 { Rgba([255, 0, 0, 255]) } else { Rgba([0, 255, 0, 255]) });
+        let card = ResultCard { level_name: "Misty Volcano Run #412".to_owned(), time: 42.5, scene: Some(scene), ..Default::default() };
+        let image = card.render();
+        assert_eq!(image.dimensions(), (WIDTH, HEIGHT));
+
+        // dimmed into the background down the sides, where nothing is drawn over it
+        let (left, right) = (*image.get_pixel(2, HEIGHT / 2), *image.get_pixel(WIDTH - 3, HEIGHT / 2));
+        assert!(left[0] > BACKGROUND[0] && left[1] < right[1]);
+        assert!(right[1] > BACKGROUND[1] && right[0] < left[0]);
+        assert!(left[0] < 255 && right[1] < 255);
+        // the text is still drawn over it
+        assert!(image.pixels().any(|p| *p == ACCENT));
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{game::entity::entities::car_entity::PEAK_SLIP, simulation::particles::particle_vec::ParticleVec};
+
+const MIN_HIT_SPEED: f32 = 1.0; // m/s a wheel particle hits something at before it's felt
+const FULL_HIT_SPEED: f32 = 8.0; // m/s for the strong motor to go flat out
+const HIT_FADE_RATE: f32 = 10.0; // per second, how quickly a jolt dies away
+const MAX_SLIP_RUMBLE: f32 = 0.5; // the weak motor's strength with the wheels spinning on the spot
+
+/// How hard the gamepad shakes, cycled with the gamepad's Select button and remembered in the settings
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RumbleIntensity {
+    Off,
+    Low,
+    #[default]
+    Full,
+}
+
+impl RumbleIntensity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RumbleIntensity::Off => "Off",
+            RumbleIntensity::Low => "Low",
+            RumbleIntensity::Full => "Full",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            RumbleIntensity::Off => RumbleIntensity::Low,
+            RumbleIntensity::Low => RumbleIntensity::Full,
+            RumbleIntensity::Full => RumbleIntensity::Off,
+        }
+    }
+
+    pub fn scale(&self) -> f32 {
+        match self {
+            RumbleIntensity::Off => 0.0,
+            RumbleIntensity::Low => 0.4,
+            RumbleIntensity::Full => 1.0,
+        }
+    }
+}
+
+/// Works out how the gamepad should shake from what the car goes through: the strong motor jolts when the wheels hit
+/// something, hardest when landing with both of them, and the weak one buzzes while the wheels spin.
+#[derive(Default)]
+pub struct Rumble {
+    pub strong: f32, // 0 to 1
+    pub weak: f32, // 0 to 1
+    touching: HashSet<usize>, // wheel particles that were touching something last step
+}
+
+impl Rumble {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.strong = 0.0;
+        self.weak = 0.0;
+        self.touching.clear();
+    }
+
+    /// Update from this step's contacts, each pair is a wheel particle and what it's touching, and the car's slip
+    pub fn update(&mut self, contact_pairs: &[(usize, usize)], particle_vec: &ParticleVec, slip: f32, time_delta: f32) {
+        let landing = self.touching.is_empty();
+        let mut hit = 0.0f32; // how fast the hardest new contact closed, or all of them together when landing
+        let mut touching = HashSet::new();
+        for &(wheel, other) in contact_pairs {
+            if touching.insert(wheel) && !self.touching.contains(&wheel) {
+                let wheel_particle = &particle_vec[wheel];
+                let other_particle = &particle_vec[other];
+                let normal = (wheel_particle.pos - other_particle.pos).normalize();
+                let closing = (-(wheel_particle.vel - other_particle.vel).dot(normal)).max(0.0);
+                hit = if landing { hit + closing } else { hit.max(closing) };
+            }
+        }
+        self.touching = touching;
+
+        let jolt = ((hit - MIN_HIT_SPEED) / (FULL_HIT_SPEED - MIN_HIT_SPEED)).clamp(0.0, 1.0);
+        self.strong = (self.strong * (-HIT_FADE_RATE * time_delta).exp()).max(jolt);
+        self.weak = ((slip - PEAK_SLIP) / (1.0 - PEAK_SLIP)).clamp(0.0, 1.0) * MAX_SLIP_RUMBLE;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::math::vec2::Vec2, simulation::particles::particle::Particle};
+
+    #[test]
+    fn test_landing_jolts_then_fades() {
+        let mut particle_vec = ParticleVec::new();
+        particle_vec.push(*Particle::default().set_radius(0.1).set_static(true).set_pos(Vec2::new(0.0, 0.0)));
+        particle_vec.push(*Particle::default().set_radius(0.08).set_pos(Vec2::new(0.0, 0.18)).set_vel(Vec2::new(0.0, -6.0)));
+        let mut rumble = Rumble::new();
+
+        rumble.update(&[(1, 0)], &particle_vec, 0.0, 0.005);
+        assert!(rumble.strong > 0.5);
+        let jolt = rumble.strong;
+
+        // staying on the ground doesn't jolt again
+        rumble.update(&[(1, 0)], &particle_vec, 0.0, 0.1);
+        assert!(rumble.strong < jolt * 0.5);
+        assert_eq!(rumble.weak, 0.0);
+
+        // spinning the wheels buzzes
+        rumble.update(&[(1, 0)], &particle_vec, 1.0, 0.005);
+        assert_eq!(rumble.weak, MAX_SLIP_RUMBLE);
+    }
+}
@@ -0,0 +1,221 @@
+use std::path::PathBuf;
+
+use crate::{
+    core::math::{vec2::Vec2, vec4::Vec4},
+    engine::renderer::instance_renderer::Instance,
+    simulation::particles::{
+        particle::{Particle, Phase},
+        particle_vec::ParticleVec,
+        simulation::Simulation,
+        surface_material::MaterialId,
+    },
+};
+
+const BRUSH_RADIUS: f32 = 0.1; // radius of painted particles
+const BRUSH_MASS: f32 = 1.0;
+const IMPULSE_SPEED: f32 = 5.0; // m/s added to the selection towards the cursor
+const SELECTED_COLOUR: Vec4 = Vec4(cgmath::Vector4::new(1.0, 0.9, 0.2, 1.0));
+const OUTLINE_RADIUS: f32 = 0.03; // dots the selection rectangle is drawn with
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SandboxTool {
+    Select, // drag a rectangle to select particles
+    Brush, // drag to paint new particles
+}
+
+/// Tools for playing with the demo scenes: select particles with a rectangle to delete, freeze, change
+/// the material of or push them, or paint new particles in with the brush
+pub struct Sandbox {
+    pub tool: SandboxTool,
+    pub selection: Vec<usize>,
+    pub material: MaterialId, // what painted particles are made of, and what the selection is changed to
+    pub scene_path: Option<PathBuf>, // the scene file last loaded or saved, see SceneFile
+    cursor: Vec2, // world position under the mouse
+    drag_start: Option<Vec2>, // where the left button went down, while it's held
+}
+
+impl Sandbox {
+    pub fn new(scene_path: Option<PathBuf>) -> Self {
+        Self {
+            tool: SandboxTool::Select,
+            selection: vec![],
+            material: 0,
+            scene_path,
+            cursor: Vec2::new(0.0, 0.0),
+            drag_start: None,
+        }
+    }
+
+    pub fn toggle_tool(&mut self) {
+        self.tool = match self.tool {
+            SandboxTool::Select => SandboxTool::Brush,
+            SandboxTool::Brush => SandboxTool::Select,
+        };
+        self.drag_start = None;
+    }
+
+    pub fn cursor_moved(&mut self, cursor: Vec2, sim: &mut Simulation) {
+        self.cursor = cursor;
+        if self.tool == SandboxTool::Brush && self.drag_start.is_some() {
+            self.paint(sim);
+        }
+    }
+
+    /// The left mouse button went down or up
+    pub fn button(&mut self, pressed: bool, sim: &mut Simulation) {
+        match (pressed, self.drag_start) {
+            (true, _) => {
+                self.drag_start = Some(self.cursor);
+                if self.tool == SandboxTool::Brush {
+                    self.paint(sim);
+                }
+            }
+            (false, Some(start)) => {
+                self.drag_start = None;
+                if self.tool == SandboxTool::Select {
+                    self.select(start, self.cursor, &sim.particles);
+                }
+            }
+            (false, None) => {}
+        }
+    }
+
+    /// Select the particles whose centres are inside the rectangle with corners a and b
+    pub fn select(&mut self, a: Vec2, b: Vec2, particles: &ParticleVec) {
+        let (min, max) = (Vec2::new(a.x.min(b.x), a.y.min(b.y)), Vec2::new(a.x.max(b.x), a.y.max(b.y)));
+        self.selection = particles.iter().enumerate()
+            .filter(|(_, p)| p.pos.x >= min.x && p.pos.x <= max.x && p.pos.y >= min.y && p.pos.y <= max.y)
+            .map(|(i, _)| i)
+            .collect();
+    }
+
+    /// Add a particle under the cursor, unless it would overlap one already there
+    fn paint(&mut self, sim: &mut Simulation) {
+        if sim.particles.iter().any(|p| (p.pos - self.cursor).magnitude() < p.radius + BRUSH_RADIUS) {
+            return;
+        }
+        let colour = sim.materials.get(self.material).colour;
+        sim.add_particle(*Particle::default().set_radius(BRUSH_RADIUS).set_pos(self.cursor).set_mass_2(BRUSH_MASS).set_material(self.material).set_colour(colour));
+    }
+
+    /// Removes the selection, with any rigid bodies it's part of. Returns how many particles went.
+    pub fn delete(&mut self, sim: &mut Simulation) -> usize {
+        let removed = sim.remove_particles(&self.selection);
+        self.selection.clear();
+        removed
+    }
+
+    /// Pins the selection where it is. Rigid bodies and gases can't have a point of infinite mass, so they are left moving.
+    pub fn freeze(&self, sim: &mut Simulation) {
+        for &i in &self.selection {
+            let p = &mut sim.particles[i];
+            if is_in_rigid_body(p) || p.phase == Phase::Gas {
+                continue;
+            }
+            p.set_static(true);
+            p.vel = Vec2::new(0.0, 0.0);
+        }
+    }
+
+    /// Moves on to the next surface material, changing the selection to it
+    pub fn next_material(&mut self, sim: &mut Simulation) {
+        self.material = ((self.material as usize + 1) % sim.materials.0.len()) as MaterialId;
+        let colour = sim.materials.get(self.material).colour;
+        for &i in &self.selection {
+            sim.particles[i].set_material(self.material).set_colour(colour);
+        }
+    }
+
+    /// Throws the selection towards the cursor
+    pub fn impulse(&self, sim: &mut Simulation) {
+        if self.selection.is_empty() {
+            return;
+        }
+        let centre = self.selection.iter().fold(Vec2::new(0.0, 0.0), |total, &i| total + sim.particles[i].pos) / self.selection.len() as f32;
+        let towards = self.cursor - centre;
+        if towards.magnitude() < f32::EPSILON {
+            return;
+        }
+        let impulse = towards.normalize() * IMPULSE_SPEED;
+        for &i in &self.selection {
+            let p = &mut sim.particles[i];
+            if p.imass > 0.0 {
+                p.vel += impulse;
+            }
+        }
+    }
+
+    /// Highlight the selected particles in instances, which has one per particle at the start,
+    /// then add the rectangle being dragged out as a dotted outline
+    pub fn extend_instances(&self, instances: &mut Vec<Instance>) {
+        for &i in &self.selection {
+            instances[i].colour = SELECTED_COLOUR;
+        }
+
+        let Some(start) = self.drag_start.filter(|_| self.tool == SandboxTool::Select) else { return };
+        let corners = [start, Vec2::new(self.cursor.x, start.y), self.cursor, Vec2::new(start.x, self.cursor.y)];
+        for k in 0..corners.len() {
+            let (from, to) = (corners[k], corners[(k + 1) % corners.len()]);
+            let num_dots = ((to - from).magnitude() / (OUTLINE_RADIUS * 4.0)) as usize;
+            for d in 0..num_dots {
+                let pos = from + (to - from) * (d as f32 / num_dots as f32);
+                instances.push(Instance { position: cgmath::Vector3 { x: pos.x, y: pos.y, z: 0.0 }, angle: 0.0, colour: SELECTED_COLOUR, radius: OUTLINE_RADIUS });
+            }
+        }
+    }
+}
+
+fn is_in_rigid_body(p: &Particle) -> bool {
+    p.phase == Phase::Solid && p.body >= 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::math::random::Random;
+
+    #[test]
+    fn test_select_and_operate() {
+        let mut sim = Simulation::new(Random::seed_from_str("sandbox"));
+        for x in [0.0, 1.0, 2.0] {
+            sim.add_particle(*Particle::default().set_pos(Vec2::new(x, 0.0)).set_radius(0.1).set_mass_2(1.0));
+        }
+        let mut sandbox = Sandbox::new(None);
+
+        // dragging from either corner selects what's inside
+        sandbox.cursor_moved(Vec2::new(1.5, 1.0), &mut sim);
+        sandbox.button(true, &mut sim);
+        sandbox.cursor_moved(Vec2::new(-0.5, -1.0), &mut sim);
+        sandbox.button(false, &mut sim);
+        assert_eq!(sandbox.selection, vec![0, 1]);
+
+        sandbox.cursor_moved(Vec2::new(-2.0, 0.0), &mut sim);
+        sandbox.impulse(&mut sim);
+        assert_eq!(sim.particles[0].vel, Vec2::new(-IMPULSE_SPEED, 0.0));
+
+        sandbox.next_material(&mut sim);
+        assert_eq!((sim.particles[1].material, sim.particles[2].material), (1, 0));
+
+        sandbox.freeze(&mut sim);
+        assert!(sim.particles[0].is_static && sim.particles[0].imass == 0.0);
+        assert_eq!(sim.particles[0].vel, Vec2::new(0.0, 0.0));
+
+        assert_eq!(sandbox.delete(&mut sim), 2);
+        assert_eq!(sim.particles[0].pos, Vec2::new(2.0, 0.0));
+        assert!(sandbox.selection.is_empty());
+    }
+
+    #[test]
+    fn test_brush_paints_without_overlapping() {
+        let mut sim = Simulation::new(Random::seed_from_str("sandbox"));
+        let mut sandbox = Sandbox::new(None);
+        sandbox.toggle_tool();
+        sandbox.button(true, &mut sim);
+        sandbox.cursor_moved(Vec2::new(0.1, 0.0), &mut sim);
+        sandbox.cursor_moved(Vec2::new(0.25, 0.0), &mut sim);
+        sandbox.button(false, &mut sim);
+        sandbox.cursor_moved(Vec2::new(1.0, 0.0), &mut sim);
+        assert_eq!(sim.particles.len(), 2);
+        assert_eq!(sim.particles[1].pos, Vec2::new(0.25, 0.0));
+    }
+}
@@ -0,0 +1,341 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    core::math::{vec2::Vec2, vec4::Vec4},
+    simulation::{
+        constraints::{distance_constraint::DistanceConstraint, gas_constraint::GasConstraint, spring_constraint::SpringConstraint, total_fluid_constraint::TotalFluidConstraint, volume_constraint::VolumeConstraint},
+        particles::{body::Body, particle::{Particle, Phase}, sdf_data::SdfData, simulation::Simulation, surface_material::MaterialId},
+    },
+};
+
+/// Where scenes saved from the sandbox go
+pub const SCENES_DIR: &str = "scenes";
+
+/// Bumped when the format changes in a way older builds can't read
+pub const SCENE_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneParticle {
+    pub pos: (f32, f32),
+    pub vel: (f32, f32),
+    pub radius: f32,
+    pub mass: f32,
+    pub imass: f32, // 0 for particles that don't move
+    pub is_static: bool,
+    pub colour: (f32, f32, f32, f32),
+    pub phase: String, // "solid", "fluid" or "gas"
+    pub body: isize, // particles sharing a body don't collide, and for rigid bodies it's the index into bodies
+    pub s_friction: f32,
+    pub k_friction: f32,
+    #[serde(default)]
+    pub material: MaterialId,
+}
+
+/// A rigid body with its rest shape, one entry in each list per particle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneBody {
+    pub particles: Vec<usize>,
+    pub rs: Vec<(f32, f32)>,
+    pub sdf: Vec<(f32, f32, f32)>, // gradient x, gradient y, distance
+    pub center: (f32, f32),
+    pub angle: f32,
+    pub imass: f32,
+    pub stiffness: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneFluid {
+    pub density: f32,
+    pub particles: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneGas {
+    pub density: f32,
+    pub open: bool,
+    pub particles: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneDistanceConstraint {
+    pub i1: usize,
+    pub i2: usize,
+    pub d: f32,
+    pub stable: bool,
+    pub is_rope: bool,
+    #[serde(default)]
+    pub max_strain: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneSpringConstraint {
+    pub i1: usize,
+    pub i2: usize,
+    pub d: f32,
+    pub stiffness: f32,
+    pub stable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneVolumeConstraint {
+    pub particles: Vec<usize>,
+    pub rest_volume: f32,
+    pub compliance: f32,
+}
+
+/// The starting state of a physics scene: every particle and what holds them together, shared as a single json file.
+/// Saved from the sandbox in the demo scenes and played with `planck-time-trials demo --file <scene file>`.
+/// Emitters, force fields and the like aren't kept, only what's made of particles.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneFile {
+    pub version: u32,
+    pub gravity: (f32, f32),
+    pub x_boundaries: (f32, f32),
+    pub y_boundaries: (f32, f32),
+    pub body_count: usize, // ids handed out to bodies so far, so ones added later don't clash
+    pub particles: Vec<SceneParticle>,
+    #[serde(default)]
+    pub bodies: Vec<SceneBody>,
+    #[serde(default)]
+    pub fluids: Vec<SceneFluid>,
+    #[serde(default)]
+    pub gases: Vec<SceneGas>,
+    #[serde(default)]
+    pub distance_constraints: Vec<SceneDistanceConstraint>,
+    #[serde(default)]
+    pub spring_constraints: Vec<SceneSpringConstraint>,
+    #[serde(default)]
+    pub volume_constraints: Vec<SceneVolumeConstraint>,
+}
+
+fn phase_name(phase: Phase) -> &'static str {
+    match phase {
+        Phase::Solid => "solid",
+        Phase::Fluid => "fluid",
+        Phase::Gas => "gas",
+    }
+}
+
+fn phase_from_name(name: &str) -> Result<Phase, String> {
+    match name {
+        "solid" => Ok(Phase::Solid),
+        "fluid" => Ok(Phase::Fluid),
+        "gas" => Ok(Phase::Gas),
+        _ => Err(format!("unknown particle phase '{}'", name)),
+    }
+}
+
+impl SceneFile {
+    /// Everything in the simulation as it is now
+    pub fn from_simulation(sim: &Simulation) -> Self {
+        let pair = |v: Vec2| (v.x, v.y);
+        Self {
+            version: SCENE_FORMAT_VERSION,
+            gravity: pair(sim.gravity),
+            x_boundaries: pair(sim.x_boundaries),
+            y_boundaries: pair(sim.y_boundaries),
+            body_count: sim.body_count,
+            particles: sim.particles.iter().map(|p| SceneParticle {
+                pos: pair(p.pos),
+                vel: pair(p.vel),
+                radius: p.radius,
+                mass: p.mass,
+                imass: p.imass,
+                is_static: p.is_static,
+                colour: (p.colour.x, p.colour.y, p.colour.z, p.colour.w),
+                phase: phase_name(p.phase).to_owned(),
+                body: p.body,
+                s_friction: p.s_friction,
+                k_friction: p.k_friction,
+                material: p.material,
+            }).collect(),
+            bodies: sim.bodies.iter().map(|body| SceneBody {
+                particles: body.particle_indicies.clone(),
+                rs: body.particle_indicies.iter().map(|i| body.rs.get(i).copied().map_or((0.0, 0.0), pair)).collect(),
+                sdf: body.particle_indicies.iter().map(|i| body.sdf.get(i).map_or((0.0, 0.0, 0.0), |sdf| (sdf.gradient.x, sdf.gradient.y, sdf.distance))).collect(),
+                center: pair(body.center),
+                angle: body.angle,
+                imass: body.imass,
+                stiffness: body.stiffness,
+            }).collect(),
+            fluids: sim.global_standard_total_fluid_constraints.0.iter().map(|c| SceneFluid { density: c.p0, particles: c.ps.clone() }).collect(),
+            gases: sim.global_standard_gas_constraints.0.iter().map(|c| SceneGas { density: c.p0, open: c.open, particles: c.ps.clone() }).collect(),
+            distance_constraints: sim.distance_constraints.0.iter().map(|c| SceneDistanceConstraint { i1: c.i1, i2: c.i2, d: c.d, stable: c.stable, is_rope: c.is_rope, max_strain: c.max_strain }).collect(),
+            spring_constraints: sim.spring_constraints.0.iter().map(|c| SceneSpringConstraint { i1: c.i1, i2: c.i2, d: c.d, stiffness: c.stiffness, stable: c.stable }).collect(),
+            volume_constraints: sim.volume_constraints.0.iter().map(|c| SceneVolumeConstraint { particles: c.particle_indices.clone(), rest_volume: c.rest_volume, compliance: c.compliance }).collect(),
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let scene: SceneFile = serde_json::from_str(json).map_err(|e| format!("not a scene file: {}", e))?;
+        scene.validate()?;
+        Ok(scene)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = fs::read_to_string(path).map_err(|e| format!("can't read {}: {}", path.display(), e))?;
+        Self::from_json(&json)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| format!("can't create {}: {}", dir.display(), e))?;
+        }
+        fs::write(path, self.to_json()).map_err(|e| format!("can't write {}: {}", path.display(), e))
+    }
+
+    /// A new file in the scenes folder named for when it was saved
+    pub fn new_path(now: chrono::DateTime<chrono::Local>) -> PathBuf {
+        Path::new(SCENES_DIR).join(format!("scene-{}.json", now.format("%Y%m%d-%H%M%S")))
+    }
+
+    /// Catch files that would panic the solver, eg. a constraint on a particle that isn't there
+    pub fn validate(&self) -> Result<(), String> {
+        if self.version > SCENE_FORMAT_VERSION {
+            return Err(format!("scene file version {} is newer than this build reads ({})", self.version, SCENE_FORMAT_VERSION));
+        }
+        let num_particles = self.particles.len();
+        let check = |what: &str, indices: Vec<usize>| match indices.into_iter().find(|&i| i >= num_particles) {
+            Some(i) => Err(format!("{} uses particle {} but there are only {}", what, i, num_particles)),
+            None => Ok(()),
+        };
+        for particle in &self.particles {
+            phase_from_name(&particle.phase)?;
+            if particle.radius.is_nan() || particle.radius <= 0.0 {
+                return Err(format!("particle radius {} isn't positive", particle.radius));
+            }
+        }
+        for (b, body) in self.bodies.iter().enumerate() {
+            check("a rigid body", body.particles.clone())?;
+            if body.particles.len() < 2 || body.rs.len() != body.particles.len() || body.sdf.len() != body.particles.len() {
+                return Err(String::from("a rigid body needs at least 2 particles, with a rest position and sdf for each"));
+            }
+            if body.particles.iter().any(|&i| self.particles[i].body != b as isize || self.particles[i].imass == 0.0) {
+                return Err(String::from("a rigid body's particles must be movable and belong to it"));
+            }
+        }
+        for fluid in &self.fluids {
+            check("a fluid", fluid.particles.clone())?;
+        }
+        for gas in &self.gases {
+            check("a gas", gas.particles.clone())?;
+        }
+        check("a distance constraint", self.distance_constraints.iter().flat_map(|c| [c.i1, c.i2]).collect())?;
+        check("a spring constraint", self.spring_constraints.iter().flat_map(|c| [c.i1, c.i2]).collect())?;
+        check("a volume constraint", self.volume_constraints.iter().flat_map(|c| c.particles.iter().copied()).collect())?;
+        Ok(())
+    }
+
+    /// Add the scene to an empty simulation
+    pub fn build(&self, sim: &mut Simulation) {
+        let vec2 = |(x, y): (f32, f32)| Vec2::new(x, y);
+        sim.gravity = vec2(self.gravity);
+        sim.x_boundaries = vec2(self.x_boundaries);
+        sim.y_boundaries = vec2(self.y_boundaries);
+        sim.body_count = self.body_count;
+
+        for particle in &self.particles {
+            let (r, g, b, a) = particle.colour;
+            let mut p = *Particle::default()
+                .set_radius(particle.radius)
+                .set_pos(vec2(particle.pos))
+                .set_vel(vec2(particle.vel))
+                .set_colour(Vec4::new(r, g, b, a))
+                .set_material(particle.material)
+                .set_phase(phase_from_name(&particle.phase).unwrap_or(Phase::Solid));
+            p.set_mass_2(-particle.imass); // a negative mass is taken as the inverse mass
+            p.mass = particle.mass;
+            p.is_static = particle.is_static;
+            p.body = particle.body;
+            p.s_friction = particle.s_friction;
+            p.k_friction = particle.k_friction;
+            sim.add_particle(p);
+        }
+
+        for body in &self.bodies {
+            let mut b = Body::new();
+            b.particle_indicies = body.particles.clone();
+            for (k, &i) in body.particles.iter().enumerate() {
+                b.rs.insert(i, vec2(body.rs[k]));
+                let (x, y, distance) = body.sdf[k];
+                b.sdf.insert(i, SdfData::new(Vec2::new(x, y), distance));
+            }
+            b.center = vec2(body.center);
+            b.angle = body.angle;
+            b.imass = body.imass;
+            b.stiffness = body.stiffness;
+            sim.bodies.push(b);
+        }
+
+        for fluid in &self.fluids {
+            sim.global_standard_total_fluid_constraints.push(TotalFluidConstraint::new(fluid.density, &fluid.particles));
+        }
+        for gas in &self.gases {
+            sim.global_standard_gas_constraints.push(GasConstraint::new(gas.density, &gas.particles, gas.open));
+        }
+        for c in &self.distance_constraints {
+            let mut distance_constraint = DistanceConstraint::new(c.d, c.i1, c.i2, c.stable);
+            distance_constraint.is_rope = c.is_rope;
+            distance_constraint.max_strain = c.max_strain;
+            sim.add_distance_constraint(distance_constraint);
+        }
+        for c in &self.spring_constraints {
+            sim.add_spring_constraint(SpringConstraint::new(c.d, c.stiffness, c.i1, c.i2, c.stable));
+        }
+        for c in &self.volume_constraints {
+            let mut volume_constraint = VolumeConstraint::new(c.compliance, c.particles.clone(), &sim.particles);
+            volume_constraint.rest_volume = c.rest_volume;
+            sim.add_volume_constraint(volume_constraint);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::math::random::Random, simulation::particles::simulation_demos::SimulationDemos};
+
+    #[test]
+    fn test_demo_scenes_round_trip() {
+        let demos: [fn(&mut Simulation); 3] = [SimulationDemos::init_boxes, SimulationDemos::init_rope, SimulationDemos::init_water_balloon];
+        for init in demos {
+            let mut sim = Simulation::new(Random::seed_from_str("scene"));
+            init(&mut sim);
+            let scene = SceneFile::from_simulation(&sim);
+            let loaded = SceneFile::from_json(&scene.to_json()).unwrap();
+            assert_eq!(loaded, scene);
+
+            // the rebuilt scene plays out the same as the original
+            let mut rebuilt = Simulation::new(Random::seed_from_str("scene"));
+            loaded.build(&mut rebuilt);
+            assert_eq!(SceneFile::from_simulation(&rebuilt), scene);
+            for _ in 0..20 {
+                sim.step(0.005);
+                rebuilt.step(0.005);
+            }
+            let positions = |sim: &Simulation| sim.particles.iter().map(|p| p.pos).collect::<Vec<Vec2>>();
+            assert_eq!(positions(&rebuilt), positions(&sim));
+        }
+    }
+
+    #[test]
+    fn test_bad_files_are_rejected() {
+        let mut sim = Simulation::new(Random::seed_from_str("scene"));
+        SimulationDemos::init_rope(&mut sim);
+        let scene = SceneFile::from_simulation(&sim);
+
+        let mut missing_particle = scene.clone();
+        missing_particle.distance_constraints[0].i2 = scene.particles.len();
+        assert!(missing_particle.validate().unwrap_err().contains("distance constraint"));
+
+        let newer = SceneFile { version: SCENE_FORMAT_VERSION + 1, ..scene.clone() };
+        assert!(newer.validate().is_err());
+        assert!(SceneFile::from_json("{}").is_err());
+    }
+}
@@ -0,0 +1,107 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use now::DateTimeNow;
+
+pub const CUSTOM_SEED_PREFIX: &str = "custom-";
+const MAX_CUSTOM_SEED_LENGTH: usize = 32;
+
+/// The daily seed is the UTC date, so everyone gets the same level and it rolls over at midnight UTC
+pub fn daily_seed(now: DateTime<Utc>) -> String {
+    now.format("%Y-%m-%d").to_string()
+}
+
+/// The start of the day a daily seed is for
+pub fn seed_day(seed: &str) -> Result<DateTime<Utc>, String> {
+    let date = NaiveDate::parse_from_str(seed, "%Y-%m-%d").map_err(|_| format!("'{}' isn't a daily seed", seed))?;
+    Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc())
+}
+
+/// The level seed for a seed the player picked, None if there's nothing usable in it.
+/// It's prefixed so a custom level never shares a board with a daily, and kept to characters that fit in a message field.
+pub fn custom_seed(input: &str) -> Option<String> {
+    let input = input.trim();
+    let input = input.strip_prefix(CUSTOM_SEED_PREFIX).unwrap_or(input);
+    let seed: String = input.split_whitespace().collect::<Vec<_>>().join("-").chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .take(MAX_CUSTOM_SEED_LENGTH)
+        .collect();
+    (!seed.is_empty()).then(|| format!("{}{}", CUSTOM_SEED_PREFIX, seed))
+}
+
+/// When the next daily becomes available
+pub fn next_rollover(now: DateTime<Utc>) -> DateTime<Utc> {
+    now.beginning_of_day() + Duration::days(1)
+}
+
+pub fn time_until_rollover(now: DateTime<Utc>) -> Duration {
+    next_rollover(now) - now
+}
+
+/// "HH:MM:SS" for the next daily countdown
+pub fn format_countdown(duration: Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+    format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+/// Raised when the daily seed changes while the game is open
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeedRolloverEvent {
+    pub previous_seed: String,
+    pub seed: String,
+}
+
+/// Remembers the daily seed from the last check, to spot it changing
+#[derive(Debug, Clone)]
+pub struct SeedRolloverWatcher {
+    seed: String,
+}
+
+impl SeedRolloverWatcher {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self { seed: daily_seed(now) }
+    }
+
+    pub fn check(&mut self, now: DateTime<Utc>) -> Option<SeedRolloverEvent> {
+        let seed = daily_seed(now);
+        if seed == self.seed {
+            return None;
+        }
+        let previous_seed = std::mem::replace(&mut self.seed, seed.clone());
+        Some(SeedRolloverEvent { previous_seed, seed })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_countdown_to_midnight_utc() {
+        let now = Utc.with_ymd_and_hms(2025, 3, 14, 22, 58, 30).unwrap();
+        assert_eq!(daily_seed(now), "2025-03-14");
+        assert_eq!(next_rollover(now), Utc.with_ymd_and_hms(2025, 3, 15, 0, 0, 0).unwrap());
+        assert_eq!(format_countdown(time_until_rollover(now)), "01:01:30");
+    }
+
+    #[test]
+    fn test_custom_seed() {
+        assert_eq!(custom_seed("  my level "), Some(String::from("custom-my-level")));
+        assert_eq!(custom_seed("custom-my-level"), Some(String::from("custom-my-level")));
+        assert_eq!(custom_seed("2025-03-14"), Some(String::from("custom-2025-03-14"))); // not the daily's board
+        assert_eq!(custom_seed("a=b c#d"), Some(String::from("custom-ab-cd")));
+        assert_eq!(custom_seed(&"x".repeat(100)).map(|seed| seed.len()), Some(CUSTOM_SEED_PREFIX.len() + MAX_CUSTOM_SEED_LENGTH));
+        assert_eq!(custom_seed(" "), None);
+        assert_eq!(custom_seed("!!"), None);
+        assert!(seed_day(&custom_seed("2025-03-14").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_rollover_event() {
+        let mut watcher = SeedRolloverWatcher::new(Utc.with_ymd_and_hms(2025, 3, 14, 23, 59, 59).unwrap());
+        assert_eq!(watcher.check(Utc.with_ymd_and_hms(2025, 3, 14, 23, 59, 59).unwrap()), None);
+        let event = watcher.check(Utc.with_ymd_and_hms(2025, 3, 15, 0, 0, 1).unwrap());
+        assert_eq!(event, Some(SeedRolloverEvent { previous_seed: String::from("2025-03-14"), seed: String::from("2025-03-15") }));
+        // only raised once
+        assert_eq!(watcher.check(Utc.with_ymd_and_hms(2025, 3, 15, 0, 0, 2).unwrap()), None);
+    }
+}
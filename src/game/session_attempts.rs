@@ -0,0 +1,87 @@
+/// A run that reached the finish screen this session
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attempt {
+    pub time: f32,
+    pub checkpoints: Option<u32>, // time attack only
+    pub submitted: bool, // sent to the leaderboard channels
+}
+
+impl Attempt {
+    /// Same order as the leaderboard: most checkpoints first (only set for time attack), then the lowest time
+    pub fn is_better_than(&self, other: &Attempt) -> bool {
+        match self.checkpoints.cmp(&other.checkpoints) {
+            std::cmp::Ordering::Equal => self.time < other.time,
+            ordering => ordering == std::cmp::Ordering::Greater,
+        }
+    }
+}
+
+/// Every attempt at the current leaderboard seed this session, so only improvements are posted to IRC
+#[derive(Debug, Clone, Default)]
+pub struct SessionAttempts {
+    seed: String,
+    pub attempts: Vec<Attempt>,
+}
+
+impl SessionAttempts {
+    /// Add an attempt, starting over if the seed changed (a new daily, mode or level).
+    /// Returns true if it should be submitted automatically, ie. it beats everything submitted so far.
+    pub fn record(&mut self, seed: &str, time: f32, checkpoints: Option<u32>) -> bool {
+        if self.seed != seed {
+            self.seed = seed.to_owned();
+            self.attempts.clear();
+        }
+        let attempt = Attempt { time, checkpoints, submitted: false };
+        let should_submit = self.best_submitted().is_none_or(|best| attempt.is_better_than(best));
+        self.attempts.push(attempt);
+        should_submit
+    }
+
+    pub fn latest(&self) -> Option<&Attempt> {
+        self.attempts.last()
+    }
+
+    pub fn mark_latest_submitted(&mut self) {
+        if let Some(attempt) = self.attempts.last_mut() {
+            attempt.submitted = true;
+        }
+    }
+
+    pub fn best(&self) -> Option<&Attempt> {
+        self.attempts.iter().reduce(|best, attempt| if attempt.is_better_than(best) { attempt } else { best })
+    }
+
+    fn best_submitted(&self) -> Option<&Attempt> {
+        self.attempts.iter().filter(|attempt| attempt.submitted).reduce(|best, attempt| if attempt.is_better_than(best) { attempt } else { best })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_improvements_are_submitted() {
+        let mut attempts = SessionAttempts::default();
+        assert!(attempts.record("2025-01-01", 20.0, None));
+        attempts.mark_latest_submitted();
+        assert!(!attempts.record("2025-01-01", 21.0, None));
+        assert!(attempts.record("2025-01-01", 19.5, None));
+        attempts.mark_latest_submitted();
+        assert!(!attempts.record("2025-01-01", 19.8, None));
+        assert_eq!(attempts.attempts.len(), 4);
+        assert_eq!(attempts.best().unwrap().time, 19.5);
+
+        // a new seed starts over
+        assert!(attempts.record("2025-01-02", 30.0, None));
+        assert_eq!(attempts.attempts.len(), 1);
+    }
+
+    #[test]
+    fn test_checkpoints_rank_before_time() {
+        let slow_but_further = Attempt { time: 40.0, checkpoints: Some(3), submitted: false };
+        let fast = Attempt { time: 20.0, checkpoints: Some(2), submitted: false };
+        assert!(slow_but_further.is_better_than(&fast));
+        assert!(!fast.is_better_than(&slow_but_further));
+    }
+}
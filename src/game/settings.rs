@@ -2,10 +2,45 @@ use serde::{Serialize, Deserialize};
 use std::fs;
 use std::path::Path;
 
+use crate::core::math::deterministic::MathMode;
+use crate::engine::app::camera::Projection;
+use crate::game::camera_mode::CameraMode;
+use crate::game::clock_check::DEFAULT_TIME_SERVER;
+use crate::game::color_scheme::ColorScheme;
+use crate::game::rumble::RumbleIntensity;
+#[cfg(feature = "client")]
+use crate::game::ui::theme::{parse_hex_color, UiTheme};
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Settings {
     pub player_name: Option<String>,
     pub show_debug_info: Option<bool>,
+    pub private_channel: Option<String>, // extra IRC channel with its own leaderboard, for friends or workplaces
+    pub private_channel_key: Option<String>, // password for the private channel
+    pub friends: Option<Vec<String>>, // nicknames for the friends only leaderboard filter
+    pub level_pack_index_url: Option<String>, // http json index of community level packs for the level packs browser
+    pub color_scheme: Option<ColorScheme>, // dark, light or high_contrast
+    pub accent_color: Option<String>, // "#rrggbb" to replace the scheme's accent colour
+    pub show_ghost_camera: Option<bool>, // picture in picture view from the ghost being raced, toggled with G
+    pub camera_projection: Option<Projection>, // perspective or orthographic, toggled with O
+    pub camera_mode: Option<CameraMode>, // free, chase or trackside, cycled with V
+    pub ignored_players: Option<Vec<String>>, // nicknames whose messages and times are dropped
+    pub gpu_solver: Option<bool>, // solve demo scenes of loose particles with a compute shader, same as --gpu-solver
+    pub rumble_intensity: Option<RumbleIntensity>, // off, low or full, cycled with the gamepad's Select button
+    pub master_volume: Option<f32>, // 0 to 1, scales all the sounds
+    pub effects_volume: Option<f32>, // 0 to 1, collisions
+    pub engine_volume: Option<f32>, // 0 to 1
+    pub time_server: Option<String>, // "host:port" of the SNTP server the clock is checked against, empty to not check
+    pub math_mode: Option<MathMode>, // native or deterministic, how the physics works out sin, cos etc., see MathMode
+    pub commentary: Option<bool>, // narrate runs to commentary.log, for streaming overlays
+    pub commentary_channel: Option<String>, // IRC channel the commentary is also posted to
+    pub stream_overlay: Option<bool>, // write the live run to overlay.json for OBS browser sources
+    pub stream_overlay_port: Option<u16>, // also serve it on http://127.0.0.1:<port>
+    pub render_scale_min: Option<f32>, // lowest fraction of the window's resolution the scene drops to when frames run long, 0.5 by default
+    pub render_scale_max: Option<f32>, // highest, 1 by default. Set both the same to turn dynamic resolution off
+    pub frame_budget_ms: Option<f32>, // how long a frame's work can take before the resolution drops
+    pub result_card: Option<bool>, // save a picture of each finished run to result_cards/ for sharing
+    pub result_card_clipboard: Option<bool>, // copy the picture of each finished run for pasting into chat
 }
 
 impl Settings {
@@ -21,8 +56,43 @@ impl Settings {
         Settings::default()
     }
 
+    /// The private channel name (with a leading #) if one is configured
+    pub fn private_channel(&self) -> Option<String> {
+        channel_name(self.private_channel.as_deref()?)
+    }
+
+    /// The commentary channel name (with a leading #) if commentary is on and a channel is configured
+    pub fn commentary_channel(&self) -> Option<String> {
+        channel_name(self.commentary_channel.as_deref().filter(|_| self.commentary.unwrap_or(false))?)
+    }
+
+    /// Where to check the clock, see ClockCheck
+    pub fn time_server(&self) -> &str {
+        self.time_server.as_deref().unwrap_or(DEFAULT_TIME_SERVER)
+    }
+
+    /// The UI theme from the colour scheme and accent colour, ignoring an accent colour that doesn't parse
+    #[cfg(feature = "client")]
+    pub fn ui_theme(&self) -> UiTheme {
+        let accent = self.accent_color.as_deref().and_then(parse_hex_color);
+        UiTheme::new(self.color_scheme.unwrap_or_default(), accent)
+    }
+
     pub fn save(&self) -> Result<(), std::io::Error> {
         let content = serde_json::to_string_pretty(self).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
         fs::write("settings.json", content)
     }
 }
+
+/// The channel with a leading #, None if it is blank
+fn channel_name(channel: &str) -> Option<String> {
+    let channel = channel.trim();
+    if channel.is_empty() {
+        return None;
+    }
+    if channel.starts_with('#') {
+        Some(channel.to_string())
+    } else {
+        Some(format!("#{}", channel))
+    }
+}
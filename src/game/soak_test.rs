@@ -0,0 +1,151 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::game::{headless_run::HeadlessRun, irc::presence_tracker::PresenceTracker, leaderboard::Leaderboard, metrics::{resident_memory_kb, Metrics}};
+
+pub const DEFAULT_SOAK_MINUTES: u64 = 60;
+const BOTS_PER_RUN: usize = 20; // simulated players posting a time after each run
+const RUNS_PER_DAY: usize = 5; // the simulated day rolls over every few runs, so per seed storage gets exercised
+const WARMUP_RUNS: usize = 3; // memory use settles after the first few runs, growth is measured from here
+
+/// How long to soak for and the limits that must hold the whole time
+#[derive(Debug, Clone)]
+pub struct SoakConfig {
+    pub duration: Duration,
+    pub max_runs: Option<usize>,
+    pub max_run_frames: u32, // give up on a bot run that hasn't finished by now
+    pub max_particles: usize,
+    pub max_leaderboard_scores: usize,
+    pub max_presence_entries: usize,
+    pub max_memory_growth_kb: u64,
+    pub metrics_path: Option<PathBuf>, // where to dump metrics while soaking, see Metrics
+}
+
+impl Default for SoakConfig {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_secs(DEFAULT_SOAK_MINUTES * 60),
+            max_runs: None,
+            max_run_frames: 12000, // 60 seconds of game time
+            max_particles: 20000,
+            max_leaderboard_scores: 1000,
+            max_presence_entries: BOTS_PER_RUN * 200,
+            max_memory_growth_kb: 64 * 1024,
+            metrics_path: None,
+        }
+    }
+}
+
+/// What one bot run left behind, printed as a line of metrics
+#[derive(Debug, Clone)]
+pub struct SoakMetrics {
+    pub run: usize,
+    pub frames: u32,
+    pub finished: bool,
+    pub time: f32,
+    pub max_particles: usize, // also the number of particle instances the renderer would need
+    pub leaderboard_seeds: usize,
+    pub leaderboard_scores: usize,
+    pub presence_entries: usize,
+    pub memory_kb: Option<u64>, // resident memory, only available on linux
+}
+
+impl SoakMetrics {
+    pub fn to_line(&self) -> String {
+        format!(
+            "SOAK run={} frames={} finished={} time={:.3} particles={} seeds={} scores={} presence={} rss_kb={}",
+            self.run, self.frames, self.finished, self.time, self.max_particles, self.leaderboard_seeds,
+            self.leaderboard_scores, self.presence_entries, self.memory_kb.map_or(String::from("-"), |kb| kb.to_string())
+        )
+    }
+}
+
+/// Play bot runs of today's level back to back, feeding simulated leaderboard and presence traffic,
+/// and fail as soon as something grows past its budget. Returns the number of runs completed.
+pub fn run_soak(config: &SoakConfig) -> Result<usize, String> {
+    let start = Instant::now();
+    let mut leaderboard = Leaderboard::new();
+    let mut presence = PresenceTracker::new();
+    let mut dump = Metrics::new(config.metrics_path.clone());
+    let mut baseline_memory_kb = None;
+    let mut run_idx = 0;
+
+    while start.elapsed() < config.duration && config.max_runs.is_none_or(|max_runs| run_idx < max_runs) {
+        let mut run = HeadlessRun::new();
+        let mut frames = 0;
+        let mut max_particles = 0;
+        while frames < config.max_run_frames && !run.finished() {
+            run.apply_bot_input(frames);
+            run.step();
+            max_particles = max_particles.max(run.simulation.particles.len());
+            frames += 1;
+        }
+
+        // every bot posts a time, as if it came over IRC. Bot names are unique so nothing is reused.
+        let seed = format!("soak-day{:05}", run_idx / RUNS_PER_DAY);
+        let now = Instant::now();
+        for bot in 0..BOTS_PER_RUN {
+            let user = format!("soakbot{}-{}", run_idx, bot);
+            let best_time = format!("BEST_TIME seed={} time={:.3} user={}", seed, run.total_time + bot as f32 * 0.1, user);
+            presence.handle_message(&user, &format!("PRESENCE seed={} state=racing", seed), now);
+            leaderboard.handle_message(&best_time, &seed);
+            presence.handle_message(&user, &best_time, now);
+        }
+
+        let metrics = SoakMetrics {
+            run: run_idx,
+            frames,
+            finished: run.finished(),
+            time: run.total_time,
+            max_particles,
+            leaderboard_seeds: leaderboard.num_seeds(),
+            leaderboard_scores: leaderboard.num_scores(),
+            presence_entries: presence.num_tracked(),
+            memory_kb: resident_memory_kb(),
+        };
+        println!("{}", metrics.to_line());
+        dump.increment("runs");
+        if !metrics.finished {
+            dump.increment("unfinished_runs");
+        }
+        dump.set("leaderboard_scores", metrics.leaderboard_scores as u64);
+        dump.set("presence_entries", metrics.presence_entries as u64);
+        dump.update(Instant::now());
+
+        if metrics.max_particles > config.max_particles {
+            return Err(format!("Run {} peaked at {} particles, budget is {}", run_idx, metrics.max_particles, config.max_particles));
+        }
+        if metrics.leaderboard_scores > config.max_leaderboard_scores {
+            return Err(format!("Leaderboard holds {} scores after run {}, budget is {}", metrics.leaderboard_scores, run_idx, config.max_leaderboard_scores));
+        }
+        if metrics.presence_entries > config.max_presence_entries {
+            return Err(format!("Presence tracker holds {} players after run {}, budget is {}", metrics.presence_entries, run_idx, config.max_presence_entries));
+        }
+        if run_idx + 1 == WARMUP_RUNS {
+            baseline_memory_kb = metrics.memory_kb;
+        }
+        if let (Some(baseline), Some(memory_kb)) = (baseline_memory_kb, metrics.memory_kb) {
+            if memory_kb > baseline + config.max_memory_growth_kb {
+                return Err(format!("Memory grew from {}kb to {}kb by run {}, budget is {}kb of growth", baseline, memory_kb, run_idx, config.max_memory_growth_kb));
+            }
+        }
+
+        run_idx += 1;
+    }
+    Ok(run_idx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_soak_stays_in_budget() {
+        let config = SoakConfig {
+            max_runs: Some(RUNS_PER_DAY * 2),
+            max_run_frames: 5,
+            ..SoakConfig::default()
+        };
+        assert_eq!(run_soak(&config), Ok(RUNS_PER_DAY * 2));
+    }
+}
@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+
+use crate::game::entity::gameplay_events::GameplayEvent;
+
+pub const SPLITS_PATH: &str = "splits.json";
+const MAX_LEVELS: usize = 60; // boards kept, the least recently played is dropped first
+
+/// My splits on one board. A sector runs up to a checkpoint, the last one up to the finish.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct LevelSplits {
+    pub seed: String, // the leaderboard seed, so it includes the mode
+    pub pb_splits: Vec<Option<f32>>, // time at each checkpoint and then the finish, in my best finished run
+    pub best_sectors: Vec<Option<f32>>, // fastest time through each sector from any run
+}
+
+/// A checkpoint or the finish crossed this run, for the HUD
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Split {
+    pub sector: usize, // from 0, the last sector ends at the finish
+    pub time: f32,
+    pub pb_delta: Option<f32>, // against my best run at the same point, negative when ahead
+    pub best_sector: bool, // the fastest I've been through this sector
+}
+
+/// The times a run crossed each checkpoint and the finish
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RunSplits {
+    pub seed: String,
+    pub times: Vec<Option<f32>>, // one per checkpoint, then the finish
+}
+
+impl RunSplits {
+    pub fn new(seed: String, num_checkpoints: usize) -> Self {
+        Self { seed, times: vec![None; num_checkpoints + 1] }
+    }
+
+    /// Time through a sector, only when the checkpoint before it was crossed too
+    pub fn sector_time(&self, sector: usize) -> Option<f32> {
+        let end = self.times.get(sector).copied().flatten()?;
+        let start = if sector == 0 { 0.0 } else { self.times[sector - 1]? };
+        Some(end - start)
+    }
+
+    /// Take the split from a checkpoint or finish event, compared against my earlier runs on this board
+    pub fn handle_event(&mut self, event: &GameplayEvent, history: &SplitHistory) -> Option<Split> {
+        let (sector, time) = match event {
+            GameplayEvent::CheckpointReached { checkpoint, time } => (*checkpoint, *time),
+            GameplayEvent::Finished { time } => (self.times.len().checked_sub(1)?, *time),
+            _ => return None,
+        };
+        *self.times.get_mut(sector)? = Some(time);
+
+        let level = history.level(&self.seed);
+        let pb_split = level.and_then(|level| level.pb_splits.get(sector).copied().flatten());
+        let best_sector = level.and_then(|level| level.best_sectors.get(sector).copied().flatten());
+        Some(Split {
+            sector,
+            time,
+            pb_delta: pb_split.map(|pb_split| time - pb_split),
+            best_sector: self.sector_time(sector).is_some_and(|sector_time| best_sector.is_none_or(|best| sector_time < best)),
+        })
+    }
+}
+
+/// My splits on the boards I've played, kept locally as the leaderboard only has finish times
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SplitHistory {
+    pub levels: Vec<LevelSplits>, // least recently played first
+}
+
+impl SplitHistory {
+    pub fn load(path: &str) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)
+    }
+
+    pub fn level(&self, seed: &str) -> Option<&LevelSplits> {
+        self.levels.iter().find(|level| level.seed == seed)
+    }
+
+    /// Keep any sectors the run was faster through, and all its splits if it finished faster than my best.
+    /// Returns true if anything changed.
+    pub fn record(&mut self, run: &RunSplits) -> bool {
+        let index = match self.levels.iter().position(|level| level.seed == run.seed) {
+            Some(index) => index,
+            None => {
+                if self.levels.len() >= MAX_LEVELS {
+                    self.levels.remove(0);
+                }
+                self.levels.push(LevelSplits { seed: run.seed.clone(), ..LevelSplits::default() });
+                self.levels.len() - 1
+            }
+        };
+        // the level moves to the back as the most recently played
+        let mut level = self.levels.remove(index);
+        let before = level.clone();
+
+        level.best_sectors.resize(run.times.len(), None);
+        for (sector, best) in level.best_sectors.iter_mut().enumerate() {
+            if let Some(sector_time) = run.sector_time(sector) {
+                if best.is_none_or(|best| sector_time < best) {
+                    *best = Some(sector_time);
+                }
+            }
+        }
+        let pb_time = level.pb_splits.last().copied().flatten();
+        let finish_time = run.times.last().copied().flatten();
+        if finish_time.is_some_and(|finish_time| pb_time.is_none_or(|pb_time| finish_time < pb_time)) {
+            level.pb_splits = run.times.clone();
+        }
+
+        let changed = level != before || index != self.levels.len();
+        self.levels.push(level);
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(history: &mut SplitHistory, times: &[(usize, f32)], finish: Option<f32>) -> Vec<Split> {
+        let mut run_splits = RunSplits::new(String::from("2025-07-07"), 3);
+        let mut events: Vec<GameplayEvent> = times.iter().map(|&(checkpoint, time)| GameplayEvent::CheckpointReached { checkpoint, time }).collect();
+        events.extend(finish.map(|time| GameplayEvent::Finished { time }));
+        events.push(GameplayEvent::LapCompleted { lap: 1, lap_time: 1.0 });
+        let splits = events.iter().filter_map(|event| run_splits.handle_event(event, history)).collect();
+        history.record(&run_splits);
+        splits
+    }
+
+    #[test]
+    fn test_splits_against_my_best() {
+        let mut history = SplitHistory::default();
+        let splits = run(&mut history, &[(0, 10.0), (1, 20.0), (2, 30.0)], Some(40.0));
+        assert_eq!(splits.len(), 4);
+        assert!(splits.iter().all(|split| split.pb_delta.is_none() && split.best_sector));
+        assert_eq!(history.level("2025-07-07").unwrap().pb_splits, vec![Some(10.0), Some(20.0), Some(30.0), Some(40.0)]);
+
+        // a crash before the finish still sets a sector best, but not the splits to beat
+        let splits = run(&mut history, &[(0, 11.0), (1, 19.0)], None);
+        assert_eq!(splits[0], Split { sector: 0, time: 11.0, pb_delta: Some(1.0), best_sector: false });
+        assert_eq!(splits[1], Split { sector: 1, time: 19.0, pb_delta: Some(-1.0), best_sector: true });
+        let level = history.level("2025-07-07").unwrap();
+        assert_eq!(level.best_sectors, vec![Some(10.0), Some(8.0), Some(10.0), Some(10.0)]);
+        assert_eq!(level.pb_splits[3], Some(40.0));
+
+        // a missed checkpoint leaves the sectors either side of it out
+        let splits = run(&mut history, &[(0, 9.0), (2, 25.0)], Some(33.0));
+        assert_eq!(splits.iter().map(|split| split.best_sector).collect::<Vec<_>>(), vec![true, false, true]);
+        let level = history.level("2025-07-07").unwrap();
+        assert_eq!(level.best_sectors, vec![Some(9.0), Some(8.0), Some(10.0), Some(8.0)]);
+        assert_eq!(level.pb_splits, vec![Some(9.0), None, Some(25.0), Some(33.0)]);
+        assert!(!history.record(&RunSplits::new(String::from("2025-07-07"), 3)));
+    }
+}
@@ -0,0 +1,194 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::game::{entity::gameplay_events::GameplayEvent, game_state::GameState};
+
+pub const OVERLAY_PATH: &str = "overlay.json";
+const WRITE_INTERVAL: Duration = Duration::from_millis(100); // plenty for a timer on stream, without writing a file every frame
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A checkpoint crossed this run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OverlaySplit {
+    pub checkpoint: usize, // counting from 1, as the commentary does
+    pub time: f32,
+    pub pb_delta: Option<f32>, // against my personal best at the same checkpoint, negative when ahead
+}
+
+/// What the game knows about the run right now, filled in by the game each frame
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LiveRun {
+    pub player: String,
+    pub seed: String, // the leaderboard seed, so it includes the mode
+    pub level_name: Option<String>,
+    pub state: GameState,
+    pub time: f32,
+    pub time_remaining: Option<f32>, // only in time attack
+    pub speed: f32, // m/s
+    pub leaderboard_position: Option<usize>, // my best on the board, None before I have a time
+    pub leaderboard_players: usize,
+}
+
+#[derive(Serialize)]
+struct OverlayState<'a> {
+    #[serde(flatten)]
+    run: &'a LiveRun,
+    splits: &'a [OverlaySplit],
+    pb_time: Option<f32>,
+}
+
+/// Live run state for OBS browser sources and the like to build overlays from, without screen scraping the HUD.
+/// Written as JSON to a file, and served over HTTP on localhost when there's a port.
+pub struct StreamOverlay {
+    path: Option<String>,
+    pub address: Option<SocketAddr>, // where the HTTP endpoint is listening
+    served: Arc<Mutex<String>>, // the latest JSON, shared with the HTTP thread
+    pb_splits: Vec<Option<f32>>, // my best time at each checkpoint
+    pb_time: Option<f32>,
+    splits: Vec<OverlaySplit>,
+    last_write: Option<Instant>,
+}
+
+impl StreamOverlay {
+    /// Write to path and serve on 127.0.0.1:port if given, port 0 picks any free port
+    pub fn new(path: Option<&str>, port: Option<u16>) -> Self {
+        let served = Arc::new(Mutex::new(String::from("{}")));
+        let address = port.and_then(|port| match TcpListener::bind(("127.0.0.1", port)) {
+            Ok(listener) => {
+                let address = listener.local_addr().ok();
+                let served = served.clone();
+                std::thread::spawn(move || serve(listener, served));
+                address
+            }
+            Err(e) => {
+                eprintln!("Couldn't serve the stream overlay on port {}: {}", port, e);
+                None
+            }
+        });
+        Self {
+            path: path.map(str::to_owned),
+            address,
+            served,
+            pb_splits: vec![],
+            pb_time: None,
+            splits: vec![],
+            last_write: None,
+        }
+    }
+
+    /// Clear the splits for a new run, compared against my personal best's
+    pub fn start_run(&mut self, pb_splits: Vec<Option<f32>>, pb_time: Option<f32>) {
+        self.pb_splits = pb_splits;
+        self.pb_time = pb_time;
+        self.splits.clear();
+    }
+
+    /// Pick up the splits from the gameplay events of an update
+    pub fn handle_events(&mut self, events: &[GameplayEvent]) {
+        for event in events {
+            if let GameplayEvent::CheckpointReached { checkpoint, time } = event {
+                let pb_split = self.pb_splits.get(*checkpoint).copied().flatten();
+                self.splits.push(OverlaySplit { checkpoint: checkpoint + 1, time: *time, pb_delta: pb_split.map(|pb_split| time - pb_split) });
+            }
+        }
+    }
+
+    /// Publish the run, at most every WRITE_INTERVAL and only when something has changed
+    pub fn update(&mut self, run: &LiveRun, now: Instant) {
+        if self.last_write.is_some_and(|last_write| now - last_write < WRITE_INTERVAL) {
+            return;
+        }
+        let state = OverlayState { run, splits: &self.splits, pb_time: self.pb_time };
+        let Ok(json) = serde_json::to_string_pretty(&state) else { return };
+        let Ok(mut served) = self.served.lock() else { return };
+        if *served == json {
+            return;
+        }
+        self.last_write = Some(now);
+        if let Some(path) = &self.path {
+            if let Err(e) = fs::write(path, &json) {
+                eprintln!("Failed to write the stream overlay to {}: {}", path, e);
+            }
+        }
+        *served = json;
+    }
+}
+
+fn serve(listener: TcpListener, served: Arc<Mutex<String>>) {
+    for stream in listener.incoming().flatten() {
+        let _ = respond(stream, &served);
+    }
+}
+
+/// Whatever was asked for, there is only the one thing to serve. Browser sources load from their own
+/// origin, so it has to allow any.
+fn respond(mut stream: TcpStream, served: &Mutex<String>) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut request = [0u8; 1024];
+    let _ = stream.read(&mut request)?;
+    let body = served.lock().map(|json| json.clone()).unwrap_or_default();
+    write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nAccess-Control-Allow-Origin: *\r\nCache-Control: no-store\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn live_run(time: f32) -> LiveRun {
+        LiveRun {
+            player: "me".to_owned(),
+            seed: "2025-07-07".to_owned(),
+            level_name: None,
+            state: GameState::Playing,
+            time,
+            time_remaining: None,
+            speed: 12.5,
+            leaderboard_position: Some(3),
+            leaderboard_players: 10,
+        }
+    }
+
+    #[test]
+    fn test_overlay_file_and_endpoint() {
+        let path = std::env::temp_dir().join(format!("planck_overlay_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut overlay = StreamOverlay::new(Some(path), Some(0));
+        overlay.start_run(vec![Some(5.0), None], Some(30.0));
+        overlay.handle_events(&[
+            GameplayEvent::CheckpointReached { checkpoint: 0, time: 4.5 },
+            GameplayEvent::Crashed { pos: crate::core::math::vec2::Vec2::new(0.0, 0.0), speed: 10.0, time: 5.0 },
+            GameplayEvent::CheckpointReached { checkpoint: 1, time: 9.0 },
+        ]);
+        assert_eq!(overlay.splits, vec![
+            OverlaySplit { checkpoint: 1, time: 4.5, pb_delta: Some(-0.5) },
+            OverlaySplit { checkpoint: 2, time: 9.0, pb_delta: None },
+        ]);
+
+        let start = Instant::now();
+        overlay.update(&live_run(9.0), start);
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(json["time"], 9.0);
+        assert_eq!(json["leaderboard_position"], 3);
+        assert_eq!(json["splits"][0]["pb_delta"], -0.5);
+        assert_eq!(json["pb_time"], 30.0);
+
+        // held back until the interval is up
+        overlay.update(&live_run(9.5), start + WRITE_INTERVAL / 2);
+        assert!(fs::read_to_string(path).unwrap().contains("\"time\": 9.0"));
+        overlay.update(&live_run(9.5), start + WRITE_INTERVAL);
+        assert!(fs::read_to_string(path).unwrap().contains("\"time\": 9.5"));
+
+        let mut stream = TcpStream::connect(overlay.address.unwrap()).unwrap();
+        stream.write_all(b"GET /overlay.json HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK") && response.contains("Access-Control-Allow-Origin: *"));
+        assert!(response.ends_with(&fs::read_to_string(path).unwrap()));
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+
+use crate::{core::math::{aabb2d::Aabb2d, vec2::Vec2}, game::entity::entities::{car_entity::CarEntity, checkpoint_entity::CheckpointEntity}, simulation::particles::particle_vec::ParticleVec};
+
+/// Car state captured each frame while racing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetryFrame {
+    pub frame: u128,
+    pub time: f32,
+    pub x: f32,
+    pub y: f32,
+    pub speed: f32,
+    pub distance: f32, // distance travelled along the car's path
+    pub input: f32, // 1.0 ccw, -1.0 clockwise, 0.0 none
+    pub airborne: bool,
+    pub airtime: f32, // how long the car has been airborne for
+    pub contacts: u32, // number of wheel particles touching something
+    #[serde(default)]
+    pub slip: f32, // worst wheel slip, see CarEntity::slip
+}
+
+/// Telemetry for a whole run. Saved next to the input recording as a sidecar file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TelemetryRecording {
+    pub seed: String,
+    pub finished: bool,
+    pub frames: Vec<TelemetryFrame>,
+}
+
+impl TelemetryRecording {
+    /// "recording.json" -> "recording.telemetry.json"
+    pub fn sidecar_path(recording_path: &str) -> String {
+        match recording_path.strip_suffix(".json") {
+            Some(stem) => format!("{}.telemetry.json", stem),
+            None => format!("{}.telemetry.json", recording_path),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let json = serde_json::to_string(self)?;
+        fs::write(path, json)
+    }
+
+    pub fn load(path: &str) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    pub fn total_time(&self) -> Option<f32> {
+        self.frames.last().map(|f| f.time)
+    }
+
+    pub fn total_distance(&self) -> f32 {
+        self.frames.last().map_or(0.0, |f| f.distance)
+    }
+
+    /// When the car first got into area, eg. a checkpoint, which is tested against the same position
+    pub fn time_reached(&self, area: &Aabb2d) -> Option<f32> {
+        self.frames.iter().find(|f| area.contains_point(Vec2::new(f.x, f.y))).map(|f| f.time)
+    }
+
+    /// When the car got to each checkpoint, None for those it never reached
+    pub fn checkpoint_times(&self, checkpoints: &[CheckpointEntity]) -> Vec<Option<f32>> {
+        checkpoints.iter().map(|checkpoint| self.time_reached(&checkpoint.aabb)).collect()
+    }
+
+    pub fn top_speed(&self) -> f32 {
+        self.frames.iter().map(|f| f.speed).fold(0.0, f32::max)
+    }
+
+    pub fn total_airtime(&self, time_delta: f32) -> f32 {
+        self.frames.iter().filter(|f| f.airborne).count() as f32 * time_delta
+    }
+
+    /// Average speed in each of num_buckets equal slices of distance up to max_distance.
+    /// Buckets the car never reached are None.
+    pub fn speed_over_distance(&self, max_distance: f32, num_buckets: usize) -> Vec<Option<f32>> {
+        let mut totals = vec![(0.0, 0); num_buckets];
+        if max_distance <= 0.0 || num_buckets == 0 {
+            return vec![None; num_buckets];
+        }
+
+        for frame in &self.frames {
+            let bucket = ((frame.distance / max_distance) * num_buckets as f32) as usize;
+            if let Some((total, count)) = totals.get_mut(bucket) {
+                *total += frame.speed;
+                *count += 1;
+            }
+        }
+
+        totals.iter().map(|(total, count)| if *count > 0 { Some(total / *count as f32) } else { None }).collect()
+    }
+}
+
+/// Builds up a TelemetryRecording as the run progresses
+pub struct TelemetryRecorder {
+    pub recording: TelemetryRecording,
+    last_pos: Option<Vec2>,
+    distance: f32,
+    airtime: f32,
+}
+
+impl TelemetryRecorder {
+    pub fn new(seed: String) -> Self {
+        Self {
+            recording: TelemetryRecording {
+                seed,
+                finished: false,
+                frames: vec![],
+            },
+            last_pos: None,
+            distance: 0.0,
+            airtime: 0.0,
+        }
+    }
+
+    pub fn record(&mut self, frame: u128, time: f32, time_delta: f32, car: &CarEntity, particle_vec: &ParticleVec, contacts: u32) {
+        let pos = car.get_camera_look_at_position(particle_vec);
+        if let Some(last_pos) = self.last_pos {
+            self.distance += (pos - last_pos).magnitude();
+        }
+        self.last_pos = Some(pos);
+
+        let airborne = contacts == 0;
+        self.airtime = if airborne { self.airtime + time_delta } else { 0.0 };
+
+        self.recording.frames.push(TelemetryFrame {
+            frame,
+            time,
+            x: pos.x,
+            y: pos.y,
+            speed: car.velocity(particle_vec).magnitude(),
+            distance: self.distance,
+            input: car.input_direction(),
+            airborne,
+            airtime: self.airtime,
+            contacts,
+            slip: car.slip(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TelemetryFrame, TelemetryRecording};
+
+    fn frame(distance: f32, speed: f32) -> TelemetryFrame {
+        TelemetryFrame { frame: 0, time: 0.0, x: 0.0, y: 0.0, speed, distance, input: 0.0, airborne: false, airtime: 0.0, contacts: 1, slip: 0.0 }
+    }
+
+    #[test]
+    fn sidecar_path_sits_next_to_recording() {
+        assert_eq!(TelemetryRecording::sidecar_path("recording.json"), "recording.telemetry.json");
+        assert_eq!(TelemetryRecording::sidecar_path("run"), "run.telemetry.json");
+    }
+
+    #[test]
+    fn speed_over_distance_averages_buckets() {
+        let recording = TelemetryRecording {
+            seed: String::new(),
+            finished: true,
+            frames: vec![frame(0.0, 1.0), frame(1.0, 3.0), frame(5.0, 4.0)],
+        };
+        let speeds = recording.speed_over_distance(10.0, 2);
+        assert_eq!(speeds, vec![Some(2.0), Some(4.0)]);
+
+        let speeds = recording.speed_over_distance(20.0, 4);
+        assert_eq!(speeds[2], None);
+    }
+}
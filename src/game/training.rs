@@ -0,0 +1,95 @@
+use crate::{
+    core::math::random::Random,
+    game::{entity::entity_system::EntitySystem, level::{level_builder::LevelBuilder, level_generation_info::LevelOperationInfo, level_pack::PackLevel}},
+    simulation::particles::{particle_vec::ParticleVec, simulation::Simulation},
+};
+
+/// How many takes on each block the practice picker offers
+pub const TRAINING_VARIANTS: usize = 3;
+
+const SPAWN_BLOCK: &str = "SpawnOperation";
+const FINISH_BLOCK: &str = "FinishOperation";
+const MAX_SEEDS: usize = 20; // seeds tried for distinct variants before settling for repeats, some blocks have few params to pick from
+
+/// Blocks that can be drilled on their own, everything in the registry but the spawn and finish that go around them
+pub fn training_blocks(level_builder: &LevelBuilder) -> Vec<String> {
+    level_builder.registry().iter()
+        .map(|operation| operation.type_name().to_owned())
+        .filter(|type_name| type_name != SPAWN_BLOCK && type_name != FINISH_BLOCK)
+        .collect()
+}
+
+/// The block's name to show in the picker, eg. "SaggyBridgeOperation" -> "Saggy Bridge"
+pub fn training_block_name(type_name: &str) -> String {
+    let type_name = type_name.strip_suffix("Operation").unwrap_or(type_name);
+    let mut name = String::new();
+    for c in type_name.chars() {
+        if c.is_uppercase() && !name.is_empty() {
+            name.push(' ');
+        }
+        name.push(c);
+    }
+    name
+}
+
+/// Levels with just the one block between a spawn and a finish, to drill the block that keeps ending daily runs.
+/// Each variant is the block built with params from a different seed, and they're kept different where the block
+/// has enough params to pick from. The params are written into the level so a variant plays the same every time.
+pub fn training_levels(block: &str) -> Result<Vec<PackLevel>, String> {
+    let operation = |type_name: &str| LevelOperationInfo { type_name: type_name.to_owned(), params: None };
+    let operations = [operation(SPAWN_BLOCK), operation(block), operation(FINISH_BLOCK)];
+
+    let mut variants: Vec<Vec<LevelOperationInfo>> = vec![];
+    let mut repeats = vec![];
+    for n in 0..MAX_SEEDS {
+        if variants.len() == TRAINING_VARIANTS {
+            break;
+        }
+        let seed = format!("training-{}-{}", block, n);
+        let mut sim = Simulation::new(Random::seed_from_str(&seed));
+        let level_info = LevelBuilder::default().generate_from_operations(&seed, &operations, &mut EntitySystem::new(), &mut ParticleVec::new(), &mut sim)?;
+        if variants.iter().any(|variant| variant[1] == level_info.operations[1]) {
+            repeats.push(level_info.operations);
+        } else {
+            variants.push(level_info.operations);
+        }
+    }
+    variants.extend(repeats.into_iter().take(TRAINING_VARIANTS - variants.len()));
+
+    let name = training_block_name(block);
+    Ok(variants.into_iter().enumerate()
+        .map(|(i, operations)| PackLevel { name: format!("Training: {} {}", name, i + 1), operations })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_training_levels() {
+        assert_eq!(training_block_name("SaggyBridgeOperation"), "Saggy Bridge");
+        assert_eq!(training_block_name("SandHill"), "Sand Hill");
+
+        let blocks = training_blocks(&LevelBuilder::default());
+        assert!(blocks.iter().any(|block| block == "SaggyBridgeOperation"));
+        assert!(!blocks.iter().any(|block| block == SPAWN_BLOCK || block == FINISH_BLOCK));
+
+        let levels = training_levels("SaggyBridgeOperation").unwrap();
+        assert_eq!(levels.len(), TRAINING_VARIANTS);
+        for level in &levels {
+            let type_names: Vec<&str> = level.operations.iter().map(|op| op.type_name.as_str()).collect();
+            assert_eq!(type_names, [SPAWN_BLOCK, "SaggyBridgeOperation", FINISH_BLOCK]);
+            assert!(level.operations[1].params.is_some());
+        }
+        // the bridge has enough widths to go round
+        assert_ne!(levels[0].operations[1], levels[1].operations[1]);
+        assert_ne!(levels[1].operations[1], levels[2].operations[1]);
+        assert_ne!(levels[0].operations[1], levels[2].operations[1]);
+
+        // and the same variant every time
+        assert_eq!(training_levels("SaggyBridgeOperation").unwrap(), levels);
+
+        assert!(training_levels("NoSuchBlock").is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use iced::widget::{column, text, row, container, Space};
+use iced::{Color, Element, Length, Theme, Alignment};
+use super::game_ui::{Message, GameUI};
+
+const GRAPH_HEIGHT: f32 = 200.0;
+const BAR_WIDTH: f32 = 5.0;
+
+pub(crate) fn bar<'a>(height: f32, color: Color) -> Element<'a, Message, Theme, iced::Renderer> {
+    container(Space::new())
+        .width(Length::Fixed(BAR_WIDTH))
+        .height(Length::Fixed(height.max(1.0)))
+        .style(move |_theme: &Theme| {
+            container::Style {
+                background: Some(iced::Background::Color(color)),
+                ..Default::default()
+            }
+        })
+        .into()
+}
+
+pub fn analysis_view(ui: &GameUI) -> Element<'_, Message, Theme, iced::Renderer> {
+    let theme = ui.theme;
+    let run_col = theme.positive;
+    let pb_col = theme.accent;
+
+    let mut content = column![
+        text("Run Analysis")
+            .size(40)
+            .color(theme.text),
+    ]
+    .spacing(20)
+    .align_x(Alignment::Center);
+
+    if let Some(analysis) = &ui.telemetry_analysis {
+        let mut stats = row![
+            text(format!("Top Speed: {:.1}m/s", analysis.top_speed)).color(theme.text),
+            text(format!("Distance: {:.1}m", analysis.distance)).color(theme.text),
+            text(format!("Airtime: {:.2}s", analysis.airtime)).color(theme.text),
+        ]
+        .spacing(30);
+        if let Some(pb_time) = analysis.pb_time {
+            stats = stats.push(text(format!("PB: {:.2}s", pb_time)).color(pb_col));
+        }
+        content = content.push(stats);
+
+        // speed over distance as a bar graph, this run next to the personal best
+        let max_speed = analysis.speeds.iter().chain(analysis.pb_speeds.iter()).flatten().fold(0.0f32, |a, b| a.max(*b)).max(0.1);
+        let mut graph = row![].spacing(2).align_y(Alignment::End);
+        for (i, speed) in analysis.speeds.iter().enumerate() {
+            let mut bucket = row![bar(speed.unwrap_or(0.0) / max_speed * GRAPH_HEIGHT, run_col)].align_y(Alignment::End);
+            if let Some(pb_speed) = analysis.pb_speeds.get(i) {
+                bucket = bucket.push(bar(pb_speed.unwrap_or(0.0) / max_speed * GRAPH_HEIGHT, pb_col));
+            }
+            graph = graph.push(bucket);
+        }
+
+        content = content.push(
+            container(
+                column![
+                    text("Speed over distance").color(theme.text),
+                    graph,
+                    row![
+                        text("This run").color(run_col),
+                        text("Personal best").color(pb_col),
+                    ]
+                    .spacing(20),
+                ]
+                .spacing(10)
+            )
+            .padding(20)
+            .style(move |_theme: &Theme| theme.panel_style())
+        );
+    } else {
+        content = content.push(text("No telemetry recorded for this run").color(theme.dim_text));
+    }
+
+    content = content.push(
+        text("Press 't' for the leaderboard, 'r' to retry")
+            .size(22)
+            .color(theme.accent)
+    );
+
+    container(content)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x(Length::Fill)
+        .center_y(Length::Fill)
+        .style(move |_theme: &Theme| theme.overlay_style())
+        .into()
+}
@@ -1,35 +1,280 @@
+use iced::widget::stack;
 use iced::{Element, Theme};
+use crate::core::math::random::Random;
+use crate::engine::app::event_system::HeldInput;
 use crate::game::game_state::GameState;
 use crate::game::leaderboard::LeaderboardEntry;
+use crate::game::level_pack_browser::LevelPackListing;
+use crate::game::nickname::random_nickname;
+use crate::game::physics_tuning::PhysicsTuning;
+use crate::game::splits::Split;
+use crate::game::ui::analysis::analysis_view;
+use crate::game::ui::ghost_camera::{ghost_camera_view, GhostCameraInfo};
 use crate::game::ui::hud::hud_view;
 use crate::game::ui::leaderboard::leaderboard_view;
+use crate::game::ui::level_editor::level_editor_view;
+use crate::game::ui::level_packs::level_packs_view;
 use crate::game::ui::name_entry::name_entry_view;
+use crate::game::ui::new_daily::new_daily_view;
+use crate::game::ui::pre_race::pre_race_view;
+use crate::game::ui::theme::UiTheme;
+use crate::game::ui::toast::toast_view;
+use crate::game::ui::tuning::tuning_view;
 
+/// Lap progress for multi-lap mode
+#[derive(Debug, Clone, Default)]
+pub struct LapInfo {
+    pub current_lap: u32,
+    pub num_laps: u32,
+    pub lap_times: Vec<f32>,
+    pub best_lap: Option<f32>, // best lap this session
+}
+
+/// Countdown state for time attack mode
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TimeAttackInfo {
+    pub time_remaining: f32,
+    pub checkpoints_reached: u32,
+    pub num_checkpoints: u32,
+    pub out_of_time: bool,
+}
+
+/// Post run telemetry summary for the analysis view
+#[derive(Debug, Clone, Default)]
+pub struct TelemetryAnalysis {
+    pub speeds: Vec<Option<f32>>, // average speed per distance bucket
+    pub pb_speeds: Vec<Option<f32>>, // same buckets for the personal best run
+    pub top_speed: f32,
+    pub distance: f32,
+    pub airtime: f32,
+    pub pb_time: Option<f32>,
+}
+
+/// Solver iterations and constraint error from the last step, see SolverStats
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SolverInfo {
+    pub iterations: i32,
+    pub max_iterations: i32,
+    pub initial_residual: f32,
+    pub residual: f32,
+    pub clamped: usize, // particles slowed to the solver's max speed
+    pub quarantined: usize, // particles frozen after going NaN
+}
+
+/// Energy and momentum drift for the debug overlay, see EnergyDiagnostics
+#[derive(Debug, Clone, Default)]
+pub struct EnergyInfo {
+    pub kinetic: f32,
+    pub potential: f32,
+    pub energy_drift: f32,
+    pub relative_energy_drift: f32,
+    pub momentum_drift: (f32, f32),
+    pub drift_history: Vec<f32>, // oldest first
+}
+
+/// Who else is playing, from the IRC presence tracker
+#[derive(Debug, Clone, Default)]
+pub struct PresenceInfo {
+    pub online: usize,
+    pub racing: usize, // racing the same seed as us
+    pub recent_finishers: Vec<(String, f32)>,
+}
+
+/// Rolling skill rating from daily placements
+#[derive(Debug, Clone, Default)]
+pub struct RatingInfo {
+    pub rating: f32,
+    pub change: f32, // change from the most recent placement
+    pub days: usize,
+}
+
+/// An installed level pack for the level packs view
+#[derive(Debug, Clone, Default)]
+pub struct LevelPackSummary {
+    pub id: String,
+    pub name: String,
+    pub author: String,
+    pub level_names: Vec<String>,
+}
+
+/// Another player's run that can be raced as a ghost
+#[derive(Debug, Clone, Default)]
+pub struct GhostOffer {
+    pub name: String,
+    pub time: Option<f32>,
+    pub ready: bool, // their run has been downloaded
+    pub selected: bool,
+    pub issue: Option<String>, // why their run can't be raced, eg. it was recorded on a different level generator
+}
+
+/// This session's attempts at the current seed, for the finish screen
+#[derive(Debug, Clone, Default)]
+pub struct AttemptInfo {
+    pub attempt: usize, // number of the latest attempt, from 1
+    pub submitted: bool, // the latest attempt was posted to the leaderboard
+    pub best_time: Option<f32>, // best attempt this session
+}
+
+/// The demo scene sandbox tools, see Sandbox
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SandboxInfo {
+    pub brush: bool, // painting particles rather than selecting them
+    pub selected: usize,
+    pub material: String, // what's painted, and what M changes the selection to
+    pub scene_file: Option<String>, // the scene file F7 loads again
+}
+
+/// The level being put together in the editor scene, see LevelEditor
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LevelEditorInfo {
+    pub path: String, // where Save writes the level
+    pub blocks: Vec<String>, // type of each block, in order
+    pub selected: Option<usize>,
+    pub block_type: String, // what Add puts in
+}
+
+/// Which leaderboard the results screen is showing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LeaderboardTab {
+    #[default]
+    Global,
+    Private, // the private channel from settings
+    Friends, // the global leaderboard filtered to friends
+}
 
 #[derive(Debug, Clone)]
 pub struct GameUI {
     pub(crate) fps: i32,
     pub(crate) total_time: f32,
+    pub(crate) last_split: Option<Split>, // the last checkpoint crossed this run, against my best
     pub(crate) simulation_time_ms: f32,
+    pub(crate) solver_info: SolverInfo,
     pub(crate) update_time_ms: f32,
     pub(crate) render_time_ms: f32,
+    pub(crate) render_scale: f32, // fraction of the window's resolution the scene is rendered at, see DynamicResolution
+    pub(crate) gpu_timings: Vec<(&'static str, f32)>, // GPU ms per render pass, empty without timestamp query support
     pub(crate) game_state: GameState,
     pub(crate) leaderboard_results: Vec<LeaderboardEntry>,
+    pub(crate) private_leaderboard_results: Vec<LeaderboardEntry>,
+    pub(crate) private_channel: Option<String>,
+    pub(crate) leaderboard_tab: LeaderboardTab,
+    pub(crate) leaderboard_page: usize,
+    pub(crate) leaderboard_search: String,
+    pub(crate) leaderboard_search_active: bool, // search box is open, so hotkeys are ignored while typing
+    pub(crate) friends_only: bool,
+    pub(crate) friends: Vec<String>,
+    pub(crate) toast: Option<String>,
+    pub(crate) rating_info: Option<RatingInfo>,
+    pub(crate) practice: bool,
+    pub(crate) show_level_packs: bool,
+    pub(crate) level_packs: Vec<LevelPackSummary>,
+    pub(crate) training_blocks: Vec<String>, // block type names that can be practised on their own
+    pub(crate) level_pack_listings: Vec<LevelPackListing>,
+    pub(crate) level_name: Option<String>, // the level pack level, or the daily's name from its seed
+    pub(crate) ghost_offers: Vec<GhostOffer>,
     pub(crate) name_input: String,
+    pub(crate) seed_input: String, // a custom seed to play instead of the daily
+    pub(crate) seed_entry_active: bool, // typing a seed in the level packs panel
     pub(crate) show_debug_info: bool,
+    pub(crate) lap_info: Option<LapInfo>,
+    pub(crate) time_attack_info: Option<TimeAttackInfo>,
+    pub(crate) telemetry_analysis: Option<TelemetryAnalysis>,
+    pub(crate) energy_info: Option<EnergyInfo>, // only with --diagnostics
+    pub(crate) show_analysis: bool,
+    pub(crate) presence_info: Option<PresenceInfo>,
+    pub(crate) offline: Option<usize>, // None while IRC can be reached, otherwise the number of times waiting to be posted
+    pub(crate) theme: UiTheme,
+    pub(crate) next_daily_in: chrono::Duration,
+    pub(crate) new_daily_available: bool, // the seed rolled over while the game was open
+    pub(crate) clock_warning: Option<String>, // the local clock is off, so the daily seed may be wrong
+    pub(crate) attempt_info: Option<AttemptInfo>,
+    pub(crate) ghost_exported: Option<bool>, // None when the run can't be exported as a ghost track, otherwise whether it has been
+    pub(crate) ghost_camera: Option<GhostCameraInfo>, // picture in picture from a ghost car while racing it
+    pub(crate) slip: f32, // my car's worst wheel slip, 0 to 1
+    pub(crate) replay_input: Option<HeldInput>, // what a replay being watched is pressing
+    pub(crate) physics_tuning: Option<PhysicsTuning>, // only with --tuning
+    pub(crate) sandbox_info: Option<SandboxInfo>, // only in demo scenes
+    pub(crate) level_editor_info: Option<LevelEditorInfo>, // only in the editor scene
+    pub(crate) editor_params: Vec<(String, String)>, // the selected block's params, name and value as text
+    dirty: bool, // something on screen changed since the view was last built
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     UpdateFps(i32),
     UpdateTime(f32),
+    UpdateSplit(Option<Split>),
     UpdateSimulationTime(f32),
+    UpdateSolverInfo(SolverInfo),
     UpdateUpdateTime(f32),
     UpdateRenderTime(f32),
+    UpdateRenderScale(f32),
+    UpdateGpuTimings(Vec<(&'static str, f32)>),
     UpdateGameState(GameState),
     UpdateLeaderboardResults(Vec<LeaderboardEntry>),
+    UpdatePrivateLeaderboardResults(Vec<LeaderboardEntry>),
+    UpdatePrivateChannel(Option<String>),
+    SelectLeaderboardTab(LeaderboardTab),
+    SelectLeaderboardPage(usize),
+    UpdateLeaderboardSearch(String),
+    ToggleLeaderboardSearch,
+    ToggleFriendsOnly,
+    UpdateFriends(Vec<String>),
+    ToggleFriend(String),
+    UpdateToast(Option<String>),
+    UpdateRatingInfo(Option<RatingInfo>),
+    UpdatePractice(bool),
+    UpdateShowLevelPacks(bool),
+    UpdateLevelPacks(Vec<LevelPackSummary>),
+    UpdateLevelPackListings(Vec<LevelPackListing>),
+    UpdateLevelName(Option<String>),
+    DownloadLevelPack(LevelPackListing),
+    PlayPackLevel(String, usize), // pack id, level index
+    UpdateTrainingBlocks(Vec<String>),
+    PlayTraining(String, usize), // block type name, variant
+    PlayDaily,
+    UpdateSeedInput(String),
+    ToggleSeedEntry,
+    PlaySeed,
+    SaveLevelToMyLevels,
+    UpdateGhostOffers(Vec<GhostOffer>),
+    ToggleGhost(String),
+    StartRace,
     UpdateNameInput(String),
+    RandomizeName,
     UpdateShowDebugInfo(bool),
+    UpdateLapInfo(Option<LapInfo>),
+    UpdateTimeAttackInfo(Option<TimeAttackInfo>),
+    UpdateTelemetryAnalysis(Option<TelemetryAnalysis>),
+    UpdateEnergyInfo(Option<EnergyInfo>),
+    UpdateShowAnalysis(bool),
+    UpdatePresenceInfo(Option<PresenceInfo>),
+    UpdateOffline(Option<usize>),
+    UpdateTheme(UiTheme),
+    UpdateNextDailyIn(chrono::Duration),
+    UpdateNewDailyAvailable(bool),
+    UpdateClockWarning(Option<String>),
+    PlayNewDaily,
+    UpdateAttemptInfo(Option<AttemptInfo>),
+    UpdateGhostExported(Option<bool>),
+    UpdateGhostCamera(Option<GhostCameraInfo>),
+    UpdateSlip(f32),
+    UpdateReplayInput(Option<HeldInput>),
+    UpdatePhysicsTuning(Option<PhysicsTuning>),
+    TunePhysics(PhysicsTuning),
+    UpdateSandboxInfo(Option<SandboxInfo>),
+    UpdateLevelEditorInfo(Option<LevelEditorInfo>),
+    UpdateEditorParams(Vec<(String, String)>),
+    EditEditorParam(usize, String), // param index, new value
+    SelectEditorBlock(usize),
+    MoveEditorBlock(isize), // -1 earlier, 1 later
+    DeleteEditorBlock,
+    CycleEditorBlockType(isize),
+    AddEditorBlock,
+    ApplyEditorParams,
+    SaveEditorLevel,
+    SubmitRun,
+    ExportGhost,
     SubmitName,
 }
 
@@ -38,36 +283,192 @@ impl GameUI {
         Self {
             fps: 60,
             total_time: 0.0,
+            last_split: None,
             simulation_time_ms: 0.0,
+            solver_info: SolverInfo::default(),
             update_time_ms: 0.0,
             render_time_ms: 0.0,
+            render_scale: 1.0,
+            gpu_timings: Vec::new(),
             game_state: GameState::Playing,
             leaderboard_results: Vec::new(),
+            private_leaderboard_results: Vec::new(),
+            private_channel: None,
+            leaderboard_tab: LeaderboardTab::Global,
+            leaderboard_page: 0,
+            leaderboard_search: String::new(),
+            leaderboard_search_active: false,
+            friends_only: false,
+            friends: Vec::new(),
+            toast: None,
+            rating_info: None,
+            practice: false,
+            show_level_packs: false,
+            level_packs: Vec::new(),
+            training_blocks: Vec::new(),
+            level_pack_listings: Vec::new(),
+            level_name: None,
+            ghost_offers: Vec::new(),
             name_input: String::new(),
+            seed_input: String::new(),
+            seed_entry_active: false,
             show_debug_info: true,
+            lap_info: None,
+            time_attack_info: None,
+            telemetry_analysis: None,
+            energy_info: None,
+            show_analysis: false,
+            presence_info: None,
+            offline: None,
+            theme: UiTheme::default(),
+            next_daily_in: chrono::Duration::zero(),
+            new_daily_available: false,
+            clock_warning: None,
+            attempt_info: None,
+            ghost_exported: None,
+            ghost_camera: None,
+            slip: 0.0,
+            replay_input: None,
+            physics_tuning: None,
+            sandbox_info: None,
+            level_editor_info: None,
+            editor_params: Vec::new(),
+            dirty: true,
         }
     }
 
+    /// Whether the view needs building again since it was last built. Messages only mark it dirty when
+    /// what they change is on screen, and any number of them cost one rebuild.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Call when the view is built
+    pub fn clear_dirty(&mut self) {
+        self.dirty = false;
+    }
+
+    /// Debug stats arrive every frame but are only shown on the HUD with debug info turned on
+    fn shows_message(&self, message: &Message) -> bool {
+        let is_debug_stat = matches!(message,
+            Message::UpdateFps(_) | Message::UpdateSimulationTime(_) | Message::UpdateSolverInfo(_) | Message::UpdateUpdateTime(_)
+            | Message::UpdateRenderTime(_) | Message::UpdateRenderScale(_) | Message::UpdateGpuTimings(_) | Message::UpdateEnergyInfo(_));
+        !is_debug_stat || (self.show_debug_info && self.game_state == GameState::Playing)
+    }
+
     pub fn update(&mut self, message: Message) {
+        self.dirty |= self.shows_message(&message);
         match message {
             Message::UpdateFps(fps) => self.fps = fps,
             Message::UpdateTime(time) => self.total_time = time,
+            Message::UpdateSplit(split) => self.last_split = split,
             Message::UpdateSimulationTime(time) => self.simulation_time_ms = time,
+            Message::UpdateSolverInfo(info) => self.solver_info = info,
             Message::UpdateUpdateTime(time) => self.update_time_ms = time,
             Message::UpdateRenderTime(time) => self.render_time_ms = time,
+            Message::UpdateRenderScale(scale) => self.render_scale = scale,
+            Message::UpdateGpuTimings(timings) => self.gpu_timings = timings,
             Message::UpdateGameState(state) => self.game_state = state,
             Message::UpdateLeaderboardResults(results) => self.leaderboard_results = results,
+            Message::UpdatePrivateLeaderboardResults(results) => self.private_leaderboard_results = results,
+            Message::UpdatePrivateChannel(channel) => self.private_channel = channel,
+            Message::SelectLeaderboardTab(tab) => {
+                self.leaderboard_tab = tab;
+                self.leaderboard_page = 0;
+            }
+            Message::SelectLeaderboardPage(page) => self.leaderboard_page = page,
+            Message::UpdateLeaderboardSearch(search) => {
+                self.leaderboard_search = search;
+                self.leaderboard_page = 0;
+            }
+            Message::ToggleLeaderboardSearch => self.leaderboard_search_active = !self.leaderboard_search_active,
+            Message::ToggleFriendsOnly => {
+                self.friends_only = !self.friends_only;
+                self.leaderboard_page = 0;
+            }
+            Message::UpdateFriends(friends) => self.friends = friends,
+            Message::ToggleFriend(_) => {} // Handled by Game
+            Message::UpdateToast(toast) => self.toast = toast,
+            Message::UpdateRatingInfo(rating_info) => self.rating_info = rating_info,
+            Message::UpdatePractice(practice) => self.practice = practice,
+            Message::UpdateShowLevelPacks(show) => self.show_level_packs = show,
+            Message::UpdateLevelPacks(level_packs) => self.level_packs = level_packs,
+            Message::UpdateTrainingBlocks(training_blocks) => self.training_blocks = training_blocks,
+            Message::UpdateLevelPackListings(listings) => self.level_pack_listings = listings,
+            Message::UpdateLevelName(name) => self.level_name = name,
+            Message::DownloadLevelPack(_) | Message::PlayPackLevel(_, _) | Message::PlayTraining(_, _) | Message::PlayDaily | Message::PlaySeed | Message::SaveLevelToMyLevels => {} // Handled by Game
+            Message::UpdateSeedInput(seed) => self.seed_input = seed,
+            Message::ToggleSeedEntry => self.seed_entry_active = !self.seed_entry_active,
+            Message::UpdateGhostOffers(offers) => self.ghost_offers = offers,
+            Message::ToggleGhost(_) | Message::StartRace => {} // Handled by Game
             Message::UpdateNameInput(name) => self.name_input = name,
+            Message::RandomizeName => self.name_input = random_nickname(&mut Random::seed_from_now()),
             Message::UpdateShowDebugInfo(show) => self.show_debug_info = show,
+            Message::UpdateLapInfo(lap_info) => self.lap_info = lap_info,
+            Message::UpdateTimeAttackInfo(time_attack_info) => self.time_attack_info = time_attack_info,
+            Message::UpdateTelemetryAnalysis(analysis) => self.telemetry_analysis = analysis,
+            Message::UpdateEnergyInfo(info) => self.energy_info = info,
+            Message::UpdateShowAnalysis(show) => self.show_analysis = show,
+            Message::UpdatePresenceInfo(presence_info) => self.presence_info = presence_info,
+            Message::UpdateOffline(offline) => self.offline = offline,
+            Message::UpdateTheme(theme) => self.theme = theme,
+            Message::UpdateNextDailyIn(duration) => self.next_daily_in = duration,
+            Message::UpdateNewDailyAvailable(available) => self.new_daily_available = available,
+            Message::UpdateClockWarning(warning) => self.clock_warning = warning,
+            Message::PlayNewDaily => {} // Handled by Game
+            Message::UpdateAttemptInfo(attempt_info) => self.attempt_info = attempt_info,
+            Message::UpdateGhostExported(ghost_exported) => self.ghost_exported = ghost_exported,
+            Message::UpdateGhostCamera(ghost_camera) => self.ghost_camera = ghost_camera,
+            Message::UpdateSlip(slip) => self.slip = slip,
+            Message::UpdateReplayInput(replay_input) => self.replay_input = replay_input,
+            Message::UpdatePhysicsTuning(physics_tuning) => self.physics_tuning = physics_tuning,
+            Message::TunePhysics(_) => {} // Handled by Game
+            Message::UpdateSandboxInfo(sandbox_info) => self.sandbox_info = sandbox_info,
+            Message::UpdateLevelEditorInfo(level_editor_info) => self.level_editor_info = level_editor_info,
+            Message::UpdateEditorParams(params) => self.editor_params = params,
+            Message::EditEditorParam(index, value) => {
+                if let Some((_, param)) = self.editor_params.get_mut(index) {
+                    *param = value;
+                }
+            }
+            Message::SelectEditorBlock(_) | Message::MoveEditorBlock(_) | Message::DeleteEditorBlock | Message::CycleEditorBlockType(_)
+            | Message::AddEditorBlock | Message::ApplyEditorParams | Message::SaveEditorLevel => {} // Handled by Game
+            Message::SubmitRun => {} // Handled by Game
+            Message::ExportGhost => {} // Handled by Game
             Message::SubmitName => {} // Handled by Game
         }
     }
 
     pub fn view(&self) -> Element<'_, Message, Theme, iced::Renderer> {
-        match self.game_state {
+        let view = match self.game_state {
             GameState::NameEntry => name_entry_view(self),
+            GameState::PreRace => pre_race_view(self),
+            GameState::Finished if self.show_level_packs => level_packs_view(self),
+            GameState::Finished if self.show_analysis => analysis_view(self),
             GameState::Finished => leaderboard_view(self),
             GameState::Playing => hud_view(self),
+        };
+        let view = match &self.ghost_camera {
+            Some(ghost_camera) if self.game_state == GameState::Playing => stack![view, ghost_camera_view(ghost_camera, self.theme)].into(),
+            _ => view,
+        };
+        let view = match self.physics_tuning {
+            Some(physics_tuning) if self.game_state == GameState::Playing => stack![view, tuning_view(physics_tuning, self.theme)].into(),
+            _ => view,
+        };
+        let view = match &self.level_editor_info {
+            Some(level_editor_info) if self.game_state == GameState::Playing => stack![view, level_editor_view(level_editor_info, &self.editor_params, self.theme)].into(),
+            _ => view,
+        };
+        let view = match &self.toast {
+            Some(toast) => stack![view, toast_view(toast, self.theme)].into(),
+            None => view,
+        };
+        // the new daily prompt waits until the name has been entered
+        if self.new_daily_available && self.game_state != GameState::NameEntry {
+            stack![view, new_daily_view(self.theme)].into()
+        } else {
+            view
         }
     }
 }
@@ -0,0 +1,166 @@
+use iced::widget::shader::{self, Viewport};
+use iced::widget::{column, container, text};
+use iced::{mouse, Element, Length, Rectangle, Theme, Alignment};
+use super::game_ui::Message;
+use super::theme::UiTheme;
+
+/// The ghost camera texture and whose car it is following
+#[derive(Debug, Clone)]
+pub struct GhostCameraInfo {
+    pub name: String,
+    pub view: wgpu::TextureView,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The view from a ghost car, picture in picture in the bottom right corner
+pub fn ghost_camera_view(info: &GhostCameraInfo, theme: UiTheme) -> Element<'_, Message, Theme, iced::Renderer> {
+    container(
+        container(
+            column![
+                text(&info.name).size(14).color(theme.text),
+                shader::Shader::new(GhostCameraProgram { view: info.view.clone() })
+                    .width(info.width as f32)
+                    .height(info.height as f32),
+            ]
+            .spacing(4)
+        )
+        .padding(5)
+        .style(move |_theme: &Theme| theme.panel_style())
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .padding(20)
+    .align_x(Alignment::End)
+    .align_y(Alignment::End)
+    .into()
+}
+
+struct GhostCameraProgram {
+    view: wgpu::TextureView,
+}
+
+impl shader::Program<Message> for GhostCameraProgram {
+    type State = ();
+    type Primitive = GhostCameraPrimitive;
+
+    fn draw(&self, _state: &Self::State, _cursor: mouse::Cursor, _bounds: Rectangle) -> Self::Primitive {
+        GhostCameraPrimitive { view: self.view.clone() }
+    }
+}
+
+#[derive(Debug)]
+struct GhostCameraPrimitive {
+    view: wgpu::TextureView,
+}
+
+impl shader::Primitive for GhostCameraPrimitive {
+    type Pipeline = GhostCameraPipeline;
+
+    fn prepare(&self, pipeline: &mut Self::Pipeline, device: &wgpu::Device, _queue: &wgpu::Queue, _bounds: &Rectangle, _viewport: &Viewport) {
+        // cheap enough to remake each frame, and it picks up a new texture if the ghost camera is recreated
+        pipeline.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &pipeline.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&self.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&pipeline.sampler),
+                },
+            ],
+            label: Some("ghost_camera_bind_group"),
+        }));
+    }
+
+    fn draw(&self, pipeline: &Self::Pipeline, render_pass: &mut wgpu::RenderPass<'_>) -> bool {
+        let Some(bind_group) = &pipeline.bind_group else { return true };
+        render_pass.set_pipeline(&pipeline.render_pipeline);
+        render_pass.set_bind_group(0, bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+        true
+    }
+}
+
+/// Copies the ghost camera texture into the UI
+struct GhostCameraPipeline {
+    render_pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    bind_group: Option<wgpu::BindGroup>,
+}
+
+impl shader::Pipeline for GhostCameraPipeline {
+    fn new(device: &wgpu::Device, _queue: &wgpu::Queue, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("ghost_camera.wgsl"));
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("ghost_camera_bind_group_layout"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Ghost Camera Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Ghost Camera Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            render_pipeline,
+            bind_group_layout,
+            sampler,
+            bind_group: None,
+        }
+    }
+}
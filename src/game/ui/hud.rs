@@ -1,37 +1,261 @@
-use iced::widget::{column, text, container};
-use iced::{Color, Element, Length, Theme, Alignment};
+use iced::widget::{column, row, text, container};
+use iced::{Element, Length, Theme, Alignment};
+use super::analysis::bar;
 use super::game_ui::{Message, GameUI};
+use crate::engine::app::event_system::{GamepadAxisType, KeyCodeType};
+use crate::game::entity::entities::car_entity::{PEAK_SLIP, SPINNING_SLIP};
+
+const DRIFT_GRAPH_HEIGHT: f32 = 40.0;
+
+/// The car's keys as shown on the replay input overlay, see CarEntity::handle_key
+const CAR_KEYS: [(KeyCodeType, &str); 5] = [
+    (KeyCodeType::KeyZ, "Z left"),
+    (KeyCodeType::KeyX, "X right"),
+    (KeyCodeType::KeyQ, "Q lean"),
+    (KeyCodeType::KeyE, "E lean"),
+    (KeyCodeType::KeyC, "C winch"),
+];
+
+/// Sticks and triggers the car reads, see CarEntity::handle_axis
+const CAR_AXES: [(GamepadAxisType, &str); 3] = [
+    (GamepadAxisType::RightTrigger, "Throttle"),
+    (GamepadAxisType::LeftTrigger, "Reverse"),
+    (GamepadAxisType::LeftStickX, "Lean"),
+];
 
 pub fn hud_view(ui: &GameUI) -> Element<'_, Message, Theme, iced::Renderer> {
+    let theme = ui.theme;
     let mut content = column![].padding(10).spacing(2);
 
     content = content.push(
         text(format!("Time: {:.2}s", ui.total_time))
             .size(18)
-            .color(Color::WHITE)
+            .color(theme.text)
     );
 
+    // ahead of my best in green, behind in red, and the fastest I've been through the sector stands out
+    if let Some(split) = &ui.last_split {
+        let delta = split.pb_delta.map_or(String::new(), |delta| format!(" {:+.2}", delta));
+        let best = if split.best_sector { ", best sector" } else { "" };
+        let color = match split.pb_delta {
+            _ if split.best_sector => theme.notice,
+            Some(delta) if delta < 0.0 => theme.positive,
+            Some(_) => theme.negative,
+            None => theme.dim_text,
+        };
+        content = content.push(
+            text(format!("Split {}: {:.2}s{}{}", split.sector + 1, split.time, delta, best))
+                .size(15)
+                .color(color)
+        );
+    }
+
+    // wheelspin, ease off the throttle when it goes red
+    let slip_color = if ui.slip >= SPINNING_SLIP { theme.negative } else if ui.slip > PEAK_SLIP { theme.warning } else { theme.dim_text };
+    content = content.push(
+        text(format!("Slip: {:.0}%", ui.slip * 100.0))
+            .size(15)
+            .color(slip_color)
+    );
+
+    // what the replay is pressing, held keys lit up
+    if let Some(replay_input) = &ui.replay_input {
+        let mut keys = row![].spacing(8);
+        for (key_code, label) in CAR_KEYS {
+            let color = if replay_input.is_pressed(key_code) { theme.positive } else { theme.dim_text };
+            keys = keys.push(text(label).size(15).color(color));
+        }
+        for (axis, label) in CAR_AXES {
+            let value = replay_input.axis(axis);
+            if value != 0.0 {
+                keys = keys.push(text(format!("{} {:+.0}%", label, value * 100.0)).size(15).color(theme.positive));
+            }
+        }
+        content = content.push(keys);
+    }
+
+    if let Some(name) = &ui.level_name {
+        content = content.push(
+            text(name)
+                .size(15)
+                .color(theme.accent)
+        );
+    }
+
+    if let Some(warning) = &ui.clock_warning {
+        content = content.push(
+            text(warning)
+                .size(15)
+                .color(theme.warning)
+        );
+    }
+
+    if ui.practice {
+        content = content.push(
+            text("Practice: F5 save, F9 load, F8 older save")
+                .size(15)
+                .color(theme.warning)
+        );
+    }
+
+    if let Some(sandbox_info) = &ui.sandbox_info {
+        let tool = if sandbox_info.brush { "Brush: drag to paint" } else { "Select: drag a rectangle" };
+        content = content.push(
+            text(format!("{}, B to switch tools", tool))
+                .size(15)
+                .color(theme.warning)
+        );
+        content = content.push(
+            text(format!("{} selected: Delete remove, F freeze, I push to cursor, M material ({})", sandbox_info.selected, sandbox_info.material))
+                .size(15)
+                .color(theme.dim_text)
+        );
+        let scene = match &sandbox_info.scene_file {
+            Some(scene_file) => format!("F6 save scene, F7 reload {}", scene_file),
+            None => String::from("F6 save scene"),
+        };
+        content = content.push(
+            text(scene)
+                .size(15)
+                .color(theme.dim_text)
+        );
+    }
+
+    if let Some(time_attack_info) = &ui.time_attack_info {
+        let color = if time_attack_info.time_remaining < 5.0 { theme.negative } else { theme.text };
+        content = content.push(
+            text(format!("Time Left: {:.1}s", time_attack_info.time_remaining.max(0.0)))
+                .size(28)
+                .color(color)
+        );
+        content = content.push(
+            text(format!("Checkpoints: {}/{}", time_attack_info.checkpoints_reached, time_attack_info.num_checkpoints))
+                .size(15)
+                .color(theme.text)
+        );
+    }
+
+    if let Some(lap_info) = &ui.lap_info {
+        content = content.push(
+            text(format!("Lap: {}/{}", lap_info.current_lap, lap_info.num_laps))
+                .size(18)
+                .color(theme.text)
+        );
+        for (i, lap_time) in lap_info.lap_times.iter().enumerate() {
+            content = content.push(
+                text(format!("Lap {}: {:.2}s", i + 1, lap_time))
+                    .size(15)
+                    .color(theme.text)
+            );
+        }
+        if let Some(best_lap) = lap_info.best_lap {
+            content = content.push(
+                text(format!("Best Lap: {:.2}s", best_lap))
+                    .size(15)
+                    .color(theme.positive)
+            );
+        }
+    }
+
+    if let Some(rating_info) = ui.rating_info.as_ref().filter(|info| info.days > 0) {
+        content = content.push(
+            text(format!("Rating: {:.0}", rating_info.rating))
+                .size(15)
+                .color(theme.notice)
+        );
+    }
+
+    if let Some(presence_info) = &ui.presence_info {
+        content = content.push(
+            text(format!("Racing now: {} ({} online)", presence_info.racing, presence_info.online))
+                .size(15)
+                .color(theme.accent)
+        );
+        if !presence_info.recent_finishers.is_empty() {
+            let ticker = presence_info.recent_finishers.iter()
+                .map(|(user, time)| format!("{} {:.2}s", user, time))
+                .collect::<Vec<_>>()
+                .join("  ·  ");
+            content = content.push(
+                text(format!("Recent: {}", ticker))
+                    .size(15)
+                    .color(theme.accent)
+            );
+        }
+    }
+
     if ui.show_debug_info {
         content = content.push(
             text(format!("FPS: {}", ui.fps))
                 .size(15)
-                .color(Color::WHITE)
+                .color(theme.text)
         );
         content = content.push(
             text(format!("Update: {:.2}ms", ui.update_time_ms))
                 .size(15)
-                .color(Color::WHITE)
+                .color(theme.text)
         );
         content = content.push(
             text(format!("Sim: {:.2}ms", ui.simulation_time_ms))
                 .size(15)
-                .color(Color::WHITE)
+                .color(theme.text)
+        );
+        let solver_info = &ui.solver_info;
+        content = content.push(
+            text(format!("Solver: {}/{} iterations, residual {:.2}mm (from {:.2}mm)", solver_info.iterations, solver_info.max_iterations, solver_info.residual * 1000.0, solver_info.initial_residual * 1000.0))
+                .size(15)
+                .color(theme.text)
         );
+        if solver_info.clamped > 0 || solver_info.quarantined > 0 {
+            content = content.push(
+                text(format!("Solver containment: {} quarantined, {} slowed to max speed", solver_info.quarantined, solver_info.clamped))
+                    .size(15)
+                    .color(theme.negative)
+            );
+        }
         content = content.push(
-            text(format!("Render: {:.2}ms", ui.render_time_ms))
+            text(format!("Render: {:.2}ms at {:.0}% resolution", ui.render_time_ms, ui.render_scale * 100.0))
                 .size(15)
-                .color(Color::WHITE)
+                .color(theme.text)
         );
+        if !ui.gpu_timings.is_empty() {
+            let passes = ui.gpu_timings.iter()
+                .map(|(pass, time)| format!("{} {:.2}ms", pass, time))
+                .collect::<Vec<_>>()
+                .join(", ");
+            content = content.push(
+                text(format!("GPU: {}", passes))
+                    .size(15)
+                    .color(theme.text)
+            );
+        }
+
+        if let Some(energy_info) = &ui.energy_info {
+            content = content.push(
+                text(format!("Energy: {:.1} (kinetic {:.1}, potential {:.1})", energy_info.kinetic + energy_info.potential, energy_info.kinetic, energy_info.potential))
+                    .size(15)
+                    .color(theme.text)
+            );
+            content = content.push(
+                text(format!("Energy drift: {:+.2} ({:+.2}%)", energy_info.energy_drift, energy_info.relative_energy_drift * 100.0))
+                    .size(15)
+                    .color(theme.text)
+            );
+            content = content.push(
+                text(format!("Momentum drift: ({:+.3}, {:+.3})", energy_info.momentum_drift.0, energy_info.momentum_drift.1))
+                    .size(15)
+                    .color(theme.text)
+            );
+
+            // energy drift over time, gains in red as the solver should never add energy
+            let max_drift = energy_info.drift_history.iter().fold(0.0f32, |a, b| a.max(b.abs())).max(1e-3);
+            let mut graph = row![].spacing(1).align_y(Alignment::End);
+            for drift in &energy_info.drift_history {
+                let color = if *drift > 0.0 { theme.negative } else { theme.accent };
+                graph = graph.push(bar(drift.abs() / max_drift * DRIFT_GRAPH_HEIGHT, color));
+            }
+            content = content.push(graph);
+        }
     }
 
     container(content)
@@ -39,5 +263,11 @@ pub fn hud_view(ui: &GameUI) -> Element<'_, Message, Theme, iced::Renderer> {
         .height(Length::Fill)
         .align_x(Alignment::Start)
         .align_y(Alignment::Start)
+        .style(move |_theme: &Theme| {
+            container::Style {
+                background: Some(iced::Background::Color(theme.hud)),
+                ..Default::default()
+            }
+        })
         .into()
 }
@@ -1,64 +1,295 @@
-use iced::widget::{column, text, row, container};
-use iced::{Color, Element, Length, Theme, Alignment};
-use super::game_ui::{Message, GameUI};
+use iced::widget::{button, column, text, text_input, row, container};
+use iced::{Element, Length, Theme, Alignment};
+use crate::game::entity::entities::car_entity::STANDARD_CAR;
+use crate::game::leaderboard::{LeaderboardEntry, ScoreAnnotations, Verification};
+use crate::game::seed_policy::format_countdown;
+use super::game_ui::{Message, GameUI, LeaderboardTab};
+
+const LEADERBOARD_PAGE_SIZE: usize = 10;
+
+/// A badge after a time on the board, for what the time was set with
+#[derive(Debug, Clone, PartialEq)]
+pub enum Badge {
+    Verified,
+    Rejected,
+    Ghost,
+    Car(String),
+    Modifier(String),
+}
+
+impl Badge {
+    pub fn label(&self) -> String {
+        match self {
+            Badge::Verified => String::from("V"),
+            Badge::Rejected => String::from("X"),
+            Badge::Ghost => String::from("G"),
+            Badge::Car(car) => car.chars().next().map_or(String::new(), |c| c.to_uppercase().to_string()),
+            Badge::Modifier(modifier) => match modifier.as_str() {
+                "mirror" => String::from("Mi"),
+                "headwind" => String::from("Hw"),
+                "tailwind" => String::from("Tw"),
+                "timeattack" => String::from("TA"),
+                _ => match modifier.strip_prefix("laps") {
+                    Some(laps) => format!("{}L", laps),
+                    None => modifier.chars().take(2).collect(), // one from a newer client
+                },
+            },
+        }
+    }
+}
+
+/// The badges for a time, verification first as it matters most
+pub fn annotation_badges(annotations: &ScoreAnnotations) -> Vec<Badge> {
+    let mut badges = vec![];
+    match annotations.verification {
+        Verification::Unchecked => {}
+        Verification::Verified => badges.push(Badge::Verified),
+        Verification::Rejected => badges.push(Badge::Rejected),
+    }
+    if annotations.ghost {
+        badges.push(Badge::Ghost);
+    }
+    // the standard car isn't worth a badge while it's the only one
+    if let Some(car) = annotations.car.as_ref().filter(|car| *car != STANDARD_CAR) {
+        badges.push(Badge::Car(car.clone()));
+    }
+    badges.extend(annotations.modifiers.iter().cloned().map(Badge::Modifier));
+    badges
+}
+
+/// Entries whose name contains the search text (case insensitive).
+/// If friends is given only their entries and my own are kept.
+pub fn filter_leaderboard_entries<'a>(entries: &'a [LeaderboardEntry], search: &str, friends: Option<&[String]>) -> Vec<&'a LeaderboardEntry> {
+    let search = search.trim().to_lowercase();
+    entries
+        .iter()
+        .filter(|entry| search.is_empty() || entry.name.to_lowercase().contains(&search))
+        .filter(|entry| match friends {
+            Some(friends) => entry.is_me || friends.iter().any(|friend| friend.eq_ignore_ascii_case(&entry.name)),
+            None => true,
+        })
+        .collect()
+}
 
 pub fn leaderboard_view(ui: &GameUI) -> Element<'_, Message, Theme, iced::Renderer> {
-    let header_text_col = Color::from_rgb(0.6, 0.6, 1.0);
-    let mut leaderboard_col = column![
+    let theme = ui.theme;
+    let header_text_col = theme.accent;
+    let show_checkpoints = ui.time_attack_info.is_some();
+    let mut header_row = row![
+        text("Pos").width(Length::Fixed(50.0)).color(header_text_col),
+        text("Player").width(Length::Fill).color(header_text_col),
+    ]
+    .spacing(10)
+    .padding(5);
+    if show_checkpoints {
+        header_row = header_row.push(text("CP").width(Length::Fixed(40.0)).color(header_text_col));
+    }
+    header_row = header_row.push(text("Time").width(Length::Fixed(100.0)).color(header_text_col));
+    header_row = header_row.push(text("").width(Length::Fixed(70.0)));
+    header_row = header_row.push(text("").width(Length::Fixed(30.0)));
+
+    let mut leaderboard_col = column![].spacing(5);
+
+    if let Some(pending) = ui.offline {
+        let badge = match pending {
+            0 => String::from("Offline, showing the last saved leaderboard"),
+            1 => String::from("Offline, 1 time will be posted when reconnected"),
+            _ => format!("Offline, {} times will be posted when reconnected", pending),
+        };
+        leaderboard_col = leaderboard_col.push(text(badge).size(18).color(theme.warning));
+    }
+
+    // tabs to switch between the global and private channel leaderboards
+    let private_channel = ui.private_channel.as_ref();
+    if private_channel.is_some() || !ui.friends.is_empty() {
+        let tab_button = |label: String, tab: LeaderboardTab| {
+            let color = if ui.leaderboard_tab == tab { theme.text } else { theme.inactive };
+            button(text(label).size(18).color(color))
+                .padding(5)
+                .style(button::text)
+                .on_press(Message::SelectLeaderboardTab(tab))
+        };
+        let mut tabs_row = row![tab_button(String::from("Global"), LeaderboardTab::Global)].spacing(10);
+        if let Some(private_channel) = private_channel {
+            tabs_row = tabs_row.push(tab_button(private_channel.clone(), LeaderboardTab::Private));
+        }
+        if !ui.friends.is_empty() {
+            tabs_row = tabs_row.push(tab_button(String::from("Friends"), LeaderboardTab::Friends));
+        }
+        leaderboard_col = leaderboard_col.push(tabs_row);
+    }
+    // search and friends filter
+    let search: Element<'_, Message, Theme, iced::Renderer> = if ui.leaderboard_search_active {
+        text_input("Search players...", &ui.leaderboard_search)
+            .on_input(Message::UpdateLeaderboardSearch)
+            .on_submit(Message::ToggleLeaderboardSearch)
+            .padding(5)
+            .size(18)
+            .width(Length::Fill)
+            .into()
+    } else {
+        let label = if ui.leaderboard_search.is_empty() { String::from("Search") } else { format!("Search: {}", ui.leaderboard_search) };
+        button(text(label).size(18))
+            .padding(5)
+            .on_press(Message::ToggleLeaderboardSearch)
+            .width(Length::Fill)
+            .into()
+    };
+    let friends_label = if ui.friends_only { "Friends: on" } else { "Friends: off" };
+    leaderboard_col = leaderboard_col.push(
         row![
-            text("Pos").width(Length::Fixed(50.0)).color(header_text_col),
-            text("Player").width(Length::Fill).color(header_text_col),
-            text("Time").width(Length::Fixed(100.0)).color(header_text_col),
+            search,
+            button(text(friends_label).size(18)).padding(5).on_press(Message::ToggleFriendsOnly),
         ]
         .spacing(10)
-        .padding(5)
-    ]
-    .spacing(5);
+    );
+    leaderboard_col = leaderboard_col.push(header_row);
 
-    if ui.leaderboard_results.is_empty() {
-        leaderboard_col = leaderboard_col.push(text("Loading leaderboard...").color(Color::from_rgb(0.7, 0.7, 0.7)));
+    let results = match ui.leaderboard_tab {
+        LeaderboardTab::Private if private_channel.is_some() => &ui.private_leaderboard_results,
+        _ => &ui.leaderboard_results,
+    };
+
+    let friends = if ui.friends_only || ui.leaderboard_tab == LeaderboardTab::Friends { Some(ui.friends.as_slice()) } else { None };
+    let filtered = filter_leaderboard_entries(results, &ui.leaderboard_search, friends);
+    let num_pages = filtered.len().div_ceil(LEADERBOARD_PAGE_SIZE).max(1);
+    let page = ui.leaderboard_page.min(num_pages - 1);
+
+    if results.is_empty() && ui.offline.is_some() {
+        leaderboard_col = leaderboard_col.push(text("No saved times for this level").color(theme.dim_text));
+    } else if results.is_empty() {
+        leaderboard_col = leaderboard_col.push(text("Loading leaderboard...").color(theme.dim_text));
+    } else if filtered.is_empty() {
+        leaderboard_col = leaderboard_col.push(text("No matching players").color(theme.dim_text));
     } else {
-        for entry in &ui.leaderboard_results {
+        for entry in filtered.iter().skip(page * LEADERBOARD_PAGE_SIZE).take(LEADERBOARD_PAGE_SIZE) {
             let color = if entry.is_current_run {
-                Color::from_rgb(0.0, 1.0, 0.0) // Green for current run
+                theme.positive // Green for current run
+            } else if entry.is_me {
+                theme.positive_dim // Light green for my other runs
+            } else if entry.beats_my_best {
+                theme.warning // Orange for runs set since yesterday that beat my best
             } else {
-                Color::WHITE
+                theme.text
             };
 
-            leaderboard_col = leaderboard_col.push(
-                row![
-                    text(format!("{}.", entry.rank)).width(Length::Fixed(50.0)).color(color),
-                    text(&entry.name).width(Length::Fill).color(color),
-                    text(format!("{:.3}s", entry.time)).width(Length::Fixed(100.0)).color(color),
-                ]
-                .spacing(10)
-                .padding(2)
-            );
+            let mut entry_row = row![
+                text(format!("{}.", entry.rank)).width(Length::Fixed(50.0)).color(color),
+                text(&entry.name).width(Length::Fill).color(color),
+            ]
+            .spacing(10)
+            .padding(2);
+            if show_checkpoints {
+                entry_row = entry_row.push(text(entry.checkpoints.map_or(String::from("-"), |c| c.to_string())).width(Length::Fixed(40.0)).color(color));
+            }
+            entry_row = entry_row.push(text(format!("{:.3}s", entry.time)).width(Length::Fixed(100.0)).color(color));
+
+            let mut badges_row = row![].spacing(4).width(Length::Fixed(70.0));
+            for badge in annotation_badges(&entry.annotations) {
+                let badge_color = match badge {
+                    Badge::Verified => theme.positive,
+                    Badge::Rejected => theme.negative,
+                    _ => theme.dim_text,
+                };
+                badges_row = badges_row.push(text(badge.label()).size(14).color(badge_color));
+            }
+            entry_row = entry_row.push(badges_row);
+
+            // add or remove friends straight from the leaderboard
+            if entry.is_me {
+                entry_row = entry_row.push(text("").width(Length::Fixed(30.0)));
+            } else {
+                let is_friend = ui.friends.iter().any(|friend| friend.eq_ignore_ascii_case(&entry.name));
+                entry_row = entry_row.push(
+                    button(text(if is_friend { "-" } else { "+" }).size(15))
+                        .padding(2)
+                        .width(Length::Fixed(30.0))
+                        .on_press(Message::ToggleFriend(entry.name.clone()))
+                );
+            }
+
+            leaderboard_col = leaderboard_col.push(entry_row);
+        }
+    }
+
+    if filtered.iter().any(|entry| !annotation_badges(&entry.annotations).is_empty()) {
+        leaderboard_col = leaderboard_col.push(text("V verified, X failed verification, G ghost to race").size(14).color(theme.dim_text));
+    }
+
+    if num_pages > 1 {
+        let prev_button = button(text("<").size(18)).padding(5).on_press_maybe(page.checked_sub(1).map(Message::SelectLeaderboardPage));
+        let next_button = button(text(">").size(18)).padding(5).on_press_maybe((page + 1 < num_pages).then_some(Message::SelectLeaderboardPage(page + 1)));
+        leaderboard_col = leaderboard_col.push(
+            row![
+                prev_button,
+                text(format!("Page {}/{}", page + 1, num_pages)).size(18).color(theme.text),
+                next_button,
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center)
+        );
+    }
+
+    let mut lap_times_row = row![].spacing(20);
+    if let Some(lap_info) = &ui.lap_info {
+        for (i, lap_time) in lap_info.lap_times.iter().enumerate() {
+            let color = if Some(*lap_time) == lap_info.best_lap { theme.positive } else { theme.text };
+            lap_times_row = lap_times_row.push(text(format!("Lap {}: {:.2}s", i + 1, lap_time)).size(18).color(color));
         }
     }
 
+    let title = match &ui.time_attack_info {
+        Some(info) if info.out_of_time => format!("Out of Time! {}/{} Checkpoints", info.checkpoints_reached, info.num_checkpoints),
+        _ => format!("Final Time: {:.2}s", ui.total_time),
+    };
+    let title = if ui.practice { format!("Practice - {}", title) } else { title };
+    let title = match &ui.level_name {
+        Some(name) => format!("{} - {}", name, title),
+        None => title,
+    };
+
+    // slower runs aren't posted automatically, but can be submitted from here
+    let mut attempt_row = row![].spacing(15).align_y(Alignment::Center);
+    if let Some(attempt_info) = &ui.attempt_info {
+        let best = attempt_info.best_time.map_or(String::new(), |time| format!(", session best {:.2}s", time));
+        attempt_row = attempt_row.push(text(format!("Attempt {}{}", attempt_info.attempt, best)).size(18).color(theme.dim_text));
+        if attempt_info.submitted {
+            attempt_row = attempt_row.push(text("Submitted").size(18).color(theme.positive));
+        } else {
+            attempt_row = attempt_row.push(button(text("Submit this run").size(18)).padding(5).on_press(Message::SubmitRun));
+        }
+    }
+    match ui.ghost_exported {
+        Some(true) => attempt_row = attempt_row.push(text("Ghost exported").size(18).color(theme.positive)),
+        Some(false) => attempt_row = attempt_row.push(button(text("Export ghost").size(18)).padding(5).on_press(Message::ExportGhost)),
+        None => {}
+    }
+
+    let rating_text = match &ui.rating_info {
+        Some(info) if info.days > 0 => format!("Rating: {:.0} ({:+.0})", info.rating, info.change),
+        _ => String::new(),
+    };
+
     container(
         column![
-            text(format!("Final Time: {:.2}s", ui.total_time))
+            text(title)
                 .size(40)
-                .color(Color::WHITE),
+                .color(theme.text),
+            text(rating_text)
+                .size(22)
+                .color(theme.notice),
+            text(format!("Next daily in {}", format_countdown(ui.next_daily_in)))
+                .size(18)
+                .color(theme.dim_text),
+            attempt_row,
+            lap_times_row,
             container(leaderboard_col)
-                .width(Length::Fixed(400.0))
+                .width(Length::Fixed(520.0))
                 .padding(20)
-                .style(|_theme: &Theme| {
-                    container::Style {
-                        background: Some(iced::Background::Color(Color::from_rgba(0.0, 0.0, 0.0, 0.5))),
-                        border: iced::Border {
-                            radius: 10.0.into(),
-                            width: 1.0,
-                            color: Color::from_rgb(0.4, 0.4, 0.4),
-                        },
-                        ..Default::default()
-                    }
-                }),
-            text("Press 'r' to retry")
+                .style(move |_theme: &Theme| theme.panel_style()),
+            text("Press 'r' to retry, 't' for run analysis, 'p' for level packs")
                 .size(22)
-                .color(Color::from_rgb(0.6, 0.6, 1.0)),
+                .color(theme.accent),
         ]
         .spacing(30)
         .align_x(Alignment::Center)
@@ -67,11 +298,42 @@ pub fn leaderboard_view(ui: &GameUI) -> Element<'_, Message, Theme, iced::Render
     .height(Length::Fill)
     .center_x(Length::Fill)
     .center_y(Length::Fill)
-    .style(|_theme: &Theme| {
-        container::Style {
-            background: Some(iced::Background::Color(Color::from_rgba(0.0, 0.0, 0.0, 0.8))),
-            ..Default::default()
-        }
-    })
+    .style(move |_theme: &Theme| theme.overlay_style())
     .into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, is_me: bool) -> LeaderboardEntry {
+        LeaderboardEntry { rank: 1, name: name.to_owned(), time: 10.0, checkpoints: None, is_current_run: false, is_me, beats_my_best: false, annotations: ScoreAnnotations::default() }
+    }
+
+    #[test]
+    fn test_filter_leaderboard_entries() {
+        let entries = vec![entry("Alice", false), entry("bob", false), entry("me", true)];
+        let friends = vec![String::from("alice")];
+
+        assert_eq!(filter_leaderboard_entries(&entries, "", None).len(), 3);
+        assert_eq!(filter_leaderboard_entries(&entries, " ALI ", None)[0].name, "Alice");
+        let names: Vec<_> = filter_leaderboard_entries(&entries, "", Some(&friends)).iter().map(|e| e.name.clone()).collect();
+        assert_eq!(names, vec!["Alice", "me"]);
+    }
+
+    #[test]
+    fn test_annotation_badges() {
+        assert!(annotation_badges(&ScoreAnnotations::default()).is_empty());
+        let annotations = ScoreAnnotations {
+            car: Some(String::from("standard")),
+            modifiers: vec![String::from("mirror"), String::from("laps3"), String::from("zerog")],
+            ghost: true,
+            verification: Verification::Rejected,
+            set_at: None,
+        };
+        let labels: Vec<String> = annotation_badges(&annotations).iter().map(Badge::label).collect();
+        assert_eq!(labels, vec!["X", "G", "Mi", "3L", "ze"]);
+        let annotations = ScoreAnnotations { car: Some(String::from("buggy")), verification: Verification::Verified, ..ScoreAnnotations::default() };
+        assert_eq!(annotation_badges(&annotations), vec![Badge::Verified, Badge::Car(String::from("buggy"))]);
+    }
+}
@@ -0,0 +1,86 @@
+use iced::widget::{button, column, container, row, scrollable, text, text_input};
+use iced::{Element, Length, Theme, Alignment};
+use super::game_ui::{LevelEditorInfo, Message};
+use super::theme::UiTheme;
+
+const PANEL_WIDTH: f32 = 260.0;
+const BLOCK_LIST_HEIGHT: f32 = 240.0;
+
+/// The blocks of the level being edited in the top right corner. Pick one to move, remove or change its params,
+/// every change is built straight away so it can be driven.
+pub fn level_editor_view<'a>(info: &'a LevelEditorInfo, params: &'a [(String, String)], theme: UiTheme) -> Element<'a, Message, Theme, iced::Renderer> {
+    let mut blocks = column![].spacing(2);
+    for (i, block) in info.blocks.iter().enumerate() {
+        let color = if info.selected == Some(i) { theme.selected } else { theme.text };
+        blocks = blocks.push(
+            button(text(format!("{}. {}", i + 1, block)).size(14).color(color))
+                .padding(2)
+                .style(button::text)
+                .on_press(Message::SelectEditorBlock(i))
+        );
+    }
+
+    let mut panel = column![
+        text("Level editor").size(16).color(theme.accent),
+        container(scrollable(blocks)).height(Length::Fixed(BLOCK_LIST_HEIGHT)),
+        row![
+            button(text("<").size(14)).padding(4).on_press(Message::CycleEditorBlockType(-1)),
+            text(&info.block_type).size(14).color(theme.text).width(Length::Fill),
+            button(text(">").size(14)).padding(4).on_press(Message::CycleEditorBlockType(1)),
+            button(text("Add").size(14)).padding(4).on_press(Message::AddEditorBlock),
+        ]
+        .spacing(5)
+        .align_y(Alignment::Center),
+    ]
+    .spacing(6)
+    .width(Length::Fixed(PANEL_WIDTH));
+
+    if info.selected.is_some() {
+        panel = panel.push(
+            row![
+                button(text("Up").size(14)).padding(4).on_press(Message::MoveEditorBlock(-1)),
+                button(text("Down").size(14)).padding(4).on_press(Message::MoveEditorBlock(1)),
+                button(text("Delete").size(14)).padding(4).on_press(Message::DeleteEditorBlock),
+            ]
+            .spacing(5)
+        );
+        for (i, (name, value)) in params.iter().enumerate() {
+            panel = panel.push(
+                row![
+                    text(name).size(14).color(theme.dim_text).width(Length::FillPortion(1)),
+                    text_input("", value)
+                        .on_input(move |value| Message::EditEditorParam(i, value))
+                        .on_submit(Message::ApplyEditorParams)
+                        .size(14)
+                        .padding(2)
+                        .width(Length::FillPortion(1)),
+                ]
+                .spacing(5)
+                .align_y(Alignment::Center)
+            );
+        }
+        if !params.is_empty() {
+            panel = panel.push(button(text("Apply").size(14)).padding(4).on_press(Message::ApplyEditorParams));
+        }
+    }
+
+    panel = panel.push(
+        column![
+            button(text("Save").size(14)).padding(4).on_press(Message::SaveEditorLevel),
+            text(format!("to {}, click a block to pick it, right click to add one after it", info.path)).size(12).color(theme.dim_text),
+        ]
+        .spacing(4)
+    );
+
+    container(
+        container(panel)
+            .padding(10)
+            .style(move |_theme: &Theme| theme.panel_style())
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .padding(20)
+    .align_x(Alignment::End)
+    .align_y(Alignment::Start)
+    .into()
+}
@@ -0,0 +1,109 @@
+use iced::widget::{button, column, container, row, scrollable, text, text_input};
+use iced::{Element, Length, Theme, Alignment};
+use super::game_ui::{Message, GameUI};
+use crate::game::training::{training_block_name, TRAINING_VARIANTS};
+
+/// Installed level packs to play, plus packs from the index and the level packs channel to download
+pub fn level_packs_view(ui: &GameUI) -> Element<'_, Message, Theme, iced::Renderer> {
+    let theme = ui.theme;
+    let header_text_col = theme.accent;
+    let dim_text_col = theme.dim_text;
+
+    let mut library_col = column![text("Library").size(24).color(header_text_col)].spacing(8);
+    if ui.level_packs.is_empty() {
+        library_col = library_col.push(text("No level packs installed yet").color(dim_text_col));
+    }
+    for pack in &ui.level_packs {
+        let by = if pack.author.is_empty() { String::new() } else { format!(" by {}", pack.author) };
+        library_col = library_col.push(text(format!("{}{}", pack.name, by)).size(18).color(theme.text));
+
+        let mut levels_row = row![].spacing(5);
+        for (i, level_name) in pack.level_names.iter().enumerate() {
+            let label = if level_name.is_empty() { format!("{}", i + 1) } else { format!("{}. {}", i + 1, level_name) };
+            levels_row = levels_row.push(
+                button(text(label).size(15))
+                    .padding(4)
+                    .on_press(Message::PlayPackLevel(pack.id.clone(), i))
+            );
+        }
+        library_col = library_col.push(levels_row.wrap());
+    }
+
+    let mut browse_col = column![text("Browse").size(24).color(header_text_col)].spacing(8);
+    if ui.level_pack_listings.is_empty() {
+        browse_col = browse_col.push(text("Nothing shared yet, packs announced in #planck-levels show up here").color(dim_text_col));
+    }
+    for listing in &ui.level_pack_listings {
+        let by = if listing.author.is_empty() { String::new() } else { format!(" by {}", listing.author) };
+        browse_col = browse_col.push(
+            row![
+                text(format!("{}{}", listing.name, by)).size(18).width(Length::Fill).color(theme.text),
+                button(text("Download").size(15)).padding(4).on_press(Message::DownloadLevelPack(listing.clone())),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center)
+        );
+    }
+
+    // one block between a spawn and a finish, for drilling the block that keeps ending runs
+    let mut training_col = column![text("Training").size(24).color(header_text_col)].spacing(8);
+    for block in &ui.training_blocks {
+        let mut variants_row = row![text(training_block_name(block)).size(18).width(Length::Fill).color(theme.text)].spacing(5).align_y(Alignment::Center);
+        for variant in 0..TRAINING_VARIANTS {
+            variants_row = variants_row.push(
+                button(text(format!("{}", variant + 1)).size(15))
+                    .padding(4)
+                    .on_press(Message::PlayTraining(block.clone(), variant))
+            );
+        }
+        training_col = training_col.push(variants_row);
+    }
+
+    // any seed builds a level, with its own leaderboard
+    let seed_entry: Element<'_, Message, Theme, iced::Renderer> = if ui.seed_entry_active {
+        text_input("Type a seed and press enter...", &ui.seed_input)
+            .on_input(Message::UpdateSeedInput)
+            .on_submit(Message::PlaySeed)
+            .padding(5)
+            .size(18)
+            .width(Length::Fixed(400.0))
+            .into()
+    } else {
+        button(text("Play a seed").size(18)).padding(5).on_press(Message::ToggleSeedEntry).into()
+    };
+
+    let title = match &ui.level_name {
+        Some(name) => format!("Level Packs - playing {}", name),
+        None => String::from("Level Packs"),
+    };
+
+    container(
+        column![
+            text(title)
+                .size(40)
+                .color(theme.text),
+            container(scrollable(column![library_col, training_col, browse_col].spacing(30)))
+                .width(Length::Fixed(560.0))
+                .height(Length::Fixed(420.0))
+                .padding(20)
+                .style(move |_theme: &Theme| theme.panel_style()),
+            row![
+                button(text("Play the daily").size(18)).padding(5).on_press(Message::PlayDaily),
+                button(text("Save this level to My Levels").size(18)).padding(5).on_press(Message::SaveLevelToMyLevels),
+            ]
+            .spacing(10),
+            seed_entry,
+            text("Press 'p' to go back")
+                .size(22)
+                .color(header_text_col),
+        ]
+        .spacing(30)
+        .align_x(Alignment::Center)
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .center_x(Length::Fill)
+    .center_y(Length::Fill)
+    .style(move |_theme: &Theme| theme.overlay_style())
+    .into()
+}
@@ -2,3 +2,12 @@ pub mod game_ui;
 pub mod hud;
 pub mod leaderboard;
 pub mod name_entry;
+pub mod analysis;
+pub mod toast;
+pub mod level_packs;
+pub mod pre_race;
+pub mod theme;
+pub mod new_daily;
+pub mod ghost_camera;
+pub mod tuning;
+pub mod level_editor;
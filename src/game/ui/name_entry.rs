@@ -1,30 +1,68 @@
-use iced::widget::{column, text, text_input, button, container};
-use iced::{Color, Element, Length, Theme, Alignment};
+use iced::widget::{column, row, text, text_input, button, container};
+use iced::{Element, Length, Theme, Alignment};
+use crate::game::nickname::validate_nickname;
 use super::game_ui::{Message, GameUI};
 
 pub fn name_entry_view(ui: &GameUI) -> Element<'_, Message, Theme, iced::Renderer> {
+    let theme = ui.theme;
     let input = text_input("Enter your name...", &ui.name_input)
         .on_input(Message::UpdateNameInput)
         .on_submit(Message::SubmitName)
         .padding(15)
         .size(30)
         .width(Length::Fixed(400.0));
+    let seed_input = text_input("Seed, blank for today's daily", &ui.seed_input)
+        .on_input(Message::UpdateSeedInput)
+        .on_submit(Message::SubmitName)
+        .padding(10)
+        .size(20)
+        .width(Length::Fixed(400.0));
+
+    // only complain once something has been typed
+    let name = ui.name_input.trim();
+    let validation = validate_nickname(name);
+    let error_text = match &validation {
+        Err(error) if !name.is_empty() => error.to_string(),
+        _ => String::new(),
+    };
 
+    let randomize_button = button(text("Randomize").size(24))
+        .padding(10)
+        .on_press(Message::RandomizeName);
     let submit_button = button(text("Start Game").size(24))
         .padding(10)
-        .on_press(Message::SubmitName);
+        .on_press_maybe(validation.is_ok().then_some(Message::SubmitName));
+
+    // how the name will look as a row on the leaderboard
+    let preview_name = if name.is_empty() { "..." } else { name };
+    let preview = container(
+        row![
+            text("1.").width(Length::Fixed(50.0)).color(theme.positive),
+            text(preview_name).width(Length::Fill).color(theme.positive),
+            text("--.---s").width(Length::Fixed(100.0)).color(theme.positive),
+        ]
+        .spacing(10)
+        .padding(2)
+    )
+    .width(Length::Fixed(400.0))
+    .padding(10)
+    .style(move |_theme: &Theme| theme.panel_style());
 
     container(
         column![
             text("Welcome to Planck Time Trials")
                 .size(50)
-                .color(Color::WHITE),
+                .color(theme.text),
             text("Please enter your name to continue")
                 .size(24)
-                .color(Color::from_rgb(0.7, 0.7, 0.7)),
+                .color(theme.dim_text),
             column![
                 input,
-                submit_button,
+                text(error_text).size(18).color(theme.negative),
+                seed_input,
+                row![randomize_button, submit_button].spacing(20),
+                text("Leaderboard preview").size(18).color(theme.accent),
+                preview,
             ]
             .spacing(20)
             .align_x(Alignment::Center),
@@ -36,9 +74,9 @@ pub fn name_entry_view(ui: &GameUI) -> Element<'_, Message, Theme, iced::Rendere
     .height(Length::Fill)
     .center_x(Length::Fill)
     .center_y(Length::Fill)
-    .style(|_theme: &Theme| {
+    .style(move |_theme: &Theme| {
         container::Style {
-            background: Some(iced::Background::Color(Color::from_rgb(0.05, 0.05, 0.1))),
+            background: Some(iced::Background::Color(theme.screen)),
             ..Default::default()
         }
     })
@@ -0,0 +1,27 @@
+use iced::widget::{button, container, row, text};
+use iced::{Element, Length, Theme, Alignment};
+use super::game_ui::Message;
+use super::theme::UiTheme;
+
+/// Offers the new daily when the seed rolls over mid session, at the bottom so it doesn't cover toasts
+pub fn new_daily_view<'a>(theme: UiTheme) -> Element<'a, Message, Theme, iced::Renderer> {
+    container(
+        container(
+            row![
+                text("New daily available - play now?").size(20).color(theme.text),
+                button(text("Play now").size(18)).padding(5).on_press(Message::PlayNewDaily),
+                button(text("Later").size(18)).padding(5).on_press(Message::UpdateNewDailyAvailable(false)),
+            ]
+            .spacing(15)
+            .align_y(Alignment::Center)
+        )
+        .padding(10)
+        .style(move |_theme: &Theme| theme.panel_style())
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .padding(20)
+    .align_x(Alignment::Center)
+    .align_y(Alignment::End)
+    .into()
+}
@@ -0,0 +1,79 @@
+use iced::widget::{button, column, container, row, text};
+use iced::{Element, Length, Theme, Alignment};
+use crate::game::ghost::MAX_GHOSTS;
+use crate::game::seed_policy::format_countdown;
+use super::game_ui::{Message, GameUI};
+
+/// Pick ghosts to race before the daily starts
+pub fn pre_race_view(ui: &GameUI) -> Element<'_, Message, Theme, iced::Renderer> {
+    let theme = ui.theme;
+    let header_text_col = theme.accent;
+    let dim_text_col = theme.dim_text;
+
+    let mut ghosts_col = column![
+        text(format!("Ghosts (up to {})", MAX_GHOSTS)).size(24).color(header_text_col)
+    ].spacing(8);
+
+    if ui.ghost_offers.is_empty() {
+        ghosts_col = ghosts_col.push(text("Looking for runs to race...").color(dim_text_col));
+    }
+    let num_selected = ui.ghost_offers.iter().filter(|offer| offer.selected).count();
+    for offer in &ui.ghost_offers {
+        let time = offer.time.map_or(String::from("-"), |time| format!("{:.3}s", time));
+        let (label, message) = if !offer.ready {
+            ("Fetching...", None)
+        } else if offer.issue.is_some() {
+            ("Can't race", None)
+        } else if offer.selected {
+            ("Racing", Some(Message::ToggleGhost(offer.name.clone())))
+        } else if num_selected < MAX_GHOSTS {
+            ("Race", Some(Message::ToggleGhost(offer.name.clone())))
+        } else {
+            ("Race", None)
+        };
+        let color = if offer.selected { theme.selected } else { theme.text };
+
+        ghosts_col = ghosts_col.push(
+            row![
+                text(&offer.name).width(Length::Fill).color(color),
+                text(time).width(Length::Fixed(100.0)).color(color),
+                button(text(label).size(15)).padding(4).width(Length::Fixed(90.0)).on_press_maybe(message),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center)
+        );
+        if let Some(issue) = &offer.issue {
+            ghosts_col = ghosts_col.push(text(issue).size(14).color(theme.warning));
+        }
+    }
+
+    container(
+        column![
+            text("Daily")
+                .size(40)
+                .color(theme.text),
+            text(format!("Next daily in {}", format_countdown(ui.next_daily_in)))
+                .size(18)
+                .color(dim_text_col),
+            container(ghosts_col)
+                .width(Length::Fixed(440.0))
+                .padding(20)
+                .style(move |_theme: &Theme| theme.panel_style()),
+            button(text("Start").size(22)).padding(8).on_press(Message::StartRace),
+            text("Press space, or Start on a gamepad, to start")
+                .size(22)
+                .color(header_text_col),
+            text("G toggles the ghost camera while racing, O the orthographic view, V the chase and trackside cameras")
+                .size(16)
+                .color(dim_text_col),
+        ]
+        .spacing(30)
+        .align_x(Alignment::Center)
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .center_x(Length::Fill)
+    .center_y(Length::Fill)
+    .style(move |_theme: &Theme| theme.overlay_style())
+    .into()
+}
@@ -0,0 +1,166 @@
+use iced::widget::container;
+use iced::theme::Palette;
+use iced::{Color, Theme};
+
+use crate::game::color_scheme::ColorScheme;
+
+/// Every colour the views use, so none of them hard code their own
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UiTheme {
+    pub scheme: ColorScheme,
+    pub text: Color,
+    pub dim_text: Color, // hints, empty lists
+    pub accent: Color, // headings and secondary info, can be set in settings
+    pub warning: Color, // practice mode, runs that beat my best, toasts
+    pub notice: Color, // rating, personal bests
+    pub positive: Color, // my current run, best laps
+    pub positive_dim: Color, // my other runs
+    pub negative: Color, // running out of time, energy gains
+    pub selected: Color,
+    pub inactive: Color, // unselected tabs
+    pub panel: Color, // boxes within a screen
+    pub border: Color,
+    pub overlay: Color, // behind a whole screen over the game
+    pub screen: Color, // opaque background when there's no game to see, eg. name entry
+    pub toast: Color,
+    pub hud: Color, // behind the hud text, so it can be read over the level
+}
+
+impl UiTheme {
+    pub fn new(scheme: ColorScheme, accent: Option<Color>) -> Self {
+        let mut theme = match scheme {
+            ColorScheme::Dark => Self {
+                scheme,
+                text: Color::WHITE,
+                dim_text: Color::from_rgb(0.7, 0.7, 0.7),
+                accent: Color::from_rgb(0.6, 0.6, 1.0),
+                warning: Color::from_rgb(1.0, 0.7, 0.3),
+                notice: Color::from_rgb(1.0, 0.85, 0.3),
+                positive: Color::from_rgb(0.0, 1.0, 0.0),
+                positive_dim: Color::from_rgb(0.6, 1.0, 0.6),
+                negative: Color::from_rgb(1.0, 0.2, 0.2),
+                selected: Color::from_rgb(0.6, 0.8, 1.0),
+                inactive: Color::from_rgb(0.5, 0.5, 0.5),
+                panel: Color::from_rgba(0.0, 0.0, 0.0, 0.5),
+                border: Color::from_rgb(0.4, 0.4, 0.4),
+                overlay: Color::from_rgba(0.0, 0.0, 0.0, 0.8),
+                screen: Color::from_rgb(0.05, 0.05, 0.1),
+                toast: Color::from_rgba(0.2, 0.1, 0.0, 0.85),
+                hud: Color::TRANSPARENT,
+            },
+            ColorScheme::Light => Self {
+                scheme,
+                text: Color::from_rgb(0.1, 0.1, 0.12),
+                dim_text: Color::from_rgb(0.4, 0.4, 0.45),
+                accent: Color::from_rgb(0.2, 0.3, 0.8),
+                warning: Color::from_rgb(0.8, 0.4, 0.0),
+                notice: Color::from_rgb(0.65, 0.5, 0.0),
+                positive: Color::from_rgb(0.0, 0.55, 0.0),
+                positive_dim: Color::from_rgb(0.25, 0.6, 0.25),
+                negative: Color::from_rgb(0.8, 0.1, 0.1),
+                selected: Color::from_rgb(0.1, 0.4, 0.8),
+                inactive: Color::from_rgb(0.55, 0.55, 0.55),
+                panel: Color::from_rgba(1.0, 1.0, 1.0, 0.6),
+                border: Color::from_rgb(0.6, 0.6, 0.65),
+                overlay: Color::from_rgba(0.92, 0.92, 0.95, 0.85),
+                screen: Color::from_rgb(0.92, 0.92, 0.95),
+                toast: Color::from_rgba(1.0, 0.95, 0.85, 0.95),
+                hud: Color::from_rgba(1.0, 1.0, 1.0, 0.6),
+            },
+            ColorScheme::HighContrast => Self {
+                scheme,
+                text: Color::WHITE,
+                dim_text: Color::from_rgb(0.85, 0.85, 0.85),
+                accent: Color::from_rgb(0.0, 1.0, 1.0),
+                warning: Color::from_rgb(1.0, 0.6, 0.0),
+                notice: Color::from_rgb(1.0, 1.0, 0.0),
+                positive: Color::from_rgb(0.0, 1.0, 0.0),
+                positive_dim: Color::from_rgb(0.5, 1.0, 0.5),
+                negative: Color::from_rgb(1.0, 0.2, 0.2),
+                selected: Color::from_rgb(1.0, 1.0, 0.0),
+                inactive: Color::from_rgb(0.6, 0.6, 0.6),
+                panel: Color::from_rgba(0.0, 0.0, 0.0, 0.9),
+                border: Color::WHITE,
+                overlay: Color::from_rgba(0.0, 0.0, 0.0, 0.95),
+                screen: Color::BLACK,
+                toast: Color::from_rgba(0.0, 0.0, 0.0, 0.95),
+                hud: Color::from_rgba(0.0, 0.0, 0.0, 0.7),
+            },
+        };
+        if let Some(accent) = accent {
+            theme.accent = accent;
+        }
+        theme
+    }
+
+    /// The iced theme for the widgets the views don't colour themselves, eg. buttons and text inputs
+    pub fn iced_theme(&self) -> Theme {
+        Theme::custom(self.scheme.name(), Palette {
+            background: self.screen,
+            text: self.text,
+            primary: self.accent,
+            success: self.positive,
+            warning: self.warning,
+            danger: self.negative,
+        })
+    }
+
+    /// A bordered box within a screen, eg. the leaderboard table
+    pub fn panel_style(&self) -> container::Style {
+        container::Style {
+            background: Some(iced::Background::Color(self.panel)),
+            border: iced::Border {
+                radius: 10.0.into(),
+                width: 1.0,
+                color: self.border,
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Darkens (or lightens) the game behind a whole screen
+    pub fn overlay_style(&self) -> container::Style {
+        container::Style {
+            background: Some(iced::Background::Color(self.overlay)),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for UiTheme {
+    fn default() -> Self {
+        Self::new(ColorScheme::default(), None)
+    }
+}
+
+/// "#rrggbb" or "rrggbb", as used for the accent colour in settings
+pub fn parse_hex_color(hex: &str) -> Option<Color> {
+    let hex = hex.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(Color::from_rgb8(channel(0)?, channel(2)?, channel(4)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hex_color() {
+        assert_eq!(parse_hex_color("#ff8000"), Some(Color::from_rgb8(255, 128, 0)));
+        assert_eq!(parse_hex_color("00FF00"), Some(Color::from_rgb8(0, 255, 0)));
+        assert_eq!(parse_hex_color("#ff80"), None);
+        assert_eq!(parse_hex_color("#gg0000"), None);
+    }
+
+    #[test]
+    fn test_accent_overrides_scheme() {
+        let accent = Color::from_rgb8(255, 0, 255);
+        let theme = UiTheme::new(ColorScheme::Light, Some(accent));
+        assert_eq!(theme.accent, accent);
+        assert_eq!(theme.text, UiTheme::new(ColorScheme::Light, None).text);
+        assert_eq!(ColorScheme::HighContrast.next(), ColorScheme::Dark);
+    }
+}
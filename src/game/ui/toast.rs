@@ -0,0 +1,28 @@
+use iced::widget::{container, text};
+use iced::{Element, Length, Theme, Alignment};
+use super::game_ui::Message;
+use super::theme::UiTheme;
+
+/// A short notification shown at the top of the screen over the other views
+pub fn toast_view(toast: &str, theme: UiTheme) -> Element<'_, Message, Theme, iced::Renderer> {
+    container(
+        container(text(toast).size(20).color(theme.text))
+            .padding(10)
+            .style(move |_theme: &Theme| {
+                container::Style {
+                    background: Some(iced::Background::Color(theme.toast)),
+                    border: iced::Border {
+                        radius: 10.0.into(),
+                        width: 1.0,
+                        color: theme.warning,
+                    },
+                    ..Default::default()
+                }
+            })
+    )
+    .width(Length::Fill)
+    .padding(20)
+    .align_x(Alignment::Center)
+    .align_y(Alignment::Start)
+    .into()
+}
@@ -0,0 +1,43 @@
+use iced::widget::{button, column, container, slider, text};
+use iced::{Element, Length, Theme, Alignment};
+use super::game_ui::Message;
+use super::theme::UiTheme;
+use crate::game::physics_tuning::PhysicsTuning;
+
+const SLIDER_WIDTH: f32 = 200.0;
+
+/// A label with the value above its slider
+fn labelled<'a>(label: String, control: impl Into<Element<'a, Message, Theme, iced::Renderer>>, theme: UiTheme) -> Element<'a, Message, Theme, iced::Renderer> {
+    column![text(label).size(14).color(theme.text), control.into()].spacing(2).into()
+}
+
+/// Sliders for the solver parameters in the top right corner, changes are applied to the running simulation straight away
+pub fn tuning_view(tuning: PhysicsTuning, theme: UiTheme) -> Element<'static, Message, Theme, iced::Renderer> {
+    container(
+        container(
+            column![
+                text("Physics tuning").size(16).color(theme.accent),
+                labelled(format!("Gravity: {:.1}m/s²", tuning.gravity),
+                    slider(PhysicsTuning::GRAVITY_RANGE, tuning.gravity, move |gravity| Message::TunePhysics(PhysicsTuning { gravity, ..tuning })).step(0.1f32).width(Length::Fixed(SLIDER_WIDTH)), theme),
+                labelled(format!("Friction: x{:.2}", tuning.friction),
+                    slider(PhysicsTuning::SCALE_RANGE, tuning.friction, move |friction| Message::TunePhysics(PhysicsTuning { friction, ..tuning })).step(0.05f32).width(Length::Fixed(SLIDER_WIDTH)), theme),
+                labelled(format!("Stiffness: x{:.2}", tuning.stiffness),
+                    slider(PhysicsTuning::SCALE_RANGE, tuning.stiffness, move |stiffness| Message::TunePhysics(PhysicsTuning { stiffness, ..tuning })).step(0.05f32).width(Length::Fixed(SLIDER_WIDTH)), theme),
+                labelled(format!("Max iterations: {}", tuning.max_iterations),
+                    slider(PhysicsTuning::MAX_ITERATIONS_RANGE, tuning.max_iterations, move |max_iterations| Message::TunePhysics(PhysicsTuning { max_iterations, ..tuning })).step(1).width(Length::Fixed(SLIDER_WIDTH)), theme),
+                labelled(format!("Time step: {:.1}ms", tuning.time_delta * 1000.0),
+                    slider(PhysicsTuning::TIME_DELTA_RANGE, tuning.time_delta, move |time_delta| Message::TunePhysics(PhysicsTuning { time_delta, ..tuning })).step(0.0005f32).width(Length::Fixed(SLIDER_WIDTH)), theme),
+                button(text("Defaults").size(14)).padding(4).on_press(Message::TunePhysics(PhysicsTuning::default())),
+            ]
+            .spacing(6)
+        )
+        .padding(10)
+        .style(move |_theme: &Theme| theme.panel_style())
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .padding(20)
+    .align_x(Alignment::End)
+    .align_y(Alignment::Start)
+    .into()
+}
@@ -0,0 +1,223 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::math::deterministic::MathMode,
+    engine::app::event_system::EventRecording,
+    game::{
+        ghost::{GhostReplay, StateChecksum},
+        golden_replays::GOLDEN_TIME_EPSILON,
+        headless_run::HeadlessRun,
+        leaderboard::verified_message,
+        level::level_generation_info::LevelGenerationInfo,
+        seed_policy::seed_day,
+    },
+};
+
+/// Where the game saves the recording of the last run, and what gets verified if no other file is given
+pub const RECORDING_PATH: &str = "recording.json";
+
+/// A re-simulation that hasn't finished by now never will, a daily takes well under this
+pub const MAX_VERIFY_FRAMES: u32 = 120000; // 10 minutes
+
+/// What a recording header holds besides the LevelGenerationInfo, everything else needed to re-simulate the run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecordingExtras {
+    #[serde(default)]
+    pub math_mode: MathMode,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checksums: Vec<StateChecksum>, // filled in when the run ends, the last one is on the frame it ended
+}
+
+/// The header written out with a recording: the level info with the extras alongside its fields,
+/// so older builds still read it as a LevelGenerationInfo
+pub fn recording_header(level_info: &LevelGenerationInfo, math_mode: MathMode, checksums: &[StateChecksum]) -> Option<serde_json::Value> {
+    let mut header = serde_json::to_value(level_info).ok()?;
+    let extras = serde_json::to_value(RecordingExtras { math_mode, checksums: checksums.to_vec() }).ok()?;
+    if let (Some(header), serde_json::Value::Object(extras)) = (header.as_object_mut(), extras) {
+        header.extend(extras);
+    }
+    Some(header)
+}
+
+/// A BEST_TIME message, the time a player says they set
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClaimedTime {
+    pub seed: String,
+    pub user: String,
+    pub time: f32,
+    pub level_hash: Option<String>, // older clients don't send it
+}
+
+impl ClaimedTime {
+    pub fn parse(message: &str) -> Result<Self, String> {
+        if !message.starts_with("BEST_TIME") {
+            return Err(String::from("not a BEST_TIME message"));
+        }
+        let field = |name: &str| message.split_whitespace().find_map(|part| part.strip_prefix(name)?.strip_prefix('=')).map(str::to_owned);
+        Ok(Self {
+            seed: field("seed").ok_or("BEST_TIME without a seed")?,
+            user: field("user").ok_or("BEST_TIME without a user")?,
+            time: field("time").and_then(|time| time.parse().ok()).ok_or("BEST_TIME without a time")?,
+            level_hash: field("hash"),
+        })
+    }
+}
+
+/// The outcome of re-simulating a recording, written out as JSON by the verify command
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VerificationReport {
+    pub verified: bool,
+    pub reason: Option<String>, // why it wasn't verified
+    pub seed: Option<String>,
+    pub user: Option<String>, // from the claim
+    pub claimed_time: Option<f32>,
+    pub time: Option<f32>, // the re-simulation's finish time, None if it didn't finish
+    pub frames: u32, // stepped in the re-simulation
+    pub level_hash: Option<String>, // of the level the recording was made on
+    pub math_mode: MathMode,
+    pub state_checksum: Option<String>, // of the simulation where the re-simulation stopped
+    pub checksums_checked: usize,
+    pub drifted_at_frame: Option<u32>, // the first recorded checksum the re-simulation didn't match
+    pub leaderboard_message: Option<String>, // the VERIFIED message to post for the claim, None without one
+}
+
+impl VerificationReport {
+    fn rejected(mut self, reason: String) -> Self {
+        self.verified = false;
+        self.reason = Some(reason);
+        self.leaderboard_message = self.verified_message();
+        self
+    }
+
+    /// The outcome for the leaderboard channels, for the claimed time
+    fn verified_message(&self) -> Option<String> {
+        Some(verified_message(self.seed.as_ref()?, self.user.as_ref()?, self.claimed_time?, self.verified))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+/// Re-simulate a recorded daily run without a window and check it finishes in the claimed time, if there is a claim.
+/// Only standard dailies can be checked, the headless run builds nothing else.
+pub fn verify_recording(recording: &EventRecording, claim: Option<&ClaimedTime>) -> VerificationReport {
+    let mut report = VerificationReport {
+        seed: claim.map(|claim| claim.seed.clone()),
+        user: claim.map(|claim| claim.user.clone()),
+        claimed_time: claim.map(|claim| claim.time),
+        ..VerificationReport::default()
+    };
+    let Some(header) = &recording.header else {
+        return report.rejected(String::from("the recording has no header saying which level it was made on"));
+    };
+    let level_info: LevelGenerationInfo = match serde_json::from_value(header.clone()) {
+        Ok(level_info) => level_info,
+        Err(e) => return report.rejected(format!("the recording header isn't a level: {}", e)),
+    };
+    let extras: RecordingExtras = serde_json::from_value(header.clone()).unwrap_or_default();
+    report.seed.get_or_insert(level_info.seed.clone()); // the claim's seed, to reject the time it was claimed for
+    report.level_hash = Some(level_info.level_hash.clone());
+    report.math_mode = extras.math_mode;
+
+    if let Some(claim) = claim {
+        if claim.seed != level_info.seed {
+            return report.rejected(format!("the time was claimed for {} but the recording is of {}", claim.seed, level_info.seed));
+        }
+        if claim.level_hash.as_ref().is_some_and(|hash| *hash != level_info.level_hash) {
+            return report.rejected(String::from("the time was claimed on a different level to the recording"));
+        }
+    }
+    if level_info.mirrored || level_info.headwind != 0.0 {
+        return report.rejected(String::from("only standard dailies can be verified"));
+    }
+    let day = match seed_day(&level_info.seed) {
+        Ok(day) => day,
+        Err(e) => return report.rejected(format!("only dailies can be verified, {}", e)),
+    };
+
+    let mut run = HeadlessRun::for_day_with_math_mode(day, extras.math_mode);
+    let level_hash = run.level_info.as_ref().map(|level_info| level_info.level_hash.clone()).unwrap_or_default();
+    if level_hash != level_info.level_hash {
+        return report.rejected(format!("this build generates a different level for {}, its hash is {}", level_info.seed, level_hash));
+    }
+
+    let replay = GhostReplay {
+        math_mode: extras.math_mode,
+        checksums: extras.checksums,
+        ..GhostReplay::from_recorded_events(report.user.clone().unwrap_or_default(), level_info.seed.clone(), report.claimed_time.unwrap_or_default(), Some(level_hash), &recording.events)
+    };
+    let (mut frame, mut next_input) = (0, 0);
+    while frame < MAX_VERIFY_FRAMES && !run.finished() {
+        replay.step_run(&mut run, &mut frame, &mut next_input);
+        match replay.checksum_matches(frame, &run.simulation) {
+            Some(true) => report.checksums_checked += 1,
+            Some(false) if report.drifted_at_frame.is_none() => {
+                report.checksums_checked += 1;
+                report.drifted_at_frame = Some(frame);
+            }
+            _ => {}
+        }
+    }
+    report.frames = frame;
+    report.time = run.finished().then_some(run.total_time);
+    report.state_checksum = Some(format!("{:016x}", run.simulation.state_checksum()));
+
+    if let Some(drifted_at_frame) = report.drifted_at_frame {
+        return report.rejected(format!("the re-simulation drifted from the recorded run by frame {}", drifted_at_frame));
+    }
+    let Some(time) = report.time else {
+        return report.rejected(String::from("the re-simulated run doesn't finish"));
+    };
+    if let Some(claimed_time) = report.claimed_time.filter(|claimed_time| (time - claimed_time).abs() > GOLDEN_TIME_EPSILON) {
+        return report.rejected(format!("the re-simulated run finishes in {:.3}s, not the claimed {:.3}s", time, claimed_time));
+    }
+    report.verified = true;
+    report.leaderboard_message = report.verified_message();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        engine::app::event_system::{ElementStateType, FramedEvent, GameEvent},
+        game::golden_replays::GOLDEN_REPLAYS,
+    };
+
+    #[test]
+    fn test_parse_claim() {
+        let claim = ClaimedTime::parse("BEST_TIME seed=2025-07-07 time=10.985 user=bob hash=bbf3cdcedece11d9 rating=1500").unwrap();
+        assert_eq!(claim, ClaimedTime { seed: "2025-07-07".to_owned(), user: "bob".to_owned(), time: 10.985, level_hash: Some("bbf3cdcedece11d9".to_owned()) });
+        assert!(ClaimedTime::parse("BEST_TIME seed=2025-07-07 user=bob").is_err());
+        assert!(ClaimedTime::parse("LEADERBOARD_SYNC seed=2025-07-07 data=").is_err());
+    }
+
+    #[test]
+    fn test_verify_a_recorded_run() {
+        // the golden bot run recorded the way the game records it
+        let golden = GOLDEN_REPLAYS[0];
+        let (inputs, _) = GhostReplay::decode_inputs(golden.inputs).unwrap();
+        let events = inputs.iter().map(|input| FramedEvent {
+            frame: input.frame as u128,
+            event: GameEvent::KeyboardInput { key_code: input.key_code, state: if input.pressed { ElementStateType::Pressed } else { ElementStateType::Released } },
+        }).collect();
+        let run = HeadlessRun::for_day(seed_day(golden.seed).unwrap());
+        let header = recording_header(run.level_info.as_ref().unwrap(), MathMode::Native, &[]);
+        let recording = EventRecording { header, events };
+
+        let claim = ClaimedTime::parse(&format!("BEST_TIME seed={} time={:.3} user=bot hash={}", golden.seed, golden.time, golden.level_hash)).unwrap();
+        let report = verify_recording(&recording, Some(&claim));
+        assert!(report.verified, "{:?}", report.reason);
+        assert_eq!(report.time.map(|time| (time * 1000.0).round() / 1000.0), Some(golden.time));
+        assert!(report.to_json().contains("\"verified\": true"));
+        assert_eq!(report.leaderboard_message, Some(verified_message(golden.seed, "bot", claim.time, true)));
+
+        // caught before re-simulating
+        let other_day = ClaimedTime { seed: "2025-07-08".to_owned(), ..claim.clone() };
+        let report = verify_recording(&recording, Some(&other_day));
+        assert!(!report.verified);
+        assert_eq!(report.leaderboard_message, Some(verified_message("2025-07-08", "bot", claim.time, false)));
+        assert!(!verify_recording(&EventRecording { header: None, events: vec![] }, Some(&claim)).verified);
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{
+    core::math::{aabb2d::Aabb2d, vec2::Vec2},
+    engine::{app::camera::Camera, audio::{audio_manager::AudioManager, spatial::{Listener, StereoGain}, synth::AmbientSound}},
+    game::entity::entity_system::EntitySystem,
+    simulation::particles::simulation::Simulation,
+};
+
+const FULL_CURRENT: f32 = 3.0; // m/s of flowing water for the loudest rush
+const STILL_WATER_LOUDNESS: f32 = 0.2; // water that isn't flowing still laps
+const FULL_WIND: f32 = 7.0; // m/s^2 of wind for the loudest gust
+const LIFT_LOUDNESS: f32 = 0.5; // an elevator on the move
+const PORTAL_LOUDNESS: f32 = 0.3; // teleporters hum all the time so they're kept down
+
+/// Something in the level making one of the ambient sounds. It fills an area rather than sitting at a point,
+/// so a long river is heard all the way along it.
+#[derive(Debug, Clone)]
+pub struct SoundEmitter {
+    pub sound: AmbientSound,
+    pub area: Aabb2d,
+    pub loudness: f32, // 0 to 1, before it fades with distance
+}
+
+/// Everything in the level making a sound right now: water flowing, wind blowing and machinery running
+pub fn emitters(entity_system: &EntitySystem, sim: &Simulation) -> Vec<SoundEmitter> {
+    let mut emitters = vec![];
+    for zone in &sim.flow_zones {
+        let loudness = (zone.velocity.magnitude() / FULL_CURRENT).clamp(STILL_WATER_LOUDNESS, 1.0);
+        emitters.push(SoundEmitter { sound: AmbientSound::Water, area: zone.aabb.clone(), loudness });
+    }
+    for e in &entity_system.wind_zone_entity_system.0 {
+        emitters.push(SoundEmitter { sound: AmbientSound::Wind, area: e.region.clone(), loudness: e.vector.magnitude() / FULL_WIND });
+    }
+    for e in entity_system.elevator_entity_system.0.iter().filter(|e| e.is_moving()) {
+        emitters.push(SoundEmitter { sound: AmbientSound::Machinery, area: Aabb2d::new(e.pos(), Vec2::zero()), loudness: LIFT_LOUDNESS });
+    }
+    for e in &entity_system.teleporter_entity_system.0 {
+        for area in [&e.entrance, &e.exit] {
+            emitters.push(SoundEmitter { sound: AmbientSound::Machinery, area: area.clone(), loudness: PORTAL_LOUDNESS });
+        }
+    }
+    emitters
+}
+
+/// The world is heard from the middle of the screen, panned across its width
+pub fn listener(camera: &Camera) -> Listener {
+    Listener::new(Vec2::new(camera.target.x, camera.target.y), camera.view_height() * 0.5 * camera.aspect)
+}
+
+/// How loud each ambient sound is in each speaker, all of its emitters added together
+pub fn mix(listener: &Listener, emitters: &[SoundEmitter]) -> Vec<(AmbientSound, StereoGain)> {
+    AmbientSound::ALL.iter().map(|sound| {
+        let gain = emitters.iter()
+            .filter(|e| e.sound == *sound)
+            .fold(StereoGain::default(), |gain, e| gain.combine(listener.hear(&e.area, e.loudness)));
+        (*sound, gain)
+    }).collect()
+}
+
+/// Set the ambient loops for the level around the camera, once a frame. Quiet when not racing.
+pub fn play(audio: &mut AudioManager, camera: &Camera, entity_system: &EntitySystem, sim: &Simulation, racing: bool) {
+    let mix = if racing {
+        mix(&listener(camera), &emitters(entity_system, sim))
+    } else {
+        AmbientSound::ALL.iter().map(|sound| (*sound, StereoGain::default())).collect()
+    };
+    for (sound, gain) in mix {
+        audio.set_ambient(sound, gain);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::math::random::Random,
+        game::{
+            headless_run::HeadlessRun,
+            level::{level_blocks::{river_crossing::{RiverCrossing, RiverCrossingParams}, wind_zone::{WindDirection, WindZoneOperation, WindZoneParams}}, level_builder::LevelBuilderContext, level_builder_operation::LevelBuilderOperation},
+        },
+    };
+
+    #[test]
+    fn test_level_sounds_are_heard_from_the_camera() {
+        let mut run = HeadlessRun::empty();
+        let mut rng = Random::seed_from_str("world audio");
+        {
+            let mut context = LevelBuilderContext::new(&mut run.entity_system, &mut run.particle_vec, &mut run.simulation, &mut rng);
+            RiverCrossing { params: Some(RiverCrossingParams { width: 5.0, depth: 1.0, current: 3.0 }) }.execute(&mut context);
+            WindZoneOperation { params: Some(WindZoneParams { width: 6.0, height: 3.0, direction: WindDirection::Headwind, strength: 3.5 }) }.execute(&mut context);
+        }
+
+        let emitters = emitters(&run.entity_system, &run.simulation);
+        let sounds: Vec<AmbientSound> = emitters.iter().map(|e| e.sound).collect();
+        assert_eq!(sounds, vec![AmbientSound::Water, AmbientSound::Wind]);
+        assert_eq!(emitters[0].loudness, 1.0);
+        assert_eq!(emitters[1].loudness, 0.5);
+
+        let gain = |listener: &Listener, sound: AmbientSound| mix(listener, &emitters).into_iter().find(|(s, _)| *s == sound).unwrap().1;
+
+        // over the river it's all water, with the wind off to the right
+        let listener = Listener::new(Vec2::new(emitters[0].area.closest_point(Vec2::zero()).x + 1.0, 0.0), 5.0);
+        let water = gain(&listener, AmbientSound::Water);
+        assert!((water.left - water.right).abs() < 1e-6 && water.left > 0.5);
+        let wind = gain(&listener, AmbientSound::Wind);
+        assert!(wind.right > wind.left);
+        assert_eq!(gain(&listener, AmbientSound::Machinery), StereoGain::default());
+
+        // and well past both they're out of earshot
+        let listener = Listener::new(Vec2::new(100.0, 0.0), 5.0);
+        assert!(mix(&listener, &emitters).iter().all(|(_, gain)| *gain == StereoGain::default()));
+    }
+}
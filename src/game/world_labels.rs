@@ -0,0 +1,134 @@
+use std::fmt::{self, Write};
+
+use crate::{
+    core::math::{vec2::Vec2, vec4::Vec4},
+    engine::renderer::text_renderer::WorldLabel,
+    game::{entity::entity_system::EntitySystem, game_mode::GameMode, ghost::Ghost},
+    simulation::particles::particle_vec::ParticleVec,
+};
+
+pub const DISTANCE_MARKER_SPACING: f32 = 10.0; // metres
+const GROUND_SEARCH_HALF_WIDTH: f32 = 1.0; // how far either side of a marker to look for the ground under it
+
+/// A marker every 10m from the start towards the finish, standing above the ground.
+/// The ground is static so these only need working out once per level.
+pub fn distance_markers(particles: &ParticleVec, start: Vec2, finish_x: f32) -> Vec<WorldLabel> {
+    let direction = if finish_x < start.x { -1.0 } else { 1.0 }; // mirror mode runs right to left
+    let length = (finish_x - start.x).abs();
+    let colour = Vec4::new(1.0, 1.0, 1.0, 0.6);
+
+    let mut markers = vec![];
+    let mut distance = DISTANCE_MARKER_SPACING;
+    while distance < length {
+        let x = start.x + direction * distance;
+        let ground = particles.iter()
+            .filter(|p| p.imass == 0.0 && (p.pos.x - x).abs() < GROUND_SEARCH_HALF_WIDTH)
+            .map(|p| p.pos.y + p.radius)
+            .reduce(f32::max);
+        // no ground here, eg. over a gap, so nothing to stand the marker on
+        if let Some(ground) = ground {
+            markers.push(WorldLabel::new(format!("{}m", distance), Vec2::new(x, ground + 1.5), 0.5, colour));
+        }
+        distance += DISTANCE_MARKER_SPACING;
+    }
+    markers
+}
+
+/// Labels that move with the level each frame: the finish banner, checkpoint numbers and ghost nametags.
+/// They are written over last frame's labels so their text is only allocated when there are more labels than before.
+pub fn update_level_labels(labels: &mut Vec<WorldLabel>, entity_system: &EntitySystem, game_mode: GameMode, ghosts: &[Ghost]) {
+    let mut num_labels = 0;
+
+    for finish in &entity_system.finish_entity_system.entities {
+        let x = (finish.aabb.min.x + finish.aabb.max.x) * 0.5;
+        set_label(labels, &mut num_labels, format_args!("FINISH"), Vec2::new(x, finish.aabb.max.y + 1.0), 1.2, Vec4::new(1.0, 1.0, 1.0, 1.0));
+    }
+
+    // checkpoint gates are only shown in time attack
+    if game_mode == GameMode::TimeAttack {
+        for (i, checkpoint) in entity_system.checkpoint_entity_system.entities.iter().enumerate() {
+            let colour = if checkpoint.reached { Vec4::new(0.0, 1.0, 0.0, 1.0) } else { Vec4::new(1.0, 0.8, 0.0, 1.0) };
+            let x = (checkpoint.aabb.min.x + checkpoint.aabb.max.x) * 0.5;
+            set_label(labels, &mut num_labels, format_args!("{}", i + 1), Vec2::new(x, checkpoint.aabb.max.y + 0.6), 0.8, colour);
+        }
+    }
+
+    for ghost in ghosts {
+        let (sum, count) = ghost.car_particles().into_iter().fold((Vec2::new(0.0, 0.0), 0), |(sum, count), (pos, _)| (sum + pos, count + 1));
+        if count == 0 {
+            continue;
+        }
+        let center = sum / count as f32;
+        set_label(labels, &mut num_labels, format_args!("{}", ghost.name), center + Vec2::new(0.0, 1.4), 0.35, Vec4::new(0.6, 0.8, 1.0, 1.0));
+    }
+
+    labels.truncate(num_labels);
+}
+
+/// Overwrite the next label, or add one if there aren't enough yet
+fn set_label(labels: &mut Vec<WorldLabel>, num_labels: &mut usize, text: fmt::Arguments, position: Vec2, size: f32, colour: Vec4) {
+    match labels.get_mut(*num_labels) {
+        Some(label) => {
+            label.text.clear();
+            let _ = label.text.write_fmt(text);
+            label.position = position;
+            label.size = size;
+            label.colour = colour;
+        }
+        None => labels.push(WorldLabel::new(text.to_string(), position, size, colour)),
+    }
+    *num_labels += 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::math::aabb2d::Aabb2d,
+        game::entity::entities::{checkpoint_entity::CheckpointEntity, finish_entity::FinishEntity},
+        simulation::particles::particle::Particle,
+    };
+
+    #[test]
+    fn test_distance_markers_stand_on_the_ground() {
+        let mut particles = ParticleVec::new();
+        // flat ground along y = 0 with a gap from 15m to 25m
+        for i in 0..80 {
+            let x = i as f32 * 0.5;
+            if !(15.0..25.0).contains(&x) {
+                particles.push(*Particle::default().set_pos(Vec2::new(x, 0.0)).set_radius(0.25).set_static(true));
+            }
+        }
+        // the car doesn't count as ground
+        particles.push(*Particle::default().set_pos(Vec2::new(10.0, 3.0)).set_radius(0.25).set_mass_2(1.0));
+
+        let markers = distance_markers(&particles, Vec2::new(0.0, 1.0), 35.0);
+        let texts: Vec<&str> = markers.iter().map(|marker| marker.text.as_str()).collect();
+        assert_eq!(texts, vec!["10m", "30m"]);
+        assert_eq!(markers[0].position, Vec2::new(10.0, 1.75));
+
+        // mirrored
+        let markers = distance_markers(&particles, Vec2::new(40.0, 1.0), 5.0);
+        assert_eq!(markers[0].position.x, 30.0);
+    }
+
+    #[test]
+    fn test_level_labels_are_written_over() {
+        let mut entity_system = EntitySystem::new();
+        entity_system.finish_entity_system.push(FinishEntity::new(Aabb2d::new(Vec2::new(50.0, 2.0), Vec2::new(1.0, 2.0))));
+        entity_system.checkpoint_entity_system.entities.push(CheckpointEntity::new(Aabb2d::new(Vec2::new(20.0, 2.0), Vec2::new(0.5, 2.0))));
+
+        let mut labels = vec![];
+        update_level_labels(&mut labels, &entity_system, GameMode::TimeAttack, &[]);
+        let texts: Vec<&str> = labels.iter().map(|label| label.text.as_str()).collect();
+        assert_eq!(texts, vec!["FINISH", "1"]);
+        assert_eq!(labels[0].position, Vec2::new(50.0, 5.0));
+
+        // the checkpoint label goes when the mode doesn't show them, and the finish keeps its text
+        let finish_text = labels[0].text.as_ptr();
+        update_level_labels(&mut labels, &entity_system, GameMode::Standard, &[]);
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels[0].text, "FINISH");
+        assert_eq!(labels[0].text.as_ptr(), finish_text);
+    }
+}
@@ -1,9 +1,146 @@
 #![allow(dead_code, unused_variables, unused_imports)]
 #![feature(test)]
 
+#[cfg(feature = "client")]
 use planck_time_trials::{engine::app::app::App, game::game::Game};
+use planck_time_trials::{engine::app::event_system::EventRecording, game::{bench::{run_bench, BenchConfig}, determinism_audit::{run_audit, DEFAULT_AUDIT_FRAMES}, golden_replays::{golden_replay_source, record_bot_run}, leaderboard_bot::{run_bot, DEFAULT_BOT_NICKNAME}, metrics::Metrics, level::level_pack::run_level_pack_command, level_comparison::run_compare_levels, soak_test::{run_soak, SoakConfig, DEFAULT_SOAK_MINUTES}, verify::{verify_recording, ClaimedTime, RECORDING_PATH}}};
 
 fn main() {
+    // developer tool: run today's level twice headless and report where the runs diverge
+    // usage: planck-time-trials determinism_audit [frames]
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("determinism_audit") {
+        let num_frames = args.get(2).and_then(|n| n.parse::<u32>().ok()).unwrap_or(DEFAULT_AUDIT_FRAMES);
+        match run_audit(num_frames) {
+            Some(divergence) => {
+                println!("{}", divergence);
+                std::process::exit(1);
+            }
+            None => println!("Runs matched for {} frames", num_frames),
+        }
+        return;
+    }
+
+    // developer tool: play bot runs back to back for a long time, checking nothing grows without bound
+    // usage: planck-time-trials soak [minutes] [--metrics <file>]
+    if args.get(1).map(String::as_str) == Some("soak") {
+        let minutes = args.get(2).and_then(|n| n.parse::<u64>().ok()).unwrap_or(DEFAULT_SOAK_MINUTES);
+        let config = SoakConfig { duration: std::time::Duration::from_secs(minutes * 60), metrics_path: Metrics::path_from_args(&args), ..SoakConfig::default() };
+        match run_soak(&config) {
+            Ok(runs) => println!("Soak passed, {} runs in {} minutes", runs, minutes),
+            Err(e) => {
+                println!("Soak failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // developer tool: step a demo scene without a window and print how long each phase of the step took as JSON
+    // usage: planck-time-trials bench [scene] [--seconds <simulated seconds>] [--dt <time step>]
+    if args.get(1).map(String::as_str) == Some("bench") {
+        match run_bench(&BenchConfig::from_args(&args[2..])) {
+            Ok(report) => println!("{}", report.to_json()),
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // developer tool: record the bot driving a daily, to pin in GOLDEN_REPLAYS
+    // usage: planck-time-trials golden_replay <seed>
+    if args.get(1).map(String::as_str) == Some("golden_replay") {
+        match args.get(2).ok_or_else(|| String::from("usage: planck-time-trials golden_replay <seed>")).and_then(|seed| record_bot_run(seed)) {
+            Ok(replay) => println!("{}", golden_replay_source(&replay)),
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // re-simulate a recorded run without a window and check it against the BEST_TIME posted for it, printing a JSON report
+    // usage: planck-time-trials verify [recording file] [--claim "BEST_TIME seed=... time=... user=..."]
+    if args.get(1).map(String::as_str) == Some("verify") {
+        let path = args.get(2).filter(|arg| !arg.starts_with("--")).map_or(RECORDING_PATH, String::as_str);
+        let claim = match args.iter().position(|arg| arg == "--claim").map(|i| args.get(i + 1).map(String::as_str).unwrap_or_default()) {
+            Some(message) => match ClaimedTime::parse(message) {
+                Ok(claim) => Some(claim),
+                Err(e) => {
+                    println!("{}", e);
+                    std::process::exit(2);
+                }
+            },
+            None => None,
+        };
+        let recording = match EventRecording::load(path) {
+            Ok(recording) => recording,
+            Err(e) => {
+                println!("Failed to load {}: {}", path, e);
+                std::process::exit(2);
+            }
+        };
+        let report = verify_recording(&recording, claim.as_ref());
+        println!("{}", report.to_json());
+        if !report.verified {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // import, export and list community level packs
+    // usage: planck-time-trials level_pack list | import <file> | export <pack id> <file> | hash <file>
+    if args.get(1).map(String::as_str) == Some("level_pack") {
+        match run_level_pack_command(&args[2..]) {
+            Ok(output) => println!("{}", output),
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // developer tool: build a seed with two level_gen.toml configs and print the levels side by side with difficulty estimates
+    // usage: planck-time-trials compare_levels <seed> <a.toml> <b.toml> [--svg <file>]
+    if args.get(1).map(String::as_str) == Some("compare_levels") {
+        match run_compare_levels(&args[2..]) {
+            Ok(output) => print!("{}", output),
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // idle in the leaderboard channels and post a digest of the day's results when the daily rolls over
+    // usage: planck-time-trials bot [nickname] [--metrics <file>]
+    if args.get(1).map(String::as_str) == Some("bot") {
+        #[cfg(feature = "irc")]
+        {
+            let nickname = args.get(2).filter(|arg| !arg.starts_with("--")).cloned().unwrap_or_else(|| DEFAULT_BOT_NICKNAME.to_owned());
+            run_bot(nickname, Metrics::path_from_args(&args));
+            return;
+        }
+        #[cfg(not(feature = "irc"))]
+        {
+            println!("This build has no IRC, rebuild with --features irc to run the bot");
+            std::process::exit(2);
+        }
+    }
+
+    #[cfg(feature = "client")]
     let _ = App::<Game>::new()
         .run();
+
+    // a headless build for servers, see the client feature
+    #[cfg(not(feature = "client"))]
+    {
+        println!("This build has no window, only the determinism_audit, soak, bench, golden_replay, verify, level_pack, compare_levels and bot commands");
+        std::process::exit(2);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+
+use crate::core::math::vec2::Vec2;
+
+/// The normal corrections each contact pair made during the last step, kept so a pair that is
+/// still touching next step can be warm started from where the solver finished instead of from scratch
+#[derive(Clone, Default)]
+pub struct ContactCache {
+    corrections: HashMap<(usize, usize), (Vec2, Vec2)>, // (i1, i2) -> correction applied to i1, correction applied to i2
+}
+
+impl ContactCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.corrections.clear();
+    }
+
+    pub fn insert(&mut self, i1: usize, i2: usize, correction1: Vec2, correction2: Vec2) {
+        self.corrections.insert((i1, i2), (correction1, correction2));
+    }
+
+    /// Corrections in the order asked for, the pair may have been found the other way around last step
+    pub fn get(&self, i1: usize, i2: usize) -> Option<(Vec2, Vec2)> {
+        self.corrections.get(&(i1, i2)).copied()
+            .or_else(|| self.corrections.get(&(i2, i1)).map(|(correction2, correction1)| (*correction1, *correction2)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.corrections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.corrections.is_empty()
+    }
+
+    /// Every pair that was touching last step, in no particular order
+    pub fn pairs(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.corrections.keys().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_either_order() {
+        let mut cache = ContactCache::new();
+        cache.insert(1, 2, Vec2::new(0.0, 1.0), Vec2::new(0.0, -1.0));
+        assert_eq!(cache.get(1, 2), Some((Vec2::new(0.0, 1.0), Vec2::new(0.0, -1.0))));
+        assert_eq!(cache.get(2, 1), Some((Vec2::new(0.0, -1.0), Vec2::new(0.0, 1.0))));
+        assert_eq!(cache.get(1, 3), None);
+    }
+}
@@ -1,10 +1,14 @@
-use crate::simulation::particles::particle_vec::ParticleVec;
+use crate::{core::math::vec2::Vec2, simulation::particles::particle_vec::ParticleVec};
 
 
+#[derive(Clone)]
 pub struct ContactConstraint {
     pub i1: usize,
     pub i2: usize,
     pub stable: bool,
+
+    pub correction1: Vec2, // corrections applied this step, including any warm start, see ContactCache
+    pub correction2: Vec2,
 }
 
 impl ContactConstraint {
@@ -12,11 +16,13 @@ impl ContactConstraint {
         Self {
             i1,
             i2,
-            stable
+            stable,
+            correction1: Vec2::new(0.0, 0.0),
+            correction2: Vec2::new(0.0, 0.0),
         }
     }
 
-    pub fn project(&self, estimates: &mut ParticleVec, counts: &Vec<usize>) {
+    pub fn project(&mut self, estimates: &mut ParticleVec, counts: &Vec<usize>) {
         let p1 = estimates[self.i1];
         let p2 = estimates[self.i2];
         
@@ -43,6 +49,8 @@ impl ContactConstraint {
 
         estimates[self.i1].pos_guess += dp1;
         estimates[self.i2].pos_guess += dp2;
+        self.correction1 += dp1;
+        self.correction2 += dp2;
 
         if self.stable {
             estimates[self.i1].pos += dp1;
@@ -56,6 +64,7 @@ impl ContactConstraint {
     }
 }
 
+#[derive(Clone)]
 pub struct ContactConstraintVec(pub Vec<ContactConstraint>);
 
 impl ContactConstraintVec {
@@ -69,8 +78,8 @@ impl ContactConstraintVec {
         }
     }
 
-    pub fn solve(&self, particles: &mut ParticleVec, counts: &Vec<usize>) {
-        for c in &self.0 {
+    pub fn solve(&mut self, particles: &mut ParticleVec, counts: &Vec<usize>) {
+        for c in &mut self.0 {
             c.project(particles, counts);
         }
     }
@@ -1,12 +1,15 @@
 use crate::simulation::particles::particle_vec::ParticleVec;
 
 
+#[derive(Clone)]
 pub struct DistanceConstraint {
     pub d: f32,
     pub i1: usize,
     pub i2: usize,
     pub stable: bool,
     pub enabled: bool,
+    pub is_rope: bool, // only pulls the particles together, it goes slack instead of pushing them apart
+    pub max_strain: Option<f32>, // breaks when stretched by more than this fraction of d, eg. 0.1 for 10%
 }
 
 impl DistanceConstraint {
@@ -19,9 +22,19 @@ impl DistanceConstraint {
             i2,
             stable,
             enabled: true,
+            is_rope: false,
+            max_strain: None,
         }
     }
 
+    pub fn breakable(d: f32, i1: usize, i2: usize, max_strain: f32) -> Self {
+        Self { max_strain: Some(max_strain), ..Self::new(d, i1, i2, false) }
+    }
+
+    pub fn rope(d: f32, i1: usize, i2: usize) -> Self {
+        Self { is_rope: true, ..Self::new(d, i1, i2, false) }
+    }
+
     pub fn from_particles(i1: usize, i2: usize, particles: &ParticleVec) -> Self {
         let d = (particles[i1].pos - particles[i2].pos).magnitude();
         Self::new(d, i1, i2, false)
@@ -42,6 +55,9 @@ impl DistanceConstraint {
         let diff = p1.pos_guess - p2.pos_guess; //glm::dvec2 diff = p1->ep - p2->ep;
         let w_sum = p1.imass + p2.imass;
         let dist = diff.magnitude();
+        if self.is_rope && dist <= self.d {
+            return;
+        }
         let mag = dist - self.d;
         let scale = mag / w_sum;
 
@@ -53,12 +69,36 @@ impl DistanceConstraint {
         estimates[self.i2].pos_guess += dp2;
     }
 
+    /// How far past its rest length the constraint is stretched, as a fraction of d
+    pub fn strain(&self, particles: &ParticleVec) -> f32 {
+        let dist = (particles[self.i1].pos - particles[self.i2].pos).magnitude();
+        (dist - self.d) / self.d
+    }
+
+    /// Switch a breakable constraint off for good once it has been stretched past max_strain.
+    /// It stays in the vec so the indices the car and winches hold on to still point at the right constraints.
+    pub fn break_if_overstrained(&mut self, particles: &ParticleVec) -> bool {
+        let Some(max_strain) = self.max_strain else {
+            return false;
+        };
+        if !self.enabled || self.strain(particles) <= max_strain {
+            return false;
+        }
+        self.enabled = false;
+        true
+    }
+
     pub fn update_counts(&self, counts: &mut Vec<usize>) {
+        // ropes are switched off and on at runtime, so disabled constraints shouldn't water down the others
+        if !self.enabled {
+            return;
+        }
         counts[self.i1] += 1;
         counts[self.i2] += 1;
     }
 }
 
+#[derive(Clone)]
 pub struct DistanceConstraintVec(pub Vec<DistanceConstraint>);
 
 impl DistanceConstraintVec {
@@ -81,6 +121,11 @@ impl DistanceConstraintVec {
     pub fn push(&mut self, c: DistanceConstraint) {
         self.0.push(c);
     }
+
+    /// Break any breakable constraints stretched too far this step, returns how many broke
+    pub fn break_overstrained(&mut self, particles: &ParticleVec) -> usize {
+        self.0.iter_mut().map(|c| c.break_if_overstrained(particles)).filter(|&broke| broke).count()
+    }
 }
 
 #[cfg(test)]
@@ -115,4 +160,49 @@ mod tests {
         let new_dist = (particles[0].pos_guess - particles[1].pos_guess).magnitude();
         assert_eq!(new_dist, 10.0); // Should not have moved
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_rope_goes_slack() {
+        let mut particles = ParticleVec::new();
+        particles.push(*Particle::default().set_pos(Vec2::new(0.0, 0.0)).set_mass_2(1.0));
+        particles.push(*Particle::default().set_pos(Vec2::new(2.0, 0.0)).set_mass_2(1.0));
+        for p in particles.iter_mut() {
+            p.pos_guess = p.pos;
+        }
+        let counts = vec![1, 1];
+
+        // shorter than the rope, nothing happens
+        DistanceConstraint::rope(5.0, 0, 1).project(&mut particles, &counts);
+        assert_eq!(particles[1].pos_guess, Vec2::new(2.0, 0.0));
+
+        // longer than the rope, pulled in
+        DistanceConstraint::rope(1.0, 0, 1).project(&mut particles, &counts);
+        let dist = (particles[0].pos_guess - particles[1].pos_guess).magnitude();
+        assert!((dist - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_breakable_snaps_when_overstrained() {
+        let mut particles = ParticleVec::new();
+        particles.push(*Particle::default().set_pos(Vec2::new(0.0, 0.0)).set_mass_2(1.0));
+        particles.push(*Particle::default().set_pos(Vec2::new(1.05, 0.0)).set_mass_2(1.0));
+        let mut constraints = DistanceConstraintVec::new();
+        constraints.push(DistanceConstraint::breakable(1.0, 0, 1, 0.1));
+        constraints.push(DistanceConstraint::new(1.0, 0, 1, false));
+
+        // 5% stretch holds
+        assert_eq!(constraints.break_overstrained(&particles), 0);
+        assert!(constraints.0[0].enabled);
+
+        // 20% stretch snaps the breakable one, the plain one never breaks
+        particles[1].pos = Vec2::new(1.2, 0.0);
+        assert_eq!(constraints.break_overstrained(&particles), 1);
+        assert!(!constraints.0[0].enabled);
+        assert!(constraints.0[1].enabled);
+
+        // once broken it stays broken even when the particles come back together
+        particles[1].pos = Vec2::new(1.0, 0.0);
+        assert_eq!(constraints.break_overstrained(&particles), 0);
+        assert!(!constraints.0[0].enabled);
+    }
+}
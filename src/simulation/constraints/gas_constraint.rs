@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::f32::consts::PI;
 
-use crate::{core::math::{vec2::Vec2, vec3::Vec3}, simulation::particles::{particle::{Particle, Phase}, particle_vec::ParticleVec}};
+use crate::{core::math::{deterministic::MathMode, vec2::Vec2, vec3::Vec3}, simulation::particles::{particle::{Particle, Phase}, particle_vec::ParticleVec, uniform_grid::UniformGrid}};
 
 const H: f32 = 2.0;
 const H2: f32 = 4.0;
@@ -17,6 +17,7 @@ const E_P: f32 = 4.0;
 const DQ_P: f32 = 0.2;
 
 
+#[derive(Clone)]
 pub struct GasConstraint {
     pub p0: f32,
     pub neighbors: Vec<Vec<usize>>,
@@ -50,18 +51,30 @@ impl GasConstraint {
         }
     }
 
-    pub fn project(&mut self, estimates: &mut ParticleVec, counts: &Vec<usize>) {
+    pub fn project(&mut self, estimates: &mut ParticleVec, counts: &Vec<usize>, grid: &UniformGrid, math: MathMode) {
+        // Nothing to do while all of it is frozen, eg. far from the car, see ActivityRegion
+        if self.ps.iter().all(|&i| estimates[i].imass == 0.0) {
+            return;
+        }
+
         // Find neighboring particles and estimate pi for each particle
         self.lambdas.clear();
+        let mut candidates = vec![];
         for k in 0..self.ps.len() { //for (int k = 0; k < ps.size(); k++) {
             self.neighbors[k].clear();
             let i = self.ps[k];
             let mut p_i = estimates[i]; // todo: make ref?
+
+            // Fixed particles don't move, they'd only divide by their zero inverse mass below
+            if p_i.imass == 0.0 {
+                continue;
+            }
             let mut pi = 0.0;
             let mut denom = 0.0;
 
             // Find neighbors
-            for j in 0..estimates.len() { //(int j = 0; j < estimates->size(); j++) {
+            grid.query(p_i.pos_guess, H, &mut candidates);
+            for &j in &candidates { //(int j = 0; j < estimates->size(); j++) {
 
                 // Check if the next particle is actually this particle
                 if j != i {
@@ -127,7 +140,7 @@ impl GasConstraint {
                 let r = p_i.pos_guess - p_j.pos_guess;
                 let rlen = r.magnitude(); //glm::length(r);
                 let sg = spiky_grad(&r, rlen);
-                let lambda_corr = -K_P * (poly6(rlen * rlen) / poly6(DQ_P * DQ_P * H * H)).powf(E_P); //pow(, E_P);
+                let lambda_corr = -K_P * math.powf(poly6(rlen * rlen) / poly6(DQ_P * DQ_P * H * H), E_P); //pow(, E_P);
 
                 let lambdas_i = match self.lambdas.get(&i) {
                     Some(value) => *value,
@@ -154,6 +167,9 @@ impl GasConstraint {
         for k in 0..self.ps.len() { //(int k = 0; k < ps.size(); k++) {
             let i = self.ps[k];
             //let p_i = estimates[i]; // todo: make ref
+            if estimates[i].imass == 0.0 {
+                continue;
+            }
             estimates[i].pos_guess += self.deltas[k] / (self.neighbors[k].len() + counts[i]) as f32;
         }
     }
@@ -195,8 +211,17 @@ impl GasConstraint {
         self.deltas.push(Vec2::new(0.0, 0.0));
         self.ps.push(index);
     }
+
+    /// Follow the particles being renumbered, see Simulation::remove_particles
+    pub fn remap_particles(&mut self, remap: &[Option<usize>]) {
+        self.ps = self.ps.iter().filter_map(|&i| remap[i]).collect();
+        self.neighbors = vec![Vec::<usize>::new(); self.ps.len()];
+        self.deltas = vec![Vec2::new(0.0, 0.0); self.ps.len()];
+        self.lambdas.clear();
+    }
 }
 
+#[derive(Clone)]
 pub struct GasConstraintVec(pub Vec<GasConstraint>);
 
 impl GasConstraintVec {
@@ -210,9 +235,11 @@ impl GasConstraintVec {
         }
     }
 
-    pub fn solve(&mut self, particles: &mut ParticleVec, counts: &Vec<usize>) {
+    pub fn solve(&mut self, particles: &mut ParticleVec, counts: &Vec<usize>, grid: &mut UniformGrid, math: MathMode) {
         for c in &mut self.0 {
-            c.project(particles, counts);
+            // the last constraint moved particles, the neighbour search has to see where they are now
+            grid.update(particles);
+            c.project(particles, counts, grid, math);
         }
     }
 
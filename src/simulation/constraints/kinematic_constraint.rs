@@ -0,0 +1,64 @@
+use crate::{core::math::vec2::Vec2, simulation::particles::particle_vec::ParticleVec};
+
+/// Carries a group of particles along a path driven from outside the solver, eg. a moving platform.
+/// Each particle is put at its offset from target every iteration, so the group moves as one without turning.
+/// The particles should be static: nothing else in the solver moves them then, and whatever they run into is pushed out of the way.
+#[derive(Clone)]
+pub struct KinematicConstraint {
+    pub particle_indices: Vec<usize>,
+    pub offsets: Vec<Vec2>, // where each particle sits relative to target
+    pub target: Vec2,
+}
+
+impl KinematicConstraint {
+    /// Holds the particles where they are now, relative to target
+    pub fn from_particles(particle_indices: Vec<usize>, target: Vec2, particles: &ParticleVec) -> Self {
+        let offsets = particle_indices.iter().map(|&i| particles[i].pos - target).collect();
+        Self {
+            particle_indices,
+            offsets,
+            target,
+        }
+    }
+
+    pub fn project(&self, estimates: &mut ParticleVec) {
+        for (&i, offset) in self.particle_indices.iter().zip(self.offsets.iter()) {
+            estimates[i].pos_guess = self.target + *offset;
+        }
+    }
+
+    /// Keep the particles that are still there after Simulation::remove_particles, with their new indices
+    pub fn remap_particles(&mut self, remap: &[Option<usize>]) {
+        let (particle_indices, offsets) = self.particle_indices.iter().zip(self.offsets.iter())
+            .filter_map(|(&i, offset)| Some((remap[i]?, *offset)))
+            .unzip();
+        self.particle_indices = particle_indices;
+        self.offsets = offsets;
+    }
+
+    pub fn mirror_x(&mut self) {
+        self.target.x = -self.target.x;
+        for offset in self.offsets.iter_mut() {
+            offset.x = -offset.x;
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct KinematicConstraintVec(pub Vec<KinematicConstraint>);
+
+impl KinematicConstraintVec {
+    pub fn new() -> Self {
+        Self(vec![])
+    }
+
+    pub fn solve(&self, particles: &mut ParticleVec) {
+        for c in &self.0 {
+            c.project(particles);
+        }
+    }
+
+    pub fn push(&mut self, c: KinematicConstraint) {
+        self.0.push(c);
+    }
+}
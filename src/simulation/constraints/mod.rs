@@ -6,4 +6,6 @@ pub mod total_fluid_constraint;
 pub mod contact_constraint;
 pub mod gas_constraint;
 pub mod spring_constraint;
-pub mod volume_constraint;
\ No newline at end of file
+pub mod volume_constraint;
+pub mod kinematic_constraint;
+pub mod contact_cache;
@@ -1,13 +1,17 @@
-use crate::{core::math::vec2::Vec2, simulation::particles::{body::Body, particle::Particle, particle_vec::ParticleVec}};
+use crate::{core::math::{deterministic::MathMode, vec2::Vec2}, simulation::particles::{body::Body, particle::Particle, particle_vec::ParticleVec}};
 
 
+#[derive(Clone)]
 pub struct RigidContactConstraint {
-   i1: usize,
-   i2: usize, 
+   pub i1: usize,
+   pub i2: usize, 
 
    n: Vec2,
    d: f32,
    stable: bool,
+
+   pub correction1: Vec2, // normal corrections applied this step, including any warm start, see ContactCache
+   pub correction2: Vec2,
 } 
 
 impl RigidContactConstraint {
@@ -17,15 +21,17 @@ impl RigidContactConstraint {
             i2,
             stable,
             n: Vec2::new(0.0, 0.0),
-            d: 0.0
+            d: 0.0,
+            correction1: Vec2::new(0.0, 0.0),
+            correction2: Vec2::new(0.0, 0.0),
         }
     }
 
-    pub fn project(&mut self, estimates: &mut ParticleVec, counts: &Vec<usize>, bodies: &Vec<Body>) {
+    pub fn project(&mut self, estimates: &mut ParticleVec, counts: &Vec<usize>, bodies: &Vec<Body>, math: MathMode) {
         let mut p1 = estimates[self.i1]; // todo: use ref's, but has safety issues
         let mut p2 = estimates[self.i2];
-        let dat1 = p1.get_sdf_data(bodies, self.i1);
-        let dat2 = p2.get_sdf_data(bodies, self.i2);
+        let dat1 = p1.get_sdf_data(bodies, self.i1, math);
+        let dat2 = p2.get_sdf_data(bodies, self.i2, math);
 
         if dat1.distance < 0.0 || dat2.distance < 0.0 {
             let x12 = p2.get_p(self.stable) - p1.get_p(self.stable);
@@ -59,6 +65,8 @@ impl RigidContactConstraint {
         if !self.stable {
             p1.pos_guess += dp1;
             p2.pos_guess += dp2;
+            self.correction1 += dp1;
+            self.correction2 += dp2;
 
             estimates[self.i1].pos_guess = p1.pos_guess; // copy changes to copies back into estimates (hack to work around unsafe for now)
             estimates[self.i2].pos_guess = p2.pos_guess;
@@ -142,6 +150,7 @@ impl RigidContactConstraint {
     }
 }
 
+#[derive(Clone)]
 pub struct RigidContactConstraintVec(pub Vec<RigidContactConstraint>);
 
 impl RigidContactConstraintVec {
@@ -155,9 +164,9 @@ impl RigidContactConstraintVec {
         }
     }
 
-    pub fn solve(&mut self, particles: &mut ParticleVec, counts: &Vec<usize>, bodies: &Vec<Body>) {
+    pub fn solve(&mut self, particles: &mut ParticleVec, counts: &Vec<usize>, bodies: &Vec<Body>, math: MathMode) {
         for c in &mut self.0 {
-            c.project(particles, counts, bodies);
+            c.project(particles, counts, bodies, math);
         }
     }
 
@@ -1,5 +1,6 @@
 use crate::simulation::particles::particle_vec::ParticleVec;
 
+#[derive(Clone)]
 pub struct SpringConstraint {
     pub d: f32,
     pub stiffness: f32,
@@ -78,6 +79,7 @@ impl SpringConstraint {
     }
 }
 
+#[derive(Clone)]
 pub struct SpringConstraintVec(pub Vec<SpringConstraint>);
 
 impl SpringConstraintVec {
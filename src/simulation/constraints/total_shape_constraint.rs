@@ -1,6 +1,7 @@
-use crate::{core::math::vec2::Vec2, simulation::particles::{body::Body, particle_vec::ParticleVec}};
+use crate::{core::math::{deterministic::MathMode, vec2::Vec2}, simulation::particles::{body::Body, particle_vec::ParticleVec}};
 
 
+#[derive(Clone)]
 pub struct TotalShapeConstraint {
 }
 
@@ -10,9 +11,9 @@ impl TotalShapeConstraint {
         }
     }
 
-    fn guess(&self, idx: usize, body: &Body) -> Vec2 {
-        let c = body.angle.cos();
-        let s = body.angle.sin();
+    fn guess(&self, idx: usize, body: &Body, math: MathMode) -> Vec2 {
+        let c = math.cos(body.angle);
+        let s = math.sin(body.angle);
 
         let q = match body.rs.get(&idx) {
             Some(value) => *value,
@@ -23,14 +24,14 @@ impl TotalShapeConstraint {
         return d + body.center;
     }
 
-    pub fn project(&self, estimates: &mut ParticleVec, _counts: &Vec<usize>, body: &mut Body) {
-        body.update_com(estimates, true);
+    pub fn project(&self, estimates: &mut ParticleVec, _counts: &Vec<usize>, body: &mut Body, math: MathMode) {
+        body.update_com(estimates, true, math);
 
         // implemented using http://labs.byhook.com/2010/06/29/particle-based-rigid-bodies-using-shape-matching/
         for i in 0..body.particle_indicies.len() {
             let idx = body.particle_indicies[i];
             let p = &mut estimates[idx];
-            p.pos_guess += (self.guess(idx, body) - p.pos_guess) * body.stiffness;
+            p.pos_guess += (self.guess(idx, body, math) - p.pos_guess) * body.stiffness;
         }
     }
 
@@ -1,5 +1,6 @@
 use crate::{core::math::vec2::Vec2, simulation::particles::particle_vec::ParticleVec};
 
+#[derive(Clone)]
 pub struct VolumeConstraint {
     pub rest_volume: f32,
     pub compliance: f32,
@@ -19,6 +20,11 @@ impl VolumeConstraint {
         constraint
     }
 
+    /// Mirroring the particles reverses their winding order, which flips the sign of the volume.
+    pub fn mirror_x(&mut self) {
+        self.rest_volume = -self.rest_volume;
+    }
+
     fn calculate_volume(&self, particles: &ParticleVec, use_pos: bool) -> f32 {
         let mut volume = 0.0;
         let n = self.particle_indices.len();
@@ -85,6 +91,7 @@ impl VolumeConstraint {
     }
 }
 
+#[derive(Clone)]
 pub struct VolumeConstraintVec(pub Vec<VolumeConstraint>);
 
 impl VolumeConstraintVec {
@@ -126,6 +133,32 @@ mod tests {
     use super::*;
     use crate::simulation::particles::particle::Particle;
 
+    #[test]
+    fn test_mirror_x_keeps_constraint_satisfied() {
+        let mut particles = ParticleVec::new();
+        let positions = vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(2.0, 2.0),
+            Vec2::new(0.0, 2.0),
+        ];
+
+        let mut indices = vec![];
+        for (i, pos) in positions.iter().enumerate() {
+            let mut p = Particle::default();
+            p.pos = *pos;
+            particles.push(p);
+            indices.push(i);
+        }
+
+        let mut constraint = VolumeConstraint::new(0.0, indices, &particles);
+        for p in particles.iter_mut() {
+            p.pos.x = -p.pos.x;
+        }
+        constraint.mirror_x();
+        assert_eq!(constraint.calculate_volume(&particles, true), constraint.rest_volume);
+    }
+
     #[test]
     fn test_volume_calculation() {
         let mut particles = ParticleVec::new();
@@ -0,0 +1,376 @@
+use std::sync::mpsc::channel;
+
+use crate::{core::math::vec2::Vec2, simulation::particles::simulation::{Simulation, SolverStats}};
+
+const WORKGROUP_SIZE: u32 = 64; // matches @workgroup_size in gpu_solver.wgsl
+const GUESS_SIZE: wgpu::BufferAddress = std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress;
+
+// which walls a particle is touching, see BOUNDARY_* in gpu_solver.wgsl
+const BOUNDARY_X_MIN: u32 = 1;
+const BOUNDARY_X_MAX: u32 = 2;
+const BOUNDARY_Y_MIN: u32 = 4;
+const BOUNDARY_Y_MAX: u32 = 8;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuParams {
+    x_boundaries: [f32; 2],
+    y_boundaries: [f32; 2],
+    num_particles: u32,
+    _padding: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuParticle {
+    pos: [f32; 2],
+    radius: f32,
+    tmass: f32,
+    s_friction: f32,
+    k_friction: f32,
+    count: f32,
+    boundaries: u32,
+    contact_start: u32,
+    contact_count: u32,
+}
+
+/// What gets uploaded for a step: the particles with their contacts grouped per particle, so each
+/// GPU thread can gather its own corrections without atomics
+struct GpuStep {
+    params: GpuParams,
+    particles: Vec<GpuParticle>,
+    contacts: Vec<u32>,
+    guesses: Vec<[f32; 2]>,
+}
+
+impl GpuStep {
+    fn from_simulation(sim: &Simulation) -> Self {
+        let mut particles: Vec<GpuParticle> = sim.particles.0.iter().enumerate().map(|(i, p)| GpuParticle {
+            pos: [p.pos.x, p.pos.y],
+            radius: p.radius,
+            tmass: p.tmass,
+            s_friction: p.s_friction,
+            k_friction: p.k_friction,
+            count: sim.counts[i] as f32,
+            boundaries: 0,
+            contact_start: 0,
+            contact_count: 0,
+        }).collect();
+
+        for c in &sim.contact_rigid_contact_constraints.0 {
+            particles[c.i1].contact_count += 1;
+            particles[c.i2].contact_count += 1;
+        }
+        let mut start = 0;
+        for p in particles.iter_mut() {
+            p.contact_start = start;
+            start += p.contact_count;
+        }
+        let mut contacts = vec![0; start as usize];
+        let mut filled = vec![0; particles.len()];
+        for c in &sim.contact_rigid_contact_constraints.0 {
+            for (i, other) in [(c.i1, c.i2), (c.i2, c.i1)] {
+                contacts[(particles[i].contact_start + filled[i]) as usize] = other as u32;
+                filled[i] += 1;
+            }
+        }
+
+        for c in &sim.contact_boundary_constraints.0 {
+            particles[c.index].boundaries |= match (c.x_boundary, c.greater) {
+                (true, true) => BOUNDARY_X_MIN,
+                (true, false) => BOUNDARY_X_MAX,
+                (false, true) => BOUNDARY_Y_MIN,
+                (false, false) => BOUNDARY_Y_MAX,
+            };
+        }
+
+        Self {
+            params: GpuParams {
+                x_boundaries: [sim.x_boundaries.x, sim.x_boundaries.y],
+                y_boundaries: [sim.y_boundaries.x, sim.y_boundaries.y],
+                num_particles: particles.len() as u32,
+                _padding: [0; 3],
+            },
+            particles,
+            contacts,
+            guesses: sim.particles.0.iter().map(|p| [p.pos_guess.x, p.pos_guess.y]).collect(),
+        }
+    }
+}
+
+/// Buffers sized for up to capacity particles and contact_capacity contacts, remade bigger when a step needs more
+struct GpuBuffers {
+    capacity: usize,
+    contact_capacity: usize,
+    params: wgpu::Buffer,
+    particles: wgpu::Buffer,
+    contacts: wgpu::Buffer,
+    guesses: [wgpu::Buffer; 2], // ping-ponged between iterations
+    readback: wgpu::Buffer, // guesses after the first, second last and last iterations
+    bind_groups: [wgpu::BindGroup; 2], // reading guesses[k] and writing the other
+}
+
+impl GpuBuffers {
+    fn new(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, capacity: usize, contact_capacity: usize) -> Self {
+        let buffer = |label: &str, size: usize, usage: wgpu::BufferUsages| device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: size as wgpu::BufferAddress,
+            usage,
+            mapped_at_creation: false,
+        });
+        let storage = wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST;
+        let guess_bytes = capacity * GUESS_SIZE as usize;
+
+        let params = buffer("gpu solver params", std::mem::size_of::<GpuParams>(), wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST);
+        let particles = buffer("gpu solver particles", capacity * std::mem::size_of::<GpuParticle>(), storage);
+        let contacts = buffer("gpu solver contacts", contact_capacity * std::mem::size_of::<u32>(), storage);
+        let guesses = [
+            buffer("gpu solver guesses", guess_bytes, storage | wgpu::BufferUsages::COPY_SRC),
+            buffer("gpu solver guesses", guess_bytes, storage | wgpu::BufferUsages::COPY_SRC),
+        ];
+        let readback = buffer("gpu solver readback", guess_bytes * 3, wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST);
+
+        let bind_group = |from: usize| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("gpu solver"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: params.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: particles.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: contacts.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: guesses[from].as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 4, resource: guesses[1 - from].as_entire_binding() },
+            ],
+        });
+        let bind_groups = [bind_group(0), bind_group(1)];
+
+        Self { capacity, contact_capacity, params, particles, contacts, guesses, readback, bind_groups }
+    }
+}
+
+/// Runs the solver iterations on the GPU with a compute shader, for scenes with tens of thousands of
+/// loose particles where the CPU solver can't keep up. pre_solve and post_solve stay on the CPU, the GPU
+/// takes the contacts and walls they find and projects them, then the guesses are read back for post_solve.
+///
+/// Constraints are projected Jacobi style, every particle at once from the last iteration's guesses,
+/// so results differ from the CPU solver's one constraint at a time. That makes it a choice for the demo
+/// scenes only, dailies always solve on the CPU so everyone's runs and replays match.
+/// It always runs max_iterations, stopping early would need a readback every iteration.
+pub struct GpuSolver {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    buffers: Option<GpuBuffers>,
+}
+
+impl GpuSolver {
+    /// None if the adapter can't run compute shaders (eg. WebGL), the CPU solver is used instead
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, adapter: &wgpu::Adapter) -> Option<Self> {
+        if !adapter.get_downlevel_capabilities().flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+            || device.limits().max_storage_buffers_per_shader_stage < 4 {
+            return None;
+        }
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("gpu_solver.wgsl"));
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("gpu solver"),
+            layout: None,
+            module: &shader,
+            entry_point: None,
+            compilation_options: Default::default(),
+            cache: Default::default(),
+        });
+
+        Some(Self { device: device.clone(), queue: queue.clone(), pipeline, buffers: None })
+    }
+
+    /// Whether the GPU can solve this simulation: loose solid particles and walls only.
+    /// Rigid bodies, fluids, gases and the joint constraints are only solved on the CPU.
+    pub fn supports(sim: &Simulation) -> bool {
+        !sim.particles.0.is_empty()
+            && sim.bodies.is_empty()
+            && sim.distance_constraints.0.is_empty()
+            && sim.spring_constraints.0.is_empty()
+            && sim.volume_constraints.0.is_empty()
+            && sim.kinematic_constraints.0.is_empty()
+            && sim.global_standard_total_fluid_constraints.0.is_empty()
+            && sim.global_standard_gas_constraints.0.is_empty()
+            && sim.contact_contact_constraints.0.is_empty()
+    }
+
+    /// Use in place of Simulation::solve_adaptive, between pre_solve and post_solve.
+    /// Returns false without touching the simulation if it isn't supported, so it can be solved on the CPU.
+    pub fn solve(&mut self, sim: &mut Simulation) -> bool {
+        if !Self::supports(sim) {
+            return false;
+        }
+
+        let step = GpuStep::from_simulation(sim);
+        let num_particles = step.particles.len();
+        let iterations = sim.solver_settings.max_iterations.max(1) as usize;
+
+        let fits = self.buffers.as_ref().is_some_and(|b| b.capacity >= num_particles && b.contact_capacity >= step.contacts.len());
+        if !fits {
+            let layout = self.pipeline.get_bind_group_layout(0);
+            // room to grow, and never empty as zero sized bindings aren't allowed
+            self.buffers = Some(GpuBuffers::new(&self.device, &layout, num_particles.next_power_of_two(), step.contacts.len().max(1).next_power_of_two()));
+        }
+        let buffers = self.buffers.as_ref().unwrap();
+
+        self.queue.write_buffer(&buffers.params, 0, bytemuck::bytes_of(&step.params));
+        self.queue.write_buffer(&buffers.particles, 0, bytemuck::cast_slice(&step.particles));
+        if !step.contacts.is_empty() {
+            self.queue.write_buffer(&buffers.contacts, 0, bytemuck::cast_slice(&step.contacts));
+        }
+        self.queue.write_buffer(&buffers.guesses[0], 0, bytemuck::cast_slice(&step.guesses));
+
+        let guess_bytes = num_particles as wgpu::BufferAddress * GUESS_SIZE;
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("gpu solver") });
+        let num_workgroups = (num_particles as u32).div_ceil(WORKGROUP_SIZE);
+        for iteration in 0..iterations {
+            {
+                let mut pass = encoder.begin_compute_pass(&Default::default());
+                pass.set_pipeline(&self.pipeline);
+                pass.set_bind_group(0, &buffers.bind_groups[iteration % 2], &[]);
+                pass.dispatch_workgroups(num_workgroups, 1, 1);
+            }
+            if iteration == 0 {
+                encoder.copy_buffer_to_buffer(&buffers.guesses[1], 0, &buffers.readback, 0, guess_bytes);
+            }
+        }
+        // the last iteration wrote guesses[iterations % 2], reading the one before it
+        encoder.copy_buffer_to_buffer(&buffers.guesses[(iterations + 1) % 2], 0, &buffers.readback, guess_bytes, guess_bytes);
+        encoder.copy_buffer_to_buffer(&buffers.guesses[iterations % 2], 0, &buffers.readback, guess_bytes * 2, guess_bytes);
+        self.queue.submit([encoder.finish()]);
+
+        let slice = buffers.readback.slice(0..guess_bytes * 3);
+        let (tx, rx) = channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        let _ = self.device.poll(wgpu::PollType::wait_indefinitely());
+        if !matches!(rx.recv(), Ok(Ok(()))) {
+            // lost the device or similar, this step's guesses are untouched so the CPU can still solve it
+            return false;
+        }
+
+        {
+            let data = slice.get_mapped_range();
+            let guesses: &[[f32; 2]] = bytemuck::cast_slice(&data);
+            let (first, rest) = guesses.split_at(num_particles);
+            let (previous, last) = rest.split_at(num_particles);
+
+            let max_move = |from: &[[f32; 2]], to: &[[f32; 2]]| from.iter().zip(to.iter())
+                .map(|(a, b)| (Vec2::new(b[0], b[1]) - Vec2::new(a[0], a[1])).magnitude2())
+                .fold(0.0, f32::max)
+                .sqrt();
+            sim.solver_stats = SolverStats {
+                iterations: iterations as i32,
+                initial_residual: max_move(&step.guesses, first),
+                residual: max_move(previous, last),
+                clamped: 0,
+            };
+            for (p, guess) in sim.particles.0.iter_mut().zip(last.iter()) {
+                p.pos_guess = Vec2::new(guess[0], guess[1]);
+            }
+        }
+        buffers.readback.unmap();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use super::*;
+    use crate::{core::math::random::Random, simulation::particles::particle::Particle};
+
+    /// A pile of loose particles dropped against the floor and the left wall
+    fn pile() -> Simulation {
+        let mut sim = Simulation::new(Random::seed_from_str("gpu solver"));
+        sim.x_boundaries = Vec2::new(0.0, 10.0);
+        sim.y_boundaries = Vec2::new(0.0, 10.0);
+        for i in 0..200 {
+            let pos = Vec2::new(0.099 + (i % 20) as f32 * 0.2, 0.099 + (i / 20) as f32 * 0.2);
+            let mut particle = *Particle::default().set_pos(pos).set_radius(0.1).set_mass_2(1.0);
+            particle.s_friction = 0.5;
+            particle.k_friction = 0.3;
+            sim.add_particle(particle);
+        }
+        sim
+    }
+
+    #[test]
+    fn test_step_gathers_contacts_and_walls() {
+        let mut sim = pile();
+        sim.pre_solve(0.005);
+        let step = GpuStep::from_simulation(&sim);
+
+        // every contact is seen from both particles
+        assert_eq!(step.contacts.len(), sim.contact_rigid_contact_constraints.len() * 2);
+        for c in &sim.contact_rigid_contact_constraints.0 {
+            let p = &step.particles[c.i1];
+            let range = p.contact_start as usize..(p.contact_start + p.contact_count) as usize;
+            assert!(step.contacts[range].contains(&(c.i2 as u32)));
+        }
+
+        // the corner particle touches the floor and the left wall
+        assert_eq!(step.particles[0].boundaries, BOUNDARY_X_MIN | BOUNDARY_Y_MIN);
+        assert_eq!(step.particles[0].count, sim.counts[0] as f32);
+    }
+
+    #[test]
+    fn test_supports_loose_particles_only() {
+        let mut sim = pile();
+        assert!(GpuSolver::supports(&sim));
+
+        let particles = sim.particles.clone();
+        sim.create_fluid(&particles, 1.0);
+        assert!(!GpuSolver::supports(&sim));
+    }
+
+    /// A stack of particles dropped on the floor, and one sliding along it to stop with friction
+    fn stack_and_slider() -> Simulation {
+        let mut sim = Simulation::new(Random::seed_from_str("gpu solver"));
+        sim.x_boundaries = Vec2::new(0.0, 10.0);
+        sim.y_boundaries = Vec2::new(0.0, 10.0);
+        for i in 0..5 {
+            sim.add_particle(*Particle::default().set_pos(Vec2::new(1.0, 0.15 + i as f32 * 0.25)).set_radius(0.1).set_mass_2(1.0));
+        }
+        let mut slider = *Particle::default().set_pos(Vec2::new(3.0, 0.1)).set_radius(0.1).set_mass_2(1.0);
+        slider.vel = Vec2::new(4.0, 0.0);
+        slider.s_friction = 0.0;
+        slider.k_friction = 0.0001;
+        sim.add_particle(slider);
+        sim
+    }
+
+    /// Solves the same scene on both and checks they end up in the same place. Needs a GPU adapter, or
+    /// a software one like lavapipe, without one there's nothing to check so it passes.
+    #[test]
+    fn test_gpu_matches_cpu() {
+        let instance = wgpu::Instance::new(&Default::default());
+        let Ok(adapter) = instance.request_adapter(&Default::default()).block_on() else { return };
+        let Ok((device, queue)) = adapter.request_device(&Default::default()).block_on() else { return };
+        let Some(mut gpu) = GpuSolver::new(&device, &queue, &adapter) else { return };
+
+        let (mut cpu_sim, mut gpu_sim) = (stack_and_slider(), stack_and_slider());
+        for _ in 0..300 {
+            cpu_sim.step(0.005);
+
+            gpu_sim.pre_solve(0.005);
+            assert!(gpu.solve(&mut gpu_sim));
+            gpu_sim.post_solve(0.005);
+        }
+        assert_eq!(gpu_sim.solver_stats.iterations, gpu_sim.solver_settings.max_iterations);
+
+        // the solvers project contacts in a different order so the stack only settles about the same,
+        // the slider only touches the floor so it stops in the same place
+        for (cpu, gpu) in cpu_sim.particles.0.iter().zip(gpu_sim.particles.0.iter()) {
+            assert!((cpu.pos - gpu.pos).magnitude() < 0.02, "cpu {:?} gpu {:?}", cpu.pos, gpu.pos);
+        }
+        let (cpu_slider, gpu_slider) = (cpu_sim.particles.0.last().unwrap(), gpu_sim.particles.0.last().unwrap());
+        assert!(gpu_slider.pos.x > 3.1);
+        assert!((cpu_slider.pos - gpu_slider.pos).magnitude() < 1e-4, "cpu {:?} gpu {:?}", cpu_slider.pos, gpu_slider.pos);
+    }
+}
@@ -0,0 +1,141 @@
+// One Jacobi iteration of the particle contact and boundary constraints, see gpu_solver.rs.
+// Each thread owns one particle and sums the corrections its contacts would make to it from
+// last iteration's guesses, so no two threads write the same particle.
+
+struct Params {
+    x_boundaries: vec2<f32>,
+    y_boundaries: vec2<f32>,
+    num_particles: u32,
+}
+
+struct Particle {
+    pos: vec2<f32>,
+    radius: f32,
+    tmass: f32,
+    s_friction: f32,
+    k_friction: f32,
+    count: f32, // constraints on this particle, corrections are averaged over them
+    boundaries: u32, // BOUNDARY_* bits for the walls it's touching
+    contact_start: u32, // its range in contacts
+    contact_count: u32,
+}
+
+const BOUNDARY_X_MIN: u32 = 1u;
+const BOUNDARY_X_MAX: u32 = 2u;
+const BOUNDARY_Y_MIN: u32 = 4u;
+const BOUNDARY_Y_MAX: u32 = 8u;
+
+const EPSILON: f32 = 1.1920929e-7;
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> particles: array<Particle>;
+@group(0) @binding(2) var<storage, read> contacts: array<u32>; // the other particle in each contact
+@group(0) @binding(3) var<storage, read> guesses_in: array<vec2<f32>>;
+@group(0) @binding(4) var<storage, read_write> guesses_out: array<vec2<f32>>;
+
+// The correction a contact with particle j makes to particle p at guess, as RigidContactConstraint::project
+fn contact_correction(p: Particle, guess: vec2<f32>, j: u32) -> vec2<f32> {
+    let q = particles[j];
+    let other = guesses_in[j];
+    let x12 = guess - other;
+    let len = length(x12);
+    let d = p.radius + q.radius - len;
+    let w_sum = p.tmass + q.tmass;
+    if (d < EPSILON || w_sum == 0.0) {
+        return vec2<f32>(0.0, 0.0);
+    }
+    var n = vec2<f32>(0.0, 1.0);
+    if (len > EPSILON) {
+        n = x12 / len;
+    }
+
+    let dp = n * (d / w_sum);
+    let dp1 = dp * p.tmass / p.count;
+    let dp2 = -dp * q.tmass / q.count;
+
+    // Friction, from where both particles are after the normal correction. Unlike the CPU solver this is
+    // averaged over the particle's constraints too, as every contact removes the same slip at once here.
+    let dpf = (guess + dp1 - p.pos) - (other + dp2 - q.pos);
+    let dpt = dpf - dot(dpf, n) * n;
+    let ldpt = length(dpt);
+    if (ldpt < EPSILON) {
+        return dp1;
+    }
+    let s_fric = sqrt(p.s_friction * q.s_friction);
+    let k_fric = sqrt(p.k_friction * q.k_friction);
+    let share = p.tmass / w_sum / p.count;
+    if (ldpt < s_fric * d) {
+        return dp1 - dpt * share;
+    }
+    return dp1 - dpt * min(k_fric * d / ldpt, 1.0) * share;
+}
+
+// Push guess back inside a wall and apply its friction, as BoundaryConstraint::project.
+// greater is true for the walls particles have to stay above, the minimum x and y.
+fn boundary(p: Particle, guess: vec2<f32>, value: f32, axis: u32, greater: bool) -> vec2<f32> {
+    var g = guess;
+    var n = vec2<f32>(0.0, 0.0);
+    if (greater) {
+        if (g[axis] >= value + p.radius) {
+            return g;
+        }
+        g[axis] = value + p.radius;
+        n[axis] = 1.0;
+    } else {
+        // the ceiling checks the start of step position, same as the CPU solver
+        var check = g[axis];
+        if (axis == 1u) {
+            check = p.pos.y;
+        }
+        if (check <= value - p.radius) {
+            return g;
+        }
+        g[axis] = value - p.radius;
+        n[axis] = -1.0;
+    }
+
+    let dp = (g - p.pos) / p.count;
+    let dpt = dp - dot(dp, n) * n;
+    let ldpt = length(dpt);
+    if (ldpt < EPSILON) {
+        return g;
+    }
+    if (ldpt < sqrt(p.s_friction) * p.radius) {
+        return g - dpt;
+    }
+    return g - dpt * min(sqrt(p.k_friction) * p.radius / ldpt, 1.0);
+}
+
+@compute
+@workgroup_size(64)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let i = global_invocation_id.x;
+    if (i >= params.num_particles) {
+        return;
+    }
+
+    let p = particles[i];
+    var guess = guesses_in[i];
+
+    var correction = vec2<f32>(0.0, 0.0);
+    for (var k = 0u; k < p.contact_count; k++) {
+        correction += contact_correction(p, guess, contacts[p.contact_start + k]);
+    }
+    guess += correction;
+
+    // walls are solved after contacts, as in SolverGroup::ALL
+    if ((p.boundaries & BOUNDARY_X_MIN) != 0u) {
+        guess = boundary(p, guess, params.x_boundaries.x, 0u, true);
+    }
+    if ((p.boundaries & BOUNDARY_X_MAX) != 0u) {
+        guess = boundary(p, guess, params.x_boundaries.y, 0u, false);
+    }
+    if ((p.boundaries & BOUNDARY_Y_MIN) != 0u) {
+        guess = boundary(p, guess, params.y_boundaries.x, 1u, true);
+    }
+    if ((p.boundaries & BOUNDARY_Y_MAX) != 0u) {
+        guess = boundary(p, guess, params.y_boundaries.y, 1u, false);
+    }
+
+    guesses_out[i] = guess;
+}
@@ -0,0 +1 @@
+pub mod gpu_solver;
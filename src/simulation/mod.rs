@@ -1,2 +1,4 @@
 pub mod particles;
 pub mod constraints;
+#[cfg(feature = "client")]
+pub mod gpu;
@@ -0,0 +1,49 @@
+use crate::core::math::vec2::Vec2;
+
+/// Only the part of the level around the car needs simulating at full rate. Particles near the centre step every step,
+/// further out they only step every few steps (so run in slow motion), and beyond that they are frozen until the car comes back.
+/// Particles that aren't stepping are treated as static by the solver, so the awake particles still rest against them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActivityRegion {
+    pub center: Vec2,
+    pub active_radius: f32, // full rate inside this, in metres
+    pub throttled_radius: f32, // stepped every throttled_interval steps inside this, frozen outside
+    pub throttled_interval: u32,
+}
+
+impl ActivityRegion {
+    pub fn new(center: Vec2) -> Self {
+        Self { center, ..Self::default() }
+    }
+
+    pub fn is_awake(&self, pos: Vec2, step: u64) -> bool {
+        let distance2 = (pos - self.center).magnitude2();
+        if distance2 <= self.active_radius * self.active_radius {
+            return true;
+        }
+        distance2 <= self.throttled_radius * self.throttled_radius && step.is_multiple_of(self.throttled_interval.max(1) as u64)
+    }
+}
+
+impl Default for ActivityRegion {
+    // the camera sees about 11m either side of the car, so everything that's visible runs at full rate
+    fn default() -> Self {
+        Self { center: Vec2::new(0.0, 0.0), active_radius: 30.0, throttled_radius: 60.0, throttled_interval: 4 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_awake() {
+        let region = ActivityRegion::new(Vec2::new(10.0, 0.0));
+        assert!(region.is_awake(Vec2::new(-15.0, 0.0), 1));
+        // throttled, only every 4th step
+        assert!(region.is_awake(Vec2::new(50.0, 0.0), 8));
+        assert!(!region.is_awake(Vec2::new(50.0, 0.0), 9));
+        // frozen
+        assert!(!region.is_awake(Vec2::new(80.0, 0.0), 8));
+    }
+}
@@ -0,0 +1,128 @@
+use crate::{
+    core::math::{aabb2d::Aabb2d, vec2::Vec2},
+    simulation::particles::{particle::Phase, particle_vec::ParticleVec, spatial_hash::SpatialHash},
+};
+
+/// Couples moving solids to the gas around them. Gas near a solid is dragged towards the solid's velocity
+/// and the solid loses the momentum the gas gained, so driving through smoke stirs it up and costs a little speed.
+/// An ambient wind does the same for the air we don't simulate, for the headwind and tailwind modifiers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aerodynamics {
+    pub influence_radius: f32, // how far beyond touching a solid still moves the gas, metres
+    pub gas_coupling: f32, // fraction of the relative velocity the gas picks up per second, right next to the solid
+    pub drag_coefficient: f32, // fraction of the relative velocity to the ambient wind a solid loses per second
+    pub ambient_wind: Option<Vec2>, // None for still air with no drag outside of gas
+}
+
+impl Default for Aerodynamics {
+    fn default() -> Self {
+        Self {
+            influence_radius: 0.3,
+            gas_coupling: 4.0,
+            drag_coefficient: 0.05,
+            ambient_wind: None,
+        }
+    }
+}
+
+impl Aerodynamics {
+    /// Exchange momentum between dynamic solids and nearby gas, then drag everything towards the ambient wind.
+    /// Particles are visited in index order so replays stay deterministic.
+    pub fn apply(&self, particles: &mut ParticleVec, time_delta: f32) {
+        let mut spatial_hash = SpatialHash::<usize, 1>::new();
+        let mut has_gas = false;
+        for (i, p) in particles.iter().enumerate() {
+            if p.phase == Phase::Gas && p.imass > 0.0 {
+                spatial_hash.insert_point(p.pos, i);
+                has_gas = true;
+            }
+        }
+        if !has_gas && self.ambient_wind.is_none() {
+            return;
+        }
+
+        for i in 0..particles.len() {
+            let mut solid = particles[i];
+            if solid.phase != Phase::Solid || solid.imass == 0.0 {
+                continue;
+            }
+
+            if has_gas {
+                let reach = solid.radius + self.influence_radius;
+                let aabb = Aabb2d::new(solid.pos, Vec2::new(reach, reach));
+                for j in spatial_hash.aabb_iter(aabb) {
+                    let gas = &mut particles[j];
+                    let reach = reach + gas.radius;
+                    let distance = (gas.pos - solid.pos).magnitude();
+                    if distance >= reach {
+                        continue;
+                    }
+                    let weight = 1.0 - distance / reach;
+                    let gas_delta_vel = (solid.vel - gas.vel) * (self.gas_coupling * weight * time_delta).min(1.0);
+                    gas.vel += gas_delta_vel;
+                    // equal and opposite, m_gas / m_solid = imass_solid / imass_gas
+                    solid.vel -= gas_delta_vel * (solid.imass / gas.imass);
+                }
+            }
+
+            if let Some(wind) = self.ambient_wind {
+                solid.vel += (wind - solid.vel) * (self.drag_coefficient * time_delta).min(1.0);
+            }
+            particles[i].vel = solid.vel;
+        }
+
+        // gas has no mass to speak of, so it goes with the wind
+        if let Some(wind) = self.ambient_wind {
+            for p in particles.iter_mut().filter(|p| p.phase == Phase::Gas && p.imass > 0.0) {
+                p.vel += (wind - p.vel) * (self.gas_coupling * time_delta).min(1.0);
+            }
+        }
+    }
+
+    pub fn mirror_x(&mut self) {
+        if let Some(wind) = &mut self.ambient_wind {
+            wind.x = -wind.x;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::particles::particle::Particle;
+
+    #[test]
+    fn test_solid_drags_gas_along() {
+        let mut particles = ParticleVec::new();
+        particles.push(*Particle::default().set_pos(Vec2::new(0.0, 0.0)).set_radius(0.25).set_mass_2(1.0).set_vel(Vec2::new(10.0, 0.0)));
+        let mut gas = *Particle::default().set_pos(Vec2::new(0.6, 0.0)).set_radius(0.25).set_mass_2(0.1).set_phase(Phase::Gas);
+        particles.push(gas);
+        // too far away to feel it
+        gas.pos = Vec2::new(5.0, 0.0);
+        particles.push(gas);
+
+        let momentum = |particles: &ParticleVec| particles.iter().fold(Vec2::new(0.0, 0.0), |sum, p| sum + p.vel / p.imass);
+        let momentum_before = momentum(&particles);
+        Aerodynamics::default().apply(&mut particles, 0.005);
+
+        assert!(particles[1].vel.x > 0.0);
+        assert!(particles[0].vel.x < 10.0);
+        assert_eq!(particles[2].vel, Vec2::new(0.0, 0.0));
+        assert!((momentum(&particles) - momentum_before).magnitude() < 1e-4);
+    }
+
+    #[test]
+    fn test_headwind_slows_solids() {
+        let mut particles = ParticleVec::new();
+        particles.push(*Particle::default().set_radius(0.25).set_mass_2(1.0).set_vel(Vec2::new(10.0, 0.0)));
+        let mut calm = particles.clone();
+
+        let mut aerodynamics = Aerodynamics::default();
+        aerodynamics.apply(&mut calm, 0.005);
+        assert_eq!(calm[0].vel.x, 10.0);
+
+        aerodynamics.ambient_wind = Some(Vec2::new(-5.0, 0.0));
+        aerodynamics.apply(&mut particles, 0.005);
+        assert!(particles[0].vel.x < 10.0);
+    }
+}
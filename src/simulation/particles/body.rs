@@ -1,8 +1,9 @@
 use std::collections::HashMap;
 use std::f32::consts::PI;
 
-use crate::{core::math::vec2::Vec2, simulation::particles::{particle_vec::ParticleVec, sdf_data::SdfData}};
+use crate::{core::math::{deterministic::MathMode, vec2::Vec2}, simulation::particles::{particle_vec::ParticleVec, sdf_data::SdfData}};
 
+#[derive(Clone)]
 pub struct Body {
     pub particle_indicies: Vec<usize>,
     pub center: Vec2,
@@ -28,7 +29,7 @@ impl Body {
         }
     }
 
-    pub fn update_com(&mut self, estimates: &ParticleVec, use_estimates: bool) {
+    pub fn update_com(&mut self, estimates: &ParticleVec, use_estimates: bool, math: MathMode) {
         // Recompute center of mass
         let mut total = Vec2::new(0.0, 0.0);
         for i in 0..self.particle_indicies.len() {
@@ -60,7 +61,7 @@ impl Body {
 
             let cos = r.x * q.x + r.y * q.y;
             let sin = r.y * q.x - r.x * q.y;
-            let mut next = sin.atan2(cos);
+            let mut next = math.atan2(sin, cos);
 
             // Ensure all guesses are close to each other
             if i > 0 {
@@ -110,4 +111,11 @@ impl Body {
             counts[self.particle_indicies[i]] += count;
         }
     }
+
+    /// Follow the particles being renumbered, see Simulation::remove_particles
+    pub fn remap_particles(&mut self, remap: &[Option<usize>]) {
+        self.particle_indicies = self.particle_indicies.iter().filter_map(|&i| remap[i]).collect();
+        self.rs = self.rs.drain().filter_map(|(i, r)| remap[i].map(|i| (i, r))).collect();
+        self.sdf = self.sdf.drain().filter_map(|(i, sdf)| remap[i].map(|i| (i, sdf))).collect();
+    }
 }
\ No newline at end of file
@@ -0,0 +1,160 @@
+use std::f32::consts::PI;
+
+use crate::{
+    core::math::{aabb2d::Aabb2d, vec2::Vec2},
+    simulation::{constraints::volume_constraint::VolumeConstraintVec, particles::{particle::Phase, particle_vec::ParticleVec, spatial_hash::SpatialHash}},
+};
+
+/// Floats dynamic solids in fluid. Each solid pushes aside its own area, plus its share of any closed volume it's part of (eg. a tyre),
+/// and is pushed up by the weight of the fluid that would fill it, so light things float and heavy things sink.
+/// Wet solids are also dragged towards the fluid's velocity and get some lift from moving across it, so a fast car can skim.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Buoyancy {
+    pub reach: f32, // how far beyond a solid's edge we look for fluid, metres
+    pub drag: f32, // fraction of the velocity relative to the fluid a fully wet solid loses per second
+    pub planing_lift: f32, // upward acceleration per m/s of speed across the fluid, for skimming
+}
+
+impl Default for Buoyancy {
+    fn default() -> Self {
+        Self {
+            reach: 0.25,
+            drag: 1.5,
+            planing_lift: 0.4,
+        }
+    }
+}
+
+impl Buoyancy {
+    pub fn apply(&self, particles: &mut ParticleVec, volume_constraints: &VolumeConstraintVec, gravity: Vec2, time_delta: f32) {
+        let mut fluid_hash = SpatialHash::<usize, 1>::new();
+        let mut has_fluid = false;
+        for (i, p) in particles.iter().enumerate() {
+            if p.phase == Phase::Fluid && p.imass > 0.0 {
+                fluid_hash.insert_point(p.pos, i);
+                has_fluid = true;
+            }
+        }
+        if !has_fluid {
+            return;
+        }
+
+        // the air inside a closed shape displaces fluid too, split evenly between the particles around it
+        let mut enclosed_area = vec![0.0; particles.len()];
+        let mut enclosed_centre = vec![None; particles.len()];
+        for c in volume_constraints.0.iter().filter(|c| c.enabled && !c.particle_indices.is_empty()) {
+            let share = c.rest_volume.abs() / c.particle_indices.len() as f32;
+            let centre = c.particle_indices.iter().fold(Vec2::new(0.0, 0.0), |sum, &i| sum + particles[i].pos) / c.particle_indices.len() as f32;
+            for &i in &c.particle_indices {
+                enclosed_area[i] += share;
+                enclosed_centre[i] = Some(centre);
+            }
+        }
+
+        for i in 0..particles.len() {
+            let solid = particles[i];
+            if solid.phase != Phase::Solid || solid.imass == 0.0 {
+                continue;
+            }
+
+            let reach = solid.radius + self.reach;
+            let mut covered_area = 0.0;
+            let mut fluid_mass = 0.0;
+            let mut fluid_vel = Vec2::new(0.0, 0.0);
+            let mut total_weight = 0.0;
+            for j in fluid_hash.aabb_iter(Aabb2d::new(solid.pos, Vec2::new(reach, reach))) {
+                let fluid = &particles[j];
+                let distance = (fluid.pos - solid.pos).magnitude();
+                if distance >= reach {
+                    continue;
+                }
+                // fluid held inside the shape (eg. a water balloon) pushes outwards, not up
+                if let Some(centre) = enclosed_centre[i] {
+                    if (fluid.pos - centre).magnitude() < (solid.pos - centre).magnitude() {
+                        continue;
+                    }
+                }
+                let weight = 1.0 - distance / reach;
+                // fluid particles pack roughly on a grid, so each one fills a square its diameter wide
+                let diameter = fluid.radius * 2.0;
+                covered_area += weight * diameter * diameter;
+                fluid_mass += weight / fluid.imass;
+                fluid_vel += fluid.vel * weight;
+                total_weight += weight;
+            }
+            if total_weight == 0.0 {
+                continue;
+            }
+
+            // the linear weights integrate to pi * r^2 / 3 over a disc. Being in fluid over half of it counts as fully wet,
+            // the other half is often the rest of the body or the air inside it
+            let wetness = (covered_area / (PI * reach * reach / 6.0)).min(1.0);
+            let fluid_density = fluid_mass / covered_area;
+            let displaced_mass = wetness * fluid_density * (PI * solid.radius * solid.radius + enclosed_area[i]);
+
+            let mut vel = solid.vel - gravity * (displaced_mass * solid.imass * time_delta);
+
+            let relative_vel = vel - fluid_vel / total_weight;
+            vel -= relative_vel * (self.drag * wetness * time_delta).min(1.0);
+            vel.y += self.planing_lift * relative_vel.x.abs() * wetness * time_delta;
+
+            particles[i].vel = vel;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::particles::particle::Particle;
+
+    fn pool() -> ParticleVec {
+        let mut particles = ParticleVec::new();
+        for x in -5..=5 {
+            for y in -5..=5 {
+                particles.push(*Particle::default().set_pos(Vec2::new(x as f32 * 0.2, y as f32 * 0.2)).set_radius(0.1).set_mass_2(1.0).set_phase(Phase::Fluid));
+            }
+        }
+        particles
+    }
+
+    #[test]
+    fn test_light_solids_float_and_heavy_solids_sink() {
+        let gravity = Vec2::new(0.0, -9.8);
+        let time_delta = 0.005;
+        // the pool is 25 kg/m^2, so a 0.1m particle displaces about 0.8kg
+        let net_vel_y = |mass: f32| {
+            let mut particles = pool();
+            particles.push(*Particle::default().set_pos(Vec2::new(0.1, 0.1)).set_radius(0.1).set_mass_2(mass));
+            let solid = particles.len() - 1;
+            Buoyancy::default().apply(&mut particles, &VolumeConstraintVec::new(), gravity, time_delta);
+            particles[solid].vel.y + gravity.y * time_delta
+        };
+
+        assert!(net_vel_y(0.4) > 0.0);
+        assert!(net_vel_y(4.0) < 0.0);
+    }
+
+    #[test]
+    fn test_dry_solids_are_untouched() {
+        let mut particles = pool();
+        particles.push(*Particle::default().set_pos(Vec2::new(5.0, 5.0)).set_radius(0.1).set_mass_2(1.0).set_vel(Vec2::new(10.0, 0.0)));
+        let solid = particles.len() - 1;
+        Buoyancy::default().apply(&mut particles, &VolumeConstraintVec::new(), Vec2::new(0.0, -9.8), 0.005);
+        assert_eq!(particles[solid].vel, Vec2::new(10.0, 0.0));
+    }
+
+    #[test]
+    fn test_speed_across_the_surface_gives_lift() {
+        let gravity = Vec2::new(0.0, -9.8);
+        let mut particles = pool();
+        // sat on the surface of the pool
+        particles.push(*Particle::default().set_pos(Vec2::new(0.0, 1.1)).set_radius(0.1).set_mass_2(4.0));
+        particles.push(*Particle::default().set_pos(Vec2::new(0.0, 1.1)).set_radius(0.1).set_mass_2(4.0).set_vel(Vec2::new(20.0, 0.0)));
+        Buoyancy::default().apply(&mut particles, &VolumeConstraintVec::new(), gravity, 0.005);
+
+        let n = particles.len();
+        assert!(particles[n - 1].vel.y > particles[n - 2].vel.y);
+        assert!(particles[n - 1].vel.x < 20.0);
+    }
+}
@@ -0,0 +1,55 @@
+use crate::{core::math::aabb2d::Aabb2d, simulation::particles::particle_vec::ParticleVec};
+
+/// A region placed by a level block that dynamic particles slow down in, eg. a mud pit.
+/// Unlike a flow zone there's nothing to be submerged in, everything inside loses speed.
+#[derive(Debug, Clone)]
+pub struct DragRegion {
+    pub aabb: Aabb2d,
+    pub drag: f32, // fraction of its velocity a particle inside loses per second
+}
+
+impl DragRegion {
+    pub fn new(aabb: Aabb2d, drag: f32) -> Self {
+        Self { aabb, drag }
+    }
+
+    pub fn apply(&self, particles: &mut ParticleVec, time_delta: f32) {
+        let keep = (1.0 - self.drag * time_delta).clamp(0.0, 1.0);
+        for p in particles.iter_mut() {
+            if p.imass > 0.0 && self.aabb.contains_point(p.pos) {
+                p.vel *= keep;
+            }
+        }
+    }
+
+    pub fn mirror_x(&mut self) {
+        self.aabb = self.aabb.mirror_x();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::math::vec2::Vec2, simulation::particles::particle::Particle};
+
+    #[test]
+    fn test_drag_slows_dynamic_particles_inside() {
+        let mut particles = ParticleVec::new();
+        particles.push(*Particle::default().set_pos(Vec2::new(0.5, 0.5)).set_mass_2(1.0).set_vel(Vec2::new(4.0, 0.0)));
+        // outside the region
+        particles.push(*Particle::default().set_pos(Vec2::new(5.0, 0.5)).set_mass_2(1.0).set_vel(Vec2::new(4.0, 0.0)));
+        // static
+        particles.push(*Particle::default().set_pos(Vec2::new(0.5, 0.5)).set_mass_2(0.0).set_vel(Vec2::new(4.0, 0.0)));
+
+        let region = DragRegion::new(Aabb2d { min: Vec2::new(0.0, 0.0), max: Vec2::new(1.0, 1.0) }, 2.0);
+        region.apply(&mut particles, 0.1);
+
+        assert_eq!(particles[0].vel, Vec2::new(3.2, 0.0));
+        assert_eq!(particles[1].vel, Vec2::new(4.0, 0.0));
+        assert_eq!(particles[2].vel, Vec2::new(4.0, 0.0));
+
+        // a huge step stops it rather than turning it round
+        region.apply(&mut particles, 10.0);
+        assert_eq!(particles[0].vel, Vec2::new(0.0, 0.0));
+    }
+}
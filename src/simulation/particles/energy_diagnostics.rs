@@ -0,0 +1,236 @@
+use std::collections::VecDeque;
+
+use crate::{core::math::vec2::Vec2, simulation::particles::{particle::Phase, simulation::Simulation}};
+
+/// Total energy and momentum of the dynamic particles at one step
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct EnergySample {
+    pub kinetic: f32,
+    pub potential: f32, // gravitational, relative to y = 0
+    pub momentum: Vec2,
+}
+
+impl EnergySample {
+    /// Static particles (zero inverse mass) don't count. Force fields aren't conservative so they add no potential term.
+    pub fn measure(sim: &Simulation) -> Self {
+        let mut sample = Self::default();
+        for i in 0..sim.particles.len() {
+            let p = &sim.particles[i];
+            if p.imass <= 0.0 {
+                continue;
+            }
+
+            let mass = 1.0 / p.imass;
+            // gases get scaled, upside down gravity in pre_solve
+            let gravity = if p.phase == Phase::Gas { sim.gravity * -0.2 } else { sim.gravity };
+            sample.kinetic += 0.5 * mass * p.vel.magnitude2();
+            sample.potential -= mass * gravity.dot(p.pos);
+            sample.momentum += p.vel * mass;
+        }
+        sample
+    }
+
+    pub fn total(&self) -> f32 {
+        self.kinetic + self.potential
+    }
+}
+
+/// Tracks how far the energy and momentum have drifted from the first sample,
+/// so solver changes that add or remove energy show up straight away
+#[derive(Debug, Clone)]
+pub struct EnergyDiagnostics {
+    initial: Option<EnergySample>,
+    samples: VecDeque<EnergySample>, // most recent last
+    max_samples: usize,
+    max_energy_gain: f32, // largest total energy above the initial seen so far, including samples no longer kept
+}
+
+impl EnergyDiagnostics {
+    pub fn new(max_samples: usize) -> Self {
+        Self { initial: None, samples: VecDeque::new(), max_samples: max_samples.max(1), max_energy_gain: 0.0 }
+    }
+
+    pub fn record(&mut self, sim: &Simulation) -> EnergySample {
+        let sample = EnergySample::measure(sim);
+        let initial = *self.initial.get_or_insert(sample);
+        self.max_energy_gain = self.max_energy_gain.max(sample.total() - initial.total());
+
+        self.samples.push_back(sample);
+        if self.samples.len() > self.max_samples {
+            self.samples.pop_front();
+        }
+        sample
+    }
+
+    /// Start measuring drift again from the next sample, eg. after the simulation is replaced
+    pub fn reset(&mut self) {
+        *self = Self::new(self.max_samples);
+    }
+
+    pub fn initial(&self) -> Option<&EnergySample> {
+        self.initial.as_ref()
+    }
+
+    pub fn latest(&self) -> Option<&EnergySample> {
+        self.samples.back()
+    }
+
+    pub fn energy_drift(&self) -> f32 {
+        match (self.initial, self.latest()) {
+            (Some(initial), Some(latest)) => latest.total() - initial.total(),
+            _ => 0.0,
+        }
+    }
+
+    /// Drift as a fraction of the initial energy. Potential energy depends on where y = 0 is,
+    /// so the scale uses its size rather than its sign.
+    pub fn relative_energy_drift(&self) -> f32 {
+        let Some(initial) = self.initial else { return 0.0 };
+        self.energy_drift() / (initial.kinetic + initial.potential.abs()).max(1e-6)
+    }
+
+    pub fn max_energy_gain(&self) -> f32 {
+        self.max_energy_gain
+    }
+
+    pub fn momentum_drift(&self) -> Vec2 {
+        match (self.initial, self.latest()) {
+            (Some(initial), Some(latest)) => latest.momentum - initial.momentum,
+            _ => Vec2::new(0.0, 0.0),
+        }
+    }
+
+    /// Energy drift of each kept sample, oldest first, for plotting
+    pub fn energy_drift_history(&self) -> Vec<f32> {
+        let Some(initial) = self.initial else { return vec![] };
+        self.samples.iter().map(|sample| sample.total() - initial.total()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::math::random::Random,
+        simulation::{constraints::distance_constraint::DistanceConstraint, particles::{particle::Particle, simulation_demos::SimulationDemos}},
+    };
+
+    const TIME_DELTA: f32 = 0.005;
+
+    /// Run a demo for a few seconds recording a sample every step
+    fn run_demo(init: fn(&mut Simulation), num_steps: usize) -> EnergyDiagnostics {
+        let mut sim = Simulation::new(Random::seed_from_str("energy"));
+        init(&mut sim);
+        let mut diagnostics = EnergyDiagnostics::new(num_steps);
+        diagnostics.record(&sim);
+        for _ in 0..num_steps {
+            sim.step(TIME_DELTA);
+            diagnostics.record(&sim);
+        }
+        diagnostics
+    }
+
+    #[test]
+    fn test_measure() {
+        let mut sim = Simulation::new(Random::seed_from_str("energy"));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(0.0, 2.0)).set_vel(Vec2::new(3.0, 0.0)).set_mass_2(2.0));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(1.0, 5.0)).set_mass_2(0.0)); // static, ignored
+
+        let sample = EnergySample::measure(&sim);
+        assert_eq!(sample.kinetic, 9.0);
+        assert_eq!(sample.potential, 2.0 * 9.8 * 2.0);
+        assert_eq!(sample.momentum, Vec2::new(6.0, 0.0));
+    }
+
+    #[test]
+    fn test_free_fall_conserves_energy() {
+        let mut sim = Simulation::new(Random::seed_from_str("energy"));
+        sim.y_boundaries = Vec2::new(-1000.0, 1000.0);
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(0.0, 10.0)).set_mass_2(1.0));
+
+        let mut diagnostics = EnergyDiagnostics::new(200);
+        diagnostics.record(&sim);
+        for _ in 0..200 {
+            sim.step(TIME_DELTA);
+            diagnostics.record(&sim);
+        }
+        // symplectic euler drifts by about g²·dt·t·m/2 over one second
+        assert!(diagnostics.relative_energy_drift().abs() < 0.01, "drift {}", diagnostics.relative_energy_drift());
+    }
+
+    #[test]
+    fn test_collision_conserves_momentum() {
+        let mut sim = Simulation::new(Random::seed_from_str("energy"));
+        sim.gravity = Vec2::new(0.0, 0.0);
+        sim.y_boundaries = Vec2::new(-1000.0, 1000.0);
+        sim.add_particle(*Particle::default().set_radius(0.25).set_pos(Vec2::new(-1.0, 0.0)).set_vel(Vec2::new(2.0, 0.0)).set_mass_2(1.0));
+        sim.add_particle(*Particle::default().set_radius(0.25).set_pos(Vec2::new(1.0, 0.0)).set_vel(Vec2::new(-1.0, 0.0)).set_mass_2(1.0));
+
+        let mut diagnostics = EnergyDiagnostics::new(400);
+        diagnostics.record(&sim);
+        for _ in 0..400 {
+            sim.step(TIME_DELTA);
+            diagnostics.record(&sim);
+        }
+        assert!(diagnostics.momentum_drift().magnitude() < 1e-3, "momentum drift {:?}", diagnostics.momentum_drift());
+        assert!(diagnostics.max_energy_gain() < 1e-3, "energy gain {}", diagnostics.max_energy_gain());
+    }
+
+    #[test]
+    fn test_constrained_pair_conserves_momentum() {
+        let mut sim = Simulation::new(Random::seed_from_str("energy"));
+        sim.gravity = Vec2::new(0.0, 0.0);
+        sim.y_boundaries = Vec2::new(-1000.0, 1000.0);
+        sim.add_particle(*Particle::default().set_radius(0.25).set_pos(Vec2::new(0.0, 0.0)).set_vel(Vec2::new(0.0, 1.0)).set_mass_2(1.0));
+        sim.add_particle(*Particle::default().set_radius(0.25).set_pos(Vec2::new(2.0, 0.0)).set_mass_2(2.0));
+        sim.add_distance_constraint(DistanceConstraint::from_particles(0, 1, &sim.particles));
+
+        let mut diagnostics = EnergyDiagnostics::new(400);
+        diagnostics.record(&sim);
+        for _ in 0..400 {
+            sim.step(TIME_DELTA);
+            diagnostics.record(&sim);
+        }
+        assert!(diagnostics.momentum_drift().magnitude() < 1e-2, "momentum drift {:?}", diagnostics.momentum_drift());
+        assert!(diagnostics.max_energy_gain() < 1e-3, "energy gain {}", diagnostics.max_energy_gain());
+    }
+
+    // The demos lose energy to friction, inelastic contacts and constraint projection,
+    // but none of them should end up with noticeably more than they started with.
+    // Demos that start with overlapping particles (rope, granular, wrecking ball) push apart on the first steps so aren't checked.
+    #[test]
+    fn test_demos_do_not_gain_energy() {
+        let demos: [(&str, fn(&mut Simulation), f32); 6] = [
+            ("pendulum", SimulationDemos::init_pendulum, 0.01),
+            ("newtons_cradle", SimulationDemos::init_newtons_cradle, 0.01),
+            ("boxes", SimulationDemos::init_boxes, 0.01),
+            ("friction", SimulationDemos::init_friction, 0.01),
+            ("wall", SimulationDemos::init_wall, 0.03), // the stacked bricks bounce a little as they settle
+            ("sdf", SimulationDemos::init_sdf, 0.01),
+        ];
+        for (name, init, max_gain) in demos {
+            let diagnostics = run_demo(init, 400);
+            let initial = diagnostics.initial().unwrap();
+            let scale = initial.kinetic + initial.potential.abs();
+            assert!(diagnostics.max_energy_gain() <= scale * max_gain, "{} gained energy: {} of {}", name, diagnostics.max_energy_gain(), scale);
+        }
+    }
+
+    // nothing touches the pendulum so the only loss is from the distance constraints
+    #[test]
+    fn test_pendulum_keeps_its_energy() {
+        let diagnostics = run_demo(SimulationDemos::init_pendulum, 400);
+        assert!(diagnostics.relative_energy_drift() > -0.05, "pendulum lost energy: {}", diagnostics.relative_energy_drift());
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let diagnostics = run_demo(SimulationDemos::init_newtons_cradle, 50);
+        assert_eq!(diagnostics.energy_drift_history().len(), 50);
+
+        let mut diagnostics = diagnostics;
+        diagnostics.reset();
+        assert!(diagnostics.initial().is_none());
+        assert_eq!(diagnostics.energy_drift(), 0.0);
+    }
+}
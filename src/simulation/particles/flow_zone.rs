@@ -0,0 +1,81 @@
+use crate::{
+    core::math::{aabb2d::Aabb2d, vec2::Vec2},
+    simulation::particles::{particle::Phase, particle_vec::ParticleVec, spatial_hash::SpatialHash},
+};
+
+// a solid counts as submerged with fluid this much further away than touching, so it still feels the current while floating
+const SUBMERGED_MARGIN: f32 = 1.5;
+
+/// A volume of moving fluid placed by a level block, eg. a river.
+/// Fluid inside is pulled towards the current, and dynamic solids inside are dragged along while they're submerged.
+#[derive(Debug, Clone)]
+pub struct FlowZone {
+    pub aabb: Aabb2d,
+    pub velocity: Vec2, // the current, m/s
+    pub strength: f32, // fraction of the difference to the current fluid picks up per second
+    pub drag: f32, // same for submerged solids
+}
+
+impl FlowZone {
+    pub fn new(aabb: Aabb2d, velocity: Vec2) -> Self {
+        Self { aabb, velocity, strength: 2.0, drag: 1.0 }
+    }
+
+    pub fn apply(&self, particles: &mut ParticleVec, time_delta: f32) {
+        let mut fluid_hash = SpatialHash::<usize, 1>::new();
+        let fluid_pull = (self.strength * time_delta).min(1.0);
+        for (i, p) in particles.iter_mut().enumerate() {
+            if p.phase == Phase::Fluid && p.imass > 0.0 && self.aabb.contains_point(p.pos) {
+                p.vel += (self.velocity - p.vel) * fluid_pull;
+                fluid_hash.insert_point(p.pos, i);
+            }
+        }
+
+        let solid_pull = (self.drag * time_delta).min(1.0);
+        for i in 0..particles.len() {
+            let p = particles[i];
+            if p.phase != Phase::Solid || p.imass == 0.0 || !self.aabb.contains_point(p.pos) {
+                continue;
+            }
+            let reach = p.radius * 2.0 * SUBMERGED_MARGIN;
+            let submerged = fluid_hash.aabb_iter(Aabb2d::new(p.pos, Vec2::new(reach, reach)))
+                .any(|j| (particles[j].pos - p.pos).magnitude() < (p.radius + particles[j].radius) * SUBMERGED_MARGIN);
+            if submerged {
+                particles[i].vel += (self.velocity - p.vel) * solid_pull;
+            }
+        }
+    }
+
+    pub fn mirror_x(&mut self) {
+        self.aabb = self.aabb.mirror_x();
+        self.velocity.x = -self.velocity.x;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::particles::particle::Particle;
+
+    #[test]
+    fn test_current_pushes_fluid_and_submerged_solids() {
+        let mut particles = ParticleVec::new();
+        particles.push(*Particle::default().set_pos(Vec2::new(0.0, 0.0)).set_radius(0.1).set_mass_2(1.0).set_phase(Phase::Fluid));
+        // touching the water
+        particles.push(*Particle::default().set_pos(Vec2::new(0.2, 0.0)).set_radius(0.1).set_mass_2(1.0));
+        // in the zone but dry
+        particles.push(*Particle::default().set_pos(Vec2::new(0.0, 0.9)).set_radius(0.1).set_mass_2(1.0));
+        // outside the zone
+        particles.push(*Particle::default().set_pos(Vec2::new(5.0, 0.0)).set_radius(0.1).set_mass_2(1.0).set_phase(Phase::Fluid));
+
+        let zone = FlowZone::new(Aabb2d::new(Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0)), Vec2::new(-2.0, 0.0));
+        zone.apply(&mut particles, 0.005);
+
+        assert!(particles[0].vel.x < 0.0);
+        assert!(particles[1].vel.x < 0.0);
+        assert_eq!(particles[2].vel, Vec2::new(0.0, 0.0));
+        assert_eq!(particles[3].vel, Vec2::new(0.0, 0.0));
+        // fluid follows the current more readily than the car
+        assert!(particles[0].vel.x < particles[1].vel.x);
+    }
+}
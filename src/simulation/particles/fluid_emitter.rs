@@ -3,8 +3,9 @@ use rand_pcg::Pcg64;
 
 use crate::{core::math::vec2::Vec2, simulation::{constraints::total_fluid_constraint::TotalFluidConstraintVec, particles::{particle::{Particle, Phase}, particle_vec::ParticleVec}}};
 
+#[derive(Clone)]
 pub struct FluidEmitter {
-    posn: Vec2,
+    pub posn: Vec2,
     particles_per_sec: f32,
     timer: f32,
     total_timer: f32,
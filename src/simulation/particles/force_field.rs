@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use crate::core::math::{aabb2d::Aabb2d, vec2::Vec2};
+
+/// An external force applied to every dynamic particle each step, eg. wind or a vortex registered by a plugin.
+/// The force function must be a pure function of position so replays stay deterministic.
+#[derive(Clone)]
+pub struct ForceField {
+    pub name: String,
+    force: Arc<dyn Fn(Vec2) -> Vec2 + Send + Sync>,
+    mirrored: bool,
+}
+
+impl ForceField {
+    pub fn new(name: String, force: Arc<dyn Fn(Vec2) -> Vec2 + Send + Sync>) -> Self {
+        Self { name, force, mirrored: false }
+    }
+
+    /// The same acceleration everywhere inside region and none outside it, eg. a gust over part of a level.
+    /// The name is made from the region and vector, so levels with different fields hash differently.
+    pub fn region(region: Aabb2d, vector: Vec2) -> Self {
+        let name = format!("region ({}, {})-({}, {}) {}, {}", region.min.x, region.min.y, region.max.x, region.max.y, vector.x, vector.y);
+        Self::new(name, Arc::new(move |pos: Vec2| if region.contains_point(pos) { vector } else { Vec2::new(0.0, 0.0) }))
+    }
+
+    /// Acceleration at the given position
+    pub fn force_at(&self, pos: Vec2) -> Vec2 {
+        if self.mirrored {
+            let force = (self.force)(Vec2::new(-pos.x, pos.y));
+            Vec2::new(-force.x, force.y)
+        } else {
+            (self.force)(pos)
+        }
+    }
+
+    pub fn mirror_x(&mut self) {
+        self.mirrored = !self.mirrored;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mirrored_force_field() {
+        // blows right, harder the further right you are
+        let mut field = ForceField::new(String::from("wind"), Arc::new(|pos: Vec2| Vec2::new(pos.x, 1.0)));
+        assert_eq!(field.force_at(Vec2::new(2.0, 0.0)), Vec2::new(2.0, 1.0));
+
+        field.mirror_x();
+        assert_eq!(field.force_at(Vec2::new(-2.0, 0.0)), Vec2::new(-2.0, 1.0));
+    }
+
+    #[test]
+    fn test_region_force_field() {
+        let mut field = ForceField::region(Aabb2d { min: Vec2::new(1.0, 0.0), max: Vec2::new(3.0, 2.0) }, Vec2::new(-4.0, 0.0));
+        assert_eq!(field.force_at(Vec2::new(2.0, 1.0)), Vec2::new(-4.0, 0.0));
+        assert_eq!(field.force_at(Vec2::new(0.0, 1.0)), Vec2::new(0.0, 0.0));
+
+        // mirrored, it's over on the left blowing right
+        field.mirror_x();
+        assert_eq!(field.force_at(Vec2::new(-2.0, 1.0)), Vec2::new(4.0, 0.0));
+        assert_eq!(field.force_at(Vec2::new(2.0, 1.0)), Vec2::new(0.0, 0.0));
+    }
+}
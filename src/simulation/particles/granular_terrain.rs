@@ -0,0 +1,179 @@
+use crate::{
+    core::math::{aabb2d::Aabb2d, vec2::Vec2},
+    simulation::particles::{particle::Phase, particle_vec::ParticleVec, spatial_hash::SpatialHash},
+};
+
+/// Loose particles that make up deformable ground, eg. sand. Once a grain has been still for a while it's frozen where it is,
+/// so ruts dug by the wheels hold their shape and settled ground costs nothing to simulate.
+/// Grains wake up again when something that isn't part of the terrain comes close, or a moving grain knocks into them.
+#[derive(Debug, Clone)]
+pub struct GranularTerrain {
+    pub indices: Vec<usize>,
+    imasses: Vec<f32>, // what each grain gets back when it wakes
+    still_steps: Vec<u32>, // 0 while moving, settle_steps once frozen
+    pub settle_speed: f32, // m/s, slower than this counts as still
+    pub settle_steps: u32, // how long a grain has to be still before it's frozen
+    pub wake_radius: f32, // how far beyond touching something has to come to wake a grain, metres
+}
+
+impl Default for GranularTerrain {
+    fn default() -> Self {
+        Self {
+            indices: vec![],
+            imasses: vec![],
+            still_steps: vec![],
+            settle_speed: 0.05,
+            settle_steps: 30,
+            wake_radius: 0.3,
+        }
+    }
+}
+
+impl GranularTerrain {
+    pub fn add(&mut self, index: usize, imass: f32) {
+        self.indices.push(index);
+        self.imasses.push(imass);
+        self.still_steps.push(0);
+    }
+
+    /// Whether the particle is a grain. Grains are added as they're created and renumbering keeps them in order, so the indices stay sorted.
+    pub fn contains(&self, index: usize) -> bool {
+        self.indices.binary_search(&index).is_ok()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn is_frozen(&self, k: usize) -> bool {
+        self.still_steps[k] >= self.settle_steps
+    }
+
+    /// Call after each step, once velocities are up to date
+    pub fn update(&mut self, particles: &mut ParticleVec) {
+        if self.is_empty() {
+            return;
+        }
+
+        let mut is_grain = vec![false; particles.len()];
+        let mut frozen_hash = SpatialHash::<usize, 1>::new();
+        for (k, &i) in self.indices.iter().enumerate() {
+            is_grain[i] = true;
+            if self.is_frozen(k) {
+                frozen_hash.insert_point(particles[i].pos, k);
+            }
+        }
+
+        // anything moving and not part of the terrain (the car) wakes the grains around it, as do grains still moving
+        let mut wake = vec![false; self.indices.len()];
+        for (i, p) in particles.iter().enumerate() {
+            if p.phase != Phase::Solid || p.imass == 0.0 || (is_grain[i] && p.vel.magnitude() < self.settle_speed) {
+                continue;
+            }
+            let reach = p.radius * 2.0 + self.wake_radius;
+            for k in frozen_hash.aabb_iter(Aabb2d::new(p.pos, Vec2::new(reach, reach))) {
+                let grain = &particles[self.indices[k]];
+                if (grain.pos - p.pos).magnitude() < p.radius + grain.radius + self.wake_radius {
+                    wake[k] = true;
+                }
+            }
+        }
+
+        for (k, &i) in self.indices.iter().enumerate() {
+            let p = &mut particles[i];
+            if wake[k] {
+                p.imass = self.imasses[k];
+                self.still_steps[k] = 0;
+            } else if !self.is_frozen(k) && p.imass > 0.0 {
+                if p.vel.magnitude() < self.settle_speed {
+                    self.still_steps[k] += 1;
+                    if self.is_frozen(k) {
+                        p.imass = 0.0;
+                        p.vel = Vec2::new(0.0, 0.0);
+                    }
+                } else {
+                    self.still_steps[k] = 0;
+                }
+            }
+        }
+    }
+
+    /// Bring the terrain as it is in `from` over to `to`, a copy of the same level from another time (eg. a practice save state),
+    /// so ruts from earlier attempts are still there
+    pub fn carry_over(&self, from: &ParticleVec, to: &mut ParticleVec) {
+        for &i in &self.indices {
+            to[i].pos = from[i].pos;
+            to[i].pos_guess = from[i].pos_guess;
+            to[i].vel = from[i].vel;
+            to[i].imass = from[i].imass;
+        }
+    }
+
+    /// Follow the particles being renumbered, grains that were removed are dropped. See Simulation::remove_particles
+    pub fn remap_particles(&mut self, remap: &[Option<usize>]) {
+        let mut k = 0;
+        while k < self.indices.len() {
+            match remap[self.indices[k]] {
+                Some(i) => {
+                    self.indices[k] = i;
+                    k += 1;
+                }
+                None => {
+                    self.indices.remove(k);
+                    self.imasses.remove(k);
+                    self.still_steps.remove(k);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::particles::particle::Particle;
+
+    fn terrain_with_grain() -> (GranularTerrain, ParticleVec) {
+        let mut particles = ParticleVec::new();
+        particles.push(*Particle::default().set_pos(Vec2::new(0.0, 0.0)).set_radius(0.1).set_mass_2(0.5));
+        let mut terrain = GranularTerrain::default();
+        terrain.add(0, particles[0].imass);
+        (terrain, particles)
+    }
+
+    #[test]
+    fn test_grains_freeze_once_settled_and_wake_when_disturbed() {
+        let (mut terrain, mut particles) = terrain_with_grain();
+        for _ in 0..terrain.settle_steps {
+            terrain.update(&mut particles);
+        }
+        assert!(terrain.is_frozen(0));
+        assert_eq!(particles[0].imass, 0.0);
+
+        // a wheel rolls up
+        particles.push(*Particle::default().set_pos(Vec2::new(0.3, 0.0)).set_radius(0.1).set_mass_2(1.0).set_vel(Vec2::new(-2.0, 0.0)));
+        terrain.update(&mut particles);
+        assert!(!terrain.is_frozen(0));
+        assert_eq!(particles[0].imass, 2.0);
+    }
+
+    #[test]
+    fn test_far_away_solids_leave_grains_frozen() {
+        let (mut terrain, mut particles) = terrain_with_grain();
+        particles.push(*Particle::default().set_pos(Vec2::new(5.0, 0.0)).set_radius(0.1).set_mass_2(1.0).set_vel(Vec2::new(-2.0, 0.0)));
+        for _ in 0..terrain.settle_steps + 1 {
+            terrain.update(&mut particles);
+        }
+        assert!(terrain.is_frozen(0));
+    }
+
+    #[test]
+    fn test_carry_over_keeps_ruts() {
+        let (terrain, mut particles) = terrain_with_grain();
+        let mut loaded = particles.clone();
+        particles[0].pos = Vec2::new(0.0, -0.2);
+
+        terrain.carry_over(&particles, &mut loaded);
+        assert_eq!(loaded[0].pos, Vec2::new(0.0, -0.2));
+    }
+}
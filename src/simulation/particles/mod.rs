@@ -11,4 +11,14 @@ pub mod sdf_data;
 pub mod open_smoke_emitter;
 pub mod fluid_emitter;
 pub mod simulation_demos;
-pub mod spatial_hash;
\ No newline at end of file
+pub mod spatial_hash;
+pub mod uniform_grid;
+pub mod force_field;
+pub mod energy_diagnostics;
+pub mod activity_region;
+pub mod aerodynamics;
+pub mod buoyancy;
+pub mod flow_zone;
+pub mod drag_region;
+pub mod granular_terrain;
+pub mod surface_material;
\ No newline at end of file
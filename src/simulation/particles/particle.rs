@@ -1,7 +1,7 @@
 
 use std::{fmt, usize};
 
-use crate::{core::math::{aabb2d::Aabb2d, vec2::Vec2, vec4::Vec4}, simulation::particles::{body::Body, sdf_data::SdfData}};
+use crate::{core::math::{aabb2d::Aabb2d, deterministic::MathMode, vec2::Vec2, vec4::Vec4}, simulation::particles::{body::Body, sdf_data::SdfData, surface_material::MaterialId}};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ParticleType {
@@ -60,6 +60,8 @@ pub struct Particle {
     /// drag/resistance is applied to the sliding motion, slowing it down but not necessarily stopping it instantly.
     pub k_friction: f32, // coeffs of friction
     pub t: f32,
+
+    pub material: MaterialId, // see MaterialTable
 }
 
 impl Particle {
@@ -95,6 +97,11 @@ impl Particle {
         self
     }
 
+    pub fn set_material(&mut self, material: MaterialId) -> &mut Self {
+        self.material = material;
+        self
+    }
+
     pub fn set_colour(&mut self, colour: Vec4) -> &mut Self {
         debug_assert!(!colour.x.is_nan());
         debug_assert!(!colour.y.is_nan());
@@ -189,9 +196,9 @@ impl Particle {
         self.pos = self.pos_guess;
     }
 
-    pub fn scale_mass(&mut self) {
+    pub fn scale_mass(&mut self, math: MathMode) {
         if self.imass != 0.0 {
-            self.tmass = 1. / ((1. / self.imass) * -self.pos.y.exp());
+            self.tmass = 1. / ((1. / self.imass) * -math.exp(self.pos.y));
         } else {
             self.tmass = 0.0;
         }
@@ -213,7 +220,7 @@ impl Particle {
         self
     }
 
-    pub fn get_sdf_data(&self, bodies: &Vec<Body>, idx: usize) -> SdfData {
+    pub fn get_sdf_data(&self, bodies: &Vec<Body>, idx: usize, math: MathMode) -> SdfData {
         if self.phase != Phase::Solid || self.body < 0 {
             return SdfData::new(Vec2::new(0.0, 0.0), 0.0);
         }
@@ -224,7 +231,7 @@ impl Particle {
             None => SdfData::new(Vec2::new(0.0, 0.0), 0.0)
         };
 
-        out.rotate(body.angle);
+        out.rotate(body.angle, math);
         return out;
     }
 
@@ -280,6 +287,7 @@ impl Default for Particle {
             s_friction: 0.0,
             k_friction: 0.0,
             t: 4.0,
+            material: 0,
         };
         s.set_mass_2(s.mass);
         s
@@ -7,6 +7,7 @@ use crate::simulation::particles::particle::Particle;
 pub type ParticleHandle = usize;
 
 
+#[derive(Clone)]
 pub struct ParticleVec(pub Vec<Particle>);
 
 impl<const N: usize> From<[Particle; N]> for ParticleVec {
@@ -1,4 +1,4 @@
-use crate::core::math::vec2::Vec2;
+use crate::core::math::{deterministic::MathMode, vec2::Vec2};
 
 // Signed distance field data for rigid-body collisions
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -15,8 +15,8 @@ impl SdfData {
         }
     }
 
-    pub fn rotate(&mut self, angle_rad: f32) {
-        let rotated_vector = Vec2::rotate_rad(self.gradient, angle_rad);
+    pub fn rotate(&mut self, angle_rad: f32, math: MathMode) {
+        let rotated_vector = math.rotate(self.gradient, angle_rad);
         self.gradient = rotated_vector;
     }
 }
\ No newline at end of file
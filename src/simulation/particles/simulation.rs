@@ -1,10 +1,126 @@
 use std::isize;
+use std::time::{Duration, Instant};
 
 use rand_pcg::Pcg64;
-use crate::{core::math::vec2::Vec2, simulation::{constraints::{boundary_constraint::{BoundaryConstraint, BoundaryConstraintVec}, contact_constraint::{ContactConstraint, ContactConstraintVec}, distance_constraint::{DistanceConstraint, DistanceConstraintVec}, gas_constraint::{GasConstraint, GasConstraintVec}, rigid_contact_constraint::{RigidContactConstraint, RigidContactConstraintVec}, spring_constraint::{SpringConstraint, SpringConstraintVec}, total_fluid_constraint::{TotalFluidConstraint, TotalFluidConstraintVec}, total_shape_constraint::TotalShapeConstraint, volume_constraint::{VolumeConstraint, VolumeConstraintVec}}, particles::{body::Body, fluid_emitter::FluidEmitter, open_smoke_emitter::OpenSmokeEmitter, particle::{Particle, Phase}, particle_vec::ParticleVec, sdf_data::SdfData, spatial_hash::SpatialHash}}};
+use crate::{core::{hash::Fnv1aHasher, math::{aabb2d::Aabb2d, deterministic::MathMode, vec2::Vec2}}, simulation::{constraints::{boundary_constraint::{BoundaryConstraint, BoundaryConstraintVec}, contact_cache::ContactCache, contact_constraint::{ContactConstraint, ContactConstraintVec}, distance_constraint::{DistanceConstraint, DistanceConstraintVec}, gas_constraint::{GasConstraint, GasConstraintVec}, kinematic_constraint::{KinematicConstraint, KinematicConstraintVec}, rigid_contact_constraint::{RigidContactConstraint, RigidContactConstraintVec}, spring_constraint::{SpringConstraint, SpringConstraintVec}, total_fluid_constraint::{TotalFluidConstraint, TotalFluidConstraintVec}, total_shape_constraint::TotalShapeConstraint, volume_constraint::{VolumeConstraint, VolumeConstraintVec}}, particles::{activity_region::ActivityRegion, aerodynamics::Aerodynamics, body::Body, buoyancy::Buoyancy, drag_region::DragRegion, fluid_emitter::FluidEmitter, flow_zone::FlowZone, force_field::ForceField, granular_terrain::GranularTerrain, open_smoke_emitter::OpenSmokeEmitter, particle::{Particle, Phase}, particle_vec::ParticleVec, sdf_data::SdfData, surface_material::MaterialTable, uniform_grid::UniformGrid}}};
+
+
+
+/// The constraint groups projected by each solver iteration, in order.
+/// Exposed so tools (eg. the determinism audit) can step through an iteration one group at a time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolverGroup {
+    Kinematic,
+    Shape,
+    Distance,
+    Spring,
+    Fluid,
+    Gas,
+    Volume,
+    RigidContact,
+    Contact,
+    Boundary,
+}
+
+impl SolverGroup {
+    pub const ALL: [SolverGroup; 10] = [
+        SolverGroup::Kinematic, // first, so everything else sees moving platforms where they are this step
+        SolverGroup::Shape,
+        SolverGroup::Distance,
+        SolverGroup::Spring,
+        SolverGroup::Fluid,
+        SolverGroup::Gas,
+        SolverGroup::Volume,
+        SolverGroup::RigidContact,
+        SolverGroup::Contact,
+        SolverGroup::Boundary,
+    ];
+}
+
+/// How many times the constraint groups are projected each step. The solver keeps iterating until an iteration
+/// moves no particle further than the tolerance, so calm frames do less work and chaotic frames get more.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolverSettings {
+    pub min_iterations: i32,
+    pub max_iterations: i32,
+    pub tolerance: f32, // largest position correction in an iteration that counts as converged, in metres
+    pub warm_start: f32, // fraction of last step's contact corrections applied up front to contacts that persist, 0 to disable
+    pub max_speed: f32, // m/s, faster particles are slowed to this so one bad constraint can't fling them across the level in a step
+}
 
+impl SolverSettings {
+    pub fn converged(&self, iterations: i32, residual: f32) -> bool {
+        iterations >= self.max_iterations.max(1) || (iterations >= self.min_iterations && residual <= self.tolerance)
+    }
+}
 
+impl Default for SolverSettings {
+    fn default() -> Self {
+        Self { min_iterations: 2, max_iterations: 6, tolerance: 1e-3, warm_start: 0.5, max_speed: 250.0 }
+    }
+}
+
+/// The part of a cached contact correction that can be reapplied: only along the current contact normal,
+/// and no more than it takes to leave the pair just touching. Anything more would turn into velocity in post_solve and add energy.
+fn warm_start_corrections(p1: &Particle, p2: &Particle, cached1: Vec2, cached2: Vec2) -> Option<(Vec2, Vec2)> {
+    let x12 = p1.pos_guess - p2.pos_guess;
+    let len = x12.magnitude();
+    let penetration = p1.radius + p2.radius - len;
+    if penetration <= 0.0 || len < f32::EPSILON {
+        return None;
+    }
+    let n = x12 / len;
+    let (along1, along2) = (cached1.dot(n), cached2.dot(n));
+    let separation = along1 - along2;
+    if separation <= f32::EPSILON {
+        return None;
+    }
+    let scale = (penetration / separation).min(1.0);
+    Some((n * (along1 * scale), n * (along2 * scale)))
+}
+
+/// A particle frozen for a step by the activity region, with what it gets back in post_solve
+#[derive(Debug, Clone, Copy)]
+struct SleepingParticle {
+    index: usize,
+    imass: f32,
+    pos: Vec2,
+    vel: Vec2,
+}
 
+/// Two particles that started touching this step, for collision sounds and the like
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContactEvent {
+    pub i1: usize,
+    pub i2: usize,
+    pub speed: f32, // how fast they were closing on each other, m/s
+}
+
+/// A rigid move of a group of particles, eg. a car going through a teleporter, see queue_relocation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relocation {
+    pub particles: Vec<usize>,
+    pub from: Vec2, // the point of the group that ends up at to
+    pub to: Vec2,
+    pub rotation: Vec2, // cos and sin of the angle the group and its velocity are turned by, (1, 0) for none
+}
+
+impl Relocation {
+    fn rotate(&self, v: Vec2) -> Vec2 {
+        Vec2::new(v.x * self.rotation.x - v.y * self.rotation.y, v.x * self.rotation.y + v.y * self.rotation.x)
+    }
+}
+
+/// How the last solve went, for the debug HUD
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SolverStats {
+    pub iterations: i32,
+    pub initial_residual: f32, // largest correction made by the first iteration
+    pub residual: f32, // largest correction made by the last iteration
+    pub clamped: usize, // particles slowed to max_speed
+}
+
+#[derive(Clone)]
 pub struct Simulation {
     pub particles: ParticleVec,
     pub gravity: Vec2,
@@ -17,24 +133,53 @@ pub struct Simulation {
     pub contact_boundary_constraints: BoundaryConstraintVec,
     pub contact_rigid_contact_constraints: RigidContactConstraintVec,
     pub contact_contact_constraints: ContactConstraintVec,
+    pub contact_cache: ContactCache, // contact corrections from the last step, for warm starting
+    pub contact_events: Vec<ContactEvent>, // contacts new this step, see find_contact_events
 
     pub distance_constraints: DistanceConstraintVec,
     pub spring_constraints: SpringConstraintVec,
     pub global_standard_total_fluid_constraints: TotalFluidConstraintVec,
     pub global_standard_gas_constraints: GasConstraintVec,
     pub volume_constraints: VolumeConstraintVec,
+    pub kinematic_constraints: KinematicConstraintVec,
     
     pub smoke_emitters: Vec<OpenSmokeEmitter>,
     pub fluid_emitters: Vec<FluidEmitter>,
+    pub force_fields: Vec<ForceField>,
+    pub aerodynamics: Aerodynamics,
+    pub buoyancy: Buoyancy,
+    pub flow_zones: Vec<FlowZone>,
+    pub drag_regions: Vec<DragRegion>,
+    pub granular_terrain: GranularTerrain,
+    pub materials: MaterialTable,
 
     pub counts: Vec<usize>,
     pub body_count: usize,
+    pub broadphase: UniformGrid, // finds the particles near each other, kept between steps so it only updates what moved
+
+    pub solver_settings: SolverSettings,
+    pub solver_stats: SolverStats,
+    pub broadphase_time: Duration, // of the last pre_solve, spent finding neighbours and contacts
+    pub math_mode: MathMode, // fixed at creation, the level is built with it too
+
+    pub activity_region: Option<ActivityRegion>, // None simulates everything every step
+    pub step_count: u64,
+    sleeping: Vec<SleepingParticle>,
+    pub quarantined: Vec<usize>, // particles frozen after going NaN or infinite, in the order they went, see quarantine_non_finite
+    relocations: Vec<Relocation>, // applied at the end of the next post_solve
     
     pub rng: Pcg64,
 }
 
 impl Simulation {
+    pub const MIN_CONTACT_EVENT_SPEED: f32 = 0.5; // m/s, gentler contacts come and go all the time on bumpy ground
+
     pub fn new(rng: Pcg64) -> Self {
+        Self::with_math_mode(rng, MathMode::default())
+    }
+
+    /// A simulation that works out its sin, cos etc. the given way, see MathMode
+    pub fn with_math_mode(rng: Pcg64, math_mode: MathMode) -> Self {
         Self {
             particles: ParticleVec::new(),
             gravity: Vec2::new(0.0, -9.8),
@@ -48,6 +193,8 @@ impl Simulation {
             contact_boundary_constraints: BoundaryConstraintVec::new(),
             contact_rigid_contact_constraints: RigidContactConstraintVec::new(),
             contact_contact_constraints: ContactConstraintVec::new(),
+            contact_cache: ContactCache::new(),
+            contact_events: vec![],
             // CONTACT group end.
 
             distance_constraints: DistanceConstraintVec::new(),
@@ -55,12 +202,30 @@ impl Simulation {
             global_standard_total_fluid_constraints: TotalFluidConstraintVec::new(),
             global_standard_gas_constraints: GasConstraintVec::new(),
             volume_constraints: VolumeConstraintVec::new(),
+            kinematic_constraints: KinematicConstraintVec::new(),
 
             smoke_emitters: vec![],
             fluid_emitters: vec![],
+            force_fields: vec![],
+            aerodynamics: Aerodynamics::default(),
+            buoyancy: Buoyancy::default(),
+            flow_zones: vec![],
+            drag_regions: vec![],
+            granular_terrain: GranularTerrain::default(),
+            materials: MaterialTable::default(),
 
             counts: vec![],
             body_count: 0,
+            broadphase: UniformGrid::new(),
+            solver_settings: SolverSettings::default(),
+            solver_stats: SolverStats::default(),
+            broadphase_time: Duration::ZERO,
+            math_mode,
+            activity_region: None,
+            step_count: 0,
+            sleeping: vec![],
+            quarantined: vec![],
+            relocations: vec![],
             rng,
         }
     }
@@ -73,6 +238,17 @@ impl Simulation {
         debug_assert!(self.contact_contact_constraints.len() == 0);
         debug_assert!(self.counts.len() == 0);
 
+        self.quarantine_non_finite(false);
+        self.sleep_distant_particles();
+        self.aerodynamics.apply(&mut self.particles, time_delta);
+        self.buoyancy.apply(&mut self.particles, &self.volume_constraints, self.gravity, time_delta);
+        for zone in &self.flow_zones {
+            zone.apply(&mut self.particles, time_delta);
+        }
+        for region in &self.drag_regions {
+            region.apply(&mut self.particles, time_delta);
+        }
+
         // Add all rigid body shape constraints
         // FM: doesn't need to occur as we do this dynamically as required - see how I use TotalShapeConstraint below.
 
@@ -99,33 +275,39 @@ impl Simulation {
             p.vel = p.vel + time_delta * my_gravity + time_delta * p.force;
             p.force = Vec2::new(0.0, 0.0);
 
+            if p.imass > 0.0 {
+                for field in &self.force_fields {
+                    p.vel += time_delta * field.force_at(p.pos);
+                }
+            }
+            let max_speed = self.solver_settings.max_speed;
+            if p.vel.magnitude2() > max_speed * max_speed {
+                p.vel = p.vel * (max_speed / p.vel.magnitude());
+            }
+
             // (3) Predict positions, reset n
             p.pos_guess = p.guess(time_delta);
             self.counts.push(0);//m_counts[i] = 0;
             
             // (4) Apply mass scaling (used by certain constraints)
-            p.scale_mass();
+            p.scale_mass(self.math_mode);
         }
         // (5) End for
 
         // m_contactSolver.setupM(&m_particles, true);
 
 
-        // Use SpatialHash to speed up particle collision checking
-        // Ideally we keep spatial hash as part of self to save memory reallocation.
-        let mut spatial_hash = SpatialHash::<usize, 1>::new();
-        for i in 0..particle_count {
-            let p = &mut self.particles[i];
-            let aabb = p.get_aabb();
-            spatial_hash.insert_aabb(aabb, i);
-        }
+        let broadphase_start = Instant::now();
+        self.broadphase.update(&self.particles);
+        let mut neighbors = vec![];
 
         // (6) For all particles
         for i in 0..particle_count {
             let p = &self.particles[i];
 
-            // (7) Find neighboring particles and solid contacts, naive solution
-            for j in spatial_hash.aabb_iter(p.get_aabb()) { //for j in (i + 1)..particle_count {
+            // (7) Find neighboring particles and solid contacts
+            self.broadphase.neighbors(i, p.pos_guess, p.radius, &mut neighbors);
+            for &j in &neighbors { //for j in (i + 1)..particle_count {
                 if j <= i {
                     continue;
                 }
@@ -187,6 +369,8 @@ impl Simulation {
             }
         }
         // (9) End for
+        self.broadphase_time = broadphase_start.elapsed();
+        self.find_contact_events();
 
         // m_contactSolver.setupSizes(m_particles.size(), &constraints[STABILIZATION]);
 
@@ -210,6 +394,169 @@ impl Simulation {
         self.contact_boundary_constraints.update_counts(&mut self.counts);
 
         // update_counts_callback(self);
+
+        self.warm_start_contacts();
+    }
+
+    /// Freeze the particles the activity region says aren't stepping this step by making them static until post_solve
+    fn sleep_distant_particles(&mut self) {
+        debug_assert!(self.sleeping.is_empty());
+        let Some(region) = self.activity_region else { return };
+
+        let mut awake: Vec<bool> = self.particles.0.iter().map(|p| region.is_awake(p.pos, self.step_count)).collect();
+        // rigid bodies sleep as a whole, the shape constraint can't hold half a body still
+        for body in &self.bodies {
+            let body_awake = body.particle_indicies.iter().any(|&i| awake[i]);
+            for &i in &body.particle_indicies {
+                awake[i] = body_awake;
+            }
+        }
+
+        for (index, p) in self.particles.0.iter_mut().enumerate() {
+            if awake[index] || p.imass == 0.0 {
+                continue;
+            }
+            self.sleeping.push(SleepingParticle { index, imass: p.imass, pos: p.pos, vel: p.vel });
+            p.imass = 0.0;
+        }
+    }
+
+    /// Freeze any particle that has gone NaN or infinite so it can't poison the particles it touches. Its last good
+    /// position is kept (or it's parked in the corner of the world if there isn't one) and it's made static, along with the
+    /// rest of its rigid body. Quarantined particles are added to quarantined and stay frozen for the rest of the run.
+    /// With guesses the predicted positions are checked too, otherwise only the positions and velocities.
+    pub fn quarantine_non_finite(&mut self, guesses: bool) {
+        let is_bad = |p: &Particle| !p.pos.is_finite() || !p.vel.is_finite() || (guesses && !p.pos_guess.is_finite());
+        if !self.particles.0.iter().any(is_bad) {
+            return;
+        }
+
+        let mut bad: Vec<bool> = self.particles.0.iter().map(is_bad).collect();
+        // a body can't keep its shape with some of it frozen
+        for body in &self.bodies {
+            if body.particle_indicies.iter().any(|&i| bad[i]) {
+                for &i in &body.particle_indicies {
+                    bad[i] = true;
+                }
+            }
+        }
+        let corner = Vec2::new(self.x_boundaries.x, self.y_boundaries.x);
+        for i in (0..bad.len()).filter(|&i| bad[i]) {
+            let p = &mut self.particles[i];
+            if !p.pos.is_finite() {
+                p.pos = corner;
+            }
+            p.pos_guess = p.pos;
+            p.vel = Vec2::new(0.0, 0.0);
+            p.force = Vec2::new(0.0, 0.0);
+            p.imass = 0.0;
+            if !self.quarantined.contains(&i) {
+                self.quarantined.push(i);
+            }
+        }
+        // sleeping particles get their mass back in post_solve, quarantined ones mustn't
+        self.sleeping.retain(|s| !self.quarantined.contains(&s.index));
+    }
+
+    /// Speed each particle should leave a bouncy surface at, from the velocities it arrived with.
+    /// Those are replaced in post_solve, so this has to be worked out first.
+    fn surface_bounces(&self) -> Vec<(usize, Vec2, f32)> {
+        let mut bounces = vec![];
+        for (i1, i2) in self.contact_pairs() {
+            for (surface, other) in [(i1, i2), (i2, i1)] {
+                let restitution = self.materials.get(self.particles[surface].material).restitution;
+                if restitution <= 0.0 || self.particles[other].imass == 0.0 {
+                    continue;
+                }
+                let offset = self.particles[other].pos_guess - self.particles[surface].pos_guess;
+                let len = offset.magnitude();
+                if len < f32::EPSILON {
+                    continue;
+                }
+                let n = offset / len;
+                let approach = (self.particles[other].vel - self.particles[surface].vel).dot(n);
+                if approach < -MaterialTable::MIN_BOUNCE_SPEED {
+                    bounces.push((other, n, -approach * restitution));
+                }
+            }
+        }
+        bounces
+    }
+
+    /// Bounce off bouncy surfaces, and pull anything touching a sticky surface towards it next step.
+    /// Multiple sticky contacts on one particle are averaged, so a wheel resting on two wall particles isn't held twice as hard.
+    fn apply_surface_materials(&mut self, bounces: &[(usize, Vec2, f32)]) {
+        for &(i, n, speed) in bounces {
+            let p = &mut self.particles[i];
+            let separating = p.vel.dot(n);
+            if separating < speed {
+                p.vel += n * (speed - separating);
+            }
+        }
+
+        let mut pulls: Vec<(usize, Vec2)> = vec![];
+        for (i1, i2) in self.contact_pairs() {
+            for (surface, other) in [(i1, i2), (i2, i1)] {
+                let adhesion = self.materials.get(self.particles[surface].material).adhesion;
+                if adhesion <= 0.0 || self.particles[other].imass == 0.0 {
+                    continue;
+                }
+                let offset = self.particles[surface].pos - self.particles[other].pos;
+                let len = offset.magnitude();
+                if len >= f32::EPSILON {
+                    pulls.push((other, offset * (adhesion / len)));
+                }
+            }
+        }
+        pulls.sort_by_key(|(i, _)| *i);
+        for group in pulls.chunk_by(|a, b| a.0 == b.0) {
+            let pull = group.iter().fold(Vec2::new(0.0, 0.0), |sum, (_, pull)| sum + *pull) / group.len() as f32;
+            self.particles[group[0].0].force += pull;
+        }
+    }
+
+    /// Contacts that were also touching last step start with part of last step's correction already applied.
+    /// Resting contacts then start the solve close to where the last one finished, which keeps stacks and piles still.
+    fn warm_start_contacts(&mut self) {
+        let factor = self.solver_settings.warm_start;
+        if factor <= 0.0 || self.contact_cache.is_empty() {
+            return;
+        }
+
+        // work out every warm start from the predicted positions before moving anything, then share each
+        // particle's warm starts between its contacts the same way the solver shares corrections using counts
+        let pairs: Vec<(usize, usize)> = self.contact_rigid_contact_constraints.0.iter().map(|c| (c.i1, c.i2))
+            .chain(self.contact_contact_constraints.0.iter().map(|c| (c.i1, c.i2)))
+            .collect();
+        let mut num_warm_starts = vec![0u32; self.particles.len()];
+        let mut warm_starts = Vec::with_capacity(pairs.len());
+        for (i1, i2) in pairs {
+            // loose particles (granular piles, fluids) shuffle between neighbours too much to warm start,
+            // they just slosh, so only contacts involving a rigid body are warm started
+            let involves_body = self.particles[i1].body >= 0 || self.particles[i2].body >= 0;
+            let warm_start = self.contact_cache.get(i1, i2).filter(|_| involves_body)
+                .and_then(|(cached1, cached2)| warm_start_corrections(&self.particles[i1], &self.particles[i2], cached1, cached2));
+            if warm_start.is_some() {
+                num_warm_starts[i1] += 1;
+                num_warm_starts[i2] += 1;
+            }
+            warm_starts.push(warm_start);
+        }
+
+        let constraints = self.contact_rigid_contact_constraints.iter_mut().map(|c| (c.i1, c.i2, &mut c.correction1, &mut c.correction2))
+            .chain(self.contact_contact_constraints.iter_mut().map(|c| (c.i1, c.i2, &mut c.correction1, &mut c.correction2)));
+        for ((i1, i2, correction1, correction2), warm_start) in constraints.zip(warm_starts) {
+            let Some((warm1, warm2)) = warm_start else { continue };
+            // static and sleeping particles stay put
+            if self.particles[i1].imass > 0.0 {
+                *correction1 = warm1 * (factor / num_warm_starts[i1] as f32);
+            }
+            if self.particles[i2].imass > 0.0 {
+                *correction2 = warm2 * (factor / num_warm_starts[i2] as f32);
+            }
+            self.particles[i1].pos_guess += *correction1;
+            self.particles[i2].pos_guess += *correction2;
+        }
     }
 
     pub fn solve(&mut self, time_delta: f32, _solver_iterations: i32, iteration: i32) {
@@ -237,21 +584,9 @@ impl Simulation {
  
             // (17) For constraint group
             //  (18, 19, 20) Solve constraints in g and update ep
-            {
-                let c = TotalShapeConstraint::new();
-                for i in 0..self.bodies.len() {
-                    let body = &mut self.bodies[i];
-                    c.project(&mut self.particles, &self.counts, body);
-                }
+            for group in SolverGroup::ALL {
+                self.solve_group(group, time_delta);
             }
-            self.distance_constraints.solve(&mut self.particles, &self.counts);
-            self.spring_constraints.solve(&mut self.particles, &self.counts, time_delta);
-            self.global_standard_total_fluid_constraints.solve(&mut self.particles, &self.counts);
-            self.global_standard_gas_constraints.solve(&mut self.particles, &self.counts);
-            self.volume_constraints.solve(&mut self.particles, &self.counts, time_delta);
-            self.contact_rigid_contact_constraints.solve(&mut self.particles, &self.counts, &self.bodies);
-            self.contact_contact_constraints.solve(&mut self.particles, &self.counts);
-            self.contact_boundary_constraints.solve(&mut self.particles, &self.counts);
             //solve_constraints_callback(self, time_delta);
 
         //     for (int j = 0; j < (int) NUM_CONSTRAINT_GROUPS; j++) {
@@ -270,12 +605,99 @@ impl Simulation {
         //}
     }
 
+    /// Solve iterations until the solver settings say we have converged.
+    /// after_iteration is called after each iteration for constraints kept outside the simulation, eg. elevators.
+    pub fn solve_adaptive(&mut self, time_delta: f32, mut after_iteration: impl FnMut(&mut Simulation)) -> SolverStats {
+        let settings = self.solver_settings;
+        let mut stats = SolverStats::default();
+        let mut guesses = Vec::with_capacity(self.particles.len());
+        loop {
+            self.guess_positions(&mut guesses);
+            self.solve(time_delta, settings.max_iterations, stats.iterations);
+            after_iteration(self);
+            // catch anything that went bad this iteration before the next one spreads it to its neighbours
+            self.quarantine_non_finite(true);
+
+            let residual = self.max_correction(&guesses);
+            if stats.iterations == 0 {
+                stats.initial_residual = residual;
+            }
+            stats.residual = residual;
+            stats.iterations += 1;
+            if settings.converged(stats.iterations, residual) {
+                break;
+            }
+        }
+        self.solver_stats = stats;
+        stats
+    }
+
+    /// A whole step with nothing outside the simulation, for tests
+    #[cfg(test)]
+    pub fn step(&mut self, time_delta: f32) -> SolverStats {
+        self.pre_solve(time_delta);
+        let stats = self.solve_adaptive(time_delta, |_| {});
+        self.post_solve(time_delta);
+        stats
+    }
+
+    /// Copy out the predicted positions, to measure how far an iteration moves them with max_correction
+    pub fn guess_positions(&self, guesses: &mut Vec<Vec2>) {
+        guesses.clear();
+        guesses.extend(self.particles.0.iter().map(|p| p.pos_guess));
+    }
+
+    /// The largest distance a predicted position has moved since guess_positions. This is the constraint error
+    /// the last iteration corrected, once it's small the constraints are as satisfied as they will get.
+    pub fn max_correction(&self, guesses: &[Vec2]) -> f32 {
+        self.particles.0.iter().zip(guesses.iter())
+            .map(|(p, guess)| (p.pos_guess - *guess).magnitude2())
+            .fold(0.0, f32::max)
+            .sqrt()
+    }
+
+    pub fn solve_group(&mut self, group: SolverGroup, time_delta: f32) {
+        match group {
+            SolverGroup::Kinematic => self.kinematic_constraints.solve(&mut self.particles),
+            SolverGroup::Shape => {
+                let c = TotalShapeConstraint::new();
+                for i in 0..self.bodies.len() {
+                    let body = &mut self.bodies[i];
+                    // asleep, see sleep_distant_particles
+                    if body.particle_indicies.first().is_some_and(|&first| self.particles[first].imass == 0.0) {
+                        continue;
+                    }
+                    c.project(&mut self.particles, &self.counts, body, self.math_mode);
+                }
+            }
+            SolverGroup::Distance => self.distance_constraints.solve(&mut self.particles, &self.counts),
+            SolverGroup::Spring => self.spring_constraints.solve(&mut self.particles, &self.counts, time_delta),
+            SolverGroup::Fluid => self.global_standard_total_fluid_constraints.solve(&mut self.particles, &self.counts, &mut self.broadphase, self.math_mode),
+            SolverGroup::Gas => self.global_standard_gas_constraints.solve(&mut self.particles, &self.counts, &mut self.broadphase, self.math_mode),
+            SolverGroup::Volume => self.volume_constraints.solve(&mut self.particles, &self.counts, time_delta),
+            SolverGroup::RigidContact => self.contact_rigid_contact_constraints.solve(&mut self.particles, &self.counts, &self.bodies, self.math_mode),
+            SolverGroup::Contact => self.contact_contact_constraints.solve(&mut self.particles, &self.counts),
+            SolverGroup::Boundary => self.contact_boundary_constraints.solve(&mut self.particles, &self.counts),
+        }
+    }
+
     pub fn post_solve(&mut self, time_delta: f32) {
+        // the GPU solver doesn't go through solve_adaptive, so check its guesses here too
+        self.quarantine_non_finite(true);
+        let bounces = self.surface_bounces();
+
         // (23) For all particles
+        let max_step = self.solver_settings.max_speed * time_delta;
+        self.solver_stats.clamped = 0;
         for i in 0..self.particles.len() {
             let p = &mut self.particles[i];
 
-            // (24) Update velocities
+            // (24) Update velocities, no further than max_speed allows
+            let step = p.pos_guess - p.pos;
+            if step.magnitude2() > max_step * max_step {
+                p.pos_guess = p.pos + step * (max_step / step.magnitude());
+                self.solver_stats.clamped += 1;
+            }
             p.vel = (p.pos_guess - p.pos) / time_delta;
 
             // (25, 26) Advect diffuse particles, apply internal forces
@@ -286,6 +708,29 @@ impl Simulation {
         }
         // (28) End for
 
+        // Sleeping particles pick up where they left off, nothing that happened this step touches them
+        for s in self.sleeping.drain(..) {
+            let p = &mut self.particles[s.index];
+            p.imass = s.imass;
+            p.pos = s.pos;
+            p.pos_guess = s.pos;
+            p.vel = s.vel;
+        }
+        self.apply_relocations();
+        self.distance_constraints.break_overstrained(&self.particles);
+        self.granular_terrain.update(&mut self.particles);
+        self.apply_surface_materials(&bounces);
+        self.step_count += 1;
+
+
+        // Remember this step's contact corrections for warm starting the next step
+        self.contact_cache.clear();
+        for c in &self.contact_rigid_contact_constraints.0 {
+            self.contact_cache.insert(c.i1, c.i2, c.correction1, c.correction2);
+        }
+        for c in &self.contact_contact_constraints.0 {
+            self.contact_cache.insert(c.i1, c.i2, c.correction1, c.correction2);
+        }
 
         // Delete temporary conact constraints
         self.contact_boundary_constraints.clear();
@@ -347,7 +792,7 @@ impl Simulation {
 
         // Update the body's global properties, including initial r_i vectors
         body.imass = 1.0 / total_mass;
-        body.update_com(&self.particles, false);
+        body.update_com(&self.particles, false, self.math_mode);
         body.compute_rs(&self.particles);
         // body->shape = new TotalShapeConstraint(body);
 
@@ -387,6 +832,23 @@ impl Simulation {
         return idx;
     }
 
+    /// Loose solid particles that make up deformable ground, see GranularTerrain
+    pub fn create_granular(&mut self, particles: &ParticleVec) {
+        for p in particles.iter() {
+            let mut p = *p;
+            p.phase = Phase::Solid;
+            p.body = -1;
+            self.granular_terrain.add(self.particles.len(), p.imass);
+            self.particles.push(p);
+        }
+    }
+
+    /// Keep the granular terrain from `previous`, a later point in the same level, eg. when loading a practice save state
+    pub fn carry_over_granular(&mut self, previous: &Simulation) {
+        previous.granular_terrain.carry_over(&previous.particles, &mut self.particles);
+        self.granular_terrain = previous.granular_terrain.clone();
+    }
+
     pub fn create_smoke_emitter(&mut self, posn: Vec2, particles_per_sec: f32, gas_index: usize /*GasConstraint *gs*/) {
         self.smoke_emitters.push(OpenSmokeEmitter::new(posn, particles_per_sec, gas_index /*gs*/));
     }
@@ -421,6 +883,54 @@ impl Simulation {
         return idx;
     }
 
+    /// Push every dynamic particle inside region with an acceleration of vector each step, eg. a wind zone
+    pub fn add_force_field(&mut self, region: Aabb2d, vector: Vec2) -> usize {
+        self.force_fields.push(ForceField::region(region, vector));
+        self.force_fields.len() - 1
+    }
+
+    /// Move a group of particles somewhere else at the end of the next post_solve, once the step's constraints are done
+    /// with them, so they arrive with their shape and speed as they were. Ignored if any of them are already being moved.
+    pub fn queue_relocation(&mut self, relocation: Relocation) {
+        if self.relocations.iter().any(|queued| queued.particles.iter().any(|i| relocation.particles.contains(i))) {
+            return;
+        }
+        self.relocations.push(relocation);
+    }
+
+    fn apply_relocations(&mut self) {
+        for relocation in std::mem::take(&mut self.relocations) {
+            for &i in &relocation.particles {
+                let p = &mut self.particles[i];
+                let pos = relocation.to + relocation.rotate(p.pos - relocation.from);
+                p.pos = pos;
+                p.pos_guess = pos;
+                p.vel = relocation.rotate(p.vel);
+            }
+        }
+    }
+
+    /// Slow every dynamic particle inside region by drag, the fraction of its velocity lost per second, eg. a mud pit
+    pub fn add_drag_region(&mut self, region: Aabb2d, drag: f32) -> usize {
+        self.drag_regions.push(DragRegion::new(region, drag));
+        self.drag_regions.len() - 1
+    }
+
+    /// Kick each of the given particles that is dynamic and inside region once, eg. a car on a boost pad. The impulse is given
+    /// as the change in velocity rather than momentum, so a body made of light and heavy particles moves off together instead of twisting.
+    /// Returns how many particles were kicked.
+    pub fn apply_impulse_in_region(&mut self, indices: impl IntoIterator<Item = usize>, region: &Aabb2d, impulse: Vec2) -> usize {
+        let mut count = 0;
+        for index in indices {
+            let p = &mut self.particles[index];
+            if p.imass > 0.0 && region.contains_point(p.pos) {
+                p.vel += impulse;
+                count += 1;
+            }
+        }
+        count
+    }
+
     pub fn add_distance_constraint(&mut self, c: DistanceConstraint) -> usize {
         self.distance_constraints.push(c);
         self.distance_constraints.0.len() - 1
@@ -436,7 +946,435 @@ impl Simulation {
         self.volume_constraints.0.len() - 1
     }
 
+    pub fn add_kinematic_constraint(&mut self, c: KinematicConstraint) -> usize {
+        self.kinematic_constraints.push(c);
+        self.kinematic_constraints.0.len() - 1
+    }
+
     pub fn add_particle(&mut self, p: Particle) {
         self.particles.push(p);
     }
-}
\ No newline at end of file
+
+    /// Particles that could be touching particle idx, in index order, as of the start of the last step
+    pub fn neighbors(&self, idx: usize) -> Vec<usize> {
+        let p = &self.particles[idx];
+        let mut neighbors = vec![];
+        self.broadphase.neighbors(idx, p.pos_guess, p.radius, &mut neighbors);
+        neighbors
+    }
+
+    /// Take particles out between steps, along with what can't work without them: the rest of any rigid body they are part of,
+    /// and the distance, spring and volume constraints holding them. Everything left is renumbered.
+    /// Returns how many particles went, which is more than asked for when whole bodies go.
+    pub fn remove_particles(&mut self, indices: &[usize]) -> usize {
+        let mut removed = vec![false; self.particles.len()];
+        for &i in indices {
+            removed[i] = true;
+        }
+        // a body can't keep its shape with particles missing
+        for body in &self.bodies {
+            if body.particle_indicies.iter().any(|&i| removed[i]) {
+                for &i in &body.particle_indicies {
+                    removed[i] = true;
+                }
+            }
+        }
+
+        let mut remap = vec![None; removed.len()];
+        let mut kept = 0;
+        for (i, &is_removed) in removed.iter().enumerate() {
+            if !is_removed {
+                remap[i] = Some(kept);
+                kept += 1;
+            }
+        }
+        let removed_count = removed.len() - kept;
+        if removed_count == 0 {
+            return 0;
+        }
+
+        let mut i = 0;
+        self.particles.0.retain(|_| {
+            i += 1;
+            !removed[i - 1]
+        });
+        for (i, p) in self.particles.iter_mut().enumerate() {
+            p.set_index(i);
+        }
+        self.broadphase.clear();
+
+        self.bodies.retain(|body| body.particle_indicies.first().is_none_or(|&i| !removed[i]));
+        for (b, body) in self.bodies.iter_mut().enumerate() {
+            body.remap_particles(&remap);
+            for &i in &body.particle_indicies {
+                self.particles[i].body = b as isize;
+            }
+        }
+
+        self.distance_constraints.0.retain_mut(|c| match (remap[c.i1], remap[c.i2]) {
+            (Some(i1), Some(i2)) => {
+                (c.i1, c.i2) = (i1, i2);
+                true
+            }
+            _ => false,
+        });
+        self.spring_constraints.0.retain_mut(|c| match (remap[c.i1], remap[c.i2]) {
+            (Some(i1), Some(i2)) => {
+                (c.i1, c.i2) = (i1, i2);
+                true
+            }
+            _ => false,
+        });
+        self.volume_constraints.0.retain_mut(|c| {
+            let indices: Option<Vec<usize>> = c.particle_indices.iter().map(|&i| remap[i]).collect();
+            indices.map(|indices| c.particle_indices = indices).is_some()
+        });
+        for c in self.global_standard_total_fluid_constraints.iter_mut() {
+            c.remap_particles(&remap);
+        }
+        for c in self.global_standard_gas_constraints.iter_mut() {
+            c.remap_particles(&remap);
+        }
+        for c in self.kinematic_constraints.0.iter_mut() {
+            c.remap_particles(&remap);
+        }
+        self.granular_terrain.remap_particles(&remap);
+        self.quarantined = self.quarantined.iter().filter_map(|&i| remap[i]).collect();
+        self.contact_cache.clear();
+
+        removed_count
+    }
+
+    /// Record the contacts that weren't touching last step and hit harder than MIN_CONTACT_EVENT_SPEED.
+    /// The contact cache still has last step's pairs until post_solve.
+    fn find_contact_events(&mut self) {
+        let mut contact_events = std::mem::take(&mut self.contact_events);
+        contact_events.clear();
+        for (i1, i2) in self.contact_pairs() {
+            if self.contact_cache.get(i1, i2).is_some() {
+                continue;
+            }
+            let (p1, p2) = (&self.particles[i1], &self.particles[i2]);
+            let normal = (p1.pos - p2.pos).normalize();
+            let speed = -(p1.vel - p2.vel).dot(normal);
+            if speed >= Self::MIN_CONTACT_EVENT_SPEED {
+                contact_events.push(ContactEvent { i1, i2, speed });
+            }
+        }
+        self.contact_events = contact_events;
+    }
+
+    /// Pairs of particles that are touching. Only valid between pre_solve and post_solve.
+    pub fn contact_pairs(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.contact_rigid_contact_constraints.0.iter().map(|c| (c.i1, c.i2))
+            .chain(self.contact_contact_constraints.0.iter().map(|c| (c.i1, c.i2)))
+    }
+
+    /// Hash of every particle's position, guess and velocity, bit for bit. Two runs that agree on this are in step.
+    pub fn state_checksum(&self) -> u64 {
+        let mut hasher = Fnv1aHasher::new();
+        hasher.write_u64(self.particles.len() as u64);
+        for particle in &self.particles.0 {
+            hasher.write_f32(particle.pos.x).write_f32(particle.pos.y);
+            hasher.write_f32(particle.pos_guess.x).write_f32(particle.pos_guess.y);
+            hasher.write_f32(particle.vel.x).write_f32(particle.vel.y);
+        }
+        hasher.finish()
+    }
+
+    /// Mirror everything in the simulation horizontally around x = 0.
+    /// Distance based constraints don't care, but anything with a handedness (volumes, rigid body rest shapes) needs flipping too.
+    pub fn mirror_x(&mut self) {
+        for p in self.particles.iter_mut() {
+            p.pos.x = -p.pos.x;
+            p.pos_guess.x = -p.pos_guess.x;
+            p.vel.x = -p.vel.x;
+        }
+
+        for c in self.volume_constraints.0.iter_mut() {
+            c.mirror_x();
+        }
+        for c in self.kinematic_constraints.0.iter_mut() {
+            c.mirror_x();
+        }
+
+        for body in self.bodies.iter_mut() {
+            body.center.x = -body.center.x;
+            body.angle = -body.angle;
+            for r in body.rs.values_mut() {
+                r.x = -r.x;
+            }
+            for sdf in body.sdf.values_mut() {
+                sdf.gradient.x = -sdf.gradient.x;
+            }
+        }
+
+        self.aerodynamics.mirror_x();
+        for zone in &mut self.flow_zones {
+            zone.mirror_x();
+        }
+        for region in &mut self.drag_regions {
+            region.mirror_x();
+        }
+
+        for emitter in self.smoke_emitters.iter_mut() {
+            emitter.posn.x = -emitter.posn.x;
+        }
+        for emitter in self.fluid_emitters.iter_mut() {
+            emitter.posn.x = -emitter.posn.x;
+        }
+        for field in self.force_fields.iter_mut() {
+            field.mirror_x();
+        }
+
+        self.x_boundaries = Vec2::new(-self.x_boundaries.y, -self.x_boundaries.x);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::math::random::Random, simulation::particles::{energy_diagnostics::EnergySample, simulation_demos::SimulationDemos, surface_material::MaterialId}};
+
+    #[test]
+    fn test_relocation_keeps_shape_and_turns_velocity() {
+        let mut sim = Simulation::new(Random::seed_from_str("relocation"));
+        sim.gravity = Vec2::zero();
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(1.0, 0.0)).set_mass_2(1.0).set_vel(Vec2::new(2.0, 0.0)));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(3.0, 0.0)).set_mass_2(1.0).set_vel(Vec2::new(2.0, 0.0)));
+
+        // a quarter turn about the middle of the pair, which ends up at (10, 10)
+        let relocation = Relocation { particles: vec![0, 1], from: Vec2::new(2.0, 0.0), to: Vec2::new(10.0, 10.0), rotation: Vec2::new(0.0, 1.0) };
+        sim.queue_relocation(relocation.clone());
+        sim.queue_relocation(Relocation { particles: vec![1], ..relocation }); // already on the move
+        sim.step(0.01);
+
+        // moved 0.02 along during the step before being moved
+        assert!(sim.particles[0].pos.distance(Vec2::new(10.0, 9.02)) < 1e-4, "{:?}", sim.particles[0].pos);
+        assert!(sim.particles[1].pos.distance(Vec2::new(10.0, 11.02)) < 1e-4, "{:?}", sim.particles[1].pos);
+        assert!(sim.particles[0].vel.distance(Vec2::new(0.0, 2.0)) < 1e-4);
+        assert_eq!(sim.particles[0].pos_guess, sim.particles[0].pos);
+
+        // and only the once
+        sim.step(0.01);
+        assert!(sim.particles[0].pos.distance(Vec2::new(10.0, 9.04)) < 1e-4, "{:?}", sim.particles[0].pos);
+    }
+
+    #[test]
+    fn test_impulse_in_region() {
+        let mut sim = Simulation::new(Random::seed_from_str("impulse"));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(0.5, 0.5)).set_mass_2(1.0));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(0.5, 0.8)).set_mass_2(4.0).set_vel(Vec2::new(1.0, 0.0)));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(3.0, 0.5)).set_mass_2(1.0)); // outside
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(0.5, 0.5)).set_static(true));
+
+        let region = Aabb2d { min: Vec2::new(0.0, 0.0), max: Vec2::new(1.0, 1.0) };
+        assert_eq!(sim.apply_impulse_in_region(0..4, &region, Vec2::new(2.0, 0.0)), 2);
+        // the same kick whatever the mass
+        assert_eq!(sim.particles[0].vel, Vec2::new(2.0, 0.0));
+        assert_eq!(sim.particles[1].vel, Vec2::new(3.0, 0.0));
+        assert_eq!(sim.particles[2].vel, Vec2::zero());
+        assert_eq!(sim.particles[3].vel, Vec2::zero());
+
+        // particles that weren't given are left alone even inside the region
+        assert_eq!(sim.apply_impulse_in_region([1], &region, Vec2::new(2.0, 0.0)), 1);
+        assert_eq!(sim.particles[0].vel, Vec2::new(2.0, 0.0));
+        assert_eq!(sim.particles[1].vel, Vec2::new(5.0, 0.0));
+    }
+
+    #[test]
+    fn test_solve_adaptive_stops_when_converged() {
+        // a free particle has nothing to correct, so only the minimum iterations run
+        let mut sim = Simulation::new(Random::seed_from_str("solver"));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(0.0, 10.0)).set_mass_2(1.0));
+        let stats = sim.step(0.005);
+        assert_eq!(stats.iterations, sim.solver_settings.min_iterations);
+        assert_eq!(stats.residual, 0.0);
+
+        // a badly stretched chain keeps correcting, so it runs up to the cap
+        let mut sim = Simulation::new(Random::seed_from_str("solver"));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(0.0, 10.0)).set_mass_2(1.0));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(1.0, 10.0)).set_mass_2(1.0));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(2.0, 10.0)).set_mass_2(1.0));
+        sim.add_distance_constraint(DistanceConstraint::from_particles(0, 1, &sim.particles));
+        sim.add_distance_constraint(DistanceConstraint::from_particles(1, 2, &sim.particles));
+        sim.particles[2].pos = Vec2::new(5.0, 10.0);
+        sim.solver_settings.tolerance = 0.0;
+        let stats = sim.step(0.005);
+        assert_eq!(stats.iterations, sim.solver_settings.max_iterations);
+        assert!(stats.initial_residual > stats.residual);
+    }
+
+    /// Drop a particle onto a static one of the given material, returning the falling particle's velocity after landing
+    fn land_on(material: MaterialId) -> Vec2 {
+        let mut sim = Simulation::new(Random::seed_from_str("materials"));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(0.0, 0.0)).set_radius(0.1).set_static(true).set_material(material));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(0.0, 0.21)).set_radius(0.1).set_mass_2(1.0).set_vel(Vec2::new(0.0, -3.0)));
+        sim.step(0.005);
+        sim.particles[1].vel
+    }
+
+    #[test]
+    fn test_surface_materials() {
+        // the solver only takes away the speed into the surface, it never gives any back
+        let plain = land_on(MaterialTable::DEFAULT);
+        assert!(plain.y <= 0.0);
+
+        let bouncy = land_on(MaterialTable::BOUNCY);
+        assert!(bouncy.y > 2.0);
+
+        // sticky surfaces pull on the next step
+        let mut sim = Simulation::new(Random::seed_from_str("materials"));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(0.0, 0.0)).set_radius(0.1).set_static(true).set_material(MaterialTable::STICKY));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(0.19, 0.0)).set_radius(0.1).set_mass_2(1.0));
+        sim.step(0.005);
+        assert!(sim.particles[1].force.x < 0.0);
+    }
+
+    /// Average kinetic energy of the wall demo over its last steps, once the bricks should have settled
+    fn wall_settled_kinetic_energy(warm_start: f32) -> f32 {
+        let mut sim = Simulation::new(Random::seed_from_str("solver"));
+        SimulationDemos::init_wall(&mut sim);
+        sim.solver_settings.warm_start = warm_start;
+        let mut kinetic_energy = 0.0;
+        for step in 0..1200 {
+            sim.step(0.005);
+            if step >= 900 {
+                kinetic_energy += EnergySample::measure(&sim).kinetic / 300.0;
+            }
+        }
+        kinetic_energy
+    }
+
+    #[test]
+    fn test_warm_start_steadies_the_wall() {
+        let cold = wall_settled_kinetic_energy(0.0);
+        let warm = wall_settled_kinetic_energy(SolverSettings::default().warm_start);
+        // finding each contact once already steadied the cold wall a lot, warm starting takes off less on top of that
+        assert!(warm < cold * 0.6, "warm started wall {} vs {}", warm, cold);
+    }
+
+    #[test]
+    fn test_contacts_are_found_once() {
+        // overlapping across the corner of a cell, the old broadphase found this pair in four cells
+        let mut sim = Simulation::new(Random::seed_from_str("solver"));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(-0.05, -0.05)).set_radius(0.1).set_static(true));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(0.05, 0.05)).set_radius(0.1).set_mass_2(1.0));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(3.0, 0.0)).set_radius(0.1).set_mass_2(1.0));
+        sim.pre_solve(0.005);
+        assert_eq!(sim.contact_rigid_contact_constraints.len(), 1);
+        assert_eq!(sim.neighbors(1), vec![0]);
+        assert!(sim.neighbors(2).is_empty());
+    }
+
+    #[test]
+    fn test_contact_events_only_when_contact_starts() {
+        let mut sim = Simulation::new(Random::seed_from_str("solver"));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(0.0, 0.0)).set_radius(0.1).set_static(true));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(0.0, 0.19)).set_vel(Vec2::new(0.0, -3.0)).set_radius(0.1).set_mass_2(1.0));
+        sim.pre_solve(0.005);
+        assert!(matches!(sim.contact_events[..], [ContactEvent { i1: 0, i2: 1, speed }] if (speed - 3.0).abs() < 0.1));
+        sim.solve_adaptive(0.005, |_| {});
+        sim.post_solve(0.005);
+
+        // hitting again while still in contact isn't a new event
+        sim.particles[1].set_pos(Vec2::new(0.0, 0.19)).set_vel(Vec2::new(0.0, -3.0));
+        sim.pre_solve(0.005);
+        assert_eq!(sim.contact_rigid_contact_constraints.len(), 1);
+        assert!(sim.contact_events.is_empty());
+    }
+
+    #[test]
+    fn test_activity_region_freezes_distant_particles() {
+        let mut sim = Simulation::new(Random::seed_from_str("solver"));
+        sim.x_boundaries = Vec2::new(-1000.0, 1000.0);
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(0.0, 50.0)).set_vel(Vec2::new(1.0, 0.0)).set_mass_2(1.0));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(45.0, 50.0)).set_vel(Vec2::new(1.0, 0.0)).set_mass_2(1.0));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(500.0, 50.0)).set_vel(Vec2::new(1.0, 0.0)).set_mass_2(1.0));
+        sim.activity_region = Some(ActivityRegion::new(Vec2::new(0.0, 50.0)));
+
+        for _ in 0..8 {
+            sim.step(0.005);
+        }
+        assert!((sim.particles[0].pos.x - 8.0 * 0.005).abs() < 1e-4);
+        // throttled, stepped on 2 of the 8 steps
+        assert!((sim.particles[1].pos.x - (45.0 + 2.0 * 0.005)).abs() < 1e-4);
+        assert_eq!(sim.particles[2].pos, Vec2::new(500.0, 50.0));
+        assert_eq!(sim.particles[2].vel, Vec2::new(1.0, 0.0));
+        assert_eq!(sim.particles[2].imass, 1.0);
+
+        // carries on where it left off once the region comes back
+        sim.activity_region = Some(ActivityRegion::new(Vec2::new(500.0, 50.0)));
+        sim.step(0.005);
+        assert!((sim.particles[2].pos.x - 500.005).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_non_finite_particles_are_quarantined() {
+        let mut sim = Simulation::new(Random::seed_from_str("containment"));
+        for x in [0.0, 1.0, 2.0] {
+            sim.add_particle(*Particle::default().set_pos(Vec2::new(x, 10.0)).set_mass_2(1.0));
+        }
+        sim.add_distance_constraint(DistanceConstraint::from_particles(0, 1, &sim.particles));
+        sim.add_distance_constraint(DistanceConstraint::from_particles(1, 2, &sim.particles));
+        sim.particles[1].vel = Vec2::new(f32::NAN, 0.0);
+        for _ in 0..10 {
+            sim.step(0.005);
+        }
+        // frozen where it was, and its neighbours carry on without picking up the NaN
+        assert_eq!(sim.quarantined, vec![1]);
+        assert_eq!((sim.particles[1].pos, sim.particles[1].imass), (Vec2::new(1.0, 10.0), 0.0));
+        assert!(sim.particles.iter().all(|p| p.pos.is_finite() && p.vel.is_finite()));
+
+        // a guess gone bad mid solve is caught too, and the quarantine follows the particle when others are removed
+        sim.pre_solve(0.005);
+        sim.particles[2].pos_guess = Vec2::new(f32::INFINITY, 10.0);
+        sim.solve_adaptive(0.005, |_| {});
+        sim.post_solve(0.005);
+        assert_eq!(sim.quarantined, vec![1, 2]);
+        assert!(sim.particles[0].pos.is_finite());
+        sim.remove_particles(&[0]);
+        assert_eq!(sim.quarantined, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_speed_is_clamped() {
+        let mut sim = Simulation::new(Random::seed_from_str("containment"));
+        sim.add_particle(*Particle::default().set_pos(Vec2::new(0.0, 0.0)).set_vel(Vec2::new(10000.0, 0.0)).set_mass_2(1.0));
+        sim.step(0.005);
+        let max_speed = sim.solver_settings.max_speed;
+        assert!(sim.particles[0].vel.magnitude() <= max_speed * 1.001);
+        assert!(sim.particles[0].pos.x <= max_speed * 0.005 * 1.001);
+        assert!(sim.quarantined.is_empty());
+    }
+
+    #[test]
+    fn test_remove_particles_renumbers_what_is_left() {
+        let mut sim = Simulation::new(Random::seed_from_str("remove"));
+        // two rigid bodies of 2 particles, then a chain of 3
+        for x in [0.0, 2.0] {
+            let mut particles = ParticleVec::from([
+                *Particle::default().set_pos(Vec2::new(x, 0.0)).set_mass_2(1.0),
+                *Particle::default().set_pos(Vec2::new(x + 0.5, 0.0)).set_mass_2(1.0),
+            ]);
+            sim.create_rigid_body(&mut particles, &vec![SdfData::new(Vec2::new(1.0, 0.0), 0.1); 2]);
+        }
+        for x in [4.0, 5.0, 6.0] {
+            sim.add_particle(*Particle::default().set_pos(Vec2::new(x, 0.0)).set_mass_2(1.0));
+        }
+        sim.add_distance_constraint(DistanceConstraint::from_particles(4, 5, &sim.particles));
+        sim.add_distance_constraint(DistanceConstraint::from_particles(5, 6, &sim.particles));
+
+        // taking one particle of the first body takes the whole body, and the chain link it's part of
+        assert_eq!(sim.remove_particles(&[0, 6]), 3);
+        assert_eq!(sim.particles.len(), 4);
+        assert_eq!(sim.bodies.len(), 1);
+        assert_eq!(sim.bodies[0].particle_indicies, vec![0, 1]);
+        assert!(sim.bodies[0].rs.contains_key(&1) && sim.bodies[0].sdf.contains_key(&1));
+        assert_eq!((sim.particles[0].body, sim.particles[0].pos), (0, Vec2::new(2.0, 0.0)));
+        assert_eq!(sim.distance_constraints.0.len(), 1);
+        assert_eq!((sim.distance_constraints.0[0].i1, sim.distance_constraints.0[0].i2), (2, 3));
+        assert_eq!(sim.particles[3].index, 3);
+
+        assert_eq!(sim.remove_particles(&[]), 0);
+    }
+}
@@ -6,6 +6,34 @@ pub struct SimulationDemos {
 }
 
 impl SimulationDemos {
+    /// The demo scenes, by the name they are picked with on the command line
+    pub const NAMES: &[&str] = &["friction", "granular", "sdf", "boxes", "wall", "pendulum", "rope", "fluid", "fluid_solid", "gas", "water_balloon", "newtons_cradle", "smoke_open", "smoke_closed", "rope_gas", "volcano", "wrecking_ball"];
+
+    /// Set up the demo with the given name, false if there's no such demo
+    pub fn init(name: &str, sim: &mut Simulation) -> bool {
+        match name {
+            "friction" => Self::init_friction(sim),
+            "granular" => Self::init_granular(sim),
+            "sdf" => Self::init_sdf(sim),
+            "boxes" => Self::init_boxes(sim),
+            "wall" => Self::init_wall(sim),
+            "pendulum" => Self::init_pendulum(sim),
+            "rope" => Self::init_rope(sim),
+            "fluid" => Self::init_fluid(sim),
+            "fluid_solid" => Self::init_fluid_solid(sim),
+            "gas" => Self::init_gas(sim),
+            "water_balloon" => Self::init_water_balloon(sim),
+            "newtons_cradle" => Self::init_newtons_cradle(sim),
+            "smoke_open" => Self::init_smoke_open(sim),
+            "smoke_closed" => Self::init_smoke_closed(sim),
+            "rope_gas" => Self::init_rope_gas(sim),
+            "volcano" => Self::init_volcano(sim),
+            "wrecking_ball" => Self::init_wrecking_ball(sim),
+            _ => return false,
+        }
+        true
+    }
+
     pub fn init_friction(sim: &mut Simulation) {
         sim.x_boundaries = Vec2::new(-20.0,20.0);
         sim.y_boundaries = Vec2::new(0.0,1000000.0);
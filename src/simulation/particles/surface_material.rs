@@ -0,0 +1,62 @@
+use crate::core::math::vec4::Vec4;
+
+/// Index into the MaterialTable, stored on each particle
+pub type MaterialId = u8;
+
+/// How a surface treats things that touch it, on top of friction which stays per particle
+#[derive(Debug, Clone, PartialEq)]
+pub struct SurfaceMaterial {
+    pub name: String,
+    pub restitution: f32, // fraction of the speed into the surface given back as a bounce, 0 for the usual dead stop
+    pub adhesion: f32, // m/s^2 pulling anything touching towards the surface, a little more than gravity lets you drive up a short wall
+    pub softness: f32, // -1 for hard ground tyres shrink on, up to 1 for ground like mud they swell into for grip, 0 leaves them be
+    pub colour: Vec4, // level blocks colour their particles with this so the player can tell surfaces apart
+}
+
+impl SurfaceMaterial {
+    pub fn new(name: &str, colour: Vec4) -> Self {
+        Self {
+            name: name.to_owned(),
+            restitution: 0.0,
+            adhesion: 0.0,
+            softness: 0.0,
+            colour,
+        }
+    }
+}
+
+/// Every surface material in the level. The built in ones always sit at the same ids, plugins can register more.
+#[derive(Debug, Clone)]
+pub struct MaterialTable(pub Vec<SurfaceMaterial>);
+
+impl MaterialTable {
+    /// Slower impacts than this don't bounce, so things can come to rest on a bouncy surface instead of jittering
+    pub const MIN_BOUNCE_SPEED: f32 = 0.5;
+
+    pub const DEFAULT: MaterialId = 0;
+    pub const STICKY: MaterialId = 1;
+    pub const BOUNCY: MaterialId = 2;
+    pub const MUD: MaterialId = 3;
+    pub const ROCK: MaterialId = 4;
+
+    pub fn get(&self, id: MaterialId) -> &SurfaceMaterial {
+        self.0.get(id as usize).unwrap_or(&self.0[Self::DEFAULT as usize])
+    }
+
+    pub fn register(&mut self, material: SurfaceMaterial) -> MaterialId {
+        self.0.push(material);
+        (self.0.len() - 1) as MaterialId
+    }
+}
+
+impl Default for MaterialTable {
+    fn default() -> Self {
+        Self(vec![
+            SurfaceMaterial::new("default", Vec4::WHITE),
+            SurfaceMaterial { adhesion: 12.0, ..SurfaceMaterial::new("sticky", Vec4::new(0.6, 0.2, 0.8, 1.0)) },
+            SurfaceMaterial { restitution: 0.9, ..SurfaceMaterial::new("bouncy", Vec4::new(0.2, 0.9, 0.4, 1.0)) },
+            SurfaceMaterial { softness: 1.0, ..SurfaceMaterial::new("mud", Vec4::new(0.45, 0.3, 0.15, 1.0)) },
+            SurfaceMaterial { softness: -1.0, ..SurfaceMaterial::new("rock", Vec4::new(0.5, 0.5, 0.55, 1.0)) },
+        ])
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+
+use crate::core::math::vec2::Vec2;
+
+use super::particle_vec::ParticleVec;
+
+type Cell = (i32, i32);
+
+/// Broadphase for finding the particles near each other: a grid of square cells, each listing the particles whose
+/// predicted position is in it. It's kept by the Simulation from step to step and only particles that changed cell are
+/// moved, so most of a long level, which stands still, costs one cell check a step.
+#[derive(Default, Debug, Clone)]
+pub struct UniformGrid {
+    tuned_radius: f32, // radius most particles have, see tune
+    cell_size: f32,
+    max_radius: f32, // largest particle, neighbors has to look far enough to find it
+    cells: HashMap<Cell, Vec<usize>>,
+    particle_cells: Vec<Cell>, // the cell each particle is listed in, by particle index
+}
+
+impl UniformGrid {
+    pub const MIN_CELL_SIZE: f32 = 0.01;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Size cells for particles of this radius, the level builder's particle_template. Without it the cells fit the
+    /// largest particle, which leaves a lot of particles in each cell when a few big ones are about.
+    pub fn tune(&mut self, radius: f32) {
+        self.tuned_radius = radius;
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// Forget everything, the next update rebuilds from scratch. Needed when particles are renumbered.
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.particle_cells.clear();
+    }
+
+    /// Bring the grid up to date with the particles' predicted positions. Particles being added or removed, or the
+    /// cell size changing, rebuilds it.
+    pub fn update(&mut self, particles: &ParticleVec) {
+        self.max_radius = particles.iter().fold(0.0, |max, p| p.radius.max(max));
+        let radius = if self.tuned_radius > 0.0 { self.tuned_radius } else { self.max_radius };
+        let cell_size = (radius * 2.0).max(Self::MIN_CELL_SIZE);
+
+        if cell_size != self.cell_size || particles.len() != self.particle_cells.len() {
+            self.cell_size = cell_size;
+            self.clear();
+            for (i, p) in particles.iter().enumerate() {
+                let cell = self.cell_of(p.pos_guess);
+                self.cells.entry(cell).or_default().push(i);
+                self.particle_cells.push(cell);
+            }
+            return;
+        }
+
+        for (i, p) in particles.iter().enumerate() {
+            let cell = self.cell_of(p.pos_guess);
+            let old = self.particle_cells[i];
+            if cell == old {
+                continue;
+            }
+            if let Some(list) = self.cells.get_mut(&old) {
+                if let Some(k) = list.iter().position(|&j| j == i) {
+                    list.swap_remove(k);
+                }
+                if list.is_empty() {
+                    self.cells.remove(&old);
+                }
+            }
+            self.cells.entry(cell).or_default().push(i);
+            self.particle_cells[i] = cell;
+        }
+    }
+
+    /// Puts in out every particle that could be within range of pos, in index order so callers
+    /// visit them the same way however the grid got to where it is
+    pub fn query(&self, pos: Vec2, range: f32, out: &mut Vec<usize>) {
+        out.clear();
+        let (min, max) = (self.cell_of(pos - Vec2::new(range, range)), self.cell_of(pos + Vec2::new(range, range)));
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                if let Some(list) = self.cells.get(&(x, y)) {
+                    out.extend_from_slice(list);
+                }
+            }
+        }
+        out.sort_unstable();
+    }
+
+    /// Puts in out the particles that could be touching particle i, which has this radius and is at pos.
+    /// Doesn't include i itself.
+    pub fn neighbors(&self, i: usize, pos: Vec2, radius: f32, out: &mut Vec<usize>) {
+        self.query(pos, radius + self.max_radius, out);
+        out.retain(|&j| j != i);
+    }
+
+    fn cell_of(&self, pos: Vec2) -> Cell {
+        ((pos.x / self.cell_size).floor() as i32, (pos.y / self.cell_size).floor() as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::particles::particle::Particle;
+
+    fn touching(particles: &ParticleVec, i: usize) -> Vec<usize> {
+        (0..particles.len())
+            .filter(|&j| j != i && (particles[i].pos_guess - particles[j].pos_guess).magnitude() < particles[i].radius + particles[j].radius)
+            .collect()
+    }
+
+    #[test]
+    fn test_finds_every_contact_as_particles_move() {
+        let mut particles = ParticleVec::new();
+        for k in 0..200 {
+            let radius = if k % 50 == 0 { 0.5 } else { 0.1 };
+            let pos = Vec2::new((k % 20) as f32 * 0.17 - 1.0, (k / 20) as f32 * 0.19 - 1.0);
+            let mut p = *Particle::default().set_radius(radius).set_pos(pos);
+            p.pos_guess = pos;
+            particles.push(p);
+        }
+
+        let mut grid = UniformGrid::new();
+        grid.tune(0.1);
+        let mut out = vec![];
+        for step in 0..10 {
+            grid.update(&particles);
+            assert_eq!(grid.cell_size(), 0.2);
+            for i in 0..particles.len() {
+                grid.neighbors(i, particles[i].pos_guess, particles[i].radius, &mut out);
+                let candidates = out.clone();
+                assert!(candidates.windows(2).all(|w| w[0] < w[1]));
+                assert!(touching(&particles, i).iter().all(|j| candidates.contains(j)), "step {step} particle {i}");
+            }
+
+            // swirl them about, most change cell
+            for p in particles.iter_mut() {
+                p.pos_guess = p.pos_guess + Vec2::new(-p.pos_guess.y, p.pos_guess.x) * 0.1;
+            }
+        }
+    }
+}